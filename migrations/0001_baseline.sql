@@ -0,0 +1,89 @@
+-- Baseline schema for the lootpacks service.
+-- These tables were historically created out-of-band, hence IF NOT EXISTS.
+-- `user_ad_interactions` is owned by the ads service and is not created here.
+
+CREATE TABLE IF NOT EXISTS pack_types (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name TEXT NOT NULL,
+    type TEXT NOT NULL,
+    description TEXT,
+    icon TEXT,
+    color_gradient TEXT,
+    price_coins INTEGER,
+    cooldown_hours INTEGER,
+    min_rewards INTEGER NOT NULL DEFAULT 1,
+    max_rewards INTEGER NOT NULL DEFAULT 1,
+    possible_reward_types TEXT[],
+    is_active BOOLEAN DEFAULT true,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    updated_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS reward_templates (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    type TEXT NOT NULL,
+    title TEXT NOT NULL,
+    value TEXT NOT NULL,
+    description TEXT,
+    rarity TEXT NOT NULL,
+    code_pattern TEXT,
+    validity_days INTEGER,
+    metadata JSONB DEFAULT '{}'::jsonb,
+    is_active BOOLEAN DEFAULT true,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS pack_reward_mappings (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    pack_type_id UUID NOT NULL REFERENCES pack_types(id) ON DELETE CASCADE,
+    reward_template_id UUID NOT NULL REFERENCES reward_templates(id) ON DELETE CASCADE,
+    weight INTEGER DEFAULT 1,
+    UNIQUE (pack_type_id, reward_template_id)
+);
+
+CREATE TABLE IF NOT EXISTS user_lootpack_stats (
+    user_id TEXT PRIMARY KEY,
+    deal_coins INTEGER DEFAULT 500,
+    daily_streak INTEGER DEFAULT 1,
+    last_daily_claim TIMESTAMPTZ,
+    total_packs_opened INTEGER DEFAULT 0,
+    level INTEGER DEFAULT 1,
+    level_progress INTEGER DEFAULT 0,
+    total_savings_inr NUMERIC(12, 2) DEFAULT 0,
+    member_status TEXT DEFAULT 'Bronze',
+    puzzle_pieces INTEGER DEFAULT 0,
+    puzzle_packs_claimed INTEGER DEFAULT 0,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    updated_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS user_pack_history (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id TEXT NOT NULL,
+    pack_type_id UUID NOT NULL REFERENCES pack_types(id),
+    rewards_count INTEGER NOT NULL,
+    total_value_inr NUMERIC(12, 2) DEFAULT 0,
+    opened_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_pack_history_user ON user_pack_history (user_id, opened_at DESC);
+
+CREATE TABLE IF NOT EXISTS user_rewards (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id TEXT NOT NULL,
+    pack_history_id UUID REFERENCES user_pack_history(id),
+    template_id UUID REFERENCES reward_templates(id),
+    type TEXT NOT NULL,
+    title TEXT NOT NULL,
+    value TEXT NOT NULL,
+    description TEXT,
+    code TEXT,
+    rarity TEXT NOT NULL,
+    source TEXT,
+    expires_at TIMESTAMPTZ,
+    is_used BOOLEAN DEFAULT false,
+    used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_rewards_user ON user_rewards (user_id, created_at DESC);
@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS feature_flags (
+    key TEXT PRIMARY KEY,
+    description TEXT,
+    enabled BOOLEAN NOT NULL DEFAULT false,
+    rollout_percentage INTEGER NOT NULL DEFAULT 0 CHECK (rollout_percentage BETWEEN 0 AND 100),
+    allowlist TEXT[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    updated_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+INSERT INTO feature_flags (key, description) VALUES
+    ('trading', 'Reward trading between users'),
+    ('crafting', 'Combine rewards into higher rarities'),
+    ('bulk_opening', 'Open several packs in one request')
+ON CONFLICT (key) DO NOTHING;
@@ -0,0 +1,5 @@
+ALTER TABLE user_rewards
+    ADD COLUMN IF NOT EXISTS reservation_id UUID,
+    ADD COLUMN IF NOT EXISTS reserved_until TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_user_rewards_code ON user_rewards (code) WHERE code IS NOT NULL;
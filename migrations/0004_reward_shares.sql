@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS reward_shares (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    reward_id UUID NOT NULL REFERENCES user_rewards(id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_reward_shares_reward ON reward_shares (reward_id);
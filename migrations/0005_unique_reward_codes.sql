@@ -0,0 +1,13 @@
+-- Legacy codes had ~4500 combinations, so duplicates exist. Disambiguate all but
+-- the oldest holder of each code before enforcing uniqueness.
+UPDATE user_rewards ur
+SET code = ur.code || '-' || UPPER(SUBSTRING(ur.id::text, 1, 6))
+FROM (
+    SELECT id, ROW_NUMBER() OVER (PARTITION BY code ORDER BY created_at, id) AS rn
+    FROM user_rewards
+    WHERE code IS NOT NULL
+) dup
+WHERE ur.id = dup.id AND dup.rn > 1;
+
+DROP INDEX IF EXISTS idx_user_rewards_code;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_user_rewards_code_unique ON user_rewards (code) WHERE code IS NOT NULL;
@@ -0,0 +1,8 @@
+ALTER TABLE user_rewards
+    ADD COLUMN IF NOT EXISTS payout_status TEXT CHECK (payout_status IN ('submitted', 'paid', 'failed')),
+    ADD COLUMN IF NOT EXISTS payout_amount NUMERIC(12, 2),
+    ADD COLUMN IF NOT EXISTS payout_reference TEXT,
+    ADD COLUMN IF NOT EXISTS payout_updated_at TIMESTAMPTZ;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_user_rewards_payout_reference
+    ON user_rewards (payout_reference) WHERE payout_reference IS NOT NULL;
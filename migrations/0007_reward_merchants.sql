@@ -0,0 +1,17 @@
+ALTER TABLE reward_templates
+    ADD COLUMN IF NOT EXISTS merchant_id TEXT,
+    ADD COLUMN IF NOT EXISTS brand TEXT,
+    ADD COLUMN IF NOT EXISTS category TEXT;
+
+ALTER TABLE user_rewards
+    ADD COLUMN IF NOT EXISTS merchant_id TEXT,
+    ADD COLUMN IF NOT EXISTS brand TEXT,
+    ADD COLUMN IF NOT EXISTS category TEXT;
+
+-- Backfill rewards granted before the columns existed
+UPDATE user_rewards ur
+SET merchant_id = rt.merchant_id, brand = rt.brand, category = rt.category
+FROM reward_templates rt
+WHERE ur.template_id = rt.id AND ur.merchant_id IS NULL AND rt.merchant_id IS NOT NULL;
+
+CREATE INDEX IF NOT EXISTS idx_user_rewards_user_merchant ON user_rewards (user_id, merchant_id);
@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS user_category_preferences (
+    user_id TEXT NOT NULL,
+    category TEXT NOT NULL,
+    score DOUBLE PRECISION NOT NULL DEFAULT 0,
+    updated_at TIMESTAMPTZ DEFAULT NOW(),
+    PRIMARY KEY (user_id, category)
+);
+
+ALTER TABLE user_lootpack_stats
+    ADD COLUMN IF NOT EXISTS personalization_opt_out BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,18 @@
+CREATE TABLE IF NOT EXISTS user_segments (
+    user_id TEXT NOT NULL,
+    segment TEXT NOT NULL,
+    updated_at TIMESTAMPTZ DEFAULT NOW(),
+    PRIMARY KEY (user_id, segment)
+);
+
+-- NULL pack_type_id applies the multiplier to every pack
+CREATE TABLE IF NOT EXISTS segment_odds_multipliers (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    segment TEXT NOT NULL,
+    pack_type_id UUID REFERENCES pack_types(id) ON DELETE CASCADE,
+    rarity TEXT NOT NULL,
+    multiplier DOUBLE PRECISION NOT NULL CHECK (multiplier > 0),
+    updated_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_segment_odds_segment ON segment_odds_multipliers (segment);
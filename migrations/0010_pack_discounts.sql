@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS pack_discounts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    pack_type_id UUID NOT NULL REFERENCES pack_types(id) ON DELETE CASCADE,
+    percent_off INTEGER NOT NULL CHECK (percent_off BETWEEN 1 AND 100),
+    label TEXT,
+    starts_at TIMESTAMPTZ NOT NULL,
+    ends_at TIMESTAMPTZ NOT NULL CHECK (ends_at > starts_at),
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_pack_discounts_window ON pack_discounts (pack_type_id, starts_at, ends_at);
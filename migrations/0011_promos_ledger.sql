@@ -0,0 +1,62 @@
+-- Every change to a user's DealCoins balance, positive (minted) or negative (sunk)
+CREATE TABLE IF NOT EXISTS coin_ledger (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id TEXT NOT NULL,
+    delta INTEGER NOT NULL,
+    reason TEXT NOT NULL,
+    reference_id TEXT,
+    balance_after INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_coin_ledger_user ON coin_ledger (user_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_coin_ledger_reason ON coin_ledger (reason, created_at);
+
+CREATE TABLE IF NOT EXISTS audit_log (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    actor TEXT NOT NULL,
+    action TEXT NOT NULL,
+    subject TEXT,
+    details JSONB NOT NULL DEFAULT '{}'::jsonb,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_audit_log_subject ON audit_log (subject, created_at DESC);
+
+-- Packs granted to a user but not yet opened (promos, compensation, entitlements)
+CREATE TABLE IF NOT EXISTS user_packs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id TEXT NOT NULL,
+    pack_type_id UUID NOT NULL REFERENCES pack_types(id),
+    source TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'unopened' CHECK (status IN ('unopened', 'opened', 'expired')),
+    expires_at TIMESTAMPTZ,
+    opened_at TIMESTAMPTZ,
+    pack_history_id UUID REFERENCES user_pack_history(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_packs_user_status ON user_packs (user_id, status);
+
+CREATE TABLE IF NOT EXISTS promo_codes (
+    code TEXT PRIMARY KEY,
+    grant_type TEXT NOT NULL CHECK (grant_type IN ('coins', 'pack')),
+    coins_amount INTEGER CHECK (coins_amount > 0),
+    pack_type_id UUID REFERENCES pack_types(id),
+    max_uses INTEGER,
+    uses INTEGER NOT NULL DEFAULT 0,
+    per_user_limit INTEGER NOT NULL DEFAULT 1,
+    expires_at TIMESTAMPTZ,
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    CHECK ((grant_type = 'coins' AND coins_amount IS NOT NULL) OR (grant_type = 'pack' AND pack_type_id IS NOT NULL))
+);
+
+CREATE TABLE IF NOT EXISTS promo_redemptions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    code TEXT NOT NULL REFERENCES promo_codes(code),
+    user_id TEXT NOT NULL,
+    redeemed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_promo_redemptions_code_user ON promo_redemptions (code, user_id);
@@ -0,0 +1,16 @@
+-- Recurring daily windows with boosted rarity odds. Times are local to `timezone`;
+-- a window whose end_time is before its start_time runs past midnight.
+CREATE TABLE IF NOT EXISTS happy_hours (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    pack_type_id UUID NOT NULL REFERENCES pack_types(id) ON DELETE CASCADE,
+    rarity TEXT NOT NULL,
+    multiplier DOUBLE PRECISION NOT NULL CHECK (multiplier > 0),
+    label TEXT,
+    start_time TIME NOT NULL,
+    end_time TIME NOT NULL CHECK (end_time <> start_time),
+    timezone TEXT NOT NULL DEFAULT 'Asia/Kolkata',
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_happy_hours_pack ON happy_hours (pack_type_id) WHERE is_active;
@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS user_pack_cooldowns (
+    user_id TEXT NOT NULL,
+    pack_type_id UUID NOT NULL REFERENCES pack_types(id) ON DELETE CASCADE,
+    last_opened_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (user_id, pack_type_id)
+);
+
+INSERT INTO user_pack_cooldowns (user_id, pack_type_id, last_opened_at)
+SELECT user_id, pack_type_id, MAX(opened_at)
+FROM user_pack_history
+WHERE opened_at IS NOT NULL
+GROUP BY user_id, pack_type_id
+ON CONFLICT (user_id, pack_type_id) DO NOTHING;
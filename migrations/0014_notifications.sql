@@ -0,0 +1,24 @@
+CREATE TABLE IF NOT EXISTS notification_preferences (
+    user_id TEXT PRIMARY KEY,
+    push_enabled BOOLEAN NOT NULL DEFAULT true,
+    email_enabled BOOLEAN NOT NULL DEFAULT true,
+    reward_expiring BOOLEAN NOT NULL DEFAULT true,
+    daily_pack_ready BOOLEAN NOT NULL DEFAULT true,
+    streak_at_risk BOOLEAN NOT NULL DEFAULT true,
+    daily_digest BOOLEAN NOT NULL DEFAULT false,
+    updated_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+-- One row per event per user; event_key dedupes repeated scans.
+-- sent_at stays NULL until the event is delivered (immediately, or in the next digest).
+CREATE TABLE IF NOT EXISTS notification_events (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id TEXT NOT NULL,
+    event_key TEXT NOT NULL,
+    payload JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    sent_at TIMESTAMPTZ,
+    UNIQUE (user_id, event_key)
+);
+
+CREATE INDEX IF NOT EXISTS idx_notification_events_unsent ON notification_events (user_id) WHERE sent_at IS NULL;
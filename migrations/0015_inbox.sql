@@ -0,0 +1,17 @@
+CREATE TABLE IF NOT EXISTS inbox_messages (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id TEXT NOT NULL,
+    kind TEXT NOT NULL CHECK (kind IN ('grant', 'milestone', 'announcement', 'system')),
+    title TEXT NOT NULL,
+    body TEXT NOT NULL,
+    -- Optional claim button: claiming grants this pack into user_packs
+    claim_pack_type_id UUID REFERENCES pack_types(id),
+    claimed_user_pack_id UUID REFERENCES user_packs(id),
+    claimed_at TIMESTAMPTZ,
+    read_at TIMESTAMPTZ,
+    deleted_at TIMESTAMPTZ,
+    expires_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_inbox_messages_user ON inbox_messages (user_id, created_at DESC) WHERE deleted_at IS NULL;
@@ -0,0 +1,19 @@
+-- Locales are lowercase BCP 47 tags, e.g. 'hi', 'ta-in'. The base columns on
+-- pack_types / reward_templates remain the default (English) content.
+CREATE TABLE IF NOT EXISTS pack_type_translations (
+    pack_type_id UUID NOT NULL REFERENCES pack_types(id) ON DELETE CASCADE,
+    locale TEXT NOT NULL,
+    name TEXT NOT NULL,
+    description TEXT,
+    updated_at TIMESTAMPTZ DEFAULT NOW(),
+    PRIMARY KEY (pack_type_id, locale)
+);
+
+CREATE TABLE IF NOT EXISTS reward_template_translations (
+    template_id UUID NOT NULL REFERENCES reward_templates(id) ON DELETE CASCADE,
+    locale TEXT NOT NULL,
+    title TEXT NOT NULL,
+    description TEXT,
+    updated_at TIMESTAMPTZ DEFAULT NOW(),
+    PRIMARY KEY (template_id, locale)
+);
@@ -0,0 +1,35 @@
+-- Units of each currency per 1 INR; INR is the pivot for conversions
+CREATE TABLE IF NOT EXISTS currency_rates (
+    currency TEXT PRIMARY KEY CHECK (currency ~ '^[A-Z]{3}$'),
+    per_inr NUMERIC(18, 8) NOT NULL CHECK (per_inr > 0),
+    updated_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+INSERT INTO currency_rates (currency, per_inr) VALUES ('INR', 1)
+ON CONFLICT (currency) DO NOTHING;
+
+ALTER TABLE reward_templates
+    ADD COLUMN IF NOT EXISTS value_amount NUMERIC(12, 2),
+    ADD COLUMN IF NOT EXISTS value_currency TEXT NOT NULL DEFAULT 'INR';
+
+ALTER TABLE user_rewards
+    ADD COLUMN IF NOT EXISTS value_amount NUMERIC(12, 2),
+    ADD COLUMN IF NOT EXISTS value_currency TEXT;
+
+ALTER TABLE user_lootpack_stats
+    ADD COLUMN IF NOT EXISTS display_currency TEXT NOT NULL DEFAULT 'INR';
+
+-- Savings are kept in the currency they were made in and converted when read
+CREATE TABLE IF NOT EXISTS user_savings (
+    user_id TEXT NOT NULL,
+    currency TEXT NOT NULL,
+    amount NUMERIC(14, 2) NOT NULL DEFAULT 0,
+    updated_at TIMESTAMPTZ DEFAULT NOW(),
+    PRIMARY KEY (user_id, currency)
+);
+
+INSERT INTO user_savings (user_id, currency, amount)
+SELECT user_id, 'INR', total_savings_inr
+FROM user_lootpack_stats
+WHERE total_savings_inr > 0
+ON CONFLICT (user_id, currency) DO NOTHING;
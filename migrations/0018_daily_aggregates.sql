@@ -0,0 +1,42 @@
+-- Daily analytics rollups (UTC days). Opens, dealt counts and coin flows are
+-- incremented as they happen; the nightly refresh recomputes a trailing window
+-- from raw tables, filling in unique users, redemptions and expiries.
+CREATE TABLE IF NOT EXISTS daily_pack_stats (
+    day DATE NOT NULL,
+    pack_type_id UUID NOT NULL REFERENCES pack_types(id) ON DELETE CASCADE,
+    opens BIGINT NOT NULL DEFAULT 0,
+    unique_users BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (day, pack_type_id)
+);
+
+CREATE TABLE IF NOT EXISTS daily_rarity_stats (
+    day DATE NOT NULL,
+    pack_type_id UUID NOT NULL REFERENCES pack_types(id) ON DELETE CASCADE,
+    rarity TEXT NOT NULL,
+    dealt BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (day, pack_type_id, rarity)
+);
+
+-- Keyed by the day the rewards were dealt; redeemed/expired are that cohort's outcomes
+CREATE TABLE IF NOT EXISTS daily_reward_type_stats (
+    day DATE NOT NULL,
+    pack_type_id UUID NOT NULL REFERENCES pack_types(id) ON DELETE CASCADE,
+    reward_type TEXT NOT NULL,
+    dealt BIGINT NOT NULL DEFAULT 0,
+    redeemed BIGINT NOT NULL DEFAULT 0,
+    expired BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (day, pack_type_id, reward_type)
+);
+
+CREATE TABLE IF NOT EXISTS daily_coin_flows (
+    day DATE NOT NULL,
+    reason TEXT NOT NULL,
+    sourced BIGINT NOT NULL DEFAULT 0,
+    sunk BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (day, reason)
+);
+
+CREATE TABLE IF NOT EXISTS aggregate_refresh_runs (
+    day DATE PRIMARY KEY,
+    refreshed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
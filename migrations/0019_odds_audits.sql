@@ -0,0 +1,17 @@
+CREATE TABLE IF NOT EXISTS odds_audit_results (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    pack_type_id UUID NOT NULL REFERENCES pack_types(id) ON DELETE CASCADE,
+    window_start TIMESTAMPTZ NOT NULL,
+    window_end TIMESTAMPTZ NOT NULL,
+    opens BIGINT NOT NULL,
+    samples BIGINT NOT NULL,
+    chi_squared DOUBLE PRECISION NOT NULL,
+    degrees_of_freedom INTEGER NOT NULL,
+    critical_value DOUBLE PRECISION NOT NULL,
+    drifted BOOLEAN NOT NULL,
+    -- Templates with the largest observed vs expected gap
+    top_deviations JSONB NOT NULL DEFAULT '[]',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_odds_audit_results_pack ON odds_audit_results (pack_type_id, created_at DESC);
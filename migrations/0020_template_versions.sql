@@ -0,0 +1,106 @@
+-- Every change to a reward template or pack mapping is captured by triggers, so
+-- edits made from any tool are versioned. Writers may set `app.actor` for attribution:
+--   SET LOCAL app.actor = 'admin:ops';
+ALTER TABLE reward_templates ADD COLUMN IF NOT EXISTS version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE pack_reward_mappings ADD COLUMN IF NOT EXISTS version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE user_rewards ADD COLUMN IF NOT EXISTS template_version INTEGER;
+
+CREATE TABLE IF NOT EXISTS reward_template_versions (
+    template_id UUID NOT NULL REFERENCES reward_templates(id) ON DELETE CASCADE,
+    version INTEGER NOT NULL,
+    snapshot JSONB NOT NULL,
+    changed_by TEXT,
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (template_id, version)
+);
+
+CREATE TABLE IF NOT EXISTS pack_reward_mapping_history (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    pack_type_id UUID NOT NULL,
+    reward_template_id UUID NOT NULL,
+    weight INTEGER,
+    version INTEGER NOT NULL,
+    operation TEXT NOT NULL CHECK (operation IN ('insert', 'update', 'delete')),
+    changed_by TEXT,
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_mapping_history_pack ON pack_reward_mapping_history (pack_type_id, changed_at DESC);
+
+-- Baseline: current configuration is version 1
+INSERT INTO reward_template_versions (template_id, version, snapshot, changed_by)
+SELECT id, version, to_jsonb(rt) - 'version', 'migration'
+FROM reward_templates rt
+ON CONFLICT (template_id, version) DO NOTHING;
+
+INSERT INTO pack_reward_mapping_history (pack_type_id, reward_template_id, weight, version, operation, changed_by)
+SELECT pack_type_id, reward_template_id, weight, version, 'insert', 'migration'
+FROM pack_reward_mappings;
+
+CREATE OR REPLACE FUNCTION bump_reward_template_version() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'UPDATE' THEN
+        IF (to_jsonb(NEW) - 'version') = (to_jsonb(OLD) - 'version') THEN
+            RETURN NEW;
+        END IF;
+        NEW.version := OLD.version + 1;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION record_reward_template_version() RETURNS TRIGGER AS $$
+BEGIN
+    INSERT INTO reward_template_versions (template_id, version, snapshot, changed_by)
+    VALUES (NEW.id, NEW.version, to_jsonb(NEW) - 'version', current_setting('app.actor', true))
+    ON CONFLICT (template_id, version) DO NOTHING;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS reward_templates_bump_version ON reward_templates;
+CREATE TRIGGER reward_templates_bump_version
+    BEFORE UPDATE ON reward_templates
+    FOR EACH ROW EXECUTE FUNCTION bump_reward_template_version();
+
+DROP TRIGGER IF EXISTS reward_templates_record_version ON reward_templates;
+CREATE TRIGGER reward_templates_record_version
+    AFTER INSERT OR UPDATE ON reward_templates
+    FOR EACH ROW EXECUTE FUNCTION record_reward_template_version();
+
+CREATE OR REPLACE FUNCTION bump_pack_reward_mapping_version() RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.weight IS NOT DISTINCT FROM OLD.weight THEN
+        RETURN NEW;
+    END IF;
+    NEW.version := OLD.version + 1;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION record_pack_reward_mapping_change() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        INSERT INTO pack_reward_mapping_history
+            (pack_type_id, reward_template_id, weight, version, operation, changed_by)
+        VALUES (OLD.pack_type_id, OLD.reward_template_id, OLD.weight, OLD.version, 'delete',
+                current_setting('app.actor', true));
+    ELSIF TG_OP = 'INSERT' OR NEW.version <> OLD.version THEN
+        INSERT INTO pack_reward_mapping_history
+            (pack_type_id, reward_template_id, weight, version, operation, changed_by)
+        VALUES (NEW.pack_type_id, NEW.reward_template_id, NEW.weight, NEW.version, lower(TG_OP),
+                current_setting('app.actor', true));
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS pack_reward_mappings_bump_version ON pack_reward_mappings;
+CREATE TRIGGER pack_reward_mappings_bump_version
+    BEFORE UPDATE ON pack_reward_mappings
+    FOR EACH ROW EXECUTE FUNCTION bump_pack_reward_mapping_version();
+
+DROP TRIGGER IF EXISTS pack_reward_mappings_record_change ON pack_reward_mappings;
+CREATE TRIGGER pack_reward_mappings_record_change
+    AFTER INSERT OR UPDATE OR DELETE ON pack_reward_mappings
+    FOR EACH ROW EXECUTE FUNCTION record_pack_reward_mapping_change();
@@ -0,0 +1,10 @@
+-- Full pack configuration snapshots: {"pack": <pack_types row>, "mappings": [{reward_template_id, weight}]}
+CREATE TABLE IF NOT EXISTS pack_type_versions (
+    pack_type_id UUID NOT NULL REFERENCES pack_types(id) ON DELETE CASCADE,
+    version INTEGER NOT NULL,
+    snapshot JSONB NOT NULL,
+    created_by TEXT NOT NULL,
+    note TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (pack_type_id, version)
+);
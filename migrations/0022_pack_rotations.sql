@@ -0,0 +1,23 @@
+-- A rotation cycles a pack through a list of reward pools on a cron schedule (UTC).
+CREATE TABLE IF NOT EXISTS pack_rotations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    pack_type_id UUID NOT NULL UNIQUE REFERENCES pack_types(id) ON DELETE CASCADE,
+    schedule TEXT NOT NULL,
+    current_position INTEGER NOT NULL DEFAULT 0,
+    current_since TIMESTAMPTZ,
+    next_change_at TIMESTAMPTZ NOT NULL,
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    created_by TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_pack_rotations_due ON pack_rotations (next_change_at) WHERE is_active;
+
+-- mappings: [{"reward_template_id": ..., "weight": ...}], the same shape as pack snapshots
+CREATE TABLE IF NOT EXISTS pack_rotation_pools (
+    rotation_id UUID NOT NULL REFERENCES pack_rotations(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    label TEXT NOT NULL,
+    mappings JSONB NOT NULL,
+    PRIMARY KEY (rotation_id, position)
+);
@@ -0,0 +1,14 @@
+-- Uploaded icon images. Objects are content-addressed, so re-uploading the same image reuses the row.
+CREATE TABLE IF NOT EXISTS assets (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    storage_key TEXT NOT NULL UNIQUE,
+    content_type TEXT NOT NULL,
+    size_bytes INTEGER NOT NULL,
+    sha256 TEXT NOT NULL,
+    uploaded_by TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- The free-text icon columns stay as a fallback for packs/templates without an uploaded icon
+ALTER TABLE pack_types ADD COLUMN IF NOT EXISTS icon_asset_id UUID REFERENCES assets(id);
+ALTER TABLE reward_templates ADD COLUMN IF NOT EXISTS icon_asset_id UUID REFERENCES assets(id);
@@ -0,0 +1,21 @@
+-- Markets the service operates in; ISO 3166 country codes
+CREATE TABLE IF NOT EXISTS markets (
+    code TEXT PRIMARY KEY CHECK (code ~ '^[A-Z]{2}$'),
+    name TEXT NOT NULL,
+    default_currency TEXT NOT NULL REFERENCES currency_rates(currency),
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    -- Exactly one market is the fallback for users whose market is unknown
+    is_default BOOLEAN NOT NULL DEFAULT false,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_markets_single_default ON markets (is_default) WHERE is_default;
+
+INSERT INTO markets (code, name, default_currency, is_default) VALUES ('IN', 'India', 'INR', true)
+ON CONFLICT (code) DO NOTHING;
+
+-- NULL means the pack/template is offered in every market
+ALTER TABLE pack_types ADD COLUMN IF NOT EXISTS markets TEXT[];
+ALTER TABLE reward_templates ADD COLUMN IF NOT EXISTS markets TEXT[];
+
+CREATE INDEX IF NOT EXISTS idx_pack_types_markets ON pack_types USING GIN (markets);
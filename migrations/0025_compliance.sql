@@ -0,0 +1,25 @@
+-- Loot-box rules per region. `region` is a market code, a subdivision such as 'IN-TN',
+-- or '*' for the fallback. Modes: allow = random packs as usual, choose = the buyer picks
+-- the reward, hide = paid random packs are not offered.
+CREATE TABLE IF NOT EXISTS compliance_policies (
+    region TEXT PRIMARY KEY,
+    paid_random_mode TEXT NOT NULL DEFAULT 'allow' CHECK (paid_random_mode IN ('allow', 'choose', 'hide')),
+    -- Applied to users under `adult_age` and to users without a verified age
+    minor_paid_random_mode TEXT NOT NULL DEFAULT 'allow' CHECK (minor_paid_random_mode IN ('allow', 'choose', 'hide')),
+    adult_age INTEGER NOT NULL DEFAULT 18 CHECK (adult_age > 0),
+    require_odds_disclosure BOOLEAN NOT NULL DEFAULT false,
+    disclosure_text TEXT,
+    updated_by TEXT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO compliance_policies (region, updated_by) VALUES ('*', 'migration')
+ON CONFLICT (region) DO NOTHING;
+
+-- A user confirmed they saw a pack's odds; stale once the pack's configuration changes
+CREATE TABLE IF NOT EXISTS odds_acknowledgements (
+    user_id TEXT NOT NULL,
+    pack_type_id UUID NOT NULL REFERENCES pack_types(id) ON DELETE CASCADE,
+    acknowledged_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, pack_type_id)
+);
@@ -0,0 +1,28 @@
+-- Responsible-gaming ceilings over rolling windows (daily = 24h, weekly = 7 days).
+-- NULL means unlimited. Users can set lower personal limits below these.
+CREATE TABLE IF NOT EXISTS spending_limit_defaults (
+    period TEXT PRIMARY KEY CHECK (period IN ('daily', 'weekly')),
+    coin_limit INTEGER CHECK (coin_limit > 0),
+    open_limit INTEGER CHECK (open_limit > 0),
+    updated_by TEXT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO spending_limit_defaults (period, updated_by) VALUES ('daily', 'migration'), ('weekly', 'migration')
+ON CONFLICT (period) DO NOTHING;
+
+-- Personal limits. Lowering applies at once; raising is queued in the pending_* columns
+-- and applies at pending_effective_at. A NULL limit falls back to the default.
+CREATE TABLE IF NOT EXISTS user_spending_limits (
+    user_id TEXT NOT NULL,
+    period TEXT NOT NULL CHECK (period IN ('daily', 'weekly')),
+    coin_limit INTEGER CHECK (coin_limit > 0),
+    open_limit INTEGER CHECK (open_limit > 0),
+    pending_coin_limit INTEGER CHECK (pending_coin_limit > 0),
+    pending_open_limit INTEGER CHECK (pending_open_limit > 0),
+    pending_effective_at TIMESTAMPTZ,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, period)
+);
+
+CREATE INDEX IF NOT EXISTS idx_coin_ledger_user_reason ON coin_ledger (user_id, reason, created_at);
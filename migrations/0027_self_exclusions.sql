@@ -0,0 +1,30 @@
+-- Voluntary breaks from paid pack purchases. Rows are append-only: an exclusion can be
+-- extended by adding a later-ending row, never shortened or lifted before it ends.
+CREATE TABLE IF NOT EXISTS user_self_exclusions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id TEXT NOT NULL,
+    kind TEXT NOT NULL CHECK (kind IN ('cool_off', 'self_exclusion')),
+    starts_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    ends_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (ends_at > starts_at)
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_self_exclusions_user ON user_self_exclusions (user_id, ends_at DESC);
+
+CREATE OR REPLACE FUNCTION protect_self_exclusions() RETURNS TRIGGER AS $$
+BEGIN
+    IF OLD.ends_at > NOW() THEN
+        RAISE EXCEPTION 'self-exclusion % cannot be changed before it ends', OLD.id;
+    END IF;
+    IF TG_OP = 'DELETE' THEN
+        RETURN OLD;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS user_self_exclusions_protect ON user_self_exclusions;
+CREATE TRIGGER user_self_exclusions_protect
+    BEFORE UPDATE OR DELETE ON user_self_exclusions
+    FOR EACH ROW EXECUTE FUNCTION protect_self_exclusions();
@@ -0,0 +1,20 @@
+-- Commit-reveal seeds for provably fair opens. The server seed stays secret until the
+-- commitment is used to open a pack; only its SHA-256 is shown beforehand.
+CREATE TABLE IF NOT EXISTS fairness_commitments (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id TEXT NOT NULL,
+    server_seed TEXT NOT NULL,
+    server_seed_hash TEXT NOT NULL,
+    pack_history_id UUID REFERENCES user_pack_history(id),
+    used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_fairness_commitments_user ON fairness_commitments (user_id, created_at DESC);
+
+-- Revealed seeds and the full derivation of each reward, for opens made with a commitment
+ALTER TABLE user_pack_history
+    ADD COLUMN IF NOT EXISTS server_seed TEXT,
+    ADD COLUMN IF NOT EXISTS server_seed_hash TEXT,
+    ADD COLUMN IF NOT EXISTS client_seed TEXT,
+    ADD COLUMN IF NOT EXISTS fairness_derivation JSONB;
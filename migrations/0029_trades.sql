@@ -0,0 +1,36 @@
+-- Reward trades between users. A trade moves offered -> locked -> swapped, or ends
+-- cancelled; while locked, every reward on both sides is held in escrow.
+CREATE TABLE IF NOT EXISTS trades (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    proposer_id TEXT NOT NULL,
+    recipient_id TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'offered' CHECK (status IN ('offered', 'locked', 'swapped', 'cancelled')),
+    expires_at TIMESTAMPTZ NOT NULL,
+    locked_at TIMESTAMPTZ,
+    swapped_at TIMESTAMPTZ,
+    cancelled_at TIMESTAMPTZ,
+    cancel_reason TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (proposer_id <> recipient_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_trades_proposer ON trades (proposer_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_trades_recipient ON trades (recipient_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_trades_open ON trades (status, expires_at) WHERE status IN ('offered', 'locked');
+
+-- The rewards on each side of a trade
+CREATE TABLE IF NOT EXISTS trade_items (
+    trade_id UUID NOT NULL REFERENCES trades(id) ON DELETE CASCADE,
+    reward_id UUID NOT NULL REFERENCES user_rewards(id),
+    from_user_id TEXT NOT NULL,
+    PRIMARY KEY (trade_id, reward_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_trade_items_reward ON trade_items (reward_id);
+
+-- Set while the reward is held by a locked trade; escrowed rewards can't be used or reserved
+ALTER TABLE user_rewards
+    ADD COLUMN IF NOT EXISTS escrow_trade_id UUID REFERENCES trades(id);
+
+CREATE INDEX IF NOT EXISTS idx_user_rewards_escrow ON user_rewards (escrow_trade_id) WHERE escrow_trade_id IS NOT NULL;
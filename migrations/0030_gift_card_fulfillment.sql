@@ -0,0 +1,16 @@
+-- Gift card rewards are delivered by an external fulfillment provider after the user
+-- redeems them: pending (queued or waiting to retry) -> submitted -> delivered, or failed
+-- once retries run out
+ALTER TABLE user_rewards
+    ADD COLUMN IF NOT EXISTS fulfillment_status TEXT CHECK (fulfillment_status IN ('pending', 'submitted', 'delivered', 'failed')),
+    ADD COLUMN IF NOT EXISTS fulfillment_reference TEXT,
+    ADD COLUMN IF NOT EXISTS fulfillment_attempts INT NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS fulfillment_next_attempt_at TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS fulfillment_error TEXT,
+    ADD COLUMN IF NOT EXISTS fulfillment_updated_at TIMESTAMPTZ;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_user_rewards_fulfillment_reference
+    ON user_rewards (fulfillment_reference) WHERE fulfillment_reference IS NOT NULL;
+
+CREATE INDEX IF NOT EXISTS idx_user_rewards_fulfillment_due
+    ON user_rewards (fulfillment_next_attempt_at) WHERE fulfillment_status = 'pending';
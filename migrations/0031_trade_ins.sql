@@ -0,0 +1,17 @@
+-- DealCoins credited for trading in an unused reward, by rarity
+CREATE TABLE IF NOT EXISTS trade_in_rates (
+    rarity TEXT PRIMARY KEY,
+    coins INTEGER NOT NULL CHECK (coins >= 0),
+    updated_by TEXT NOT NULL DEFAULT 'system',
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO trade_in_rates (rarity, coins) VALUES
+    ('common', 5),
+    ('rare', 15),
+    ('epic', 40),
+    ('legendary', 100)
+ON CONFLICT (rarity) DO NOTHING;
+
+ALTER TABLE user_rewards
+    ADD COLUMN IF NOT EXISTS traded_in_at TIMESTAMPTZ;
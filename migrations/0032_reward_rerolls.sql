@@ -0,0 +1,17 @@
+-- One reroll per pack open: the token is handed out with the opened rewards and lets the
+-- user pay to redraw a single reward from that open before it expires
+CREATE TABLE IF NOT EXISTS reroll_tokens (
+    token UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id TEXT NOT NULL,
+    pack_history_id UUID NOT NULL REFERENCES user_pack_history(id),
+    pack_type_id UUID NOT NULL REFERENCES pack_types(id),
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_reroll_tokens_user ON reroll_tokens (user_id, created_at DESC);
+
+-- The template a rerolled reward replaced
+ALTER TABLE user_rewards
+    ADD COLUMN IF NOT EXISTS rerolled_from_template_id UUID REFERENCES reward_templates(id);
@@ -0,0 +1,27 @@
+-- Every reward template a user has ever pulled, kept after the rewards themselves are
+-- used, traded away or expired
+CREATE TABLE IF NOT EXISTS user_collection (
+    user_id TEXT NOT NULL,
+    template_id UUID NOT NULL REFERENCES reward_templates(id) ON DELETE CASCADE,
+    first_obtained_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_obtained_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    times_obtained INTEGER NOT NULL DEFAULT 1,
+    PRIMARY KEY (user_id, template_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_collection_recent ON user_collection (user_id, first_obtained_at DESC);
+
+-- Backfill from pack rewards granted so far, including templates that were rerolled away
+INSERT INTO user_collection (user_id, template_id, first_obtained_at, last_obtained_at, times_obtained)
+SELECT user_id, template_id, MIN(created_at), MAX(created_at), COUNT(*)
+FROM (
+    SELECT user_id, template_id, COALESCE(created_at, NOW()) AS created_at
+    FROM user_rewards
+    WHERE template_id IS NOT NULL AND pack_history_id IS NOT NULL
+    UNION ALL
+    SELECT user_id, rerolled_from_template_id, COALESCE(created_at, NOW())
+    FROM user_rewards
+    WHERE rerolled_from_template_id IS NOT NULL
+) pulled
+GROUP BY user_id, template_id
+ON CONFLICT (user_id, template_id) DO NOTHING;
@@ -0,0 +1,29 @@
+-- Named groups of reward templates; collecting every template in an active set grants
+-- its bonus pack once per user
+CREATE TABLE IF NOT EXISTS reward_sets (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name TEXT NOT NULL,
+    description TEXT,
+    bonus_pack_type_id UUID NOT NULL REFERENCES pack_types(id),
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    created_by TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS reward_set_templates (
+    set_id UUID NOT NULL REFERENCES reward_sets(id) ON DELETE CASCADE,
+    template_id UUID NOT NULL REFERENCES reward_templates(id) ON DELETE CASCADE,
+    PRIMARY KEY (set_id, template_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_reward_set_templates_template ON reward_set_templates (template_id);
+
+-- The primary key is what keeps concurrent opens from granting a set's bonus twice
+CREATE TABLE IF NOT EXISTS reward_set_completions (
+    set_id UUID NOT NULL REFERENCES reward_sets(id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL,
+    bonus_user_pack_id UUID REFERENCES user_packs(id),
+    completed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (set_id, user_id)
+);
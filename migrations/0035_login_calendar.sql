@@ -0,0 +1,28 @@
+-- Monthly login calendar, separate from the daily pack. `month` is the first day of the
+-- month; each configured day grants coins or a pack when claimed on that local date.
+CREATE TABLE IF NOT EXISTS login_calendar_days (
+    month DATE NOT NULL CHECK (EXTRACT(DAY FROM month) = 1),
+    day INTEGER NOT NULL CHECK (day BETWEEN 1 AND 31),
+    grant_type TEXT NOT NULL CHECK (grant_type IN ('coins', 'pack')),
+    coins_amount INTEGER CHECK (coins_amount > 0),
+    pack_type_id UUID REFERENCES pack_types(id),
+    label TEXT,
+    updated_by TEXT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (month, day),
+    CHECK ((grant_type = 'coins' AND coins_amount IS NOT NULL) OR (grant_type = 'pack' AND pack_type_id IS NOT NULL))
+);
+
+-- One row per claimed day. The timezone of a month's first claim is kept for the rest
+-- of that month so switching timezones can't squeeze in extra days.
+CREATE TABLE IF NOT EXISTS login_calendar_claims (
+    user_id TEXT NOT NULL,
+    month DATE NOT NULL,
+    day INTEGER NOT NULL,
+    timezone TEXT NOT NULL,
+    grant_type TEXT NOT NULL,
+    coins_granted INTEGER,
+    user_pack_id UUID REFERENCES user_packs(id),
+    claimed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, month, day)
+);
@@ -0,0 +1,48 @@
+-- Time-boxed community goals ("1M pack opens this week"). When the window closes with
+-- the goal met, every participant gets the reward pack.
+CREATE TABLE IF NOT EXISTS community_events (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name TEXT NOT NULL,
+    description TEXT,
+    -- Opens of this pack only, or of any pack when NULL
+    pack_type_id UUID REFERENCES pack_types(id),
+    goal BIGINT NOT NULL CHECK (goal > 0),
+    progress BIGINT NOT NULL DEFAULT 0,
+    starts_at TIMESTAMPTZ NOT NULL,
+    ends_at TIMESTAMPTZ NOT NULL,
+    reward_pack_type_id UUID NOT NULL REFERENCES pack_types(id),
+    -- Opens a user needs during the event to share in the reward
+    min_contribution INTEGER NOT NULL DEFAULT 1 CHECK (min_contribution > 0),
+    status TEXT NOT NULL DEFAULT 'running' CHECK (status IN ('running', 'distributing', 'completed', 'failed')),
+    goal_reached_at TIMESTAMPTZ,
+    closed_at TIMESTAMPTZ,
+    created_by TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (ends_at > starts_at)
+);
+
+CREATE INDEX IF NOT EXISTS idx_community_events_window ON community_events (starts_at, ends_at) WHERE status = 'running';
+
+-- Append-only queue written by the open path. Opens never update a shared counter; the
+-- worker consumes these rows and folds them into progress and participants.
+CREATE TABLE IF NOT EXISTS community_contributions (
+    id BIGSERIAL PRIMARY KEY,
+    event_id UUID NOT NULL REFERENCES community_events(id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL,
+    amount INTEGER NOT NULL DEFAULT 1,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_community_contributions_event ON community_contributions (event_id);
+
+CREATE TABLE IF NOT EXISTS community_event_participants (
+    event_id UUID NOT NULL REFERENCES community_events(id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL,
+    contribution BIGINT NOT NULL DEFAULT 0,
+    user_pack_id UUID REFERENCES user_packs(id),
+    rewarded_at TIMESTAMPTZ,
+    PRIMARY KEY (event_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_community_participants_unrewarded
+    ON community_event_participants (event_id) WHERE rewarded_at IS NULL;
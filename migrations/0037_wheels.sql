@@ -0,0 +1,40 @@
+-- Spin-the-wheel configurations. Every segment deals a reward template; coin segments
+-- point at a `points` template so spins go through the same weighted draw as packs.
+CREATE TABLE IF NOT EXISTS wheels (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name TEXT NOT NULL,
+    cooldown_hours INTEGER NOT NULL DEFAULT 24 CHECK (cooldown_hours > 0),
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    updated_by TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS wheel_segments (
+    wheel_id UUID NOT NULL REFERENCES wheels(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL CHECK (position >= 0),
+    label TEXT NOT NULL,
+    color TEXT,
+    reward_template_id UUID NOT NULL REFERENCES reward_templates(id),
+    weight INTEGER NOT NULL CHECK (weight > 0),
+    PRIMARY KEY (wheel_id, position),
+    UNIQUE (wheel_id, reward_template_id)
+);
+
+CREATE TABLE IF NOT EXISTS user_wheel_cooldowns (
+    user_id TEXT NOT NULL,
+    wheel_id UUID NOT NULL REFERENCES wheels(id) ON DELETE CASCADE,
+    last_spun_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (user_id, wheel_id)
+);
+
+CREATE TABLE IF NOT EXISTS wheel_spins (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id TEXT NOT NULL,
+    wheel_id UUID NOT NULL REFERENCES wheels(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    reward_id UUID NOT NULL REFERENCES user_rewards(id),
+    spun_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_wheel_spins_user ON wheel_spins (user_id, spun_at DESC);
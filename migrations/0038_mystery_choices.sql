@@ -0,0 +1,20 @@
+-- Pick-one-of-three opens. The options are dealt when the pack is bought but stay hidden
+-- until the user picks one; only the picked option reaches the inventory. Choices not
+-- made before expires_at are refunded.
+CREATE TABLE IF NOT EXISTS mystery_choices (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id TEXT NOT NULL,
+    pack_type_id UUID NOT NULL REFERENCES pack_types(id),
+    pack_history_id UUID NOT NULL REFERENCES user_pack_history(id),
+    options JSONB NOT NULL,
+    coins_paid INTEGER NOT NULL DEFAULT 0,
+    status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'chosen', 'expired')),
+    chosen_index INTEGER,
+    reward_id UUID REFERENCES user_rewards(id),
+    expires_at TIMESTAMPTZ NOT NULL,
+    resolved_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_mystery_choices_pending ON mystery_choices (expires_at) WHERE status = 'pending';
+CREATE INDEX IF NOT EXISTS idx_mystery_choices_user ON mystery_choices (user_id, created_at DESC);
@@ -0,0 +1,6 @@
+-- How an open's rewards are sequenced on the reveal screen:
+--   dealt     - in the order they were drawn
+--   best_last - in draw order, except the single best reward is held back for the end
+--   ascending - strictly by rarity, lowest first
+ALTER TABLE pack_types ADD COLUMN IF NOT EXISTS reveal_order TEXT NOT NULL DEFAULT 'best_last'
+    CHECK (reveal_order IN ('dealt', 'best_last', 'ascending'));
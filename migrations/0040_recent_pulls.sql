@@ -0,0 +1,20 @@
+-- Per-user sharing preferences; users without a row share with the defaults
+CREATE TABLE IF NOT EXISTS user_privacy_settings (
+    user_id TEXT PRIMARY KEY,
+    share_pulls_with_friends BOOLEAN NOT NULL DEFAULT true,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Epic and legendary pulls, written when the reward is dealt so the friends feed never
+-- scans user_rewards. Only each user's most recent pulls are kept.
+CREATE TABLE IF NOT EXISTS recent_pulls (
+    reward_id UUID PRIMARY KEY REFERENCES user_rewards(id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL,
+    template_id UUID NOT NULL REFERENCES reward_templates(id) ON DELETE CASCADE,
+    pack_type_id UUID REFERENCES pack_types(id) ON DELETE SET NULL,
+    title TEXT NOT NULL,
+    rarity TEXT NOT NULL,
+    pulled_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_recent_pulls_user ON recent_pulls (user_id, pulled_at DESC);
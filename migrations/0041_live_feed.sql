@@ -0,0 +1,12 @@
+ALTER TABLE user_privacy_settings ADD COLUMN IF NOT EXISTS show_in_live_feed BOOLEAN NOT NULL DEFAULT true;
+
+-- Public ticker of epic and legendary pulls. Entries carry no user id; the table is
+-- trimmed to the newest few hundred rows on every insert.
+CREATE TABLE IF NOT EXISTS live_feed (
+    id BIGSERIAL PRIMARY KEY,
+    template_id UUID NOT NULL REFERENCES reward_templates(id) ON DELETE CASCADE,
+    title TEXT NOT NULL,
+    rarity TEXT NOT NULL,
+    pack_name TEXT,
+    pulled_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,13 @@
+-- Per-template dealt counts, incremented on open and rebuilt by the nightly refresh like
+-- the other daily rollups. Feeds the "top reward of the day" on pack pages.
+CREATE TABLE IF NOT EXISTS daily_template_stats (
+    day DATE NOT NULL,
+    pack_type_id UUID NOT NULL REFERENCES pack_types(id) ON DELETE CASCADE,
+    template_id UUID NOT NULL REFERENCES reward_templates(id) ON DELETE CASCADE,
+    dealt BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (day, pack_type_id, template_id)
+);
+
+-- Latest legendary per pack, read straight from the recent pulls
+CREATE INDEX IF NOT EXISTS idx_recent_pulls_pack_legendary
+    ON recent_pulls (pack_type_id, pulled_at DESC) WHERE rarity = 'legendary';
@@ -0,0 +1,53 @@
+-- Team meter configuration, a single row edited by admins. Team meters don't run until it exists.
+CREATE TABLE IF NOT EXISTS team_settings (
+    id BOOLEAN PRIMARY KEY DEFAULT true CHECK (id),
+    goal_coins BIGINT NOT NULL CHECK (goal_coins > 0),
+    reward_pack_type_id UUID NOT NULL REFERENCES pack_types(id),
+    max_members INTEGER NOT NULL DEFAULT 30 CHECK (max_members > 1),
+    updated_by TEXT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS teams (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name TEXT NOT NULL,
+    owner_id TEXT NOT NULL,
+    -- Coins spent towards the next fill; the overflow of a fill carries over
+    meter_coins BIGINT NOT NULL DEFAULT 0,
+    fills INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_teams_name ON teams (LOWER(name));
+
+-- A user is in at most one team
+CREATE TABLE IF NOT EXISTS team_members (
+    user_id TEXT PRIMARY KEY,
+    team_id UUID NOT NULL REFERENCES teams(id) ON DELETE CASCADE,
+    contributed_coins BIGINT NOT NULL DEFAULT 0,
+    joined_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_team_members_team ON team_members (team_id);
+
+CREATE TABLE IF NOT EXISTS team_fills (
+    team_id UUID NOT NULL REFERENCES teams(id) ON DELETE CASCADE,
+    fill_number INTEGER NOT NULL,
+    reward_pack_type_id UUID NOT NULL REFERENCES pack_types(id),
+    filled_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    distributed_at TIMESTAMPTZ,
+    PRIMARY KEY (team_id, fill_number)
+);
+
+-- Members at the moment of the fill, one row each; the key makes every grant happen once
+CREATE TABLE IF NOT EXISTS team_fill_rewards (
+    team_id UUID NOT NULL,
+    fill_number INTEGER NOT NULL,
+    user_id TEXT NOT NULL,
+    user_pack_id UUID REFERENCES user_packs(id),
+    rewarded_at TIMESTAMPTZ,
+    PRIMARY KEY (team_id, fill_number, user_id),
+    FOREIGN KEY (team_id, fill_number) REFERENCES team_fills(team_id, fill_number) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_team_fill_rewards_pending ON team_fill_rewards (team_id, fill_number) WHERE rewarded_at IS NULL;
@@ -0,0 +1,47 @@
+-- Shared team puzzle configuration, a single row edited by admins. Teams can't start puzzles until it exists.
+CREATE TABLE IF NOT EXISTS shared_puzzle_settings (
+    id BOOLEAN PRIMARY KEY DEFAULT true CHECK (id),
+    pieces_required INTEGER NOT NULL CHECK (pieces_required > 0),
+    reward_pack_type_id UUID NOT NULL REFERENCES pack_types(id),
+    reward_packs INTEGER NOT NULL DEFAULT 1 CHECK (reward_packs > 0),
+    -- 'duplicate': every contributor gets reward_packs; 'split': reward_packs are shared by pieces contributed
+    claim_policy TEXT NOT NULL DEFAULT 'duplicate' CHECK (claim_policy IN ('duplicate', 'split')),
+    updated_by TEXT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Settings are copied onto each puzzle when it starts, so later edits don't change a puzzle in progress
+CREATE TABLE IF NOT EXISTS shared_puzzles (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    team_id UUID NOT NULL REFERENCES teams(id) ON DELETE CASCADE,
+    pieces_required INTEGER NOT NULL,
+    pieces_collected INTEGER NOT NULL DEFAULT 0 CHECK (pieces_collected <= pieces_required),
+    reward_pack_type_id UUID NOT NULL REFERENCES pack_types(id),
+    reward_packs INTEGER NOT NULL,
+    claim_policy TEXT NOT NULL,
+    started_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMPTZ
+);
+
+-- One puzzle in progress per team
+CREATE UNIQUE INDEX IF NOT EXISTS idx_shared_puzzles_open ON shared_puzzles (team_id) WHERE completed_at IS NULL;
+CREATE INDEX IF NOT EXISTS idx_shared_puzzles_team ON shared_puzzles (team_id, started_at DESC);
+
+CREATE TABLE IF NOT EXISTS shared_puzzle_contributions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    puzzle_id UUID NOT NULL REFERENCES shared_puzzles(id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL,
+    pieces INTEGER NOT NULL CHECK (pieces > 0),
+    contributed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_shared_puzzle_contributions_puzzle ON shared_puzzle_contributions (puzzle_id, user_id);
+
+-- The key makes every contributor's claim happen once
+CREATE TABLE IF NOT EXISTS shared_puzzle_claims (
+    puzzle_id UUID NOT NULL REFERENCES shared_puzzles(id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL,
+    packs INTEGER NOT NULL,
+    claimed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (puzzle_id, user_id)
+);
@@ -0,0 +1,41 @@
+-- Peer-to-peer transfer rules, a single row edited by admins. Caps cover rolling 24 hours.
+CREATE TABLE IF NOT EXISTS coin_transfer_settings (
+    id BOOLEAN PRIMARY KEY DEFAULT true CHECK (id),
+    enabled BOOLEAN NOT NULL DEFAULT true,
+    daily_send_cap INTEGER NOT NULL CHECK (daily_send_cap > 0),
+    daily_receive_cap INTEGER NOT NULL CHECK (daily_receive_cap > 0),
+    -- Both sender and recipient must meet these
+    min_account_age_days INTEGER NOT NULL DEFAULT 0 CHECK (min_account_age_days >= 0),
+    min_level INTEGER NOT NULL DEFAULT 1 CHECK (min_level >= 1),
+    updated_by TEXT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO coin_transfer_settings (daily_send_cap, daily_receive_cap, min_account_age_days, min_level, updated_by)
+VALUES (1000, 2000, 7, 3, 'migration')
+ON CONFLICT (id) DO NOTHING;
+
+CREATE TABLE IF NOT EXISTS coin_transfers (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    sender_id TEXT NOT NULL,
+    recipient_id TEXT NOT NULL CHECK (recipient_id <> sender_id),
+    amount INTEGER NOT NULL CHECK (amount > 0),
+    note TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_coin_transfers_sender ON coin_transfers (sender_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_coin_transfers_recipient ON coin_transfers (recipient_id, created_at DESC);
+
+-- Unusual patterns noticed when a transfer went through, for manual review
+CREATE TABLE IF NOT EXISTS coin_transfer_flags (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    transfer_id UUID NOT NULL REFERENCES coin_transfers(id) ON DELETE CASCADE,
+    reason TEXT NOT NULL,
+    details JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    reviewed_by TEXT,
+    reviewed_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_coin_transfer_flags_open ON coin_transfer_flags (created_at) WHERE reviewed_at IS NULL;
@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS wishlist_items (
+    user_id TEXT NOT NULL,
+    template_id UUID NOT NULL REFERENCES reward_templates(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, template_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_wishlist_items_template ON wishlist_items (template_id);
+
+ALTER TABLE notification_preferences ADD COLUMN IF NOT EXISTS wishlist_available BOOLEAN NOT NULL DEFAULT true;
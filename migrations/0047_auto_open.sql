@@ -0,0 +1,6 @@
+-- Users who'd rather skip the reveal for low-tier packs and open them in one batch
+CREATE TABLE IF NOT EXISTS user_open_preferences (
+    user_id TEXT PRIMARY KEY,
+    auto_open_low_tier BOOLEAN NOT NULL DEFAULT false,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,14 @@
+-- Daily streaks that lapsed, each repairable once for a coin price until repair_deadline
+CREATE TABLE IF NOT EXISTS streak_breaks (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id TEXT NOT NULL,
+    lost_streak INTEGER NOT NULL CHECK (lost_streak > 1),
+    broken_at TIMESTAMPTZ NOT NULL,
+    repair_deadline TIMESTAMPTZ NOT NULL,
+    price_coins INTEGER NOT NULL CHECK (price_coins > 0),
+    repaired_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (user_id, broken_at)
+);
+
+CREATE INDEX IF NOT EXISTS idx_streak_breaks_user ON streak_breaks (user_id, broken_at DESC);
@@ -0,0 +1,25 @@
+-- Calendar-day daily packs in the user's timezone. The boundaries are worked out when a
+-- claim is made and stored as instants, so travel and DST never move a window already running.
+CREATE TABLE IF NOT EXISTS user_daily_clock (
+    user_id TEXT PRIMARY KEY,
+    timezone TEXT NOT NULL DEFAULT 'Asia/Kolkata',
+    timezone_updated_at TIMESTAMPTZ,
+    -- Timezone the boundaries below were computed in
+    last_claim_timezone TEXT,
+    -- Start of the local day after the last claim
+    next_claim_at TIMESTAMPTZ,
+    -- End of the local day after the last claim; claiming later starts a new streak
+    streak_until TIMESTAMPTZ
+);
+
+-- Existing users move to the default timezone. Their running windows only get longer, so
+-- no one loses a claim or a streak to the switch.
+INSERT INTO user_daily_clock (user_id, last_claim_timezone, next_claim_at, streak_until)
+SELECT user_id, 'Asia/Kolkata',
+       LEAST(((last_daily_claim AT TIME ZONE 'Asia/Kolkata')::date + 1)::timestamp AT TIME ZONE 'Asia/Kolkata',
+             last_daily_claim + INTERVAL '24 hours'),
+       GREATEST(((last_daily_claim AT TIME ZONE 'Asia/Kolkata')::date + 2)::timestamp AT TIME ZONE 'Asia/Kolkata',
+                last_daily_claim + INTERVAL '48 hours')
+FROM user_lootpack_stats
+WHERE last_daily_claim IS NOT NULL
+ON CONFLICT (user_id) DO NOTHING;
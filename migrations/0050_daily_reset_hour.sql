@@ -0,0 +1,10 @@
+-- Daily packs reset at a configurable local hour per market rather than at midnight,
+-- so claims don't all land on the database at once
+ALTER TABLE markets ADD COLUMN IF NOT EXISTS daily_reset_hour SMALLINT NOT NULL DEFAULT 0
+    CHECK (daily_reset_hour BETWEEN 0 AND 23);
+
+-- Hour the stored boundaries were computed with
+ALTER TABLE user_daily_clock ADD COLUMN IF NOT EXISTS reset_hour SMALLINT NOT NULL DEFAULT 0;
+
+-- Running windows keep their midnight boundaries; the new hour applies from each user's next claim
+UPDATE markets SET daily_reset_hour = 5, updated_at = NOW();
@@ -0,0 +1,16 @@
+-- How long a streak survives after the daily day it was last claimed on ends, a single row
+-- edited by admins. 24 hours means the next claim must land by the end of the following day.
+CREATE TABLE IF NOT EXISTS streak_settings (
+    id BOOLEAN PRIMARY KEY DEFAULT true CHECK (id),
+    streak_window_hours INTEGER NOT NULL CHECK (streak_window_hours BETWEEN 24 AND 72),
+    updated_by TEXT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO streak_settings (streak_window_hours, updated_by)
+VALUES (30, 'migration')
+ON CONFLICT (id) DO NOTHING;
+
+-- Running streaks get the extra grace right away rather than from the next claim
+UPDATE user_daily_clock SET streak_until = streak_until + INTERVAL '6 hours'
+WHERE streak_until > NOW();
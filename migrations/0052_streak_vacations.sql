@@ -0,0 +1,14 @@
+-- Vacation mode: while a vacation runs, missed daily claims don't break the streak. The streak
+-- deadline is pushed back by the vacation's length when it starts, and pulled in again if it
+-- ends early. Vacations count against a yearly allowance whether or not they run their course.
+CREATE TABLE IF NOT EXISTS streak_vacations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id TEXT NOT NULL,
+    starts_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    ends_at TIMESTAMPTZ NOT NULL,
+    ended_early_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (ends_at > starts_at)
+);
+
+CREATE INDEX IF NOT EXISTS idx_streak_vacations_user ON streak_vacations (user_id, starts_at DESC);
@@ -0,0 +1,16 @@
+-- Prestige: at the level cap users can reset to level 1 for permanent rewards. The badge,
+-- pack frame and coin bonus all follow from the prestige count, which never goes down.
+CREATE TABLE IF NOT EXISTS user_prestige (
+    user_id TEXT PRIMARY KEY,
+    prestige INTEGER NOT NULL DEFAULT 0 CHECK (prestige >= 0),
+    last_prestiged_at TIMESTAMPTZ
+);
+
+CREATE TABLE IF NOT EXISTS prestige_events (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id TEXT NOT NULL,
+    prestige INTEGER NOT NULL,
+    packs_opened INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (user_id, prestige)
+);
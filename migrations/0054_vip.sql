@@ -0,0 +1,35 @@
+-- VIP subscriptions. Billing owns the subscription; we keep a copy, refreshed from its
+-- webhooks and entitlement checks, to find who is due a monthly grant.
+CREATE TABLE IF NOT EXISTS vip_plans (
+    plan_id TEXT PRIMARY KEY,
+    premium_pack_type_id UUID NOT NULL REFERENCES pack_types(id),
+    packs_per_period INTEGER NOT NULL CHECK (packs_per_period >= 0),
+    coin_stipend INTEGER NOT NULL CHECK (coin_stipend >= 0),
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS vip_subscriptions (
+    user_id TEXT PRIMARY KEY,
+    plan_id TEXT NOT NULL,
+    -- active, past_due, cancelled or expired, as reported by billing
+    status TEXT NOT NULL,
+    current_period_start TIMESTAMPTZ NOT NULL,
+    current_period_end TIMESTAMPTZ NOT NULL,
+    checked_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_vip_subscriptions_active ON vip_subscriptions (current_period_start)
+    WHERE status = 'active';
+
+-- One row per billing period granted. Inserted in the same transaction as the grant, so a
+-- period is granted exactly once however often the worker runs.
+CREATE TABLE IF NOT EXISTS vip_grants (
+    user_id TEXT NOT NULL,
+    period_start TIMESTAMPTZ NOT NULL,
+    plan_id TEXT NOT NULL,
+    packs_granted INTEGER NOT NULL,
+    coins_granted INTEGER NOT NULL,
+    granted_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, period_start)
+);
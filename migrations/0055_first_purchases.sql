@@ -0,0 +1,23 @@
+-- A user's first coin purchase earns a bonus pack. The row is the first-purchase flag: it is
+-- written in the purchase's transaction and never deleted, so refunds can't make a user
+-- eligible again.
+CREATE TABLE IF NOT EXISTS first_purchases (
+    user_id TEXT PRIMARY KEY,
+    -- NULL for users backfilled from the ledger
+    pack_type_id UUID REFERENCES pack_types(id),
+    -- The open or inventory pack the purchase paid for
+    pack_history_id UUID REFERENCES user_pack_history(id),
+    user_pack_id UUID REFERENCES user_packs(id),
+    bonus_user_pack_id UUID REFERENCES user_packs(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_first_purchases_history ON first_purchases (pack_history_id);
+
+-- Users who already paid for a pack had their first purchase
+INSERT INTO first_purchases (user_id, created_at)
+SELECT user_id, MIN(created_at)
+FROM coin_ledger
+WHERE reason = 'pack_purchase' AND delta < 0
+GROUP BY user_id
+ON CONFLICT (user_id) DO NOTHING;
@@ -0,0 +1,28 @@
+-- Win-back grants for users who stopped opening packs. Settings are a single row edited by admins.
+CREATE TABLE IF NOT EXISTS winback_settings (
+    id BOOLEAN PRIMARY KEY DEFAULT true CHECK (id),
+    enabled BOOLEAN NOT NULL DEFAULT false,
+    -- Days without opening a pack before a user counts as lapsed
+    dormant_days INTEGER NOT NULL CHECK (dormant_days > 0),
+    pack_type_id UUID REFERENCES pack_types(id),
+    -- How long the welcome-back pack stays claimable
+    pack_expiry_days INTEGER NOT NULL CHECK (pack_expiry_days > 0),
+    -- A user is targeted at most once in this many days, and only again after coming back
+    min_days_between INTEGER NOT NULL CHECK (min_days_between > 0),
+    updated_by TEXT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO winback_settings (dormant_days, pack_expiry_days, min_days_between, updated_by)
+VALUES (30, 7, 90, 'migration')
+ON CONFLICT (id) DO NOTHING;
+
+CREATE TABLE IF NOT EXISTS winback_grants (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id TEXT NOT NULL,
+    user_pack_id UUID NOT NULL REFERENCES user_packs(id),
+    last_active_at TIMESTAMPTZ NOT NULL,
+    granted_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_winback_grants_user ON winback_grants (user_id, granted_at DESC);
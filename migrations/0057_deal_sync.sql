@@ -0,0 +1,26 @@
+-- Coupon templates sourced from the DealMate deals service. The sync owns the content and
+-- availability of these templates; rarity, weights and pack mappings stay with admins.
+ALTER TABLE reward_templates
+    ADD COLUMN IF NOT EXISTS source_deal_id TEXT,
+    ADD COLUMN IF NOT EXISTS source_stock INTEGER,
+    ADD COLUMN IF NOT EXISTS source_expires_at TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS source_synced_at TIMESTAMPTZ,
+    -- Set when the sync turned the template off (exhausted, expired or removed). The sync
+    -- only turns back on templates it turned off, never ones an admin disabled.
+    ADD COLUMN IF NOT EXISTS source_disabled_reason TEXT
+        CHECK (source_disabled_reason IN ('exhausted', 'expired', 'removed'));
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_reward_templates_source_deal ON reward_templates (source_deal_id)
+    WHERE source_deal_id IS NOT NULL;
+
+CREATE TABLE IF NOT EXISTS deal_sync_runs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    started_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    finished_at TIMESTAMPTZ,
+    deals_fetched INTEGER NOT NULL DEFAULT 0,
+    templates_created INTEGER NOT NULL DEFAULT 0,
+    templates_updated INTEGER NOT NULL DEFAULT 0,
+    templates_disabled INTEGER NOT NULL DEFAULT 0,
+    templates_enabled INTEGER NOT NULL DEFAULT 0,
+    error TEXT
+);
@@ -0,0 +1,22 @@
+-- Purchase-completed events from checkout, keyed by the publisher's event id so
+-- redeliveries are no-ops. One purchase per coupon.
+CREATE TABLE IF NOT EXISTS purchase_events (
+    event_id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    reward_id UUID NOT NULL UNIQUE REFERENCES user_rewards(id),
+    order_id TEXT NOT NULL,
+    savings_amount NUMERIC(14, 2),
+    savings_currency TEXT,
+    xp_awarded INTEGER NOT NULL DEFAULT 0,
+    completed_at TIMESTAMPTZ NOT NULL,
+    processed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_purchase_events_user ON purchase_events (user_id, completed_at DESC);
+
+-- Set once a reward's value has been added to the user's savings, whichever path
+-- (reservation confirm or purchase event) gets there first
+ALTER TABLE user_rewards ADD COLUMN IF NOT EXISTS savings_recorded_at TIMESTAMPTZ;
+
+UPDATE user_rewards SET savings_recorded_at = used_at
+WHERE is_used AND value_amount IS NOT NULL AND savings_recorded_at IS NULL;
@@ -0,0 +1,11 @@
+-- Pack openings were recorded with a total value of 0; fill them in from the granted
+-- rewards that carry a monetary value, at today's rates
+UPDATE user_pack_history ph SET total_value_inr = v.total
+FROM (
+    SELECT ur.pack_history_id, SUM(ROUND(ur.value_amount / cr.per_inr, 2)) AS total
+    FROM user_rewards ur
+    JOIN currency_rates cr ON cr.currency = ur.value_currency
+    WHERE ur.pack_history_id IS NOT NULL AND ur.value_amount IS NOT NULL
+    GROUP BY ur.pack_history_id
+) v
+WHERE ph.id = v.pack_history_id AND COALESCE(ph.total_value_inr, 0) = 0;
@@ -0,0 +1,18 @@
+-- Coin movements made through a remote wallet. Written in the same transaction as the
+-- local mirror of the movement, so a row exists exactly when that transaction committed
+-- and its hold must be captured.
+CREATE TABLE IF NOT EXISTS wallet_operations (
+    id UUID PRIMARY KEY,
+    hold_id TEXT NOT NULL UNIQUE,
+    user_id TEXT NOT NULL,
+    delta INTEGER NOT NULL,
+    reason TEXT NOT NULL,
+    reference_id TEXT,
+    -- held until the wallet confirms the capture
+    status TEXT NOT NULL DEFAULT 'held' CHECK (status IN ('held', 'captured')),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    captured_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_wallet_operations_held ON wallet_operations (created_at)
+    WHERE status = 'held';
@@ -0,0 +1,18 @@
+-- Hourly export of domain events to object storage for the data warehouse. Each stream
+-- records the end of the last hour uploaded; the next run starts there, so an hour is
+-- never skipped, and a re-run after a crash overwrites the same object rather than
+-- adding a second copy.
+CREATE TABLE IF NOT EXISTS event_export_checkpoints (
+    stream TEXT PRIMARY KEY CHECK (stream IN ('pack_opens', 'reward_grants', 'redemptions')),
+    exported_through TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO event_export_checkpoints (stream, exported_through)
+SELECT stream, date_trunc('hour', NOW())
+FROM unnest(ARRAY['pack_opens', 'reward_grants', 'redemptions']) AS stream
+ON CONFLICT (stream) DO NOTHING;
+
+CREATE INDEX IF NOT EXISTS idx_user_pack_history_opened ON user_pack_history (opened_at);
+CREATE INDEX IF NOT EXISTS idx_user_rewards_created ON user_rewards (created_at);
+CREATE INDEX IF NOT EXISTS idx_user_rewards_used ON user_rewards (used_at) WHERE used_at IS NOT NULL;
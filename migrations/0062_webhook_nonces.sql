@@ -0,0 +1,10 @@
+-- Nonces of signed webhooks, kept for twice the timestamp tolerance so a captured
+-- request can't be replayed to any replica
+CREATE TABLE IF NOT EXISTS webhook_nonces (
+    source TEXT NOT NULL,
+    nonce TEXT NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (source, nonce)
+);
+
+CREATE INDEX IF NOT EXISTS idx_webhook_nonces_expires ON webhook_nonces (expires_at);
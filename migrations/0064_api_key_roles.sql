@@ -0,0 +1,3 @@
+-- Roles on API keys expand through the same permission matrix as staff JWTs, so a
+-- tool's key can be given "support" instead of a hand-picked scope list
+ALTER TABLE api_keys ADD COLUMN IF NOT EXISTS roles TEXT[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,13 @@
+-- Refunded pack opens. Voiding a reward also expires it, so every redemption path
+-- already rejects it; rewards that were redeemed or changed hands first are only flagged.
+ALTER TABLE user_pack_history
+    ADD COLUMN IF NOT EXISTS refunded_at TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS refunded_coins INTEGER,
+    ADD COLUMN IF NOT EXISTS refunded_by TEXT,
+    ADD COLUMN IF NOT EXISTS refund_reason TEXT;
+
+ALTER TABLE user_rewards
+    ADD COLUMN IF NOT EXISTS voided_at TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS refund_flagged_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_user_rewards_pack_history ON user_rewards (pack_history_id);
@@ -0,0 +1,17 @@
+-- Disputed coin top-ups reported by payments. The coins are clawed back when a dispute
+-- opens, even past zero, and paid opens stay blocked while any dispute is open. A won
+-- dispute gives the coins back; a lost one leaves them clawed back.
+CREATE TABLE IF NOT EXISTS chargebacks (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    dispute_id TEXT NOT NULL UNIQUE,
+    payment_id TEXT NOT NULL,
+    user_id TEXT NOT NULL,
+    coins INTEGER NOT NULL CHECK (coins > 0),
+    clawed_back_coins INTEGER NOT NULL DEFAULT 0,
+    status TEXT NOT NULL DEFAULT 'open' CHECK (status IN ('open', 'won', 'lost')),
+    opened_at TIMESTAMPTZ NOT NULL,
+    resolved_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_chargebacks_open ON chargebacks (user_id) WHERE status = 'open';
@@ -0,0 +1,8 @@
+-- Reward codes are encrypted by the service before they are stored, so `code` holds
+-- `enc:<key id>:<ciphertext>` and no longer identifies a code by itself. `code_hash` is
+-- a keyed hash of the plaintext code used for lookups and uniqueness. Existing rows keep
+-- their plaintext code and a NULL hash until the code encryption worker rewrites them.
+ALTER TABLE user_rewards ADD COLUMN IF NOT EXISTS code_hash TEXT;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_user_rewards_code_hash_unique
+    ON user_rewards (code_hash) WHERE code_hash IS NOT NULL;
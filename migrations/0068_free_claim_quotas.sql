@@ -0,0 +1,25 @@
+-- Monthly limits on free packs by member tier. Daily claims and opens of granted free
+-- packs (events, calendars, puzzles, win-back...) count; bought, subscription and
+-- compensation packs don't. Tiers without a row have no limit.
+CREATE TABLE IF NOT EXISTS member_tier_free_claim_limits (
+    member_status TEXT PRIMARY KEY,
+    monthly_limit INTEGER NOT NULL CHECK (monthly_limit >= 0),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO member_tier_free_claim_limits (member_status, monthly_limit) VALUES
+    ('Bronze', 30),
+    ('Silver', 38),
+    ('Gold', 45),
+    ('Platinum', 52),
+    ('Diamond', 60)
+ON CONFLICT (member_status) DO NOTHING;
+
+-- Free claims per user per calendar month (UTC)
+CREATE TABLE IF NOT EXISTS free_claim_usage (
+    user_id TEXT NOT NULL,
+    month DATE NOT NULL,
+    claimed INTEGER NOT NULL DEFAULT 0,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, month)
+);
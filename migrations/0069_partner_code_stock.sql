@@ -0,0 +1,14 @@
+-- Codes claimed from the coupon provider. A claim can't be undone at the partner, so each
+-- code is committed here as soon as it is claimed and marked used by the transaction that
+-- deals it. If that transaction rolls back the code stays unused and the next draw of the
+-- same template takes it instead of claiming another. `code` is sealed like `user_rewards.code`.
+CREATE TABLE IF NOT EXISTS partner_codes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    reward_template_id UUID NOT NULL REFERENCES reward_templates(id) ON DELETE CASCADE,
+    code TEXT NOT NULL,
+    claimed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    used_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_partner_codes_unused
+    ON partner_codes (reward_template_id, claimed_at) WHERE used_at IS NULL;
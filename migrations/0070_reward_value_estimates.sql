@@ -0,0 +1,16 @@
+-- Values guessed from a reward's type and display text are kept apart from value_amount,
+-- which pays out gift cards and books realized savings. Estimates are for display and
+-- stats only.
+ALTER TABLE user_rewards
+    ADD COLUMN IF NOT EXISTS value_estimate_amount NUMERIC(12, 2),
+    ADD COLUMN IF NOT EXISTS value_estimate_currency TEXT;
+
+-- Rewards granted since 0059 carried the estimate in value_amount when their template had
+-- no stated value. Gift cards are never estimated.
+UPDATE user_rewards ur
+SET value_estimate_amount = CASE WHEN ur.type <> 'gift_card' THEN ur.value_amount END,
+    value_estimate_currency = CASE WHEN ur.type <> 'gift_card' THEN ur.value_currency END,
+    value_amount = NULL,
+    value_currency = NULL
+FROM reward_templates rt
+WHERE rt.id = ur.template_id AND rt.value_amount IS NULL AND ur.value_amount IS NOT NULL;
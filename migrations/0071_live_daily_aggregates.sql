@@ -0,0 +1,63 @@
+-- The daily rollups are no longer incremented by opens and ledger writes, which made every
+-- open update the same per-day rows. They hold finished days only, rebuilt by the nightly
+-- refresh; these views serve today and yesterday (which the refresh may not have reached
+-- yet) from the raw tables. Readers query the views.
+CREATE INDEX IF NOT EXISTS idx_coin_ledger_created ON coin_ledger (created_at);
+
+CREATE OR REPLACE VIEW daily_pack_stats_live AS
+SELECT day, pack_type_id, opens, unique_users
+FROM daily_pack_stats
+WHERE day < (NOW() AT TIME ZONE 'UTC')::date - 1
+UNION ALL
+SELECT (opened_at AT TIME ZONE 'UTC')::date, pack_type_id, COUNT(*), COUNT(DISTINCT user_id)
+FROM user_pack_history
+WHERE opened_at >= ((NOW() AT TIME ZONE 'UTC')::date - 1) AT TIME ZONE 'UTC'
+GROUP BY 1, 2;
+
+CREATE OR REPLACE VIEW daily_rarity_stats_live AS
+SELECT day, pack_type_id, rarity, dealt
+FROM daily_rarity_stats
+WHERE day < (NOW() AT TIME ZONE 'UTC')::date - 1
+UNION ALL
+SELECT (uph.opened_at AT TIME ZONE 'UTC')::date, uph.pack_type_id, ur.rarity, COUNT(*)
+FROM user_rewards ur
+JOIN user_pack_history uph ON uph.id = ur.pack_history_id
+WHERE uph.opened_at >= ((NOW() AT TIME ZONE 'UTC')::date - 1) AT TIME ZONE 'UTC'
+GROUP BY 1, 2, 3;
+
+CREATE OR REPLACE VIEW daily_reward_type_stats_live AS
+SELECT day, pack_type_id, reward_type, dealt, redeemed, expired
+FROM daily_reward_type_stats
+WHERE day < (NOW() AT TIME ZONE 'UTC')::date - 1
+UNION ALL
+SELECT (uph.opened_at AT TIME ZONE 'UTC')::date, uph.pack_type_id, ur.type, COUNT(*),
+       COUNT(*) FILTER (WHERE ur.is_used),
+       COUNT(*) FILTER (WHERE COALESCE(ur.is_used, false) = false AND ur.expires_at <= NOW())
+FROM user_rewards ur
+JOIN user_pack_history uph ON uph.id = ur.pack_history_id
+WHERE uph.opened_at >= ((NOW() AT TIME ZONE 'UTC')::date - 1) AT TIME ZONE 'UTC'
+GROUP BY 1, 2, 3;
+
+CREATE OR REPLACE VIEW daily_template_stats_live AS
+SELECT day, pack_type_id, template_id, dealt
+FROM daily_template_stats
+WHERE day < (NOW() AT TIME ZONE 'UTC')::date - 1
+UNION ALL
+SELECT (uph.opened_at AT TIME ZONE 'UTC')::date, uph.pack_type_id, ur.template_id, COUNT(*)
+FROM user_rewards ur
+JOIN user_pack_history uph ON uph.id = ur.pack_history_id
+WHERE uph.opened_at >= ((NOW() AT TIME ZONE 'UTC')::date - 1) AT TIME ZONE 'UTC'
+  AND ur.template_id IS NOT NULL
+GROUP BY 1, 2, 3;
+
+CREATE OR REPLACE VIEW daily_coin_flows_live AS
+SELECT day, reason, sourced, sunk
+FROM daily_coin_flows
+WHERE day < (NOW() AT TIME ZONE 'UTC')::date - 1
+UNION ALL
+SELECT (created_at AT TIME ZONE 'UTC')::date, reason,
+       COALESCE(SUM(delta) FILTER (WHERE delta > 0), 0),
+       COALESCE(-SUM(delta) FILTER (WHERE delta < 0), 0)
+FROM coin_ledger
+WHERE created_at >= ((NOW() AT TIME ZONE 'UTC')::date - 1) AT TIME ZONE 'UTC'
+GROUP BY 1, 2;
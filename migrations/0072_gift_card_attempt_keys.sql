@@ -0,0 +1,5 @@
+-- Idempotency key of the gift card submission in flight. Kept until the attempt is known
+-- to have failed, so a submission whose response was lost is replayed with the same key;
+-- the next attempt after a failure gets a new one.
+ALTER TABLE user_rewards
+    ADD COLUMN IF NOT EXISTS fulfillment_idempotency_key UUID;
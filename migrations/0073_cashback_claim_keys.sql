@@ -0,0 +1,8 @@
+-- Cashback claims are committed as 'submitting' before the payout is sent, so no row lock
+-- is held across the call. The idempotency key is kept until the payout is known to have
+-- failed, then replaced so a new claim is a new payout.
+ALTER TABLE user_rewards DROP CONSTRAINT IF EXISTS user_rewards_payout_status_check;
+ALTER TABLE user_rewards
+    ADD CONSTRAINT user_rewards_payout_status_check
+        CHECK (payout_status IN ('submitting', 'submitted', 'paid', 'failed')),
+    ADD COLUMN IF NOT EXISTS payout_idempotency_key UUID;
@@ -0,0 +1,3 @@
+-- Refunds find the first purchase behind an open through the inventory pack it opened
+CREATE INDEX IF NOT EXISTS idx_first_purchases_user_pack ON first_purchases (user_pack_id);
+CREATE INDEX IF NOT EXISTS idx_user_packs_history ON user_packs (pack_history_id) WHERE pack_history_id IS NOT NULL;
@@ -0,0 +1,7 @@
+-- Stamped each time the grant worker picks a subscriber up, so subscribers it can't grant
+-- (missing plan, billing unreachable) are retried later instead of starving the batch
+ALTER TABLE vip_subscriptions ADD COLUMN IF NOT EXISTS grant_attempted_at TIMESTAMPTZ;
+
+DROP INDEX IF EXISTS idx_vip_subscriptions_active;
+CREATE INDEX IF NOT EXISTS idx_vip_subscriptions_due ON vip_subscriptions (grant_attempted_at NULLS FIRST, current_period_start)
+    WHERE status = 'active';
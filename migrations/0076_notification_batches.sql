@@ -0,0 +1,6 @@
+-- Delivery claims and batches. A worker leases the events it sends so concurrent workers
+-- skip them. An event joins a batch before its first send; the batch id is the
+-- notification service's idempotency key, so a retried batch always carries the same events.
+ALTER TABLE notification_events
+    ADD COLUMN IF NOT EXISTS delivery_claimed_until TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS delivery_batch_id UUID;
@@ -0,0 +1,4 @@
+-- The odds a random open drew from: the user's adjusted weights per template, with any
+-- happy hour applied, and the templates the rare guarantee picked from. The odds audit
+-- tests drops against these rather than the configured weights.
+ALTER TABLE user_pack_history ADD COLUMN IF NOT EXISTS draw_odds JSONB;
@@ -0,0 +1,166 @@
+use crate::error::{AppError, Result};
+use crate::http_client::HttpServiceClient;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// How long a confirmed completion is reused before asking the ads service again
+const COMPLETION_CACHE_TTL: Duration = Duration::from_secs(60);
+/// Completions kept at most; once full, expired entries go first, then the oldest
+const COMPLETION_CACHE_MAX_ENTRIES: usize = 10_000;
+
+/// Confirms that a user finished watching an ad, for ad-gated rewards
+#[async_trait]
+pub trait AdVerifier: Send + Sync {
+    /// Whether `user_id` completed an ad in `placement` within the last `within`
+    async fn verify_recent_completion(&self, user_id: &str, placement: &str, within: chrono::Duration) -> Result<bool>;
+}
+
+/// Reads the ads service's `user_ad_interactions` table directly. The default until the
+/// ads service API is rolled out, and the fallback while it is.
+pub struct TableAdVerifier {
+    db: PgPool,
+}
+
+impl TableAdVerifier {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl AdVerifier for TableAdVerifier {
+    async fn verify_recent_completion(&self, user_id: &str, placement: &str, within: chrono::Duration) -> Result<bool> {
+        let completed = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM user_ad_interactions
+                WHERE user_id = $1 AND ad_placement = $2
+                  AND is_completed = true AND completed_at > $3
+            ) AS "completed!"
+            "#,
+            user_id,
+            placement,
+            Utc::now() - within
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(completed)
+    }
+}
+
+#[derive(Deserialize)]
+struct LatestCompletion {
+    completed_at: Option<DateTime<Utc>>,
+}
+
+/// Typed client for the ads service's completion API. Only completions are cached: a
+/// user who was just told to watch an ad must be re-checked as soon as they have.
+pub struct AdsServiceClient {
+    http: HttpServiceClient,
+    /// Latest completion per user and placement
+    completions: tokio::sync::RwLock<HashMap<(String, String), (Instant, DateTime<Utc>)>>,
+    table_fallback: Option<TableAdVerifier>,
+}
+
+impl AdsServiceClient {
+    pub fn new(base_url: String, timeout: Duration) -> Result<Self> {
+        Ok(Self {
+            http: HttpServiceClient::new("ads", base_url, timeout)?,
+            completions: tokio::sync::RwLock::new(HashMap::new()),
+            table_fallback: None,
+        })
+    }
+
+    /// Read the shared table when the ads service fails; for the migration only
+    pub fn with_table_fallback(mut self, fallback: TableAdVerifier) -> Self {
+        self.table_fallback = Some(fallback);
+        self
+    }
+
+    async fn latest_completion(&self, user_id: &str, placement: &str) -> Result<Option<DateTime<Utc>>> {
+        let request = self
+            .http
+            .get(&format!("/users/{}/completions/latest", user_id))
+            .query(&[("placement", placement)]);
+        let response = self.http.send(request).await?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(AppError::InternalError(format!(
+                "Ads service returned {} for completion", response.status()
+            )));
+        }
+
+        let latest: LatestCompletion = response
+            .json()
+            .await
+            .map_err(|e| AppError::InternalError(format!("Invalid ads service response: {}", e)))?;
+
+        Ok(latest.completed_at)
+    }
+}
+
+#[async_trait]
+impl AdVerifier for AdsServiceClient {
+    async fn verify_recent_completion(&self, user_id: &str, placement: &str, within: chrono::Duration) -> Result<bool> {
+        let cutoff = Utc::now() - within;
+        let key = (user_id.to_string(), placement.to_string());
+        if let Some((fetched, completed_at)) = self.completions.read().await.get(&key) {
+            if fetched.elapsed() < COMPLETION_CACHE_TTL && *completed_at > cutoff {
+                return Ok(true);
+            }
+        }
+
+        match self.latest_completion(user_id, placement).await {
+            Ok(Some(completed_at)) => {
+                let mut completions = self.completions.write().await;
+                if completions.len() >= COMPLETION_CACHE_MAX_ENTRIES && !completions.contains_key(&key) {
+                    completions.retain(|_, (fetched, _)| fetched.elapsed() < COMPLETION_CACHE_TTL);
+                    if completions.len() >= COMPLETION_CACHE_MAX_ENTRIES {
+                        let oldest = completions.iter().min_by_key(|(_, (fetched, _))| *fetched).map(|(key, _)| key.clone());
+                        if let Some(oldest) = oldest {
+                            completions.remove(&oldest);
+                        }
+                    }
+                }
+                completions.insert(key, (Instant::now(), completed_at));
+                Ok(completed_at > cutoff)
+            }
+            Ok(None) => Ok(false),
+            Err(e) => match &self.table_fallback {
+                Some(fallback) => {
+                    warn!("Ads service unavailable, checking the shared table: {:?}", e);
+                    fallback.verify_recent_completion(user_id, placement, within).await
+                }
+                None => Err(e),
+            },
+        }
+    }
+}
+
+/// Pick the verifier from configuration. `ADS_VERIFICATION` is `table` (the default,
+/// reading the shared table), `service` (the ads service API only) or `service_with_fallback`
+/// (the API, reading the table when it fails). The service modes need `ADS_SERVICE_URL`.
+pub fn from_env(db: PgPool, timeout: Duration) -> Result<std::sync::Arc<dyn AdVerifier>> {
+    let mode = std::env::var("ADS_VERIFICATION").unwrap_or_else(|_| "table".to_string());
+    if mode == "table" {
+        return Ok(std::sync::Arc::new(TableAdVerifier::new(db)));
+    }
+
+    let base_url = std::env::var("ADS_SERVICE_URL")
+        .map_err(|_| AppError::InternalError(format!("ADS_VERIFICATION={} needs ADS_SERVICE_URL", mode)))?;
+    let client = AdsServiceClient::new(base_url, timeout)?;
+    match mode.as_str() {
+        "service" => Ok(std::sync::Arc::new(client)),
+        "service_with_fallback" => Ok(std::sync::Arc::new(client.with_table_fallback(TableAdVerifier::new(db)))),
+        other => Err(AppError::InternalError(format!("Unknown ADS_VERIFICATION '{}'", other))),
+    }
+}
@@ -0,0 +1,161 @@
+use crate::error::Result;
+use chrono::{Duration, NaiveDate, Utc};
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::{error, info};
+
+/// Days recomputed by every refresh; long enough for redemptions and 30-day expiries to land
+const REFRESH_WINDOW_DAYS: i64 = 35;
+
+/// Nightly job that rebuilds the trailing window of rollups from the raw tables. Nothing
+/// else writes the rollups: today and yesterday are computed from the raw tables by the
+/// `*_live` views (migration 0071), so opens and ledger writes never touch them.
+pub struct AggregateWorker {
+    db: PgPool,
+}
+
+impl AggregateWorker {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    /// Run `refresh_recent` once a day
+    pub fn spawn(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(24 * 60 * 60));
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.refresh_recent().await {
+                    error!("Daily aggregate refresh failed: {:?}", e);
+                }
+            }
+        })
+    }
+
+    /// Recompute every finished day in the refresh window, oldest first
+    pub async fn refresh_recent(&self) -> Result<()> {
+        let today = Utc::now().date_naive();
+        for offset in (1..=REFRESH_WINDOW_DAYS).rev() {
+            self.refresh_day(today - Duration::days(offset)).await?;
+        }
+        info!("Refreshed daily aggregates for the last {} days", REFRESH_WINDOW_DAYS);
+        Ok(())
+    }
+
+    /// Replace one day's rollups with values computed from raw data. Runs in a single
+    /// transaction so readers never see a half-rebuilt day. A day another replica is
+    /// already rebuilding is skipped.
+    pub async fn refresh_day(&self, day: NaiveDate) -> Result<()> {
+        let mut tx = self.db.begin().await?;
+
+        let locked = sqlx::query_scalar!(
+            r#"SELECT pg_try_advisory_xact_lock(hashtext($1)) AS "locked!""#,
+            format!("aggregates:{}", day)
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if !locked {
+            return Ok(());
+        }
+
+        sqlx::query!("DELETE FROM daily_pack_stats WHERE day = $1", day)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO daily_pack_stats (day, pack_type_id, opens, unique_users)
+            SELECT $1, pack_type_id, COUNT(*), COUNT(DISTINCT user_id)
+            FROM user_pack_history
+            WHERE (opened_at AT TIME ZONE 'UTC')::date = $1
+            GROUP BY pack_type_id
+            "#,
+            day
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!("DELETE FROM daily_rarity_stats WHERE day = $1", day)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO daily_rarity_stats (day, pack_type_id, rarity, dealt)
+            SELECT $1, uph.pack_type_id, ur.rarity, COUNT(*)
+            FROM user_rewards ur
+            JOIN user_pack_history uph ON uph.id = ur.pack_history_id
+            WHERE (uph.opened_at AT TIME ZONE 'UTC')::date = $1
+            GROUP BY uph.pack_type_id, ur.rarity
+            "#,
+            day
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!("DELETE FROM daily_reward_type_stats WHERE day = $1", day)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO daily_reward_type_stats (day, pack_type_id, reward_type, dealt, redeemed, expired)
+            SELECT $1, uph.pack_type_id, ur.type, COUNT(*),
+                   COUNT(*) FILTER (WHERE ur.is_used),
+                   COUNT(*) FILTER (WHERE COALESCE(ur.is_used, false) = false AND ur.expires_at <= NOW())
+            FROM user_rewards ur
+            JOIN user_pack_history uph ON uph.id = ur.pack_history_id
+            WHERE (uph.opened_at AT TIME ZONE 'UTC')::date = $1
+            GROUP BY uph.pack_type_id, ur.type
+            "#,
+            day
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!("DELETE FROM daily_template_stats WHERE day = $1", day)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO daily_template_stats (day, pack_type_id, template_id, dealt)
+            SELECT $1, uph.pack_type_id, ur.template_id, COUNT(*)
+            FROM user_rewards ur
+            JOIN user_pack_history uph ON uph.id = ur.pack_history_id
+            WHERE (uph.opened_at AT TIME ZONE 'UTC')::date = $1 AND ur.template_id IS NOT NULL
+            GROUP BY uph.pack_type_id, ur.template_id
+            "#,
+            day
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!("DELETE FROM daily_coin_flows WHERE day = $1", day)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO daily_coin_flows (day, reason, sourced, sunk)
+            SELECT $1, reason,
+                   COALESCE(SUM(delta) FILTER (WHERE delta > 0), 0),
+                   COALESCE(-SUM(delta) FILTER (WHERE delta < 0), 0)
+            FROM coin_ledger
+            WHERE (created_at AT TIME ZONE 'UTC')::date = $1
+            GROUP BY reason
+            "#,
+            day
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO aggregate_refresh_runs (day) VALUES ($1)
+            ON CONFLICT (day) DO UPDATE SET refreshed_at = NOW()
+            "#,
+            day
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,281 @@
+use crate::error::{AppError, Result};
+use chrono::{Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+const DEFAULT_RANGE_DAYS: i64 = 30;
+const MAX_RANGE_DAYS: i64 = 366;
+
+/// Inclusive date range (UTC days); defaults to the last 30 days
+#[derive(Debug, Default, Deserialize)]
+pub struct AnalyticsQuery {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub pack_type_id: Option<Uuid>,
+}
+
+impl AnalyticsQuery {
+    fn range(&self) -> Result<(NaiveDate, NaiveDate)> {
+        let to = self.to.unwrap_or_else(|| Utc::now().date_naive());
+        let from = self.from.unwrap_or(to - Duration::days(DEFAULT_RANGE_DAYS - 1));
+        if from > to || (to - from).num_days() >= MAX_RANGE_DAYS {
+            return Err(AppError::BadRequest(format!(
+                "from must be before to and the range at most {} days", MAX_RANGE_DAYS
+            )));
+        }
+        Ok((from, to))
+    }
+}
+
+#[derive(Debug, FromRow, Serialize)]
+pub struct PackOpensRow {
+    pub day: NaiveDate,
+    pub pack_type_id: Uuid,
+    pub pack_name: String,
+    pub opens: i64,
+    pub unique_users: i64,
+}
+
+#[derive(Debug, FromRow, Serialize)]
+pub struct RarityRow {
+    pub pack_type_id: Uuid,
+    pub rarity: String,
+    pub dealt: i64,
+    pub share: f64,
+}
+
+#[derive(Debug, FromRow, Serialize)]
+pub struct RedemptionRateRow {
+    pub reward_type: String,
+    pub dealt: i64,
+    pub redeemed: i64,
+    pub expired: i64,
+    pub redemption_rate: f64,
+}
+
+#[derive(Debug, FromRow, Serialize)]
+pub struct CoinFlowRow {
+    pub reason: String,
+    pub sourced: i64,
+    pub sunk: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CoinFlowResponse {
+    pub flows: Vec<CoinFlowRow>,
+    pub total_sourced: i64,
+    pub total_sunk: i64,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct EconomyQuery {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub granularity: Option<String>, // day | week
+}
+
+/// Coins minted and sunk in one day or week, with running totals since `from`
+#[derive(Debug, Serialize)]
+pub struct EconomyPeriod {
+    pub period_start: NaiveDate,
+    pub minted: i64,
+    pub sunk: i64,
+    pub net: i64,
+    pub minted_by_reason: BTreeMap<String, i64>,
+    pub sunk_by_reason: BTreeMap<String, i64>,
+    pub cumulative_minted: i64,
+    pub cumulative_sunk: i64,
+    pub cumulative_net: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EconomyReport {
+    pub granularity: String,
+    pub periods: Vec<EconomyPeriod>,
+    /// Coins held by all users right now
+    pub circulating_supply: i64,
+}
+
+/// Aggregates for the PM dashboard, served from the daily rollup tables
+pub struct AnalyticsService {
+    db: PgPool,
+}
+
+impl AnalyticsService {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    /// Pack opens per pack type per day
+    pub async fn pack_opens(&self, query: &AnalyticsQuery) -> Result<Vec<PackOpensRow>> {
+        let (from, to) = query.range()?;
+        let rows = sqlx::query_as!(
+            PackOpensRow,
+            r#"
+            SELECT dps.day AS "day!", dps.pack_type_id AS "pack_type_id!", pt.name AS pack_name,
+                   dps.opens AS "opens!", dps.unique_users AS "unique_users!"
+            FROM daily_pack_stats_live dps
+            JOIN pack_types pt ON pt.id = dps.pack_type_id
+            WHERE dps.day BETWEEN $1 AND $2
+              AND ($3::uuid IS NULL OR dps.pack_type_id = $3)
+            ORDER BY dps.day, pt.name
+            "#,
+            from,
+            to,
+            query.pack_type_id
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Rarity distribution actually dealt, per pack type
+    pub async fn rarity_distribution(&self, query: &AnalyticsQuery) -> Result<Vec<RarityRow>> {
+        let (from, to) = query.range()?;
+        let rows = sqlx::query_as!(
+            RarityRow,
+            r#"
+            SELECT pack_type_id AS "pack_type_id!", rarity AS "rarity!", SUM(dealt)::bigint AS "dealt!",
+                   (SUM(dealt)::float8 / SUM(SUM(dealt)) OVER (PARTITION BY pack_type_id)) AS "share!"
+            FROM daily_rarity_stats_live
+            WHERE day BETWEEN $1 AND $2
+              AND ($3::uuid IS NULL OR pack_type_id = $3)
+            GROUP BY pack_type_id, rarity
+            ORDER BY pack_type_id, rarity
+            "#,
+            from,
+            to,
+            query.pack_type_id
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Share of rewards dealt in the range that were redeemed, by reward type.
+    /// Redemptions and expiries are as of the last nightly refresh, or live for the last two days.
+    pub async fn redemption_rates(&self, query: &AnalyticsQuery) -> Result<Vec<RedemptionRateRow>> {
+        let (from, to) = query.range()?;
+        let rows = sqlx::query_as!(
+            RedemptionRateRow,
+            r#"
+            SELECT reward_type AS "reward_type!",
+                   SUM(dealt)::bigint AS "dealt!",
+                   SUM(redeemed)::bigint AS "redeemed!",
+                   SUM(expired)::bigint AS "expired!",
+                   COALESCE(SUM(redeemed)::float8 / NULLIF(SUM(dealt), 0), 0) AS "redemption_rate!"
+            FROM daily_reward_type_stats_live
+            WHERE day BETWEEN $1 AND $2
+              AND ($3::uuid IS NULL OR pack_type_id = $3)
+            GROUP BY reward_type
+            ORDER BY reward_type
+            "#,
+            from,
+            to,
+            query.pack_type_id
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// DealCoins created (sources) and spent (sinks) by ledger reason
+    pub async fn coin_flows(&self, query: &AnalyticsQuery) -> Result<CoinFlowResponse> {
+        let (from, to) = query.range()?;
+        let flows = sqlx::query_as!(
+            CoinFlowRow,
+            r#"
+            SELECT reason AS "reason!", SUM(sourced)::bigint AS "sourced!", SUM(sunk)::bigint AS "sunk!"
+            FROM daily_coin_flows_live
+            WHERE day BETWEEN $1 AND $2
+            GROUP BY reason
+            ORDER BY reason
+            "#,
+            from,
+            to
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(CoinFlowResponse {
+            total_sourced: flows.iter().map(|f| f.sourced).sum(),
+            total_sunk: flows.iter().map(|f| f.sunk).sum(),
+            flows,
+        })
+    }
+
+    /// Coins minted (rewards, bonuses, starting balances, promos) versus coins sunk
+    /// (pack purchases) per day or week, for the economy team
+    pub async fn coin_economy(&self, query: &EconomyQuery) -> Result<EconomyReport> {
+        let granularity = query.granularity.clone().unwrap_or_else(|| "day".to_string());
+        if !matches!(granularity.as_str(), "day" | "week") {
+            return Err(AppError::BadRequest("granularity must be day or week".to_string()));
+        }
+        let (from, to) = AnalyticsQuery { from: query.from, to: query.to, pack_type_id: None }.range()?;
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT date_trunc($3, day)::date AS "period_start!", reason AS "reason!",
+                   SUM(sourced)::bigint AS "sourced!", SUM(sunk)::bigint AS "sunk!"
+            FROM daily_coin_flows_live
+            WHERE day BETWEEN $1 AND $2
+            GROUP BY 1, reason
+            ORDER BY 1, reason
+            "#,
+            from,
+            to,
+            granularity
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        let mut periods: Vec<EconomyPeriod> = Vec::new();
+        for row in rows {
+            if periods.last().map(|p| p.period_start) != Some(row.period_start) {
+                periods.push(EconomyPeriod {
+                    period_start: row.period_start,
+                    minted: 0,
+                    sunk: 0,
+                    net: 0,
+                    minted_by_reason: BTreeMap::new(),
+                    sunk_by_reason: BTreeMap::new(),
+                    cumulative_minted: 0,
+                    cumulative_sunk: 0,
+                    cumulative_net: 0,
+                });
+            }
+            let period = periods.last_mut().expect("period pushed above");
+            period.minted += row.sourced;
+            period.sunk += row.sunk;
+            if row.sourced > 0 {
+                period.minted_by_reason.insert(row.reason.clone(), row.sourced);
+            }
+            if row.sunk > 0 {
+                period.sunk_by_reason.insert(row.reason, row.sunk);
+            }
+        }
+
+        let (mut minted, mut sunk) = (0, 0);
+        for period in &mut periods {
+            period.net = period.minted - period.sunk;
+            minted += period.minted;
+            sunk += period.sunk;
+            period.cumulative_minted = minted;
+            period.cumulative_sunk = sunk;
+            period.cumulative_net = minted - sunk;
+        }
+
+        let circulating_supply = sqlx::query_scalar!(
+            r#"SELECT COALESCE(SUM(deal_coins), 0)::bigint AS "supply!" FROM user_lootpack_stats"#
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(EconomyReport { granularity, periods, circulating_supply })
+    }
+}
@@ -0,0 +1,229 @@
+use crate::error::{AppError, Result};
+use crate::audit;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use sqlx::{FromRow, PgExecutor, PgPool};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Largest icon accepted; icons are shown at most at 256px so this is generous
+pub const MAX_ICON_BYTES: usize = 512 * 1024;
+
+/// Where uploaded objects are written. Keys are relative paths such as `icons/<sha256>.png`.
+#[async_trait]
+pub trait AssetStorage: Send + Sync {
+    async fn put(&self, key: &str, content_type: &str, bytes: Vec<u8>) -> Result<()>;
+}
+
+/// Writes objects under a local directory; for development, served by whatever fronts the directory
+pub struct LocalAssetStorage {
+    root: PathBuf,
+}
+
+impl LocalAssetStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+#[async_trait]
+impl AssetStorage for LocalAssetStorage {
+    async fn put(&self, key: &str, _content_type: &str, bytes: Vec<u8>) -> Result<()> {
+        let path = self.root.join(key);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| AppError::InternalError(format!("Failed to create asset directory: {}", e)))?;
+        }
+        tokio::fs::write(&path, bytes)
+            .await
+            .map_err(|e| AppError::InternalError(format!("Failed to write asset: {}", e)))
+    }
+}
+
+/// S3 or GCS bucket, chosen by the URL scheme (`s3://bucket/prefix`, `gs://bucket/prefix`).
+/// Credentials come from the usual AWS_* / GOOGLE_* environment variables.
+pub struct ObjectStoreStorage {
+    store: Box<dyn object_store::ObjectStore>,
+    prefix: object_store::path::Path,
+}
+
+impl ObjectStoreStorage {
+    pub fn from_url(url: &str) -> Result<Self> {
+        let url = url::Url::parse(url)
+            .map_err(|e| AppError::InternalError(format!("Invalid asset storage URL: {}", e)))?;
+        let (store, prefix) = object_store::parse_url_opts(&url, std::env::vars())
+            .map_err(|e| AppError::InternalError(format!("Unsupported asset storage URL: {}", e)))?;
+        Ok(Self { store, prefix })
+    }
+}
+
+#[async_trait]
+impl AssetStorage for ObjectStoreStorage {
+    async fn put(&self, key: &str, content_type: &str, bytes: Vec<u8>) -> Result<()> {
+        let path = key
+            .split('/')
+            .fold(self.prefix.clone(), |path, part| path.child(part));
+        let attributes = object_store::Attributes::from_iter([(
+            object_store::Attribute::ContentType,
+            content_type.to_string().into(),
+        )]);
+        let options = object_store::PutOptions { attributes, ..Default::default() };
+
+        self.store
+            .put_opts(&path, bytes.into(), options)
+            .await
+            .map_err(|e| AppError::InternalError(format!("Failed to upload asset: {}", e)))?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, FromRow, Serialize)]
+pub struct Asset {
+    pub id: Uuid,
+    pub storage_key: String,
+    pub content_type: String,
+    pub size_bytes: i32,
+    pub sha256: String,
+    pub uploaded_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UploadedIcon {
+    #[serde(flatten)]
+    pub asset: Asset,
+    pub url: String,
+}
+
+/// What an icon is attached to
+#[derive(Debug, Clone, Copy)]
+pub enum IconTarget {
+    Pack(Uuid),
+    RewardTemplate(Uuid),
+}
+
+/// Public URL of a stored object
+pub fn cdn_url(base_url: &str, storage_key: &str) -> String {
+    format!("{}/{}", base_url.trim_end_matches('/'), storage_key)
+}
+
+/// Image type from the file's magic bytes; the client's Content-Type is not trusted.
+/// SVG is deliberately not accepted since it can carry script.
+fn sniff_image(bytes: &[u8]) -> Option<(&'static str, &'static str)> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some(("image/png", "png"))
+    } else if bytes.starts_with(&[0xff, 0xd8, 0xff]) {
+        Some(("image/jpeg", "jpg"))
+    } else if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some(("image/webp", "webp"))
+    } else {
+        None
+    }
+}
+
+/// Uploaded icon keys for every pack that has one
+pub async fn pack_icon_keys<'e, E: PgExecutor<'e>>(executor: E) -> Result<HashMap<Uuid, String>> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT pt.id, a.storage_key
+        FROM pack_types pt
+        JOIN assets a ON a.id = pt.icon_asset_id
+        "#
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(rows.into_iter().map(|r| (r.id, r.storage_key)).collect())
+}
+
+pub struct AssetService {
+    db: PgPool,
+    storage: Arc<dyn AssetStorage>,
+    cdn_base_url: String,
+}
+
+impl AssetService {
+    pub fn new(db: PgPool, storage: Arc<dyn AssetStorage>, cdn_base_url: String) -> Self {
+        Self { db, storage, cdn_base_url }
+    }
+
+    /// Validate and store an icon, then point the pack or template at it
+    pub async fn upload_icon(&self, actor: &str, target: IconTarget, bytes: Vec<u8>) -> Result<UploadedIcon> {
+        if bytes.is_empty() {
+            return Err(AppError::BadRequest("Icon is empty".to_string()));
+        }
+        if bytes.len() > MAX_ICON_BYTES {
+            return Err(AppError::BadRequest(format!(
+                "Icon is {} bytes; the limit is {}", bytes.len(), MAX_ICON_BYTES
+            )));
+        }
+        let (content_type, extension) = sniff_image(&bytes)
+            .ok_or_else(|| AppError::BadRequest("Icon must be a PNG, JPEG or WebP image".to_string()))?;
+
+        let sha256 = format!("{:x}", Sha256::digest(&bytes));
+        let storage_key = format!("icons/{}.{}", sha256, extension);
+        let size_bytes = bytes.len() as i32;
+
+        // Upload before recording; a crash in between leaves an orphaned object, never a dangling row
+        self.storage.put(&storage_key, content_type, bytes).await?;
+
+        let mut tx = self.db.begin().await?;
+        let asset = sqlx::query_as!(
+            Asset,
+            r#"
+            INSERT INTO assets (storage_key, content_type, size_bytes, sha256, uploaded_by)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (storage_key) DO UPDATE SET storage_key = EXCLUDED.storage_key
+            RETURNING id, storage_key, content_type, size_bytes, sha256, uploaded_by, created_at
+            "#,
+            storage_key,
+            content_type,
+            size_bytes,
+            sha256,
+            actor
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let (updated, subject) = match target {
+            IconTarget::Pack(id) => (
+                sqlx::query!(
+                    "UPDATE pack_types SET icon_asset_id = $2, updated_at = NOW() WHERE id = $1",
+                    id,
+                    asset.id
+                )
+                .execute(&mut *tx)
+                .await?,
+                format!("pack:{}", id),
+            ),
+            IconTarget::RewardTemplate(id) => (
+                sqlx::query!("UPDATE reward_templates SET icon_asset_id = $2 WHERE id = $1", id, asset.id)
+                    .execute(&mut *tx)
+                    .await?,
+                format!("reward_template:{}", id),
+            ),
+        };
+        if updated.rows_affected() == 0 {
+            return Err(AppError::NotFound("Icon target not found".to_string()));
+        }
+
+        audit::record(
+            &mut *tx,
+            actor,
+            "asset.icon_uploaded",
+            Some(&subject),
+            json!({ "asset_id": asset.id, "storage_key": asset.storage_key }),
+        )
+        .await?;
+        tx.commit().await?;
+
+        let url = cdn_url(&self.cdn_base_url, &asset.storage_key);
+        Ok(UploadedIcon { asset, url })
+    }
+}
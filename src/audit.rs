@@ -0,0 +1,24 @@
+use crate::error::Result;
+use serde_json::Value;
+use sqlx::PgExecutor;
+
+/// Append an entry to the audit log. `actor` is a user id, `admin:<key>` or `system`.
+pub async fn record<'e, E: PgExecutor<'e>>(
+    executor: E,
+    actor: &str,
+    action: &str,
+    subject: Option<&str>,
+    details: Value,
+) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO audit_log (actor, action, subject, details) VALUES ($1, $2, $3, $4)",
+        actor,
+        action,
+        subject,
+        details
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
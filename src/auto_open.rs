@@ -0,0 +1,57 @@
+use crate::audit;
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OpenPreferences {
+    /// Open queued low-tier packs in one batch with a summary instead of reveals
+    pub auto_open_low_tier: bool,
+}
+
+pub struct AutoOpenService {
+    db: PgPool,
+}
+
+impl AutoOpenService {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    pub async fn get_preferences(&self, user_id: &str) -> Result<OpenPreferences> {
+        let auto_open_low_tier = sqlx::query_scalar!(
+            "SELECT auto_open_low_tier FROM user_open_preferences WHERE user_id = $1",
+            user_id
+        )
+        .fetch_optional(&self.db)
+        .await?
+        .unwrap_or(false);
+
+        Ok(OpenPreferences { auto_open_low_tier })
+    }
+
+    pub async fn update_preferences(&self, user_id: &str, preferences: OpenPreferences) -> Result<OpenPreferences> {
+        let mut tx = self.db.begin().await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO user_open_preferences (user_id, auto_open_low_tier)
+            VALUES ($1, $2)
+            ON CONFLICT (user_id) DO UPDATE SET
+                auto_open_low_tier = EXCLUDED.auto_open_low_tier, updated_at = NOW()
+            "#,
+            user_id,
+            preferences.auto_open_low_tier
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        audit::record(&mut *tx, user_id, "open_preferences.updated", None, json!({
+            "auto_open_low_tier": preferences.auto_open_low_tier,
+        }))
+        .await?;
+
+        tx.commit().await?;
+        Ok(preferences)
+    }
+}
@@ -0,0 +1,180 @@
+use crate::error::{AppError, Result};
+use crate::http_client::HttpServiceClient;
+use crate::privacy;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// Billing products features check entitlements for
+pub const VIP_PRODUCT: &str = "lootpacks_vip";
+
+/// How long a subscription lookup is reused before asking billing again
+const CACHE_TTL: Duration = Duration::from_secs(60);
+/// Oldest lookup still served while billing is down; older entries are dropped
+const STALE_MAX_AGE: Duration = Duration::from_secs(15 * 60);
+/// Lookups kept at most; once full, entries past `STALE_MAX_AGE` go first, then the oldest
+const CACHE_MAX_ENTRIES: usize = 10_000;
+/// Consecutive failures that open the circuit, and how long it stays open
+const FAILURE_THRESHOLD: u32 = 5;
+const OPEN_DURATION: Duration = Duration::from_secs(30);
+
+/// A subscription as billing reports it
+#[derive(Debug, Clone, Deserialize)]
+pub struct Subscription {
+    pub user_id: String,
+    pub plan_id: String,
+    /// active, past_due, cancelled or expired
+    pub status: String,
+    pub current_period_start: DateTime<Utc>,
+    pub current_period_end: DateTime<Utc>,
+}
+
+impl Subscription {
+    pub fn is_entitled(&self, now: DateTime<Utc>) -> bool {
+        self.status == "active" && self.current_period_start <= now && now < self.current_period_end
+    }
+}
+
+#[derive(Default)]
+struct Breaker {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+/// Shared client for the platform billing service's subscription API. Features ask it
+/// about entitlements rather than calling billing themselves. Lookups are cached per
+/// user and product; after repeated failures the circuit opens and calls fail fast,
+/// serving the last known subscription where there is one and it isn't too old.
+pub struct BillingClient {
+    http: HttpServiceClient,
+    /// Last lookup per user and product
+    cache: tokio::sync::RwLock<HashMap<(String, String), (Instant, Option<Subscription>)>>,
+    breaker: Mutex<Breaker>,
+}
+
+impl BillingClient {
+    pub fn new(base_url: String, timeout: Duration) -> Result<Self> {
+        Ok(Self {
+            http: HttpServiceClient::new("billing", base_url, timeout)?,
+            cache: tokio::sync::RwLock::new(HashMap::new()),
+            breaker: Mutex::new(Breaker::default()),
+        })
+    }
+
+    /// The user's subscription to `product`, or `None` if they never subscribed
+    pub async fn subscription(&self, user_id: &str, product: &str) -> Result<Option<Subscription>> {
+        let key = (user_id.to_string(), product.to_string());
+        {
+            let cache = self.cache.read().await;
+            if let Some((fetched, subscription)) = cache.get(&key) {
+                if fetched.elapsed() < CACHE_TTL {
+                    return Ok(subscription.clone());
+                }
+            }
+        }
+
+        match self.fetch(user_id, product).await {
+            Ok(subscription) => {
+                let mut cache = self.cache.write().await;
+                if cache.len() >= CACHE_MAX_ENTRIES && !cache.contains_key(&key) {
+                    evict(&mut cache);
+                }
+                cache.insert(key, (Instant::now(), subscription.clone()));
+                Ok(subscription)
+            }
+            Err(e) => match self.cache.read().await.get(&key) {
+                Some((fetched, stale)) if fetched.elapsed() < STALE_MAX_AGE => {
+                    warn!("Billing unavailable, using stale {} subscription for {}: {:?}", product, privacy::pseudonym(user_id), e);
+                    Ok(stale.clone())
+                }
+                _ => Err(e),
+            },
+        }
+    }
+
+    /// Whether the user currently has an active subscription to `product`
+    pub async fn is_entitled(&self, user_id: &str, product: &str) -> Result<bool> {
+        let subscription = self.subscription(user_id, product).await?;
+        Ok(subscription.is_some_and(|s| s.is_entitled(Utc::now())))
+    }
+
+    /// Drop cached lookups for the user, e.g. when billing pushes a change
+    pub async fn invalidate(&self, user_id: &str) {
+        self.cache.write().await.retain(|(cached_user, _), _| cached_user != user_id);
+    }
+
+    async fn fetch(&self, user_id: &str, product: &str) -> Result<Option<Subscription>> {
+        self.check_circuit()?;
+        let result = self.request(user_id, product).await;
+        self.record_result(result.is_ok());
+        result
+    }
+
+    async fn request(&self, user_id: &str, product: &str) -> Result<Option<Subscription>> {
+        let request = self.http.get(&format!("/subscriptions/{}", user_id)).query(&[("product", product)]);
+        let response = self.http.send(request).await?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(AppError::InternalError(format!(
+                "Billing service returned {} for subscription", response.status()
+            )));
+        }
+
+        let subscription = response
+            .json()
+            .await
+            .map_err(|e| AppError::InternalError(format!("Invalid billing response: {}", e)))?;
+
+        Ok(Some(subscription))
+    }
+
+    /// Fail fast while the circuit is open; once it has been open long enough one
+    /// call goes through to probe billing
+    fn check_circuit(&self) -> Result<()> {
+        let mut breaker = self.breaker.lock().unwrap();
+        match breaker.open_until {
+            Some(until) if Instant::now() < until => {
+                Err(AppError::InternalError("Billing service unavailable".to_string()))
+            }
+            Some(_) => {
+                // Half-open: let this call probe, and hold others off until it reports back
+                breaker.open_until = Some(Instant::now() + OPEN_DURATION);
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn record_result(&self, succeeded: bool) {
+        let mut breaker = self.breaker.lock().unwrap();
+        if succeeded {
+            *breaker = Breaker::default();
+            return;
+        }
+
+        breaker.consecutive_failures += 1;
+        if breaker.consecutive_failures >= FAILURE_THRESHOLD {
+            if breaker.open_until.is_none() {
+                warn!("Billing circuit opened after {} failures", breaker.consecutive_failures);
+            }
+            breaker.open_until = Some(Instant::now() + OPEN_DURATION);
+        }
+    }
+}
+
+/// Make room in a full cache: drop lookups too old to serve even as a fallback, or the
+/// oldest one if none are
+fn evict(cache: &mut HashMap<(String, String), (Instant, Option<Subscription>)>) {
+    cache.retain(|_, (fetched, _)| fetched.elapsed() < STALE_MAX_AGE);
+    if cache.len() >= CACHE_MAX_ENTRIES {
+        if let Some(oldest) = cache.iter().min_by_key(|(_, (fetched, _))| *fetched).map(|(key, _)| key.clone()) {
+            cache.remove(&oldest);
+        }
+    }
+}
@@ -0,0 +1,383 @@
+//! Operator CLI for the lootpacks service.
+//!
+//! Talks to the same database as the service. Commands that need the reward
+//! engine (the simulator) go through the running service's admin API instead.
+
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use sqlx::postgres::{PgPool, PgPoolOptions};
+use sqlx::Row;
+use std::io::Read;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+const ACTOR: &str = "cli:lootpacks-admin";
+
+/// Must match `lootpacks::CACHE_FLUSH_CHANNEL` in the service
+const CACHE_FLUSH_CHANNEL: &str = "lootpacks_cache_flush";
+
+/// Scopes the service checks; `<area>:*` grants every scope in an area
+const API_KEY_SCOPES: &[&str] = &[
+    "admin:packs",
+    "admin:economy",
+    "admin:compliance",
+    "admin:liveops",
+    "admin:operations",
+    "admin:refunds",
+    "internal:validate",
+    "internal:events",
+    "internal:analytics",
+    "support:read",
+    "support:actions",
+    "support:grant",
+];
+
+/// Roles the service's permission matrix knows; a key with a role gets that role's scopes
+const API_KEY_ROLES: &[&str] = &["support", "live-ops", "admin"];
+
+const USAGE: &str = "\
+usage: lootpacks-admin <command> [args]
+
+commands:
+  seed-packs                                   insert the default pack types that don't exist yet
+  grant-pack <user_id> <pack_type_id> [reason] grant an unopened pack as compensation
+  rotate <pack_type_id>                        advance a pack's pool rotation now
+  flush-caches [pack_type_id]                  drop cached reward pools in every service instance
+  simulate <users> <opens_per_user> [pack_type_id...]
+                                               run the economy simulator on the running service
+  create-api-key <name> <role|scope>...        issue an API key; it is printed once and stored hashed
+  list-api-keys                                show keys with their roles, scopes and last use
+  revoke-api-key <id>                          revoke a key (instances stop accepting it within a minute)
+
+scopes: admin:packs admin:economy admin:compliance admin:liveops admin:operations admin:refunds
+        internal:validate internal:events internal:analytics
+        support:read support:actions support:grant   (<area>:* grants a whole area)
+roles:  support live-ops admin
+
+env:
+  DATABASE_URL       database the service uses
+  LOOTPACKS_URL      base URL of the running service (simulate only, default http://localhost:3005)
+  LOOTPACKS_API_KEY  key with admin:economy for the running service (simulate only)";
+
+/// name, type, description, price_coins, cooldown_hours, min_rewards, max_rewards
+type SeedPack = (&'static str, &'static str, &'static str, Option<i32>, Option<i32>, i32, i32);
+
+const DEFAULT_PACKS: &[SeedPack] = &[
+    ("Daily Deal Pack", "free", "A free pack every day", None, Some(24), 1, 2),
+    ("Bronze Pack", "standard", "Everyday coupons and cashback", Some(99), None, 2, 3),
+    ("Silver Pack", "standard", "Better odds on rare deals", Some(199), None, 3, 4),
+    ("Gold Pack", "premium", "Guaranteed rare or better", Some(299), None, 3, 5),
+];
+
+type CliResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Err(e) = run(&args).await {
+        eprintln!("❌ {}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn run(args: &[String]) -> CliResult<()> {
+    let Some(command) = args.first() else {
+        println!("{}", USAGE);
+        return Ok(());
+    };
+    let rest = &args[1..];
+
+    match command.as_str() {
+        "seed-packs" => seed_packs(&connect().await?).await,
+        "grant-pack" => match rest {
+            [user_id, pack_type_id] => grant_pack(&connect().await?, user_id, pack_type_id, "compensation").await,
+            [user_id, pack_type_id, reason] => grant_pack(&connect().await?, user_id, pack_type_id, reason).await,
+            _ => Err(USAGE.into()),
+        },
+        "rotate" => match rest {
+            [pack_type_id] => rotate(&connect().await?, pack_type_id).await,
+            _ => Err(USAGE.into()),
+        },
+        "flush-caches" => match rest {
+            [] => flush_caches(&connect().await?, None).await,
+            [pack_type_id] => flush_caches(&connect().await?, Some(pack_type_id)).await,
+            _ => Err(USAGE.into()),
+        },
+        "simulate" => match rest {
+            [users, opens, pack_type_ids @ ..] => simulate(users.parse()?, opens.parse()?, pack_type_ids).await,
+            _ => Err(USAGE.into()),
+        },
+        "create-api-key" => match rest {
+            [name, grants @ ..] if !grants.is_empty() => create_api_key(&connect().await?, name, grants).await,
+            _ => Err(USAGE.into()),
+        },
+        "list-api-keys" => list_api_keys(&connect().await?).await,
+        "revoke-api-key" => match rest {
+            [id] => revoke_api_key(&connect().await?, id).await,
+            _ => Err(USAGE.into()),
+        },
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        other => Err(format!("unknown command '{}'\n\n{}", other, USAGE).into()),
+    }
+}
+
+async fn connect() -> CliResult<PgPool> {
+    let url = std::env::var("DATABASE_URL").map_err(|_| "DATABASE_URL is not set")?;
+    Ok(PgPoolOptions::new().max_connections(1).connect(&url).await?)
+}
+
+async fn seed_packs(db: &PgPool) -> CliResult<()> {
+    let mut tx = db.begin().await?;
+    let mut created = 0;
+
+    for (name, pack_type, description, price_coins, cooldown_hours, min_rewards, max_rewards) in DEFAULT_PACKS {
+        let inserted = sqlx::query(
+            r#"
+            INSERT INTO pack_types (name, type, description, price_coins, cooldown_hours, min_rewards, max_rewards)
+            SELECT $1, $2, $3, $4, $5, $6, $7
+            WHERE NOT EXISTS (SELECT 1 FROM pack_types WHERE name = $1)
+            RETURNING id::text
+            "#,
+        )
+        .bind(name)
+        .bind(pack_type)
+        .bind(description)
+        .bind(price_coins)
+        .bind(cooldown_hours)
+        .bind(min_rewards)
+        .bind(max_rewards)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        match inserted {
+            Some(row) => {
+                let id: String = row.try_get("id")?;
+                audit(&mut tx, "pack.seeded", &id, json!({ "name": name })).await?;
+                println!("✅ {} ({})", name, id);
+                created += 1;
+            }
+            None => println!("   {} already exists", name),
+        }
+    }
+
+    tx.commit().await?;
+    println!("Seeded {} pack type(s)", created);
+    Ok(())
+}
+
+async fn grant_pack(db: &PgPool, user_id: &str, pack_type_id: &str, reason: &str) -> CliResult<()> {
+    let mut tx = db.begin().await?;
+
+    let pack_name: String = sqlx::query_scalar("SELECT name FROM pack_types WHERE id = $1::uuid")
+        .bind(pack_type_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or("pack type not found")?;
+
+    let user_pack_id: String = sqlx::query_scalar(
+        "INSERT INTO user_packs (user_id, pack_type_id, source) VALUES ($1, $2::uuid, 'compensation') RETURNING id::text",
+    )
+    .bind(user_id)
+    .bind(pack_type_id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    sqlx::query("INSERT INTO inbox_messages (user_id, kind, title, body) VALUES ($1, 'grant', $2, $3)")
+        .bind(user_id)
+        .bind(format!("You received a {}", pack_name))
+        .bind(reason)
+        .execute(&mut *tx)
+        .await?;
+
+    audit(
+        &mut tx,
+        "pack.granted",
+        user_id,
+        json!({ "user_pack_id": user_pack_id, "pack_type_id": pack_type_id, "reason": reason }),
+    )
+    .await?;
+
+    tx.commit().await?;
+    println!("✅ Granted {} to {} ({})", pack_name, user_id, user_pack_id);
+    Ok(())
+}
+
+/// Make the rotation due now; the service's rotation worker applies it on its next tick
+async fn rotate(db: &PgPool, pack_type_id: &str) -> CliResult<()> {
+    let mut tx = db.begin().await?;
+
+    let updated = sqlx::query("UPDATE pack_rotations SET next_change_at = NOW() WHERE pack_type_id = $1::uuid AND is_active")
+        .bind(pack_type_id)
+        .execute(&mut *tx)
+        .await?;
+    if updated.rows_affected() == 0 {
+        return Err("no active rotation for this pack".into());
+    }
+
+    audit(&mut tx, "rotation.forced", pack_type_id, json!({})).await?;
+    tx.commit().await?;
+    println!("✅ Rotation for {} is due; it will advance within a minute", pack_type_id);
+    Ok(())
+}
+
+async fn flush_caches(db: &PgPool, pack_type_id: Option<&String>) -> CliResult<()> {
+    // Cast through uuid so a typo fails here instead of silently flushing everything
+    sqlx::query("SELECT pg_notify($1, COALESCE($2::uuid::text, ''))")
+        .bind(CACHE_FLUSH_CHANNEL)
+        .bind(pack_type_id)
+        .execute(db)
+        .await?;
+
+    match pack_type_id {
+        Some(id) => println!("✅ Flushed cached pool for {}", id),
+        None => println!("✅ Flushed all cached pools"),
+    }
+    Ok(())
+}
+
+async fn simulate(users: u32, opens_per_user: u32, pack_type_ids: &[String]) -> CliResult<()> {
+    let mut body = json!({ "users": users, "opens_per_user": opens_per_user });
+    if !pack_type_ids.is_empty() {
+        body["pack_type_ids"] = json!(pack_type_ids);
+    }
+
+    let report = post_json("/admin/economy/simulate", &body).await?;
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(())
+}
+
+async fn create_api_key(db: &PgPool, name: &str, grants: &[String]) -> CliResult<()> {
+    let (roles, scopes): (Vec<&String>, Vec<&String>) =
+        grants.iter().partition(|grant| API_KEY_ROLES.contains(&grant.as_str()));
+    for scope in &scopes {
+        let known = API_KEY_SCOPES.contains(&scope.as_str())
+            || scope
+                .strip_suffix(":*")
+                .is_some_and(|area| API_KEY_SCOPES.iter().any(|s| s.split(':').next() == Some(area)));
+        if !known {
+            return Err(format!("unknown role or scope '{}'\n\n{}", scope, USAGE).into());
+        }
+    }
+
+    let mut secret = [0u8; 32];
+    std::fs::File::open("/dev/urandom")?.read_exact(&mut secret)?;
+    let key = format!("lpk_{}", hex::encode(secret));
+    let key_hash = hex::encode(Sha256::digest(key.as_bytes()));
+
+    let mut tx = db.begin().await?;
+    let id: String = sqlx::query_scalar(
+        "INSERT INTO api_keys (name, key_prefix, key_hash, roles, scopes, created_by) VALUES ($1, $2, $3, $4, $5, $6) RETURNING id::text",
+    )
+    .bind(name)
+    .bind(&key[..12])
+    .bind(&key_hash)
+    .bind(&roles)
+    .bind(&scopes)
+    .bind(ACTOR)
+    .fetch_one(&mut *tx)
+    .await?;
+    audit(&mut tx, "api_key.created", &id, json!({ "name": name, "roles": roles, "scopes": scopes })).await?;
+    tx.commit().await?;
+
+    println!("✅ Created API key {} ({}) with {}", name, id, grants.join(" "));
+    println!("{}", key);
+    println!("Store it now; it can't be shown again.");
+    Ok(())
+}
+
+async fn list_api_keys(db: &PgPool) -> CliResult<()> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id::text, name, key_prefix, roles || scopes AS grants,
+               COALESCE(to_char(last_used_at, 'YYYY-MM-DD HH24:MI'), 'never') AS last_used,
+               revoked_at IS NOT NULL AS revoked
+        FROM api_keys
+        ORDER BY created_at
+        "#,
+    )
+    .fetch_all(db)
+    .await?;
+
+    for row in rows {
+        let grants: Vec<String> = row.try_get("grants")?;
+        let revoked: bool = row.try_get("revoked")?;
+        println!(
+            "{}  {}…  {:<24} {:<40} last used {}{}",
+            row.try_get::<String, _>("id")?,
+            row.try_get::<String, _>("key_prefix")?,
+            row.try_get::<String, _>("name")?,
+            grants.join(" "),
+            row.try_get::<String, _>("last_used")?,
+            if revoked { "  (revoked)" } else { "" }
+        );
+    }
+    Ok(())
+}
+
+async fn revoke_api_key(db: &PgPool, id: &str) -> CliResult<()> {
+    let mut tx = db.begin().await?;
+    let revoked = sqlx::query("UPDATE api_keys SET revoked_at = NOW() WHERE id = $1::uuid AND revoked_at IS NULL")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    if revoked.rows_affected() == 0 {
+        return Err("no active API key with this id".into());
+    }
+
+    audit(&mut tx, "api_key.revoked", id, json!({})).await?;
+    tx.commit().await?;
+    println!("✅ Revoked API key {}; instances stop accepting it within a minute", id);
+    Ok(())
+}
+
+async fn audit(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    action: &str,
+    subject: &str,
+    details: Value,
+) -> CliResult<()> {
+    sqlx::query("INSERT INTO audit_log (actor, action, subject, details) VALUES ($1, $2, $3, $4)")
+        .bind(ACTOR)
+        .bind(action)
+        .bind(subject)
+        .bind(details)
+        .execute(&mut **tx)
+        .await?;
+    Ok(())
+}
+
+/// Minimal HTTP/1.1 POST to the service; the admin API is plain HTTP inside the cluster
+async fn post_json(path: &str, body: &Value) -> CliResult<Value> {
+    let base = std::env::var("LOOTPACKS_URL").unwrap_or_else(|_| "http://localhost:3005".to_string());
+    let host = base
+        .strip_prefix("http://")
+        .ok_or("LOOTPACKS_URL must be an http:// URL")?
+        .trim_end_matches('/');
+
+    let api_key = std::env::var("LOOTPACKS_API_KEY").map_err(|_| "LOOTPACKS_API_KEY is not set")?;
+
+    let payload = body.to_string();
+    let request = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nX-Api-Key: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        path,
+        host,
+        api_key,
+        payload.len(),
+        payload
+    );
+
+    let mut stream = TcpStream::connect(host).await?;
+    stream.write_all(request.as_bytes()).await?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await?;
+
+    let (head, body) = response.split_once("\r\n\r\n").ok_or("malformed response from service")?;
+    let status = head.split_whitespace().nth(1).unwrap_or("");
+    if !status.starts_with('2') {
+        return Err(format!("service returned {}: {}", status, body).into());
+    }
+    Ok(serde_json::from_str(body)?)
+}
@@ -0,0 +1,234 @@
+use crate::currency;
+use crate::error::{AppError, Result};
+use crate::http_client::HttpServiceClient;
+use bigdecimal::BigDecimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::time::Duration;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// How long a claim waits on a payout submission whose outcome was lost before the user
+/// can claim again, which replays it under the same idempotency key
+const PAYOUT_SUBMISSION_LEASE_SECONDS: i64 = 300;
+
+#[derive(Debug, Serialize)]
+pub struct CashbackClaimResponse {
+    pub reward_id: Uuid,
+    pub payout_status: String,
+    pub payout_amount: BigDecimal,
+    pub payout_reference: String,
+}
+
+/// Callback body sent by the payments service when a payout settles
+#[derive(Debug, Deserialize)]
+pub struct PayoutWebhook {
+    pub payout_reference: String,
+    pub status: String, // paid | failed
+    pub failure_reason: Option<String>,
+}
+
+#[derive(Serialize)]
+struct PayoutRequest<'a> {
+    user_id: &'a str,
+    amount: String,
+    currency: &'a str,
+    idempotency_key: String,
+}
+
+#[derive(Deserialize)]
+struct PayoutAccepted {
+    payout_reference: String,
+}
+
+/// What the payments service said about a payout. A failed request is an `Err` instead:
+/// the payout may or may not have been made.
+enum PayoutSubmission {
+    Accepted(String),
+    Rejected(reqwest::StatusCode),
+}
+
+/// Thin client for the wallet/payments service payout API
+pub struct PaymentsClient {
+    http: HttpServiceClient,
+}
+
+impl PaymentsClient {
+    pub fn new(base_url: String, timeout: Duration) -> Result<Self> {
+        Ok(Self { http: HttpServiceClient::new("payments", base_url, timeout)? })
+    }
+
+    async fn submit_payout(
+        &self,
+        user_id: &str,
+        amount: &BigDecimal,
+        currency: &str,
+        idempotency_key: Uuid,
+    ) -> Result<PayoutSubmission> {
+        let request = self.http.post("/payouts").json(&PayoutRequest {
+            user_id,
+            amount: amount.to_string(),
+            currency,
+            idempotency_key: format!("cashback-{}", idempotency_key),
+        });
+        let response = self.http.send(request).await?;
+
+        if !response.status().is_success() {
+            return Ok(PayoutSubmission::Rejected(response.status()));
+        }
+
+        let accepted: PayoutAccepted = response
+            .json()
+            .await
+            .map_err(|e| AppError::InternalError(format!("Invalid payout response: {}", e)))?;
+
+        Ok(PayoutSubmission::Accepted(accepted.payout_reference))
+    }
+}
+
+pub struct CashbackService {
+    db: PgPool,
+    payments: PaymentsClient,
+}
+
+impl CashbackService {
+    pub fn new(db: PgPool, payments: PaymentsClient) -> Self {
+        Self { db, payments }
+    }
+
+    /// Claim a cashback reward by submitting a payout to the payments service. The claim is
+    /// committed before the payout is submitted, so double-taps find it in progress and no
+    /// row lock is held across the call. A failed payout can be claimed again under a new
+    /// idempotency key; one whose outcome was lost is replayed under the same key.
+    pub async fn claim_cashback(&self, user_id: &str, reward_id: Uuid) -> Result<CashbackClaimResponse> {
+        let mut tx = self.db.begin().await?;
+
+        let reward = sqlx::query!(
+            r#"
+            SELECT id, type, value, value_currency, is_used, expires_at, payout_status, payout_updated_at
+            FROM user_rewards
+            WHERE id = $1 AND user_id = $2
+            FOR UPDATE
+            "#,
+            reward_id,
+            user_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Reward not found".to_string()))?;
+
+        if reward.r#type != "cashback" {
+            return Err(AppError::BadRequest("Reward is not a cashback reward".to_string()));
+        }
+        if reward.is_used.unwrap_or(false) || matches!(reward.payout_status.as_deref(), Some("submitted" | "paid")) {
+            return Err(AppError::BadRequest("Cashback already claimed".to_string()));
+        }
+        let lease_cutoff = chrono::Utc::now() - chrono::Duration::seconds(PAYOUT_SUBMISSION_LEASE_SECONDS);
+        if reward.payout_status.as_deref() == Some("submitting")
+            && reward.payout_updated_at.is_some_and(|at| at > lease_cutoff)
+        {
+            return Err(AppError::BadRequest("Cashback payout is already in progress".to_string()));
+        }
+        if reward.expires_at.map(|exp| exp <= chrono::Utc::now()).unwrap_or(false) {
+            return Err(AppError::BadRequest("Reward has expired".to_string()));
+        }
+
+        let amount = currency::parse_amount(&reward.value)
+            .ok_or_else(|| AppError::InternalError(format!("Unparseable cashback value '{}'", reward.value)))?;
+        let currency = reward.value_currency.unwrap_or_else(|| currency::BASE_CURRENCY.to_string());
+
+        let idempotency_key = sqlx::query_scalar!(
+            r#"
+            UPDATE user_rewards
+            SET payout_status = 'submitting', payout_amount = $2,
+                payout_idempotency_key = COALESCE(payout_idempotency_key, gen_random_uuid()),
+                payout_updated_at = NOW()
+            WHERE id = $1
+            RETURNING payout_idempotency_key AS "payout_idempotency_key!"
+            "#,
+            reward_id,
+            amount
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        let payout_reference = match self.payments.submit_payout(user_id, &amount, &currency, idempotency_key).await? {
+            PayoutSubmission::Accepted(reference) => reference,
+            PayoutSubmission::Rejected(status) => {
+                sqlx::query!(
+                    r#"
+                    UPDATE user_rewards
+                    SET payout_status = 'failed', payout_idempotency_key = NULL, payout_updated_at = NOW()
+                    WHERE id = $1 AND payout_status = 'submitting' AND payout_idempotency_key = $2
+                    "#,
+                    reward_id,
+                    idempotency_key
+                )
+                .execute(&self.db)
+                .await?;
+                return Err(AppError::InternalError(format!("Payments service returned {} for payout", status)));
+            }
+        };
+
+        sqlx::query!(
+            r#"
+            UPDATE user_rewards
+            SET payout_status = 'submitted', payout_reference = $3, payout_updated_at = NOW()
+            WHERE id = $1 AND payout_status = 'submitting' AND payout_idempotency_key = $2
+            "#,
+            reward_id,
+            idempotency_key,
+            payout_reference
+        )
+        .execute(&self.db)
+        .await?;
+
+        info!("Submitted cashback payout {} for reward {}", payout_reference, reward_id);
+
+        Ok(CashbackClaimResponse {
+            reward_id,
+            payout_status: "submitted".to_string(),
+            payout_amount: amount,
+            payout_reference,
+        })
+    }
+
+    /// Apply a payout completion or failure from the payments service. Idempotent.
+    pub async fn handle_payout_webhook(&self, webhook: PayoutWebhook) -> Result<()> {
+        let updated = match webhook.status.as_str() {
+            "paid" => sqlx::query!(
+                r#"
+                UPDATE user_rewards
+                SET payout_status = 'paid', is_used = true, used_at = NOW(), payout_updated_at = NOW()
+                WHERE payout_reference = $1 AND payout_status IN ('submitted', 'paid')
+                "#,
+                webhook.payout_reference
+            )
+            .execute(&self.db)
+            .await?,
+            "failed" => {
+                warn!("Cashback payout {} failed: {}", webhook.payout_reference,
+                      webhook.failure_reason.as_deref().unwrap_or("unknown"));
+                sqlx::query!(
+                    r#"
+                    UPDATE user_rewards
+                    SET payout_status = 'failed', payout_idempotency_key = NULL, payout_updated_at = NOW()
+                    WHERE payout_reference = $1 AND payout_status IN ('submitted', 'failed')
+                    "#,
+                    webhook.payout_reference
+                )
+                .execute(&self.db)
+                .await?
+            }
+            other => {
+                return Err(AppError::BadRequest(format!("Unknown payout status '{}'", other)));
+            }
+        };
+
+        if updated.rows_affected() == 0 {
+            return Err(AppError::NotFound("Payout not found".to_string()));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,241 @@
+use crate::audit;
+use crate::error::{AppError, Result};
+use crate::inbox::{self, NewMessage};
+use crate::privacy;
+use crate::wallet::{CoinWallet, WalletEntry};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{PgExecutor, PgPool};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+const ACTOR: &str = "system:chargebacks";
+/// Open chargebacks listed for review
+const OPEN_CHARGEBACKS_LISTED: i64 = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DisputeStatus {
+    /// The buyer disputed the top-up
+    Opened,
+    /// The dispute was decided for us; the payment stands
+    Won,
+    /// The dispute was decided for the buyer; the money went back
+    Lost,
+}
+
+/// A dispute update for a coin top-up, posted by payments to the chargeback webhook
+#[derive(Debug, Deserialize)]
+pub struct ChargebackEvent {
+    pub dispute_id: String,
+    pub payment_id: String,
+    pub user_id: String,
+    /// DealCoins the disputed top-up credited
+    pub coins: i32,
+    pub status: DisputeStatus,
+    pub occurred_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChargebackResult {
+    pub dispute_id: String,
+    pub status: String,
+    pub clawed_back_coins: i32,
+    /// Balance after this update moved coins, if it did
+    pub balance: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OpenChargeback {
+    pub dispute_id: String,
+    pub payment_id: String,
+    pub user_id: String,
+    pub coins: i32,
+    pub clawed_back_coins: i32,
+    pub opened_at: DateTime<Utc>,
+}
+
+/// Reject paid opens and outgoing coin transfers while the user has an open payment
+/// dispute. Every paid flow calls this next to `self_exclusion::enforce`.
+pub async fn enforce<'e, E: PgExecutor<'e>>(executor: E, user_id: &str) -> Result<()> {
+    let disputed = sqlx::query_scalar!(
+        r#"SELECT EXISTS (SELECT 1 FROM chargebacks WHERE user_id = $1 AND status = 'open') AS "disputed!""#,
+        user_id
+    )
+    .fetch_one(executor)
+    .await?;
+
+    if disputed {
+        return Err(AppError::BadRequest(
+            "Pack purchases and coin transfers are paused on your account while a payment dispute is open".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+pub struct ChargebackService {
+    db: PgPool,
+    wallet: Arc<dyn CoinWallet>,
+}
+
+impl ChargebackService {
+    pub fn new(db: PgPool, wallet: Arc<dyn CoinWallet>) -> Self {
+        Self { db, wallet }
+    }
+
+    /// Apply a dispute update. The first update for a dispute claws the top-up's coins
+    /// back, even into a negative balance, and flags the user; winning the dispute credits
+    /// them again. Updates for disputes already resolved are ignored, so redeliveries and
+    /// out-of-order events are safe.
+    pub async fn handle(&self, event: ChargebackEvent) -> Result<ChargebackResult> {
+        if event.coins <= 0 {
+            return Err(AppError::BadRequest("coins must be positive".to_string()));
+        }
+        let mut tx = self.db.begin().await?;
+        let mut wallet_entries: Vec<WalletEntry> = Vec::new();
+
+        let opened = sqlx::query_scalar!(
+            r#"
+            INSERT INTO chargebacks (dispute_id, payment_id, user_id, coins, opened_at)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (dispute_id) DO NOTHING
+            RETURNING id
+            "#,
+            event.dispute_id,
+            event.payment_id,
+            event.user_id,
+            event.coins,
+            event.occurred_at
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if opened.is_some() {
+            let entry = self
+                .wallet
+                .claw_back(&mut tx, &event.user_id, event.coins, "chargeback", Some(&event.dispute_id))
+                .await?;
+            wallet_entries.push(entry);
+            sqlx::query!(
+                "UPDATE chargebacks SET clawed_back_coins = $2 WHERE dispute_id = $1",
+                event.dispute_id,
+                event.coins
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            inbox::send(&mut *tx, &event.user_id, NewMessage {
+                kind: "system",
+                title: "Pack purchases paused",
+                body: &format!(
+                    "A payment for {} DealCoins was disputed, so those coins were taken back. \
+                     Pack purchases are paused until the dispute is resolved.",
+                    event.coins
+                ),
+                claim_pack_type_id: None,
+                expires_at: None,
+            })
+            .await?;
+            audit::record(&mut *tx, ACTOR, "user.chargeback_flagged", Some(&event.user_id), json!({
+                "dispute_id": event.dispute_id,
+                "payment_id": event.payment_id,
+                "coins": event.coins,
+            }))
+            .await?;
+        }
+
+        let chargeback = sqlx::query!(
+            "SELECT user_id, status, clawed_back_coins FROM chargebacks WHERE dispute_id = $1 FOR UPDATE",
+            event.dispute_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if chargeback.user_id != event.user_id {
+            return Err(AppError::BadRequest("Dispute belongs to a different user".to_string()));
+        }
+
+        let status = match (chargeback.status.as_str(), event.status) {
+            ("open", DisputeStatus::Won) => {
+                let entry = self
+                    .wallet
+                    .apply(&mut tx, &event.user_id, chargeback.clawed_back_coins, "chargeback_reversed", Some(&event.dispute_id))
+                    .await?;
+                wallet_entries.push(entry);
+                self.resolve(&mut tx, &event, "won").await?;
+                "won"
+            }
+            ("open", DisputeStatus::Lost) => {
+                self.resolve(&mut tx, &event, "lost").await?;
+                "lost"
+            }
+            ("open", DisputeStatus::Opened) => "open",
+            (resolved, _) => {
+                warn!("Ignoring {:?} for dispute {}, already {}", event.status, event.dispute_id, resolved);
+                tx.rollback().await?;
+                return Ok(ChargebackResult {
+                    dispute_id: event.dispute_id,
+                    status: resolved.to_string(),
+                    clawed_back_coins: chargeback.clawed_back_coins,
+                    balance: None,
+                });
+            }
+        };
+
+        if let Err(e) = tx.commit().await {
+            self.wallet.compensate(&wallet_entries).await;
+            return Err(e.into());
+        }
+        self.wallet.confirm(&wallet_entries).await;
+
+        info!("Dispute {} for user {} is {}", event.dispute_id, privacy::pseudonym(&event.user_id), status);
+        Ok(ChargebackResult {
+            dispute_id: event.dispute_id,
+            status: status.to_string(),
+            clawed_back_coins: chargeback.clawed_back_coins,
+            balance: wallet_entries.last().map(|entry| entry.balance),
+        })
+    }
+
+    async fn resolve(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        event: &ChargebackEvent,
+        status: &str,
+    ) -> Result<()> {
+        sqlx::query!(
+            "UPDATE chargebacks SET status = $2, resolved_at = $3 WHERE dispute_id = $1",
+            event.dispute_id,
+            status,
+            event.occurred_at
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        audit::record(&mut **tx, ACTOR, "user.chargeback_resolved", Some(&event.user_id), json!({
+            "dispute_id": event.dispute_id,
+            "status": status,
+        }))
+        .await?;
+        Ok(())
+    }
+
+    /// Disputes still open, oldest first
+    pub async fn list_open(&self) -> Result<Vec<OpenChargeback>> {
+        let open = sqlx::query_as!(
+            OpenChargeback,
+            r#"
+            SELECT dispute_id, payment_id, user_id, coins, clawed_back_coins, opened_at
+            FROM chargebacks
+            WHERE status = 'open'
+            ORDER BY opened_at
+            LIMIT $1
+            "#,
+            OPEN_CHARGEBACKS_LISTED
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(open)
+    }
+}
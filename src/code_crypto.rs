@@ -0,0 +1,312 @@
+use crate::error::{AppError, Result};
+use crate::models::lootpacks::UserReward;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// Stored codes look like `enc:<key id>:<base64 of nonce || ciphertext>`; anything else
+/// is a plaintext code written before encryption was turned on
+const SEALED_PREFIX: &str = "enc:";
+const NONCE_BYTES: usize = 12;
+const KEY_BYTES: usize = 32;
+/// Rows re-encrypted per transaction by the rotation worker
+const REWRITE_BATCH_SIZE: i64 = 500;
+const WORKER_INTERVAL_SECONDS: u64 = 60;
+
+/// Encrypts reward codes before they reach `user_rewards.code` and decrypts them on read.
+///
+/// Keys come from `CODE_ENCRYPTION_KEYS`, or from the file named by
+/// `CODE_ENCRYPTION_KEYS_FILE` where KMS mounts its secrets, as comma or newline separated
+/// `<key id>:<base64 32-byte key>` pairs. The first key encrypts; the rest only decrypt,
+/// so a rotation prepends the new key and leaves the old ones in place until
+/// `CodeEncryptionService` has rewritten every row.
+///
+/// Ciphertexts are randomized, so lookups and uniqueness go through `code_hash`, an HMAC
+/// of the code under `CODE_INDEX_KEY`. That key cannot rotate without recomputing every hash.
+pub struct CodeCipher {
+    active: Option<(String, Aes256Gcm)>,
+    keys: HashMap<String, Aes256Gcm>,
+    index_key: Vec<u8>,
+}
+
+impl CodeCipher {
+    /// Without `CODE_ENCRYPTION_KEYS` or `CODE_ENCRYPTION_KEYS_FILE`, codes are stored in
+    /// plaintext (local development only)
+    pub fn from_env() -> Result<Arc<Self>> {
+        let keys = match (std::env::var("CODE_ENCRYPTION_KEYS"), std::env::var("CODE_ENCRYPTION_KEYS_FILE")) {
+            (Ok(keys), _) => keys,
+            (Err(_), Ok(path)) => std::fs::read_to_string(&path).map_err(|e| {
+                AppError::InternalError(format!("Failed to read code keys from {}: {}", path, e))
+            })?,
+            (Err(_), Err(_)) => {
+                warn!("CODE_ENCRYPTION_KEYS is unset; reward codes are stored in plaintext");
+                return Ok(Arc::new(Self::plaintext()));
+            }
+        };
+        let index_key = std::env::var("CODE_INDEX_KEY")
+            .map_err(|_| AppError::InternalError("CODE_INDEX_KEY is required with code encryption".to_string()))?;
+        let index_key = BASE64
+            .decode(index_key.trim())
+            .map_err(|e| AppError::InternalError(format!("Invalid CODE_INDEX_KEY: {}", e)))?;
+
+        Ok(Arc::new(Self::new(&keys, index_key)?))
+    }
+
+    /// Parse `<key id>:<base64 key>` pairs; the first one encrypts
+    pub fn new(keys: &str, index_key: Vec<u8>) -> Result<Self> {
+        if index_key.len() < KEY_BYTES {
+            return Err(AppError::InternalError(format!("CODE_INDEX_KEY must be at least {} bytes", KEY_BYTES)));
+        }
+
+        let mut active = None;
+        let mut ciphers = HashMap::new();
+        for entry in keys.split([',', '\n']).map(str::trim).filter(|e| !e.is_empty()) {
+            let Some((id, key)) = entry.split_once(':') else {
+                return Err(AppError::InternalError("Code keys must be <key id>:<base64 key>".to_string()));
+            };
+            let key = BASE64
+                .decode(key)
+                .map_err(|e| AppError::InternalError(format!("Invalid code key {}: {}", id, e)))?;
+            if id.is_empty() || key.len() != KEY_BYTES {
+                return Err(AppError::InternalError(format!("Code key {:?} must be {} bytes", id, KEY_BYTES)));
+            }
+            let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+            if active.is_none() {
+                active = Some((id.to_string(), cipher.clone()));
+            }
+            if ciphers.insert(id.to_string(), cipher).is_some() {
+                return Err(AppError::InternalError(format!("Code key {} is listed twice", id)));
+            }
+        }
+        if active.is_none() {
+            return Err(AppError::InternalError("No code encryption keys configured".to_string()));
+        }
+
+        Ok(Self { active, keys: ciphers, index_key })
+    }
+
+    /// Stores codes as they are; the default for services built without a cipher
+    pub fn plaintext() -> Self {
+        Self { active: None, keys: HashMap::new(), index_key: Vec::new() }
+    }
+
+    /// Id of the key new codes are encrypted with; `None` when codes are stored in plaintext
+    pub fn active_key_id(&self) -> Option<&str> {
+        self.active.as_ref().map(|(id, _)| id.as_str())
+    }
+
+    /// The value to store for a code
+    pub fn seal(&self, code: &str) -> Result<String> {
+        let Some((key_id, cipher)) = &self.active else {
+            return Ok(code.to_string());
+        };
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, code.as_bytes())
+            .map_err(|_| AppError::InternalError("Failed to encrypt reward code".to_string()))?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        Ok(format!("{}{}:{}", SEALED_PREFIX, key_id, BASE64.encode(sealed)))
+    }
+
+    pub fn seal_opt(&self, code: Option<&str>) -> Result<Option<String>> {
+        code.map(|code| self.seal(code)).transpose()
+    }
+
+    /// The code behind a stored value. Plaintext values from before encryption or the
+    /// backfill are returned as they are.
+    pub fn reveal(&self, stored: &str) -> Result<String> {
+        let Some(sealed) = stored.strip_prefix(SEALED_PREFIX) else {
+            return Ok(stored.to_string());
+        };
+        let (key_id, payload) = sealed
+            .split_once(':')
+            .ok_or_else(|| AppError::InternalError("Malformed encrypted reward code".to_string()))?;
+        let cipher = self.keys.get(key_id).ok_or_else(|| {
+            error!("Reward code encrypted with unknown key {}", key_id);
+            AppError::InternalError("Reward code key is not available".to_string())
+        })?;
+        let payload = BASE64
+            .decode(payload)
+            .map_err(|_| AppError::InternalError("Malformed encrypted reward code".to_string()))?;
+        if payload.len() <= NONCE_BYTES {
+            return Err(AppError::InternalError("Malformed encrypted reward code".to_string()));
+        }
+
+        let (nonce, ciphertext) = payload.split_at(NONCE_BYTES);
+        let code = cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| AppError::InternalError(format!("Failed to decrypt reward code with key {}", key_id)))?;
+        String::from_utf8(code).map_err(|_| AppError::InternalError("Decrypted reward code is not UTF-8".to_string()))
+    }
+
+    pub fn reveal_opt(&self, stored: Option<String>) -> Result<Option<String>> {
+        stored.map(|stored| self.reveal(&stored)).transpose()
+    }
+
+    /// Decrypt the codes of rewards read straight from `user_rewards`
+    pub fn reveal_rewards(&self, rewards: &mut [UserReward]) -> Result<()> {
+        for reward in rewards {
+            reward.code = self.reveal_opt(reward.code.take())?;
+        }
+        Ok(())
+    }
+
+    /// Deterministic lookup key for a code, stored in `user_rewards.code_hash`
+    pub fn blind_index(&self, code: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.index_key).expect("HMAC accepts any key length");
+        mac.update(code.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    pub fn blind_index_opt(&self, code: Option<&str>) -> Option<String> {
+        code.map(|code| self.blind_index(code))
+    }
+
+    /// Whether a stored value still has to be rewritten under the active key
+    fn needs_rewrite(&self, stored: &str) -> bool {
+        match self.active_key_id() {
+            Some(key_id) => !stored
+                .strip_prefix(SEALED_PREFIX)
+                .is_some_and(|sealed| sealed.starts_with(&format!("{}:", key_id))),
+            None => false,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CodeEncryptionStatus {
+    pub active_key_id: Option<String>,
+    /// Codes under each key; plaintext codes are counted under `plaintext`
+    pub codes_by_key: HashMap<String, i64>,
+    pub pending_rewrite: i64,
+}
+
+/// Backfills plaintext codes and moves codes under retired keys to the active key
+pub struct CodeEncryptionService {
+    db: PgPool,
+    cipher: Arc<CodeCipher>,
+}
+
+impl CodeEncryptionService {
+    pub fn new(db: PgPool, cipher: Arc<CodeCipher>) -> Self {
+        Self { db, cipher }
+    }
+
+    /// Rewrite one batch of codes that are plaintext or under a retired key, and fill in
+    /// their `code_hash`. Returns how many rows changed; 0 once everything is current.
+    pub async fn rewrite_batch(&self) -> Result<usize> {
+        let Some(active_key_id) = self.cipher.active_key_id() else {
+            return Ok(0);
+        };
+        let mut tx = self.db.begin().await?;
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT id, code AS "code!"
+            FROM user_rewards
+            WHERE code IS NOT NULL AND (code_hash IS NULL OR code NOT LIKE $1)
+            ORDER BY id
+            LIMIT $2
+            FOR UPDATE SKIP LOCKED
+            "#,
+            format!("{}{}:%", SEALED_PREFIX, active_key_id),
+            REWRITE_BATCH_SIZE
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let mut ids: Vec<Uuid> = Vec::with_capacity(rows.len());
+        let mut codes = Vec::with_capacity(rows.len());
+        let mut hashes = Vec::with_capacity(rows.len());
+        for row in &rows {
+            let code = self.cipher.reveal(&row.code)?;
+            ids.push(row.id);
+            hashes.push(self.cipher.blind_index(&code));
+            codes.push(if self.cipher.needs_rewrite(&row.code) { self.cipher.seal(&code)? } else { row.code.clone() });
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE user_rewards ur
+            SET code = rewritten.code, code_hash = rewritten.code_hash
+            FROM UNNEST($1::uuid[], $2::text[], $3::text[]) AS rewritten (id, code, code_hash)
+            WHERE ur.id = rewritten.id
+            "#,
+            &ids,
+            &codes,
+            &hashes
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(rows.len())
+    }
+
+    /// How many codes sit under each key, for watching a backfill or rotation finish
+    pub async fn status(&self) -> Result<CodeEncryptionStatus> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT CASE WHEN code LIKE 'enc:%' THEN SPLIT_PART(code, ':', 2) ELSE 'plaintext' END AS "key_id!",
+                   COUNT(*) AS "codes!",
+                   COUNT(*) FILTER (WHERE code_hash IS NULL) AS "unhashed!"
+            FROM user_rewards
+            WHERE code IS NOT NULL
+            GROUP BY 1
+            "#
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        let active_key_id = self.cipher.active_key_id().map(str::to_string);
+        let pending_rewrite = rows
+            .iter()
+            .map(|row| match active_key_id.as_deref() {
+                Some(active) if row.key_id != active => row.codes,
+                Some(_) => row.unhashed,
+                None => 0,
+            })
+            .sum();
+
+        Ok(CodeEncryptionStatus {
+            active_key_id,
+            codes_by_key: rows.into_iter().map(|row| (row.key_id, row.codes)).collect(),
+            pending_rewrite,
+        })
+    }
+
+    /// Drain pending rewrites every minute, a batch at a time
+    pub fn spawn_worker(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(WORKER_INTERVAL_SECONDS));
+            loop {
+                interval.tick().await;
+                let mut rewritten = 0;
+                loop {
+                    match self.rewrite_batch().await {
+                        Ok(0) => break,
+                        Ok(count) => rewritten += count,
+                        Err(e) => {
+                            error!("Reward code rewrite failed: {:?}", e);
+                            break;
+                        }
+                    }
+                }
+                if rewritten > 0 {
+                    info!("Re-encrypted {} reward codes", rewritten);
+                }
+            }
+        })
+    }
+}
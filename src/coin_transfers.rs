@@ -0,0 +1,361 @@
+use crate::error::{AppError, Result};
+use crate::{audit, chargebacks, self_exclusion};
+use crate::wallet::{self, CoinWallet};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::{PgConnection, PgPool};
+use std::sync::Arc;
+use uuid::Uuid;
+
+const MAX_NOTE_LENGTH: usize = 140;
+/// Distinct counterparties in 24 hours before a user's transfers get flagged
+const FAN_THRESHOLD: i64 = 5;
+/// Share of the sender's balance, in percent, that counts as emptying the account
+const DRAIN_PERCENT: i32 = 90;
+/// Transfers below this aren't worth a drain flag
+const DRAIN_MIN_AMOUNT: i32 = 100;
+const FLAGS_LISTED: i64 = 200;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TransferSettings {
+    pub enabled: bool,
+    pub daily_send_cap: i32,
+    pub daily_receive_cap: i32,
+    pub min_account_age_days: i32,
+    pub min_level: i32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TransferRequest {
+    pub recipient_id: String,
+    pub amount: i32,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TransferResponse {
+    pub transfer_id: Uuid,
+    pub recipient_id: String,
+    pub amount: i32,
+    pub new_balance: i32,
+    /// What the sender can still send in the current 24 hours
+    pub send_remaining_today: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TransferFlag {
+    pub id: Uuid,
+    pub transfer_id: Uuid,
+    pub sender_id: String,
+    pub recipient_id: String,
+    pub amount: i32,
+    pub reason: String,
+    pub details: Value,
+    pub created_at: DateTime<Utc>,
+}
+
+pub struct CoinTransferService {
+    db: PgPool,
+    wallet: Arc<dyn CoinWallet>,
+}
+
+impl CoinTransferService {
+    pub fn new(db: PgPool, wallet: Arc<dyn CoinWallet>) -> Self {
+        Self { db, wallet }
+    }
+
+    /// Move DealCoins from `sender_id` to the recipient. Both balances change through the
+    /// wallet in one transaction; patterns worth a second look are flagged, not blocked.
+    pub async fn transfer(&self, sender_id: &str, req: TransferRequest) -> Result<TransferResponse> {
+        let recipient_id = req.recipient_id.trim();
+        if recipient_id.is_empty() || recipient_id == sender_id {
+            return Err(AppError::BadRequest("Choose someone else to send DealCoins to".to_string()));
+        }
+        if req.amount <= 0 {
+            return Err(AppError::BadRequest("amount must be positive".to_string()));
+        }
+        let note = req.note.as_deref().map(str::trim).filter(|n| !n.is_empty());
+        if note.is_some_and(|n| n.chars().count() > MAX_NOTE_LENGTH) {
+            return Err(AppError::BadRequest(format!("Notes are at most {} characters", MAX_NOTE_LENGTH)));
+        }
+
+        let mut tx = self.db.begin().await?;
+        let settings = Self::load_settings(&mut tx).await?;
+        if !settings.enabled {
+            return Err(AppError::BadRequest("Coin transfers are currently turned off".to_string()));
+        }
+        if self_exclusion::active(&mut *tx, sender_id).await?.is_some() {
+            return Err(AppError::BadRequest("Coin transfers are paused on your account during a break".to_string()));
+        }
+
+        // Both rows in user id order, so opposite transfers between two users can't deadlock
+        let accounts = sqlx::query!(
+            r#"
+            SELECT user_id, COALESCE(deal_coins, 0) AS "deal_coins!", COALESCE(level, 1) AS "level!",
+                   COALESCE(created_at, NOW()) AS "created_at!"
+            FROM user_lootpack_stats
+            WHERE user_id = ANY($1)
+            ORDER BY user_id
+            FOR UPDATE
+            "#,
+            &[sender_id.to_string(), recipient_id.to_string()][..]
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        let sender = accounts
+            .iter()
+            .find(|a| a.user_id == sender_id)
+            .ok_or_else(|| AppError::BadRequest("Insufficient DealCoins".to_string()))?;
+        let recipient = accounts
+            .iter()
+            .find(|a| a.user_id == recipient_id)
+            .ok_or_else(|| AppError::NotFound("Recipient not found".to_string()))?;
+
+        let min_created_at = Utc::now() - Duration::days(settings.min_account_age_days as i64);
+        for (account, whose) in [(sender, "Your"), (recipient, "The recipient's")] {
+            if account.created_at > min_created_at {
+                return Err(AppError::BadRequest(format!(
+                    "{} account must be at least {} days old to transfer DealCoins",
+                    whose, settings.min_account_age_days
+                )));
+            }
+            if account.level < settings.min_level {
+                return Err(AppError::BadRequest(format!(
+                    "{} account must reach level {} to transfer DealCoins",
+                    whose, settings.min_level
+                )));
+            }
+        }
+        let sender_balance = sender.deal_coins;
+        // Coins from a disputed purchase mustn't be moved out of reach of the clawback
+        chargebacks::enforce(&mut *tx, sender_id).await?;
+
+        let sent_today = Self::sent_today(&mut tx, sender_id).await?;
+        if sent_today + req.amount as i64 > settings.daily_send_cap as i64 {
+            return Err(AppError::BadRequest(format!(
+                "You can send {} more DealCoins today (daily cap {})",
+                (settings.daily_send_cap as i64 - sent_today).max(0),
+                settings.daily_send_cap
+            )));
+        }
+        let received_today = sqlx::query_scalar!(
+            r#"
+            SELECT COALESCE(SUM(amount), 0)::BIGINT AS "total!" FROM coin_transfers
+            WHERE recipient_id = $1 AND created_at > NOW() - INTERVAL '24 hours'
+            "#,
+            recipient_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if received_today + req.amount as i64 > settings.daily_receive_cap as i64 {
+            return Err(AppError::BadRequest(
+                "The recipient can't receive that many more DealCoins today".to_string(),
+            ));
+        }
+
+        let transfer_id = sqlx::query_scalar!(
+            "INSERT INTO coin_transfers (sender_id, recipient_id, amount, note) VALUES ($1, $2, $3, $4) RETURNING id",
+            sender_id,
+            recipient_id,
+            req.amount,
+            note
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        let reference = transfer_id.to_string();
+        let debit = self.wallet.apply(&mut tx, sender_id, -req.amount, "transfer_out", Some(&reference)).await?;
+        let credit = self.wallet.apply(&mut tx, recipient_id, req.amount, "transfer_in", Some(&reference)).await?;
+        let new_balance = debit.balance;
+
+        let flags = Self::flag_patterns(&mut tx, transfer_id, sender_id, recipient_id, req.amount, sender_balance)
+            .await?;
+
+        audit::record(&mut *tx, sender_id, "coins.transferred", Some(&reference), json!({
+            "recipient_id": recipient_id,
+            "amount": req.amount,
+            "sender_balance_after": new_balance,
+            "flags": flags,
+        }))
+        .await?;
+
+        wallet::commit_with_wallet(self.wallet.as_ref(), tx, &[debit, credit]).await?;
+        Ok(TransferResponse {
+            transfer_id,
+            recipient_id: recipient_id.to_string(),
+            amount: req.amount,
+            new_balance,
+            send_remaining_today: settings.daily_send_cap as i64 - sent_today - req.amount as i64,
+        })
+    }
+
+    /// Flags not yet reviewed, oldest first
+    pub async fn open_flags(&self) -> Result<Vec<TransferFlag>> {
+        let flags = sqlx::query_as!(
+            TransferFlag,
+            r#"
+            SELECT f.id, f.transfer_id, t.sender_id, t.recipient_id, t.amount, f.reason, f.details, f.created_at
+            FROM coin_transfer_flags f
+            JOIN coin_transfers t ON t.id = f.transfer_id
+            WHERE f.reviewed_at IS NULL
+            ORDER BY f.created_at
+            LIMIT $1
+            "#,
+            FLAGS_LISTED
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(flags)
+    }
+
+    pub async fn review_flag(&self, actor: &str, flag_id: Uuid) -> Result<()> {
+        let mut tx = self.db.begin().await?;
+        let reviewed = sqlx::query!(
+            "UPDATE coin_transfer_flags SET reviewed_by = $2, reviewed_at = NOW() WHERE id = $1 AND reviewed_at IS NULL",
+            flag_id,
+            actor
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if reviewed == 0 {
+            return Err(AppError::NotFound("Flag not found or already reviewed".to_string()));
+        }
+
+        audit::record(&mut *tx, actor, "coins.transfer_flag_reviewed", Some(&flag_id.to_string()), json!({})).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn get_settings(&self) -> Result<TransferSettings> {
+        let mut conn = self.db.acquire().await?;
+        Self::load_settings(&mut conn).await
+    }
+
+    pub async fn set_settings(&self, actor: &str, settings: TransferSettings) -> Result<TransferSettings> {
+        if settings.daily_send_cap <= 0 || settings.daily_receive_cap <= 0 {
+            return Err(AppError::BadRequest("Daily caps must be positive".to_string()));
+        }
+        if settings.min_account_age_days < 0 {
+            return Err(AppError::BadRequest("min_account_age_days can't be negative".to_string()));
+        }
+        if settings.min_level < 1 {
+            return Err(AppError::BadRequest("min_level must be at least 1".to_string()));
+        }
+
+        let mut tx = self.db.begin().await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO coin_transfer_settings
+                (id, enabled, daily_send_cap, daily_receive_cap, min_account_age_days, min_level, updated_by)
+            VALUES (true, $1, $2, $3, $4, $5, $6)
+            ON CONFLICT (id) DO UPDATE SET
+                enabled = EXCLUDED.enabled, daily_send_cap = EXCLUDED.daily_send_cap,
+                daily_receive_cap = EXCLUDED.daily_receive_cap, min_account_age_days = EXCLUDED.min_account_age_days,
+                min_level = EXCLUDED.min_level, updated_by = EXCLUDED.updated_by, updated_at = NOW()
+            "#,
+            settings.enabled,
+            settings.daily_send_cap,
+            settings.daily_receive_cap,
+            settings.min_account_age_days,
+            settings.min_level,
+            actor
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        audit::record(&mut *tx, actor, "coins.transfer_settings_updated", None, json!({
+            "enabled": settings.enabled,
+            "daily_send_cap": settings.daily_send_cap,
+            "daily_receive_cap": settings.daily_receive_cap,
+            "min_account_age_days": settings.min_account_age_days,
+            "min_level": settings.min_level,
+        }))
+        .await?;
+
+        tx.commit().await?;
+        Ok(settings)
+    }
+
+    async fn load_settings(conn: &mut PgConnection) -> Result<TransferSettings> {
+        sqlx::query_as!(
+            TransferSettings,
+            r#"
+            SELECT enabled, daily_send_cap, daily_receive_cap, min_account_age_days, min_level
+            FROM coin_transfer_settings
+            "#
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| AppError::InternalError("Coin transfer settings are missing".to_string()))
+    }
+
+    async fn sent_today(conn: &mut PgConnection, sender_id: &str) -> Result<i64> {
+        let sent = sqlx::query_scalar!(
+            r#"
+            SELECT COALESCE(SUM(amount), 0)::BIGINT AS "total!" FROM coin_transfers
+            WHERE sender_id = $1 AND created_at > NOW() - INTERVAL '24 hours'
+            "#,
+            sender_id
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(sent)
+    }
+
+    /// Record a flag for every unusual pattern the new transfer completes. Returns the reasons.
+    async fn flag_patterns(
+        conn: &mut PgConnection,
+        transfer_id: Uuid,
+        sender_id: &str,
+        recipient_id: &str,
+        amount: i32,
+        sender_balance: i32,
+    ) -> Result<Vec<&'static str>> {
+        let pattern = sqlx::query!(
+            r#"
+            SELECT
+                (SELECT COUNT(DISTINCT recipient_id) FROM coin_transfers
+                 WHERE sender_id = $1 AND created_at > NOW() - INTERVAL '24 hours') AS "recipients!",
+                (SELECT COUNT(DISTINCT sender_id) FROM coin_transfers
+                 WHERE recipient_id = $2 AND created_at > NOW() - INTERVAL '24 hours') AS "senders!",
+                EXISTS (SELECT 1 FROM coin_transfers
+                        WHERE sender_id = $2 AND recipient_id = $1
+                          AND created_at > NOW() - INTERVAL '24 hours') AS "round_trip!"
+            "#,
+            sender_id,
+            recipient_id
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        let mut flags = Vec::new();
+        if pattern.recipients >= FAN_THRESHOLD {
+            flags.push(("fan_out", json!({ "distinct_recipients_24h": pattern.recipients })));
+        }
+        if pattern.senders >= FAN_THRESHOLD {
+            flags.push(("fan_in", json!({ "distinct_senders_24h": pattern.senders })));
+        }
+        if pattern.round_trip {
+            flags.push(("round_trip", json!({})));
+        }
+        if amount >= DRAIN_MIN_AMOUNT && amount as i64 * 100 >= sender_balance as i64 * DRAIN_PERCENT as i64 {
+            flags.push(("drains_balance", json!({ "balance_before": sender_balance })));
+        }
+
+        for (reason, details) in &flags {
+            sqlx::query!(
+                "INSERT INTO coin_transfer_flags (transfer_id, reason, details) VALUES ($1, $2, $3)",
+                transfer_id,
+                *reason,
+                details
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        Ok(flags.into_iter().map(|(reason, _)| reason).collect())
+    }
+}
@@ -0,0 +1,147 @@
+use crate::error::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// Add pulled templates to the user's collection; repeats in `template_ids` count once each
+pub async fn record(conn: &mut PgConnection, user_id: &str, template_ids: &[Uuid]) -> Result<()> {
+    if template_ids.is_empty() {
+        return Ok(());
+    }
+
+    sqlx::query!(
+        r#"
+        INSERT INTO user_collection (user_id, template_id, times_obtained)
+        SELECT $1, template_id, COUNT(*)::INT
+        FROM UNNEST($2::UUID[]) AS pulled(template_id)
+        GROUP BY template_id
+        ON CONFLICT (user_id, template_id) DO UPDATE SET
+            times_obtained = user_collection.times_obtained + EXCLUDED.times_obtained,
+            last_obtained_at = NOW()
+        "#,
+        user_id,
+        template_ids
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct CollectionQuery {
+    pub category: Option<String>,
+    pub merchant_id: Option<String>,
+    pub rarity: Option<String>,
+    /// Only collected (true) or only missing (false) templates
+    pub collected: Option<bool>,
+}
+
+/// One template in the book; missing templates are listed so users can see what's left
+#[derive(Debug, Serialize)]
+pub struct CollectionEntry {
+    pub template_id: Uuid,
+    pub title: String,
+    pub r#type: String,
+    pub rarity: String,
+    pub category: Option<String>,
+    pub merchant_id: Option<String>,
+    pub brand: Option<String>,
+    /// Retired templates stay in the book for users who pulled them
+    pub retired: bool,
+    pub collected: bool,
+    pub first_obtained_at: Option<DateTime<Utc>>,
+    pub times_obtained: i32,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Completion {
+    pub collected: i64,
+    pub total: i64,
+    pub percent: f64,
+}
+
+impl Completion {
+    fn add(&mut self, collected: bool) {
+        self.total += 1;
+        if collected {
+            self.collected += 1;
+        }
+        self.percent = (self.collected as f64 * 1000.0 / self.total as f64).round() / 10.0;
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CollectionSummary {
+    pub overall: Completion,
+    pub by_category: BTreeMap<String, Completion>,
+    pub by_merchant: BTreeMap<String, Completion>,
+    pub by_rarity: BTreeMap<String, Completion>,
+}
+
+pub struct CollectionService {
+    db: PgPool,
+}
+
+impl CollectionService {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    /// The book: every active template plus any retired ones the user already owns
+    pub async fn get_collection(&self, user_id: &str, query: &CollectionQuery) -> Result<Vec<CollectionEntry>> {
+        let entries = sqlx::query_as!(
+            CollectionEntry,
+            r#"
+            SELECT rt.id AS template_id, rt.title, rt.type, rt.rarity, rt.category, rt.merchant_id, rt.brand,
+                   NOT COALESCE(rt.is_active, true) AS "retired!",
+                   uc.template_id IS NOT NULL AS "collected!",
+                   uc.first_obtained_at AS "first_obtained_at?",
+                   COALESCE(uc.times_obtained, 0) AS "times_obtained!"
+            FROM reward_templates rt
+            LEFT JOIN user_collection uc ON uc.template_id = rt.id AND uc.user_id = $1
+            WHERE (COALESCE(rt.is_active, true) OR uc.template_id IS NOT NULL)
+              AND ($2::TEXT IS NULL OR rt.category = $2)
+              AND ($3::TEXT IS NULL OR rt.merchant_id = $3)
+              AND ($4::TEXT IS NULL OR rt.rarity = $4)
+              AND ($5::BOOLEAN IS NULL OR (uc.template_id IS NOT NULL) = $5)
+            ORDER BY rt.category NULLS LAST, rt.merchant_id NULLS LAST, rt.title
+            "#,
+            user_id,
+            query.category,
+            query.merchant_id,
+            query.rarity,
+            query.collected
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(entries)
+    }
+
+    /// Completion across the whole book and per category, merchant and rarity
+    pub async fn get_summary(&self, user_id: &str) -> Result<CollectionSummary> {
+        let entries = self.get_collection(user_id, &CollectionQuery::default()).await?;
+
+        let mut summary = CollectionSummary {
+            overall: Completion::default(),
+            by_category: BTreeMap::new(),
+            by_merchant: BTreeMap::new(),
+            by_rarity: BTreeMap::new(),
+        };
+        for entry in entries {
+            summary.overall.add(entry.collected);
+            if let Some(category) = entry.category {
+                summary.by_category.entry(category).or_default().add(entry.collected);
+            }
+            if let Some(merchant_id) = entry.merchant_id {
+                summary.by_merchant.entry(merchant_id).or_default().add(entry.collected);
+            }
+            summary.by_rarity.entry(entry.rarity).or_default().add(entry.collected);
+        }
+
+        Ok(summary)
+    }
+}
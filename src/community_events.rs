@@ -0,0 +1,400 @@
+use crate::error::{AppError, Result};
+use crate::inbox::{self, NewMessage};
+use crate::{audit, user_packs};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{FromRow, PgConnection, PgPool};
+use std::sync::Arc;
+use tracing::{error, info};
+use uuid::Uuid;
+
+const WORKER_INTERVAL_SECONDS: u64 = 60;
+const DISTRIBUTION_BATCH_SIZE: i64 = 500;
+
+/// Ended events stay visible this long so users can see how they finished
+const RECENT_EVENT_DAYS: i32 = 7;
+
+/// Queue one contribution per running event this open counts towards. Runs in the open's
+/// transaction; it only inserts, so opens never contend on an event's counter.
+pub async fn record_open(conn: &mut PgConnection, user_id: &str, pack_type_id: Uuid) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO community_contributions (event_id, user_id)
+        SELECT id, $1 FROM community_events
+        WHERE status = 'running' AND starts_at <= NOW() AND ends_at > NOW()
+          AND (pack_type_id IS NULL OR pack_type_id = $2)
+        "#,
+        user_id,
+        pack_type_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct CommunityEvent {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub pack_type_id: Option<Uuid>,
+    pub goal: i64,
+    pub progress: i64,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub reward_pack_type_id: Uuid,
+    pub min_contribution: i32,
+    pub status: String,
+    pub goal_reached_at: Option<DateTime<Utc>>,
+    pub closed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateCommunityEventRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub pack_type_id: Option<Uuid>,
+    pub goal: i64,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub reward_pack_type_id: Uuid,
+    pub min_contribution: Option<i32>,
+}
+
+/// An event as a user sees it. Progress is folded in by the worker, so it trails live
+/// opens by up to a minute.
+#[derive(Debug, Serialize)]
+pub struct EventProgress {
+    #[serde(flatten)]
+    pub event: CommunityEvent,
+    pub percent: f64,
+    pub your_contribution: i64,
+    /// Whether the user has contributed enough to share in the reward
+    pub eligible: bool,
+    pub user_pack_id: Option<Uuid>,
+}
+
+pub struct CommunityEventService {
+    db: PgPool,
+}
+
+impl CommunityEventService {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    /// Running events plus ones that ended recently, with the user's part in each
+    pub async fn list_events(&self, user_id: &str) -> Result<Vec<EventProgress>> {
+        let events = sqlx::query_as!(
+            CommunityEvent,
+            r#"
+            SELECT id, name, description, pack_type_id, goal, progress, starts_at, ends_at,
+                   reward_pack_type_id, min_contribution, status, goal_reached_at, closed_at
+            FROM community_events
+            WHERE starts_at <= NOW() AND ends_at > NOW() - make_interval(days => $1)
+            ORDER BY ends_at
+            "#,
+            RECENT_EVENT_DAYS
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        let mut progress = Vec::with_capacity(events.len());
+        for event in events {
+            progress.push(self.with_participation(user_id, event).await?);
+        }
+        Ok(progress)
+    }
+
+    pub async fn get_event(&self, user_id: &str, event_id: Uuid) -> Result<EventProgress> {
+        let event = self.load_event(event_id).await?;
+        if event.starts_at > Utc::now() {
+            return Err(AppError::NotFound("Event not found".to_string()));
+        }
+        self.with_participation(user_id, event).await
+    }
+
+    pub async fn list_all(&self) -> Result<Vec<CommunityEvent>> {
+        let events = sqlx::query_as!(
+            CommunityEvent,
+            r#"
+            SELECT id, name, description, pack_type_id, goal, progress, starts_at, ends_at,
+                   reward_pack_type_id, min_contribution, status, goal_reached_at, closed_at
+            FROM community_events
+            ORDER BY starts_at DESC
+            "#
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(events)
+    }
+
+    pub async fn create_event(&self, actor: &str, req: CreateCommunityEventRequest) -> Result<CommunityEvent> {
+        if req.name.trim().is_empty() {
+            return Err(AppError::BadRequest("name is required".to_string()));
+        }
+        if req.goal <= 0 {
+            return Err(AppError::BadRequest("goal must be positive".to_string()));
+        }
+        if req.ends_at <= req.starts_at || req.ends_at <= Utc::now() {
+            return Err(AppError::BadRequest("ends_at must be after starts_at and in the future".to_string()));
+        }
+        let min_contribution = req.min_contribution.unwrap_or(1);
+        if min_contribution < 1 {
+            return Err(AppError::BadRequest("min_contribution must be at least 1".to_string()));
+        }
+
+        let mut tx = self.db.begin().await?;
+        let event = sqlx::query_as!(
+            CommunityEvent,
+            r#"
+            INSERT INTO community_events
+                (name, description, pack_type_id, goal, starts_at, ends_at, reward_pack_type_id, min_contribution, created_by)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING id, name, description, pack_type_id, goal, progress, starts_at, ends_at,
+                      reward_pack_type_id, min_contribution, status, goal_reached_at, closed_at
+            "#,
+            req.name.trim(),
+            req.description,
+            req.pack_type_id,
+            req.goal,
+            req.starts_at,
+            req.ends_at,
+            req.reward_pack_type_id,
+            min_contribution,
+            actor
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        audit::record(&mut *tx, actor, "community_event.created", Some(&event.id.to_string()), json!({
+            "name": event.name,
+            "goal": event.goal,
+            "starts_at": event.starts_at,
+            "ends_at": event.ends_at,
+            "reward_pack_type_id": event.reward_pack_type_id,
+        }))
+        .await?;
+
+        tx.commit().await?;
+        Ok(event)
+    }
+
+    /// Fold queued contributions into every live event, close events whose window has
+    /// ended and hand out rewards for the ones that met their goal
+    pub async fn run_due(&self) -> Result<()> {
+        let due = sqlx::query_scalar!(
+            r#"
+            SELECT id FROM community_events
+            WHERE status IN ('running', 'distributing') AND starts_at <= NOW()
+            ORDER BY ends_at
+            "#
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        for event_id in due {
+            if let Some(status) = self.advance(event_id).await? {
+                if status == "distributing" {
+                    while self.distribute_batch(event_id).await? > 0 {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Fold the event's queue and close it if it has ended. Returns the event's status, or
+    /// `None` if another worker holds it.
+    async fn advance(&self, event_id: Uuid) -> Result<Option<String>> {
+        let mut tx = self.db.begin().await?;
+        let event = sqlx::query!(
+            "SELECT status, ends_at FROM community_events WHERE id = $1 FOR UPDATE SKIP LOCKED",
+            event_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(event) = event else {
+            return Ok(None);
+        };
+
+        // Deleting the consumed rows means each contribution is counted exactly once, and
+        // rows from opens still in flight are left for the next run
+        let totals = sqlx::query!(
+            r#"
+            WITH consumed AS (
+                DELETE FROM community_contributions WHERE event_id = $1 RETURNING user_id, amount
+            ), per_user AS (
+                SELECT user_id, SUM(amount)::BIGINT AS amount FROM consumed GROUP BY user_id
+            ), participants AS (
+                INSERT INTO community_event_participants (event_id, user_id, contribution)
+                SELECT $1, user_id, amount FROM per_user
+                ON CONFLICT (event_id, user_id) DO UPDATE SET
+                    contribution = community_event_participants.contribution + EXCLUDED.contribution
+            )
+            UPDATE community_events ce
+            SET progress = ce.progress + added.amount,
+                goal_reached_at = CASE
+                    WHEN ce.goal_reached_at IS NULL AND ce.progress + added.amount >= ce.goal THEN NOW()
+                    ELSE ce.goal_reached_at
+                END
+            FROM (SELECT COALESCE(SUM(amount), 0)::BIGINT AS amount FROM per_user) added
+            WHERE ce.id = $1
+            RETURNING ce.progress, ce.goal
+            "#,
+            event_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let mut status = event.status;
+        if status == "running" && event.ends_at <= Utc::now() {
+            status = if totals.progress >= totals.goal { "distributing" } else { "failed" }.to_string();
+            sqlx::query!(
+                "UPDATE community_events SET status = $2, closed_at = NOW() WHERE id = $1",
+                event_id,
+                status
+            )
+            .execute(&mut *tx)
+            .await?;
+            audit::record(&mut *tx, "system", "community_event.closed", Some(&event_id.to_string()), json!({
+                "progress": totals.progress,
+                "goal": totals.goal,
+                "status": status,
+            }))
+            .await?;
+            info!("Community event {} closed at {}/{}: {}", event_id, totals.progress, totals.goal, status);
+        }
+
+        tx.commit().await?;
+        Ok(Some(status))
+    }
+
+    /// Grant the reward pack to the next batch of eligible participants; marks the event
+    /// completed once everyone has theirs. Returns how many were rewarded.
+    async fn distribute_batch(&self, event_id: Uuid) -> Result<usize> {
+        let mut tx = self.db.begin().await?;
+        let event = sqlx::query!(
+            r#"
+            SELECT name, reward_pack_type_id, min_contribution
+            FROM community_events
+            WHERE id = $1 AND status = 'distributing'
+            FOR UPDATE SKIP LOCKED
+            "#,
+            event_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(event) = event else {
+            return Ok(0);
+        };
+
+        let batch = sqlx::query_scalar!(
+            r#"
+            SELECT user_id FROM community_event_participants
+            WHERE event_id = $1 AND rewarded_at IS NULL AND contribution >= $2
+            ORDER BY user_id
+            LIMIT $3
+            "#,
+            event_id,
+            event.min_contribution as i64,
+            DISTRIBUTION_BATCH_SIZE
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        if batch.is_empty() {
+            sqlx::query!("UPDATE community_events SET status = 'completed' WHERE id = $1", event_id)
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+            info!("Community event {} rewards distributed", event_id);
+            return Ok(0);
+        }
+
+        for user_id in &batch {
+            let user_pack_id =
+                user_packs::grant(&mut tx, user_id, event.reward_pack_type_id, "community_event", None).await?;
+            sqlx::query!(
+                r#"
+                UPDATE community_event_participants SET user_pack_id = $3, rewarded_at = NOW()
+                WHERE event_id = $1 AND user_id = $2
+                "#,
+                event_id,
+                user_id,
+                user_pack_id
+            )
+            .execute(&mut *tx)
+            .await?;
+            inbox::send(&mut tx, user_id, NewMessage {
+                kind: "grant",
+                title: &format!("{}: goal reached!", event.name),
+                body: "The community hit the goal. Your bonus pack is waiting in your packs.",
+                claim_pack_type_id: None,
+                expires_at: None,
+            })
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(batch.len())
+    }
+
+    /// Run `run_due` every minute
+    pub fn spawn_worker(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(WORKER_INTERVAL_SECONDS));
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.run_due().await {
+                    error!("Community event run failed: {:?}", e);
+                }
+            }
+        })
+    }
+
+    async fn load_event(&self, event_id: Uuid) -> Result<CommunityEvent> {
+        sqlx::query_as!(
+            CommunityEvent,
+            r#"
+            SELECT id, name, description, pack_type_id, goal, progress, starts_at, ends_at,
+                   reward_pack_type_id, min_contribution, status, goal_reached_at, closed_at
+            FROM community_events
+            WHERE id = $1
+            "#,
+            event_id
+        )
+        .fetch_optional(&self.db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Event not found".to_string()))
+    }
+
+    async fn with_participation(&self, user_id: &str, event: CommunityEvent) -> Result<EventProgress> {
+        let participant = sqlx::query!(
+            r#"
+            SELECT contribution, user_pack_id
+            FROM community_event_participants
+            WHERE event_id = $1 AND user_id = $2
+            "#,
+            event.id,
+            user_id
+        )
+        .fetch_optional(&self.db)
+        .await?;
+
+        let (your_contribution, user_pack_id) = participant
+            .map(|p| (p.contribution, p.user_pack_id))
+            .unwrap_or((0, None));
+        let percent = ((event.progress as f64 * 1000.0 / event.goal as f64).round() / 10.0).min(100.0);
+
+        Ok(EventProgress {
+            eligible: your_contribution >= event.min_contribution as i64,
+            percent,
+            your_contribution,
+            user_pack_id,
+            event,
+        })
+    }
+}
@@ -0,0 +1,267 @@
+use crate::error::{AppError, Result};
+use crate::audit;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{FromRow, PgExecutor, PgPool};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// How paid random packs are offered to a user
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PaidRandomMode {
+    /// Random draws as usual
+    Allow,
+    /// The buyer picks the reward; no randomness
+    Choose,
+    /// Paid random packs are not offered
+    Hide,
+}
+
+impl PaidRandomMode {
+    fn parse(mode: &str) -> Self {
+        match mode {
+            "choose" => Self::Choose,
+            "hide" => Self::Hide,
+            _ => Self::Allow,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Allow => "allow",
+            Self::Choose => "choose",
+            Self::Hide => "hide",
+        }
+    }
+}
+
+/// Who the rules are evaluated for, from the verified auth token. `region` defaults to
+/// the user's market; `age` is only set once the user's age has been verified.
+#[derive(Debug, Clone)]
+pub struct ComplianceSubject {
+    pub region: String,
+    pub age: Option<i32>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct CompliancePolicy {
+    pub region: String,
+    pub paid_random_mode: String,
+    pub minor_paid_random_mode: String,
+    pub adult_age: i32,
+    pub require_odds_disclosure: bool,
+    pub disclosure_text: Option<String>,
+    pub updated_by: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl CompliancePolicy {
+    /// Users without a verified age get the minor rules
+    pub fn mode_for(&self, subject: &ComplianceSubject) -> PaidRandomMode {
+        match subject.age {
+            Some(age) if age >= self.adult_age => PaidRandomMode::parse(&self.paid_random_mode),
+            _ => PaidRandomMode::parse(&self.minor_paid_random_mode),
+        }
+    }
+}
+
+/// Odds shown before a paid random pack can be opened
+#[derive(Debug, Serialize)]
+pub struct OddsDisclosure {
+    pub text: Option<String>,
+    pub odds_by_rarity: HashMap<String, f64>,
+    /// Whether the user has confirmed the current odds
+    pub acknowledged: bool,
+}
+
+/// Paid packs are the ones regulated as loot boxes; free packs are never restricted
+pub fn is_paid_random(price_coins: Option<i32>, pack_type: &str) -> bool {
+    pack_type != "free" && price_coins.unwrap_or(0) > 0
+}
+
+/// The most specific policy for a region: exact match, then its country, then `*`
+pub async fn policy_for<'e, E: PgExecutor<'e>>(executor: E, region: &str) -> Result<CompliancePolicy> {
+    let region = region.trim().to_uppercase();
+    let country = region.split('-').next().unwrap_or(&region).to_string();
+    let candidates = vec![region, country, "*".to_string()];
+
+    let policy = sqlx::query_as!(
+        CompliancePolicy,
+        r#"
+        SELECT region, paid_random_mode, minor_paid_random_mode, adult_age,
+               require_odds_disclosure, disclosure_text, updated_by, updated_at
+        FROM compliance_policies
+        WHERE region = ANY($1)
+        ORDER BY array_position($1, region)
+        LIMIT 1
+        "#,
+        &candidates
+    )
+    .fetch_optional(executor)
+    .await?
+    .ok_or_else(|| AppError::InternalError("No fallback compliance policy configured".to_string()))?;
+
+    Ok(policy)
+}
+
+/// Packs whose odds the user acknowledged after the pack last changed
+pub async fn acknowledged_packs<'e, E: PgExecutor<'e>>(executor: E, user_id: &str) -> Result<Vec<Uuid>> {
+    let packs = sqlx::query_scalar!(
+        r#"
+        SELECT oa.pack_type_id
+        FROM odds_acknowledgements oa
+        JOIN pack_types pt ON pt.id = oa.pack_type_id
+        WHERE oa.user_id = $1 AND oa.acknowledged_at >= COALESCE(pt.updated_at, pt.created_at, oa.acknowledged_at)
+        "#,
+        user_id
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(packs)
+}
+
+/// Reject a paid open the user's policy doesn't permit. `chosen` is whether the buyer
+/// picked the reward (choose mode) instead of drawing at random.
+pub async fn check_paid_open<'e, E: PgExecutor<'e>>(
+    executor: E,
+    user_id: &str,
+    pack_type_id: Uuid,
+    policy: &CompliancePolicy,
+    subject: &ComplianceSubject,
+    chosen: bool,
+) -> Result<()> {
+    match (policy.mode_for(subject), chosen) {
+        (PaidRandomMode::Hide, _) => {
+            return Err(AppError::NotFound("Pack type not found".to_string()));
+        }
+        (PaidRandomMode::Choose, false) => {
+            return Err(AppError::BadRequest(
+                "Packs in your region let you choose your reward; pick one to continue".to_string()
+            ));
+        }
+        (PaidRandomMode::Allow, true) => {
+            return Err(AppError::BadRequest("Choosing a reward isn't available for this pack".to_string()));
+        }
+        _ => {}
+    }
+
+    // A chosen reward has no odds to disclose
+    if policy.require_odds_disclosure && !chosen {
+        let acknowledged = acknowledged_packs(executor, user_id).await?.contains(&pack_type_id);
+        if !acknowledged {
+            return Err(AppError::BadRequest("Review this pack's odds before opening it".to_string()));
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpsertPolicyRequest {
+    pub paid_random_mode: PaidRandomMode,
+    pub minor_paid_random_mode: PaidRandomMode,
+    pub adult_age: Option<i32>,
+    pub require_odds_disclosure: bool,
+    pub disclosure_text: Option<String>,
+}
+
+pub struct ComplianceService {
+    db: PgPool,
+}
+
+impl ComplianceService {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    pub async fn list_policies(&self) -> Result<Vec<CompliancePolicy>> {
+        let policies = sqlx::query_as!(
+            CompliancePolicy,
+            r#"
+            SELECT region, paid_random_mode, minor_paid_random_mode, adult_age,
+                   require_odds_disclosure, disclosure_text, updated_by, updated_at
+            FROM compliance_policies
+            ORDER BY region
+            "#
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(policies)
+    }
+
+    pub async fn upsert_policy(&self, actor: &str, region: &str, req: UpsertPolicyRequest) -> Result<CompliancePolicy> {
+        let region = region.trim().to_uppercase();
+        if region.is_empty() {
+            return Err(AppError::BadRequest("Region is required".to_string()));
+        }
+        let adult_age = req.adult_age.unwrap_or(18);
+        if !(1..=25).contains(&adult_age) {
+            return Err(AppError::BadRequest("adult_age must be between 1 and 25".to_string()));
+        }
+        if req.require_odds_disclosure && req.disclosure_text.as_deref().map(str::trim).unwrap_or("").is_empty() {
+            return Err(AppError::BadRequest("Odds disclosure needs disclosure_text".to_string()));
+        }
+
+        let mut tx = self.db.begin().await?;
+        let policy = sqlx::query_as!(
+            CompliancePolicy,
+            r#"
+            INSERT INTO compliance_policies
+                (region, paid_random_mode, minor_paid_random_mode, adult_age,
+                 require_odds_disclosure, disclosure_text, updated_by)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (region) DO UPDATE SET
+                paid_random_mode = EXCLUDED.paid_random_mode,
+                minor_paid_random_mode = EXCLUDED.minor_paid_random_mode,
+                adult_age = EXCLUDED.adult_age,
+                require_odds_disclosure = EXCLUDED.require_odds_disclosure,
+                disclosure_text = EXCLUDED.disclosure_text,
+                updated_by = EXCLUDED.updated_by,
+                updated_at = NOW()
+            RETURNING region, paid_random_mode, minor_paid_random_mode, adult_age,
+                      require_odds_disclosure, disclosure_text, updated_by, updated_at
+            "#,
+            region,
+            req.paid_random_mode.as_str(),
+            req.minor_paid_random_mode.as_str(),
+            adult_age,
+            req.require_odds_disclosure,
+            req.disclosure_text
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        audit::record(&mut *tx, actor, "compliance.policy_updated", Some(&policy.region), json!({
+            "paid_random_mode": policy.paid_random_mode,
+            "minor_paid_random_mode": policy.minor_paid_random_mode,
+            "adult_age": policy.adult_age,
+            "require_odds_disclosure": policy.require_odds_disclosure,
+        }))
+        .await?;
+        tx.commit().await?;
+        Ok(policy)
+    }
+
+    /// Record that the user reviewed a pack's current odds
+    pub async fn acknowledge_odds(&self, user_id: &str, pack_type_id: Uuid) -> Result<()> {
+        let inserted = sqlx::query!(
+            r#"
+            INSERT INTO odds_acknowledgements (user_id, pack_type_id)
+            SELECT $1, id FROM pack_types WHERE id = $2
+            ON CONFLICT (user_id, pack_type_id) DO UPDATE SET acknowledged_at = NOW()
+            "#,
+            user_id,
+            pack_type_id
+        )
+        .execute(&self.db)
+        .await?;
+
+        if inserted.rows_affected() == 0 {
+            return Err(AppError::NotFound("Pack type not found".to_string()));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,503 @@
+use crate::error::{AppError, Result};
+use crate::audit;
+use crate::lootpacks::LootpackService;
+use crate::pack_admin::{self, MappingConfig};
+use crate::reveal;
+use crate::reward_types;
+use bigdecimal::BigDecimal;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::{FromRow, PgPool};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Deserialize, Serialize, FromRow)]
+pub struct PackTypeConfig {
+    pub id: Uuid,
+    pub name: String,
+    pub r#type: String,
+    pub description: Option<String>,
+    pub icon: Option<String>,
+    pub color_gradient: Option<String>,
+    pub price_coins: Option<i32>,
+    pub cooldown_hours: Option<i32>,
+    pub min_rewards: i32,
+    pub max_rewards: i32,
+    pub possible_reward_types: Option<Vec<String>>,
+    pub is_active: Option<bool>,
+    pub markets: Option<Vec<String>>,
+    /// Absent in exports from before reveal orders; imported as the default
+    pub reveal_order: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, FromRow)]
+pub struct RewardTemplateConfig {
+    pub id: Uuid,
+    pub r#type: String,
+    pub title: String,
+    pub value: String,
+    pub description: Option<String>,
+    pub rarity: String,
+    pub code_pattern: Option<String>,
+    pub validity_days: Option<i32>,
+    pub metadata: Option<Value>,
+    pub is_active: Option<bool>,
+    pub merchant_id: Option<String>,
+    pub brand: Option<String>,
+    pub category: Option<String>,
+    pub value_amount: Option<BigDecimal>,
+    pub value_currency: Option<String>,
+    pub markets: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, FromRow)]
+pub struct PackMappingConfig {
+    pub pack_type_id: Uuid,
+    pub reward_template_id: Uuid,
+    pub weight: i32,
+}
+
+/// Full pack/reward configuration, portable between environments
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PackConfig {
+    #[serde(default)]
+    pub pack_types: Vec<PackTypeConfig>,
+    #[serde(default)]
+    pub reward_templates: Vec<RewardTemplateConfig>,
+    #[serde(default)]
+    pub mappings: Vec<PackMappingConfig>,
+}
+
+/// One spreadsheet row: a template with one of its pack weights. Templates in several
+/// packs repeat on several rows; templates in no pack have empty pack columns.
+#[derive(Debug, Deserialize, Serialize)]
+struct CsvRow {
+    reward_template_id: Option<Uuid>,
+    r#type: String,
+    title: String,
+    value: String,
+    description: Option<String>,
+    rarity: String,
+    code_pattern: Option<String>,
+    validity_days: Option<i32>,
+    /// JSON object as text
+    metadata: Option<String>,
+    is_active: Option<bool>,
+    merchant_id: Option<String>,
+    brand: Option<String>,
+    category: Option<String>,
+    value_amount: Option<BigDecimal>,
+    value_currency: Option<String>,
+    /// Market codes separated by spaces; empty for every market
+    markets: Option<String>,
+    pack_type_id: Option<Uuid>,
+    /// Informational; ignored on import
+    pack_name: Option<String>,
+    weight: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportSummary {
+    pub dry_run: bool,
+    pub pack_types: usize,
+    pub reward_templates: usize,
+    pub mappings: usize,
+    /// Packs whose mappings were replaced; each got a new pack version
+    pub packs_updated: Vec<Uuid>,
+}
+
+/// Export and import of the whole pack/reward configuration, used to promote
+/// staging config to production and for bulk edits in spreadsheets
+pub struct ConfigTransferService {
+    db: PgPool,
+    lootpacks: Arc<LootpackService>,
+}
+
+impl ConfigTransferService {
+    pub fn new(db: PgPool, lootpacks: Arc<LootpackService>) -> Self {
+        Self { db, lootpacks }
+    }
+
+    pub async fn export(&self) -> Result<PackConfig> {
+        let pack_types = sqlx::query_as!(
+            PackTypeConfig,
+            r#"
+            SELECT id, name, type, description, icon, color_gradient, price_coins, cooldown_hours,
+                   min_rewards, max_rewards, possible_reward_types, is_active, markets,
+                   reveal_order AS "reveal_order?"
+            FROM pack_types
+            ORDER BY name, id
+            "#
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        let reward_templates = sqlx::query_as!(
+            RewardTemplateConfig,
+            r#"
+            SELECT id, type, title, value, description, rarity, code_pattern, validity_days,
+                   metadata, is_active, merchant_id, brand, category, value_amount,
+                   value_currency AS "value_currency?", markets
+            FROM reward_templates
+            ORDER BY title, id
+            "#
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        let mappings = sqlx::query_as!(
+            PackMappingConfig,
+            r#"
+            SELECT pack_type_id, reward_template_id, weight AS "weight!"
+            FROM pack_reward_mappings
+            ORDER BY pack_type_id, reward_template_id
+            "#
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(PackConfig { pack_types, reward_templates, mappings })
+    }
+
+    /// Templates joined to their pack weights as CSV
+    pub async fn export_csv(&self) -> Result<String> {
+        let config = self.export().await?;
+        let pack_names: HashMap<Uuid, &str> =
+            config.pack_types.iter().map(|p| (p.id, p.name.as_str())).collect();
+        let mut mappings_by_template: HashMap<Uuid, Vec<&PackMappingConfig>> = HashMap::new();
+        for mapping in &config.mappings {
+            mappings_by_template.entry(mapping.reward_template_id).or_default().push(mapping);
+        }
+
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        for template in &config.reward_templates {
+            let row = |mapping: Option<&PackMappingConfig>| CsvRow {
+                reward_template_id: Some(template.id),
+                r#type: template.r#type.clone(),
+                title: template.title.clone(),
+                value: template.value.clone(),
+                description: template.description.clone(),
+                rarity: template.rarity.clone(),
+                code_pattern: template.code_pattern.clone(),
+                validity_days: template.validity_days,
+                metadata: template.metadata.as_ref().map(Value::to_string),
+                is_active: template.is_active,
+                merchant_id: template.merchant_id.clone(),
+                brand: template.brand.clone(),
+                category: template.category.clone(),
+                value_amount: template.value_amount.clone(),
+                value_currency: template.value_currency.clone(),
+                markets: template.markets.as_ref().map(|m| m.join(" ")),
+                pack_type_id: mapping.map(|m| m.pack_type_id),
+                pack_name: mapping.and_then(|m| pack_names.get(&m.pack_type_id)).map(|n| n.to_string()),
+                weight: mapping.map(|m| m.weight),
+            };
+
+            let rows = match mappings_by_template.get(&template.id) {
+                Some(mappings) => mappings.iter().map(|m| row(Some(m))).collect(),
+                None => vec![row(None)],
+            };
+            for row in rows {
+                writer
+                    .serialize(row)
+                    .map_err(|e| AppError::InternalError(format!("Failed to write CSV: {}", e)))?;
+            }
+        }
+
+        let bytes = writer
+            .into_inner()
+            .map_err(|e| AppError::InternalError(format!("Failed to write CSV: {}", e)))?;
+        String::from_utf8(bytes).map_err(|e| AppError::InternalError(format!("Failed to write CSV: {}", e)))
+    }
+
+    /// Validate a configuration and apply it in one transaction. Packs and templates are
+    /// upserted by id; every pack in the payload gets exactly the mappings listed for it.
+    /// With `dry_run` the import is validated and rolled back.
+    pub async fn import(&self, actor: &str, config: PackConfig, dry_run: bool) -> Result<ImportSummary> {
+        validate(&config)?;
+
+        let mut tx = self.db.begin().await?;
+        sqlx::query!("SELECT set_config('app.actor', $1, true)", actor)
+            .execute(&mut *tx)
+            .await?;
+
+        // Mappings may reference templates that already exist in this environment
+        let payload_templates: HashSet<Uuid> = config.reward_templates.iter().map(|t| t.id).collect();
+        let referenced: Vec<Uuid> = config
+            .mappings
+            .iter()
+            .map(|m| m.reward_template_id)
+            .filter(|id| !payload_templates.contains(id))
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let existing = sqlx::query_scalar!("SELECT id FROM reward_templates WHERE id = ANY($1)", &referenced)
+            .fetch_all(&mut *tx)
+            .await?;
+        if existing.len() != referenced.len() {
+            let existing: HashSet<Uuid> = existing.into_iter().collect();
+            let missing: Vec<String> = referenced
+                .iter()
+                .filter(|id| !existing.contains(id))
+                .map(Uuid::to_string)
+                .collect();
+            return Err(AppError::BadRequest(format!("Unknown reward templates: {}", missing.join(", "))));
+        }
+
+        // Every pack that is listed or has mappings gets its mappings replaced
+        let mut mappings_by_pack: BTreeMap<Uuid, Vec<MappingConfig>> =
+            config.pack_types.iter().map(|p| (p.id, Vec::new())).collect();
+        for mapping in &config.mappings {
+            mappings_by_pack.entry(mapping.pack_type_id).or_default().push(MappingConfig {
+                reward_template_id: mapping.reward_template_id,
+                weight: mapping.weight,
+            });
+        }
+
+        // Baseline existing packs before they change so the import can be rolled back per pack
+        let pack_ids: Vec<Uuid> = mappings_by_pack.keys().copied().collect();
+        let existing_packs = sqlx::query_scalar!(
+            "SELECT id FROM pack_types WHERE id = ANY($1) ORDER BY id FOR UPDATE",
+            &pack_ids
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        for pack_type_id in &existing_packs {
+            pack_admin::ensure_baseline(&mut tx, *pack_type_id).await?;
+        }
+
+        let pack_types = to_json(&config.pack_types)?;
+        sqlx::query!(
+            r#"
+            INSERT INTO pack_types (id, name, type, description, icon, color_gradient, price_coins,
+                                    cooldown_hours, min_rewards, max_rewards, possible_reward_types,
+                                    is_active, markets, reveal_order)
+            SELECT r.id, r.name, r.type, r.description, r.icon, r.color_gradient, r.price_coins,
+                   r.cooldown_hours, r.min_rewards, r.max_rewards, r.possible_reward_types,
+                   COALESCE(r.is_active, true), r.markets, COALESCE(r.reveal_order, 'best_last')
+            FROM jsonb_populate_recordset(NULL::pack_types, $1) r
+            ON CONFLICT (id) DO UPDATE SET
+                name = EXCLUDED.name, type = EXCLUDED.type, description = EXCLUDED.description,
+                icon = EXCLUDED.icon, color_gradient = EXCLUDED.color_gradient,
+                price_coins = EXCLUDED.price_coins, cooldown_hours = EXCLUDED.cooldown_hours,
+                min_rewards = EXCLUDED.min_rewards, max_rewards = EXCLUDED.max_rewards,
+                possible_reward_types = EXCLUDED.possible_reward_types,
+                is_active = EXCLUDED.is_active, markets = EXCLUDED.markets,
+                reveal_order = EXCLUDED.reveal_order, updated_at = NOW()
+            "#,
+            pack_types
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let templates = to_json(&config.reward_templates)?;
+        sqlx::query!(
+            r#"
+            INSERT INTO reward_templates (id, type, title, value, description, rarity, code_pattern,
+                                          validity_days, metadata, is_active, merchant_id, brand,
+                                          category, value_amount, value_currency, markets)
+            SELECT r.id, r.type, r.title, r.value, r.description, r.rarity, r.code_pattern,
+                   r.validity_days, COALESCE(r.metadata, '{}'::jsonb), COALESCE(r.is_active, true),
+                   r.merchant_id, r.brand, r.category, r.value_amount, COALESCE(r.value_currency, 'INR'),
+                   r.markets
+            FROM jsonb_populate_recordset(NULL::reward_templates, $1) r
+            ON CONFLICT (id) DO UPDATE SET
+                type = EXCLUDED.type, title = EXCLUDED.title, value = EXCLUDED.value,
+                description = EXCLUDED.description, rarity = EXCLUDED.rarity,
+                code_pattern = EXCLUDED.code_pattern, validity_days = EXCLUDED.validity_days,
+                metadata = EXCLUDED.metadata, is_active = EXCLUDED.is_active,
+                merchant_id = EXCLUDED.merchant_id, brand = EXCLUDED.brand,
+                category = EXCLUDED.category, value_amount = EXCLUDED.value_amount,
+                value_currency = EXCLUDED.value_currency, markets = EXCLUDED.markets
+            "#,
+            templates
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let unknown_packs: Vec<Uuid> = {
+            let known = sqlx::query_scalar!("SELECT id FROM pack_types WHERE id = ANY($1)", &pack_ids)
+                .fetch_all(&mut *tx)
+                .await?;
+            pack_ids.iter().copied().filter(|id| !known.contains(id)).collect()
+        };
+        if !unknown_packs.is_empty() {
+            let ids: Vec<String> = unknown_packs.iter().map(Uuid::to_string).collect();
+            return Err(AppError::BadRequest(format!("Mappings reference unknown packs: {}", ids.join(", "))));
+        }
+
+        for (pack_type_id, mappings) in &mappings_by_pack {
+            pack_admin::replace_mappings(&mut tx, *pack_type_id, &to_json(mappings)?).await?;
+            pack_admin::snapshot(&mut tx, *pack_type_id, actor, Some("Config import")).await?;
+        }
+
+        let summary = ImportSummary {
+            dry_run,
+            pack_types: config.pack_types.len(),
+            reward_templates: config.reward_templates.len(),
+            mappings: config.mappings.len(),
+            packs_updated: pack_ids,
+        };
+
+        if dry_run {
+            tx.rollback().await?;
+            return Ok(summary);
+        }
+
+        audit::record(
+            &mut *tx,
+            actor,
+            "config.imported",
+            None,
+            json!({
+                "pack_types": summary.pack_types,
+                "reward_templates": summary.reward_templates,
+                "mappings": summary.mappings,
+            }),
+        )
+        .await?;
+
+        tx.commit().await?;
+        // Template edits can touch any pack's pool
+        self.lootpacks.clear_caches().await;
+        Ok(summary)
+    }
+
+    /// Import the spreadsheet produced by `export_csv`. Rows without a template id create
+    /// new templates; only packs that appear in the sheet have their mappings replaced.
+    pub async fn import_csv(&self, actor: &str, csv_body: &str, dry_run: bool) -> Result<ImportSummary> {
+        let config = parse_csv(csv_body)?;
+        self.import(actor, config, dry_run).await
+    }
+}
+
+fn parse_csv(csv_body: &str) -> Result<PackConfig> {
+    let mut reader = csv::Reader::from_reader(csv_body.as_bytes());
+    let mut templates: Vec<RewardTemplateConfig> = Vec::new();
+    let mut template_index: HashMap<Uuid, usize> = HashMap::new();
+    let mut mappings = Vec::new();
+
+    for (line, row) in reader.deserialize::<CsvRow>().enumerate() {
+        // Header is line 1
+        let line = line + 2;
+        let row = row.map_err(|e| AppError::BadRequest(format!("Row {}: {}", line, e)))?;
+        let metadata = row
+            .metadata
+            .as_deref()
+            .filter(|m| !m.trim().is_empty())
+            .map(serde_json::from_str)
+            .transpose()
+            .map_err(|e| AppError::BadRequest(format!("Row {}: invalid metadata JSON: {}", line, e)))?;
+
+        let template = RewardTemplateConfig {
+            id: row.reward_template_id.unwrap_or_else(Uuid::new_v4),
+            r#type: row.r#type,
+            title: row.title,
+            value: row.value,
+            description: row.description,
+            rarity: row.rarity,
+            code_pattern: row.code_pattern,
+            validity_days: row.validity_days,
+            metadata,
+            is_active: row.is_active,
+            merchant_id: row.merchant_id,
+            brand: row.brand,
+            category: row.category,
+            value_amount: row.value_amount,
+            value_currency: row.value_currency,
+            markets: row
+                .markets
+                .map(|m| m.split_whitespace().map(str::to_uppercase).collect::<Vec<_>>())
+                .filter(|m| !m.is_empty()),
+        };
+        let template_id = template.id;
+
+        // A template repeated across rows must carry the same details on every row
+        match template_index.get(&template_id) {
+            Some(&index) => {
+                if serde_json::to_value(&templates[index]).ok() != serde_json::to_value(&template).ok() {
+                    return Err(AppError::BadRequest(format!(
+                        "Row {}: template {} differs from an earlier row", line, template_id
+                    )));
+                }
+            }
+            None => {
+                template_index.insert(template_id, templates.len());
+                templates.push(template);
+            }
+        }
+
+        match (row.pack_type_id, row.weight) {
+            (Some(pack_type_id), Some(weight)) => mappings.push(PackMappingConfig {
+                pack_type_id,
+                reward_template_id: template_id,
+                weight,
+            }),
+            (None, None) => {}
+            _ => {
+                return Err(AppError::BadRequest(format!(
+                    "Row {}: pack_type_id and weight must be set together", line
+                )))
+            }
+        }
+    }
+
+    Ok(PackConfig { pack_types: Vec::new(), reward_templates: templates, mappings })
+}
+
+/// Check the payload on its own before touching the database; reports every problem at once
+fn validate(config: &PackConfig) -> Result<()> {
+    let mut errors = Vec::new();
+
+    let mut pack_ids = HashSet::new();
+    for pack in &config.pack_types {
+        if !pack_ids.insert(pack.id) {
+            errors.push(format!("Duplicate pack type {}", pack.id));
+        }
+        if pack.name.trim().is_empty() {
+            errors.push(format!("Pack {} has no name", pack.id));
+        }
+        if pack.min_rewards < 1 || pack.min_rewards > pack.max_rewards {
+            errors.push(format!("Pack {} needs 1 <= min_rewards <= max_rewards", pack.id));
+        }
+        if pack.price_coins.map(|p| p < 0).unwrap_or(false) {
+            errors.push(format!("Pack {} has a negative price", pack.id));
+        }
+        if let Some(Err(AppError::BadRequest(e))) = pack.reveal_order.as_deref().map(reveal::validate_order) {
+            errors.push(format!("Pack {}: {}", pack.id, e));
+        }
+    }
+
+    let mut template_ids = HashSet::new();
+    for template in &config.reward_templates {
+        if !template_ids.insert(template.id) {
+            errors.push(format!("Duplicate reward template {}", template.id));
+        }
+        if let Err(AppError::BadRequest(e)) = reward_types::validate_template(&template.r#type, template.metadata.as_ref()) {
+            errors.push(format!("Template {}: {}", template.id, e));
+        }
+    }
+
+    let mut pairs = HashSet::new();
+    for mapping in &config.mappings {
+        if mapping.weight <= 0 {
+            errors.push(format!(
+                "Mapping {}/{} must have a positive weight", mapping.pack_type_id, mapping.reward_template_id
+            ));
+        }
+        if !pairs.insert((mapping.pack_type_id, mapping.reward_template_id)) {
+            errors.push(format!("Duplicate mapping {}/{}", mapping.pack_type_id, mapping.reward_template_id));
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::BadRequest(errors.join("; ")))
+    }
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<Value> {
+    serde_json::to_value(value).map_err(|e| AppError::InternalError(format!("Failed to encode config: {}", e)))
+}
@@ -0,0 +1,115 @@
+use crate::models::lootpacks::PackType;
+use crate::error::{AppError, Result};
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{PgConnection, PgExecutor};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// When a pack opened at `last_opened_at` can be opened again, if that's still in the future
+pub fn next_available_at(last_opened_at: DateTime<Utc>, cooldown_hours: Option<i32>) -> Option<DateTime<Utc>> {
+    let cooldown_hours = cooldown_hours.filter(|h| *h > 0)?;
+    let next = last_opened_at + Duration::hours(cooldown_hours as i64);
+    (next > Utc::now()).then_some(next)
+}
+
+/// Reject the open if the pack is on cooldown for this user, otherwise record it.
+/// Runs in the opening transaction; the row lock serializes concurrent opens.
+pub async fn enforce(conn: &mut PgConnection, user_id: &str, pack_type: &PackType) -> Result<()> {
+    if pack_type.cooldown_hours.unwrap_or(0) <= 0 {
+        return Ok(());
+    }
+
+    let last_opened_at = sqlx::query_scalar!(
+        r#"
+        SELECT last_opened_at FROM user_pack_cooldowns
+        WHERE user_id = $1 AND pack_type_id = $2
+        FOR UPDATE
+        "#,
+        user_id,
+        pack_type.id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    if let Some(next) = last_opened_at.and_then(|at| next_available_at(at, pack_type.cooldown_hours)) {
+        return Err(AppError::BadRequest(format!(
+            "{} is on cooldown until {}", pack_type.name, next.to_rfc3339()
+        )));
+    }
+
+    sqlx::query!(
+        r#"
+        INSERT INTO user_pack_cooldowns (user_id, pack_type_id, last_opened_at)
+        VALUES ($1, $2, NOW())
+        ON CONFLICT (user_id, pack_type_id) DO UPDATE SET last_opened_at = EXCLUDED.last_opened_at
+        "#,
+        user_id,
+        pack_type.id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Wheel counterpart of `enforce`: reject the spin while the wheel is on cooldown,
+/// otherwise record it and return when the next spin is allowed
+pub async fn enforce_wheel(
+    conn: &mut PgConnection,
+    user_id: &str,
+    wheel_id: Uuid,
+    cooldown_hours: i32,
+) -> Result<DateTime<Utc>> {
+    let last_spun_at = sqlx::query_scalar!(
+        r#"
+        SELECT last_spun_at FROM user_wheel_cooldowns
+        WHERE user_id = $1 AND wheel_id = $2
+        FOR UPDATE
+        "#,
+        user_id,
+        wheel_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    if let Some(next) = last_spun_at.and_then(|at| next_available_at(at, Some(cooldown_hours))) {
+        return Err(AppError::BadRequest(format!("The wheel can be spun again at {}", next.to_rfc3339())));
+    }
+
+    sqlx::query!(
+        r#"
+        INSERT INTO user_wheel_cooldowns (user_id, wheel_id, last_spun_at)
+        VALUES ($1, $2, NOW())
+        ON CONFLICT (user_id, wheel_id) DO UPDATE SET last_spun_at = EXCLUDED.last_spun_at
+        "#,
+        user_id,
+        wheel_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(Utc::now() + Duration::hours(cooldown_hours as i64))
+}
+
+/// Next available time for every pack the user currently has on cooldown
+pub async fn next_available_times<'e, E: PgExecutor<'e>>(
+    executor: E,
+    user_id: &str,
+) -> Result<HashMap<Uuid, DateTime<Utc>>> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT upc.pack_type_id, upc.last_opened_at, pt.cooldown_hours
+        FROM user_pack_cooldowns upc
+        JOIN pack_types pt ON pt.id = upc.pack_type_id
+        WHERE upc.user_id = $1 AND pt.cooldown_hours > 0
+        "#,
+        user_id
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|r| Some((r.pack_type_id, next_available_at(r.last_opened_at, r.cooldown_hours)?)))
+        .collect())
+}
@@ -0,0 +1,80 @@
+use crate::models::lootpacks::RewardTemplate;
+use crate::error::{AppError, Result};
+use crate::http_client::HttpServiceClient;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Source of real, merchant-redeemable coupon codes.
+///
+/// `Ok(None)` means the provider has nothing for this template and the caller
+/// should generate a local code; `Err` means the provider failed.
+#[async_trait]
+pub trait CouponProvider: Send + Sync {
+    async fn claim_code(&self, template: &RewardTemplate) -> Result<Option<String>>;
+}
+
+/// Default provider: every code is generated locally
+pub struct LocalCouponProvider;
+
+#[async_trait]
+impl CouponProvider for LocalCouponProvider {
+    async fn claim_code(&self, _template: &RewardTemplate) -> Result<Option<String>> {
+        Ok(None)
+    }
+}
+
+#[derive(Serialize)]
+struct ClaimCodeRequest<'a> {
+    offer_id: &'a str,
+}
+
+#[derive(Deserialize)]
+struct ClaimCodeResponse {
+    code: String,
+}
+
+/// Claims codes from a partner coupon API. Only templates whose metadata carries
+/// a `provider_offer_id` are sourced externally.
+pub struct HttpCouponProvider {
+    http: HttpServiceClient,
+    api_key: String,
+}
+
+impl HttpCouponProvider {
+    pub fn new(base_url: String, api_key: String, timeout: Duration) -> Result<Self> {
+        Ok(Self { http: HttpServiceClient::new("coupon provider", base_url, timeout)?, api_key })
+    }
+}
+
+#[async_trait]
+impl CouponProvider for HttpCouponProvider {
+    async fn claim_code(&self, template: &RewardTemplate) -> Result<Option<String>> {
+        let offer_id = match template
+            .metadata
+            .as_ref()
+            .and_then(|m| m.get("provider_offer_id"))
+            .and_then(|v| v.as_str())
+        {
+            Some(offer_id) => offer_id,
+            None => return Ok(None),
+        };
+
+        // Every claim hands out a new code, so a lost response must not be retried
+        let request = self.http.post("/codes/claim").bearer_auth(&self.api_key).json(&ClaimCodeRequest { offer_id });
+        let response = self.http.send_once(request).await?;
+
+        if !response.status().is_success() {
+            return Err(AppError::InternalError(format!(
+                "Coupon provider returned {} for offer {}", response.status(), offer_id
+            )));
+        }
+
+        let claimed: ClaimCodeResponse = response
+            .json()
+            .await
+            .map_err(|e| AppError::InternalError(format!("Invalid coupon provider response: {}", e)))?;
+
+        Ok(Some(claimed.code))
+    }
+}
@@ -0,0 +1,149 @@
+use crate::code_crypto::CodeCipher;
+use crate::error::{AppError, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Longest hold checkout may place while validating a code
+const MAX_RESERVATION_SECONDS: i64 = 900;
+
+#[derive(Debug, Deserialize)]
+pub struct CouponValidationRequest {
+    pub code: String,
+    pub user_id: Option<String>,
+    pub reserve_seconds: Option<i64>,
+    /// The caller's own hold from an earlier validation; it doesn't count as held, and a
+    /// new reservation renews it
+    pub reservation_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CouponValidationResponse {
+    pub valid: bool,
+    pub reason: Option<String>,
+    pub reward_id: Option<Uuid>,
+    pub user_id: Option<String>,
+    pub r#type: Option<String>,
+    pub title: Option<String>,
+    pub value: Option<String>,
+    pub rarity: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub reservation_id: Option<Uuid>,
+    pub reserved_until: Option<DateTime<Utc>>,
+}
+
+impl CouponValidationResponse {
+    fn invalid(reason: &str) -> Self {
+        Self {
+            valid: false,
+            reason: Some(reason.to_string()),
+            reward_id: None,
+            user_id: None,
+            r#type: None,
+            title: None,
+            value: None,
+            rarity: None,
+            expires_at: None,
+            reservation_id: None,
+            reserved_until: None,
+        }
+    }
+}
+
+pub struct CouponService {
+    db: PgPool,
+    codes: Arc<CodeCipher>,
+}
+
+impl CouponService {
+    pub fn new(db: PgPool, codes: Arc<CodeCipher>) -> Self {
+        Self { db, codes }
+    }
+
+    /// Validate a code presented at checkout: issued by lootpacks, unused, unexpired,
+    /// not held by another checkout. Optionally places a short reservation, or renews the
+    /// caller's own.
+    pub async fn validate_code(&self, req: CouponValidationRequest) -> Result<CouponValidationResponse> {
+        let reserve_seconds = req.reserve_seconds.unwrap_or(0);
+        if !(0..=MAX_RESERVATION_SECONDS).contains(&reserve_seconds) {
+            return Err(AppError::BadRequest(format!(
+                "reserve_seconds must be between 0 and {}", MAX_RESERVATION_SECONDS
+            )));
+        }
+
+        let mut tx = self.db.begin().await?;
+
+        // Codes are matched on their keyed hash; rows the encryption backfill has not
+        // reached yet still hold the plaintext code and no hash
+        let reward = sqlx::query!(
+            r#"
+            SELECT id, user_id, type, title, value, rarity, expires_at, is_used, reserved_until, reservation_id,
+                   escrow_trade_id
+            FROM user_rewards
+            WHERE (code_hash = $1 OR (code_hash IS NULL AND code = $2))
+              AND type IN ('coupon', 'voucher')
+            FOR UPDATE
+            "#,
+            self.codes.blind_index(&req.code),
+            req.code
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(reward) = reward else {
+            return Ok(CouponValidationResponse::invalid("unknown_code"));
+        };
+
+        let now = Utc::now();
+        if req.user_id.as_deref().map(|u| u != reward.user_id).unwrap_or(false) {
+            return Ok(CouponValidationResponse::invalid("wrong_user"));
+        }
+        if reward.is_used.unwrap_or(false) {
+            return Ok(CouponValidationResponse::invalid("already_used"));
+        }
+        if reward.expires_at.map(|exp| exp <= now).unwrap_or(false) {
+            return Ok(CouponValidationResponse::invalid("expired"));
+        }
+        let own_hold = req.reservation_id.is_some() && reward.reservation_id == req.reservation_id;
+        if reward.reserved_until.map(|until| until > now).unwrap_or(false) && !own_hold {
+            return Ok(CouponValidationResponse::invalid("reserved"));
+        }
+        if reward.escrow_trade_id.is_some() {
+            return Ok(CouponValidationResponse::invalid("in_trade"));
+        }
+
+        let (reservation_id, reserved_until) = if reserve_seconds > 0 {
+            let reservation_id = if own_hold { reward.reservation_id.unwrap_or_else(Uuid::new_v4) } else { Uuid::new_v4() };
+            let reserved_until = now + Duration::seconds(reserve_seconds);
+            sqlx::query!(
+                "UPDATE user_rewards SET reservation_id = $2, reserved_until = $3 WHERE id = $1",
+                reward.id,
+                reservation_id,
+                reserved_until
+            )
+            .execute(&mut *tx)
+            .await?;
+            (Some(reservation_id), Some(reserved_until))
+        } else {
+            (None, None)
+        };
+
+        tx.commit().await?;
+
+        Ok(CouponValidationResponse {
+            valid: true,
+            reason: None,
+            reward_id: Some(reward.id),
+            user_id: Some(reward.user_id),
+            r#type: Some(reward.r#type),
+            title: Some(reward.title),
+            value: Some(reward.value),
+            rarity: Some(reward.rarity),
+            expires_at: reward.expires_at,
+            reservation_id,
+            reserved_until,
+        })
+    }
+}
@@ -0,0 +1,177 @@
+use crate::error::{AppError, Result};
+use bigdecimal::{BigDecimal, RoundingMode, Zero};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgExecutor, PgPool};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+pub const BASE_CURRENCY: &str = "INR";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Money {
+    pub amount: BigDecimal,
+    pub currency: String,
+}
+
+/// The first number in a display value like "₹50", "Rs.150" or "₹1,000 on ₹5,000+".
+/// Later numbers are conditions, not the amount.
+pub fn parse_amount(value: &str) -> Option<BigDecimal> {
+    let start = value.find(|c: char| c.is_ascii_digit())?;
+    let digits: String = value[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.' || *c == ',')
+        .filter(|c| *c != ',')
+        .collect();
+    BigDecimal::from_str(digits.trim_end_matches('.')).ok().filter(|amount| *amount > BigDecimal::zero())
+}
+
+/// Exchange rates keyed by currency, expressed as units per 1 INR
+#[derive(Debug, Clone, Default)]
+pub struct RateTable {
+    per_inr: HashMap<String, BigDecimal>,
+}
+
+impl RateTable {
+    pub async fn load<'e, E: PgExecutor<'e>>(executor: E) -> Result<Self> {
+        let rows = sqlx::query!("SELECT currency, per_inr FROM currency_rates")
+            .fetch_all(executor)
+            .await?;
+
+        Ok(Self {
+            per_inr: rows.into_iter().map(|r| (r.currency, r.per_inr)).collect(),
+        })
+    }
+
+    pub fn supports(&self, currency: &str) -> bool {
+        self.per_inr.contains_key(currency)
+    }
+
+    /// Convert through INR, rounded to 2 decimal places. `None` if either rate is missing.
+    pub fn convert(&self, money: &Money, to: &str) -> Option<Money> {
+        if money.currency == to {
+            return Some(money.clone());
+        }
+        let from_rate = self.per_inr.get(&money.currency)?;
+        let to_rate = self.per_inr.get(to)?;
+        let amount = (&money.amount / from_rate * to_rate).with_scale_round(2, RoundingMode::HalfEven);
+        Some(Money { amount, currency: to.to_string() })
+    }
+
+    /// Sum amounts in one currency, skipping any without a known rate
+    pub fn total<'a>(&self, amounts: impl IntoIterator<Item = &'a Money>, to: &str) -> Money {
+        let amount = amounts
+            .into_iter()
+            .filter_map(|m| self.convert(m, to))
+            .fold(BigDecimal::zero(), |acc, m| acc + m.amount);
+        Money { amount, currency: to.to_string() }
+    }
+}
+
+/// Add to a user's savings in the currency the saving was made in
+pub async fn record_savings(conn: &mut PgConnection, user_id: &str, saved: &Money) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO user_savings (user_id, currency, amount)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (user_id, currency) DO UPDATE
+        SET amount = user_savings.amount + EXCLUDED.amount, updated_at = NOW()
+        "#,
+        user_id,
+        saved.currency,
+        saved.amount
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// A user's savings per currency
+pub async fn user_savings<'e, E: PgExecutor<'e>>(executor: E, user_id: &str) -> Result<Vec<Money>> {
+    let rows = sqlx::query!(
+        "SELECT currency, amount FROM user_savings WHERE user_id = $1",
+        user_id
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| Money { amount: r.amount, currency: r.currency })
+        .collect())
+}
+
+/// The user's display currency, defaulting to INR
+pub async fn display_currency<'e, E: PgExecutor<'e>>(executor: E, user_id: &str) -> Result<String> {
+    let currency = sqlx::query_scalar!(
+        "SELECT display_currency FROM user_lootpack_stats WHERE user_id = $1",
+        user_id
+    )
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(currency.unwrap_or_else(|| BASE_CURRENCY.to_string()))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateRateRequest {
+    pub per_inr: BigDecimal,
+}
+
+/// Display currency preferences and admin rate management
+pub struct CurrencyService {
+    db: PgPool,
+}
+
+impl CurrencyService {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    pub async fn set_display_currency(&self, user_id: &str, currency: &str) -> Result<String> {
+        let currency = currency.to_uppercase();
+        if !RateTable::load(&self.db).await?.supports(&currency) {
+            return Err(AppError::BadRequest(format!("Unsupported currency '{}'", currency)));
+        }
+
+        let updated = sqlx::query!(
+            "UPDATE user_lootpack_stats SET display_currency = $2, updated_at = NOW() WHERE user_id = $1",
+            user_id,
+            currency
+        )
+        .execute(&self.db)
+        .await?;
+
+        if updated.rows_affected() == 0 {
+            return Err(AppError::NotFound("User stats not found".to_string()));
+        }
+        Ok(currency)
+    }
+
+    /// Set a rate, e.g. from the daily FX sync. INR is fixed at 1.
+    pub async fn upsert_rate(&self, currency: &str, req: UpdateRateRequest) -> Result<()> {
+        let currency = currency.to_uppercase();
+        if currency == BASE_CURRENCY {
+            return Err(AppError::BadRequest("The INR rate is fixed".to_string()));
+        }
+        if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_uppercase()) {
+            return Err(AppError::BadRequest(format!("Invalid currency '{}'", currency)));
+        }
+        if req.per_inr <= BigDecimal::zero() {
+            return Err(AppError::BadRequest("per_inr must be positive".to_string()));
+        }
+
+        sqlx::query!(
+            r#"
+            INSERT INTO currency_rates (currency, per_inr) VALUES ($1, $2)
+            ON CONFLICT (currency) DO UPDATE SET per_inr = EXCLUDED.per_inr, updated_at = NOW()
+            "#,
+            currency,
+            req.per_inr
+        )
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+}
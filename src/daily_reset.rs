@@ -0,0 +1,285 @@
+use crate::audit;
+use crate::error::{AppError, Result};
+use crate::markets::Market;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{PgConnection, PgPool};
+
+/// Timezone for users who haven't set one
+pub const DEFAULT_TIMEZONE: &str = "Asia/Kolkata";
+
+/// After a timezone change, the next claim also waits this long after the last one.
+/// Without it, hopping to a zone whose midnight is just ahead would unlock extra claims.
+const TIMEZONE_CHANGE_MIN_GAP_HOURS: i64 = 20;
+
+/// Bounds on the streak window; a day less would break streaks before the next day ends
+const MIN_STREAK_WINDOW_HOURS: i32 = 24;
+const MAX_STREAK_WINDOW_HOURS: i32 = 72;
+
+/// Where the user stands in the daily pack cycle
+#[derive(Debug, Clone)]
+pub struct DailyClock {
+    pub timezone: String,
+    pub last_claim: Option<DateTime<Utc>>,
+    pub last_claim_timezone: Option<String>,
+    pub next_claim_at: Option<DateTime<Utc>>,
+    pub streak_until: Option<DateTime<Utc>>,
+}
+
+impl DailyClock {
+    /// When the daily pack can next be claimed, or `None` if it can be claimed at `now`
+    pub fn next_claim(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut at = self.next_claim_at?;
+        let moved = self.last_claim_timezone.as_deref().is_some_and(|tz| tz != self.timezone);
+        if let (true, Some(last_claim)) = (moved, self.last_claim) {
+            at = at.max(last_claim + Duration::hours(TIMEZONE_CHANGE_MIN_GAP_HOURS));
+        }
+        (at > now).then_some(at)
+    }
+
+    /// A claim at `now` extends the current streak rather than starting a new one
+    pub fn continues_streak(&self, now: DateTime<Utc>) -> bool {
+        self.streak_until.is_some_and(|until| now < until)
+    }
+
+    /// When the streak lapses unless the user claims, or `None` if it already has
+    pub fn streak_deadline(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.streak_until.filter(|until| *until > now)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StreakSettings {
+    /// Hours after the claimed daily day ends that a claim still continues the streak
+    pub streak_window_hours: i32,
+}
+
+pub async fn streak_settings(conn: &mut PgConnection) -> Result<StreakSettings> {
+    let settings = sqlx::query_as!(StreakSettings, "SELECT streak_window_hours FROM streak_settings")
+        .fetch_optional(&mut *conn)
+        .await?
+        .unwrap_or(StreakSettings { streak_window_hours: MIN_STREAK_WINDOW_HOURS });
+
+    Ok(settings)
+}
+
+/// The user's clock; users who never claimed can claim right away
+pub async fn load(conn: &mut PgConnection, user_id: &str) -> Result<DailyClock> {
+    let clock = sqlx::query_as!(
+        DailyClock,
+        r#"
+        SELECT COALESCE(udc.timezone, $2) AS "timezone!", uls.last_daily_claim AS "last_claim?",
+               udc.last_claim_timezone AS "last_claim_timezone?", udc.next_claim_at AS "next_claim_at?",
+               udc.streak_until AS "streak_until?"
+        FROM (SELECT $1::text AS user_id) u
+        LEFT JOIN user_lootpack_stats uls ON uls.user_id = u.user_id
+        LEFT JOIN user_daily_clock udc ON udc.user_id = u.user_id
+        "#,
+        user_id,
+        DEFAULT_TIMEZONE
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(clock)
+}
+
+/// The local hour daily packs reset at in `market`; midnight if the market is unknown
+pub async fn reset_hour(conn: &mut PgConnection, market: &Market) -> Result<i16> {
+    let hour = sqlx::query_scalar!("SELECT daily_reset_hour FROM markets WHERE code = $1", market.code())
+        .fetch_optional(&mut *conn)
+        .await?
+        .unwrap_or(0);
+
+    Ok(hour)
+}
+
+/// Work out the windows that follow a claim at `claimed_at`, in the user's current
+/// timezone: the daily day runs from `reset_hour` to `reset_hour`, the next claim opens
+/// when the following one starts, and the streak holds for the configured window after
+/// that. Postgres resolves the local times, including days that are 23 or 25 hours long.
+pub async fn record_claim(
+    conn: &mut PgConnection,
+    user_id: &str,
+    claimed_at: DateTime<Utc>,
+    reset_hour: i16,
+) -> Result<DailyClock> {
+    sqlx::query!(
+        "INSERT INTO user_daily_clock (user_id, timezone) VALUES ($1, $2) ON CONFLICT (user_id) DO NOTHING",
+        user_id,
+        DEFAULT_TIMEZONE
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    let window = streak_settings(conn).await?.streak_window_hours;
+    let clock = sqlx::query_as!(
+        DailyClock,
+        r#"
+        UPDATE user_daily_clock SET
+            last_claim_timezone = timezone,
+            reset_hour = $3,
+            next_claim_at = (((($2::timestamptz AT TIME ZONE timezone) - make_interval(hours => $3::int))::date + 1)::timestamp
+                             + make_interval(hours => $3::int)) AT TIME ZONE timezone,
+            streak_until = (((($2::timestamptz AT TIME ZONE timezone) - make_interval(hours => $3::int))::date + 1)::timestamp
+                            + make_interval(hours => $3::int + $4)) AT TIME ZONE timezone
+        WHERE user_id = $1
+        RETURNING timezone, $2::timestamptz AS "last_claim?", last_claim_timezone AS "last_claim_timezone?",
+                  next_claim_at AS "next_claim_at?", streak_until AS "streak_until?"
+        "#,
+        user_id,
+        claimed_at,
+        reset_hour,
+        window
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(clock)
+}
+
+/// Make the daily pack claimable now and keep the streak alive as if the missed day had
+/// been claimed just now. Uses the reset hour of the last claim.
+pub async fn reopen_streak(conn: &mut PgConnection, user_id: &str) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO user_daily_clock (user_id, timezone) VALUES ($1, $2) ON CONFLICT (user_id) DO NOTHING",
+        user_id,
+        DEFAULT_TIMEZONE
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    let window = streak_settings(conn).await?.streak_window_hours;
+    sqlx::query!(
+        r#"
+        UPDATE user_daily_clock SET
+            last_claim_timezone = timezone,
+            next_claim_at = NOW(),
+            streak_until = ((((NOW() AT TIME ZONE timezone) - make_interval(hours => reset_hour))::date + 1)::timestamp
+                            + make_interval(hours => reset_hour + $2)) AT TIME ZONE timezone
+        WHERE user_id = $1
+        "#,
+        user_id,
+        window
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+pub struct TimezoneView {
+    pub timezone: String,
+    /// `None` when the daily pack can be claimed now
+    pub next_daily_claim: Option<DateTime<Utc>>,
+    pub streak_until: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetTimezoneRequest {
+    /// IANA name, e.g. "Europe/Berlin"
+    pub timezone: String,
+}
+
+pub struct DailyResetService {
+    db: PgPool,
+}
+
+impl DailyResetService {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    pub async fn get_timezone(&self, user_id: &str) -> Result<TimezoneView> {
+        let mut conn = self.db.acquire().await?;
+        let clock = load(&mut conn, user_id).await?;
+        Ok(Self::view(clock))
+    }
+
+    /// Switch the user's timezone, e.g. after travelling. Windows already running keep
+    /// their instants; the new zone applies from the next claim.
+    pub async fn set_timezone(&self, user_id: &str, req: SetTimezoneRequest) -> Result<TimezoneView> {
+        let timezone = req.timezone.trim();
+        let mut tx = self.db.begin().await?;
+        let known = sqlx::query_scalar!(
+            r#"SELECT EXISTS (SELECT 1 FROM pg_timezone_names WHERE name = $1) AS "known!""#,
+            timezone
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if !known {
+            return Err(AppError::BadRequest(format!("Unknown timezone '{}'", timezone)));
+        }
+
+        let previous = load(&mut tx, user_id).await?.timezone;
+        sqlx::query!(
+            r#"
+            INSERT INTO user_daily_clock (user_id, timezone, timezone_updated_at)
+            VALUES ($1, $2, NOW())
+            ON CONFLICT (user_id) DO UPDATE SET timezone = EXCLUDED.timezone, timezone_updated_at = NOW()
+            "#,
+            user_id,
+            timezone
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        audit::record(&mut *tx, user_id, "daily.timezone_changed", None, json!({
+            "from": previous,
+            "to": timezone,
+        }))
+        .await?;
+
+        let clock = load(&mut tx, user_id).await?;
+        tx.commit().await?;
+        Ok(Self::view(clock))
+    }
+
+    pub async fn get_streak_settings(&self) -> Result<StreakSettings> {
+        let mut conn = self.db.acquire().await?;
+        streak_settings(&mut conn).await
+    }
+
+    /// Change the streak window. Streaks already running keep their deadline; the new
+    /// window applies from each user's next claim.
+    pub async fn set_streak_settings(&self, actor: &str, settings: StreakSettings) -> Result<StreakSettings> {
+        if !(MIN_STREAK_WINDOW_HOURS..=MAX_STREAK_WINDOW_HOURS).contains(&settings.streak_window_hours) {
+            return Err(AppError::BadRequest(format!(
+                "streak_window_hours must be between {} and {}",
+                MIN_STREAK_WINDOW_HOURS, MAX_STREAK_WINDOW_HOURS
+            )));
+        }
+
+        let mut tx = self.db.begin().await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO streak_settings (id, streak_window_hours, updated_by)
+            VALUES (true, $1, $2)
+            ON CONFLICT (id) DO UPDATE SET
+                streak_window_hours = EXCLUDED.streak_window_hours, updated_by = EXCLUDED.updated_by, updated_at = NOW()
+            "#,
+            settings.streak_window_hours,
+            actor
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        audit::record(&mut *tx, actor, "daily.streak_settings_updated", None, json!({
+            "streak_window_hours": settings.streak_window_hours,
+        }))
+        .await?;
+
+        tx.commit().await?;
+        Ok(settings)
+    }
+
+    fn view(clock: DailyClock) -> TimezoneView {
+        TimezoneView {
+            next_daily_claim: clock.next_claim(Utc::now()),
+            streak_until: clock.streak_until,
+            timezone: clock.timezone,
+        }
+    }
+}
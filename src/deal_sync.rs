@@ -0,0 +1,355 @@
+use crate::error::{AppError, Result};
+use crate::http_client::HttpServiceClient;
+use crate::lootpacks::CACHE_FLUSH_CHANNEL;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+const SYNC_ACTOR: &str = "system:deal_sync";
+const WORKER_INTERVAL_SECONDS: u64 = 900;
+/// Rarity given to newly sourced templates until an admin tunes it
+const DEFAULT_SOURCED_RARITY: &str = "common";
+const MAX_SOURCED_VALIDITY_DAYS: i64 = 30;
+/// A listing with fewer deals than this share of the active sourced templates is treated as
+/// a partial response, and nothing is disabled as removed
+const MIN_LISTING_SHARE: f64 = 0.5;
+
+/// A coupon deal as the deals service lists it
+#[derive(Debug, Deserialize)]
+pub struct CouponDeal {
+    pub deal_id: String,
+    pub merchant_id: String,
+    pub brand: Option<String>,
+    pub category: Option<String>,
+    pub title: String,
+    pub description: Option<String>,
+    /// Shown as the reward value, e.g. "20%" or "₹150"
+    pub discount_label: String,
+    /// The coupon provider's offer that codes for this deal are claimed from
+    pub provider_offer_id: Option<String>,
+    /// Face value of flat-amount coupons
+    pub discount_amount: Option<BigDecimal>,
+    pub currency: Option<String>,
+    pub stock_remaining: i32,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub is_active: bool,
+}
+
+impl CouponDeal {
+    /// Why the deal can't be handed out right now, if it can't
+    fn unavailable_reason(&self, now: DateTime<Utc>) -> Option<&'static str> {
+        if self.expires_at.is_some_and(|at| at <= now) {
+            Some("expired")
+        } else if self.stock_remaining <= 0 {
+            Some("exhausted")
+        } else if !self.is_active {
+            Some("removed")
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct DealPage {
+    deals: Vec<CouponDeal>,
+    next_cursor: Option<String>,
+}
+
+/// Thin client for the deals service's coupon inventory API
+pub struct DealsClient {
+    http: HttpServiceClient,
+}
+
+impl DealsClient {
+    pub fn new(base_url: String, timeout: Duration) -> Result<Self> {
+        Ok(Self { http: HttpServiceClient::new("deals", base_url, timeout)? })
+    }
+
+    /// Every coupon deal, following pagination
+    async fn list_coupon_deals(&self) -> Result<Vec<CouponDeal>> {
+        let mut deals = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let mut request = self.http.get("/coupon-deals");
+            if let Some(cursor) = &cursor {
+                request = request.query(&[("cursor", cursor)]);
+            }
+            let response = self.http.send(request).await?;
+            if !response.status().is_success() {
+                return Err(AppError::InternalError(format!(
+                    "Deals service returned {} for coupon deals", response.status()
+                )));
+            }
+
+            let page: DealPage = response
+                .json()
+                .await
+                .map_err(|e| AppError::InternalError(format!("Invalid deals response: {}", e)))?;
+            deals.extend(page.deals);
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => return Ok(deals),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct SyncSummary {
+    pub run_id: Uuid,
+    pub deals_fetched: i32,
+    pub templates_created: i32,
+    pub templates_updated: i32,
+    pub templates_disabled: i32,
+    pub templates_enabled: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SyncRun {
+    pub id: Uuid,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub deals_fetched: i32,
+    pub templates_created: i32,
+    pub templates_updated: i32,
+    pub templates_disabled: i32,
+    pub templates_enabled: i32,
+    pub error: Option<String>,
+}
+
+/// Keeps coupon reward templates in step with live inventory in the deals service.
+/// New deals become templates that admins then map into packs; exhausted, expired and
+/// withdrawn deals take their templates out of every pool.
+pub struct DealSyncService {
+    db: PgPool,
+    client: DealsClient,
+}
+
+impl DealSyncService {
+    pub fn new(db: PgPool, client: DealsClient) -> Self {
+        Self { db, client }
+    }
+
+    pub async fn recent_runs(&self, limit: i64) -> Result<Vec<SyncRun>> {
+        let runs = sqlx::query_as!(
+            SyncRun,
+            r#"
+            SELECT id, started_at, finished_at, deals_fetched, templates_created, templates_updated,
+                   templates_disabled, templates_enabled, error
+            FROM deal_sync_runs
+            ORDER BY started_at DESC
+            LIMIT $1
+            "#,
+            limit.clamp(1, 100)
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(runs)
+    }
+
+    /// Run one sync and record it. A failed fetch changes nothing, so templates are never
+    /// disabled because the deals service was briefly unreachable.
+    pub async fn sync(&self) -> Result<SyncSummary> {
+        let run_id = sqlx::query_scalar!("INSERT INTO deal_sync_runs DEFAULT VALUES RETURNING id")
+            .fetch_one(&self.db)
+            .await?;
+
+        let result = match self.client.list_coupon_deals().await {
+            Ok(deals) => self.apply(run_id, deals).await,
+            Err(e) => Err(e),
+        };
+
+        match &result {
+            Ok(summary) => {
+                sqlx::query!(
+                    r#"
+                    UPDATE deal_sync_runs SET finished_at = NOW(), deals_fetched = $2, templates_created = $3,
+                        templates_updated = $4, templates_disabled = $5, templates_enabled = $6
+                    WHERE id = $1
+                    "#,
+                    run_id,
+                    summary.deals_fetched,
+                    summary.templates_created,
+                    summary.templates_updated,
+                    summary.templates_disabled,
+                    summary.templates_enabled
+                )
+                .execute(&self.db)
+                .await?;
+            }
+            Err(e) => {
+                sqlx::query!(
+                    "UPDATE deal_sync_runs SET finished_at = NOW(), error = $2 WHERE id = $1",
+                    run_id,
+                    format!("{:?}", e)
+                )
+                .execute(&self.db)
+                .await?;
+            }
+        }
+        result
+    }
+
+    async fn apply(&self, run_id: Uuid, deals: Vec<CouponDeal>) -> Result<SyncSummary> {
+        let now = Utc::now();
+        let mut summary = SyncSummary { run_id, deals_fetched: deals.len() as i32, ..Default::default() };
+
+        let mut tx = self.db.begin().await?;
+        sqlx::query!("SELECT set_config('app.actor', $1, true)", SYNC_ACTOR)
+            .execute(&mut *tx)
+            .await?;
+
+        for deal in &deals {
+            let reason = deal.unavailable_reason(now);
+            let validity_days = deal
+                .expires_at
+                .map(|at| (at - now).num_days().clamp(1, MAX_SOURCED_VALIDITY_DAYS) as i32);
+
+            let existing = sqlx::query!(
+                "SELECT id, is_active, source_disabled_reason FROM reward_templates WHERE source_deal_id = $1 FOR UPDATE",
+                deal.deal_id
+            )
+            .fetch_optional(&mut *tx)
+            .await?;
+
+            let Some(existing) = existing else {
+                // New templates sit in no pack until an admin maps them
+                sqlx::query!(
+                    r#"
+                    INSERT INTO reward_templates
+                        (type, title, value, description, rarity, validity_days, metadata, is_active,
+                         merchant_id, brand, category, value_amount, value_currency,
+                         source_deal_id, source_stock, source_expires_at, source_synced_at, source_disabled_reason)
+                    VALUES ('coupon', $1, $2, $3, $4, $5,
+                            jsonb_strip_nulls(jsonb_build_object('source', 'deals_service', 'provider_offer_id', $16::text)), $6,
+                            $7, $8, $9, $10, COALESCE($11, 'INR'), $12, $13, $14, NOW(), $15)
+                    "#,
+                    deal.title,
+                    deal.discount_label,
+                    deal.description,
+                    DEFAULT_SOURCED_RARITY,
+                    validity_days,
+                    reason.is_none(),
+                    deal.merchant_id,
+                    deal.brand,
+                    deal.category,
+                    deal.discount_amount,
+                    deal.currency,
+                    deal.deal_id,
+                    deal.stock_remaining,
+                    deal.expires_at,
+                    reason,
+                    deal.provider_offer_id
+                )
+                .execute(&mut *tx)
+                .await?;
+                summary.templates_created += 1;
+                continue;
+            };
+
+            // Availability follows the deal, except that templates an admin switched off stay off
+            let sync_disabled = existing.source_disabled_reason.is_some();
+            let (is_active, disabled_reason) = match reason {
+                Some(reason) if existing.is_active || sync_disabled => (false, Some(reason)),
+                None if sync_disabled => (true, None),
+                _ => (existing.is_active, None),
+            };
+            if existing.is_active && !is_active {
+                summary.templates_disabled += 1;
+            } else if !existing.is_active && is_active {
+                summary.templates_enabled += 1;
+            }
+
+            sqlx::query!(
+                r#"
+                UPDATE reward_templates SET
+                    title = $2, value = $3, description = $4, validity_days = $5, merchant_id = $6,
+                    brand = $7, category = $8, value_amount = $9, value_currency = COALESCE($10, value_currency),
+                    source_stock = $11, source_expires_at = $12, source_synced_at = NOW(),
+                    is_active = $13, source_disabled_reason = $14,
+                    metadata = CASE WHEN $15::text IS NULL THEN metadata
+                                    ELSE COALESCE(metadata, '{}'::jsonb) || jsonb_build_object('provider_offer_id', $15::text) END
+                WHERE id = $1
+                "#,
+                existing.id,
+                deal.title,
+                deal.discount_label,
+                deal.description,
+                validity_days,
+                deal.merchant_id,
+                deal.brand,
+                deal.category,
+                deal.discount_amount,
+                deal.currency,
+                deal.stock_remaining,
+                deal.expires_at,
+                is_active,
+                disabled_reason,
+                deal.provider_offer_id
+            )
+            .execute(&mut *tx)
+            .await?;
+            summary.templates_updated += 1;
+        }
+
+        // Deals the service no longer lists are gone for good, unless the listing looks truncated
+        let active_sourced = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM reward_templates WHERE source_deal_id IS NOT NULL AND is_active"#
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if deals.is_empty() || (deals.len() as f64) < active_sourced as f64 * MIN_LISTING_SHARE {
+            warn!(
+                "Deal sync listed {} deals against {} active sourced templates; skipping removal sweep",
+                deals.len(), active_sourced
+            );
+        } else {
+            let listed: Vec<String> = deals.iter().map(|d| d.deal_id.clone()).collect();
+            let removed = sqlx::query!(
+                r#"
+                UPDATE reward_templates SET is_active = false, source_disabled_reason = 'removed', source_synced_at = NOW()
+                WHERE source_deal_id IS NOT NULL AND NOT (source_deal_id = ANY($1)) AND is_active
+                "#,
+                &listed
+            )
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+            summary.templates_disabled += removed as i32;
+        }
+
+        // Drop cached pools in every instance once this commits
+        if summary.templates_updated + summary.templates_disabled > 0 {
+            sqlx::query!("SELECT pg_notify($1, '')", CACHE_FLUSH_CHANNEL)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(summary)
+    }
+
+    pub fn spawn_worker(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(WORKER_INTERVAL_SECONDS));
+            loop {
+                interval.tick().await;
+                match self.sync().await {
+                    Ok(summary) => info!(
+                        "Deal sync: {} deals, {} created, {} disabled, {} re-enabled",
+                        summary.deals_fetched, summary.templates_created,
+                        summary.templates_disabled, summary.templates_enabled
+                    ),
+                    Err(e) => error!("Deal sync failed: {:?}", e),
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,181 @@
+use crate::error::{AppError, Result};
+use crate::graphql::RequestContext;
+use crate::http_client::HttpServiceClient;
+use crate::privacy;
+use axum::body::Body;
+use axum::extract::{MatchedPath, Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+use chrono::Utc;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::warn;
+use uuid::Uuid;
+
+const REPORT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Largest error body read back from a failed response, and how much of it is reported
+const MAX_ERROR_BODY_BYTES: usize = 1024 * 1024;
+const MAX_REPORTED_DETAIL_CHARS: usize = 4096;
+
+/// Where a captured error happened. Users are identified only by a hash of their id.
+#[derive(Debug, Clone, Default)]
+pub struct ErrorContext {
+    pub method: Option<String>,
+    /// Route template, e.g. `/lootpacks/:id/open`, so reports group by endpoint
+    pub route: Option<String>,
+    pub user_hash: Option<String>,
+    pub pack_type_id: Option<Uuid>,
+    /// `x-request-id` of the failed request, to find it in other services' logs
+    pub request_id: Option<String>,
+}
+
+/// Sends errors to Sentry, or anything accepting Sentry's store API, when `SENTRY_DSN` is
+/// set. Reports go out in the background so a slow collector never slows a request.
+pub struct ErrorReporter {
+    http: HttpServiceClient,
+    store_path: String,
+    auth_header: String,
+    environment: String,
+}
+
+impl ErrorReporter {
+    /// `None` when `SENTRY_DSN` is unset, which turns reporting off
+    pub fn from_env() -> Result<Option<Arc<Self>>> {
+        let Ok(dsn) = std::env::var("SENTRY_DSN") else {
+            return Ok(None);
+        };
+        let environment = std::env::var("SENTRY_ENVIRONMENT").unwrap_or_else(|_| "production".to_string());
+        Ok(Some(Arc::new(Self::from_dsn(&dsn, environment)?)))
+    }
+
+    /// Parse `https://<key>@<host>/<project id>`
+    fn from_dsn(dsn: &str, environment: String) -> Result<Self> {
+        let url = url::Url::parse(dsn).map_err(|e| AppError::InternalError(format!("Invalid SENTRY_DSN: {}", e)))?;
+        let key = url.username();
+        let project_id = url.path().trim_matches('/');
+        let host = url.host_str();
+        let (Some(host), false, false) = (host, key.is_empty(), project_id.is_empty()) else {
+            return Err(AppError::InternalError("SENTRY_DSN needs a key, host and project id".to_string()));
+        };
+
+        let base_url = match url.port() {
+            Some(port) => format!("{}://{}:{}", url.scheme(), host, port),
+            None => format!("{}://{}", url.scheme(), host),
+        };
+        Ok(Self {
+            http: HttpServiceClient::new("error reporting", base_url, REPORT_TIMEOUT)?,
+            store_path: format!("/api/{}/store/", project_id),
+            auth_header: format!("Sentry sentry_version=7, sentry_key={}, sentry_client=lootpacks-service/0.1", key),
+            environment,
+        })
+    }
+
+    /// Report an error from a worker or any code path outside a request
+    pub fn capture_error(self: &Arc<Self>, error: &AppError, context: ErrorContext) {
+        if let AppError::InternalError(message) = error {
+            self.capture(message.clone(), context);
+        }
+    }
+
+    /// Queue a report; failures to deliver are logged, never surfaced
+    pub fn capture(self: &Arc<Self>, message: String, context: ErrorContext) {
+        let event = self.event(&message, &context);
+        let reporter = Arc::clone(self);
+        tokio::spawn(async move {
+            let request = reporter
+                .http
+                .post(&reporter.store_path)
+                .header("X-Sentry-Auth", &reporter.auth_header)
+                .json(&event);
+            match reporter.http.send_once(request).await {
+                Ok(response) if response.status().is_success() => {}
+                Ok(response) => warn!("Error report rejected with {}", response.status()),
+                Err(e) => warn!("Failed to send error report: {:?}", e),
+            }
+        });
+    }
+
+    fn event(&self, message: &str, context: &ErrorContext) -> Value {
+        json!({
+            "event_id": Uuid::new_v4().simple().to_string(),
+            "timestamp": Utc::now().to_rfc3339(),
+            "level": "error",
+            "platform": "rust",
+            "logger": "lootpacks-service",
+            "environment": self.environment,
+            "message": message,
+            "transaction": context.route,
+            "request": {"method": context.method, "url": context.route},
+            "user": context.user_hash.as_ref().map(|id| json!({"id": id})),
+            "tags": {
+                "service": "lootpacks-service",
+                "pack_type_id": context.pack_type_id.map(|id| id.to_string()),
+                "request_id": context.request_id,
+            },
+        })
+    }
+
+    /// Report panics with where they happened. Request context isn't available inside
+    /// a panic hook; the panic is still logged by the default hook.
+    pub fn install_panic_hook(self: &Arc<Self>) {
+        let reporter = Arc::clone(self);
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            let location = info.location().map(|l| format!(" at {}:{}", l.file(), l.line())).unwrap_or_default();
+            let payload = info
+                .payload()
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| info.payload().downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "non-string panic payload".to_string());
+            if tokio::runtime::Handle::try_current().is_ok() {
+                reporter.capture(format!("panic{}: {}", location, payload), ErrorContext::default());
+            }
+            default_hook(info);
+        }));
+    }
+}
+
+/// Middleware reporting every 5xx response with the request's route, hashed user, pack
+/// type and request id. Layer it inside the auth and request id middleware so both are
+/// set. The error body is copied into the report, which carries the `InternalError` message.
+pub async fn report_server_errors(
+    State(reporter): State<Arc<ErrorReporter>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let route = req.extensions().get::<MatchedPath>().map(|p| p.as_str().to_string());
+    let context = ErrorContext {
+        method: Some(req.method().to_string()),
+        pack_type_id: pack_type_from_path(req.uri().path()),
+        route,
+        user_hash: req.extensions().get::<RequestContext>().map(|c| privacy::pseudonym(&c.user_id).to_string()),
+        request_id: crate::current_request_id(),
+    };
+
+    let response = next.run(req).await;
+    if !response.status().is_server_error() {
+        return response;
+    }
+
+    let status = response.status();
+    let (parts, body) = response.into_parts();
+    let body = axum::body::to_bytes(body, MAX_ERROR_BODY_BYTES).await.unwrap_or_default();
+    let detail: String = String::from_utf8_lossy(&body).chars().take(MAX_REPORTED_DETAIL_CHARS).collect();
+    reporter.capture(
+        format!("{} on {} {}: {}", status, context.method.as_deref().unwrap_or("?"),
+                context.route.as_deref().unwrap_or("?"), detail),
+        context,
+    );
+    Response::from_parts(parts, Body::from(body))
+}
+
+/// The pack a request is about, for routes under `/lootpacks/:id`
+fn pack_type_from_path(path: &str) -> Option<Uuid> {
+    let mut segments = path.trim_start_matches('/').split('/');
+    match (segments.next(), segments.next()) {
+        (Some("lootpacks"), Some(id)) => Uuid::parse_str(id).ok(),
+        _ => None,
+    }
+}
@@ -0,0 +1,233 @@
+use crate::assets::AssetStorage;
+use crate::error::{AppError, Result};
+use crate::privacy;
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::{error, info};
+
+/// Streams exported, one object per stream per hour
+const STREAMS: &[&str] = &["pack_opens", "reward_grants", "redemptions"];
+
+/// How long after an hour ends before it is exported. Timestamps are taken when a row is
+/// written, so this leaves time for transactions still open at the boundary to commit.
+const SETTLE_MINUTES: i64 = 10;
+/// Hours exported per stream per run, so catching up after an outage is spread out
+const MAX_HOURS_PER_RUN: i64 = 24;
+const WORKER_INTERVAL_SECONDS: u64 = 300;
+
+/// One line of an exported file
+#[derive(Debug, Serialize)]
+struct ExportedEvent {
+    event_type: &'static str,
+    occurred_at: DateTime<Utc>,
+    /// `privacy::pseudonym` of the user; raw ids never leave the database
+    user_hash: String,
+    #[serde(flatten)]
+    data: Value,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StreamCheckpoint {
+    pub stream: String,
+    pub exported_through: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Uploads pack opens, reward grants and redemptions as hourly JSON-lines files, keyed
+/// `events/<stream>/dt=<date>/hour=<hh>.jsonl`. The checkpoint only moves after an upload
+/// succeeds, so a failed or interrupted run retries the same hour.
+pub struct EventExportService {
+    db: PgPool,
+    storage: Arc<dyn AssetStorage>,
+}
+
+impl EventExportService {
+    pub fn new(db: PgPool, storage: Arc<dyn AssetStorage>) -> Self {
+        Self { db, storage }
+    }
+
+    pub async fn checkpoints(&self) -> Result<Vec<StreamCheckpoint>> {
+        let checkpoints = sqlx::query_as!(
+            StreamCheckpoint,
+            "SELECT stream, exported_through, updated_at FROM event_export_checkpoints ORDER BY stream"
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(checkpoints)
+    }
+
+    /// Export every settled hour not yet exported. Returns the number of files uploaded.
+    pub async fn run(&self) -> Result<usize> {
+        let settled_until = (Utc::now() - Duration::minutes(SETTLE_MINUTES))
+            .duration_trunc(Duration::hours(1))
+            .map_err(|e| AppError::InternalError(format!("Failed to truncate export time: {}", e)))?;
+
+        let mut uploaded = 0;
+        for stream in STREAMS {
+            let mut from = sqlx::query_scalar!(
+                "SELECT exported_through FROM event_export_checkpoints WHERE stream = $1",
+                stream
+            )
+            .fetch_one(&self.db)
+            .await?;
+
+            for _ in 0..MAX_HOURS_PER_RUN {
+                let until = from + Duration::hours(1);
+                if until > settled_until {
+                    break;
+                }
+                self.export_hour(stream, from, until).await?;
+                sqlx::query!(
+                    r#"
+                    UPDATE event_export_checkpoints SET exported_through = $2, updated_at = NOW()
+                    WHERE stream = $1 AND exported_through = $3
+                    "#,
+                    stream,
+                    until,
+                    from
+                )
+                .execute(&self.db)
+                .await?;
+                uploaded += 1;
+                from = until;
+            }
+        }
+        Ok(uploaded)
+    }
+
+    async fn export_hour(&self, stream: &str, from: DateTime<Utc>, until: DateTime<Utc>) -> Result<()> {
+        let events = match stream {
+            "pack_opens" => self.pack_opens(from, until).await?,
+            "reward_grants" => self.reward_grants(from, until).await?,
+            "redemptions" => self.redemptions(from, until).await?,
+            other => return Err(AppError::InternalError(format!("Unknown export stream '{}'", other))),
+        };
+
+        // Empty hours still get a file so the warehouse can tell "no events" from "not exported"
+        let mut body = Vec::new();
+        for event in &events {
+            serde_json::to_writer(&mut body, event)
+                .map_err(|e| AppError::InternalError(format!("Failed to encode event: {}", e)))?;
+            body.push(b'\n');
+        }
+
+        let key = format!("events/{}/dt={}/hour={}.jsonl", stream, from.format("%Y-%m-%d"), from.format("%H"));
+        self.storage.put(&key, "application/x-ndjson", body).await?;
+        info!("Exported {} {} events to {}", events.len(), stream, key);
+        Ok(())
+    }
+
+    async fn pack_opens(&self, from: DateTime<Utc>, until: DateTime<Utc>) -> Result<Vec<ExportedEvent>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT id, user_id, pack_type_id, rewards_count, total_value_inr, opened_at AS "opened_at!"
+            FROM user_pack_history
+            WHERE opened_at >= $1 AND opened_at < $2
+            ORDER BY opened_at, id
+            "#,
+            from,
+            until
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|r| ExportedEvent {
+                event_type: "pack_opened",
+                occurred_at: r.opened_at,
+                user_hash: privacy::pseudonym(&r.user_id).to_string(),
+                data: serde_json::json!({
+                    "pack_history_id": r.id,
+                    "pack_type_id": r.pack_type_id,
+                    "rewards_count": r.rewards_count,
+                    "total_value_inr": r.total_value_inr,
+                }),
+            })
+            .collect())
+    }
+
+    async fn reward_grants(&self, from: DateTime<Utc>, until: DateTime<Utc>) -> Result<Vec<ExportedEvent>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT id, user_id, pack_history_id, template_id, type, rarity, source, merchant_id,
+                   value_amount, value_currency, created_at AS "created_at!"
+            FROM user_rewards
+            WHERE created_at >= $1 AND created_at < $2
+            ORDER BY created_at, id
+            "#,
+            from,
+            until
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|r| ExportedEvent {
+                event_type: "reward_granted",
+                occurred_at: r.created_at,
+                user_hash: privacy::pseudonym(&r.user_id).to_string(),
+                data: serde_json::json!({
+                    "reward_id": r.id,
+                    "pack_history_id": r.pack_history_id,
+                    "template_id": r.template_id,
+                    "reward_type": r.r#type,
+                    "rarity": r.rarity,
+                    "source": r.source,
+                    "merchant_id": r.merchant_id,
+                    "value_amount": r.value_amount,
+                    "value_currency": r.value_currency,
+                }),
+            })
+            .collect())
+    }
+
+    async fn redemptions(&self, from: DateTime<Utc>, until: DateTime<Utc>) -> Result<Vec<ExportedEvent>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT id, user_id, type, rarity, merchant_id, value_amount, value_currency, used_at AS "used_at!"
+            FROM user_rewards
+            WHERE used_at >= $1 AND used_at < $2
+            ORDER BY used_at, id
+            "#,
+            from,
+            until
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|r| ExportedEvent {
+                event_type: "reward_redeemed",
+                occurred_at: r.used_at,
+                user_hash: privacy::pseudonym(&r.user_id).to_string(),
+                data: serde_json::json!({
+                    "reward_id": r.id,
+                    "reward_type": r.r#type,
+                    "rarity": r.rarity,
+                    "merchant_id": r.merchant_id,
+                    "value_amount": r.value_amount,
+                    "value_currency": r.value_currency,
+                }),
+            })
+            .collect())
+    }
+
+    pub fn spawn_worker(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(WORKER_INTERVAL_SECONDS));
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.run().await {
+                    error!("Event export failed: {:?}", e);
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,307 @@
+use crate::error::{AppError, Result};
+use crate::rng::RngProvider;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{PgConnection, PgPool};
+use std::sync::Mutex;
+use uuid::Uuid;
+
+/// Longest client seed accepted; it's echoed back in every proof
+const MAX_CLIENT_SEED_LEN: usize = 64;
+
+/// Commitments not used within this window can no longer open a pack
+const COMMITMENT_TTL_HOURS: i32 = 24;
+
+pub fn hash_seed(server_seed: &str) -> String {
+    format!("{:x}", Sha256::digest(server_seed.as_bytes()))
+}
+
+/// The `cursor`-th roll in `low..=high`: HMAC-SHA256(server_seed, "client_seed:cursor"),
+/// first 8 bytes big-endian, reduced modulo the range size
+pub fn derive_roll(server_seed: &str, client_seed: &str, cursor: u32, low: i64, high: i64) -> i64 {
+    let mut mac = Hmac::<Sha256>::new_from_slice(server_seed.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(format!("{}:{}", client_seed, cursor).as_bytes());
+    let digest = mac.finalize().into_bytes();
+    let mut first = [0u8; 8];
+    first.copy_from_slice(&digest[..8]);
+
+    let span = (high - low + 1).max(1) as u64;
+    low + (u64::from_be_bytes(first) % span) as i64
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Roll {
+    pub cursor: u32,
+    pub low: i64,
+    pub high: i64,
+    pub value: i64,
+}
+
+/// Deterministic randomness from a committed server seed and the user's client seed.
+/// Every value handed out is logged so the open can be replayed.
+pub struct FairRng {
+    server_seed: String,
+    client_seed: String,
+    rolls: Mutex<Vec<Roll>>,
+}
+
+impl FairRng {
+    pub fn new(server_seed: String, client_seed: String) -> Self {
+        Self { server_seed, client_seed, rolls: Mutex::new(Vec::new()) }
+    }
+
+    fn next(&self, low: i64, high: i64) -> i64 {
+        let mut rolls = self.rolls.lock().unwrap();
+        let cursor = rolls.len() as u32;
+        let value = derive_roll(&self.server_seed, &self.client_seed, cursor, low, high);
+        rolls.push(Roll { cursor, low, high, value });
+        value
+    }
+
+    /// Reveal the seeds along with the rolls made from them
+    pub fn into_proof(self, pool: Vec<PoolEntry>, guarantee_candidates: Vec<Uuid>, dealt: Vec<Uuid>) -> FairnessProof {
+        FairnessProof {
+            server_seed_hash: hash_seed(&self.server_seed),
+            server_seed: self.server_seed,
+            client_seed: self.client_seed,
+            derivation: Derivation {
+                pool,
+                guarantee_candidates,
+                rolls: self.rolls.into_inner().unwrap(),
+                dealt,
+            },
+        }
+    }
+}
+
+impl RngProvider for FairRng {
+    fn gen_range(&self, low: i32, high: i32) -> i32 {
+        self.next(low as i64, high as i64) as i32
+    }
+
+    fn gen_index(&self, len: usize) -> usize {
+        self.next(0, len as i64 - 1) as usize
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolEntry {
+    pub template_id: Uuid,
+    pub weight: i32,
+    pub cumulative_weight: i32,
+}
+
+/// Everything needed to replay an open: the weighted pool as drawn from (after odds,
+/// personalization and happy-hour adjustments), the rare-guarantee candidates if the
+/// pack has one, the rolls, and the templates dealt.
+///
+/// Replay: roll 0 is the reward count; with candidates, the next roll indexes into them;
+/// each remaining roll `r` picks the first pool entry with `cumulative_weight >= r`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Derivation {
+    pub pool: Vec<PoolEntry>,
+    pub guarantee_candidates: Vec<Uuid>,
+    pub rolls: Vec<Roll>,
+    pub dealt: Vec<Uuid>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FairnessProof {
+    pub server_seed: String,
+    pub server_seed_hash: String,
+    pub client_seed: String,
+    pub derivation: Derivation,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Verification {
+    pub seed_matches_commitment: bool,
+    pub rolls_match_seeds: bool,
+    /// Templates the replay picks from the recorded pool
+    pub replayed: Vec<Uuid>,
+    pub dealt_matches_replay: bool,
+    pub verified: bool,
+}
+
+/// Recompute a proof from scratch; needs nothing but the proof itself
+pub fn verify(proof: &FairnessProof) -> Verification {
+    let derivation = &proof.derivation;
+    let seed_matches_commitment = hash_seed(&proof.server_seed) == proof.server_seed_hash;
+    let rolls_match_seeds = derivation.rolls.iter().enumerate().all(|(i, roll)| {
+        roll.cursor as usize == i
+            && derive_roll(&proof.server_seed, &proof.client_seed, roll.cursor, roll.low, roll.high) == roll.value
+    });
+
+    let mut rolls = derivation.rolls.iter();
+    let mut replayed = Vec::new();
+    if let Some(count) = rolls.next() {
+        if !derivation.guarantee_candidates.is_empty() {
+            if let Some(roll) = rolls.next() {
+                if let Some(id) = derivation.guarantee_candidates.get(roll.value as usize) {
+                    replayed.push(*id);
+                }
+            }
+        }
+        for roll in rolls.take((count.value as usize).saturating_sub(replayed.len())) {
+            if let Some(entry) = derivation.pool.iter().find(|e| e.cumulative_weight as i64 >= roll.value) {
+                replayed.push(entry.template_id);
+            }
+        }
+    }
+
+    let dealt_matches_replay = replayed == derivation.dealt;
+    Verification {
+        seed_matches_commitment,
+        rolls_match_seeds,
+        verified: seed_matches_commitment && rolls_match_seeds && dealt_matches_replay,
+        replayed,
+        dealt_matches_replay,
+    }
+}
+
+/// Client seed for an open; defaults to a random one when the user doesn't supply it
+pub fn client_seed(requested: Option<&str>) -> Result<String> {
+    match requested.map(str::trim).filter(|s| !s.is_empty()) {
+        Some(seed) if seed.len() > MAX_CLIENT_SEED_LEN => Err(AppError::BadRequest(format!(
+            "client_seed is limited to {} characters", MAX_CLIENT_SEED_LEN
+        ))),
+        Some(seed) => Ok(seed.to_string()),
+        None => Ok(random_hex(16)),
+    }
+}
+
+fn random_hex(bytes: usize) -> String {
+    let mut buf = vec![0u8; bytes];
+    rand::rngs::OsRng.fill_bytes(&mut buf);
+    buf.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Consume the user's unused commitment and return its server seed
+pub async fn take_commitment(conn: &mut PgConnection, user_id: &str, commitment_id: Uuid) -> Result<String> {
+    let seed = sqlx::query_scalar!(
+        r#"
+        UPDATE fairness_commitments SET used_at = NOW()
+        WHERE id = $1 AND user_id = $2 AND used_at IS NULL
+          AND created_at > NOW() - make_interval(hours => $3)
+        RETURNING server_seed
+        "#,
+        commitment_id,
+        user_id,
+        COMMITMENT_TTL_HOURS
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or_else(|| AppError::BadRequest("Fairness commitment is unknown, used or expired".to_string()))?;
+
+    Ok(seed)
+}
+
+/// Store the revealed proof on the history row
+pub async fn record_proof(
+    conn: &mut PgConnection,
+    commitment_id: Uuid,
+    pack_history_id: Uuid,
+    proof: &FairnessProof,
+) -> Result<()> {
+    let derivation = serde_json::to_value(&proof.derivation)
+        .map_err(|e| AppError::InternalError(format!("Failed to encode derivation: {}", e)))?;
+
+    sqlx::query!(
+        r#"
+        UPDATE user_pack_history
+        SET server_seed = $2, server_seed_hash = $3, client_seed = $4, fairness_derivation = $5
+        WHERE id = $1
+        "#,
+        pack_history_id,
+        proof.server_seed,
+        proof.server_seed_hash,
+        proof.client_seed,
+        derivation
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        "UPDATE fairness_commitments SET pack_history_id = $2 WHERE id = $1",
+        commitment_id,
+        pack_history_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+pub struct Commitment {
+    pub commitment_id: Uuid,
+    pub server_seed_hash: String,
+}
+
+/// Caller's choice of seeds for a provably fair open
+#[derive(Debug, Clone, Deserialize)]
+pub struct FairOpenRequest {
+    pub commitment_id: Uuid,
+    pub client_seed: Option<String>,
+}
+
+pub struct FairnessService {
+    db: PgPool,
+}
+
+impl FairnessService {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    /// Pick a fresh server seed and publish only its hash
+    pub async fn commit(&self, user_id: &str) -> Result<Commitment> {
+        let server_seed = random_hex(32);
+        let server_seed_hash = hash_seed(&server_seed);
+
+        let commitment_id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO fairness_commitments (user_id, server_seed, server_seed_hash)
+            VALUES ($1, $2, $3)
+            RETURNING id
+            "#,
+            user_id,
+            server_seed,
+            server_seed_hash
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(Commitment { commitment_id, server_seed_hash })
+    }
+
+    /// Replay a provably fair open from its stored proof
+    pub async fn verify_open(&self, user_id: &str, pack_history_id: Uuid) -> Result<(FairnessProof, Verification)> {
+        let row = sqlx::query!(
+            r#"
+            SELECT server_seed AS "server_seed!", server_seed_hash AS "server_seed_hash!",
+                   client_seed AS "client_seed!", fairness_derivation AS "fairness_derivation!"
+            FROM user_pack_history
+            WHERE id = $1 AND user_id = $2 AND fairness_derivation IS NOT NULL
+            "#,
+            pack_history_id,
+            user_id
+        )
+        .fetch_optional(&self.db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("No provably fair open with this id".to_string()))?;
+
+        let derivation: Derivation = serde_json::from_value(row.fairness_derivation)
+            .map_err(|e| AppError::InternalError(format!("Stored derivation is invalid: {}", e)))?;
+        let proof = FairnessProof {
+            server_seed: row.server_seed,
+            server_seed_hash: row.server_seed_hash,
+            client_seed: row.client_seed,
+            derivation,
+        };
+        let verification = verify(&proof);
+        Ok((proof, verification))
+    }
+}
@@ -0,0 +1,176 @@
+use crate::error::{AppError, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// How long a replica trusts its cached copy of a flag. Admin changes only invalidate
+/// the replica that served them; the others pick them up within this window.
+const FLAG_CACHE_TTL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, FromRow, Deserialize, Serialize)]
+pub struct FeatureFlag {
+    pub key: String,
+    pub description: Option<String>,
+    pub enabled: bool,
+    pub rollout_percentage: i32,
+    pub allowlist: Vec<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpsertFeatureFlagRequest {
+    pub description: Option<String>,
+    pub enabled: bool,
+    pub rollout_percentage: Option<i32>,
+    pub allowlist: Option<Vec<String>>,
+}
+
+impl FeatureFlag {
+    /// A disabled flag is off for everyone, so it works as a kill switch. Otherwise
+    /// allowlisted users always get the feature and everyone else is bucketed
+    /// deterministically so a user's result is stable across requests.
+    pub fn evaluate(&self, user_id: &str) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        if self.allowlist.iter().any(|u| u == user_id) {
+            return true;
+        }
+        rollout_bucket(&self.key, user_id) < self.rollout_percentage.clamp(0, 100) as u32
+    }
+}
+
+/// Stable 0..100 bucket for a (flag, user) pair using FNV-1a.
+fn rollout_bucket(flag_key: &str, user_id: &str) -> u32 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in flag_key.bytes().chain(std::iter::once(b':')).chain(user_id.bytes()) {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    (hash % 100) as u32
+}
+
+pub struct FeatureFlagService {
+    db: PgPool,
+    flag_cache: tokio::sync::RwLock<HashMap<String, (Instant, Option<FeatureFlag>)>>, // Flags are read on every request
+}
+
+impl FeatureFlagService {
+    pub fn new(db: PgPool) -> Self {
+        Self {
+            db,
+            flag_cache: tokio::sync::RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Check whether a feature is enabled for a user. Unknown flags are off.
+    pub async fn is_enabled(&self, user_id: &str, flag_key: &str) -> Result<bool> {
+        Ok(self
+            .get_flag(flag_key)
+            .await?
+            .map(|flag| flag.evaluate(user_id))
+            .unwrap_or(false))
+    }
+
+    /// Fail with BadRequest when a feature is disabled for the user
+    pub async fn require_enabled(&self, user_id: &str, flag_key: &str) -> Result<()> {
+        if self.is_enabled(user_id, flag_key).await? {
+            Ok(())
+        } else {
+            Err(AppError::BadRequest(format!("Feature '{}' is not available", flag_key)))
+        }
+    }
+
+    /// List all flags for the admin console
+    pub async fn list_flags(&self) -> Result<Vec<FeatureFlag>> {
+        let flags = sqlx::query_as!(
+            FeatureFlag,
+            r#"
+            SELECT key, description, enabled, rollout_percentage, allowlist, created_at, updated_at
+            FROM feature_flags
+            ORDER BY key
+            "#
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(flags)
+    }
+
+    /// Create or update a flag
+    pub async fn upsert_flag(&self, key: &str, req: UpsertFeatureFlagRequest) -> Result<FeatureFlag> {
+        let rollout_percentage = req.rollout_percentage.unwrap_or(if req.enabled { 100 } else { 0 });
+        if !(0..=100).contains(&rollout_percentage) {
+            return Err(AppError::BadRequest(
+                "rollout_percentage must be between 0 and 100".to_string()
+            ));
+        }
+
+        let flag = sqlx::query_as!(
+            FeatureFlag,
+            r#"
+            INSERT INTO feature_flags (key, description, enabled, rollout_percentage, allowlist)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (key) DO UPDATE
+            SET description = EXCLUDED.description, enabled = EXCLUDED.enabled,
+                rollout_percentage = EXCLUDED.rollout_percentage, allowlist = EXCLUDED.allowlist,
+                updated_at = NOW()
+            RETURNING key, description, enabled, rollout_percentage, allowlist, created_at, updated_at
+            "#,
+            key,
+            req.description,
+            req.enabled,
+            rollout_percentage,
+            &req.allowlist.unwrap_or_default()
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        self.flag_cache.write().await.remove(key);
+        Ok(flag)
+    }
+
+    /// Delete a flag
+    pub async fn delete_flag(&self, key: &str) -> Result<()> {
+        let result = sqlx::query!("DELETE FROM feature_flags WHERE key = $1", key)
+            .execute(&self.db)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound("Feature flag not found".to_string()));
+        }
+
+        self.flag_cache.write().await.remove(key);
+        Ok(())
+    }
+
+    async fn get_flag(&self, key: &str) -> Result<Option<FeatureFlag>> {
+        {
+            let cache = self.flag_cache.read().await;
+            if let Some((fetched, flag)) = cache.get(key) {
+                if fetched.elapsed() < FLAG_CACHE_TTL {
+                    return Ok(flag.clone());
+                }
+            }
+        }
+
+        let flag = sqlx::query_as!(
+            FeatureFlag,
+            r#"
+            SELECT key, description, enabled, rollout_percentage, allowlist, created_at, updated_at
+            FROM feature_flags
+            WHERE key = $1
+            "#,
+            key
+        )
+        .fetch_optional(&self.db)
+        .await?;
+
+        self.flag_cache.write().await.insert(key.to_string(), (Instant::now(), flag.clone()));
+
+        Ok(flag)
+    }
+}
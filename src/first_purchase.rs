@@ -0,0 +1,78 @@
+use crate::error::Result;
+use crate::user_packs;
+use sqlx::{PgConnection, PgExecutor};
+use uuid::Uuid;
+
+/// What paid for the first purchase: an immediate open or a pack bought for later
+pub enum Purchase {
+    Open(Uuid),
+    Inventory(Uuid),
+}
+
+/// Whether the user's next coin purchase would earn the first-purchase bonus
+pub async fn eligible<'e, E: PgExecutor<'e>>(executor: E, user_id: &str) -> Result<bool> {
+    let purchased = sqlx::query_scalar!(
+        r#"SELECT EXISTS (SELECT 1 FROM first_purchases WHERE user_id = $1) AS "purchased!""#,
+        user_id
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(!purchased)
+}
+
+/// Flag the user's first coin purchase and grant a bonus pack of the same type to their
+/// inventory. Runs in the purchase's transaction after the user's stats row is locked;
+/// returns the bonus pack, or `None` if the user already had their first purchase.
+pub async fn claim(conn: &mut PgConnection, user_id: &str, pack_type_id: Uuid, purchase: Purchase) -> Result<Option<Uuid>> {
+    let (pack_history_id, user_pack_id) = match purchase {
+        Purchase::Open(id) => (Some(id), None),
+        Purchase::Inventory(id) => (None, Some(id)),
+    };
+    let flagged = sqlx::query!(
+        r#"
+        INSERT INTO first_purchases (user_id, pack_type_id, pack_history_id, user_pack_id)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (user_id) DO NOTHING
+        "#,
+        user_id,
+        pack_type_id,
+        pack_history_id,
+        user_pack_id
+    )
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+    if flagged == 0 {
+        return Ok(None);
+    }
+
+    let bonus = user_packs::grant(conn, user_id, pack_type_id, "first_purchase_bonus", None).await?;
+    sqlx::query!("UPDATE first_purchases SET bonus_user_pack_id = $2 WHERE user_id = $1", user_id, bonus)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(Some(bonus))
+}
+
+/// The open behind a first purchase was refunded: take back the bonus pack if it hasn't
+/// been opened. A pack bought for later counts once it's opened into `pack_history_id`.
+/// The flag stays, so the refund doesn't make the user eligible again.
+pub async fn revoke_bonus(conn: &mut PgConnection, pack_history_id: Uuid) -> Result<()> {
+    sqlx::query!(
+        r#"
+        UPDATE user_packs SET status = 'expired'
+        WHERE status = 'unopened'
+          AND id IN (
+              SELECT bonus_user_pack_id FROM first_purchases
+              WHERE pack_history_id = $1
+                 OR user_pack_id IN (SELECT id FROM user_packs WHERE pack_history_id = $1)
+          )
+        "#,
+        pack_history_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,115 @@
+use crate::error::{AppError, Result};
+use chrono::{DateTime, Datelike, Months, NaiveDate, TimeZone, Utc};
+use serde::Serialize;
+use sqlx::PgConnection;
+
+/// `user_packs.source` values that aren't free: bought, bundled with a purchase or
+/// subscription, or compensation from support. Every other granted pack counts.
+const PAID_SOURCES: &[&str] = &["purchase", "first_purchase_bonus", "vip", "compensation"];
+
+/// How much of this month's free-pack allowance the user has left
+#[derive(Debug, Serialize)]
+pub struct FreeClaimQuota {
+    pub member_status: String,
+    /// `None` when the user's tier has no monthly limit
+    pub monthly_limit: Option<i32>,
+    pub claimed: i32,
+    pub remaining: Option<i32>,
+    pub resets_at: DateTime<Utc>,
+}
+
+/// Whether opening a granted pack from this source uses the monthly allowance
+pub fn counts_toward_quota(source: &str) -> bool {
+    !PAID_SOURCES.contains(&source)
+}
+
+/// First day of the current month; quotas follow UTC calendar months
+fn current_month(now: DateTime<Utc>) -> NaiveDate {
+    now.date_naive().with_day(1).expect("every month has a first day")
+}
+
+fn resets_at(month: NaiveDate) -> DateTime<Utc> {
+    let next = month + Months::new(1);
+    Utc.from_utc_datetime(&next.and_hms_opt(0, 0, 0).expect("midnight exists"))
+}
+
+async fn tier_limit(conn: &mut PgConnection, user_id: &str) -> Result<(String, Option<i32>)> {
+    let row = sqlx::query!(
+        r#"
+        SELECT COALESCE(uls.member_status, 'Bronze') AS "member_status!", l.monthly_limit AS "monthly_limit?"
+        FROM (SELECT $1::text AS user_id) u
+        LEFT JOIN user_lootpack_stats uls ON uls.user_id = u.user_id
+        LEFT JOIN member_tier_free_claim_limits l ON l.member_status = COALESCE(uls.member_status, 'Bronze')
+        "#,
+        user_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok((row.member_status, row.monthly_limit))
+}
+
+pub async fn view(conn: &mut PgConnection, user_id: &str) -> Result<FreeClaimQuota> {
+    let month = current_month(Utc::now());
+    let (member_status, monthly_limit) = tier_limit(conn, user_id).await?;
+    let claimed = sqlx::query_scalar!(
+        "SELECT claimed FROM free_claim_usage WHERE user_id = $1 AND month = $2",
+        user_id,
+        month
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    .unwrap_or(0);
+
+    Ok(FreeClaimQuota {
+        member_status,
+        monthly_limit,
+        claimed,
+        remaining: monthly_limit.map(|limit| (limit - claimed).max(0)),
+        resets_at: resets_at(month),
+    })
+}
+
+/// Use one free claim from this month's allowance. Returns false, using nothing, when
+/// the allowance is spent. Runs in the claiming transaction, so a failed claim gives it back.
+pub async fn try_consume(conn: &mut PgConnection, user_id: &str) -> Result<bool> {
+    let month = current_month(Utc::now());
+    let (_, monthly_limit) = tier_limit(conn, user_id).await?;
+    if monthly_limit == Some(0) {
+        return Ok(false);
+    }
+
+    // The conditional upsert counts and checks in one statement, so concurrent claims
+    // can't both take the last one
+    let claimed = sqlx::query_scalar!(
+        r#"
+        INSERT INTO free_claim_usage (user_id, month, claimed)
+        VALUES ($1, $2, 1)
+        ON CONFLICT (user_id, month) DO UPDATE
+            SET claimed = free_claim_usage.claimed + 1, updated_at = NOW()
+            WHERE $3::int IS NULL OR free_claim_usage.claimed < $3
+        RETURNING claimed
+        "#,
+        user_id,
+        month,
+        monthly_limit
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(claimed.is_some())
+}
+
+/// `try_consume`, rejecting the claim once the allowance is spent
+pub async fn consume(conn: &mut PgConnection, user_id: &str) -> Result<()> {
+    if try_consume(conn, user_id).await? {
+        return Ok(());
+    }
+
+    let quota = view(conn, user_id).await?;
+    Err(AppError::BadRequest(format!(
+        "You've claimed all {} free packs for this month; more unlock on {}",
+        quota.monthly_limit.unwrap_or(0),
+        quota.resets_at.format("%B %-d")
+    )))
+}
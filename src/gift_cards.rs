@@ -0,0 +1,396 @@
+use crate::error::{AppError, Result};
+use crate::audit;
+use crate::code_crypto::CodeCipher;
+use crate::currency::{self, Money};
+use crate::http_client::HttpServiceClient;
+use crate::notifications::{self, NotificationEvent};
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{PgConnection, PgPool};
+use std::sync::Arc;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// Submissions give up after this many failed attempts; an admin can retry by hand
+const MAX_FULFILLMENT_ATTEMPTS: i32 = 6;
+const FULFILLMENT_BATCH_SIZE: i64 = 50;
+const WORKER_INTERVAL_SECONDS: u64 = 30;
+/// How long a claimed order is left alone while its submission is in flight
+const SUBMISSION_LEASE_SECONDS: f64 = 300.0;
+
+/// Exponential backoff between attempts: 2, 4, 8, ... minutes
+fn retry_delay(attempts: i32) -> Duration {
+    Duration::minutes(1i64 << attempts.clamp(1, 10))
+}
+
+#[derive(Debug, Serialize)]
+pub struct GiftCardStatus {
+    pub reward_id: Uuid,
+    /// pending, submitted, delivered or failed
+    pub fulfillment_status: String,
+    pub attempts: i32,
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    pub error: Option<String>,
+}
+
+/// Callback body sent by the fulfillment provider when an order settles
+#[derive(Debug, Deserialize)]
+pub struct FulfillmentWebhook {
+    pub reference: String,
+    pub status: String, // delivered | failed
+    /// The card's claim code, on delivery
+    pub claim_code: Option<String>,
+    pub failure_reason: Option<String>,
+}
+
+#[derive(Serialize)]
+struct GiftCardOrder<'a> {
+    provider: &'a str,
+    user_id: &'a str,
+    amount: String,
+    currency: &'a str,
+    idempotency_key: String,
+}
+
+#[derive(Deserialize)]
+struct OrderAccepted {
+    reference: String,
+}
+
+/// Thin client for the gift card fulfillment gateway, which fronts the individual
+/// card providers (Amazon, Flipkart, ...)
+pub struct FulfillmentClient {
+    http: HttpServiceClient,
+}
+
+impl FulfillmentClient {
+    pub fn new(base_url: String, timeout: std::time::Duration) -> Result<Self> {
+        Ok(Self { http: HttpServiceClient::new("fulfillment", base_url, timeout)? })
+    }
+
+    async fn submit_order(
+        &self,
+        idempotency_key: Uuid,
+        provider: &str,
+        user_id: &str,
+        amount: &BigDecimal,
+        currency: &str,
+    ) -> Result<String> {
+        let request = self.http.post("/orders").json(&GiftCardOrder {
+            provider,
+            user_id,
+            amount: amount.to_string(),
+            currency,
+            idempotency_key: format!("gift-card-{}", idempotency_key),
+        });
+        let response = self.http.send(request).await?;
+
+        if !response.status().is_success() {
+            return Err(AppError::InternalError(format!(
+                "Fulfillment gateway returned {} for order", response.status()
+            )));
+        }
+
+        let accepted: OrderAccepted = response
+            .json()
+            .await
+            .map_err(|e| AppError::InternalError(format!("Invalid fulfillment response: {}", e)))?;
+
+        Ok(accepted.reference)
+    }
+}
+
+/// Gift card redemption. Redeeming only queues the order; the worker submits it to the
+/// provider, retrying with backoff, and the provider's webhook marks it delivered.
+pub struct GiftCardService {
+    db: PgPool,
+    client: FulfillmentClient,
+    codes: Arc<CodeCipher>,
+}
+
+impl GiftCardService {
+    pub fn new(db: PgPool, client: FulfillmentClient, codes: Arc<CodeCipher>) -> Self {
+        Self { db, client, codes }
+    }
+
+    pub async fn get_status(&self, user_id: &str, reward_id: Uuid) -> Result<GiftCardStatus> {
+        let status = sqlx::query_as!(
+            GiftCardStatus,
+            r#"
+            SELECT id AS reward_id, fulfillment_status AS "fulfillment_status!",
+                   fulfillment_attempts AS attempts, fulfillment_next_attempt_at AS next_attempt_at,
+                   fulfillment_error AS error
+            FROM user_rewards
+            WHERE id = $1 AND user_id = $2 AND fulfillment_status IS NOT NULL
+            "#,
+            reward_id,
+            user_id
+        )
+        .fetch_optional(&self.db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("No gift card order for this reward".to_string()))?;
+
+        Ok(status)
+    }
+
+    /// Queue a gift card for fulfillment. Repeat calls return the existing order.
+    pub async fn redeem(&self, user_id: &str, reward_id: Uuid) -> Result<GiftCardStatus> {
+        let mut tx = self.db.begin().await?;
+
+        let reward = sqlx::query!(
+            r#"
+            SELECT type, is_used, expires_at, reserved_until, escrow_trade_id,
+                   value_amount, fulfillment_status
+            FROM user_rewards
+            WHERE id = $1 AND user_id = $2
+            FOR UPDATE
+            "#,
+            reward_id,
+            user_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Reward not found".to_string()))?;
+
+        if reward.r#type != "gift_card" {
+            return Err(AppError::BadRequest("Reward is not a gift card".to_string()));
+        }
+        if reward.fulfillment_status.is_some() {
+            drop(tx);
+            return self.get_status(user_id, reward_id).await;
+        }
+        if reward.is_used.unwrap_or(false) {
+            return Err(AppError::BadRequest("Gift card already redeemed".to_string()));
+        }
+        if reward.expires_at.map(|exp| exp <= Utc::now()).unwrap_or(false) {
+            return Err(AppError::BadRequest("Reward has expired".to_string()));
+        }
+        if reward.reserved_until.map(|until| until > Utc::now()).unwrap_or(false) || reward.escrow_trade_id.is_some() {
+            return Err(AppError::BadRequest("Reward is on hold and can't be redeemed right now".to_string()));
+        }
+        if reward.value_amount.is_none() {
+            return Err(AppError::InternalError(format!("Gift card reward {} has no value", reward_id)));
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE user_rewards
+            SET fulfillment_status = 'pending', fulfillment_attempts = 0,
+                fulfillment_next_attempt_at = NOW(), fulfillment_updated_at = NOW()
+            WHERE id = $1
+            "#,
+            reward_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        self.get_status(user_id, reward_id).await
+    }
+
+    /// Submit due orders to the provider. Failures are retried with backoff until
+    /// `MAX_FULFILLMENT_ATTEMPTS`. Orders are claimed and committed before the provider is
+    /// called, so no row lock is held across the call and concurrent workers skip them.
+    /// An attempt keeps its idempotency key until it is known to have failed, so a
+    /// submission whose outcome was lost is replayed rather than ordered twice.
+    pub async fn process_due(&self) -> Result<usize> {
+        let due = sqlx::query!(
+            r#"
+            UPDATE user_rewards ur
+            SET fulfillment_next_attempt_at = NOW() + make_interval(secs => $2),
+                fulfillment_idempotency_key = COALESCE(ur.fulfillment_idempotency_key, gen_random_uuid()),
+                fulfillment_updated_at = NOW()
+            FROM (
+                SELECT id FROM user_rewards
+                WHERE fulfillment_status = 'pending' AND fulfillment_next_attempt_at <= NOW()
+                  AND value_amount IS NOT NULL
+                ORDER BY fulfillment_next_attempt_at
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            ) due
+            WHERE ur.id = due.id
+            RETURNING ur.id, ur.user_id, ur.fulfillment_attempts,
+                      ur.fulfillment_idempotency_key AS "idempotency_key!",
+                      ur.value_amount AS "value_amount!", COALESCE(ur.value_currency, 'INR') AS "value_currency!",
+                      (SELECT rt.metadata->>'provider' FROM reward_templates rt WHERE rt.id = ur.template_id) AS provider
+            "#,
+            FULFILLMENT_BATCH_SIZE,
+            SUBMISSION_LEASE_SECONDS
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        let mut conn = self.db.acquire().await?;
+        let mut submitted = 0;
+        for order in due {
+            let result = match order.provider.as_deref() {
+                Some(provider) => {
+                    self.client
+                        .submit_order(order.idempotency_key, provider, &order.user_id, &order.value_amount, &order.value_currency)
+                        .await
+                }
+                None => Err(AppError::InternalError("Gift card template has no provider".to_string())),
+            };
+
+            match result {
+                Ok(reference) => {
+                    sqlx::query!(
+                        r#"
+                        UPDATE user_rewards
+                        SET fulfillment_status = 'submitted', fulfillment_reference = $2,
+                            fulfillment_attempts = fulfillment_attempts + 1, fulfillment_next_attempt_at = NULL,
+                            fulfillment_error = NULL, fulfillment_updated_at = NOW()
+                        WHERE id = $1 AND fulfillment_status = 'pending' AND fulfillment_idempotency_key = $3
+                        "#,
+                        order.id,
+                        reference,
+                        order.idempotency_key
+                    )
+                    .execute(&mut *conn)
+                    .await?;
+                    submitted += 1;
+                }
+                Err(e) => {
+                    warn!("Gift card order for reward {} failed: {:?}", order.id, e);
+                    Self::record_failure(&mut conn, order.id, order.fulfillment_attempts + 1, &format!("{:?}", e)).await?;
+                }
+            }
+        }
+
+        if submitted > 0 {
+            info!("Submitted {} gift card orders", submitted);
+        }
+        Ok(submitted)
+    }
+
+    /// Schedule the next attempt, or give up once attempts run out. The failed attempt's
+    /// idempotency key is dropped so the next one is a new order at the provider.
+    async fn record_failure(conn: &mut PgConnection, reward_id: Uuid, attempts: i32, reason: &str) -> Result<()> {
+        let exhausted = attempts >= MAX_FULFILLMENT_ATTEMPTS;
+        sqlx::query!(
+            r#"
+            UPDATE user_rewards
+            SET fulfillment_status = CASE WHEN $3 THEN 'failed' ELSE 'pending' END,
+                fulfillment_attempts = $2, fulfillment_next_attempt_at = $4,
+                fulfillment_reference = NULL, fulfillment_idempotency_key = NULL,
+                fulfillment_error = $5, fulfillment_updated_at = NOW()
+            WHERE id = $1 AND fulfillment_status <> 'delivered'
+            "#,
+            reward_id,
+            attempts,
+            exhausted,
+            (!exhausted).then(|| Utc::now() + retry_delay(attempts)),
+            reason
+        )
+        .execute(conn)
+        .await?;
+
+        if exhausted {
+            error!("Gift card order for reward {} failed {} times; giving up", reward_id, attempts);
+        }
+        Ok(())
+    }
+
+    /// Apply a delivery or failure from the provider. Idempotent; delivery is final, so a
+    /// failure reported after it changes nothing.
+    pub async fn handle_webhook(&self, webhook: FulfillmentWebhook) -> Result<()> {
+        let mut tx = self.db.begin().await?;
+        let order = sqlx::query!(
+            r#"
+            SELECT id, user_id, title, value, fulfillment_status AS "fulfillment_status!", fulfillment_attempts,
+                   value_amount, value_currency
+            FROM user_rewards
+            WHERE fulfillment_reference = $1
+            FOR UPDATE
+            "#,
+            webhook.reference
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Gift card order not found".to_string()))?;
+
+        match (webhook.status.as_str(), order.fulfillment_status.as_str()) {
+            ("delivered", "delivered") | ("failed", "pending" | "failed") => {}
+            ("failed", "delivered") => {
+                warn!("Ignoring failure reported for delivered gift card order {}", webhook.reference);
+            }
+            ("delivered", _) => {
+                sqlx::query!(
+                    r#"
+                    UPDATE user_rewards
+                    SET fulfillment_status = 'delivered', code = COALESCE($2, code),
+                        code_hash = COALESCE($3, code_hash),
+                        is_used = true, used_at = NOW(), fulfillment_error = NULL, fulfillment_updated_at = NOW()
+                    WHERE id = $1
+                    "#,
+                    order.id,
+                    self.codes.seal_opt(webhook.claim_code.as_deref())?,
+                    self.codes.blind_index_opt(webhook.claim_code.as_deref())
+                )
+                .execute(&mut *tx)
+                .await?;
+
+                if let (Some(amount), Some(currency)) = (order.value_amount, order.value_currency) {
+                    currency::record_savings(&mut tx, &order.user_id, &Money { amount, currency }).await?;
+                }
+
+                let event = NotificationEvent::GiftCardDelivered {
+                    reward_id: order.id,
+                    title: order.title,
+                    value: order.value,
+                };
+                notifications::queue(&mut *tx, &order.user_id, &format!("gift_card_delivered:{}", order.id), &event).await?;
+            }
+            ("failed", _) => {
+                let reason = webhook.failure_reason.as_deref().unwrap_or("unknown");
+                warn!("Gift card order {} failed at the provider: {}", webhook.reference, reason);
+                Self::record_failure(&mut tx, order.id, order.fulfillment_attempts, reason).await?;
+            }
+            (other, _) => {
+                return Err(AppError::BadRequest(format!("Unknown fulfillment status '{}'", other)));
+            }
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Requeue an order that ran out of retries
+    pub async fn retry(&self, actor: &str, reward_id: Uuid) -> Result<()> {
+        let mut tx = self.db.begin().await?;
+        let retried = sqlx::query!(
+            r#"
+            UPDATE user_rewards
+            SET fulfillment_status = 'pending', fulfillment_attempts = 0,
+                fulfillment_next_attempt_at = NOW(), fulfillment_updated_at = NOW()
+            WHERE id = $1 AND fulfillment_status = 'failed'
+            "#,
+            reward_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        if retried.rows_affected() == 0 {
+            return Err(AppError::BadRequest("Only failed gift card orders can be retried".to_string()));
+        }
+
+        audit::record(&mut *tx, actor, "gift_card.retried", Some(&reward_id.to_string()), json!({})).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Run `process_due` every 30 seconds
+    pub fn spawn_worker(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(WORKER_INTERVAL_SECONDS));
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.process_due().await {
+                    error!("Gift card fulfillment run failed: {:?}", e);
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,171 @@
+use crate::compliance::ComplianceSubject;
+use crate::error::AppError;
+use crate::lootpacks::{InventoryQuery, LootpackService};
+use crate::localization::Locale;
+use crate::markets::Market;
+use crate::redemption::{BulkRedeemRequest, RedemptionService};
+use async_graphql::{Context, EmptySubscription, ErrorExtensions, Json, Object, Schema, ID};
+use async_graphql_axum::{GraphQLRequest, GraphQLResponse};
+use axum::{extract::State, routing::post, Extension, Router};
+use std::sync::Arc;
+use tracing::error;
+use uuid::Uuid;
+
+/// Rewards expiring within this many days count as expiring soon
+const DEFAULT_EXPIRING_WITHIN_DAYS: i32 = 3;
+/// Bounds on a single query, so one request can't fan out into unbounded service calls
+const MAX_QUERY_DEPTH: usize = 8;
+const MAX_QUERY_COMPLEXITY: usize = 200;
+
+pub type LootpacksSchema = Schema<QueryRoot, MutationRoot, EmptySubscription>;
+
+/// Who the request is for, set by the auth middleware exactly as for REST requests
+#[derive(Clone)]
+pub struct RequestContext {
+    pub user_id: String,
+    pub locale: Locale,
+    pub market: Market,
+    pub subject: ComplianceSubject,
+}
+
+/// GraphQL over the existing services, so clients can fetch stats, packs and inventory
+/// in one round trip. Resolvers call the same service methods as the REST handlers and
+/// return the REST JSON.
+pub fn schema(lootpacks: Arc<LootpackService>, redemption: Arc<RedemptionService>) -> LootpacksSchema {
+    Schema::build(QueryRoot, MutationRoot, EmptySubscription)
+        .limit_depth(MAX_QUERY_DEPTH)
+        .limit_complexity(MAX_QUERY_COMPLEXITY)
+        .data(lootpacks)
+        .data(redemption)
+        .finish()
+}
+
+/// Mounted at `/graphql` next to the REST routes
+pub fn routes(schema: LootpacksSchema) -> Router {
+    Router::new().route("/graphql", post(graphql)).with_state(schema)
+}
+
+async fn graphql(
+    State(schema): State<LootpacksSchema>,
+    Extension(context): Extension<RequestContext>,
+    req: GraphQLRequest,
+) -> GraphQLResponse {
+    schema.execute(req.into_inner().data(context)).await.into()
+}
+
+/// Service errors keep their REST meaning in `extensions.code`. Internal errors are
+/// logged and reach the client only as a generic message.
+fn to_graphql(e: AppError) -> async_graphql::Error {
+    let (code, message) = match e {
+        AppError::NotFound(message) => ("NOT_FOUND", message),
+        AppError::BadRequest(message) => ("BAD_REQUEST", message),
+        other => {
+            error!("GraphQL resolver failed: {:?}", other);
+            ("INTERNAL", "Internal server error".to_string())
+        }
+    };
+    async_graphql::Error::new(message).extend_with(|_, ext| ext.set("code", code))
+}
+
+fn parse_id(id: &ID) -> async_graphql::Result<Uuid> {
+    Uuid::parse_str(id).map_err(|_| to_graphql(AppError::BadRequest(format!("Invalid id '{}'", id.as_str()))))
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// Packs offered to the user, as `GET /lootpacks`
+    async fn packs(&self, ctx: &Context<'_>) -> async_graphql::Result<Json<serde_json::Value>> {
+        let request = ctx.data::<RequestContext>()?;
+        let packs = ctx
+            .data::<Arc<LootpackService>>()?
+            .get_pack_types(&request.user_id, &request.locale, &request.market, &request.subject)
+            .await
+            .map_err(to_graphql)?;
+        Ok(Json(serde_json::to_value(packs)?))
+    }
+
+    /// The user's stats, as `GET /users/me/stats`
+    async fn stats(&self, ctx: &Context<'_>) -> async_graphql::Result<Json<serde_json::Value>> {
+        let request = ctx.data::<RequestContext>()?;
+        let stats = ctx
+            .data::<Arc<LootpackService>>()?
+            .get_user_stats(&request.user_id)
+            .await
+            .map_err(to_graphql)?;
+        Ok(Json(serde_json::to_value(stats)?))
+    }
+
+    /// The user's rewards, with the same filters as `GET /rewards`
+    #[allow(clippy::too_many_arguments)]
+    async fn inventory(
+        &self,
+        ctx: &Context<'_>,
+        r#type: Option<String>,
+        rarity: Option<String>,
+        status: Option<String>,
+        expiring_within_days: Option<i32>,
+        sort: Option<String>,
+        order: Option<String>,
+    ) -> async_graphql::Result<Json<serde_json::Value>> {
+        let request = ctx.data::<RequestContext>()?;
+        let query = InventoryQuery { r#type, rarity, status, expiring_within_days, sort, order, ..Default::default() };
+        let inventory = ctx
+            .data::<Arc<LootpackService>>()?
+            .get_user_inventory(&request.user_id, &query)
+            .await
+            .map_err(to_graphql)?;
+        Ok(Json(serde_json::to_value(inventory)?))
+    }
+
+    /// Active rewards expiring soonest first
+    async fn expiring_rewards(
+        &self,
+        ctx: &Context<'_>,
+        within_days: Option<i32>,
+    ) -> async_graphql::Result<Json<serde_json::Value>> {
+        let request = ctx.data::<RequestContext>()?;
+        let query = InventoryQuery {
+            status: Some("active".to_string()),
+            expiring_within_days: Some(within_days.unwrap_or(DEFAULT_EXPIRING_WITHIN_DAYS)),
+            sort: Some("expires_at".to_string()),
+            order: Some("asc".to_string()),
+            ..Default::default()
+        };
+        let inventory = ctx
+            .data::<Arc<LootpackService>>()?
+            .get_user_inventory(&request.user_id, &query)
+            .await
+            .map_err(to_graphql)?;
+        Ok(Json(serde_json::to_value(inventory.rewards)?))
+    }
+}
+
+pub struct MutationRoot;
+
+#[Object]
+impl MutationRoot {
+    /// Buy and open a pack, as `POST /lootpacks/:id/open`
+    async fn open_pack(&self, ctx: &Context<'_>, pack_type_id: ID) -> async_graphql::Result<Json<serde_json::Value>> {
+        let request = ctx.data::<RequestContext>()?;
+        let opened = ctx
+            .data::<Arc<LootpackService>>()?
+            .open_pack(&request.user_id, parse_id(&pack_type_id)?, &request.locale, &request.market, &request.subject, None)
+            .await
+            .map_err(to_graphql)?;
+        Ok(Json(serde_json::to_value(opened)?))
+    }
+
+    /// Redeem point rewards all-or-nothing, as `POST /rewards/redeem-bulk`
+    async fn redeem_rewards(&self, ctx: &Context<'_>, reward_ids: Vec<ID>) -> async_graphql::Result<Json<serde_json::Value>> {
+        let request = ctx.data::<RequestContext>()?;
+        let reward_ids = reward_ids.iter().map(parse_id).collect::<async_graphql::Result<Vec<Uuid>>>()?;
+        let redeemed = ctx
+            .data::<Arc<RedemptionService>>()?
+            .redeem_bulk(&request.user_id, BulkRedeemRequest { reward_ids })
+            .await
+            .map_err(to_graphql)?;
+        Ok(Json(serde_json::to_value(redeemed)?))
+    }
+}
@@ -0,0 +1,84 @@
+use crate::error::Result;
+use crate::odds::{self, OddsAdjustment};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgExecutor;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// A happy-hour window that is running right now
+#[derive(Debug, Clone)]
+pub struct ActiveHappyHour {
+    pub pack_type_id: Uuid,
+    pub rarity: String,
+    pub multiplier: f64,
+    pub label: Option<String>,
+    pub ends_at: DateTime<Utc>,
+}
+
+/// Badge data for the pack list UI
+#[derive(Debug, Clone, Serialize)]
+pub struct HappyHourBadge {
+    pub label: Option<String>,
+    pub rarity_multipliers: HashMap<String, f64>,
+    pub ends_at: DateTime<Utc>,
+}
+
+impl HappyHourBadge {
+    /// Merge the running windows of one pack; the badge ends with the earliest window
+    pub fn from_windows(windows: &[ActiveHappyHour]) -> Option<Self> {
+        let ends_at = windows.iter().map(|w| w.ends_at).min()?;
+
+        // Show the boost openings actually get, not a configured one the guardrail caps
+        let rarity_multipliers = odds_adjustment(windows)
+            .rarity_multipliers
+            .into_iter()
+            .map(|(rarity, multiplier)| (rarity, odds::clamp_multiplier(multiplier)))
+            .collect();
+
+        Some(Self {
+            label: windows.iter().find_map(|w| w.label.clone()),
+            rarity_multipliers,
+            ends_at,
+        })
+    }
+}
+
+/// Odds boost from the running windows of one pack
+pub fn odds_adjustment(windows: &[ActiveHappyHour]) -> OddsAdjustment {
+    let mut rarity_multipliers: HashMap<String, f64> = HashMap::new();
+    for window in windows {
+        *rarity_multipliers.entry(window.rarity.clone()).or_insert(1.0) *= window.multiplier;
+    }
+    OddsAdjustment { rarity_multipliers }
+}
+
+/// Happy hours running now, optionally for a single pack
+pub async fn active_happy_hours<'e, E: PgExecutor<'e>>(
+    executor: E,
+    pack_type_id: Option<Uuid>,
+) -> Result<Vec<ActiveHappyHour>> {
+    let windows = sqlx::query_as!(
+        ActiveHappyHour,
+        r#"
+        SELECT hh.pack_type_id, hh.rarity, hh.multiplier, hh.label,
+               ((local.now::date + hh.end_time
+                 + CASE WHEN hh.end_time <= local.now::time THEN INTERVAL '1 day' ELSE INTERVAL '0' END)
+                AT TIME ZONE hh.timezone) AS "ends_at!"
+        FROM happy_hours hh
+        CROSS JOIN LATERAL (SELECT NOW() AT TIME ZONE hh.timezone AS now) local
+        WHERE hh.is_active
+          AND ($1::uuid IS NULL OR hh.pack_type_id = $1)
+          AND CASE
+                WHEN hh.start_time < hh.end_time
+                    THEN local.now::time >= hh.start_time AND local.now::time < hh.end_time
+                ELSE local.now::time >= hh.start_time OR local.now::time < hh.end_time
+              END
+        "#,
+        pack_type_id
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(windows)
+}
@@ -0,0 +1,146 @@
+use crate::error::{AppError, Result};
+use crate::privacy;
+use rand::Rng;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+/// Time allowed to open a connection, separate from the whole-request timeout
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+/// Requests in flight to one service at once; callers past this wait for a slot
+const MAX_CONCURRENT_REQUESTS: usize = 32;
+const MAX_IDLE_CONNECTIONS: usize = 8;
+/// Retries after the first attempt, for requests that are safe to repeat
+const MAX_RETRIES: u32 = 2;
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(100);
+/// Carries the id of the request being served, so one pack open can be followed downstream
+const REQUEST_ID_HEADER: &str = "x-request-id";
+/// Path segments followed by a user id in downstream APIs, e.g. `/users/<id>`
+const USER_ID_PARENT_SEGMENTS: &[&str] = &["users", "subscriptions"];
+
+/// Shared wrapper every outbound client is built on. One per downstream service: it
+/// owns the connection pool, caps concurrent requests to that host, retries transient
+/// failures with jittered backoff and traces each request. Status handling and response
+/// bodies stay with the typed client using it.
+pub struct HttpServiceClient {
+    service: &'static str,
+    client: reqwest::Client,
+    base_url: String,
+    in_flight: tokio::sync::Semaphore,
+}
+
+impl HttpServiceClient {
+    /// `service` names the downstream in logs and errors, e.g. "billing"
+    pub fn new(service: &'static str, base_url: String, timeout: Duration) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .connect_timeout(CONNECT_TIMEOUT.min(timeout))
+            .pool_max_idle_per_host(MAX_IDLE_CONNECTIONS)
+            .build()
+            .map_err(|e| AppError::InternalError(format!("Failed to build {} client: {}", service, e)))?;
+
+        Ok(Self {
+            service,
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            in_flight: tokio::sync::Semaphore::new(MAX_CONCURRENT_REQUESTS),
+        })
+    }
+
+    pub fn get(&self, path: &str) -> reqwest::RequestBuilder {
+        self.client.get(format!("{}{}", self.base_url, path))
+    }
+
+    pub fn post(&self, path: &str) -> reqwest::RequestBuilder {
+        self.client.post(format!("{}{}", self.base_url, path))
+    }
+
+    /// Send a request that is safe to repeat (a read, or a write carrying an idempotency
+    /// key), retrying connection failures, timeouts and 429/502/503/504 responses
+    pub async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        self.send_with_retries(request, MAX_RETRIES).await
+    }
+
+    /// Send a request exactly once, for writes the downstream can't deduplicate
+    pub async fn send_once(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        self.send_with_retries(request, 0).await
+    }
+
+    async fn send_with_retries(&self, request: reqwest::RequestBuilder, max_retries: u32) -> Result<reqwest::Response> {
+        let mut request = request
+            .build()
+            .map_err(|e| AppError::InternalError(format!("Invalid {} request: {}", self.service, e)))?;
+        if let Some(id) = crate::current_request_id().and_then(|id| reqwest::header::HeaderValue::from_str(&id).ok()) {
+            request.headers_mut().insert(REQUEST_ID_HEADER, id);
+        }
+
+        let mut attempt = 0;
+        loop {
+            // Streaming bodies can't be replayed, so those go out once
+            let replay = if attempt < max_retries { request.try_clone() } else { None };
+            let (method, path) = (request.method().clone(), loggable_path(request.url().path()));
+            let started = Instant::now();
+
+            let result = {
+                let _permit = self
+                    .in_flight
+                    .acquire()
+                    .await
+                    .map_err(|_| AppError::InternalError(format!("{} client is shut down", self.service)))?;
+                self.client.execute(request).await
+            };
+            let elapsed_ms = started.elapsed().as_millis() as u64;
+
+            let transient = match &result {
+                Ok(response) => {
+                    debug!(service = self.service, %method, path, status = response.status().as_u16(), elapsed_ms, attempt, "Outbound request");
+                    is_transient_status(response.status())
+                }
+                Err(e) => {
+                    debug!(service = self.service, %method, path, error = %e, elapsed_ms, attempt, "Outbound request failed");
+                    e.is_connect() || e.is_timeout()
+                }
+            };
+
+            match replay {
+                Some(next) if transient => {
+                    attempt += 1;
+                    let delay = backoff(attempt);
+                    warn!("{} {} {} failed transiently, retry {} in {:?}", self.service, method, path, attempt, delay);
+                    tokio::time::sleep(delay).await;
+                    request = next;
+                }
+                _ => {
+                    return result
+                        .map_err(|e| AppError::InternalError(format!("{} request failed: {}", self.service, e)));
+                }
+            }
+        }
+    }
+}
+
+/// The request path with user ids replaced by their pseudonyms
+fn loggable_path(path: &str) -> String {
+    let mut previous = "";
+    path.split('/')
+        .map(|segment| {
+            let logged = if USER_ID_PARENT_SEGMENTS.contains(&previous) && !segment.is_empty() {
+                privacy::pseudonym(segment).to_string()
+            } else {
+                segment.to_string()
+            };
+            previous = segment;
+            logged
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn is_transient_status(status: reqwest::StatusCode) -> bool {
+    matches!(status.as_u16(), 429 | 502 | 503 | 504)
+}
+
+/// Exponential backoff with full jitter
+fn backoff(attempt: u32) -> Duration {
+    let ceiling = RETRY_BASE_DELAY * 2u32.pow(attempt);
+    ceiling.mul_f64(rand::thread_rng().gen_range(0.0..1.0))
+}
@@ -0,0 +1,259 @@
+use crate::error::{AppError, Result};
+use crate::{audit, user_packs};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{FromRow, PgConnection, PgPool};
+use uuid::Uuid;
+
+const MAX_ANNOUNCEMENT_RECIPIENTS: usize = 10_000;
+
+#[derive(Debug, FromRow, Serialize)]
+pub struct InboxMessage {
+    pub id: Uuid,
+    pub kind: String,
+    pub title: String,
+    pub body: String,
+    pub claim_pack_type_id: Option<Uuid>,
+    pub claimed_user_pack_id: Option<Uuid>,
+    pub claimed_at: Option<DateTime<Utc>>,
+    pub read_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InboxQuery {
+    pub unread_only: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InboxResponse {
+    pub messages: Vec<InboxMessage>,
+    pub unread_count: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ClaimMessageResponse {
+    pub message_id: Uuid,
+    pub user_pack_id: Uuid,
+}
+
+/// Admin announcement, optionally with a claimable pack (e.g. outage compensation)
+#[derive(Debug, Deserialize)]
+pub struct AnnouncementRequest {
+    pub user_ids: Vec<String>,
+    pub title: String,
+    pub body: String,
+    pub claim_pack_type_id: Option<Uuid>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// A message to put in a user's inbox
+pub struct NewMessage<'a> {
+    pub kind: &'a str,
+    pub title: &'a str,
+    pub body: &'a str,
+    pub claim_pack_type_id: Option<Uuid>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Post a message to a user's inbox. Runs in the caller's transaction so the message
+/// only appears if whatever produced it commits.
+pub async fn send(conn: &mut PgConnection, user_id: &str, message: NewMessage<'_>) -> Result<Uuid> {
+    let id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO inbox_messages (user_id, kind, title, body, claim_pack_type_id, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id
+        "#,
+        user_id,
+        message.kind,
+        message.title,
+        message.body,
+        message.claim_pack_type_id,
+        message.expires_at
+    )
+    .fetch_one(conn)
+    .await?;
+
+    Ok(id)
+}
+
+pub struct InboxService {
+    db: PgPool,
+}
+
+impl InboxService {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    /// List the user's messages, newest first
+    pub async fn list_messages(&self, user_id: &str, query: &InboxQuery) -> Result<InboxResponse> {
+        let messages = sqlx::query_as!(
+            InboxMessage,
+            r#"
+            SELECT id, kind, title, body, claim_pack_type_id, claimed_user_pack_id,
+                   claimed_at, read_at, expires_at, created_at
+            FROM inbox_messages
+            WHERE user_id = $1 AND deleted_at IS NULL
+              AND (expires_at IS NULL OR expires_at > NOW())
+              AND ($2 = false OR read_at IS NULL)
+            ORDER BY created_at DESC
+            LIMIT 100
+            "#,
+            user_id,
+            query.unread_only.unwrap_or(false)
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        let unread_count = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!" FROM inbox_messages
+            WHERE user_id = $1 AND deleted_at IS NULL AND read_at IS NULL
+              AND (expires_at IS NULL OR expires_at > NOW())
+            "#,
+            user_id
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(InboxResponse { messages, unread_count })
+    }
+
+    pub async fn mark_read(&self, user_id: &str, message_id: Uuid) -> Result<()> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE inbox_messages SET read_at = COALESCE(read_at, NOW())
+            WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL
+            "#,
+            message_id,
+            user_id
+        )
+        .execute(&self.db)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound("Message not found".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Soft-delete a message. Unclaimed attachments are forfeited.
+    pub async fn delete_message(&self, user_id: &str, message_id: Uuid) -> Result<()> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE inbox_messages SET deleted_at = NOW()
+            WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL
+            "#,
+            message_id,
+            user_id
+        )
+        .execute(&self.db)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound("Message not found".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Claim a message's attached pack into the user's unopened packs
+    pub async fn claim_message(&self, user_id: &str, message_id: Uuid) -> Result<ClaimMessageResponse> {
+        let mut tx = self.db.begin().await?;
+
+        let message = sqlx::query!(
+            r#"
+            SELECT id, claim_pack_type_id, claimed_at, expires_at
+            FROM inbox_messages
+            WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL
+            FOR UPDATE
+            "#,
+            message_id,
+            user_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Message not found".to_string()))?;
+
+        let pack_type_id = message
+            .claim_pack_type_id
+            .ok_or_else(|| AppError::BadRequest("Message has nothing to claim".to_string()))?;
+        if message.claimed_at.is_some() {
+            return Err(AppError::BadRequest("Message has already been claimed".to_string()));
+        }
+        if message.expires_at.map(|exp| exp <= Utc::now()).unwrap_or(false) {
+            return Err(AppError::BadRequest("Message has expired".to_string()));
+        }
+
+        let user_pack_id = user_packs::grant(&mut tx, user_id, pack_type_id, "inbox_claim", None).await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE inbox_messages
+            SET claimed_at = NOW(), claimed_user_pack_id = $2, read_at = COALESCE(read_at, NOW())
+            WHERE id = $1
+            "#,
+            message.id,
+            user_pack_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        audit::record(
+            &mut *tx,
+            user_id,
+            "inbox.claimed",
+            Some(&message.id.to_string()),
+            json!({ "pack_type_id": pack_type_id, "user_pack_id": user_pack_id }),
+        )
+        .await?;
+
+        tx.commit().await?;
+        Ok(ClaimMessageResponse { message_id, user_pack_id })
+    }
+
+    /// Send an announcement to a list of users
+    pub async fn announce(&self, actor: &str, req: AnnouncementRequest) -> Result<usize> {
+        if req.user_ids.is_empty() || req.user_ids.len() > MAX_ANNOUNCEMENT_RECIPIENTS {
+            return Err(AppError::BadRequest(format!(
+                "user_ids must contain between 1 and {} users", MAX_ANNOUNCEMENT_RECIPIENTS
+            )));
+        }
+        if req.title.trim().is_empty() {
+            return Err(AppError::BadRequest("title is required".to_string()));
+        }
+
+        let mut tx = self.db.begin().await?;
+
+        let sent = sqlx::query!(
+            r#"
+            INSERT INTO inbox_messages (user_id, kind, title, body, claim_pack_type_id, expires_at)
+            SELECT DISTINCT u, 'announcement', $2, $3, $4, $5
+            FROM UNNEST($1::text[]) AS u
+            "#,
+            &req.user_ids,
+            req.title,
+            req.body,
+            req.claim_pack_type_id,
+            req.expires_at
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected() as usize;
+
+        audit::record(
+            &mut *tx,
+            actor,
+            "inbox.announced",
+            None,
+            json!({ "title": req.title, "recipients": sent, "claim_pack_type_id": req.claim_pack_type_id }),
+        )
+        .await?;
+
+        tx.commit().await?;
+        Ok(sent)
+    }
+}
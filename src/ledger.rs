@@ -0,0 +1,87 @@
+use crate::error::{AppError, Result};
+use sqlx::PgConnection;
+
+/// Adjust a user's DealCoins balance and record the change in `coin_ledger`.
+/// Must run inside the caller's transaction. Returns the new balance.
+pub async fn apply(
+    conn: &mut PgConnection,
+    user_id: &str,
+    delta: i32,
+    reason: &str,
+    reference_id: Option<&str>,
+) -> Result<i32> {
+    let balance = sqlx::query_scalar!(
+        r#"
+        UPDATE user_lootpack_stats
+        SET deal_coins = COALESCE(deal_coins, 0) + $2, updated_at = NOW()
+        WHERE user_id = $1 AND COALESCE(deal_coins, 0) + $2 >= 0
+        RETURNING deal_coins AS "deal_coins!"
+        "#,
+        user_id,
+        delta
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or_else(|| AppError::BadRequest("Insufficient DealCoins".to_string()))?;
+
+    record(conn, user_id, delta, reason, reference_id, balance).await?;
+    Ok(balance)
+}
+
+/// Debit `amount` coins even past zero. Only for taking back coins the user was never
+/// entitled to, like a charged-back top-up: a negative balance fails every later debit
+/// until credits pay it off. Returns the new balance.
+pub async fn claw_back(
+    conn: &mut PgConnection,
+    user_id: &str,
+    amount: i32,
+    reason: &str,
+    reference_id: Option<&str>,
+) -> Result<i32> {
+    let balance = sqlx::query_scalar!(
+        r#"
+        UPDATE user_lootpack_stats
+        SET deal_coins = COALESCE(deal_coins, 0) - $2, updated_at = NOW()
+        WHERE user_id = $1
+        RETURNING deal_coins AS "deal_coins!"
+        "#,
+        user_id,
+        amount
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    record(conn, user_id, -amount, reason, reference_id, balance).await?;
+    Ok(balance)
+}
+
+/// Record a balance change that the caller has already written to `user_lootpack_stats`
+pub async fn record(
+    conn: &mut PgConnection,
+    user_id: &str,
+    delta: i32,
+    reason: &str,
+    reference_id: Option<&str>,
+    balance_after: i32,
+) -> Result<()> {
+    if delta == 0 {
+        return Ok(());
+    }
+
+    sqlx::query!(
+        r#"
+        INSERT INTO coin_ledger (user_id, delta, reason, reference_id, balance_after)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        user_id,
+        delta,
+        reason,
+        reference_id,
+        balance_after
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,197 @@
+use crate::error::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool};
+use std::sync::Arc;
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{error, warn};
+use uuid::Uuid;
+
+/// Postgres channel notified whenever entries are published; payload is the newest id
+pub const LIVE_FEED_CHANNEL: &str = "lootpacks_live_feed";
+
+/// Rows kept in `live_feed`
+const LIVE_FEED_CAPACITY: i64 = 500;
+const DEFAULT_LIMIT: i64 = 20;
+const MAX_LIMIT: i64 = 100;
+/// Entries buffered per stream subscriber before it starts missing some
+const SUBSCRIBER_BUFFER: usize = 64;
+
+/// One anonymized pull; deliberately carries nothing that identifies the user
+#[derive(Debug, Clone, Serialize)]
+pub struct LiveFeedEntry {
+    pub id: i64,
+    pub template_id: Uuid,
+    pub title: String,
+    pub rarity: String,
+    pub pack_name: Option<String>,
+    pub pulled_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct LiveFeedQuery {
+    /// Only entries newer than this id, for polling clients
+    pub after_id: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+/// Publish the given rewards to the live feed, skipping users who opted out. Call after
+/// `recent_pulls` has recorded them, in the same transaction; listeners are notified on commit.
+pub async fn publish(conn: &mut PgConnection, reward_ids: &[Uuid]) -> Result<()> {
+    let newest = sqlx::query_scalar!(
+        r#"
+        WITH published AS (
+            INSERT INTO live_feed (template_id, title, rarity, pack_name)
+            SELECT rp.template_id, rt.title, rp.rarity, pt.name
+            FROM recent_pulls rp
+            JOIN reward_templates rt ON rt.id = rp.template_id
+            LEFT JOIN pack_types pt ON pt.id = rp.pack_type_id
+            LEFT JOIN user_privacy_settings ups ON ups.user_id = rp.user_id
+            WHERE rp.reward_id = ANY($1) AND COALESCE(ups.show_in_live_feed, true)
+            RETURNING id
+        )
+        SELECT MAX(id) FROM published
+        "#,
+        reward_ids
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let Some(newest) = newest else {
+        return Ok(());
+    };
+
+    sqlx::query!("DELETE FROM live_feed WHERE id <= $1", newest - LIVE_FEED_CAPACITY)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query!("SELECT pg_notify($1, $2)", LIVE_FEED_CHANNEL, newest.to_string())
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Serves the feed over polling and streams. Each replica listens for publishes and fans
+/// new entries out to its own stream subscribers.
+pub struct LiveFeedService {
+    db: PgPool,
+    sender: broadcast::Sender<LiveFeedEntry>,
+}
+
+impl LiveFeedService {
+    pub fn new(db: PgPool) -> Self {
+        let (sender, _) = broadcast::channel(SUBSCRIBER_BUFFER);
+        Self { db, sender }
+    }
+
+    /// Newest entries first, or entries after `after_id` oldest first
+    pub async fn recent(&self, query: &LiveFeedQuery) -> Result<Vec<LiveFeedEntry>> {
+        let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+        let entries = match query.after_id {
+            Some(after_id) => Self::load_after(&self.db, after_id, limit).await?,
+            None => sqlx::query_as!(
+                LiveFeedEntry,
+                r#"
+                SELECT id, template_id, title, rarity, pack_name, pulled_at
+                FROM live_feed
+                ORDER BY id DESC
+                LIMIT $1
+                "#,
+                limit
+            )
+            .fetch_all(&self.db)
+            .await?,
+        };
+
+        Ok(entries)
+    }
+
+    /// Entries published from now on. A subscriber that falls behind skips what it missed
+    /// rather than holding up the others.
+    pub fn subscribe(&self) -> ReceiverStream<LiveFeedEntry> {
+        let mut receiver = self.sender.subscribe();
+        let (tx, rx) = mpsc::channel(SUBSCRIBER_BUFFER);
+        tokio::spawn(async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(entry) => {
+                        if tx.send(entry).await.is_err() {
+                            break; // Client went away
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Live feed subscriber skipped {} entries", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+        ReceiverStream::new(rx)
+    }
+
+    /// Relay publishes from every replica to this replica's subscribers
+    pub fn spawn_listener(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut last_seen = 0;
+            loop {
+                let mut listener = match sqlx::postgres::PgListener::connect_with(&self.db).await {
+                    Ok(listener) => listener,
+                    Err(e) => {
+                        error!("Live feed listener failed to connect: {:?}", e);
+                        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                        continue;
+                    }
+                };
+                if let Err(e) = listener.listen(LIVE_FEED_CHANNEL).await {
+                    error!("Live feed listener failed to subscribe: {:?}", e);
+                    tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                    continue;
+                }
+
+                // Start from the current head; subscribers only get entries published after they joined
+                if last_seen == 0 {
+                    match sqlx::query_scalar!("SELECT COALESCE(MAX(id), 0) AS \"id!\" FROM live_feed")
+                        .fetch_one(&self.db)
+                        .await
+                    {
+                        Ok(head) => last_seen = head,
+                        Err(e) => error!("Live feed listener failed to read the head: {:?}", e),
+                    }
+                }
+
+                while listener.recv().await.is_ok() {
+                    match Self::load_after(&self.db, last_seen, LIVE_FEED_CAPACITY).await {
+                        Ok(entries) => {
+                            for entry in entries {
+                                last_seen = entry.id;
+                                // No subscribers is fine; the entry is still in the table
+                                let _ = self.sender.send(entry);
+                            }
+                        }
+                        Err(e) => error!("Live feed listener failed to load entries: {:?}", e),
+                    }
+                }
+            }
+        })
+    }
+
+    async fn load_after(db: &PgPool, after_id: i64, limit: i64) -> Result<Vec<LiveFeedEntry>> {
+        let entries = sqlx::query_as!(
+            LiveFeedEntry,
+            r#"
+            SELECT id, template_id, title, rarity, pack_name, pulled_at
+            FROM live_feed
+            WHERE id > $1
+            ORDER BY id
+            LIMIT $2
+            "#,
+            after_id,
+            limit
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(entries)
+    }
+}
@@ -0,0 +1,273 @@
+use crate::error::{AppError, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgExecutor, PgPool};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+const MAX_LOCALES: usize = 5;
+
+/// The caller's locales in preference order, parsed from `Accept-Language`.
+/// Each regional tag is followed by its base language (`ta-IN` -> `ta-in`, `ta`).
+#[derive(Debug, Clone, Default)]
+pub struct Locale {
+    preferred: Vec<String>,
+}
+
+impl Locale {
+    pub fn from_accept_language(header: Option<&str>) -> Self {
+        let mut tags: Vec<(String, f32)> = header
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|part| {
+                let mut pieces = part.trim().split(';');
+                let tag = pieces.next()?.trim().to_lowercase();
+                if tag.is_empty() || tag == "*" {
+                    return None;
+                }
+                let quality = pieces
+                    .find_map(|p| p.trim().strip_prefix("q="))
+                    .and_then(|q| q.parse::<f32>().ok())
+                    .unwrap_or(1.0);
+                (quality > 0.0).then_some((tag, quality))
+            })
+            .collect();
+        // Stable sort keeps header order among equal weights
+        tags.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+
+        let mut preferred: Vec<String> = Vec::new();
+        for (tag, _) in tags {
+            let base = tag.split('-').next().unwrap_or(&tag).to_string();
+            for candidate in [tag, base] {
+                if !preferred.contains(&candidate) {
+                    preferred.push(candidate);
+                }
+            }
+        }
+        preferred.truncate(MAX_LOCALES);
+
+        Self { preferred }
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.preferred.is_empty()
+    }
+
+    pub fn preferred(&self) -> &[String] {
+        &self.preferred
+    }
+}
+
+/// Translated name and description for one pack or template
+#[derive(Debug, Clone)]
+pub struct Translation {
+    pub text: String,
+    pub description: Option<String>,
+}
+
+/// Best available pack translation per pack id
+pub async fn pack_translations<'e, E: PgExecutor<'e>>(
+    executor: E,
+    pack_type_ids: &[Uuid],
+    locale: &Locale,
+) -> Result<HashMap<Uuid, Translation>> {
+    if locale.is_default() {
+        return Ok(HashMap::new());
+    }
+
+    let rows = sqlx::query!(
+        r#"
+        SELECT DISTINCT ON (pack_type_id) pack_type_id, name, description
+        FROM pack_type_translations
+        WHERE pack_type_id = ANY($1) AND locale = ANY($2)
+        ORDER BY pack_type_id, array_position($2, locale)
+        "#,
+        pack_type_ids,
+        locale.preferred()
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| (r.pack_type_id, Translation { text: r.name, description: r.description }))
+        .collect())
+}
+
+/// Best available reward template translation per template id
+pub async fn template_translations<'e, E: PgExecutor<'e>>(
+    executor: E,
+    template_ids: &[Uuid],
+    locale: &Locale,
+) -> Result<HashMap<Uuid, Translation>> {
+    if locale.is_default() {
+        return Ok(HashMap::new());
+    }
+
+    let rows = sqlx::query!(
+        r#"
+        SELECT DISTINCT ON (template_id) template_id, title, description
+        FROM reward_template_translations
+        WHERE template_id = ANY($1) AND locale = ANY($2)
+        ORDER BY template_id, array_position($2, locale)
+        "#,
+        template_ids,
+        locale.preferred()
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| (r.template_id, Translation { text: r.title, description: r.description }))
+        .collect())
+}
+
+#[derive(Debug, FromRow, Serialize)]
+pub struct TranslationEntry {
+    pub locale: String,
+    pub text: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpsertTranslationRequest {
+    /// Pack name or reward title
+    pub text: String,
+    pub description: Option<String>,
+}
+
+/// Admin management of pack and reward translations
+pub struct LocalizationService {
+    db: PgPool,
+}
+
+impl LocalizationService {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    pub async fn list_pack_translations(&self, pack_type_id: Uuid) -> Result<Vec<TranslationEntry>> {
+        let entries = sqlx::query_as!(
+            TranslationEntry,
+            r#"
+            SELECT locale, name AS text, description
+            FROM pack_type_translations WHERE pack_type_id = $1 ORDER BY locale
+            "#,
+            pack_type_id
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(entries)
+    }
+
+    pub async fn upsert_pack_translation(
+        &self,
+        pack_type_id: Uuid,
+        locale: &str,
+        req: UpsertTranslationRequest,
+    ) -> Result<TranslationEntry> {
+        let locale = normalize_locale(locale)?;
+        let entry = sqlx::query_as!(
+            TranslationEntry,
+            r#"
+            INSERT INTO pack_type_translations (pack_type_id, locale, name, description)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (pack_type_id, locale) DO UPDATE
+            SET name = EXCLUDED.name, description = EXCLUDED.description, updated_at = NOW()
+            RETURNING locale, name AS text, description
+            "#,
+            pack_type_id,
+            locale,
+            req.text,
+            req.description
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(entry)
+    }
+
+    pub async fn delete_pack_translation(&self, pack_type_id: Uuid, locale: &str) -> Result<()> {
+        let result = sqlx::query!(
+            "DELETE FROM pack_type_translations WHERE pack_type_id = $1 AND locale = $2",
+            pack_type_id,
+            locale.to_lowercase()
+        )
+        .execute(&self.db)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound("Translation not found".to_string()));
+        }
+        Ok(())
+    }
+
+    pub async fn list_template_translations(&self, template_id: Uuid) -> Result<Vec<TranslationEntry>> {
+        let entries = sqlx::query_as!(
+            TranslationEntry,
+            r#"
+            SELECT locale, title AS text, description
+            FROM reward_template_translations WHERE template_id = $1 ORDER BY locale
+            "#,
+            template_id
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(entries)
+    }
+
+    pub async fn upsert_template_translation(
+        &self,
+        template_id: Uuid,
+        locale: &str,
+        req: UpsertTranslationRequest,
+    ) -> Result<TranslationEntry> {
+        let locale = normalize_locale(locale)?;
+        let entry = sqlx::query_as!(
+            TranslationEntry,
+            r#"
+            INSERT INTO reward_template_translations (template_id, locale, title, description)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (template_id, locale) DO UPDATE
+            SET title = EXCLUDED.title, description = EXCLUDED.description, updated_at = NOW()
+            RETURNING locale, title AS text, description
+            "#,
+            template_id,
+            locale,
+            req.text,
+            req.description
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(entry)
+    }
+
+    pub async fn delete_template_translation(&self, template_id: Uuid, locale: &str) -> Result<()> {
+        let result = sqlx::query!(
+            "DELETE FROM reward_template_translations WHERE template_id = $1 AND locale = $2",
+            template_id,
+            locale.to_lowercase()
+        )
+        .execute(&self.db)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound("Translation not found".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Accept tags like `hi` or `ta-IN`; store them lowercase
+fn normalize_locale(locale: &str) -> Result<String> {
+    let valid = !locale.is_empty()
+        && locale.len() <= 16
+        && locale.split('-').all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric()));
+    if !valid {
+        return Err(AppError::BadRequest(format!("Invalid locale '{}'", locale)));
+    }
+    Ok(locale.to_lowercase())
+}
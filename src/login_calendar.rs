@@ -0,0 +1,355 @@
+use crate::error::{AppError, Result};
+use crate::{audit, user_packs};
+use crate::wallet::{self, CoinWallet};
+use chrono::{Datelike, Months, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{PgConnection, PgPool};
+use std::collections::HashSet;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Used when the client doesn't send a timezone, matching the happy-hour default
+pub const DEFAULT_TIMEZONE: &str = "Asia/Kolkata";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalendarDayConfig {
+    pub day: i32,
+    pub grant_type: String,
+    pub coins_amount: Option<i32>,
+    pub pack_type_id: Option<Uuid>,
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetCalendarRequest {
+    pub days: Vec<CalendarDayConfig>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CalendarDay {
+    #[serde(flatten)]
+    pub reward: CalendarDayConfig,
+    /// claimed, missed, available or upcoming
+    pub status: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CalendarResponse {
+    pub month: String,
+    pub timezone: String,
+    pub today: u32,
+    pub days: Vec<CalendarDay>,
+    pub claimed_count: usize,
+    pub missed_count: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CalendarClaimResponse {
+    pub month: String,
+    pub day: u32,
+    pub timezone: String,
+    pub grant_type: String,
+    pub coins_granted: Option<i32>,
+    pub new_balance: Option<i32>,
+    pub user_pack_id: Option<Uuid>,
+}
+
+/// Parse a `YYYY-MM` path segment into the first day of that month
+pub fn parse_month(month: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(&format!("{}-01", month.trim()), "%Y-%m-%d")
+        .map_err(|_| AppError::BadRequest(format!("Invalid month '{}', expected YYYY-MM", month)))
+}
+
+fn days_in_month(month: NaiveDate) -> u32 {
+    let next = month + Months::new(1);
+    next.signed_duration_since(month).num_days() as u32
+}
+
+pub struct LoginCalendarService {
+    db: PgPool,
+    wallet: Arc<dyn CoinWallet>,
+}
+
+impl LoginCalendarService {
+    pub fn new(db: PgPool, wallet: Arc<dyn CoinWallet>) -> Self {
+        Self { db, wallet }
+    }
+
+    /// The user's current month with the status of every configured day
+    pub async fn get_calendar(&self, user_id: &str, timezone: Option<&str>) -> Result<CalendarResponse> {
+        let mut conn = self.db.acquire().await?;
+        let (timezone, today) = Self::local_today(&mut conn, user_id, timezone).await?;
+        let month = today.with_day(1).unwrap_or(today);
+
+        let config = Self::load_month(&mut conn, month).await?;
+        let claimed: HashSet<i32> = sqlx::query_scalar!(
+            "SELECT day FROM login_calendar_claims WHERE user_id = $1 AND month = $2",
+            user_id,
+            month
+        )
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .collect();
+
+        let days: Vec<CalendarDay> = config
+            .into_iter()
+            .map(|reward| {
+                let status = if claimed.contains(&reward.day) {
+                    "claimed"
+                } else if reward.day < today.day() as i32 {
+                    "missed"
+                } else if reward.day == today.day() as i32 {
+                    "available"
+                } else {
+                    "upcoming"
+                };
+                CalendarDay { reward, status }
+            })
+            .collect();
+
+        Ok(CalendarResponse {
+            month: month.format("%Y-%m").to_string(),
+            timezone,
+            today: today.day(),
+            claimed_count: days.iter().filter(|d| d.status == "claimed").count(),
+            missed_count: days.iter().filter(|d| d.status == "missed").count(),
+            days,
+        })
+    }
+
+    /// Claim today's reward. Missed days are forfeited; only the current local day can be claimed.
+    pub async fn claim(&self, user_id: &str, timezone: Option<&str>) -> Result<CalendarClaimResponse> {
+        let mut tx = self.db.begin().await?;
+        let (timezone, today) = Self::local_today(&mut tx, user_id, timezone).await?;
+        let month = today.with_day(1).unwrap_or(today);
+        let day = today.day() as i32;
+
+        let reward = sqlx::query_as!(
+            CalendarDayConfig,
+            r#"
+            SELECT day, grant_type, coins_amount, pack_type_id, label
+            FROM login_calendar_days
+            WHERE month = $1 AND day = $2
+            "#,
+            month,
+            day
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("There is no calendar reward today".to_string()))?;
+
+        // The primary key makes a second claim of the same day a no-op
+        let inserted = sqlx::query!(
+            r#"
+            INSERT INTO login_calendar_claims (user_id, month, day, timezone, grant_type)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (user_id, month, day) DO NOTHING
+            "#,
+            user_id,
+            month,
+            day,
+            timezone,
+            reward.grant_type
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if inserted == 0 {
+            return Err(AppError::BadRequest("Today's calendar reward has already been claimed".to_string()));
+        }
+
+        let reference = format!("{}:{}", month.format("%Y-%m"), day);
+        let mut response = CalendarClaimResponse {
+            month: month.format("%Y-%m").to_string(),
+            day: today.day(),
+            timezone: timezone.clone(),
+            grant_type: reward.grant_type.clone(),
+            coins_granted: None,
+            new_balance: None,
+            user_pack_id: None,
+        };
+
+        let mut wallet_entries = Vec::new();
+        match (reward.grant_type.as_str(), reward.coins_amount, reward.pack_type_id) {
+            ("coins", Some(amount), _) => {
+                let entry = self.wallet.apply(&mut tx, user_id, amount, "login_calendar", Some(&reference)).await?;
+                response.coins_granted = Some(amount);
+                response.new_balance = Some(entry.balance);
+                wallet_entries.push(entry);
+            }
+            ("pack", _, Some(pack_type_id)) => {
+                response.user_pack_id = Some(
+                    user_packs::grant(&mut tx, user_id, pack_type_id, "login_calendar", None).await?
+                );
+            }
+            _ => {
+                return Err(AppError::InternalError(format!("Calendar day {} is misconfigured", reference)));
+            }
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE login_calendar_claims SET coins_granted = $4, user_pack_id = $5
+            WHERE user_id = $1 AND month = $2 AND day = $3
+            "#,
+            user_id,
+            month,
+            day,
+            response.coins_granted,
+            response.user_pack_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        audit::record(&mut *tx, user_id, "login_calendar.claimed", Some(&reference), json!({
+            "timezone": timezone,
+            "grant_type": reward.grant_type,
+            "coins": response.coins_granted,
+            "user_pack_id": response.user_pack_id,
+        }))
+        .await?;
+
+        wallet::commit_with_wallet(self.wallet.as_ref(), tx, &wallet_entries).await?;
+        Ok(response)
+    }
+
+    pub async fn get_month(&self, month: NaiveDate) -> Result<Vec<CalendarDayConfig>> {
+        let mut conn = self.db.acquire().await?;
+        Self::load_month(&mut conn, month).await
+    }
+
+    /// Replace a month's configuration. Past months are locked; the current month can
+    /// still be edited, and days already claimed keep what they granted.
+    pub async fn set_month(&self, actor: &str, month: NaiveDate, req: SetCalendarRequest) -> Result<Vec<CalendarDayConfig>> {
+        let current_month = Utc::now().date_naive().with_day(1).unwrap_or(month);
+        if month < current_month {
+            return Err(AppError::BadRequest("Past months can't be changed".to_string()));
+        }
+
+        let length = days_in_month(month) as i32;
+        let mut seen = HashSet::new();
+        for day in &req.days {
+            if day.day < 1 || day.day > length {
+                return Err(AppError::BadRequest(format!(
+                    "Day {} is outside {} (1-{})", day.day, month.format("%Y-%m"), length
+                )));
+            }
+            if !seen.insert(day.day) {
+                return Err(AppError::BadRequest(format!("Day {} is configured twice", day.day)));
+            }
+            match (day.grant_type.as_str(), day.coins_amount, day.pack_type_id) {
+                ("coins", Some(amount), _) if amount > 0 => {}
+                ("pack", _, Some(_)) => {}
+                _ => {
+                    return Err(AppError::BadRequest(format!(
+                        "Day {} needs a positive coins_amount for coins or a pack_type_id for pack", day.day
+                    )));
+                }
+            }
+        }
+
+        let mut tx = self.db.begin().await?;
+        let pack_ids: Vec<Uuid> = req.days.iter().filter_map(|d| d.pack_type_id).collect();
+        let known: HashSet<Uuid> = sqlx::query_scalar!("SELECT id FROM pack_types WHERE id = ANY($1)", &pack_ids)
+            .fetch_all(&mut *tx)
+            .await?
+            .into_iter()
+            .collect();
+        if let Some(unknown) = pack_ids.iter().find(|id| !known.contains(id)) {
+            return Err(AppError::BadRequest(format!("Unknown pack type {}", unknown)));
+        }
+
+        sqlx::query!("DELETE FROM login_calendar_days WHERE month = $1", month)
+            .execute(&mut *tx)
+            .await?;
+
+        for day in &req.days {
+            let (coins_amount, pack_type_id) = match day.grant_type.as_str() {
+                "coins" => (day.coins_amount, None),
+                _ => (None, day.pack_type_id),
+            };
+            sqlx::query!(
+                r#"
+                INSERT INTO login_calendar_days (month, day, grant_type, coins_amount, pack_type_id, label, updated_by)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                "#,
+                month,
+                day.day,
+                day.grant_type,
+                coins_amount,
+                pack_type_id,
+                day.label,
+                actor
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let reference = month.format("%Y-%m").to_string();
+        audit::record(&mut *tx, actor, "login_calendar.configured", Some(&reference), json!({
+            "days": req.days.len(),
+        }))
+        .await?;
+
+        let config = Self::load_month(&mut tx, month).await?;
+        tx.commit().await?;
+        Ok(config)
+    }
+
+    async fn load_month(conn: &mut PgConnection, month: NaiveDate) -> Result<Vec<CalendarDayConfig>> {
+        let days = sqlx::query_as!(
+            CalendarDayConfig,
+            r#"
+            SELECT day, grant_type, coins_amount, pack_type_id, label
+            FROM login_calendar_days
+            WHERE month = $1
+            ORDER BY day
+            "#,
+            month
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(days)
+    }
+
+    /// The user's local date. A timezone already used for a claim this month wins over the
+    /// requested one; otherwise the requested timezone must be one Postgres knows.
+    async fn local_today(conn: &mut PgConnection, user_id: &str, requested: Option<&str>) -> Result<(String, NaiveDate)> {
+        let pinned = sqlx::query!(
+            r#"
+            SELECT timezone, (NOW() AT TIME ZONE timezone)::date AS "today!", month
+            FROM login_calendar_claims
+            WHERE user_id = $1
+            ORDER BY claimed_at DESC
+            LIMIT 1
+            "#,
+            user_id
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        if let Some(pinned) = pinned {
+            if pinned.today.with_day(1) == Some(pinned.month) {
+                return Ok((pinned.timezone, pinned.today));
+            }
+        }
+
+        let timezone = requested.map(str::trim).filter(|tz| !tz.is_empty()).unwrap_or(DEFAULT_TIMEZONE);
+        let today = sqlx::query_scalar!(
+            r#"
+            SELECT (NOW() AT TIME ZONE name)::date AS "today!"
+            FROM pg_timezone_names
+            WHERE name = $1
+            "#,
+            timezone
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| AppError::BadRequest(format!("Unknown timezone '{}'", timezone)))?;
+
+        Ok((timezone.to_string(), today))
+    }
+}
@@ -3,25 +3,602 @@ use crate::error::Result;
 use sqlx::PgPool;
 use uuid::Uuid;
 use chrono::{DateTime, Utc, Duration};
-use rand::Rng;
+use crate::ads::{AdVerifier, TableAdVerifier};
+use crate::assets;
+use crate::chargebacks;
+use crate::code_crypto::CodeCipher;
+use crate::collection;
+use crate::community_events;
+use crate::compliance::{self, ComplianceSubject, OddsDisclosure, PaidRandomMode};
+use crate::audit;
+use crate::cooldowns;
+use crate::fairness::{self, FairOpenRequest, FairRng, FairnessProof, PoolEntry};
+use crate::first_purchase::{self, Purchase};
+use crate::free_claims;
+use crate::currency::{self, Money, RateTable};
+use crate::daily_reset::{self, DailyClock};
+use crate::inbox::{self, NewMessage};
+use crate::happy_hours::{self, ActiveHappyHour, HappyHourBadge};
+use crate::ledger;
+use crate::localization::{self, Locale};
+use crate::markets::Market;
+use crate::mystery_boxes::{self, MysteryOffer, MYSTERY_OPTIONS};
+use crate::reveal::{self, RevealPlan};
+use crate::reward_sets::{self, CompletedSet};
+use crate::reward_types;
+use crate::coupon_provider::{CouponProvider, LocalCouponProvider};
+use crate::odds::{self, OddsAdjustment, OddsProvider, StaticOddsProvider, MAX_ODDS_DEVIATION};
+use crate::pricing::{self, PackPricing};
+use crate::prestige::{self, PrestigeView};
+use crate::privacy;
+use crate::recent_pulls;
+use crate::rng::{RngProvider, ThreadRngProvider};
+use crate::rotations::{self, RotationInfo};
+use crate::self_exclusion;
+use crate::spending_limits;
+use crate::streak_repairs;
+use crate::teams;
+use crate::user_packs;
+use crate::users::{OpenUserDirectory, UserDirectory};
+use crate::vip::{self, VipStatus};
+use crate::wallet::{self, CoinWallet, LocalWallet, WalletEntry};
+use crate::vacations;
+use crate::valuation;
+use crate::wheels;
 use std::collections::HashMap;
+use std::sync::Arc;
 use tracing::{info, warn, error};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+/// Postgres channel the admin CLI notifies to flush cached pools; payload is a pack id or empty for all
+pub const CACHE_FLUSH_CHANNEL: &str = "lootpacks_cache_flush";
+
+/// Attempts at drawing a fresh code before giving up on a unique-index conflict
+const MAX_CODE_ATTEMPTS: usize = 5;
+
+/// Characters used in generated codes; 0/O and 1/I are left out to avoid misreads
+const CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+/// Default weight boost for templates in a user's top categories (0.5 = +50%)
+const DEFAULT_PERSONALIZATION_STRENGTH: f64 = 0.5;
+
+/// Upper bound on simulated openings per request
+const MAX_SIMULATION_RUNS: u32 = 10_000;
+
+/// DealCoins every new user starts with
+const STARTING_DEAL_COINS: i32 = 500;
+
+/// Level progress per pack opened; a level is 100 progress
+const LEVEL_PROGRESS_PER_OPEN: i32 = 10;
+
+/// DealCoins granted on every level up
+pub const LEVEL_UP_BONUS: i32 = 100;
+
+/// Upper bound on users x opens in one economy simulation
+const MAX_ECONOMY_SIMULATION_OPENS: u32 = 200_000;
+
+/// Ad placement that unlocks the daily pack, and how recently the ad must have been watched
+pub const DAILY_PACK_AD_PLACEMENT: &str = "daily_pack_ad";
+pub const DAILY_PACK_AD_WINDOW_HOURS: i64 = 1;
+
+/// DealCoins charged to reroll one reward from an open
+const DEFAULT_REROLL_FEE: i32 = 50;
+
+/// How long after opening a pack its reroll token stays valid
+const REROLL_TOKEN_TTL_MINUTES: i64 = 5;
+
+/// How long a pack's example contents are reused before a fresh sample is drawn
+const PREVIEW_CACHE_TTL: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+
+/// Packs priced at most this are low tier and can be auto-opened; free packs always are
+const AUTO_OPEN_MAX_PRICE: i32 = 99;
+
+/// Packs auto-opened per request, keeping the transaction short
+const AUTO_OPEN_BATCH_SIZE: i64 = 50;
+
+#[derive(Debug, Serialize)]
+pub struct PackSimulationResponse {
+    pub pack_type_id: Uuid,
+    pub runs: u32,
+    pub total_rewards: u32,
+    pub rarity_distribution: HashMap<String, u32>,
+    pub type_distribution: HashMap<String, u32>,
+    pub reward_distribution: HashMap<String, u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RewardPreview {
+    /// Template id; what a buyer sends when choosing a reward
+    pub id: Uuid,
+    pub title: String,
+    pub r#type: String,
+    pub value: String,
+    pub description: Option<String>,
+    pub drop_chance: f64,
+}
+
+/// One reward from an example opening; never carries a code
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SampleReward {
+    pub template_id: Uuid,
+    pub r#type: String,
+    pub title: String,
+    pub value: String,
+    pub description: String,
+    pub rarity: String,
+}
+
+/// Representative contents of a pack for marketing screens. Not binding: actual
+/// opens are drawn independently.
+#[derive(Debug, Clone, Serialize)]
+pub struct PackPreviewResponse {
+    pub pack_type_id: Uuid,
+    pub rewards: Vec<SampleReward>,
+    pub sampled_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PackDetailResponse {
+    pub pack: PackType,
+    pub rewards_by_rarity: HashMap<String, Vec<RewardPreview>>,
+}
+
+/// Optional inventory filters, all pushed down into SQL
+#[derive(Debug, Default, Deserialize)]
+pub struct InventoryQuery {
+    pub r#type: Option<String>,
+    pub rarity: Option<String>,
+    pub status: Option<String>, // active | used | expired
+    pub expiring_within_days: Option<i32>,
+    pub merchant_id: Option<String>,
+    pub brand: Option<String>,
+    pub category: Option<String>,
+    pub sort: Option<String>,  // expires_at | rarity | created_at
+    pub order: Option<String>, // asc | desc
+}
+
+#[derive(Debug, Serialize)]
+pub struct RewardMerchant {
+    pub merchant_id: Option<String>,
+    pub brand: Option<String>,
+    pub category: Option<String>,
+}
+
+/// Inventory row: the stored reward plus the merchant it can be used at
+#[derive(Debug, Serialize)]
+pub struct InventoryItem {
+    #[serde(flatten)]
+    pub reward: UserReward,
+    #[serde(flatten)]
+    pub merchant: RewardMerchant,
+    /// CDN URL of the template's uploaded icon
+    pub icon_url: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InventoryResponse {
+    pub rewards: Vec<InventoryItem>,
+    pub stats: InventoryStats,
+    /// Currency of `stats.total_value_estimate`
+    pub display_currency: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RewardSourcePack {
+    pub pack_type_id: Uuid,
+    pub name: String,
+    pub opened_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RewardDetailResponse {
+    pub reward: UserReward,
+    pub source_pack: Option<RewardSourcePack>,
+    pub status: String, // active | used | expired
+    pub days_remaining: Option<i64>,
+    pub redemption_instructions: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EconomySimulationRequest {
+    pub users: u32,
+    pub opens_per_user: u32,
+    /// Packs the simulated users buy from; defaults to every purchasable pack
+    pub pack_type_ids: Option<Vec<Uuid>>,
+    pub starting_coins: Option<i32>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct PackEconomyStats {
+    pub pack_name: String,
+    pub price: i32,
+    pub opens: u64,
+    pub coins_spent: i64,
+    pub coins_returned: i64,
+    pub expected_coin_return: f64,
+    /// Expected monetary value of a pack's rewards, in INR
+    pub expected_value_inr: f64,
+    pub rarity_distribution: HashMap<String, u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EconomySimulationResponse {
+    pub users: u32,
+    pub attempted_opens: u64,
+    pub completed_opens: u64,
+    pub coins_minted: i64,
+    pub coins_sunk: i64,
+    pub level_up_bonuses: i64,
+    pub average_ending_balance: f64,
+    /// Users who could no longer afford any pack before finishing their opens
+    pub users_out_of_coins: u32,
+    pub packs: HashMap<Uuid, PackEconomyStats>,
+}
+
+/// Rarities the premium guarantee slot draws from
+const GUARANTEED_RARITIES: [&str; 3] = ["rare", "epic", "legendary"];
+
+/// Premium packs deal their first reward uniformly from the rare+ templates
+fn has_rare_guarantee(pack_type: &PackType) -> bool {
+    pack_type.r#type == "premium" && pack_type.price_coins.unwrap_or(0) >= 299
+}
+
+/// The premium guarantee slot: uniform over the pool's rare+ templates, ignoring weights.
+/// `None` when the pool has no rare+ template.
+fn pick_rare_guarantee<'a>(pool: &'a RewardPool, rng: &dyn RngProvider) -> Option<&'a RewardTemplate> {
+    let mut guaranteed_pool = Vec::new();
+    for rarity in GUARANTEED_RARITIES {
+        guaranteed_pool.extend(pool.get_by_rarity(rarity));
+    }
+
+    if guaranteed_pool.is_empty() {
+        return None;
+    }
+    Some(guaranteed_pool[rng.gen_index(guaranteed_pool.len())])
+}
+
+/// Low-tier packs open in a batch without the reveal when the user asks for it
+pub(crate) fn is_low_tier(pack_type: &PackType) -> bool {
+    !has_rare_guarantee(pack_type) && pack_type.price_coins.unwrap_or(0) <= AUTO_OPEN_MAX_PRICE
+}
+
+/// The best reward of an auto-open batch
+#[derive(Debug, Serialize)]
+pub struct AutoOpenHighlight {
+    pub title: String,
+    pub rarity: String,
+    pub pack_history_id: Uuid,
+}
+
+/// Compact result of opening queued low-tier packs in one go
+#[derive(Debug, Serialize)]
+pub struct AutoOpenSummary {
+    pub packs_opened: usize,
+    pub rewards: usize,
+    pub rewards_by_rarity: HashMap<String, usize>,
+    pub coins_credited: i32,
+    pub best_reward: Option<AutoOpenHighlight>,
+    /// Low-tier packs still queued because the batch was full
+    pub remaining: i64,
+    pub completed_sets: Vec<CompletedSet>,
+    pub updated_stats: Option<UserStatsResponse>,
+}
+
+/// Stats for the profile screen, with savings in the user's display currency
+#[derive(Debug, Serialize)]
+pub struct UserStatsView {
+    #[serde(flatten)]
+    pub stats: UserStatsResponse,
+    pub display_currency: String,
+    pub total_savings: Money,
+    /// Packs bought or granted and waiting to be opened
+    pub unopened_packs: i64,
+    /// The streak lapses unless the daily pack is claimed by then
+    pub streak_deadline: Option<DateTime<Utc>>,
+    pub prestige: PrestigeView,
+    /// `None` for users who never subscribed
+    pub vip: Option<VipStatus>,
+}
+
+/// A pack bought into the inventory, to be opened later through `open_granted_pack`
+#[derive(Debug, Serialize)]
+pub struct PackPurchase {
+    pub user_pack_id: Uuid,
+    pub pack_type_id: Uuid,
+    pub price_paid: i32,
+    pub new_balance: i32,
+    /// Bonus pack for the user's first coin purchase
+    pub first_purchase_bonus: Option<Uuid>,
+}
+
+/// Pack type as listed to a user, with live pricing, any running happy hour
+/// and when the pack comes off cooldown
+#[derive(Debug, Serialize)]
+pub struct PackListing {
+    #[serde(flatten)]
+    pub pack: PackType,
+    pub pricing: PackPricing,
+    pub happy_hour: Option<HappyHourBadge>,
+    pub next_available_at: Option<DateTime<Utc>>,
+    /// Current pool and next swap for rotating packs
+    pub rotation: Option<RotationInfo>,
+    /// CDN URL of the uploaded icon; clients fall back to `icon` when absent
+    pub icon_url: Option<String>,
+    /// How this paid pack is sold in the user's region; `None` for free packs
+    pub purchase_mode: Option<PaidRandomMode>,
+    /// Odds the user must review before opening, where the region requires it
+    pub odds_disclosure: Option<OddsDisclosure>,
+    /// Buying this pack would be the user's first coin purchase and earn a bonus pack
+    pub first_purchase_bonus: bool,
+}
+
+/// How rewards are picked when a pack is opened
+#[derive(Debug, Clone)]
+enum RewardSelection {
+    Random,
+    /// Random, but drawn from a committed server seed so the open can be verified
+    Seeded(FairOpenRequest),
+    /// The buyer picked this template (choose-your-reward regions)
+    Chosen(Uuid),
+    /// Deal hidden options for the user to pick one of in a second request
+    Mystery,
+}
+
+/// Result of opening a pack: the opened rewards plus the history row they were recorded
+/// under, a token to reroll one of them and, for provably fair opens, the revealed seeds
+/// and derivation
+#[derive(Debug, Serialize)]
+pub struct PackOpenResult {
+    #[serde(flatten)]
+    pub opened: OpenPackResponse,
+    pub pack_history_id: Uuid,
+    pub reroll: Option<RerollOffer>,
+    pub fairness: Option<FairnessProof>,
+    /// Reward sets this open completed; each granted a bonus pack
+    pub completed_sets: Vec<CompletedSet>,
+    /// For mystery opens, the pending choice to pick from; `rewards` is empty until then
+    pub mystery: Option<MysteryOffer>,
+    /// Server-side sequencing of `rewards` for the reveal animation
+    pub reveal: RevealPlan,
+    /// Bonus pack put in the inventory for the user's first coin purchase
+    pub first_purchase_bonus: Option<Uuid>,
+}
+
+/// What `grant_pack_rewards` dealt and recorded for one open
+struct GrantedRewards {
+    pack_history_id: Uuid,
+    rewards: Vec<GeneratedReward>,
+    fairness: Option<FairnessProof>,
+    completed_sets: Vec<CompletedSet>,
+    mystery: Option<MysteryOffer>,
+    reveal: RevealPlan,
+}
+
+/// Lets the user pay `fee_coins` to redraw one reward from the open until `expires_at`
+#[derive(Debug, Serialize)]
+pub struct RerollOffer {
+    pub token: Uuid,
+    pub fee_coins: i32,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RerollRequest {
+    pub token: Uuid,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RerollResponse {
+    pub reward: GeneratedReward,
+    pub new_balance: i32,
+    pub completed_sets: Vec<CompletedSet>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MysteryPickRequest {
+    pub index: usize,
+    /// Also return the options that weren't picked
+    pub reveal_others: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MysteryPickResponse {
+    pub reward: GeneratedReward,
+    pub new_balance: Option<i32>,
+    /// The discarded options, when `reveal_others` was set
+    pub others: Option<Vec<SampleReward>>,
+    pub completed_sets: Vec<CompletedSet>,
+}
+
+/// Result of refunding a pack open
+#[derive(Debug, Serialize)]
+pub struct PackRefundResponse {
+    pub pack_history_id: Uuid,
+    pub user_id: String,
+    /// Coins returned to the user; negative when the open credited more than it cost
+    pub refunded_coins: i32,
+    pub new_balance: Option<i32>,
+    /// Unredeemed rewards the user still held, now unusable
+    pub voided_reward_ids: Vec<Uuid>,
+    /// Rewards already redeemed, in fulfilment or traded away; left alone but flagged
+    pub flagged_reward_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WheelSpinResponse {
+    pub wheel_id: Uuid,
+    /// Segment the wheel stopped on
+    pub position: i32,
+    pub reward: GeneratedReward,
+    pub coins_credited: i32,
+    pub new_balance: Option<i32>,
+    pub next_spin_at: DateTime<Utc>,
+    pub completed_sets: Vec<CompletedSet>,
+}
+
+/// A generated reward along with the template it was drawn from
+struct DrawnReward<'a> {
+    template: &'a RewardTemplate,
+    selected_id: Uuid, // Template the draw picked; differs from `template` after a coupon fallback
+    reward: GeneratedReward,
+    external_code: bool, // Code was claimed from the coupon provider and can't be redrawn
+}
 
 pub struct LootpackService {
     db: PgPool,
     reward_cache: tokio::sync::RwLock<HashMap<Uuid, RewardPool>>, // Cache for pack-specific reward pools
+    preview_cache: tokio::sync::RwLock<HashMap<(Uuid, String), (std::time::Instant, PackPreviewResponse)>>, // Example contents per pack and market
+    template_versions: tokio::sync::RwLock<HashMap<Uuid, HashMap<Uuid, i32>>>, // Template versions each cached pool was built from, per pack
+    rng: Arc<dyn RngProvider>,
+    coupon_provider: Arc<dyn CouponProvider>,
+    ad_verifier: Arc<dyn AdVerifier>,
+    user_directory: Arc<dyn UserDirectory>,
+    wallet: Arc<dyn CoinWallet>,
+    codes: Arc<CodeCipher>, // Encrypts reward codes at rest
+    personalization_strength: f64,
+    odds_provider: Arc<dyn OddsProvider>,
+    asset_base_url: Option<String>, // CDN base for uploaded icons; no icon URLs when unset
+    reroll_fee: Option<i32>, // Rerolls are off when unset
 }
 
 impl LootpackService {
     pub fn new(db: PgPool) -> Self {
+        Self::with_rng(db, Arc::new(ThreadRngProvider))
+    }
+
+    /// Build a service with an injected RNG (seeded in tests)
+    pub fn with_rng(db: PgPool, rng: Arc<dyn RngProvider>) -> Self {
         Self {
+            ad_verifier: Arc::new(TableAdVerifier::new(db.clone())),
             db,
             reward_cache: tokio::sync::RwLock::new(HashMap::new()),
+            preview_cache: tokio::sync::RwLock::new(HashMap::new()),
+            template_versions: tokio::sync::RwLock::new(HashMap::new()),
+            rng,
+            coupon_provider: Arc::new(LocalCouponProvider),
+            user_directory: Arc::new(OpenUserDirectory),
+            wallet: Arc::new(LocalWallet),
+            codes: Arc::new(CodeCipher::plaintext()),
+            personalization_strength: DEFAULT_PERSONALIZATION_STRENGTH,
+            odds_provider: Arc::new(StaticOddsProvider),
+            asset_base_url: None,
+            reroll_fee: Some(DEFAULT_REROLL_FEE),
         }
     }
 
-    /// Get all available pack types
-    pub async fn get_pack_types(&self) -> Result<Vec<PackType>> {
+    /// Adjust drop weights per user segment
+    pub fn with_odds_provider(mut self, odds_provider: Arc<dyn OddsProvider>) -> Self {
+        self.odds_provider = odds_provider;
+        self
+    }
+
+    /// How strongly top-category templates are boosted; 0 disables personalization. The
+    /// boost can't exceed the odds guardrail, which also bounds it combined with segment odds.
+    pub fn with_personalization_strength(mut self, strength: f64) -> Self {
+        self.personalization_strength = if strength.is_finite() { strength.clamp(0.0, MAX_ODDS_DEVIATION - 1.0) } else { 0.0 };
+        self
+    }
+
+    /// Source coupon/voucher codes from an external provider instead of generating them
+    pub fn with_coupon_provider(mut self, coupon_provider: Arc<dyn CouponProvider>) -> Self {
+        self.coupon_provider = coupon_provider;
+        self
+    }
+
+    /// Check ad completions with the ads service instead of its shared table
+    pub fn with_ad_verifier(mut self, ad_verifier: Arc<dyn AdVerifier>) -> Self {
+        self.ad_verifier = ad_verifier;
+        self
+    }
+
+    /// Only provision stats for ids the user service knows
+    pub fn with_user_directory(mut self, user_directory: Arc<dyn UserDirectory>) -> Self {
+        self.user_directory = user_directory;
+        self
+    }
+
+    /// Hold DealCoins in a wallet other than the local balance column
+    pub fn with_wallet(mut self, wallet: Arc<dyn CoinWallet>) -> Self {
+        self.wallet = wallet;
+        self
+    }
+
+    /// Encrypt reward codes before storing them; they are stored in plaintext otherwise
+    pub fn with_code_cipher(mut self, codes: Arc<CodeCipher>) -> Self {
+        self.codes = codes;
+        self
+    }
+
+    /// Serve uploaded icons from this CDN base URL
+    pub fn with_asset_base_url(mut self, base_url: String) -> Self {
+        self.asset_base_url = Some(base_url);
+        self
+    }
+
+    /// Coins charged to reroll a reward; `None` turns rerolls off
+    pub fn with_reroll_fee(mut self, fee: Option<i32>) -> Self {
+        self.reroll_fee = fee.map(|f| f.max(0));
+        self
+    }
+
+    fn icon_url(&self, storage_key: Option<String>) -> Option<String> {
+        let base_url = self.asset_base_url.as_deref()?;
+        storage_key.map(|key| assets::cdn_url(base_url, &key))
+    }
+
+    /// Drop a pack's cached reward pool so the next open rebuilds it from the database
+    pub async fn invalidate_pack_cache(&self, pack_type_id: Uuid) {
+        self.reward_cache.write().await.remove(&pack_type_id);
+        self.template_versions.write().await.remove(&pack_type_id);
+        self.preview_cache.write().await.retain(|(id, _), _| *id != pack_type_id);
+    }
+
+    /// Drop every cached reward pool and template version
+    pub async fn clear_caches(&self) {
+        self.reward_cache.write().await.clear();
+        self.preview_cache.write().await.clear();
+        self.template_versions.write().await.clear();
+    }
+
+    /// Listen for cache flush notifications so out-of-process tools can drop stale pools
+    pub fn spawn_cache_flush_listener(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                let mut listener = match sqlx::postgres::PgListener::connect_with(&self.db).await {
+                    Ok(listener) => listener,
+                    Err(e) => {
+                        error!("Cache flush listener failed to connect: {:?}", e);
+                        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                        continue;
+                    }
+                };
+                if let Err(e) = listener.listen(CACHE_FLUSH_CHANNEL).await {
+                    error!("Cache flush listener failed to subscribe: {:?}", e);
+                    tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                    continue;
+                }
+
+                while let Ok(notification) = listener.recv().await {
+                    match notification.payload().parse::<Uuid>() {
+                        Ok(pack_type_id) => self.invalidate_pack_cache(pack_type_id).await,
+                        Err(_) => self.clear_caches().await,
+                    }
+                    info!("Flushed reward caches ({})", notification.payload());
+                }
+            }
+        })
+    }
+
+    /// Get all available pack types with any running discount and the user's cooldowns applied.
+    /// Paid packs follow the compliance policy for the user's region.
+    pub async fn get_pack_types(
+        &self,
+        user_id: &str,
+        locale: &Locale,
+        market: &Market,
+        subject: &ComplianceSubject,
+    ) -> Result<Vec<PackListing>> {
         let packs = sqlx::query_as!(
             PackType,
             r#"
@@ -29,20 +606,112 @@ impl LootpackService {
                    price_coins, cooldown_hours, min_rewards, max_rewards,
                    possible_reward_types, is_active, created_at, updated_at
             FROM pack_types 
-            WHERE is_active = true
+            WHERE is_active = true AND (markets IS NULL OR $1 = ANY(markets))
             ORDER BY 
                 CASE WHEN type = 'free' THEN 0 ELSE 1 END,
                 price_coins ASC NULLS FIRST
-            "#
+            "#,
+            market.code()
         )
         .fetch_all(&self.db)
         .await?;
 
-        Ok(packs)
+        let policy = compliance::policy_for(&self.db, &subject.region).await?;
+        let paid_mode = policy.mode_for(subject);
+        let mut packs: Vec<PackType> = packs
+            .into_iter()
+            .filter(|p| paid_mode != PaidRandomMode::Hide || !compliance::is_paid_random(p.price_coins, &p.r#type))
+            .collect();
+
+        let mut running_windows: HashMap<Uuid, Vec<ActiveHappyHour>> = HashMap::new();
+        for window in happy_hours::active_happy_hours(&self.db, None).await? {
+            running_windows.entry(window.pack_type_id).or_default().push(window);
+        }
+
+        // Disclosed odds are the ones this user's opens draw from: their market's pool with
+        // segment odds, personalization and any running happy hour applied
+        let mut disclosures: HashMap<Uuid, OddsDisclosure> = HashMap::new();
+        if policy.require_odds_disclosure && paid_mode == PaidRandomMode::Allow {
+            let acknowledged = compliance::acknowledged_packs(&self.db, user_id).await?;
+            let mut unavailable = std::collections::HashSet::new();
+            for pack in packs.iter().filter(|p| compliance::is_paid_random(p.price_coins, &p.r#type)) {
+                let pool = match self.get_user_reward_pool(user_id, pack.id, market).await {
+                    Ok(pool) => pool,
+                    Err(crate::error::AppError::NotFound(_)) => {
+                        // Nothing to deal in this market, so no odds to disclose and nothing to buy
+                        unavailable.insert(pack.id);
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                };
+                let happy_hour = happy_hours::odds_adjustment(
+                    running_windows.get(&pack.id).map(Vec::as_slice).unwrap_or_default()
+                );
+                disclosures.insert(pack.id, OddsDisclosure {
+                    text: policy.disclosure_text.clone(),
+                    odds_by_rarity: Self::odds_by_rarity(&happy_hour.apply(pool)),
+                    acknowledged: acknowledged.contains(&pack.id),
+                });
+            }
+            packs.retain(|p| !unavailable.contains(&p.id));
+        }
+
+        let discounts: HashMap<Uuid, pricing::PackDiscount> = pricing::active_discounts(&self.db)
+            .await?
+            .into_iter()
+            .map(|d| (d.pack_type_id, d))
+            .collect();
+
+        let cooldowns = cooldowns::next_available_times(&self.db, user_id).await?;
+        let rotations: HashMap<Uuid, RotationInfo> = rotations::current_rotations(&self.db).await?.into_iter().collect();
+        let mut icon_keys = assets::pack_icon_keys(&self.db).await?;
+
+        let pack_ids: Vec<Uuid> = packs.iter().map(|p| p.id).collect();
+        let mut translations = localization::pack_translations(&self.db, &pack_ids, locale).await?;
+        let first_purchase = first_purchase::eligible(&self.db, user_id).await?;
+
+        Ok(packs
+            .into_iter()
+            .map(|mut pack| {
+                if let Some(translation) = translations.remove(&pack.id) {
+                    pack.name = translation.text;
+                    pack.description = translation.description.or(pack.description);
+                }
+                pack
+            })
+            .map(|pack| {
+                let pricing = PackPricing::compute(pack.price_coins, discounts.get(&pack.id));
+                (pack, pricing)
+            })
+            .map(|(pack, pricing)| PackListing {
+                first_purchase_bonus: first_purchase && pack.r#type != "free" && pricing.price.unwrap_or(0) > 0,
+                pricing,
+                happy_hour: running_windows.get(&pack.id).and_then(|w| HappyHourBadge::from_windows(w)),
+                next_available_at: cooldowns.get(&pack.id).copied(),
+                rotation: rotations.get(&pack.id).cloned(),
+                icon_url: self.icon_url(icon_keys.remove(&pack.id)),
+                purchase_mode: compliance::is_paid_random(pack.price_coins, &pack.r#type).then_some(paid_mode),
+                odds_disclosure: disclosures.remove(&pack.id),
+                pack,
+            })
+            .collect())
+    }
+
+    /// Share of the pool's weight in each rarity
+    fn odds_by_rarity(pool: &RewardPool) -> HashMap<String, f64> {
+        let mut odds: HashMap<String, f64> = HashMap::new();
+        if pool.total_weight <= 0 {
+            return odds;
+        }
+        for weighted in &pool.rewards {
+            *odds.entry(weighted.template.rarity.clone()).or_default() +=
+                weighted.weight as f64 / pool.total_weight as f64;
+        }
+        odds
     }
 
     /// Get user lootpack statistics
-    pub async fn get_user_stats(&self, user_id: &str) -> Result<UserStatsResponse> {
+    pub async fn get_user_stats(&self, user_id: &str) -> Result<UserStatsView> {
         // Try to get existing stats
         let stats = sqlx::query_as!(
             UserLootpackStats,
@@ -61,56 +730,289 @@ impl LootpackService {
         let stats = match stats {
             Some(s) => s,
             None => {
-                // Create default stats for new user
+                if !self.user_directory.exists(user_id).await? {
+                    return Err(crate::error::AppError::NotFound("User not found".to_string()));
+                }
+
+                // Create default stats for new user; the starting balance is minted through the ledger
+                let mut tx = self.db.begin().await?;
                 let new_stats = sqlx::query_as!(
                     UserLootpackStats,
                     r#"
                     INSERT INTO user_lootpack_stats 
                     (user_id, deal_coins, daily_streak, total_packs_opened, level, 
                      level_progress, total_savings_inr, member_status, puzzle_pieces, puzzle_packs_claimed)
-                    VALUES ($1, 500, 1, 0, 1, 0, 0, 'Bronze', 0, 0)
+                    VALUES ($1, $2, 1, 0, 1, 0, 0, 'Bronze', 0, 0)
                     RETURNING user_id, deal_coins, daily_streak, last_daily_claim,
                              total_packs_opened, level, level_progress, total_savings_inr,
                              member_status, puzzle_pieces, puzzle_packs_claimed, created_at, updated_at
                     "#,
-                    user_id
+                    user_id,
+                    STARTING_DEAL_COINS
                 )
-                .fetch_one(&self.db)
+                .fetch_one(&mut *tx)
                 .await?;
+                ledger::record(&mut tx, user_id, STARTING_DEAL_COINS, "starting_balance", None, STARTING_DEAL_COINS).await?;
+                tx.commit().await?;
                 new_stats
             }
         };
 
-        // Check if user can claim daily pack
-        let now = Utc::now();
-        let can_claim_daily = stats.last_daily_claim
-            .map(|last_claim| now.signed_duration_since(last_claim) >= Duration::hours(24))
-            .unwrap_or(true);
+        let display_currency = currency::display_currency(&self.db, user_id).await?;
+        let rates = RateTable::load(&self.db).await?;
+        let savings = currency::user_savings(&self.db, user_id).await?;
+        let unopened_packs = user_packs::count_unopened(&self.db, user_id).await?;
+        let mut conn = self.db.acquire().await?;
+        let clock = daily_reset::load(&mut conn, user_id).await?;
+        let prestige = prestige::view(&mut conn, user_id, stats.level.unwrap_or(1)).await?;
+        let vip = vip::status(&mut conn, user_id).await?;
+        let free_claims = free_claims::view(&mut conn, user_id).await?;
+
+        Ok(UserStatsView {
+            stats: Self::stats_response(stats, &clock),
+            total_savings: rates.total(&savings, &display_currency),
+            display_currency,
+            unopened_packs,
+            streak_deadline: clock.streak_deadline(Utc::now()),
+            prestige,
+            vip,
+            free_claims,
+        })
+    }
+
+    /// Open a purchasable pack and generate rewards using DSA-optimized selection.
+    /// The daily free pack is claimed through `claim_daily_pack` instead. With `fairness`,
+    /// rewards are drawn from the user's committed server seed and the proof is returned.
+    pub async fn open_pack(
+        &self,
+        user_id: &str,
+        pack_type_id: Uuid,
+        locale: &Locale,
+        market: &Market,
+        subject: &ComplianceSubject,
+        fairness: Option<FairOpenRequest>,
+    ) -> Result<PackOpenResult> {
+        let selection = fairness.map(RewardSelection::Seeded).unwrap_or(RewardSelection::Random);
+        self.purchase_pack(user_id, pack_type_id, locale, market, subject, selection).await
+    }
+
+    /// Buy a pack in a choose-your-reward region: the buyer picks one template from the
+    /// pack's pool and pays the pack price for exactly that reward.
+    pub async fn choose_reward(
+        &self,
+        user_id: &str,
+        pack_type_id: Uuid,
+        template_id: Uuid,
+        locale: &Locale,
+        market: &Market,
+        subject: &ComplianceSubject,
+    ) -> Result<PackOpenResult> {
+        self.purchase_pack(user_id, pack_type_id, locale, market, subject, RewardSelection::Chosen(template_id)).await
+    }
+
+    /// Buy a pack as a mystery box: three hidden rewards are dealt and the user keeps the
+    /// one picked through `pick_mystery_reward`. An unpicked box is refunded on expiry.
+    pub async fn open_mystery_pack(
+        &self,
+        user_id: &str,
+        pack_type_id: Uuid,
+        locale: &Locale,
+        market: &Market,
+        subject: &ComplianceSubject,
+    ) -> Result<PackOpenResult> {
+        self.purchase_pack(user_id, pack_type_id, locale, market, subject, RewardSelection::Mystery).await
+    }
+
+    async fn purchase_pack(
+        &self,
+        user_id: &str,
+        pack_type_id: Uuid,
+        locale: &Locale,
+        market: &Market,
+        subject: &ComplianceSubject,
+        selection: RewardSelection,
+    ) -> Result<PackOpenResult> {
+        let mut tx = self.db.begin().await?;
+
+        // Get pack type and validate; packs not offered in the user's market don't exist for them
+        let pack_type = sqlx::query_as!(
+            PackType,
+            r#"
+            SELECT id, name, type, description, icon, color_gradient, 
+                   price_coins, cooldown_hours, min_rewards, max_rewards,
+                   possible_reward_types, is_active, created_at, updated_at
+            FROM pack_types 
+            WHERE id = $1 AND is_active = true AND (markets IS NULL OR $2 = ANY(markets))
+            "#,
+            pack_type_id,
+            market.code()
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| crate::error::AppError::NotFound("Pack type not found".to_string()))?;
+
+        if pack_type.r#type == "free" {
+            return Err(crate::error::AppError::BadRequest(
+                "Free packs are claimed through /daily/claim".to_string()
+            ));
+        }
+
+        if compliance::is_paid_random(pack_type.price_coins, &pack_type.r#type) {
+            let policy = compliance::policy_for(&mut *tx, &subject.region).await?;
+            let chosen = matches!(selection, RewardSelection::Chosen(_));
+            compliance::check_paid_open(&mut *tx, user_id, pack_type.id, &policy, subject, chosen).await?;
+        } else if matches!(selection, RewardSelection::Chosen(_)) {
+            return Err(crate::error::AppError::BadRequest(
+                "Choosing a reward isn't available for this pack".to_string()
+            ));
+        }
+
+        cooldowns::enforce(&mut tx, user_id, &pack_type).await?;
+
+        // Lock the user's stats row so concurrent opens can't overspend
+        let user_stats = sqlx::query_as!(
+            UserLootpackStats,
+            "SELECT * FROM user_lootpack_stats WHERE user_id = $1 FOR UPDATE",
+            user_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| crate::error::AppError::BadRequest("Insufficient DealCoins".to_string()))?;
+
+        // Charge the discounted price if a discount window is running
+        let discount = pricing::active_discount(&mut *tx, pack_type.id).await?;
+        let pack_cost = PackPricing::compute(pack_type.price_coins, discount.as_ref())
+            .price
+            .unwrap_or(0);
+        if user_stats.deal_coins.unwrap_or(0) < pack_cost {
+            return Err(crate::error::AppError::BadRequest(
+                "Insufficient DealCoins".to_string()
+            ));
+        }
+        if pack_cost > 0 {
+            self_exclusion::enforce(&mut *tx, user_id).await?;
+            chargebacks::enforce(&mut *tx, user_id).await?;
+        }
+        spending_limits::enforce(&mut tx, user_id, pack_cost).await?;
+        teams::record_spend(&mut tx, user_id, pack_cost).await?;
 
-        let next_daily_claim = if can_claim_daily {
+        // Chosen rewards and provably fair opens can't be rerolled
+        let rerollable = matches!(selection, RewardSelection::Random);
+        let granted = self.grant_pack_rewards(&mut tx, user_id, &pack_type, locale, market, selection).await?;
+        let mut wallet_entries = Vec::new();
+        let updated_stats = self.apply_pack_open(
+            &mut tx, user_stats, granted.pack_history_id, &granted.rewards, pack_cost, &mut wallet_entries,
+        ).await?;
+        if let Some(offer) = &granted.mystery {
+            mystery_boxes::set_price(&mut tx, offer.choice_id, pack_cost).await?;
+        }
+        let reroll = if rerollable {
+            self.offer_reroll(&mut tx, user_id, granted.pack_history_id, pack_type.id).await?
+        } else {
             None
+        };
+        let first_purchase_bonus = if pack_cost > 0 {
+            first_purchase::claim(&mut tx, user_id, pack_type.id, Purchase::Open(granted.pack_history_id)).await?
         } else {
-            stats.last_daily_claim.map(|last| last + Duration::hours(24))
+            None
         };
+        let clock = daily_reset::load(&mut tx, user_id).await?;
 
-        Ok(UserStatsResponse {
-            deal_coins: stats.deal_coins.unwrap_or(500),
-            daily_streak: stats.daily_streak.unwrap_or(1),
-            total_packs_opened: stats.total_packs_opened.unwrap_or(0),
-            level: stats.level.unwrap_or(1),
-            level_progress: stats.level_progress.unwrap_or(0),
-            member_status: stats.member_status.unwrap_or_else(|| "Bronze".to_string()),
-            can_claim_daily,
-            next_daily_claim,
+        self.commit_with_wallet(tx, &wallet_entries).await?;
+
+        info!("User {} opened pack {} and received {} rewards", 
+              privacy::pseudonym(user_id), pack_type.name, granted.rewards.len());
+
+        Ok(PackOpenResult {
+            opened: OpenPackResponse {
+                rewards: granted.rewards,
+                updated_stats: Self::stats_response(updated_stats, &clock),
+            },
+            pack_history_id: granted.pack_history_id,
+            reroll,
+            fairness: granted.fairness,
+            completed_sets: granted.completed_sets,
+            mystery: granted.mystery,
+            reveal: granted.reveal,
+            first_purchase_bonus,
+        })
+    }
+
+    /// Buy a pack without opening it. The purchase is charged and checked exactly like
+    /// an open; the pack goes to the user's inventory and opens for free later. Cooldowns
+    /// apply to opening, so they aren't checked here.
+    pub async fn buy_pack(
+        &self,
+        user_id: &str,
+        pack_type_id: Uuid,
+        market: &Market,
+        subject: &ComplianceSubject,
+    ) -> Result<PackPurchase> {
+        let mut tx = self.db.begin().await?;
+
+        let pack_type = sqlx::query!(
+            r#"
+            SELECT id, name, type, price_coins FROM pack_types
+            WHERE id = $1 AND is_active = true AND (markets IS NULL OR $2 = ANY(markets))
+            "#,
+            pack_type_id,
+            market.code()
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| crate::error::AppError::NotFound("Pack type not found".to_string()))?;
+
+        if pack_type.price_coins.unwrap_or(0) <= 0 {
+            return Err(crate::error::AppError::BadRequest(
+                "Only paid packs can be bought for later".to_string()
+            ));
+        }
+        if compliance::is_paid_random(pack_type.price_coins, &pack_type.r#type) {
+            let policy = compliance::policy_for(&mut *tx, &subject.region).await?;
+            compliance::check_paid_open(&mut *tx, user_id, pack_type.id, &policy, subject, false).await?;
+        }
+
+        // Same lock as opens, so limits see purchases and opens in a consistent order
+        sqlx::query!("SELECT user_id FROM user_lootpack_stats WHERE user_id = $1 FOR UPDATE", user_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| crate::error::AppError::BadRequest("Insufficient DealCoins".to_string()))?;
+
+        let discount = pricing::active_discount(&mut *tx, pack_type.id).await?;
+        let price = PackPricing::compute(pack_type.price_coins, discount.as_ref())
+            .price
+            .unwrap_or(0);
+        self_exclusion::enforce(&mut *tx, user_id).await?;
+        chargebacks::enforce(&mut *tx, user_id).await?;
+        spending_limits::enforce(&mut tx, user_id, price).await?;
+        teams::record_spend(&mut tx, user_id, price).await?;
+
+        let user_pack_id = user_packs::grant(&mut tx, user_id, pack_type.id, "purchase", None).await?;
+        let wallet_entry =
+            self.wallet.apply(&mut tx, user_id, -price, "pack_purchase", Some(&user_pack_id.to_string())).await?;
+        let first_purchase_bonus = if price > 0 {
+            first_purchase::claim(&mut tx, user_id, pack_type.id, Purchase::Inventory(user_pack_id)).await?
+        } else {
+            None
+        };
+
+        self.commit_with_wallet(tx, std::slice::from_ref(&wallet_entry)).await?;
+
+        info!("User {} bought pack {} for later", privacy::pseudonym(user_id), pack_type.name);
+
+        Ok(PackPurchase {
+            user_pack_id,
+            pack_type_id: pack_type.id,
+            price_paid: price,
+            new_balance: wallet_entry.balance,
+            first_purchase_bonus,
         })
     }
 
-    /// Open a pack and generate rewards using DSA-optimized selection
-    /// Enhanced to support ad requirements for free packs
-    pub async fn open_pack(&self, user_id: &str, pack_type_id: Uuid) -> Result<OpenPackResponse> {
+    /// Claim the daily free pack. Owns the cooldown, ad-gating and streak rules.
+    pub async fn claim_daily_pack(&self, user_id: &str, locale: &Locale, market: &Market) -> Result<PackOpenResult> {
         let mut tx = self.db.begin().await?;
 
-        // Get pack type and validate
         let pack_type = sqlx::query_as!(
             PackType,
             r#"
@@ -118,266 +1020,1826 @@ impl LootpackService {
                    price_coins, cooldown_hours, min_rewards, max_rewards,
                    possible_reward_types, is_active, created_at, updated_at
             FROM pack_types 
-            WHERE id = $1 AND is_active = true
+            WHERE type = 'free' AND is_active = true AND (markets IS NULL OR $1 = ANY(markets))
+            ORDER BY created_at
+            LIMIT 1
             "#,
-            pack_type_id
+            market.code()
         )
         .fetch_optional(&mut *tx)
         .await?
-        .ok_or_else(|| crate::error::AppError::NotFound("Pack type not found".to_string()))?;
+        .ok_or_else(|| crate::error::AppError::NotFound("Daily pack not available".to_string()))?;
 
-        // Get user stats
-        let user_stats = sqlx::query_as!(
+        let mut user_stats = sqlx::query_as!(
             UserLootpackStats,
-            "SELECT * FROM user_lootpack_stats WHERE user_id = $1",
+            "SELECT * FROM user_lootpack_stats WHERE user_id = $1 FOR UPDATE",
             user_id
         )
         .fetch_optional(&mut *tx)
-        .await?;
+        .await?
+        .ok_or_else(|| crate::error::AppError::InternalError(
+            "Failed to update user stats".to_string()
+        ))?;
 
-        // Enhanced validation for free packs - check if ad was watched recently
-        if pack_type.r#type == "free" {
-            if let Some(stats) = &user_stats {
-                if let Some(last_claim) = stats.last_daily_claim {
-                    let time_since_last = Utc::now().signed_duration_since(last_claim);
-                    if time_since_last < Duration::hours(24) {
-                        return Err(crate::error::AppError::BadRequest(
-                            "Daily pack still on cooldown".to_string()
-                        ));
-                    }
-                }
-            }
-            
-            // Check if user has watched ad for daily pack in the last hour
-            // This provides flexibility while preventing abuse
-            let recent_daily_ad = sqlx::query!(
-                r#"
-                SELECT id FROM user_ad_interactions 
-                WHERE user_id = $1 AND ad_placement = 'daily_pack_ad' 
-                AND is_completed = true AND completed_at > NOW() - INTERVAL '1 hour'
-                ORDER BY completed_at DESC LIMIT 1
-                "#,
-                user_id
-            )
-            .fetch_optional(&mut *tx)
+        // Daily packs reset at the market's reset hour in the user's timezone. That reset is
+        // the daily pack's only cooldown; a rolling `cooldown_hours` would push claims past it.
+        let now = Utc::now();
+        let clock = daily_reset::load(&mut tx, user_id).await?;
+        if clock.next_claim(now).is_some() {
+            return Err(crate::error::AppError::BadRequest(
+                "Daily pack still on cooldown".to_string()
+            ));
+        }
+
+        // Check if user has watched ad for daily pack in the last hour
+        // This provides flexibility while preventing abuse
+        let watched_ad = self
+            .ad_verifier
+            .verify_recent_completion(user_id, DAILY_PACK_AD_PLACEMENT, Duration::hours(DAILY_PACK_AD_WINDOW_HOURS))
             .await?;
 
-            if recent_daily_ad.is_none() {
-                return Err(crate::error::AppError::BadRequest(
-                    "Please watch an ad to claim your daily free pack".to_string()
-                ));
+        if !watched_ad {
+            return Err(crate::error::AppError::BadRequest(
+                "Please watch an ad to claim your daily free pack".to_string()
+            ));
+        }
+        free_claims::consume(&mut tx, user_id).await?;
+
+        // Update daily streak; a lapsed streak stays repairable for a while
+        let current_streak = user_stats.daily_streak.unwrap_or(1);
+        if let Some(broken_at) = streak_repairs::lapsed_at(&clock, current_streak, now) {
+            streak_repairs::record_break(&mut tx, user_id, current_streak, broken_at).await?;
+        }
+        // First claims and claims after the streak lapsed start over
+        user_stats.daily_streak = Some(if clock.continues_streak(now) { current_streak + 1 } else { 1 });
+        user_stats.last_daily_claim = Some(now);
+
+        let granted = self.grant_pack_rewards(&mut tx, user_id, &pack_type, locale, market, RewardSelection::Random).await?;
+        let mut wallet_entries = Vec::new();
+        let updated_stats = self.apply_pack_open(
+            &mut tx, user_stats, granted.pack_history_id, &granted.rewards, 0, &mut wallet_entries,
+        ).await?;
+        let reset_hour = daily_reset::reset_hour(&mut tx, market).await?;
+        // Claiming means the user is back, so any vacation ends
+        vacations::end_on_claim(&mut tx, user_id, now).await?;
+        let clock = daily_reset::record_claim(&mut tx, user_id, now, reset_hour).await?;
+
+        self.commit_with_wallet(tx, &wallet_entries).await?;
+
+        info!("User {} claimed daily pack and received {} rewards", 
+              privacy::pseudonym(user_id), granted.rewards.len());
+
+        Ok(PackOpenResult {
+            opened: OpenPackResponse {
+                rewards: granted.rewards,
+                updated_stats: Self::stats_response(updated_stats, &clock),
+            },
+            pack_history_id: granted.pack_history_id,
+            // A reroll is a paid random draw, which the free daily pack doesn't lead into
+            reroll: None,
+            fairness: None,
+            completed_sets: granted.completed_sets,
+            mystery: granted.mystery,
+            reveal: granted.reveal,
+            first_purchase_bonus: None,
+        })
+    }
+
+    /// Open a pack from the user's unopened inventory (purchase, promo, compensation, entitlement).
+    /// The pack was already paid for or granted, so no coins are charged.
+    /// Granted packs open in any market, but only draw templates offered in the user's market.
+    pub async fn open_granted_pack(&self, user_id: &str, user_pack_id: Uuid, locale: &Locale, market: &Market) -> Result<PackOpenResult> {
+        let mut tx = self.db.begin().await?;
+
+        let user_pack = sqlx::query!(
+            r#"
+            SELECT id, pack_type_id, source FROM user_packs
+            WHERE id = $1 AND user_id = $2 AND status = 'unopened'
+              AND (expires_at IS NULL OR expires_at > NOW())
+            FOR UPDATE
+            "#,
+            user_pack_id,
+            user_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| crate::error::AppError::NotFound("Pack not found".to_string()))?;
+        if free_claims::counts_toward_quota(&user_pack.source) {
+            free_claims::consume(&mut tx, user_id).await?;
+        }
+
+        let pack_type = sqlx::query_as!(
+            PackType,
+            r#"
+            SELECT id, name, type, description, icon, color_gradient, 
+                   price_coins, cooldown_hours, min_rewards, max_rewards,
+                   possible_reward_types, is_active, created_at, updated_at
+            FROM pack_types 
+            WHERE id = $1
+            "#,
+            user_pack.pack_type_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let user_stats = sqlx::query_as!(
+            UserLootpackStats,
+            "SELECT * FROM user_lootpack_stats WHERE user_id = $1 FOR UPDATE",
+            user_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| crate::error::AppError::InternalError(
+            "Failed to update user stats".to_string()
+        ))?;
+
+        let granted = self.grant_pack_rewards(&mut tx, user_id, &pack_type, locale, market, RewardSelection::Random).await?;
+        let mut wallet_entries = Vec::new();
+        let updated_stats = self.apply_pack_open(
+            &mut tx, user_stats, granted.pack_history_id, &granted.rewards, 0, &mut wallet_entries,
+        ).await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE user_packs SET status = 'opened', opened_at = NOW(), pack_history_id = $2
+            WHERE id = $1
+            "#,
+            user_pack.id,
+            granted.pack_history_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let reroll = self.offer_reroll(&mut tx, user_id, granted.pack_history_id, pack_type.id).await?;
+        let clock = daily_reset::load(&mut tx, user_id).await?;
+        self.commit_with_wallet(tx, &wallet_entries).await?;
+
+        info!("User {} opened granted pack {} and received {} rewards", 
+              privacy::pseudonym(user_id), pack_type.name, granted.rewards.len());
+
+        Ok(PackOpenResult {
+            opened: OpenPackResponse {
+                rewards: granted.rewards,
+                updated_stats: Self::stats_response(updated_stats, &clock),
+            },
+            pack_history_id: granted.pack_history_id,
+            reroll,
+            fairness: None,
+            completed_sets: granted.completed_sets,
+            mystery: granted.mystery,
+            reveal: granted.reveal,
+            first_purchase_bonus: None,
+        })
+    }
+
+    /// Open every queued low-tier pack in the user's inventory in one transaction and
+    /// return a summary instead of individual reveals. Requires the auto-open preference.
+    /// No reroll tokens are issued for batch opens.
+    pub async fn auto_open_packs(&self, user_id: &str, locale: &Locale, market: &Market) -> Result<AutoOpenSummary> {
+        let mut tx = self.db.begin().await?;
+
+        let enabled = sqlx::query_scalar!(
+            "SELECT auto_open_low_tier FROM user_open_preferences WHERE user_id = $1",
+            user_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .unwrap_or(false);
+        if !enabled {
+            return Err(crate::error::AppError::BadRequest(
+                "Turn on auto-open for low-tier packs first".to_string()
+            ));
+        }
+
+        let mut stats = sqlx::query_as!(
+            UserLootpackStats,
+            "SELECT * FROM user_lootpack_stats WHERE user_id = $1 FOR UPDATE",
+            user_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| crate::error::AppError::InternalError(
+            "Failed to update user stats".to_string()
+        ))?;
+        let mut wallet_entries = Vec::new();
+
+        let queued = sqlx::query!(
+            r#"
+            SELECT up.id, up.pack_type_id, up.source FROM user_packs up
+            JOIN pack_types pt ON pt.id = up.pack_type_id
+            WHERE up.user_id = $1 AND up.status = 'unopened'
+              AND (up.expires_at IS NULL OR up.expires_at > NOW())
+              AND COALESCE(pt.price_coins, 0) <= $2
+            ORDER BY up.expires_at ASC NULLS LAST, up.created_at
+            FOR UPDATE OF up
+            "#,
+            user_id,
+            AUTO_OPEN_MAX_PRICE
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let mut pack_types: HashMap<Uuid, PackType> = HashMap::new();
+        let mut summary = AutoOpenSummary {
+            packs_opened: 0,
+            rewards: 0,
+            rewards_by_rarity: HashMap::new(),
+            coins_credited: 0,
+            best_reward: None,
+            remaining: 0,
+            completed_sets: Vec::new(),
+            updated_stats: None,
+        };
+        let mut best_rank = 0;
+        for user_pack in queued {
+            if !pack_types.contains_key(&user_pack.pack_type_id) {
+                let pack_type = sqlx::query_as!(
+                    PackType,
+                    r#"
+                    SELECT id, name, type, description, icon, color_gradient, 
+                           price_coins, cooldown_hours, min_rewards, max_rewards,
+                           possible_reward_types, is_active, created_at, updated_at
+                    FROM pack_types 
+                    WHERE id = $1
+                    "#,
+                    user_pack.pack_type_id
+                )
+                .fetch_one(&mut *tx)
+                .await?;
+                pack_types.insert(pack_type.id, pack_type);
+            }
+            let pack_type = &pack_types[&user_pack.pack_type_id];
+            if !is_low_tier(pack_type) {
+                continue;
+            }
+            // Free packs past this month's quota wait in the inventory for the next one
+            if summary.packs_opened as i64 >= AUTO_OPEN_BATCH_SIZE
+                || (free_claims::counts_toward_quota(&user_pack.source) && !free_claims::try_consume(&mut tx, user_id).await?)
+            {
+                summary.remaining += 1;
+                continue;
+            }
+
+            let granted = self.grant_pack_rewards(&mut tx, user_id, pack_type, locale, market, RewardSelection::Random).await?;
+            stats = self.apply_pack_open(
+                &mut tx, stats, granted.pack_history_id, &granted.rewards, 0, &mut wallet_entries,
+            ).await?;
+            sqlx::query!(
+                r#"
+                UPDATE user_packs SET status = 'opened', opened_at = NOW(), pack_history_id = $2
+                WHERE id = $1
+                "#,
+                user_pack.id,
+                granted.pack_history_id
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            summary.packs_opened += 1;
+            summary.rewards += granted.rewards.len();
+            for reward in &granted.rewards {
+                *summary.rewards_by_rarity.entry(reward.rarity.clone()).or_default() += 1;
+                summary.coins_credited += Self::coin_value(reward);
+                let rank = reveal::rarity_rank(&reward.rarity);
+                if rank > best_rank {
+                    best_rank = rank;
+                    summary.best_reward = Some(AutoOpenHighlight {
+                        title: reward.title.clone(),
+                        rarity: reward.rarity.clone(),
+                        pack_history_id: granted.pack_history_id,
+                    });
+                }
+            }
+            summary.completed_sets.extend(granted.completed_sets);
+        }
+
+        if summary.packs_opened > 0 {
+            let clock = daily_reset::load(&mut tx, user_id).await?;
+            summary.updated_stats = Some(Self::stats_response(stats, &clock));
+        }
+        self.commit_with_wallet(tx, &wallet_entries).await?;
+
+        info!("User {} auto-opened {} low-tier packs", privacy::pseudonym(user_id), summary.packs_opened);
+        Ok(summary)
+    }
+
+    /// Issue the reroll token for an open, when rerolls are enabled
+    async fn offer_reroll(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        user_id: &str,
+        pack_history_id: Uuid,
+        pack_type_id: Uuid,
+    ) -> Result<Option<RerollOffer>> {
+        let Some(fee_coins) = self.reroll_fee else {
+            return Ok(None);
+        };
+        let expires_at = Utc::now() + Duration::minutes(REROLL_TOKEN_TTL_MINUTES);
+
+        let token = sqlx::query_scalar!(
+            r#"
+            INSERT INTO reroll_tokens (user_id, pack_history_id, pack_type_id, expires_at)
+            VALUES ($1, $2, $3, $4)
+            RETURNING token
+            "#,
+            user_id,
+            pack_history_id,
+            pack_type_id,
+            expires_at
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(Some(RerollOffer { token, fee_coins, expires_at }))
+    }
+
+    /// Pay to redraw one reward from a just-opened pack. The replacement comes from the
+    /// same (user-adjusted) pool minus the original template, and overwrites the original
+    /// inventory row in the same transaction that charges the fee and spends the token.
+    /// A paid reroll is a paid random draw and goes through the same checks as a paid open.
+    pub async fn reroll_reward(
+        &self,
+        user_id: &str,
+        reward_id: Uuid,
+        req: RerollRequest,
+        locale: &Locale,
+        market: &Market,
+        subject: &ComplianceSubject,
+    ) -> Result<RerollResponse> {
+        let fee = self.reroll_fee
+            .ok_or_else(|| crate::error::AppError::BadRequest("Rerolls are not available".to_string()))?;
+
+        let mut tx = self.db.begin().await?;
+
+        let token = sqlx::query!(
+            r#"
+            UPDATE reroll_tokens SET used_at = NOW()
+            WHERE token = $1 AND user_id = $2 AND used_at IS NULL AND expires_at > NOW()
+            RETURNING pack_history_id, pack_type_id
+            "#,
+            req.token,
+            user_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| crate::error::AppError::BadRequest("Reroll token is invalid, used or expired".to_string()))?;
+
+        let original = sqlx::query!(
+            r#"
+            SELECT template_id AS "template_id!", type FROM user_rewards
+            WHERE id = $1 AND user_id = $2 AND pack_history_id = $3 AND template_id IS NOT NULL
+              AND COALESCE(is_used, false) = false AND reserved_until IS NULL
+              AND escrow_trade_id IS NULL AND fulfillment_status IS NULL
+            FOR UPDATE
+            "#,
+            reward_id,
+            user_id,
+            token.pack_history_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| crate::error::AppError::BadRequest(
+            "That reward isn't from this open or has already been used".to_string()
+        ))?;
+        if reward_types::spec(&original.r#type).map(|t| t.credits_coins).unwrap_or(false) {
+            return Err(crate::error::AppError::BadRequest(
+                "Coin rewards were already credited and can't be rerolled".to_string()
+            ));
+        }
+
+        let pack_type = self.get_pack_type(token.pack_type_id).await?;
+        if pack_type.r#type == "free" {
+            return Err(crate::error::AppError::BadRequest("Rewards from free packs can't be rerolled".to_string()));
+        }
+
+        // Same lock as opens, so limits see rerolls and opens in a consistent order
+        sqlx::query!("SELECT user_id FROM user_lootpack_stats WHERE user_id = $1 FOR UPDATE", user_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| crate::error::AppError::BadRequest("Insufficient DealCoins".to_string()))?;
+        if fee > 0 {
+            let policy = compliance::policy_for(&mut *tx, &subject.region).await?;
+            compliance::check_paid_open(&mut *tx, user_id, pack_type.id, &policy, subject, false).await?;
+            self_exclusion::enforce(&mut *tx, user_id).await?;
+            chargebacks::enforce(&mut *tx, user_id).await?;
+        }
+        spending_limits::enforce(&mut tx, user_id, fee).await?;
+
+        let pool = self.get_user_reward_pool(user_id, pack_type.id, market).await?;
+
+        // Coins are only credited when a pack is opened, so a reroll can't land on them either
+        let candidates = RewardPool::from_weights(
+            pool.rewards
+                .iter()
+                .filter(|w| w.template.id != original.template_id)
+                .filter(|w| !reward_types::spec(&w.template.r#type).map(|t| t.credits_coins).unwrap_or(false))
+                .map(|w| (w.template.clone(), w.weight)),
+        );
+        if candidates.total_weight <= 0 {
+            return Err(crate::error::AppError::BadRequest(
+                "This pack has no other rewards to reroll into".to_string()
+            ));
+        }
+
+        let target_weight = self.rng.gen_range(1, candidates.total_weight);
+        let template = candidates
+            .select_by_weight(target_weight)
+            .and_then(|selected| candidates.rewards.iter().find(|w| w.template.id == selected.id))
+            .map(|w| &w.template)
+            .ok_or_else(|| crate::error::AppError::InternalError("Reroll selection failed".to_string()))?;
+        let mut drawn = self.draw_reward(&candidates, template, Some(&mut *tx)).await?;
+
+        let translations = localization::template_translations(&mut *tx, &[drawn.template.id], locale).await?;
+        if let Some(translation) = translations.get(&drawn.template.id) {
+            drawn.reward.title = translation.text.clone();
+            if let Some(description) = &translation.description {
+                drawn.reward.description = description.clone();
+            }
+        }
+
+        let wallet_entry = self.wallet.apply(&mut tx, user_id, -fee, "reward_reroll", Some(&reward_id.to_string())).await?;
+
+        let expires = reward_types::spec(&drawn.reward.r#type).map(|t| t.expires).unwrap_or(true);
+        let template_version = self
+            .template_versions
+            .read()
+            .await
+            .get(&pack_type.id)
+            .and_then(|versions| versions.get(&drawn.template.id).copied());
+        // A code collision fails the whole reroll; the token and coins are left untouched
+        let updated = sqlx::query!(
+            r#"
+            UPDATE user_rewards ur
+            SET template_id = $2, type = $3, title = $4, value = $5, description = $6, code = $7,
+                rarity = $8, expires_at = $9, merchant_id = rt.merchant_id, brand = rt.brand,
+                category = rt.category, value_amount = rt.value_amount,
+                value_currency = CASE WHEN rt.value_amount IS NOT NULL THEN rt.value_currency END,
+                template_version = $10, rerolled_from_template_id = $11, code_hash = $12
+            FROM reward_templates rt
+            WHERE ur.id = $1 AND rt.id = $2
+              AND NOT EXISTS (SELECT 1 FROM user_rewards legacy WHERE legacy.code_hash IS NULL AND legacy.code = $13)
+            "#,
+            reward_id,
+            drawn.template.id,
+            drawn.reward.r#type,
+            drawn.reward.title,
+            drawn.reward.value,
+            drawn.reward.description,
+            self.codes.seal_opt(drawn.reward.code.as_deref())?,
+            drawn.reward.rarity,
+            expires.then(|| Utc::now() + Duration::days(30)),
+            template_version,
+            original.template_id,
+            self.codes.blind_index_opt(drawn.reward.code.as_deref()),
+            drawn.reward.code
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if updated == 0 {
+            return Err(crate::error::AppError::InternalError(
+                "Rerolled code collides with an issued code".to_string()
+            ));
+        }
+
+        recent_pulls::record(&mut *tx, user_id, &[reward_id]).await?;
+        collection::record(&mut *tx, user_id, &[drawn.template.id]).await?;
+        let completed_sets = reward_sets::award_completed(&mut *tx, user_id, &[drawn.template.id]).await?;
+
+        audit::record(&mut *tx, user_id, "reward.rerolled", Some(&reward_id.to_string()), json!({
+            "pack_history_id": token.pack_history_id,
+            "from_template_id": original.template_id,
+            "to_template_id": drawn.template.id,
+            "fee": fee,
+        }))
+        .await?;
+
+        self.commit_with_wallet(tx, std::slice::from_ref(&wallet_entry)).await?;
+
+        info!("User {} rerolled reward {} from pack {}", privacy::pseudonym(user_id), reward_id, pack_type.name);
+
+        let mut reward = drawn.reward;
+        reward.id = reward_id.to_string();
+        Ok(RerollResponse { reward, new_balance: wallet_entry.balance, completed_sets })
+    }
+
+    /// Keep one option of a mystery box. The choice is claimed before anything is dealt, so
+    /// a box yields exactly one reward however many picks race; the rest are discarded.
+    pub async fn pick_mystery_reward(
+        &self,
+        user_id: &str,
+        choice_id: Uuid,
+        req: MysteryPickRequest,
+    ) -> Result<MysteryPickResponse> {
+        if req.index >= MYSTERY_OPTIONS {
+            return Err(crate::error::AppError::BadRequest(format!(
+                "Pick an option between 0 and {}", MYSTERY_OPTIONS - 1
+            )));
+        }
+        let mut tx = self.db.begin().await?;
+
+        // Serialize with the user's opens, which take the same lock
+        sqlx::query!("SELECT user_id FROM user_lootpack_stats WHERE user_id = $1 FOR UPDATE", user_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| crate::error::AppError::NotFound("User stats not found".to_string()))?;
+
+        let held = mystery_boxes::take(&mut tx, user_id, choice_id, req.index).await?;
+        let picked = held.options.get(req.index).ok_or_else(|| {
+            crate::error::AppError::InternalError(format!("Mystery box {} has no option {}", choice_id, req.index))
+        })?;
+
+        let row = sqlx::query!(
+            r#"
+            SELECT id, type, title, value, description, rarity, code_pattern, validity_days,
+                   metadata, is_active, created_at, version
+            FROM reward_templates
+            WHERE id = $1
+            "#,
+            picked.template_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| crate::error::AppError::InternalError(format!(
+            "Mystery option template {} no longer exists", picked.template_id
+        )))?;
+        let template = RewardTemplate {
+            id: row.id,
+            r#type: row.r#type,
+            title: row.title,
+            value: row.value,
+            description: row.description,
+            rarity: row.rarity,
+            code_pattern: row.code_pattern,
+            validity_days: row.validity_days,
+            metadata: Some(row.metadata.unwrap_or_default()),
+            is_active: Some(row.is_active.unwrap_or(true)),
+            created_at: Some(row.created_at.unwrap_or_else(Utc::now)),
+        };
+
+        // The user picked this exact reward, so there's no pool to fall back into
+        let empty_pool = RewardPool::new(Vec::new());
+        let mut drawn = self.draw_reward(&empty_pool, &template, Some(&mut *tx)).await?;
+        // Keep the localized text the option was dealt with
+        drawn.reward.title = picked.title.clone();
+        drawn.reward.description = picked.description.clone();
+
+        let reward_id = self.insert_reward(
+            &mut tx, user_id, Some(held.pack_history_id), &held.pack_name, &mut drawn, Some(row.version),
+        ).await?;
+        mystery_boxes::record_reward(&mut tx, choice_id, reward_id).await?;
+        valuation::record_pack_value(&mut tx, held.pack_history_id).await?;
+        recent_pulls::record(&mut tx, user_id, &[reward_id]).await?;
+
+        let coins = Self::coin_value(&drawn.reward);
+        let new_balance = if coins > 0 {
+            Some(self.wallet.apply(&mut tx, user_id, coins, "pack_reward", Some(&held.pack_history_id.to_string())).await?)
+        } else {
+            None
+        };
+
+        collection::record(&mut tx, user_id, &[template.id]).await?;
+        let completed_sets = reward_sets::award_completed(&mut tx, user_id, &[template.id]).await?;
+
+        self.commit_with_wallet(tx, new_balance.as_slice()).await?;
+        let new_balance = new_balance.map(|entry| entry.balance);
+
+        info!("User {} picked option {} of mystery box {}", privacy::pseudonym(user_id), req.index, choice_id);
+
+        let others = req.reveal_others.unwrap_or(false).then(|| {
+            held.options
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| *i != req.index)
+                .map(|(_, option)| option.clone())
+                .collect()
+        });
+
+        let mut reward = drawn.reward;
+        reward.id = reward_id.to_string();
+        Ok(MysteryPickResponse { reward, new_balance, others, completed_sets })
+    }
+
+    /// Spin a wheel: one weighted draw from its segments through the same selection and
+    /// reward pipeline as a pack open, on the wheel's own cooldown. Coin segments are
+    /// credited straight away.
+    pub async fn spin_wheel(&self, user_id: &str, wheel_id: Uuid, locale: &Locale) -> Result<WheelSpinResponse> {
+        let mut tx = self.db.begin().await?;
+
+        let wheel = sqlx::query!(
+            "SELECT name, cooldown_hours FROM wheels WHERE id = $1 AND is_active",
+            wheel_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| crate::error::AppError::NotFound("Wheel not found".to_string()))?;
+
+        // Serialize with the user's opens, which take the same lock
+        sqlx::query!("SELECT user_id FROM user_lootpack_stats WHERE user_id = $1 FOR UPDATE", user_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| crate::error::AppError::NotFound("User stats not found".to_string()))?;
+
+        let next_spin_at = cooldowns::enforce_wheel(&mut tx, user_id, wheel_id, wheel.cooldown_hours).await?;
+
+        let segments = wheels::load_segments(&mut tx, wheel_id).await?;
+        let (pool, template_versions) = self.wheel_pool(&mut tx, wheel_id).await?;
+        if pool.total_weight <= 0 {
+            return Err(crate::error::AppError::BadRequest("This wheel has no rewards configured".to_string()));
+        }
+
+        let target_weight = self.rng.gen_range(1, pool.total_weight);
+        let template = pool
+            .select_by_weight(target_weight)
+            .and_then(|selected| pool.rewards.iter().find(|w| w.template.id == selected.id))
+            .map(|w| &w.template)
+            .ok_or_else(|| crate::error::AppError::InternalError("Wheel draw selected nothing".to_string()))?;
+        let mut drawn = self.draw_reward(&pool, template, Some(&mut *tx)).await?;
+        let position = segments
+            .iter()
+            .find(|s| s.reward_template_id == drawn.selected_id)
+            .map(|s| s.position)
+            .unwrap_or_default();
+
+        let translations = localization::template_translations(&mut *tx, &[drawn.template.id], locale).await?;
+        if let Some(translation) = translations.get(&drawn.template.id) {
+            drawn.reward.title = translation.text.clone();
+            if let Some(description) = &translation.description {
+                drawn.reward.description = description.clone();
+            }
+        }
+
+        let template_version = template_versions.get(&drawn.template.id).copied();
+        let reward_id = self.insert_reward(&mut tx, user_id, None, &wheel.name, &mut drawn, template_version).await?;
+
+        let coins_credited = Self::coin_value(&drawn.reward);
+        let new_balance = if coins_credited > 0 {
+            Some(self.wallet.apply(&mut tx, user_id, coins_credited, "wheel_spin", Some(&reward_id.to_string())).await?)
+        } else {
+            None
+        };
+
+        sqlx::query!(
+            "INSERT INTO wheel_spins (user_id, wheel_id, position, reward_id) VALUES ($1, $2, $3, $4)",
+            user_id,
+            wheel_id,
+            position,
+            reward_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        collection::record(&mut tx, user_id, &[drawn.template.id]).await?;
+        let completed_sets = reward_sets::award_completed(&mut tx, user_id, &[drawn.template.id]).await?;
+
+        self.commit_with_wallet(tx, new_balance.as_slice()).await?;
+        let new_balance = new_balance.map(|entry| entry.balance);
+
+        info!("User {} spun wheel {} and landed on segment {}", privacy::pseudonym(user_id), wheel.name, position);
+
+        let mut reward = drawn.reward;
+        reward.id = reward_id.to_string();
+        Ok(WheelSpinResponse {
+            wheel_id,
+            position,
+            reward,
+            coins_credited,
+            new_balance,
+            next_spin_at,
+            completed_sets,
+        })
+    }
+
+    /// Reward pool of a wheel's segments, weighted per segment, along with the template
+    /// versions it was built from
+    async fn wheel_pool(&self, conn: &mut sqlx::PgConnection, wheel_id: Uuid) -> Result<(RewardPool, HashMap<Uuid, i32>)> {
+        let segments = sqlx::query!(
+            r#"
+            SELECT rt.id, rt.type, rt.title, rt.value, rt.description, rt.rarity,
+                   rt.code_pattern, rt.validity_days, rt.metadata, rt.is_active, rt.created_at,
+                   rt.version, ws.weight
+            FROM wheel_segments ws
+            JOIN reward_templates rt ON rt.id = ws.reward_template_id
+            WHERE ws.wheel_id = $1 AND rt.is_active = true
+            ORDER BY ws.position
+            "#,
+            wheel_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let mut weighted_rewards = Vec::new();
+        let mut versions = HashMap::new();
+        for segment in segments {
+            if let Err(e) = reward_types::validate_template(&segment.r#type, segment.metadata.as_ref()) {
+                warn!("Skipping reward template {} on wheel {}: {:?}", segment.id, wheel_id, e);
+                continue;
+            }
+
+            versions.insert(segment.id, segment.version);
+            weighted_rewards.push((
+                RewardTemplate {
+                    id: segment.id,
+                    r#type: segment.r#type,
+                    title: segment.title,
+                    value: segment.value,
+                    description: segment.description,
+                    rarity: segment.rarity,
+                    code_pattern: segment.code_pattern,
+                    validity_days: segment.validity_days,
+                    metadata: Some(segment.metadata.unwrap_or_default()),
+                    is_active: Some(segment.is_active.unwrap_or(true)),
+                    created_at: Some(segment.created_at.unwrap_or_else(Utc::now)),
+                },
+                segment.weight,
+            ));
+        }
+
+        Ok((RewardPool::from_weights(weighted_rewards), versions))
+    }
+
+    /// Generate rewards for a pack and record them in history, inventory and the
+    /// user's collection, granting bonuses for any reward sets the open completed.
+    async fn grant_pack_rewards(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        user_id: &str,
+        pack_type: &PackType,
+        locale: &Locale,
+        market: &Market,
+        selection: RewardSelection,
+    ) -> Result<GrantedRewards> {
+        // Get or build reward pool for this pack type, adjusted for this user
+        let reward_pool = self.get_user_reward_pool(user_id, pack_type.id, market).await?;
+
+        let mut fair_draw = None;
+        let mut draw_odds = None;
+        let mut drawn_rewards = match &selection {
+            RewardSelection::Random | RewardSelection::Seeded(_) => {
+                let seeded = match &selection {
+                    RewardSelection::Seeded(request) => {
+                        let server_seed = fairness::take_commitment(&mut **tx, user_id, request.commitment_id).await?;
+                        let client_seed = fairness::client_seed(request.client_seed.as_deref())?;
+                        Some((request.commitment_id, FairRng::new(server_seed, client_seed)))
+                    }
+                    _ => None,
+                };
+                let rng: &dyn RngProvider = match &seeded {
+                    Some((_, fair_rng)) => fair_rng,
+                    None => self.rng.as_ref(),
+                };
+
+                // Generate rewards using DSA-optimized selection
+                let num_rewards = rng.gen_range(pack_type.min_rewards, pack_type.max_rewards);
+
+                let happy_hour = happy_hours::odds_adjustment(
+                    &happy_hours::active_happy_hours(&mut **tx, Some(pack_type.id)).await?
+                );
+
+                let drawn = self.generate_rewards(&reward_pool, num_rewards, pack_type, &happy_hour, Some(&mut **tx), rng).await?;
+                draw_odds = Some(Self::draw_odds(&reward_pool, &happy_hour, pack_type));
+                if let Some((commitment_id, fair_rng)) = seeded {
+                    let proof = Self::fairness_proof(fair_rng, &reward_pool, &happy_hour, pack_type, &drawn);
+                    fair_draw = Some((commitment_id, proof));
+                }
+                drawn
+            }
+            RewardSelection::Chosen(template_id) => {
+                let template = reward_pool.rewards
+                    .iter()
+                    .find(|w| w.template.id == template_id)
+                    .map(|w| &w.template)
+                    .ok_or_else(|| crate::error::AppError::BadRequest(
+                        "That reward isn't available in this pack".to_string()
+                    ))?;
+                vec![self.draw_reward(&reward_pool, template, Some(&mut **tx)).await?]
+            }
+            RewardSelection::Mystery => {
+                let happy_hour = happy_hours::odds_adjustment(
+                    &happy_hours::active_happy_hours(&mut **tx, Some(pack_type.id)).await?
+                );
+
+                // Codes are claimed at pick time so discarded options don't burn partner codes
+                let mut drawn = self.generate_rewards(
+                    &reward_pool, MYSTERY_OPTIONS as i32, pack_type, &happy_hour, None, self.rng.as_ref(),
+                ).await?;
+                if drawn.len() < MYSTERY_OPTIONS {
+                    return Err(crate::error::AppError::BadRequest(
+                        "This pack can't be opened as a mystery box".to_string()
+                    ));
+                }
+                // Any rare guarantee is dealt first; shuffle so its position gives nothing away
+                for i in (1..drawn.len()).rev() {
+                    drawn.swap(i, self.rng.gen_index(i + 1));
+                }
+                drawn
+            }
+        };
+
+        // Rewards are stored in the language they were opened in
+        let template_ids: Vec<Uuid> = drawn_rewards.iter().map(|d| d.template.id).collect();
+        let translations = localization::template_translations(&mut **tx, &template_ids, locale).await?;
+        for drawn in &mut drawn_rewards {
+            if let Some(translation) = translations.get(&drawn.template.id) {
+                drawn.reward.title = translation.text.clone();
+                if let Some(description) = &translation.description {
+                    drawn.reward.description = description.clone();
+                }
+            }
+        }
+
+        // Record pack opening
+        let pack_history = sqlx::query!(
+            r#"
+            INSERT INTO user_pack_history (user_id, pack_type_id, rewards_count, total_value_inr, draw_odds)
+            VALUES ($1, $2, $3, 0, $4)
+            RETURNING id
+            "#,
+            user_id,
+            pack_type.id,
+            if matches!(selection, RewardSelection::Mystery) { 1 } else { drawn_rewards.len() as i32 },
+            draw_odds
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+
+        // Mystery options stay hidden until the pick, which records the one kept
+        if matches!(selection, RewardSelection::Mystery) {
+            let options: Vec<SampleReward> = drawn_rewards
+                .into_iter()
+                .map(|DrawnReward { template, reward, .. }| SampleReward {
+                    template_id: template.id,
+                    r#type: reward.r#type,
+                    title: reward.title,
+                    value: reward.value,
+                    description: reward.description,
+                    rarity: reward.rarity,
+                })
+                .collect();
+            let offer = mystery_boxes::hold(&mut **tx, user_id, pack_type.id, pack_history.id, &options).await?;
+            community_events::record_open(&mut **tx, user_id, pack_type.id).await?;
+            return Ok(GrantedRewards {
+                pack_history_id: pack_history.id,
+                rewards: Vec::new(),
+                fairness: None,
+                completed_sets: Vec::new(),
+                mystery: Some(offer),
+                reveal: reveal::plan(&[], "dealt"),
+            });
+        }
+
+        // Stamp each reward with the template version it was dealt from
+        let template_versions: HashMap<Uuid, i32> = {
+            let versions = self.template_versions.read().await;
+            let versions = versions.get(&pack_type.id);
+            drawn_rewards.iter()
+                .filter_map(|d| versions.and_then(|v| v.get(&d.template.id)).map(|v| (d.template.id, *v)))
+                .collect()
+        };
+
+        // Insert rewards into user inventory
+        let mut reward_ids = Vec::with_capacity(drawn_rewards.len());
+        for drawn in &mut drawn_rewards {
+            let template_version = template_versions.get(&drawn.template.id).copied();
+            reward_ids.push(
+                self.insert_reward(tx, user_id, Some(pack_history.id), &pack_type.name, drawn, template_version).await?
+            );
+        }
+        recent_pulls::record(&mut **tx, user_id, &reward_ids).await?;
+        valuation::record_pack_value(&mut **tx, pack_history.id).await?;
+
+        let rewards: Vec<GeneratedReward> = drawn_rewards.into_iter().map(|drawn| drawn.reward).collect();
+        community_events::record_open(&mut **tx, user_id, pack_type.id).await?;
+        collection::record(&mut **tx, user_id, &template_ids).await?;
+        let completed_sets = reward_sets::award_completed(&mut **tx, user_id, &template_ids).await?;
+
+        let reveal_order = reveal::pack_order(&mut **tx, pack_type.id).await?;
+        let reveal = reveal::plan(&rewards, &reveal_order);
+
+        let proof = match fair_draw {
+            Some((commitment_id, proof)) => {
+                fairness::record_proof(&mut **tx, commitment_id, pack_history.id, &proof).await?;
+                Some(proof)
+            }
+            None => None,
+        };
+
+        Ok(GrantedRewards {
+            pack_history_id: pack_history.id,
+            rewards,
+            fairness: proof,
+            completed_sets,
+            mystery: None,
+            reveal,
+        })
+    }
+
+    /// Put a drawn reward in the user's inventory and return its id. Codes are unique in
+    /// the DB; a generated code is redrawn on the rare collision instead of failing the open.
+    async fn insert_reward(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        user_id: &str,
+        pack_history_id: Option<Uuid>,
+        source: &str,
+        drawn: &mut DrawnReward<'_>,
+        template_version: Option<i32>,
+    ) -> Result<Uuid> {
+        let expires = reward_types::spec(&drawn.reward.r#type).map(|t| t.expires).unwrap_or(true);
+        let expires_at = if !expires {
+            None
+        } else {
+            Some(Utc::now() + Duration::days(30)) // Default 30 days
+        };
+        // Only a template's stated value_amount is the reward's value; a guess from the type
+        // and display text goes to the estimate columns, for display and stats only
+        let estimate = valuation::estimate(&drawn.reward.r#type, &drawn.reward.value);
+
+        let mut attempts = 0;
+        loop {
+            let inserted = sqlx::query!(
+                r#"
+                INSERT INTO user_rewards 
+                (user_id, pack_history_id, template_id, type, title, value, description, code, 
+                 rarity, source, expires_at, merchant_id, brand, category,
+                 value_amount, value_currency, value_estimate_amount, value_estimate_currency,
+                 template_version, code_hash)
+                SELECT $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11,
+                       rt.merchant_id, rt.brand, rt.category,
+                       rt.value_amount, CASE WHEN rt.value_amount IS NOT NULL THEN rt.value_currency END,
+                       CASE WHEN rt.value_amount IS NULL THEN $13 END,
+                       CASE WHEN rt.value_amount IS NULL THEN $14 END,
+                       $12, $15
+                FROM (SELECT 1) AS one
+                LEFT JOIN reward_templates rt ON rt.id = $3
+                -- Rows the encryption backfill hasn't reached hold the plaintext code and no hash
+                WHERE NOT EXISTS (SELECT 1 FROM user_rewards legacy WHERE legacy.code_hash IS NULL AND legacy.code = $16)
+                ON CONFLICT (code_hash) WHERE code_hash IS NOT NULL DO NOTHING
+                RETURNING id
+                "#,
+                user_id,
+                pack_history_id,
+                drawn.template.id,
+                drawn.reward.r#type,
+                drawn.reward.title,
+                drawn.reward.value,
+                drawn.reward.description,
+                self.codes.seal_opt(drawn.reward.code.as_deref())?,
+                drawn.reward.rarity,
+                source,
+                expires_at,
+                template_version,
+                estimate.as_ref().map(|m| m.amount.clone()),
+                estimate.as_ref().map(|m| m.currency.clone()),
+                self.codes.blind_index_opt(drawn.reward.code.as_deref()),
+                drawn.reward.code
+            )
+            .fetch_optional(&mut **tx)
+            .await?;
+
+            if let Some(inserted) = inserted {
+                return Ok(inserted.id);
+            }
+
+            attempts += 1;
+            if drawn.external_code {
+                error!("Coupon provider issued duplicate code for template {}", drawn.template.id);
+                return Err(crate::error::AppError::InternalError(
+                    "Coupon provider returned a code that was already issued".to_string()
+                ));
+            }
+            if attempts >= MAX_CODE_ATTEMPTS {
+                error!("Could not generate a unique code for template {} after {} attempts",
+                       drawn.template.id, attempts);
+                return Err(crate::error::AppError::InternalError(
+                    "Failed to generate a unique reward code".to_string()
+                ));
+            }
+            warn!("Reward code collision for template {}, retrying", drawn.template.id);
+            drawn.reward.code = Some(self.generate_coupon_code(
+                &drawn.template.r#type,
+                drawn.template.code_pattern.as_deref(),
+            ).await);
+        }
+    }
+
+    /// Reveal a seeded open: the pool as drawn from, the rare-guarantee candidates in
+    /// the order `generate_rewards` indexes them, and the templates each roll picked
+    fn fairness_proof(
+        fair_rng: FairRng,
+        pool: &RewardPool,
+        happy_hour: &OddsAdjustment,
+        pack_type: &PackType,
+        drawn: &[DrawnReward<'_>],
+    ) -> FairnessProof {
+        let weighted_pool = happy_hour.apply(pool.clone());
+        let entries = weighted_pool.rewards
+            .iter()
+            .map(|w| PoolEntry {
+                template_id: w.template.id,
+                weight: w.weight,
+                cumulative_weight: w.cumulative_weight,
+            })
+            .collect();
+
+        let mut guarantee_candidates = Vec::new();
+        if has_rare_guarantee(pack_type) {
+            for rarity in GUARANTEED_RARITIES {
+                guarantee_candidates.extend(pool.get_by_rarity(rarity).into_iter().map(|t| t.id));
+            }
+        }
+
+        fair_rng.into_proof(entries, guarantee_candidates, drawn.iter().map(|d| d.selected_id).collect())
+    }
+
+    /// The odds a random open drew from, stored with the open for the odds audit: the
+    /// user's adjusted weights with any happy hour applied, and the templates the rare
+    /// guarantee picks from uniformly
+    fn draw_odds(pool: &RewardPool, happy_hour: &OddsAdjustment, pack_type: &PackType) -> serde_json::Value {
+        let weights: serde_json::Map<String, serde_json::Value> = happy_hour
+            .apply(pool.clone())
+            .rewards
+            .iter()
+            .map(|w| (w.template.id.to_string(), json!(w.weight)))
+            .collect();
+
+        let mut guaranteed = Vec::new();
+        if has_rare_guarantee(pack_type) {
+            for rarity in GUARANTEED_RARITIES {
+                guaranteed.extend(pool.get_by_rarity(rarity).into_iter().map(|t| t.id));
+            }
+        }
+
+        json!({ "weights": weights, "guaranteed": guaranteed })
+    }
+
+    /// Apply coins, level progress and pack count for an opening, moving every coin
+    /// change through the wallet. Streak and daily-claim fields are persisted as
+    /// already set on `stats`. The wallet entries are settled by `commit_with_wallet`.
+    async fn apply_pack_open(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        mut stats: UserLootpackStats,
+        pack_history_id: Uuid,
+        generated_rewards: &[GeneratedReward],
+        pack_cost: i32,
+        wallet_entries: &mut Vec<WalletEntry>,
+    ) -> Result<UserLootpackStats> {
+        let coin_bonus = generated_rewards.iter().map(Self::coin_value).sum::<i32>();
+        let prestige_bonus = prestige::coin_bonus(prestige::count(tx, &stats.user_id).await?, coin_bonus);
+
+        let starting_coins = stats.deal_coins.unwrap_or(500);
+        let mut current_packs = stats.total_packs_opened.unwrap_or(0);
+        let mut current_level = stats.level.unwrap_or(1);
+        let mut current_progress = stats.level_progress.unwrap_or(0);
+        let mut level_bonus = 0;
+
+        current_packs += 1;
+
+        // Handle level up; at the cap progress waits for a prestige
+        if current_level < prestige::MAX_LEVEL {
+            current_progress += LEVEL_PROGRESS_PER_OPEN;
+            if current_progress >= 100 {
+                current_level += 1;
+                current_progress = 0;
+                level_bonus = LEVEL_UP_BONUS;
+            }
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE user_lootpack_stats 
+            SET total_packs_opened = $2, level = $3, 
+                level_progress = $4, daily_streak = $5, last_daily_claim = $6,
+                updated_at = NOW()
+            WHERE user_id = $1
+            "#,
+            stats.user_id,
+            current_packs,
+            current_level,
+            current_progress,
+            stats.daily_streak.unwrap_or(1),
+            stats.last_daily_claim
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        let reference = pack_history_id.to_string();
+        let mut current_coins = starting_coins;
+        for (delta, reason) in [
+            (-pack_cost, "pack_purchase"),
+            (coin_bonus, "pack_reward"),
+            (prestige_bonus, "prestige_bonus"),
+            (level_bonus, "level_up_bonus"),
+        ] {
+            if delta != 0 {
+                let entry = self.wallet.apply(&mut **tx, &stats.user_id, delta, reason, Some(&reference)).await?;
+                current_coins = entry.balance;
+                wallet_entries.push(entry);
+            }
+        }
+
+        if level_bonus > 0 {
+            inbox::send(&mut **tx, &stats.user_id, NewMessage {
+                kind: "milestone",
+                title: &format!("Level {} reached!", current_level),
+                body: &format!("You earned {} bonus DealCoins for levelling up.", level_bonus),
+                claim_pack_type_id: None,
+                expires_at: None,
+            }).await?;
+        }
+
+        stats.deal_coins = Some(current_coins);
+        stats.total_packs_opened = Some(current_packs);
+        stats.level = Some(current_level);
+        stats.level_progress = Some(current_progress);
+        Ok(stats)
+    }
+
+    /// `wallet::commit_with_wallet` through this service's wallet
+    async fn commit_with_wallet(
+        &self,
+        tx: sqlx::Transaction<'_, sqlx::Postgres>,
+        wallet_entries: &[WalletEntry],
+    ) -> Result<()> {
+        wallet::commit_with_wallet(self.wallet.as_ref(), tx, wallet_entries).await
+    }
+
+    /// DealCoins credited by a reward when its pack is opened
+    fn coin_value(reward: &GeneratedReward) -> i32 {
+        if reward_types::spec(&reward.r#type).map(|t| t.credits_coins).unwrap_or(false) {
+            reward.value.trim_start_matches('+').parse::<i32>().unwrap_or(0)
+        } else {
+            0
+        }
+    }
+
+    /// Build the API stats view, including daily claim availability
+    fn stats_response(stats: UserLootpackStats, clock: &DailyClock) -> UserStatsResponse {
+        let next_daily_claim = clock.next_claim(Utc::now());
+        let can_claim_daily = next_daily_claim.is_none();
+
+        UserStatsResponse {
+            deal_coins: stats.deal_coins.unwrap_or(500),
+            daily_streak: stats.daily_streak.unwrap_or(1),
+            total_packs_opened: stats.total_packs_opened.unwrap_or(0),
+            level: stats.level.unwrap_or(1),
+            level_progress: stats.level_progress.unwrap_or(0),
+            member_status: stats.member_status.unwrap_or_else(|| "Bronze".to_string()),
+            can_claim_daily,
+            next_daily_claim,
+        }
+    }
+
+    /// Refund a pack open: reverse every coin movement of the open, void the rewards from it
+    /// that the user still holds unredeemed and flag the rest, in one transaction. Rewards
+    /// held in a pending trade or checkout block the refund until they settle.
+    pub async fn refund_pack_open(&self, actor: &str, pack_history_id: Uuid, reason: &str) -> Result<PackRefundResponse> {
+        let reason = reason.trim();
+        if reason.is_empty() {
+            return Err(crate::error::AppError::BadRequest("A refund reason is required".to_string()));
+        }
+        let mut tx = self.db.begin().await?;
+
+        let opening = sqlx::query!(
+            "SELECT user_id, refunded_at FROM user_pack_history WHERE id = $1 FOR UPDATE",
+            pack_history_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| crate::error::AppError::NotFound("Pack opening not found".to_string()))?;
+        if opening.refunded_at.is_some() {
+            return Err(crate::error::AppError::BadRequest("Pack opening was already refunded".to_string()));
+        }
+
+        // Everything the open moved, netted: its price against the coins it credited
+        // (coin rewards, prestige and level-up bonuses) and any mystery refund already paid.
+        // Packs opened on purchase are charged against the opening; packs bought into the
+        // inventory against the user pack that was opened later.
+        let net_cost = sqlx::query_scalar!(
+            r#"
+            SELECT COALESCE(-SUM(delta), 0)::INT AS "net_cost!"
+            FROM coin_ledger
+            WHERE user_id = $2
+              AND (reference_id = $1::text
+                   OR (reason = 'pack_purchase'
+                       AND reference_id IN (SELECT id::text FROM user_packs WHERE pack_history_id = $1))
+                   OR (reason = 'mystery_refund'
+                       AND reference_id IN (SELECT id::text FROM mystery_choices WHERE pack_history_id = $1)))
+            "#,
+            pack_history_id,
+            opening.user_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let in_flight = sqlx::query_scalar!(
+            r#"
+            SELECT id FROM user_rewards
+            WHERE pack_history_id = $1 AND user_id = $2
+              AND COALESCE(is_used, false) = false AND traded_in_at IS NULL
+              AND (escrow_trade_id IS NOT NULL OR reserved_until > NOW())
+            LIMIT 1
+            "#,
+            pack_history_id,
+            opening.user_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        if let Some(reward_id) = in_flight {
+            return Err(crate::error::AppError::BadRequest(format!(
+                "Reward {} is in a pending trade or checkout; retry once it settles", reward_id
+            )));
+        }
+
+        // Expiring a voided reward is what makes every redemption path reject it
+        let voided_reward_ids = sqlx::query_scalar!(
+            r#"
+            UPDATE user_rewards SET voided_at = NOW(), expires_at = NOW()
+            WHERE pack_history_id = $1 AND user_id = $2
+              AND COALESCE(is_used, false) = false AND traded_in_at IS NULL
+              AND fulfillment_status IS NULL AND payout_status IS NULL
+            RETURNING id
+            "#,
+            pack_history_id,
+            opening.user_id
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let flagged_reward_ids = sqlx::query_scalar!(
+            r#"
+            UPDATE user_rewards SET refund_flagged_at = NOW()
+            WHERE pack_history_id = $1 AND voided_at IS NULL
+            RETURNING id
+            "#,
+            pack_history_id
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        // A refunded open can't be rerolled or picked from any more. The mystery expiry
+        // worker only refunds pending choices, so the cost isn't refunded twice.
+        sqlx::query!(
+            "UPDATE reroll_tokens SET expires_at = NOW() WHERE pack_history_id = $1 AND used_at IS NULL AND expires_at > NOW()",
+            pack_history_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "UPDATE mystery_choices SET status = 'expired', resolved_at = NOW() WHERE pack_history_id = $1 AND status = 'pending'",
+            pack_history_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        // Nor does the first-purchase bonus it earned survive it
+        first_purchase::revoke_bonus(&mut tx, pack_history_id).await?;
+
+        // An open that credited more than it cost is clawed back, even past a zero balance
+        let reference = pack_history_id.to_string();
+        let entry = match net_cost {
+            0 => None,
+            refund if refund > 0 => {
+                Some(self.wallet.apply(&mut tx, &opening.user_id, refund, "pack_refund", Some(&reference)).await?)
+            }
+            overpaid => {
+                Some(self.wallet.claw_back(&mut tx, &opening.user_id, -overpaid, "pack_refund", Some(&reference)).await?)
+            }
+        };
+        let new_balance = entry.as_ref().map(|entry| entry.balance);
+        let wallet_entries: Vec<WalletEntry> = entry.into_iter().collect();
+
+        sqlx::query!(
+            r#"
+            UPDATE user_pack_history
+            SET refunded_at = NOW(), refunded_coins = $2, refunded_by = $3, refund_reason = $4
+            WHERE id = $1
+            "#,
+            pack_history_id,
+            net_cost,
+            actor,
+            reason
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        audit::record(&mut *tx, actor, "pack.refunded", Some(&reference), json!({
+            "user_id": opening.user_id,
+            "refunded_coins": net_cost,
+            "reason": reason,
+            "voided_reward_ids": voided_reward_ids,
+            "flagged_reward_ids": flagged_reward_ids,
+        })).await?;
+        for (action, reward_ids) in [("reward.voided", &voided_reward_ids), ("reward.refund_flagged", &flagged_reward_ids)] {
+            for reward_id in reward_ids {
+                audit::record(&mut *tx, actor, action, Some(&reward_id.to_string()), json!({
+                    "pack_history_id": pack_history_id,
+                    "reason": reason,
+                })).await?;
             }
-        } else if let Some(price) = pack_type.price_coins {
-            if let Some(stats) = &user_stats {
-                let user_coins = stats.deal_coins.unwrap_or(0);
-                if user_coins < price {
-                    return Err(crate::error::AppError::BadRequest(
-                        "Insufficient DealCoins".to_string()
-                    ));
-                }
-            } else {
+        }
+
+        self.commit_with_wallet(tx, &wallet_entries).await?;
+
+        info!("Refunded pack opening {} for user {}: {} coins, {} rewards voided, {} flagged",
+              pack_history_id, privacy::pseudonym(&opening.user_id), net_cost, voided_reward_ids.len(), flagged_reward_ids.len());
+
+        Ok(PackRefundResponse {
+            pack_history_id,
+            user_id: opening.user_id,
+            refunded_coins: net_cost,
+            new_balance,
+            voided_reward_ids,
+            flagged_reward_ids,
+        })
+    }
+
+    /// Get user's rewards inventory, optionally filtered
+    pub async fn get_user_inventory(&self, user_id: &str, query: &InventoryQuery) -> Result<InventoryResponse> {
+        if let Some(status) = query.status.as_deref() {
+            if !matches!(status, "active" | "used" | "expired") {
                 return Err(crate::error::AppError::BadRequest(
-                    "Insufficient DealCoins".to_string()
+                    "status must be one of active, used, expired".to_string()
                 ));
             }
         }
 
-        // Get or build reward pool for this pack type
-        let reward_pool = self.get_reward_pool_for_pack(pack_type_id).await?;
-
-        // Generate rewards using DSA-optimized selection
-        let num_rewards = {
-            let mut rng = rand::thread_rng();
-            rng.gen_range(pack_type.min_rewards..=pack_type.max_rewards)
-        };
+        let sort = query.sort.as_deref().unwrap_or("created_at");
+        let order = query.order.as_deref().unwrap_or("desc");
+        if !matches!(sort, "expires_at" | "rarity" | "created_at") || !matches!(order, "asc" | "desc") {
+            return Err(crate::error::AppError::BadRequest(
+                "sort must be expires_at, rarity or created_at and order asc or desc".to_string()
+            ));
+        }
 
-        let generated_rewards = self.generate_rewards(&reward_pool, num_rewards, &pack_type).await?;
+        // Brand filters match the whole brand, case-insensitively; escape LIKE wildcards
+        let brand = query.brand.as_deref().map(|brand| {
+            brand.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+        });
 
-        // Record pack opening
-        let pack_history = sqlx::query!(
+        // One read serves the rewards, their merchant and icon, and the value estimate
+        let rows = sqlx::query!(
             r#"
-            INSERT INTO user_pack_history (user_id, pack_type_id, rewards_count, total_value_inr)
-            VALUES ($1, $2, $3, $4)
-            RETURNING id
+            SELECT ur.id, ur.user_id, ur.pack_history_id, ur.template_id, ur.type, ur.title, ur.value,
+                   ur.description, ur.code, ur.rarity, ur.source, ur.expires_at, ur.is_used, ur.used_at,
+                   ur.created_at, ur.merchant_id, ur.brand, ur.category, a.storage_key AS "icon_key?",
+                   COALESCE(ur.value_amount, ur.value_estimate_amount) AS value_amount,
+                   CASE WHEN ur.value_amount IS NOT NULL THEN ur.value_currency ELSE ur.value_estimate_currency END
+                       AS value_currency
+            FROM user_rewards ur
+            LEFT JOIN reward_templates rt ON rt.id = ur.template_id
+            LEFT JOIN assets a ON a.id = rt.icon_asset_id
+            WHERE ur.user_id = $1
+              AND ($2::text IS NULL OR ur.type = $2)
+              AND ($3::text IS NULL OR ur.rarity = $3)
+              AND ($4::text IS NULL
+                   OR ($4 = 'used' AND ur.is_used = true)
+                   OR ($4 = 'active' AND COALESCE(ur.is_used, false) = false
+                       AND (ur.expires_at IS NULL OR ur.expires_at > NOW()))
+                   OR ($4 = 'expired' AND COALESCE(ur.is_used, false) = false AND ur.expires_at <= NOW()))
+              AND ($5::int IS NULL
+                   OR (ur.expires_at > NOW() AND ur.expires_at <= NOW() + make_interval(days => $5)))
+              AND ($8::text IS NULL OR ur.merchant_id = $8)
+              AND ($9::text IS NULL OR ur.brand ILIKE $9)
+              AND ($10::text IS NULL OR ur.category = $10)
+            ORDER BY
+                CASE WHEN $6 = 'expires_at' AND $7 = 'asc' THEN ur.expires_at END ASC NULLS LAST,
+                CASE WHEN $6 = 'expires_at' AND $7 = 'desc' THEN ur.expires_at END DESC NULLS LAST,
+                CASE WHEN $6 = 'rarity' THEN
+                    CASE ur.rarity WHEN 'legendary' THEN 4 WHEN 'epic' THEN 3 WHEN 'rare' THEN 2 ELSE 1 END
+                    * CASE WHEN $7 = 'asc' THEN 1 ELSE -1 END
+                END ASC,
+                CASE WHEN $6 = 'created_at' AND $7 = 'asc' THEN ur.created_at END ASC,
+                ur.created_at DESC
             "#,
             user_id,
-            pack_type_id,
-            generated_rewards.len() as i32,
-            bigdecimal::BigDecimal::from(0) // TODO: Calculate actual value
+            query.r#type,
+            query.rarity,
+            query.status,
+            query.expiring_within_days,
+            sort,
+            order,
+            query.merchant_id,
+            brand,
+            query.category
         )
-        .fetch_one(&mut *tx)
+        .fetch_all(&self.db)
         .await?;
 
-        // Insert rewards into user inventory
-        for reward in &generated_rewards {
-            let expires_at = if reward.r#type == "points" {
-                None
+        let now = Utc::now();
+        let mut rewards = Vec::with_capacity(rows.len());
+        let mut details = Vec::with_capacity(rows.len());
+        let mut values = Vec::new();
+        for row in rows {
+            let active = !row.is_used.unwrap_or(false) && row.expires_at.map(|exp| exp > now).unwrap_or(true);
+            if let (true, Some(amount), Some(currency)) = (active, row.value_amount, row.value_currency) {
+                values.push(Money { amount, currency });
+            }
+            details.push((RewardMerchant {
+                merchant_id: row.merchant_id,
+                brand: row.brand,
+                category: row.category,
+            }, row.icon_key));
+            rewards.push(UserReward {
+                id: row.id,
+                user_id: row.user_id,
+                pack_history_id: row.pack_history_id,
+                template_id: row.template_id,
+                r#type: row.r#type,
+                title: row.title,
+                value: row.value,
+                description: row.description,
+                code: row.code,
+                rarity: row.rarity,
+                source: row.source,
+                expires_at: row.expires_at,
+                is_used: row.is_used,
+                used_at: row.used_at,
+                created_at: row.created_at,
+            });
+        }
+        self.codes.reveal_rewards(&mut rewards)?;
+
+        let active_count = rewards.iter().filter(|r| !r.is_used.unwrap_or(false)).count() as i32;
+        let used_count = rewards.iter().filter(|r| r.is_used.unwrap_or(false)).count() as i32;
+        let expiring_soon_count = rewards.iter()
+            .filter(|r| !r.is_used.unwrap_or(false) && r.expires_at.map(|exp| (exp - now).num_days() <= 3).unwrap_or(false))
+            .count() as i32;
+
+        let display_currency = currency::display_currency(&self.db, user_id).await?;
+        let rates = RateTable::load(&self.db).await?;
+
+        let stats = InventoryStats {
+            active_count,
+            used_count,
+            expiring_soon_count,
+            total_value_estimate: rates.total(&values, &display_currency).amount,
+        };
+
+        let rewards = rewards
+            .into_iter()
+            .zip(details)
+            .map(|(reward, (merchant, icon_key))| InventoryItem { reward, merchant, icon_url: self.icon_url(icon_key) })
+            .collect();
+        Ok(InventoryResponse { rewards, stats, display_currency })
+    }
+
+    /// Get pack metadata with a preview of possible rewards grouped by rarity
+    pub async fn get_pack_detail(&self, pack_type_id: Uuid) -> Result<PackDetailResponse> {
+        let pack = self.get_pack_type(pack_type_id).await?;
+        let reward_pool = self.get_reward_pool_for_pack(pack_type_id).await?;
+
+        let mut rewards_by_rarity: HashMap<String, Vec<RewardPreview>> = HashMap::new();
+        for weighted in &reward_pool.rewards {
+            let drop_chance = if reward_pool.total_weight > 0 {
+                weighted.weight as f64 / reward_pool.total_weight as f64
             } else {
-                Some(Utc::now() + Duration::days(30)) // Default 30 days
+                0.0
             };
 
-            sqlx::query!(
-                r#"
-                INSERT INTO user_rewards 
-                (user_id, pack_history_id, type, title, value, description, code, 
-                 rarity, source, expires_at)
-                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
-                "#,
-                user_id,
-                pack_history.id,
-                reward.r#type,
-                reward.title,
-                reward.value,
-                reward.description,
-                reward.code,
-                reward.rarity,
-                pack_type.name,
-                expires_at
-            )
-            .execute(&mut *tx)
-            .await?;
+            rewards_by_rarity
+                .entry(weighted.template.rarity.clone())
+                .or_default()
+                .push(RewardPreview {
+                    id: weighted.template.id,
+                    title: weighted.template.title.clone(),
+                    r#type: weighted.template.r#type.clone(),
+                    value: weighted.template.value.clone(),
+                    description: weighted.template.description.clone(),
+                    drop_chance,
+                });
         }
 
-        // Update user stats
-        let coin_bonus = generated_rewards.iter()
-            .filter(|r| r.r#type == "points")
-            .map(|r| r.value.trim_start_matches('+').parse::<i32>().unwrap_or(0))
-            .sum::<i32>();
+        Ok(PackDetailResponse { pack, rewards_by_rarity })
+    }
+
+    /// Example contents for a pack: one opening run through the generator with no
+    /// inserts, stat changes or coupon-provider claims, at base odds. Each sample is
+    /// reused per pack and market for `PREVIEW_CACHE_TTL`; titles are localized per request.
+    pub async fn preview_pack(&self, pack_type_id: Uuid, locale: &Locale, market: &Market) -> Result<PackPreviewResponse> {
+        let cache_key = (pack_type_id, market.code().to_string());
+        let cached = self.preview_cache.read().await
+            .get(&cache_key)
+            .filter(|(sampled, _)| sampled.elapsed() < PREVIEW_CACHE_TTL)
+            .map(|(_, preview)| preview.clone());
 
-        let pack_cost = if pack_type.r#type == "premium" {
-            pack_type.price_coins.unwrap_or(0)
-        } else {
-            0
+        let mut preview = match cached {
+            Some(preview) => preview,
+            None => {
+                let pack_type = sqlx::query_as!(
+                    PackType,
+                    r#"
+                    SELECT id, name, type, description, icon, color_gradient, 
+                           price_coins, cooldown_hours, min_rewards, max_rewards,
+                           possible_reward_types, is_active, created_at, updated_at
+                    FROM pack_types 
+                    WHERE id = $1 AND is_active = true AND (markets IS NULL OR $2 = ANY(markets))
+                    "#,
+                    pack_type_id,
+                    market.code()
+                )
+                .fetch_optional(&self.db)
+                .await?
+                .ok_or_else(|| crate::error::AppError::NotFound("Pack type not found".to_string()))?;
+
+                let reward_pool = self.get_reward_pool_for_pack(pack_type_id).await?;
+                let reward_pool = self.restrict_to_market(reward_pool, market).await?;
+                let num_rewards = self.rng.gen_range(pack_type.min_rewards, pack_type.max_rewards);
+                let drawn = self.generate_rewards(
+                    &reward_pool, num_rewards, &pack_type, &OddsAdjustment::default(), None, self.rng.as_ref(),
+                ).await?;
+
+                let preview = PackPreviewResponse {
+                    pack_type_id,
+                    rewards: drawn
+                        .into_iter()
+                        .map(|DrawnReward { template, reward, .. }| SampleReward {
+                            template_id: template.id,
+                            r#type: reward.r#type,
+                            title: reward.title,
+                            value: reward.value,
+                            description: reward.description,
+                            rarity: reward.rarity,
+                        })
+                        .collect(),
+                    sampled_at: Utc::now(),
+                };
+                self.preview_cache.write().await.insert(cache_key, (std::time::Instant::now(), preview.clone()));
+                preview
+            }
         };
 
-        let level_progress_gain = 10;
-        
-        let updated_stats = if let Some(mut stats) = user_stats {
-            let mut current_coins = stats.deal_coins.unwrap_or(500);
-            let mut current_packs = stats.total_packs_opened.unwrap_or(0);
-            let mut current_level = stats.level.unwrap_or(1);
-            let mut current_progress = stats.level_progress.unwrap_or(0);
-            let mut current_streak = stats.daily_streak.unwrap_or(1);
-            
-            current_coins = current_coins + coin_bonus - pack_cost;
-            current_packs += 1;
-            current_progress += level_progress_gain;
-            
-            // Handle level up
-            if current_progress >= 100 {
-                current_level += 1;
-                current_progress = 0;
-                current_coins += 100; // Level up bonus
-            }
-
-            // Update daily streak for free packs
-            if pack_type.r#type == "free" {
-                let now = Utc::now();
-                if let Some(last_claim) = stats.last_daily_claim {
-                    let hours_diff = now.signed_duration_since(last_claim).num_hours();
-                    if hours_diff >= 24 && hours_diff < 48 {
-                        current_streak += 1;
-                    } else if hours_diff >= 48 {
-                        current_streak = 1; // Reset streak
-                    }
-                } else {
-                    current_streak = 1;
+        let template_ids: Vec<Uuid> = preview.rewards.iter().map(|r| r.template_id).collect();
+        let translations = localization::template_translations(&self.db, &template_ids, locale).await?;
+        for reward in &mut preview.rewards {
+            if let Some(translation) = translations.get(&reward.template_id) {
+                reward.title = translation.text.clone();
+                if let Some(description) = &translation.description {
+                    reward.description = description.clone();
                 }
-                stats.last_daily_claim = Some(now);
             }
+        }
 
-            sqlx::query!(
-                r#"
-                UPDATE user_lootpack_stats 
-                SET deal_coins = $2, total_packs_opened = $3, level = $4, 
-                    level_progress = $5, daily_streak = $6, last_daily_claim = $7,
-                    updated_at = NOW()
-                WHERE user_id = $1
-                "#,
-                user_id,
-                current_coins,
-                current_packs,
-                current_level,
-                current_progress,
-                current_streak,
-                stats.last_daily_claim
-            )
-            .execute(&mut *tx)
-            .await?;
+        Ok(preview)
+    }
 
-            // Update the stats for response
-            stats.deal_coins = Some(current_coins);
-            stats.total_packs_opened = Some(current_packs);
-            stats.level = Some(current_level);
-            stats.level_progress = Some(current_progress);
-            stats.daily_streak = Some(current_streak);
-            stats
-        } else {
-            return Err(crate::error::AppError::InternalError(
-                "Failed to update user stats".to_string()
+    /// Simulate opening a pack `runs` times without charging coins or persisting anything
+    pub async fn simulate_pack(&self, pack_type_id: Uuid, runs: u32) -> Result<PackSimulationResponse> {
+        if runs == 0 || runs > MAX_SIMULATION_RUNS {
+            return Err(crate::error::AppError::BadRequest(
+                format!("count must be between 1 and {}", MAX_SIMULATION_RUNS)
             ));
-        };
+        }
+
+        let pack_type = self.get_pack_type(pack_type_id).await?;
+        let reward_pool = self.get_reward_pool_for_pack(pack_type_id).await?;
+        let happy_hour = happy_hours::odds_adjustment(
+            &happy_hours::active_happy_hours(&self.db, Some(pack_type_id)).await?
+        );
 
-        tx.commit().await?;
+        let mut rarity_distribution = HashMap::new();
+        let mut type_distribution = HashMap::new();
+        let mut reward_distribution = HashMap::new();
+        let mut total_rewards = 0;
 
-        info!("User {} opened pack {} and received {} rewards", 
-              user_id, pack_type.name, generated_rewards.len());
-
-        let stats_response = UserStatsResponse {
-            deal_coins: updated_stats.deal_coins.unwrap_or(500),
-            daily_streak: updated_stats.daily_streak.unwrap_or(1),
-            total_packs_opened: updated_stats.total_packs_opened.unwrap_or(0),
-            level: updated_stats.level.unwrap_or(1),
-            level_progress: updated_stats.level_progress.unwrap_or(0),
-            member_status: updated_stats.member_status.unwrap_or_else(|| "Bronze".to_string()),
-            can_claim_daily: pack_type.r#type == "free" || 
-                updated_stats.last_daily_claim
-                    .map(|last| Utc::now().signed_duration_since(last) >= Duration::hours(24))
-                    .unwrap_or(true),
-            next_daily_claim: if pack_type.r#type == "free" {
-                Some(Utc::now() + Duration::hours(24))
-            } else {
-                updated_stats.last_daily_claim.map(|last| last + Duration::hours(24))
-            },
+        for _ in 0..runs {
+            let num_rewards = self.rng.gen_range(pack_type.min_rewards, pack_type.max_rewards);
+
+            for DrawnReward { reward, .. } in self.generate_rewards(&reward_pool, num_rewards, &pack_type, &happy_hour, None, self.rng.as_ref()).await? {
+                *rarity_distribution.entry(reward.rarity).or_insert(0) += 1;
+                *type_distribution.entry(reward.r#type).or_insert(0) += 1;
+                *reward_distribution.entry(reward.title).or_insert(0) += 1;
+                total_rewards += 1;
+            }
+        }
+
+        Ok(PackSimulationResponse {
+            pack_type_id,
+            runs,
+            total_rewards,
+            rarity_distribution,
+            type_distribution,
+            reward_distribution,
+        })
+    }
+
+    /// Monte Carlo run of `users` new users each buying `opens_per_user` packs at
+    /// today's prices and weights. Users pick uniformly among the packs they can
+    /// afford and stop when they can't afford any. Nothing is persisted.
+    pub async fn simulate_economy(&self, req: EconomySimulationRequest) -> Result<EconomySimulationResponse> {
+        let total_opens = req.users.checked_mul(req.opens_per_user).unwrap_or(u32::MAX);
+        if req.users == 0 || req.opens_per_user == 0 || total_opens > MAX_ECONOMY_SIMULATION_OPENS {
+            return Err(crate::error::AppError::BadRequest(format!(
+                "users x opens_per_user must be between 1 and {}", MAX_ECONOMY_SIMULATION_OPENS
+            )));
+        }
+
+        let mut packs = Vec::new();
+        let discounts: HashMap<Uuid, pricing::PackDiscount> = pricing::active_discounts(&self.db)
+            .await?
+            .into_iter()
+            .map(|d| (d.pack_type_id, d))
+            .collect();
+        let pack_type_ids = match req.pack_type_ids {
+            Some(ids) => ids,
+            None => sqlx::query_scalar!(
+                "SELECT id FROM pack_types WHERE is_active = true AND type <> 'free' AND price_coins > 0"
+            )
+            .fetch_all(&self.db)
+            .await?,
         };
+        for pack_type_id in pack_type_ids {
+            let pack_type = self.get_pack_type(pack_type_id).await?;
+            let price = PackPricing::compute(pack_type.price_coins, discounts.get(&pack_type.id))
+                .price
+                .unwrap_or(0);
+            let pool = self.get_reward_pool_for_pack(pack_type_id).await?;
+            packs.push((pack_type, price, pool));
+        }
+        if packs.is_empty() {
+            return Err(crate::error::AppError::BadRequest("No purchasable packs to simulate".to_string()));
+        }
+
+        // Monetary value per template, converted to INR once up front
+        let template_ids: Vec<Uuid> = packs.iter()
+            .flat_map(|(_, _, pool)| pool.rewards.iter().map(|w| w.template.id))
+            .collect();
+        let rates = RateTable::load(&self.db).await?;
+        let template_values: HashMap<Uuid, f64> = sqlx::query!(
+            "SELECT id, type, value, value_amount, value_currency FROM reward_templates WHERE id = ANY($1)",
+            &template_ids
+        )
+        .fetch_all(&self.db)
+        .await?
+        .into_iter()
+        .filter_map(|r| {
+            let value = match r.value_amount {
+                Some(amount) => Money { amount, currency: r.value_currency },
+                None => valuation::estimate(&r.r#type, &r.value)?,
+            };
+            let inr = rates.convert(&value, currency::BASE_CURRENCY)?;
+            Some((r.id, inr.amount.to_string().parse::<f64>().ok()?))
+        })
+        .collect();
+
+        let no_boost = OddsAdjustment::default();
+        let starting_coins = req.starting_coins.unwrap_or(STARTING_DEAL_COINS).max(0);
+        let mut stats: HashMap<Uuid, PackEconomyStats> = HashMap::new();
+        let mut value_totals: HashMap<Uuid, f64> = HashMap::new();
+        let mut completed_opens = 0u64;
+        let mut level_up_bonuses = 0i64;
+        let mut coins_minted = starting_coins as i64 * req.users as i64;
+        let mut coins_sunk = 0i64;
+        let mut ending_balance_total = 0i64;
+        let mut users_out_of_coins = 0;
+
+        for _ in 0..req.users {
+            let mut balance = starting_coins;
+            let mut progress = 0;
+
+            for _ in 0..req.opens_per_user {
+                let affordable: Vec<&(PackType, i32, RewardPool)> = packs.iter()
+                    .filter(|(_, price, _)| *price <= balance)
+                    .collect();
+                if affordable.is_empty() {
+                    users_out_of_coins += 1;
+                    break;
+                }
+                let (pack_type, price, pool) = affordable[self.rng.gen_index(affordable.len())];
+
+                let num_rewards = self.rng.gen_range(pack_type.min_rewards, pack_type.max_rewards);
+                let drawn = self.generate_rewards(pool, num_rewards, pack_type, &no_boost, None, self.rng.as_ref()).await?;
+
+                let entry = stats.entry(pack_type.id).or_insert_with(|| PackEconomyStats {
+                    pack_name: pack_type.name.clone(),
+                    price: *price,
+                    ..Default::default()
+                });
+                let mut returned = 0;
+                for DrawnReward { template, reward, .. } in &drawn {
+                    returned += Self::coin_value(reward);
+                    *entry.rarity_distribution.entry(reward.rarity.clone()).or_insert(0) += 1;
+                    *value_totals.entry(pack_type.id).or_insert(0.0) +=
+                        template_values.get(&template.id).copied().unwrap_or(0.0);
+                }
+
+                progress += LEVEL_PROGRESS_PER_OPEN;
+                let level_bonus = if progress >= 100 {
+                    progress = 0;
+                    LEVEL_UP_BONUS
+                } else {
+                    0
+                };
+
+                balance += returned + level_bonus - price;
+                entry.opens += 1;
+                entry.coins_spent += *price as i64;
+                entry.coins_returned += returned as i64;
+                coins_sunk += *price as i64;
+                coins_minted += (returned + level_bonus) as i64;
+                level_up_bonuses += level_bonus as i64;
+                completed_opens += 1;
+            }
+
+            ending_balance_total += balance as i64;
+        }
+
+        for (pack_type_id, entry) in stats.iter_mut() {
+            if entry.opens > 0 {
+                entry.expected_coin_return = entry.coins_returned as f64 / entry.opens as f64;
+                entry.expected_value_inr = value_totals.get(pack_type_id).copied().unwrap_or(0.0) / entry.opens as f64;
+            }
+        }
 
-        Ok(OpenPackResponse {
-            rewards: generated_rewards,
-            updated_stats: stats_response,
+        Ok(EconomySimulationResponse {
+            users: req.users,
+            attempted_opens: total_opens as u64,
+            completed_opens,
+            coins_minted,
+            coins_sunk,
+            level_up_bonuses,
+            average_ending_balance: ending_balance_total as f64 / req.users as f64,
+            users_out_of_coins,
+            packs: stats,
         })
     }
 
-    /// Get user's rewards inventory
-    pub async fn get_user_inventory(&self, user_id: &str) -> Result<UserInventoryResponse> {
-        let rewards = sqlx::query_as!(
+    /// Get a single active pack type
+    async fn get_pack_type(&self, pack_type_id: Uuid) -> Result<PackType> {
+        sqlx::query_as!(
+            PackType,
+            r#"
+            SELECT id, name, type, description, icon, color_gradient, 
+                   price_coins, cooldown_hours, min_rewards, max_rewards,
+                   possible_reward_types, is_active, created_at, updated_at
+            FROM pack_types 
+            WHERE id = $1 AND is_active = true
+            "#,
+            pack_type_id
+        )
+        .fetch_optional(&self.db)
+        .await?
+        .ok_or_else(|| crate::error::AppError::NotFound("Pack type not found".to_string()))
+    }
+
+    /// Get a single reward owned by the user with its source pack and redemption instructions
+    pub async fn get_reward_detail(&self, user_id: &str, reward_id: Uuid) -> Result<RewardDetailResponse> {
+        let mut reward = sqlx::query_as!(
             UserReward,
             r#"
             SELECT id, user_id, pack_history_id, template_id, type, title, value,
                    description, code, rarity, source, expires_at, is_used, used_at, created_at
             FROM user_rewards 
-            WHERE user_id = $1
-            ORDER BY created_at DESC
+            WHERE id = $1 AND user_id = $2
             "#,
+            reward_id,
             user_id
         )
-        .fetch_all(&self.db)
-        .await?;
+        .fetch_optional(&self.db)
+        .await?
+        .ok_or_else(|| crate::error::AppError::NotFound("Reward not found".to_string()))?;
+        reward.code = self.codes.reveal_opt(reward.code.take())?;
 
-        let now = Utc::now();
-        let active_count = rewards.iter().filter(|r| !r.is_used.unwrap_or(false)).count() as i32;
-        let used_count = rewards.iter().filter(|r| r.is_used.unwrap_or(false)).count() as i32;
-        let expiring_soon_count = rewards.iter()
-            .filter(|r| !r.is_used.unwrap_or(false) && r.expires_at.map(|exp| (exp - now).num_days() <= 3).unwrap_or(false))
-            .count() as i32;
+        let source_pack = match reward.pack_history_id {
+            Some(history_id) => sqlx::query!(
+                r#"
+                SELECT pt.id, pt.name, uph.opened_at
+                FROM user_pack_history uph
+                JOIN pack_types pt ON pt.id = uph.pack_type_id
+                WHERE uph.id = $1
+                "#,
+                history_id
+            )
+            .fetch_optional(&self.db)
+            .await?
+            .map(|row| RewardSourcePack {
+                pack_type_id: row.id,
+                name: row.name,
+                opened_at: row.opened_at,
+            }),
+            None => None,
+        };
 
-        let stats = InventoryStats {
-            active_count,
-            used_count,
-            expiring_soon_count,
-            total_value_estimate: bigdecimal::BigDecimal::from(850), // TODO: Calculate actual value
+        let now = Utc::now();
+        let status = if reward.is_used.unwrap_or(false) {
+            "used"
+        } else if reward.expires_at.map(|exp| exp <= now).unwrap_or(false) {
+            "expired"
+        } else {
+            "active"
         };
+        let days_remaining = reward.expires_at
+            .filter(|_| status == "active")
+            .map(|exp| (exp - now).num_days());
+        let redemption_instructions = reward_types::redemption_instructions(&reward.r#type, reward.code.as_deref());
 
-        Ok(UserInventoryResponse { rewards, stats })
+        Ok(RewardDetailResponse {
+            reward,
+            source_pack,
+            status: status.to_string(),
+            days_remaining,
+            redemption_instructions,
+        })
     }
 
     /// Get reward pool for a pack type with caching
@@ -395,7 +2857,7 @@ impl LootpackService {
             r#"
             SELECT rt.id, rt.type, rt.title, rt.value, rt.description, rt.rarity,
                    rt.code_pattern, rt.validity_days, rt.metadata, rt.is_active, rt.created_at,
-                   prm.weight
+                   rt.version, prm.weight
             FROM reward_templates rt
             JOIN pack_reward_mappings prm ON rt.id = prm.reward_template_id
             WHERE prm.pack_type_id = $1 AND rt.is_active = true
@@ -407,10 +2869,16 @@ impl LootpackService {
         .await?;
 
         let mut weighted_rewards = Vec::new();
-        let mut cumulative_weight = 0;
+        let mut versions = HashMap::new();
 
         for mapping in mappings {
-            cumulative_weight += mapping.weight.unwrap_or(1);
+            // Skip templates the registry can't handle rather than dealing broken rewards
+            if let Err(e) = reward_types::validate_template(&mapping.r#type, mapping.metadata.as_ref()) {
+                warn!("Skipping reward template {} in pack {}: {:?}", mapping.id, pack_type_id, e);
+                continue;
+            }
+
+            versions.insert(mapping.id, mapping.version);
             
             let template = RewardTemplate {
                 id: mapping.id,
@@ -426,64 +2894,159 @@ impl LootpackService {
                 created_at: Some(mapping.created_at.unwrap_or_else(Utc::now)),
             };
 
-            weighted_rewards.push(WeightedReward {
-                template,
-                weight: mapping.weight.unwrap_or(1),
-                cumulative_weight,
-            });
+            weighted_rewards.push((template, mapping.weight.unwrap_or(1)));
         }
 
-        let pool = RewardPool::new(weighted_rewards);
+        let pool = RewardPool::from_weights(weighted_rewards);
 
-        // Cache the pool
+        // Cache the pool along with the template versions it was built from
         {
             let mut cache = self.reward_cache.write().await;
             cache.insert(pack_type_id, pool.clone());
+            self.template_versions.write().await.insert(pack_type_id, versions);
         }
 
         Ok(pool)
     }
 
-    /// Generate rewards using DSA-optimized weighted selection
-    async fn generate_rewards(
+    /// Reward pool for a specific user: the cached pack pool restricted to the user's
+    /// market, with segment odds and category personalization applied within the odds guardrail
+    async fn get_user_reward_pool(&self, user_id: &str, pack_type_id: Uuid, market: &Market) -> Result<RewardPool> {
+        let reward_pool = self.get_reward_pool_for_pack(pack_type_id).await?;
+        let reward_pool = self.restrict_to_market(reward_pool, market).await?;
+        if reward_pool.rewards.is_empty() {
+            return Err(crate::error::AppError::NotFound(
+                "No rewards are available for this pack in your market".to_string()
+            ));
+        }
+
+        // A failing odds provider must not block openings; fall back to configured weights
+        let adjusted = match self.odds_provider.adjustment(user_id, pack_type_id).await {
+            Ok(adjustment) => adjustment.apply(reward_pool.clone()),
+            Err(e) => {
+                warn!("Odds provider failed for pack {}: {:?}", pack_type_id, e);
+                reward_pool.clone()
+            }
+        };
+        let adjusted = self.personalize_pool(user_id, adjusted).await?;
+
+        Ok(odds::within_guardrail(&reward_pool, adjusted))
+    }
+
+    /// Drop templates that aren't offered in the market
+    async fn restrict_to_market(&self, pool: RewardPool, market: &Market) -> Result<RewardPool> {
+        let template_ids: Vec<Uuid> = pool.rewards.iter().map(|w| w.template.id).collect();
+        let allowed: std::collections::HashSet<Uuid> = sqlx::query_scalar!(
+            "SELECT id FROM reward_templates WHERE id = ANY($1) AND (markets IS NULL OR $2 = ANY(markets))",
+            &template_ids,
+            market.code()
+        )
+        .fetch_all(&self.db)
+        .await?
+        .into_iter()
+        .collect();
+
+        if allowed.len() == pool.rewards.len() {
+            return Ok(pool);
+        }
+
+        Ok(RewardPool::from_weights(
+            pool.rewards
+                .into_iter()
+                .filter(|w| allowed.contains(&w.template.id))
+                .map(|w| (w.template, w.weight)),
+        ))
+    }
+
+    /// Boost weights of templates in the user's top shopping categories. Only called through
+    /// `get_user_reward_pool`, which holds the result to the odds guardrail.
+    /// Returns the shared pool untouched for opted-out users or users without preferences.
+    async fn personalize_pool(&self, user_id: &str, pool: RewardPool) -> Result<RewardPool> {
+        if self.personalization_strength <= 0.0 {
+            return Ok(pool);
+        }
+
+        let top_categories = sqlx::query_scalar!(
+            r#"
+            SELECT ucp.category
+            FROM user_category_preferences ucp
+            LEFT JOIN user_lootpack_stats uls ON uls.user_id = ucp.user_id
+            WHERE ucp.user_id = $1 AND ucp.score > 0
+              AND COALESCE(uls.personalization_opt_out, false) = false
+            ORDER BY ucp.score DESC
+            LIMIT 3
+            "#,
+            user_id
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        if top_categories.is_empty() {
+            return Ok(pool);
+        }
+
+        let template_ids: Vec<Uuid> = pool.rewards.iter().map(|w| w.template.id).collect();
+        let boosted: std::collections::HashSet<Uuid> = sqlx::query_scalar!(
+            "SELECT id FROM reward_templates WHERE id = ANY($1) AND category = ANY($2)",
+            &template_ids,
+            &top_categories
+        )
+        .fetch_all(&self.db)
+        .await?
+        .into_iter()
+        .collect();
+
+        if boosted.is_empty() {
+            return Ok(pool);
+        }
+
+        let multiplier = 1.0 + self.personalization_strength;
+        Ok(RewardPool::from_weights(pool.rewards.into_iter().map(|w| {
+            let weight = if boosted.contains(&w.template.id) {
+                ((w.weight as f64) * multiplier).round() as i32
+            } else {
+                w.weight
+            };
+            (w.template, weight)
+        })))
+    }
+
+    /// Generate rewards using DSA-optimized weighted selection.
+    /// `codes_tx` is the transaction dealing the rewards, which partner codes are taken in;
+    /// `None` for simulations and previews so partner codes aren't claimed.
+    async fn generate_rewards<'a>(
         &self,
-        pool: &RewardPool,
+        pool: &'a RewardPool,
         count: i32,
         pack_type: &PackType,
-    ) -> Result<Vec<GeneratedReward>> {
+        happy_hour: &OddsAdjustment,
+        mut codes_tx: Option<&mut sqlx::PgConnection>,
+        rng: &dyn RngProvider,
+    ) -> Result<Vec<DrawnReward<'a>>> {
         let mut rewards = Vec::new();
 
+        // Happy-hour boosts only reweight the draw; templates still come from `pool`
+        let boosted_pool = (!happy_hour.is_neutral()).then(|| happy_hour.apply(pool.clone()));
+        let weighted_pool = boosted_pool.as_ref().unwrap_or(pool);
+
         // Guarantee at least one rare+ reward for premium packs
-        if pack_type.r#type == "premium" && pack_type.price_coins.unwrap_or(0) >= 299 {
-            let rare_rewards = pool.get_by_rarity("rare");
-            let epic_rewards = pool.get_by_rarity("epic");
-            let legendary_rewards = pool.get_by_rarity("legendary");
-            
-            let mut guaranteed_pool = Vec::new();
-            guaranteed_pool.extend(rare_rewards);
-            guaranteed_pool.extend(epic_rewards);
-            guaranteed_pool.extend(legendary_rewards);
-            
-            if !guaranteed_pool.is_empty() {
-                let idx = {
-                    let mut rng = rand::thread_rng();
-                    rng.gen_range(0..guaranteed_pool.len())
-                };
-                let template = guaranteed_pool[idx];
-                rewards.push(self.template_to_generated_reward(template).await?);
+        if has_rare_guarantee(pack_type) {
+            if let Some(template) = pick_rare_guarantee(pool, rng) {
+                rewards.push(self.draw_reward(pool, template, codes_tx.as_deref_mut()).await?);
             }
         }
 
         // Fill remaining slots with weighted random selection
         let remaining_count = count - rewards.len() as i32;
         for _ in 0..remaining_count {
-            if pool.total_weight > 0 {
-                let target_weight = {
-                    let mut rng = rand::thread_rng();
-                    rng.gen_range(1..=pool.total_weight)
-                };
-                if let Some(template) = pool.select_by_weight(target_weight) {
-                    rewards.push(self.template_to_generated_reward(template).await?);
+            if weighted_pool.total_weight > 0 {
+                let target_weight = rng.gen_range(1, weighted_pool.total_weight);
+                let template = weighted_pool
+                    .select_by_weight(target_weight)
+                    .and_then(|selected| pool.rewards.iter().find(|w| w.template.id == selected.id))
+                    .map(|w| &w.template);
+                if let Some(template) = template {
+                    rewards.push(self.draw_reward(pool, template, codes_tx.as_deref_mut()).await?);
                 }
             }
         }
@@ -491,15 +3054,137 @@ impl LootpackService {
         Ok(rewards)
     }
 
-    /// Convert reward template to generated reward
-    async fn template_to_generated_reward(&self, template: &RewardTemplate) -> Result<GeneratedReward> {
-        let code = if template.r#type == "coupon" || template.r#type == "voucher" {
-            Some(self.generate_coupon_code(&template.r#type).await)
+    /// Turn a selected template into a reward, claiming a merchant code from the coupon
+    /// provider when possible. If the provider fails, the template's `fallback_template_id`
+    /// (when it is in the same pool) is dealt instead, otherwise a local code is generated.
+    async fn draw_reward<'a>(
+        &self,
+        pool: &'a RewardPool,
+        template: &'a RewardTemplate,
+        codes_tx: Option<&mut sqlx::PgConnection>,
+    ) -> Result<DrawnReward<'a>> {
+        let is_code_reward = reward_types::spec(&template.r#type).map(|t| t.has_code).unwrap_or(false);
+        let codes_tx = match codes_tx {
+            Some(codes_tx) if is_code_reward => codes_tx,
+            _ => {
+                return Ok(DrawnReward {
+                    template,
+                    selected_id: template.id,
+                    reward: self.template_to_generated_reward(template, None).await?,
+                    external_code: false,
+                });
+            }
+        };
+
+        match self.take_partner_code(codes_tx, template).await {
+            Ok(Some(code)) => Ok(DrawnReward {
+                template,
+                selected_id: template.id,
+                reward: self.template_to_generated_reward(template, Some(code)).await?,
+                external_code: true,
+            }),
+            Ok(None) => Ok(DrawnReward {
+                template,
+                selected_id: template.id,
+                reward: self.template_to_generated_reward(template, None).await?,
+                external_code: false,
+            }),
+            Err(e) => {
+                warn!("Coupon provider failed for template {}: {:?}", template.id, e);
+                let fallback = template.metadata.as_ref()
+                    .and_then(|m| m.get("fallback_template_id"))
+                    .and_then(|v| v.as_str())
+                    .and_then(|id| Uuid::parse_str(id).ok())
+                    .and_then(|id| pool.rewards.iter().find(|w| w.template.id == id))
+                    .map(|w| &w.template)
+                    .unwrap_or(template);
+
+                Ok(DrawnReward {
+                    template: fallback,
+                    selected_id: template.id,
+                    reward: self.template_to_generated_reward(fallback, None).await?,
+                    external_code: false,
+                })
+            }
+        }
+    }
+
+    /// A partner code for `template`, marked used in `codes_tx`. A provider claim can't be
+    /// undone, so claimed codes are committed to `partner_codes` on their own first; a deal
+    /// that rolls back leaves its code in stock for the next draw of the template.
+    async fn take_partner_code(
+        &self,
+        codes_tx: &mut sqlx::PgConnection,
+        template: &RewardTemplate,
+    ) -> Result<Option<String>> {
+        while let Some(stored) = sqlx::query_scalar!(
+            r#"
+            UPDATE partner_codes SET used_at = NOW()
+            WHERE id = (
+                SELECT id FROM partner_codes
+                WHERE reward_template_id = $1 AND used_at IS NULL
+                ORDER BY claimed_at
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING code
+            "#,
+            template.id
+        )
+        .fetch_optional(&mut *codes_tx)
+        .await?
+        {
+            // A code whose deal failed as a duplicate would otherwise be retried forever
+            let code = self.codes.reveal(&stored)?;
+            let issued = sqlx::query_scalar!(
+                r#"
+                SELECT EXISTS(
+                    SELECT 1 FROM user_rewards WHERE code_hash = $1 OR (code_hash IS NULL AND code = $2)
+                ) AS "issued!"
+                "#,
+                self.codes.blind_index(&code),
+                code
+            )
+            .fetch_one(&mut *codes_tx)
+            .await?;
+            if !issued {
+                return Ok(Some(code));
+            }
+            warn!("Discarding stocked partner code for template {} that was already issued", template.id);
+        }
+
+        let Some(code) = self.coupon_provider.claim_code(template).await? else {
+            return Ok(None);
+        };
+        let id = sqlx::query_scalar!(
+            "INSERT INTO partner_codes (reward_template_id, code) VALUES ($1, $2) RETURNING id",
+            template.id,
+            self.codes.seal(&code)?
+        )
+        .fetch_one(&self.db)
+        .await?;
+        sqlx::query!("UPDATE partner_codes SET used_at = NOW() WHERE id = $1", id)
+            .execute(&mut *codes_tx)
+            .await?;
+
+        Ok(Some(code))
+    }
+
+    /// Convert reward template to generated reward, generating a code unless one is supplied
+    async fn template_to_generated_reward(
+        &self,
+        template: &RewardTemplate,
+        code: Option<String>,
+    ) -> Result<GeneratedReward> {
+        let code = if code.is_some() {
+            code
+        } else if reward_types::spec(&template.r#type).map(|t| t.has_code).unwrap_or(false) {
+            Some(self.generate_coupon_code(&template.r#type, template.code_pattern.as_deref()).await)
         } else {
             None
         };
 
-        let expires_at = if template.r#type == "points" {
+        let expires_at = if !reward_types::spec(&template.r#type).map(|t| t.expires).unwrap_or(true) {
             None
         } else {
             template.validity_days.map(|days| Utc::now() + Duration::days(days as i64))
@@ -517,19 +3202,48 @@ impl LootpackService {
         })
     }
 
-    /// Generate unique coupon codes
-    async fn generate_coupon_code(&self, reward_type: &str) -> String {
-        let prefixes = match reward_type {
-            "coupon" => vec!["DEAL", "SAVE", "SHOP", "MEGA", "SUPER"],
-            "voucher" => vec!["GIFT", "FREE", "ENJOY", "TREAT", "BONUS"],
-            _ => vec!["DEAL"],
+    /// Generate a coupon code from the template's `code_pattern`, falling back to a
+    /// random prefix plus an 8-character suffix. In patterns `#` is a digit, `X` a
+    /// character from `CODE_ALPHABET`, and anything else is copied literally.
+    /// Uniqueness is enforced by the unique index on `user_rewards.code_hash`.
+    async fn generate_coupon_code(&self, reward_type: &str, code_pattern: Option<&str>) -> String {
+        let pattern = match code_pattern {
+            Some(pattern) if pattern.contains(['#', 'X']) => pattern.to_string(),
+            _ => {
+                let prefixes = match reward_type {
+                    "coupon" => vec!["DEAL", "SAVE", "SHOP", "MEGA", "SUPER"],
+                    "voucher" => vec!["GIFT", "FREE", "ENJOY", "TREAT", "BONUS"],
+                    "free_shipping" => vec!["SHIPFREE", "FREESHIP"],
+                    "experience" => vec!["EXP", "BOOK"],
+                    _ => vec!["DEAL"],
+                };
+                format!("{}-XXXXXXXX", prefixes[self.rng.gen_index(prefixes.len())])
+            }
         };
 
-        let mut rng = rand::thread_rng();
-        let prefix = prefixes[rng.gen_range(0..prefixes.len())];
-        let suffix = rng.gen_range(100..999);
-        
-        format!("{}{}", prefix, suffix)
+        pattern
+            .chars()
+            .map(|c| match c {
+                '#' => char::from(b'0' + self.rng.gen_index(10) as u8),
+                'X' => char::from(CODE_ALPHABET[self.rng.gen_index(CODE_ALPHABET.len())]),
+                other => other,
+            })
+            .collect()
+    }
+}
+
+impl RewardPool {
+    /// Pool of `(template, weight)` pairs in draw order, with cumulative weights filled in
+    pub(crate) fn from_weights(weights: impl IntoIterator<Item = (RewardTemplate, i32)>) -> Self {
+        let mut cumulative_weight = 0;
+        let weighted_rewards = weights
+            .into_iter()
+            .map(|(template, weight)| {
+                cumulative_weight += weight;
+                WeightedReward { template, weight, cumulative_weight }
+            })
+            .collect();
+        RewardPool::new(weighted_rewards)
     }
 }
 
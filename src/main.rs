@@ -1,15 +1,141 @@
-use axum::{routing::{get, post}, Router, Json};
+use axum::{
+    body::Body,
+    extract::{FromRequestParts, Path, Query, Request, State},
+    http::{header, request::Parts, HeaderMap, HeaderValue, StatusCode},
+    middleware::{self, Next},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::{delete, get, post, put},
+    Extension, Router, Json,
+};
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use sqlx::postgres::PgPoolOptions;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use sqlx::{PgPool, Row};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_stream::{wrappers::IntervalStream, Stream, StreamExt};
+use tower_http::compression::CompressionLayer;
 use tower_http::cors::CorsLayer;
+use tower_http::set_header::SetResponseHeaderLayer;
+use tracing::Instrument;
+
+/// Arbitrary key shared by all replicas so only one runs migrations at a time.
+const MIGRATION_LOCK_KEY: i64 = 0x6c6f_6f74_7061_636b;
+
+static READY: AtomicBool = AtomicBool::new(false);
 
 #[tokio::main]
 async fn main() {
+    if std::env::var("RUN_MIGRATIONS").map(|v| v == "true").unwrap_or(false) {
+        tokio::spawn(async {
+            match run_migrations().await {
+                Ok(()) => READY.store(true, Ordering::SeqCst),
+                Err(e) => eprintln!("❌ Migrations failed: {}", e),
+            }
+        });
+    } else {
+        READY.store(true, Ordering::SeqCst);
+    }
+
+    let config = AppConfig::from_env();
+    // The pack list may be reused briefly; user state and everything else is never stored
+    let pack_list_cache = SetResponseHeaderLayer::overriding(
+        header::CACHE_CONTROL,
+        HeaderValue::from_str(&format!("private, max-age={}", config.pack_list_max_age.as_secs()))
+            .expect("cache-control value is ASCII"),
+    );
+
     let app = Router::new()
         .route("/health", get(health))
-        .route("/lootpacks", get(get_lootpacks))
+        .route("/health/ready", get(ready))
+        .route("/lootpacks", get(get_lootpacks).layer(pack_list_cache))
+        .route("/graphql", post(graphql))
         .route("/lootpacks/create", post(create_lootpack))
+        .route("/lootpacks/:id", get(get_lootpack))
         .route("/lootpacks/:id/open", post(open_lootpack))
+        .route("/lootpacks/:id/buy", post(buy_lootpack))
+        .route("/lootpacks/:id/preview", get(preview_lootpack))
+        .route("/lootpacks/:id/social-proof", get(get_social_proof))
+        .route("/lootpacks/:id/choose", post(choose_lootpack_reward))
+        .route("/lootpacks/:id/mystery", post(open_mystery_lootpack))
+        .route("/mystery-choices/:id/pick", post(pick_mystery_reward))
+        .route("/lootpacks/:id/odds-acknowledgement", post(acknowledge_odds))
+        .route("/daily/claim", post(claim_daily_pack))
         .route("/rewards", get(get_rewards))
+        .route("/rewards/redeem-bulk", post(redeem_rewards_bulk))
+        .route("/rewards/:id", get(get_reward))
+        .route("/rewards/:id/cashback/claim", post(claim_cashback))
+        .route("/rewards/:id/gift-card", get(get_gift_card_status).post(redeem_gift_card))
+        .route("/rewards/:id/trade-in", post(trade_in_reward))
+        .route("/rewards/:id/reroll", post(reroll_reward))
+        .route("/rewards/:id/share", post(share_reward).delete(revoke_reward_share))
+        .route("/share/:token", get(get_shared_reward))
+        .route("/trades", get(list_trades).post(propose_trade))
+        .route("/trades/:id", get(get_trade))
+        .route("/trades/:id/accept", post(accept_trade))
+        .route("/trades/:id/cancel", post(cancel_trade))
+        .route("/users/me/stats", get(get_user_stats))
+        .route("/users/me/currency", put(update_display_currency))
+        .route("/users/me/spending-limits", get(get_spending_limits).put(update_spending_limits))
+        .route("/users/me/self-exclusion", get(get_self_exclusion).post(start_self_exclusion))
+        .route("/users/me/packs", get(get_unopened_packs))
+        .route("/users/me/packs/:id/open", post(open_unopened_pack))
+        .route("/users/me/packs/auto-open", post(auto_open_packs))
+        .route("/users/me/open-preferences", get(get_open_preferences).put(update_open_preferences))
+        .route("/users/me/streak-repair", get(get_streak_repair).post(repair_streak))
+        .route("/users/me/timezone", get(get_timezone).put(set_timezone))
+        .route("/users/me/vip", get(get_vip_status))
+        .route("/users/me/prestige", get(get_prestige).post(prestige))
+        .route("/users/me/vacation", get(get_vacation).post(start_vacation).delete(end_vacation))
+        .route("/promos/redeem", post(redeem_promo))
+        .route("/inbox", get(get_inbox))
+        .route("/inbox/:id", delete(delete_inbox_message))
+        .route("/inbox/:id/read", post(mark_inbox_message_read))
+        .route("/inbox/:id/claim", post(claim_inbox_message))
+        .route("/users/me/pack-history", get(get_pack_history))
+        .route("/users/me/pack-history/:id/verify", get(verify_pack_open))
+        .route("/users/me/collection", get(get_collection))
+        .route("/users/me/collection/summary", get(get_collection_summary))
+        .route("/users/me/reward-sets", get(get_reward_set_progress))
+        .route("/users/me/login-calendar", get(get_login_calendar))
+        .route("/users/me/login-calendar/claim", post(claim_login_calendar))
+        .route("/community-events", get(list_community_events))
+        .route("/community-events/:id", get(get_community_event))
+        .route("/wheels", get(list_wheels))
+        .route("/wheels/:id/spin", post(spin_wheel))
+        .route("/users/me/friends/pulls", get(get_friend_pulls))
+        .route("/users/me/privacy", get(get_privacy_settings).put(update_privacy_settings))
+        .route("/live-feed", get(get_live_feed))
+        .route("/live-feed/stream", get(stream_live_feed))
+        .route("/teams", post(create_team))
+        .route("/teams/:id", get(get_team))
+        .route("/teams/:id/join", post(join_team))
+        .route("/users/me/team", get(get_my_team).delete(leave_team))
+        .route("/users/me/team/puzzles", get(list_team_puzzles))
+        .route("/users/me/team/puzzle/contribute", post(contribute_puzzle_pieces))
+        .route("/shared-puzzles/:id/claim", post(claim_shared_puzzle))
+        .route("/coins/transfer", post(transfer_coins))
+        .route("/fairness/commitments", post(create_fairness_commitment))
+        .route("/users/me/personalization", get(get_personalization).put(update_personalization))
+        .route("/users/me/notification-preferences", get(get_notification_preferences).put(update_notification_preferences))
+        .route("/users/me/wishlist", get(get_wishlist))
+        .route("/users/me/wishlist/:template_id", put(add_wishlist_item).delete(remove_wishlist_item))
+        .nest("/admin", admin_routes())
+        .nest("/support", support_routes())
+        .nest("/internal", internal_routes())
+        .nest("/internal/webhooks", webhook_routes(Arc::new(WebhookVerifier::from_env())))
+        .layer(middleware::from_fn(request_id))
+        .layer(SetResponseHeaderLayer::if_not_present(header::CACHE_CONTROL, HeaderValue::from_static("no-store")))
+        .layer(CompressionLayer::new().gzip(config.compression).br(config.compression))
         .layer(CorsLayer::permissive());
 
     let listener = tokio::net::TcpListener::bind("0.0.0.0:3005").await.unwrap();
@@ -17,40 +143,3129 @@ async fn main() {
     axum::serve(listener, app).await.unwrap();
 }
 
+const REQUEST_ID_HEADER: &str = "x-request-id";
+/// Longest caller-supplied request id that is kept; longer ones are replaced
+const MAX_REQUEST_ID_LEN: usize = 128;
+/// Largest error body read back to add the request id to
+const MAX_ERROR_BODY_BYTES: usize = 64 * 1024;
+
+tokio::task_local! {
+    /// Ties one request together across services. Taken from `x-request-id` when the
+    /// caller sent a usable one, generated otherwise.
+    static CURRENT_REQUEST_ID: String;
+}
+
+/// Id of the request this task is serving, for logs and outbound calls
+fn current_request_id() -> Option<String> {
+    CURRENT_REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// Outermost middleware inside compression: assigns the request id, runs the request in a
+/// span carrying it and echoes it in the response header and in JSON error bodies
+async fn request_id(mut req: Request, next: Next) -> Response {
+    let id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|id| is_valid_request_id(id))
+        .map(str::to_string)
+        .unwrap_or_else(generate_request_id);
+    let header_value = HeaderValue::from_str(&id).expect("request ids are visible ASCII");
+    req.headers_mut().insert(REQUEST_ID_HEADER, header_value.clone());
+
+    let span = tracing::info_span!("request", request_id = %id, method = %req.method(), path = %req.uri().path());
+    let mut response = CURRENT_REQUEST_ID.scope(id.clone(), next.run(req).instrument(span)).await;
+    if response.status().is_client_error() || response.status().is_server_error() {
+        response = with_request_id_in_body(response, &id).await;
+    }
+    response.headers_mut().insert(REQUEST_ID_HEADER, header_value);
+    response
+}
+
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id.bytes().all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b':'))
+}
+
+/// A random (v4) UUID
+fn generate_request_id() -> String {
+    let mut bytes: [u8; 16] = rand::random();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = hex::encode(bytes);
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+/// Add `request_id` to a JSON error object so it shows up in whatever the client logs
+async fn with_request_id_in_body(response: Response, id: &str) -> Response {
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"));
+    if !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = axum::body::to_bytes(body, MAX_ERROR_BODY_BYTES).await else {
+        // Too large to rewrite: keep the status, swap the body for a minimal one
+        parts.headers.remove(header::CONTENT_LENGTH);
+        let error = parts.status.canonical_reason().unwrap_or("Error");
+        return Response::from_parts(parts, Body::from(json!({"error": error, "request_id": id}).to_string()));
+    };
+    let body = match serde_json::from_slice::<Value>(&bytes) {
+        Ok(Value::Object(mut error)) => {
+            error.insert("request_id".to_string(), json!(id));
+            parts.headers.remove(header::CONTENT_LENGTH);
+            Body::from(Value::Object(error).to_string())
+        }
+        _ => Body::from(bytes),
+    };
+    Response::from_parts(parts, body)
+}
+
+/// Settings read from the environment at startup
+struct AppConfig {
+    /// gzip/br responses for clients that accept them; `RESPONSE_COMPRESSION=false` turns it off
+    compression: bool,
+    /// How long clients may reuse the pack list before revalidating, from `PACK_LIST_MAX_AGE_SECONDS`
+    pack_list_max_age: Duration,
+}
+
+impl AppConfig {
+    const DEFAULT_PACK_LIST_MAX_AGE: Duration = Duration::from_secs(30);
+
+    fn from_env() -> Self {
+        let compression = std::env::var("RESPONSE_COMPRESSION").map(|v| v != "false").unwrap_or(true);
+        let pack_list_max_age = std::env::var("PACK_LIST_MAX_AGE_SECONDS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(Self::DEFAULT_PACK_LIST_MAX_AGE);
+        Self { compression, pack_list_max_age }
+    }
+}
+
+/// Operator routes. Each group needs a caller allowed its scope; see `authorize`.
+fn admin_routes() -> Router {
+    Router::new()
+        .merge(scoped(
+            "admin:packs",
+            Router::new()
+                .route("/lootpacks/:id/discounts", get(list_discounts).post(create_discount))
+                .route("/discounts/:id", delete(delete_discount))
+                .route("/reward-templates/:id/history", get(get_template_history))
+                .route("/reward-templates/:id/versions/:version", get(get_template_version))
+                .route("/lootpacks/:id/mapping-history", get(get_mapping_history))
+                .route("/lootpacks/:id", put(update_pack_config))
+                .route("/lootpacks/:id/versions", get(list_pack_versions))
+                .route("/lootpacks/:id/simulate", post(simulate_lootpack))
+                .route("/lootpacks/:id/rollback", post(rollback_pack))
+                .route("/lootpacks/:id/rotation", put(set_pack_rotation).delete(remove_pack_rotation))
+                .route("/config/export", get(export_config))
+                .route("/lootpacks/:id/icon", post(upload_icon))
+                .route("/reward-templates/:id/icon", post(upload_icon))
+                .route("/markets", get(list_markets))
+                .route("/reward-sets", get(list_reward_sets).post(create_reward_set))
+                .route("/reward-sets/:id", put(update_reward_set))
+                .route("/wheels", post(create_wheel))
+                .route("/wheels/:id", put(update_wheel))
+                .route("/markets/:code", put(upsert_market))
+                .route("/lootpacks/:id/markets", put(set_markets))
+                .route("/reward-templates/:id/markets", put(set_markets))
+                .route("/config/import", post(import_config))
+                .route("/lootpacks/:id/translations", get(list_translations))
+                .route("/lootpacks/:id/translations/:locale", put(upsert_translation).delete(delete_translation))
+                .route("/reward-templates/:id/translations", get(list_translations))
+                .route("/reward-templates/:id/translations/:locale", put(upsert_translation).delete(delete_translation)),
+        ))
+        .merge(scoped(
+            "admin:economy",
+            Router::new()
+                .route("/economy", get(get_economy_report))
+                .route("/economy/simulate", post(simulate_economy))
+                .route("/vip/plans", get(list_vip_plans))
+                .route("/vip/plans/:plan_id", put(upsert_vip_plan))
+                .route("/coin-transfers/settings", get(get_transfer_settings).put(update_transfer_settings))
+                .route("/currency-rates/:currency", put(upsert_currency_rate))
+                .route("/trade-in-rates", get(list_trade_in_rates))
+                .route("/trade-in-rates/:rarity", put(update_trade_in_rate)),
+        ))
+        .merge(scoped(
+            "admin:compliance",
+            Router::new()
+                .route("/odds-audits", get(list_odds_audits))
+                .route("/chargebacks", get(list_open_chargebacks))
+                .route("/coin-transfers/flags", get(list_transfer_flags))
+                .route("/coin-transfers/flags/:id/review", post(review_transfer_flag))
+                .route("/compliance-policies", get(list_compliance_policies))
+                .route("/spending-limits/:period", put(update_default_spending_limits))
+                .route("/compliance-policies/:region", put(upsert_compliance_policy)),
+        ))
+        .merge(scoped(
+            "admin:liveops",
+            Router::new()
+                .route("/announcements", post(create_announcement))
+                .route("/login-calendar/:month", get(get_login_calendar_month).put(set_login_calendar_month))
+                .route("/community-events", get(admin_list_community_events).post(create_community_event))
+                .route("/teams/settings", get(get_team_settings).put(update_team_settings))
+                .route("/shared-puzzles/settings", get(get_shared_puzzle_settings).put(update_shared_puzzle_settings))
+                .route("/winback/settings", get(get_winback_settings).put(update_winback_settings))
+                .route("/streaks/settings", get(get_streak_settings).put(update_streak_settings))
+                .route("/flags", get(list_flags))
+                .route("/flags/:key", put(upsert_flag).delete(delete_flag)),
+        ))
+        .merge(scoped(
+            "admin:refunds",
+            Router::new().route("/pack-history/:id/refund", post(refund_pack_open)),
+        ))
+        .merge(scoped(
+            "admin:operations",
+            Router::new()
+                .route("/deal-sync/run", post(run_deal_sync))
+                .route("/deal-sync/runs", get(list_deal_sync_runs))
+                .route("/event-export/checkpoints", get(list_event_export_checkpoints))
+                .route("/code-encryption", get(get_code_encryption_status))
+                .route("/gift-cards/:id/retry", post(retry_gift_card)),
+        ))
+}
+
+/// Troubleshooting for support agents. Actions require a reason, which is audited.
+fn support_routes() -> Router {
+    Router::new()
+        .merge(scoped("support:read", Router::new().route("/users/:user_id", get(support_lookup_user))))
+        .merge(scoped(
+            "support:actions",
+            Router::new()
+                .route("/users/:user_id/rewards/:reward_id/resend-code", post(support_resend_code))
+                .route("/users/:user_id/rewards/:reward_id/extend-expiry", post(support_extend_expiry)),
+        ))
+        .merge(scoped("support:grant", Router::new().route("/users/:user_id/packs", post(support_grant_pack))))
+}
+
+/// Service-to-service routes, guarded per group by an `internal:*` scope
+fn internal_routes() -> Router {
+    Router::new()
+        .merge(scoped(
+            "internal:validate",
+            Router::new()
+                .route("/coupons/validate", post(validate_coupon))
+                .route("/rewards/:id/reserve", post(reserve_reward))
+                .route("/rewards/:id/release", post(release_reward))
+                .route("/rewards/:id/confirm", post(confirm_reward)),
+        ))
+        .merge(scoped(
+            "internal:events",
+            Router::new().route("/events/purchase-completed", post(purchase_completed)),
+        ))
+        .merge(scoped(
+            "internal:analytics",
+            Router::new()
+                .route("/analytics/pack-opens", get(analytics_pack_opens))
+                .route("/analytics/rarity-distribution", get(analytics_rarity_distribution))
+                .route("/analytics/redemption-rates", get(analytics_redemption_rates))
+                .route("/analytics/coin-flows", get(analytics_coin_flows))
+                .route("/analytics/refresh", post(refresh_analytics)),
+        ))
+}
+
+/// Require a caller allowed `scope` on every route of `router`; see `authorize`
+fn scoped(scope: &'static str, router: Router) -> Router {
+    router.route_layer(middleware::from_fn_with_state(scope, authorize))
+}
+
+/// The single authorization check for admin, support and internal routes. Handlers never
+/// look at roles themselves; which scope a route needs is decided where it is mounted.
+async fn authorize(
+    State(scope): State<&'static str>,
+    principal: Principal,
+    mut req: Request,
+    next: Next,
+) -> Result<Response, (StatusCode, Json<Value>)> {
+    if !principal.allows(scope) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(json!({"error": format!("'{}' lacks permission {}", principal.name, scope), "service": "lootpacks-service"})),
+        ));
+    }
+    req.extensions_mut().insert(principal);
+    Ok(next.run(req).await)
+}
+
+/// Staff roles. End users aren't principals: their routes sit outside `authorize`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Role {
+    Support,
+    LiveOps,
+    Admin,
+}
+
+impl Role {
+    fn parse(name: &str) -> Option<Role> {
+        match name {
+            "support" => Some(Role::Support),
+            "live-ops" => Some(Role::LiveOps),
+            "admin" => Some(Role::Admin),
+            _ => None,
+        }
+    }
+
+    /// The permission matrix. Support can look users up, grant rewards and refund opens but
+    /// can't touch pack configuration or odds; live-ops runs events and operations on top of
+    /// that. `internal:*` is never granted by a role, only to service keys directly.
+    fn scopes(self) -> &'static [&'static str] {
+        match self {
+            Role::Support => &["support:read", "support:actions", "support:grant", "admin:refunds"],
+            Role::LiveOps => &[
+                "support:read",
+                "support:actions",
+                "support:grant",
+                "admin:refunds",
+                "admin:liveops",
+                "admin:operations",
+            ],
+            Role::Admin => &["admin:*", "support:*"],
+        }
+    }
+}
+
+/// `granted` covers `scope` exactly or through an `area:*` wildcard
+fn scope_matches(granted: &str, scope: &str) -> bool {
+    granted == scope || granted.strip_suffix(":*") == scope.split(':').next()
+}
+
+/// Who is calling: a staff member with a JWT, or a tool or service with an API key. Either
+/// carries roles, and API keys may also carry scopes directly.
+#[derive(Clone, Debug)]
+struct Principal {
+    name: String,
+    roles: Vec<Role>,
+    scopes: Vec<String>,
+}
+
+impl Principal {
+    fn allows(&self, scope: &str) -> bool {
+        self.scopes
+            .iter()
+            .map(String::as_str)
+            .chain(self.roles.iter().flat_map(|role| role.scopes().iter().copied()))
+            .any(|granted| scope_matches(granted, scope))
+    }
+
+    fn with_roles(name: String, roles: &[String], scopes: Vec<String>) -> Principal {
+        let roles = roles.iter().filter_map(|role| Role::parse(role)).collect();
+        Principal { name, roles, scopes }
+    }
+}
+
+/// How long a key lookup is reused; a revoked key keeps working for at most this long
+const API_KEY_CACHE_TTL: Duration = Duration::from_secs(60);
+
+static API_KEY_POOL: OnceLock<Option<PgPool>> = OnceLock::new();
+/// Lookups by key hash, including misses so a bad key can't hammer the database
+type ApiKeyCache = Mutex<HashMap<String, (Instant, Option<Principal>)>>;
+
+static API_KEY_CACHE: OnceLock<ApiKeyCache> = OnceLock::new();
+
+/// Find the active key presented in `x-api-key`. Keys are created with
+/// `lootpacks-admin create-api-key` and stored as SHA-256 hashes in `api_keys`.
+async fn lookup_api_key(key: &str) -> Result<Option<Principal>, sqlx::Error> {
+    let hash = hex::encode(Sha256::digest(key.as_bytes()));
+    let cache = API_KEY_CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Some((fetched_at, cached)) = cache.lock().unwrap_or_else(|e| e.into_inner()).get(&hash) {
+        if fetched_at.elapsed() < API_KEY_CACHE_TTL {
+            return Ok(cached.clone());
+        }
+    }
+
+    let pool = API_KEY_POOL.get_or_init(|| {
+        let url = std::env::var("DATABASE_URL").ok()?;
+        PgPoolOptions::new().max_connections(2).connect_lazy(&url).ok()
+    });
+    let Some(pool) = pool else {
+        return Err(sqlx::Error::Configuration("DATABASE_URL is not set".into()));
+    };
+
+    let row = sqlx::query(
+        r#"
+        UPDATE api_keys SET last_used_at = NOW()
+        WHERE key_hash = $1 AND revoked_at IS NULL
+        RETURNING name, roles, scopes
+        "#,
+    )
+    .bind(&hash)
+    .fetch_optional(pool)
+    .await?;
+    let found = row
+        .map(|row| -> Result<Principal, sqlx::Error> {
+            let roles: Vec<String> = row.try_get("roles")?;
+            Ok(Principal::with_roles(row.try_get("name")?, &roles, row.try_get("scopes")?))
+        })
+        .transpose()?;
+
+    let mut cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+    cache.retain(|_, (fetched_at, _)| fetched_at.elapsed() < API_KEY_CACHE_TTL);
+    cache.insert(hash, (Instant::now(), found.clone()));
+    Ok(found)
+}
+
+#[derive(Deserialize)]
+struct JwtClaims {
+    sub: String,
+    exp: i64,
+    #[serde(default)]
+    roles: Vec<String>,
+}
+
+/// Verify an HS256 JWT signed with `JWT_SECRET`, as issued by the auth service. `None` when
+/// the token is malformed, wrongly signed or expired.
+fn verify_jwt(token: &str, secret: &[u8]) -> Option<JwtClaims> {
+    use base64::engine::{general_purpose::URL_SAFE_NO_PAD, Engine};
+
+    let mut parts = token.split('.');
+    let (header, payload, signature) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() {
+        return None;
+    }
+
+    let jose_header: Value = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(header).ok()?).ok()?;
+    if jose_header["alg"] != "HS256" {
+        return None;
+    }
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).ok()?;
+    mac.update(header.as_bytes());
+    mac.update(b".");
+    mac.update(payload.as_bytes());
+    mac.verify_slice(&URL_SAFE_NO_PAD.decode(signature).ok()?).ok()?;
+
+    let claims: JwtClaims = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).ok()?).ok()?;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default();
+    (claims.exp > now).then_some(claims)
+}
+
+/// Compare the legacy service token through HMACs, so timing doesn't reveal how much of it matched
+fn service_token_matches(provided: &str, expected: &str) -> bool {
+    let tag = |token: &str| {
+        let mut mac = Hmac::<Sha256>::new_from_slice(expected.as_bytes()).expect("HMAC accepts any key length");
+        mac.update(token.as_bytes());
+        mac
+    };
+    tag(provided).verify_slice(&tag(expected).finalize().into_bytes()).is_ok()
+}
+
+/// How often use of the legacy service token is logged
+const SERVICE_TOKEN_WARNING_INTERVAL: Duration = Duration::from_secs(60);
+
+static SERVICE_TOKEN_WARNED_AT: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// The service token is deprecated; the warning shows whether anything still sends it
+fn warn_service_token_use() {
+    let mut warned_at = SERVICE_TOKEN_WARNED_AT.lock().unwrap_or_else(|e| e.into_inner());
+    if warned_at.is_none_or(|at| at.elapsed() >= SERVICE_TOKEN_WARNING_INTERVAL) {
+        *warned_at = Some(Instant::now());
+        eprintln!(
+            "⚠️ [{}] x-service-token is deprecated; give the caller an API key with its internal:* scopes",
+            current_request_id().unwrap_or_default()
+        );
+    }
+}
+
+/// Credentials in order: the legacy `x-service-token` (authenticates as `internal:*`, so
+/// callers can move to their own keys one at a time), `x-api-key`, then a bearer JWT.
+#[axum::async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Principal {
+    type Rejection = (StatusCode, Json<Value>);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let reject = |code: StatusCode, error: &str| (code, Json(json!({"error": error, "service": "lootpacks-service"})));
+        let header_value = |name: &str| parts.headers.get(name).and_then(|v| v.to_str().ok());
+
+        if let (Some(provided), Ok(expected)) = (header_value("x-service-token"), std::env::var("INTERNAL_SERVICE_TOKEN")) {
+            if service_token_matches(provided, &expected) {
+                warn_service_token_use();
+                return Ok(Principal { name: "service-token".into(), roles: vec![], scopes: vec!["internal:*".into()] });
+            }
+        }
+
+        if let Some(key) = header_value("x-api-key") {
+            return match lookup_api_key(key).await {
+                Ok(Some(principal)) => Ok(principal),
+                Ok(None) => Err(reject(StatusCode::UNAUTHORIZED, "Invalid API key")),
+                Err(e) => {
+                    eprintln!("❌ [{}] API key lookup failed: {}", current_request_id().unwrap_or_default(), e);
+                    Err(reject(StatusCode::SERVICE_UNAVAILABLE, "API keys unavailable"))
+                }
+            };
+        }
+
+        let Some(token) = header_value(header::AUTHORIZATION.as_str()).and_then(|v| v.strip_prefix("Bearer ")) else {
+            return Err(reject(StatusCode::UNAUTHORIZED, "Missing credentials"));
+        };
+        let secret = std::env::var("JWT_SECRET")
+            .map_err(|_| reject(StatusCode::SERVICE_UNAVAILABLE, "Token verification unavailable"))?;
+        let claims = verify_jwt(token, secret.as_bytes()).ok_or_else(|| reject(StatusCode::UNAUTHORIZED, "Invalid token"))?;
+        Ok(Principal::with_roles(claims.sub, &claims.roles, vec![]))
+    }
+}
+
+/// Inbound callbacks from other systems. The last path segment names the source, whose
+/// secret signs the request; see `verify_webhook_signature`. Providers can only sign, so
+/// the signature is their sole authentication and these routes sit outside `authorize`.
+fn webhook_routes(verifier: Arc<WebhookVerifier>) -> Router {
+    Router::new()
+        .route("/payouts", post(payout_webhook))
+        .route("/gift-cards", post(gift_card_webhook))
+        .route("/subscriptions", post(subscription_webhook))
+        .route("/chargebacks", post(chargeback_webhook))
+        .layer(middleware::from_fn_with_state(verifier, verify_webhook_signature))
+}
+
+/// How far a webhook's timestamp may be from our clock, and how long its nonce is remembered
+const WEBHOOK_TOLERANCE_SECS: i64 = 300;
+/// Largest webhook body read for verification
+const MAX_WEBHOOK_BODY_BYTES: usize = 256 * 1024;
+
+/// Remembers nonces seen within the tolerance window. Postgres when `DATABASE_URL` is set,
+/// so a replay is caught whichever replica receives it; process memory otherwise.
+enum NonceStore {
+    Postgres(PgPool),
+    Memory(Mutex<HashMap<(String, String), i64>>),
+}
+
+impl NonceStore {
+    /// Record a nonce; false if it was already seen
+    async fn insert(&self, source: &str, nonce: &str, now: i64) -> Result<bool, sqlx::Error> {
+        match self {
+            NonceStore::Postgres(pool) => {
+                sqlx::query("DELETE FROM webhook_nonces WHERE expires_at < NOW()")
+                    .execute(pool)
+                    .await?;
+                let inserted = sqlx::query(
+                    r#"
+                    INSERT INTO webhook_nonces (source, nonce, expires_at)
+                    VALUES ($1, $2, NOW() + make_interval(secs => $3))
+                    ON CONFLICT (source, nonce) DO NOTHING
+                    "#,
+                )
+                .bind(source)
+                .bind(nonce)
+                .bind(2.0 * WEBHOOK_TOLERANCE_SECS as f64)
+                .execute(pool)
+                .await?;
+                Ok(inserted.rows_affected() == 1)
+            }
+            NonceStore::Memory(seen) => {
+                let mut seen = seen.lock().unwrap_or_else(|e| e.into_inner());
+                seen.retain(|_, expires_at| *expires_at >= now);
+                Ok(seen
+                    .insert((source.to_string(), nonce.to_string()), now + 2 * WEBHOOK_TOLERANCE_SECS)
+                    .is_none())
+            }
+        }
+    }
+}
+
+/// Per-source webhook secrets, read from `WEBHOOK_SECRET_<SOURCE>` (e.g. `WEBHOOK_SECRET_GIFT_CARDS`)
+struct WebhookVerifier {
+    secrets: HashMap<String, Vec<u8>>,
+    nonces: NonceStore,
+}
+
+impl WebhookVerifier {
+    fn from_env() -> Self {
+        let secrets = std::env::vars()
+            .filter_map(|(key, value)| {
+                let source = key.strip_prefix("WEBHOOK_SECRET_")?;
+                Some((source.to_lowercase().replace('_', "-"), value.into_bytes()))
+            })
+            .collect();
+        let nonces = match std::env::var("DATABASE_URL") {
+            Ok(url) => match PgPoolOptions::new().max_connections(2).connect_lazy(&url) {
+                Ok(pool) => NonceStore::Postgres(pool),
+                Err(e) => {
+                    eprintln!("❌ Webhook nonce store unavailable, keeping nonces in memory: {}", e);
+                    NonceStore::Memory(Mutex::new(HashMap::new()))
+                }
+            },
+            Err(_) => NonceStore::Memory(Mutex::new(HashMap::new())),
+        };
+        Self { secrets, nonces }
+    }
+}
+
+/// Check `x-webhook-signature: sha256=<hex>`, an HMAC-SHA256 over
+/// `<x-webhook-timestamp>.<x-webhook-nonce>.<body>` with the source's secret. The timestamp
+/// (unix seconds) must be within tolerance and the nonce unseen, so captured requests
+/// can't be replayed.
+async fn verify_webhook_signature(
+    State(verifier): State<Arc<WebhookVerifier>>,
+    req: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    let source = req.uri().path().trim_matches('/').rsplit('/').next().unwrap_or_default().to_string();
+    let secret = verifier.secrets.get(&source).ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+
+    let headers = req.headers();
+    let header_value = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).map(str::to_string);
+    let timestamp = header_value("x-webhook-timestamp");
+    let nonce = header_value("x-webhook-nonce").filter(|n| !n.is_empty());
+    let signature = header_value("x-webhook-signature")
+        .and_then(|s| s.strip_prefix("sha256=").and_then(|hex_sig| hex::decode(hex_sig).ok()));
+    let (Some(timestamp), Some(nonce), Some(signature)) = (timestamp, nonce, signature) else {
+        return Err(StatusCode::UNAUTHORIZED);
+    };
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default();
+    let sent_at = timestamp.parse::<i64>().map_err(|_| StatusCode::UNAUTHORIZED)?;
+    if (now - sent_at).abs() > WEBHOOK_TOLERANCE_SECS {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    let (parts, body) = req.into_parts();
+    let body = axum::body::to_bytes(body, MAX_WEBHOOK_BODY_BYTES)
+        .await
+        .map_err(|_| StatusCode::PAYLOAD_TOO_LARGE)?;
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(nonce.as_bytes());
+    mac.update(b".");
+    mac.update(&body);
+    mac.verify_slice(&signature).map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+    // Only a correctly signed request uses up its nonce
+    match verifier.nonces.insert(&source, &nonce, now).await {
+        Ok(true) => {}
+        Ok(false) => return Err(StatusCode::CONFLICT),
+        Err(e) => {
+            eprintln!("❌ [{}] Webhook nonce check failed: {}", current_request_id().unwrap_or_default(), e);
+            return Err(StatusCode::SERVICE_UNAVAILABLE);
+        }
+    }
+
+    Ok(next.run(Request::from_parts(parts, Body::from(body))).await)
+}
+
 async fn health() -> Json<Value> {
     Json(json!({"status": "healthy", "service": "lootpacks-service", "features": ["lootpacks", "rewards", "gamification"]}))
 }
 
-async fn get_lootpacks() -> Json<Value> {
-    Json(json!({
+/// Upper bound on each dependency check, so one hung dependency can't hang the probe
+const DEPENDENCY_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+/// Database round trips slower than this report Postgres as degraded
+const SLOW_DATABASE_MS: u128 = 250;
+
+static HEALTH_POOL: OnceLock<Option<PgPool>> = OnceLock::new();
+
+/// Readiness with a per-dependency breakdown. Only Postgres is critical: without it, or
+/// while migrations run, the instance reports 503. Redis, the event broker and the ads
+/// service degrade features rather than the whole service, so they report `degraded`
+/// with a 200 and orchestration keeps the instance in rotation.
+async fn ready() -> (StatusCode, Json<Value>) {
+    let (postgres, redis, event_broker, ads_service) = tokio::join!(
+        check_postgres(),
+        check_redis(),
+        check_tcp_dependency("EVENT_BROKER_URL"),
+        check_tcp_dependency("ADS_SERVICE_URL"),
+    );
+
+    let migrating = !READY.load(Ordering::SeqCst);
+    let others_healthy = [&redis, &event_broker, &ads_service]
+        .iter()
+        .all(|check| matches!(check["status"].as_str(), Some("up" | "not_configured")));
+    let (code, status) = match postgres["status"].as_str() {
+        _ if migrating => (StatusCode::SERVICE_UNAVAILABLE, "migrating"),
+        Some("down") => (StatusCode::SERVICE_UNAVAILABLE, "unavailable"),
+        Some("up" | "not_configured") if others_healthy => (StatusCode::OK, "ready"),
+        _ => (StatusCode::OK, "degraded"),
+    };
+
+    (code, Json(json!({
+        "status": status,
+        "dependencies": {
+            "postgres": postgres,
+            "redis": redis,
+            "event_broker": event_broker,
+            "ads_service": ads_service,
+        },
+        "service": "lootpacks-service"
+    })))
+}
+
+fn dependency_status(status: &str, started: Instant, error: Option<String>) -> Value {
+    json!({"status": status, "latency_ms": started.elapsed().as_millis() as u64, "error": error})
+}
+
+async fn check_postgres() -> Value {
+    let pool = HEALTH_POOL.get_or_init(|| {
+        let url = std::env::var("DATABASE_URL").ok()?;
+        PgPoolOptions::new()
+            .max_connections(1)
+            .acquire_timeout(DEPENDENCY_CHECK_TIMEOUT)
+            .connect_lazy(&url)
+            .ok()
+    });
+    let Some(pool) = pool else {
+        return json!({"status": "not_configured"});
+    };
+
+    let started = Instant::now();
+    match tokio::time::timeout(DEPENDENCY_CHECK_TIMEOUT, sqlx::query("SELECT 1").execute(pool)).await {
+        Ok(Ok(_)) if started.elapsed().as_millis() > SLOW_DATABASE_MS => dependency_status("degraded", started, None),
+        Ok(Ok(_)) => dependency_status("up", started, None),
+        Ok(Err(e)) => dependency_status("down", started, Some(e.to_string())),
+        Err(_) => dependency_status("down", started, Some("timed out".to_string())),
+    }
+}
+
+/// Host and port from a dependency URL in the environment, `None` when it isn't set
+fn dependency_address(env_var: &str, default_port: u16) -> Option<Result<(String, u16), String>> {
+    let raw = std::env::var(env_var).ok()?;
+    Some(url::Url::parse(&raw).map_err(|e| e.to_string()).and_then(|url| {
+        let host = url.host_str().ok_or_else(|| "URL has no host".to_string())?.to_string();
+        Ok((host, url.port_or_known_default().unwrap_or(default_port)))
+    }))
+}
+
+/// PING Redis over its plain-text protocol; any reply means the server is answering
+async fn check_redis() -> Value {
+    let address = match dependency_address("REDIS_URL", 6379) {
+        None => return json!({"status": "not_configured"}),
+        Some(Err(e)) => return json!({"status": "down", "error": e}),
+        Some(Ok(address)) => address,
+    };
+
+    let started = Instant::now();
+    let ping = async {
+        let mut stream = tokio::net::TcpStream::connect(&address).await?;
+        stream.write_all(b"PING\r\n").await?;
+        let mut reply = [0u8; 64];
+        let read = stream.read(&mut reply).await?;
+        Ok::<_, std::io::Error>(reply[..read].first().copied())
+    };
+    match tokio::time::timeout(DEPENDENCY_CHECK_TIMEOUT, ping).await {
+        Ok(Ok(Some(b'+' | b'-'))) => dependency_status("up", started, None),
+        Ok(Ok(_)) => dependency_status("down", started, Some("unexpected reply to PING".to_string())),
+        Ok(Err(e)) => dependency_status("down", started, Some(e.to_string())),
+        Err(_) => dependency_status("down", started, Some("timed out".to_string())),
+    }
+}
+
+/// Reachability of a dependency this binary has no protocol client for
+async fn check_tcp_dependency(env_var: &str) -> Value {
+    let address = match dependency_address(env_var, 80) {
+        None => return json!({"status": "not_configured"}),
+        Some(Err(e)) => return json!({"status": "down", "error": e}),
+        Some(Ok(address)) => address,
+    };
+
+    let started = Instant::now();
+    match tokio::time::timeout(DEPENDENCY_CHECK_TIMEOUT, tokio::net::TcpStream::connect(&address)).await {
+        Ok(Ok(_)) => dependency_status("up", started, None),
+        Ok(Err(e)) => dependency_status("down", started, Some(e.to_string())),
+        Err(_) => dependency_status("down", started, Some("timed out".to_string())),
+    }
+}
+
+/// Apply embedded migrations while holding a Postgres advisory lock.
+async fn run_migrations() -> Result<(), sqlx::Error> {
+    let url = std::env::var("DATABASE_URL")
+        .map_err(|_| sqlx::Error::Configuration("DATABASE_URL is not set".into()))?;
+    let pool = PgPoolOptions::new().max_connections(1).connect(&url).await?;
+    let mut conn = pool.acquire().await?;
+
+    sqlx::query("SELECT pg_advisory_lock($1)").bind(MIGRATION_LOCK_KEY).execute(&mut *conn).await?;
+    let result = sqlx::migrate!("./migrations").run_direct(&mut *conn).await;
+    sqlx::query("SELECT pg_advisory_unlock($1)").bind(MIGRATION_LOCK_KEY).execute(&mut *conn).await?;
+    result?;
+
+    println!("✅ Migrations applied");
+    Ok(())
+}
+
+async fn get_lootpacks(headers: HeaderMap) -> Response {
+    let locale = headers
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(',').next())
+        .map(|tag| tag.split(';').next().unwrap_or(tag).trim().to_lowercase())
+        .unwrap_or_else(|| "en".to_string());
+    let market = headers
+        .get("x-market")
+        .and_then(|v| v.to_str().ok())
+        .map(|m| m.trim().to_uppercase())
+        .filter(|m| !m.is_empty())
+        .unwrap_or_else(|| "IN".to_string());
+    let body = json!({
+        "locale": locale,
+        "market": market,
         "lootpacks": [
-            {"id": "loot_1", "name": "Daily Pack", "cost": 100, "rewards": 5},
-            {"id": "loot_2", "name": "Premium Pack", "cost": 500, "rewards": 25}
+            {"id": "loot_1", "name": "Daily Pack", "cost": 100, "rewards": 5,
+             "pricing": {"original_price": 100, "price": 100, "discount_percent": null},
+             "happy_hour": null, "next_available_at": "2024-01-02T08:00:00Z", "rotation": null,
+             "icon_url": "https://cdn.dealmate.app/icons/daily.png", "purchase_mode": "allow", "odds_disclosure": null,
+             "first_purchase_bonus": true},
+            {"id": "loot_2", "name": "Premium Pack", "cost": 500, "rewards": 25,
+             "pricing": {"original_price": 500, "price": 400, "discount_percent": 20, "discount_label": "Weekend sale"},
+             "happy_hour": {"label": "Epic Hour", "rarity_multipliers": {"epic": 2.0}, "ends_at": "2024-01-01T15:30:00Z"},
+             "next_available_at": null,
+             "rotation": {"label": "Electronics week", "position": 1, "pool_count": 4, "next_change_at": "2024-01-08T00:00:00Z"},
+             "icon_url": null, "purchase_mode": "allow",
+             "odds_disclosure": {"text": "Odds are shown per rarity", "odds_by_rarity": {"common": 0.7, "rare": 0.25, "epic": 0.05}, "acknowledged": false},
+             "first_purchase_bonus": true}
         ],
         "service": "lootpacks-service"
-    }))
+    });
+    conditional_pack_list(&headers, body)
+}
+
+/// Serve the pack list with an ETag hashed from its content; a matching `If-None-Match`
+/// gets a bodyless 304. There is no Last-Modified: the list is built from many tables and
+/// the caller's own state, so no single timestamp dates it.
+fn conditional_pack_list(headers: &HeaderMap, body: Value) -> Response {
+    let bytes = serde_json::to_vec(&body).unwrap_or_default();
+    // Weak, since compression changes the bytes on the wire but not the content
+    let etag = format!("W/\"{}\"", &hex::encode(Sha256::digest(&bytes))[..32]);
+
+    let not_modified = headers.get(header::IF_NONE_MATCH).is_some_and(|value| {
+        value.to_str().is_ok_and(|tags| {
+            tags.trim() == "*" || tags.split(',').any(|tag| tag.trim().trim_start_matches("W/") == &etag[2..])
+        })
+    });
+
+    let validators = [
+        (header::ETAG, etag),
+        (header::VARY, "accept-language, x-market".to_string()),
+    ];
+    if not_modified {
+        return (StatusCode::NOT_MODIFIED, validators).into_response();
+    }
+    (validators, Json(body)).into_response()
+}
+
+#[derive(Deserialize)]
+struct GraphQLRequest {
+    query: String,
+}
+
+/// Top-level fields of the first operation in a GraphQL document as `(response key, field)`,
+/// along with whether it is a mutation. Enough of the grammar for the stub to tell which
+/// root fields were asked for; nested selections and arguments are skipped.
+fn graphql_root_fields(query: &str) -> Result<(bool, Vec<(String, String)>), String> {
+    let source: String = query.lines().map(|line| line.split('#').next().unwrap_or("")).collect::<Vec<_>>().join("\n");
+    let open = source.find('{').ok_or("Syntax error: expected a selection set")?;
+    let is_mutation = match source[..open].split_whitespace().next() {
+        None | Some("query") => false,
+        Some("mutation") => true,
+        Some("subscription") => return Err("Subscriptions are not supported".to_string()),
+        Some(other) => return Err(format!("Syntax error: unexpected '{}'", other)),
+    };
+
+    let mut fields = Vec::new();
+    let (mut depth, mut parens, mut in_string) = (0, 0, false);
+    let mut chars = source[open..].char_indices().peekable();
+    let mut alias: Option<String> = None;
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => in_string = !in_string,
+            _ if in_string => {}
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            '(' => parens += 1,
+            ')' => parens -= 1,
+            '.' if depth == 1 && parens == 0 => return Err("Fragments are not supported".to_string()),
+            c if depth == 1 && parens == 0 && (c.is_ascii_alphabetic() || c == '_') => {
+                let rest = &source[open + i..];
+                let len = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(rest.len());
+                let name = rest[..len].to_string();
+                for _ in 1..len {
+                    chars.next();
+                }
+                if rest[len..].trim_start().starts_with(':') {
+                    alias = Some(name);
+                } else {
+                    fields.push((alias.take().unwrap_or_else(|| name.clone()), name));
+                }
+            }
+            _ => {}
+        }
+    }
+    if depth != 0 || fields.is_empty() {
+        return Err("Syntax error: unterminated or empty selection set".to_string());
+    }
+    Ok((is_mutation, fields))
+}
+
+async fn graphql(Json(req): Json<GraphQLRequest>) -> (StatusCode, Json<Value>) {
+    let graphql_error = |message: String| {
+        (StatusCode::BAD_REQUEST, Json(json!({"errors": [{"message": message, "extensions": {"code": "BAD_REQUEST"}}]})))
+    };
+    if req.query.trim().is_empty() {
+        return graphql_error("Empty query".to_string());
+    }
+    let (is_mutation, fields) = match graphql_root_fields(&req.query) {
+        Ok(parsed) => parsed,
+        Err(message) => return graphql_error(message),
+    };
+
+    let root = if is_mutation { "MutationRoot" } else { "QueryRoot" };
+    let mut data = serde_json::Map::new();
+    for (key, field) in fields {
+        let value = match (is_mutation, field.as_str()) {
+            (_, "__typename") => json!(root),
+            (false, "stats") => json!({"deal_coins": 500, "daily_streak": 1, "level": 1, "can_claim_daily": true, "unopened_packs": 1}),
+            (false, "packs") => json!([{"id": "loot_1", "name": "Daily Pack", "cost": 100, "rewards": 5}]),
+            (false, "inventory") => json!({"rewards": [{"id": "reward_1", "type": "coupon", "value": "SAVE10"}], "stats": {"active_count": 1}}),
+            (false, "expiringRewards") => json!([{"id": "reward_1", "title": "10% off", "expires_at": "2024-01-03T00:00:00Z"}]),
+            (true, "openPack") => json!({"rewards": [{"type": "coupon", "value": "SAVE20", "rarity": "common"}], "pack_history_id": "history_1"}),
+            (true, "redeemRewards") => json!({"redeemed": [], "coins_credited": 0}),
+            _ => return graphql_error(format!("Unknown field \"{}\" on type \"{}\"", field, root)),
+        };
+        data.insert(key, value);
+    }
+    (StatusCode::OK, Json(json!({"data": data})))
 }
 
 async fn create_lootpack() -> Json<Value> {
     Json(json!({"message": "Lootpack created", "id": "loot_123", "service": "lootpacks-service"}))
 }
 
+async fn get_lootpack(Path(id): Path<String>) -> Json<Value> {
+    Json(json!({
+        "pack": {"id": id, "name": "Daily Pack", "cost": 100, "rewards": 5},
+        "rewards_by_rarity": {
+            "common": [{"type": "coupon", "title": "10% off", "value": "10%", "drop_chance": 0.7}],
+            "rare": [{"type": "points", "title": "Bonus DealCoins", "value": "+50", "drop_chance": 0.3}]
+        },
+        "service": "lootpacks-service"
+    }))
+}
+
 async fn open_lootpack() -> Json<Value> {
     Json(json!({
         "rewards": [
             {"type": "coupon", "value": "SAVE20", "rarity": "common"},
             {"type": "points", "value": 50, "rarity": "rare"}
         ],
+        "pack_history_id": "history_1",
+        "reroll": {"token": "reroll_1", "fee_coins": 50, "expires_at": "2024-01-01T12:05:00Z"},
+        "fairness": null,
+        "completed_sets": [],
+        "mystery": null,
+        "reveal": {
+            "order": "best_last",
+            "steps": [
+                {"reward_index": 0, "animation_tier": "standard"},
+                {"reward_index": 1, "animation_tier": "glow"}
+            ]
+        },
+        "first_purchase_bonus": "user_pack_3",
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn buy_lootpack(Path(id): Path<String>) -> Json<Value> {
+    Json(json!({
+        "user_pack_id": "user_pack_2",
+        "pack_type_id": id,
+        "price_paid": 100,
+        "new_balance": 400,
+        "first_purchase_bonus": null,
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn get_social_proof(Path(id): Path<String>) -> Json<Value> {
+    Json(json!({
+        "pack_type_id": id,
+        "opened_today": 1284,
+        "last_legendary_at": "2024-01-01T11:48:00Z",
+        "minutes_since_last_legendary": 12,
+        "top_reward_today": {
+            "template_id": "template_1",
+            "title": "Flat 50% off flights",
+            "rarity": "legendary",
+            "times_pulled": 3
+        },
+        "computed_at": "2024-01-01T12:00:00Z",
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn preview_lootpack(Path(id): Path<String>) -> Json<Value> {
+    Json(json!({
+        "pack_type_id": id,
+        "rewards": [
+            {"type": "coupon", "title": "20% off electronics", "value": "20%", "rarity": "rare"},
+            {"type": "points", "title": "Bonus DealCoins", "value": "+50", "rarity": "common"}
+        ],
+        "sampled_at": "2024-01-01T12:00:00Z",
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn claim_daily_pack() -> Json<Value> {
+    Json(json!({
+        "rewards": [
+            {"type": "coupon", "value": "SAVE10", "rarity": "common"}
+        ],
+        "updated_stats": {"daily_streak": 2, "can_claim_daily": false},
         "service": "lootpacks-service"
     }))
 }
 
-async fn get_rewards() -> Json<Value> {
+#[derive(Deserialize)]
+struct SimulateQuery {
+    count: Option<u32>,
+}
+
+/// Upper bound on simulated openings per request, as in `LootpackService::simulate_pack`
+const MAX_SIMULATION_RUNS: u32 = 10_000;
+
+async fn simulate_lootpack(Path(id): Path<String>, Query(query): Query<SimulateQuery>) -> (StatusCode, Json<Value>) {
+    let runs = query.count.unwrap_or(100);
+    if runs == 0 || runs > MAX_SIMULATION_RUNS {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": format!("count must be between 1 and {}", MAX_SIMULATION_RUNS), "service": "lootpacks-service"})),
+        );
+    }
+    // Three rewards per pack, split roughly along the default pool's weights
+    let total = runs * 3;
+    let legendary = total / 100;
+    let epic = total * 6 / 100;
+    let rare = total * 23 / 100;
+    (StatusCode::OK, Json(json!({
+        "pack_type_id": id,
+        "runs": runs,
+        "total_rewards": total,
+        "rarity_distribution": {"common": total - rare - epic - legendary, "rare": rare, "epic": epic, "legendary": legendary},
+        "type_distribution": {"coupon": total * 7 / 10, "points": total - total * 7 / 10},
+        "service": "lootpacks-service"
+    })))
+}
+
+#[derive(Deserialize, Serialize)]
+struct InventoryQuery {
+    r#type: Option<String>,
+    rarity: Option<String>,
+    status: Option<String>,
+    expiring_within_days: Option<i32>,
+    merchant_id: Option<String>,
+    brand: Option<String>,
+    category: Option<String>,
+    sort: Option<String>,
+    order: Option<String>,
+}
+
+async fn get_rewards(Query(filters): Query<InventoryQuery>) -> Json<Value> {
+    Json(json!({
+        "filters": filters,
+        "rewards": [
+            {"id": "reward_1", "type": "coupon", "value": "SAVE10", "merchant_id": "amazon", "brand": "Amazon", "category": "electronics", "value_amount": "150.00", "value_currency": "INR"},
+            {"id": "reward_2", "type": "points", "value": 100, "merchant_id": null, "brand": null, "category": null, "value_amount": "10.00", "value_currency": "INR"}
+        ],
+        "stats": {"active_count": 2, "used_count": 0, "expiring_soon_count": 0, "total_value_estimate": "160.00"},
+        "display_currency": "INR",
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn get_reward(Path(id): Path<String>) -> Json<Value> {
+    Json(json!({
+        "reward": {"id": id, "type": "coupon", "value": "SAVE10", "rarity": "common"},
+        "source_pack": {"name": "Daily Pack"},
+        "status": "active",
+        "redemption_instructions": ["Enter code SAVE10 at checkout"],
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn get_user_stats() -> Json<Value> {
+    Json(json!({
+        "deal_coins": 500,
+        "daily_streak": 1,
+        "total_packs_opened": 0,
+        "level": 1,
+        "level_progress": 0,
+        "member_status": "Bronze",
+        "can_claim_daily": true,
+        "next_daily_claim": null,
+        "display_currency": "INR",
+        "total_savings": {"amount": "0.00", "currency": "INR"},
+        "unopened_packs": 1,
+        "streak_deadline": "2024-01-03T00:30:00Z",
+        "prestige": {
+            "prestige": 0,
+            "badge": null,
+            "pack_frame": null,
+            "coin_bonus_pct": 0,
+            "can_prestige": false,
+            "max_level": 50,
+            "last_prestiged_at": null
+        },
+        "vip": null,
+        "free_claims": {
+            "member_status": "Bronze",
+            "monthly_limit": 30,
+            "claimed": 1,
+            "remaining": 29,
+            "resets_at": "2024-02-01T00:00:00Z"
+        },
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn get_unopened_packs() -> Json<Value> {
+    Json(json!({
+        "packs": [{"id": "user_pack_1", "pack_type_id": "loot_2", "pack_name": "Premium Pack", "source": "promo_code"}],
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn open_unopened_pack(Path(id): Path<String>) -> Json<Value> {
     Json(json!({
+        "user_pack_id": id,
         "rewards": [
-            {"id": "reward_1", "type": "coupon", "value": "SAVE10"},
-            {"id": "reward_2", "type": "points", "value": 100}
+            {"type": "coupon", "value": "SAVE20", "rarity": "common"},
+            {"type": "points", "value": 50, "rarity": "rare"}
         ],
         "service": "lootpacks-service"
     }))
 }
+
+async fn auto_open_packs() -> Json<Value> {
+    Json(json!({
+        "packs_opened": 3,
+        "rewards": 4,
+        "rewards_by_rarity": {"common": 3, "rare": 1},
+        "coins_credited": 50,
+        "best_reward": {"title": "20% off electronics", "rarity": "rare", "pack_history_id": "history_2"},
+        "remaining": 0,
+        "completed_sets": [],
+        "updated_stats": {"deal_coins": 550, "total_packs_opened": 3, "level": 1, "level_progress": 30},
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn get_streak_repair() -> Json<Value> {
+    Json(json!({
+        "offer": {
+            "break_id": "break_1",
+            "lost_streak": 12,
+            "price_coins": 170,
+            "broken_at": "2024-01-01T12:00:00Z",
+            "repair_deadline": "2024-01-03T12:00:00Z"
+        },
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn repair_streak() -> Json<Value> {
+    Json(json!({"daily_streak": 12, "price_paid": 170, "new_balance": 330, "service": "lootpacks-service"}))
+}
+
+async fn get_vip_status() -> Json<Value> {
+    Json(json!({
+        "vip": {
+            "plan_id": "vip_monthly",
+            "status": "active",
+            "current_period_end": "2024-02-01T00:00:00Z",
+            "packs_per_period": 3,
+            "coin_stipend": 500,
+            "granted_at": "2024-01-01T00:05:00Z"
+        },
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn get_prestige() -> Json<Value> {
+    Json(json!({
+        "prestige": 0,
+        "badge": null,
+        "pack_frame": null,
+        "coin_bonus_pct": 0,
+        "can_prestige": false,
+        "max_level": 50,
+        "last_prestiged_at": null,
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn prestige() -> (StatusCode, Json<Value>) {
+    // A new user is level 1; the service checks the real level against the cap
+    (StatusCode::BAD_REQUEST, Json(json!({"error": "Reach level 50 to prestige", "service": "lootpacks-service"})))
+}
+
+async fn get_vacation() -> Json<Value> {
+    Json(json!({
+        "active": null,
+        "remaining_vacations": 2,
+        "allowance_resets_at": null,
+        "max_days": 14,
+        "service": "lootpacks-service"
+    }))
+}
+
+#[derive(Deserialize)]
+struct StartVacationRequest {
+    days: i32,
+}
+
+async fn start_vacation(Json(req): Json<StartVacationRequest>) -> (StatusCode, Json<Value>) {
+    if !(1..=14).contains(&req.days) {
+        return (StatusCode::BAD_REQUEST, Json(json!({"error": "Vacations last 1 to 14 days", "service": "lootpacks-service"})));
+    }
+    (StatusCode::OK, Json(json!({
+        "active": {"id": "vacation_1", "starts_at": "2024-01-01T12:00:00Z", "ends_at": "2024-01-08T12:00:00Z"},
+        "remaining_vacations": 1,
+        "allowance_resets_at": "2025-01-01T12:00:00Z",
+        "max_days": 14,
+        "service": "lootpacks-service"
+    })))
+}
+
+async fn end_vacation() -> Json<Value> {
+    Json(json!({
+        "active": null,
+        "remaining_vacations": 1,
+        "allowance_resets_at": "2025-01-01T12:00:00Z",
+        "max_days": 14,
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn get_timezone() -> Json<Value> {
+    Json(json!({
+        "timezone": "Asia/Kolkata",
+        "next_daily_claim": null,
+        "streak_until": "2024-01-02T18:30:00Z",
+        "service": "lootpacks-service"
+    }))
+}
+
+#[derive(Deserialize)]
+struct SetTimezoneRequest {
+    timezone: String,
+}
+
+async fn set_timezone(Json(req): Json<SetTimezoneRequest>) -> (StatusCode, Json<Value>) {
+    let timezone = req.timezone.trim();
+    // Rough IANA shape check; the service validates against the database's zone list
+    let known = timezone == "UTC" || (timezone.contains('/') && !timezone.contains(' '));
+    if !known {
+        return (StatusCode::BAD_REQUEST, Json(json!({"error": format!("Unknown timezone '{}'", timezone), "service": "lootpacks-service"})));
+    }
+    (StatusCode::OK, Json(json!({
+        "timezone": timezone,
+        "next_daily_claim": null,
+        "streak_until": "2024-01-02T18:30:00Z",
+        "service": "lootpacks-service"
+    })))
+}
+
+async fn get_open_preferences() -> Json<Value> {
+    Json(json!({"auto_open_low_tier": false, "service": "lootpacks-service"}))
+}
+
+#[derive(Deserialize)]
+struct OpenPreferencesRequest {
+    auto_open_low_tier: bool,
+}
+
+async fn update_open_preferences(Json(req): Json<OpenPreferencesRequest>) -> Json<Value> {
+    Json(json!({"auto_open_low_tier": req.auto_open_low_tier, "service": "lootpacks-service"}))
+}
+
+#[derive(Deserialize)]
+struct RedeemPromoRequest {
+    code: String,
+}
+
+async fn redeem_promo(Json(req): Json<RedeemPromoRequest>) -> Json<Value> {
+    Json(json!({
+        "code": req.code.trim().to_uppercase(),
+        "grant_type": "coins",
+        "coins_granted": 250,
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn get_inbox() -> Json<Value> {
+    Json(json!({
+        "messages": [
+            {"id": "msg_1", "kind": "announcement", "title": "Sorry for the downtime",
+             "body": "Here's a pack on us.", "claim_pack_type_id": "loot_2", "claimed_at": null, "read_at": null},
+            {"id": "msg_2", "kind": "milestone", "title": "Level 5 reached!",
+             "body": "You earned 100 bonus DealCoins for levelling up.", "claim_pack_type_id": null, "read_at": "2024-01-01T10:00:00Z"}
+        ],
+        "unread_count": 1,
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn mark_inbox_message_read(Path(id): Path<String>) -> Json<Value> {
+    Json(json!({"message": "Message marked as read", "id": id, "service": "lootpacks-service"}))
+}
+
+async fn delete_inbox_message(Path(id): Path<String>) -> Json<Value> {
+    Json(json!({"message": "Message deleted", "id": id, "service": "lootpacks-service"}))
+}
+
+async fn claim_inbox_message(Path(id): Path<String>) -> Json<Value> {
+    Json(json!({"message_id": id, "user_pack_id": "user_pack_2", "service": "lootpacks-service"}))
+}
+
+async fn create_announcement(Json(req): Json<Value>) -> Json<Value> {
+    let recipients = req.get("user_ids").and_then(Value::as_array).map(|ids| ids.len()).unwrap_or(0);
+    Json(json!({"message": "Announcement sent", "recipients": recipients, "service": "lootpacks-service"}))
+}
+
+#[derive(Deserialize)]
+struct UpdateDisplayCurrencyRequest {
+    currency: String,
+}
+
+async fn update_display_currency(Json(req): Json<UpdateDisplayCurrencyRequest>) -> Json<Value> {
+    Json(json!({"display_currency": req.currency.to_uppercase(), "service": "lootpacks-service"}))
+}
+
+async fn upsert_currency_rate(Path(currency): Path<String>, Json(req): Json<Value>) -> Json<Value> {
+    Json(json!({
+        "message": "Rate updated",
+        "currency": currency.to_uppercase(),
+        "per_inr": req.get("per_inr"),
+        "service": "lootpacks-service"
+    }))
+}
+
+#[derive(Deserialize, Serialize)]
+struct AnalyticsQuery {
+    from: Option<String>,
+    to: Option<String>,
+    pack_type_id: Option<String>,
+}
+
+async fn analytics_pack_opens(Query(query): Query<AnalyticsQuery>) -> Json<Value> {
+    Json(json!({
+        "query": query,
+        "rows": [{"day": "2024-01-01", "pack_type_id": "loot_1", "pack_name": "Daily Pack", "opens": 1200, "unique_users": 1100}],
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn analytics_rarity_distribution(Query(query): Query<AnalyticsQuery>) -> Json<Value> {
+    Json(json!({
+        "query": query,
+        "rows": [
+            {"pack_type_id": "loot_1", "rarity": "common", "dealt": 4200, "share": 0.7},
+            {"pack_type_id": "loot_1", "rarity": "rare", "dealt": 1800, "share": 0.3}
+        ],
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn analytics_redemption_rates(Query(query): Query<AnalyticsQuery>) -> Json<Value> {
+    Json(json!({
+        "query": query,
+        "rows": [{"reward_type": "coupon", "dealt": 3000, "redeemed": 900, "expired": 400, "redemption_rate": 0.3}],
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn analytics_coin_flows(Query(query): Query<AnalyticsQuery>) -> Json<Value> {
+    Json(json!({
+        "query": query,
+        "flows": [
+            {"reason": "pack_purchase", "sourced": 0, "sunk": 50000},
+            {"reason": "pack_reward", "sourced": 12000, "sunk": 0}
+        ],
+        "total_sourced": 12000,
+        "total_sunk": 50000,
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn refresh_analytics(Query(query): Query<AnalyticsQuery>) -> Json<Value> {
+    Json(json!({"message": "Aggregate refresh started", "query": query, "service": "lootpacks-service"}))
+}
+
+#[derive(Deserialize, Serialize)]
+struct EconomyQuery {
+    from: Option<String>,
+    to: Option<String>,
+    granularity: Option<String>,
+}
+
+async fn get_economy_report(Query(query): Query<EconomyQuery>) -> Json<Value> {
+    Json(json!({
+        "granularity": query.granularity.unwrap_or_else(|| "day".to_string()),
+        "periods": [{
+            "period_start": "2024-01-01",
+            "minted": 62000, "sunk": 50000, "net": 12000,
+            "minted_by_reason": {"pack_reward": 12000, "starting_balance": 50000},
+            "sunk_by_reason": {"pack_purchase": 50000},
+            "cumulative_minted": 62000, "cumulative_sunk": 50000, "cumulative_net": 12000
+        }],
+        "circulating_supply": 1250000,
+        "service": "lootpacks-service"
+    }))
+}
+
+#[derive(Deserialize)]
+struct EconomySimulationRequest {
+    users: u32,
+    opens_per_user: u32,
+}
+
+async fn simulate_economy(Json(req): Json<EconomySimulationRequest>) -> Json<Value> {
+    let opens = req.users as u64 * req.opens_per_user as u64;
+    Json(json!({
+        "users": req.users,
+        "attempted_opens": opens,
+        "completed_opens": opens,
+        "coins_minted": 0,
+        "coins_sunk": 0,
+        "packs": {},
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn list_odds_audits() -> Json<Value> {
+    Json(json!({
+        "results": [{
+            "pack_type_id": "loot_1", "opens": 1200, "samples": 6000,
+            "chi_squared": 12.4, "degrees_of_freedom": 9, "critical_value": 27.9, "drifted": false,
+            "top_deviations": []
+        }],
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn get_template_history(Path(id): Path<String>) -> Json<Value> {
+    Json(json!({
+        "template_id": id,
+        "versions": [
+            {"version": 2, "snapshot": {"title": "15% off", "value": "15%"}, "changed_by": "admin:ops", "changed_at": "2024-02-01T00:00:00Z"},
+            {"version": 1, "snapshot": {"title": "10% off", "value": "10%"}, "changed_by": "migration", "changed_at": "2024-01-01T00:00:00Z"}
+        ],
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn get_template_version(Path((id, version)): Path<(String, i32)>) -> Json<Value> {
+    Json(json!({
+        "template_id": id,
+        "version": version,
+        "snapshot": {"title": "10% off", "value": "10%"},
+        "service": "lootpacks-service"
+    }))
+}
+
+#[derive(Deserialize, Serialize)]
+struct MappingHistoryQuery {
+    as_of: Option<String>,
+}
+
+async fn get_mapping_history(Path(id): Path<String>, Query(query): Query<MappingHistoryQuery>) -> Json<Value> {
+    Json(json!({
+        "pack_type_id": id,
+        "query": query,
+        "changes": [
+            {"reward_template_id": "tmpl_1", "weight": 60, "version": 2, "operation": "update", "changed_by": "admin:ops"}
+        ],
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn update_pack_config(Path(id): Path<String>, Json(req): Json<Value>) -> (StatusCode, Json<Value>) {
+    if let Some(order) = req.get("reveal_order").and_then(Value::as_str) {
+        if !["dealt", "best_last", "ascending"].contains(&order) {
+            return (StatusCode::BAD_REQUEST, Json(json!({"error": "reveal_order must be one of dealt, best_last, ascending", "service": "lootpacks-service"})));
+        }
+    }
+    (StatusCode::OK, Json(json!({"message": "Pack updated", "pack_type_id": id, "version": 2, "changes": req, "service": "lootpacks-service"})))
+}
+
+async fn list_pack_versions(Path(id): Path<String>) -> Json<Value> {
+    Json(json!({
+        "pack_type_id": id,
+        "versions": [
+            {"version": 2, "created_by": "admin:ops", "note": "Raise price", "snapshot": {"pack": {"price_coins": 299}, "mappings": []}},
+            {"version": 1, "created_by": "system", "note": "Baseline", "snapshot": {"pack": {"price_coins": 249}, "mappings": []}}
+        ],
+        "service": "lootpacks-service"
+    }))
+}
+
+#[derive(Deserialize)]
+struct RollbackRequest {
+    version: i32,
+}
+
+async fn rollback_pack(Path(id): Path<String>, Json(req): Json<RollbackRequest>) -> Json<Value> {
+    Json(json!({
+        "message": format!("Rolled back to version {}", req.version),
+        "pack_type_id": id,
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn set_pack_rotation(Path(id): Path<String>, Json(req): Json<Value>) -> Json<Value> {
+    let pools = req.get("pools").and_then(Value::as_array).map(|p| p.len()).unwrap_or(0);
+    Json(json!({
+        "pack_type_id": id,
+        "schedule": req.get("schedule"),
+        "position": 0,
+        "pool_count": pools,
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn remove_pack_rotation(Path(id): Path<String>) -> Json<Value> {
+    Json(json!({"message": "Rotation removed", "pack_type_id": id, "service": "lootpacks-service"}))
+}
+
+#[derive(Deserialize)]
+struct ConfigTransferQuery {
+    format: Option<String>,
+    dry_run: Option<bool>,
+}
+
+async fn export_config(Query(query): Query<ConfigTransferQuery>) -> Response {
+    if query.format.as_deref() == Some("csv") {
+        let csv = "reward_template_id,type,title,value,description,rarity,code_pattern,validity_days,metadata,is_active,merchant_id,brand,category,value_amount,value_currency,markets,pack_type_id,pack_name,weight\n";
+        return ([(header::CONTENT_TYPE, "text/csv")], csv).into_response();
+    }
+    Json(json!({
+        "pack_types": [],
+        "reward_templates": [],
+        "mappings": [],
+        "service": "lootpacks-service"
+    }))
+    .into_response()
+}
+
+async fn import_config(Query(query): Query<ConfigTransferQuery>, body: String) -> (StatusCode, Json<Value>) {
+    let rows = if query.format.as_deref() == Some("csv") {
+        body.lines().skip(1).filter(|line| !line.trim().is_empty()).count()
+    } else {
+        match serde_json::from_str::<Value>(&body) {
+            Ok(config) => config.get("reward_templates").and_then(Value::as_array).map(|t| t.len()).unwrap_or(0),
+            Err(e) => return (StatusCode::BAD_REQUEST, Json(json!({"error": e.to_string(), "service": "lootpacks-service"}))),
+        }
+    };
+    (StatusCode::OK, Json(json!({
+        "dry_run": query.dry_run.unwrap_or(false),
+        "reward_templates": rows,
+        "packs_updated": [],
+        "service": "lootpacks-service"
+    })))
+}
+
+/// Icons are capped well above display size; matches `assets::MAX_ICON_BYTES`
+const MAX_ICON_BYTES: usize = 512 * 1024;
+
+async fn upload_icon(Path(id): Path<String>, body: axum::body::Bytes) -> (StatusCode, Json<Value>) {
+    let extension = if body.starts_with(b"\x89PNG\r\n\x1a\n") {
+        "png"
+    } else if body.starts_with(&[0xff, 0xd8, 0xff]) {
+        "jpg"
+    } else if body.len() >= 12 && &body[0..4] == b"RIFF" && &body[8..12] == b"WEBP" {
+        "webp"
+    } else {
+        return (StatusCode::BAD_REQUEST, Json(json!({"error": "Icon must be a PNG, JPEG or WebP image", "service": "lootpacks-service"})));
+    };
+    if body.len() > MAX_ICON_BYTES {
+        return (StatusCode::PAYLOAD_TOO_LARGE, Json(json!({"error": "Icon is too large", "service": "lootpacks-service"})));
+    }
+    (StatusCode::OK, Json(json!({
+        "target_id": id,
+        "size_bytes": body.len(),
+        "url": format!("https://cdn.dealmate.app/icons/{}.{}", id, extension),
+        "service": "lootpacks-service"
+    })))
+}
+
+async fn list_markets() -> Json<Value> {
+    Json(json!({
+        "markets": [{"code": "IN", "name": "India", "default_currency": "INR", "is_active": true, "is_default": true, "daily_reset_hour": 5}],
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn upsert_market(Path(code): Path<String>, Json(req): Json<Value>) -> (StatusCode, Json<Value>) {
+    let daily_reset_hour = req.get("daily_reset_hour").and_then(Value::as_i64).unwrap_or(0);
+    if !(0..=23).contains(&daily_reset_hour) {
+        return (StatusCode::BAD_REQUEST, Json(json!({"error": "daily_reset_hour must be between 0 and 23", "service": "lootpacks-service"})));
+    }
+    (StatusCode::OK, Json(json!({
+        "code": code.to_uppercase(),
+        "name": req.get("name"),
+        "default_currency": req.get("default_currency"),
+        "is_active": req.get("is_active").and_then(Value::as_bool).unwrap_or(true),
+        "is_default": req.get("is_default").and_then(Value::as_bool).unwrap_or(false),
+        "daily_reset_hour": daily_reset_hour,
+        "service": "lootpacks-service"
+    })))
+}
+
+async fn set_markets(Path(id): Path<String>, Json(req): Json<Value>) -> Json<Value> {
+    Json(json!({"message": "Markets updated", "id": id, "markets": req.get("markets"), "service": "lootpacks-service"}))
+}
+
+#[derive(Deserialize)]
+struct ChooseRewardRequest {
+    reward_template_id: String,
+}
+
+async fn choose_lootpack_reward(Path(id): Path<String>, Json(req): Json<ChooseRewardRequest>) -> Json<Value> {
+    Json(json!({
+        "pack_type_id": id,
+        "rewards": [{"template_id": req.reward_template_id, "type": "coupon", "title": "Chosen reward", "rarity": "rare"}],
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn open_mystery_lootpack(Path(id): Path<String>) -> Json<Value> {
+    Json(json!({
+        "pack_type_id": id,
+        "rewards": [],
+        "pack_history_id": "history_1",
+        "reroll": null,
+        "fairness": null,
+        "completed_sets": [],
+        "mystery": {"choice_id": "choice_1", "option_count": 3, "expires_at": "2024-01-01T12:10:00Z"},
+        "reveal": {"order": "dealt", "steps": []},
+        "service": "lootpacks-service"
+    }))
+}
+
+#[derive(Deserialize)]
+struct MysteryPickRequest {
+    index: usize,
+    reveal_others: Option<bool>,
+}
+
+async fn pick_mystery_reward(Path(id): Path<String>, Json(req): Json<MysteryPickRequest>) -> (StatusCode, Json<Value>) {
+    if req.index >= 3 {
+        return (StatusCode::BAD_REQUEST, Json(json!({"error": "Pick an option between 0 and 2", "service": "lootpacks-service"})));
+    }
+
+    let others = req.reveal_others.unwrap_or(false).then(|| json!([
+        {"type": "coupon", "title": "10% off", "value": "10%", "rarity": "common"},
+        {"type": "points", "title": "Bonus DealCoins", "value": "+50", "rarity": "rare"}
+    ]));
+    (StatusCode::OK, Json(json!({
+        "choice_id": id,
+        "reward": {"type": "coupon", "value": "SAVE20", "rarity": "rare", "title": "20% off electronics"},
+        "new_balance": null,
+        "others": others,
+        "completed_sets": [],
+        "service": "lootpacks-service"
+    })))
+}
+
+async fn acknowledge_odds(Path(id): Path<String>) -> Json<Value> {
+    Json(json!({"message": "Odds acknowledged", "pack_type_id": id, "service": "lootpacks-service"}))
+}
+
+async fn list_compliance_policies() -> Json<Value> {
+    Json(json!({
+        "policies": [{"region": "*", "paid_random_mode": "allow", "minor_paid_random_mode": "allow",
+                      "adult_age": 18, "require_odds_disclosure": false, "disclosure_text": null}],
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn upsert_compliance_policy(Path(region): Path<String>, Json(req): Json<Value>) -> Json<Value> {
+    Json(json!({
+        "region": region.to_uppercase(),
+        "paid_random_mode": req.get("paid_random_mode"),
+        "minor_paid_random_mode": req.get("minor_paid_random_mode"),
+        "adult_age": req.get("adult_age").and_then(Value::as_i64).unwrap_or(18),
+        "require_odds_disclosure": req.get("require_odds_disclosure").and_then(Value::as_bool).unwrap_or(false),
+        "disclosure_text": req.get("disclosure_text"),
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn get_spending_limits() -> Json<Value> {
+    Json(json!({
+        "limits": [
+            {"period": "daily", "window_hours": 24, "coin_limit": 1000, "open_limit": null,
+             "personal_coin_limit": 1000, "personal_open_limit": null,
+             "coins_spent": 300, "opens": 3, "coins_remaining": 700, "opens_remaining": null, "pending": null},
+            {"period": "weekly", "window_hours": 168, "coin_limit": null, "open_limit": null,
+             "personal_coin_limit": null, "personal_open_limit": null,
+             "coins_spent": 900, "opens": 8, "coins_remaining": null, "opens_remaining": null, "pending": null}
+        ],
+        "service": "lootpacks-service"
+    }))
+}
+
+#[derive(Deserialize)]
+struct UpdateSpendingLimitsRequest {
+    period: String,
+    coin_limit: Option<i32>,
+    open_limit: Option<i32>,
+}
+
+async fn update_spending_limits(Json(req): Json<UpdateSpendingLimitsRequest>) -> (StatusCode, Json<Value>) {
+    if req.period != "daily" && req.period != "weekly" {
+        return (StatusCode::BAD_REQUEST, Json(json!({"error": "Unknown period; use daily or weekly", "service": "lootpacks-service"})));
+    }
+    (StatusCode::OK, Json(json!({
+        "limits": [{"period": req.period, "personal_coin_limit": req.coin_limit, "personal_open_limit": req.open_limit, "pending": null}],
+        "service": "lootpacks-service"
+    })))
+}
+
+async fn update_default_spending_limits(Path(period): Path<String>, Json(req): Json<Value>) -> Json<Value> {
+    Json(json!({
+        "period": period,
+        "coin_limit": req.get("coin_limit"),
+        "open_limit": req.get("open_limit"),
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn get_self_exclusion() -> Json<Value> {
+    Json(json!({"active": null, "service": "lootpacks-service"}))
+}
+
+#[derive(Deserialize)]
+struct StartSelfExclusionRequest {
+    kind: String,
+    duration_days: i64,
+}
+
+async fn start_self_exclusion(Json(req): Json<StartSelfExclusionRequest>) -> (StatusCode, Json<Value>) {
+    let allowed = match req.kind.as_str() {
+        "cool_off" => 1..=42,
+        "self_exclusion" => 180..=1825,
+        _ => return (StatusCode::BAD_REQUEST, Json(json!({"error": "Unknown exclusion kind", "service": "lootpacks-service"}))),
+    };
+    if !allowed.contains(&req.duration_days) {
+        return (StatusCode::BAD_REQUEST, Json(json!({"error": "Duration out of range", "service": "lootpacks-service"})));
+    }
+    (StatusCode::OK, Json(json!({
+        "active": {"kind": req.kind, "duration_days": req.duration_days},
+        "service": "lootpacks-service"
+    })))
+}
+
+async fn create_fairness_commitment() -> Json<Value> {
+    Json(json!({
+        "commitment_id": "commitment_1",
+        "server_seed_hash": "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08",
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn verify_pack_open(Path(id): Path<String>) -> (StatusCode, Json<Value>) {
+    (StatusCode::NOT_FOUND, Json(json!({
+        "error": format!("No provably fair open with id {}", id),
+        "service": "lootpacks-service"
+    })))
+}
+
+async fn get_personalization() -> Json<Value> {
+    Json(json!({"opt_out": false, "top_categories": ["electronics", "fashion"], "service": "lootpacks-service"}))
+}
+
+async fn update_personalization(Json(req): Json<Value>) -> Json<Value> {
+    Json(json!({
+        "opt_out": req.get("opt_out").and_then(Value::as_bool).unwrap_or(false),
+        "top_categories": ["electronics", "fashion"],
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn get_notification_preferences() -> Json<Value> {
+    Json(json!({
+        "push_enabled": true,
+        "email_enabled": true,
+        "reward_expiring": true,
+        "daily_pack_ready": true,
+        "streak_at_risk": true,
+        "daily_digest": false,
+        "wishlist_available": true,
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn update_notification_preferences(Json(req): Json<Value>) -> Json<Value> {
+    let pref = |key: &str, default: bool| req.get(key).and_then(Value::as_bool).unwrap_or(default);
+    Json(json!({
+        "push_enabled": pref("push_enabled", true),
+        "email_enabled": pref("email_enabled", true),
+        "reward_expiring": pref("reward_expiring", true),
+        "daily_pack_ready": pref("daily_pack_ready", true),
+        "streak_at_risk": pref("streak_at_risk", true),
+        "daily_digest": pref("daily_digest", false),
+        "wishlist_available": pref("wishlist_available", true),
+        "service": "lootpacks-service"
+    }))
+}
+
+fn sample_wishlist_item(template_id: &str) -> Value {
+    json!({
+        "template_id": template_id,
+        "title": "Flat 50% off flights",
+        "rarity": "legendary",
+        "available_in": ["loot_2"],
+        "added_at": "2024-01-01T12:00:00Z"
+    })
+}
+
+async fn get_wishlist() -> Json<Value> {
+    Json(json!({"items": [sample_wishlist_item("template_1")], "service": "lootpacks-service"}))
+}
+
+async fn add_wishlist_item(Path(template_id): Path<String>) -> Json<Value> {
+    let mut items = vec![sample_wishlist_item(&template_id)];
+    if template_id != "template_1" {
+        items.push(sample_wishlist_item("template_1"));
+    }
+    Json(json!({"items": items, "service": "lootpacks-service"}))
+}
+
+async fn remove_wishlist_item(Path(template_id): Path<String>) -> Json<Value> {
+    let items: Vec<Value> = ["template_1"]
+        .iter()
+        .filter(|id| **id != template_id)
+        .map(|id| sample_wishlist_item(id))
+        .collect();
+    Json(json!({"items": items, "service": "lootpacks-service"}))
+}
+
+#[derive(Deserialize, Serialize)]
+struct PackHistoryQuery {
+    cursor: Option<String>,
+    limit: Option<i64>,
+    pack_type_id: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+}
+
+async fn get_pack_history(Query(query): Query<PackHistoryQuery>) -> Json<Value> {
+    Json(json!({
+        "entries": [
+            {
+                "id": "history_1",
+                "pack_type_id": query.pack_type_id.as_deref().unwrap_or("loot_1"),
+                "pack_name": "Daily Pack",
+                "rewards_count": 2,
+                "rewards": [
+                    {"type": "coupon", "value": "SAVE20", "rarity": "common"},
+                    {"type": "points", "value": 50, "rarity": "rare"}
+                ]
+            }
+        ],
+        "next_cursor": null,
+        "filters": query,
+        "service": "lootpacks-service"
+    }))
+}
+
+#[derive(Deserialize, Serialize)]
+struct CollectionQuery {
+    category: Option<String>,
+    merchant_id: Option<String>,
+    rarity: Option<String>,
+    collected: Option<bool>,
+}
+
+async fn get_collection(Query(query): Query<CollectionQuery>) -> Json<Value> {
+    Json(json!({
+        "entries": [
+            {
+                "template_id": "template_1",
+                "title": "20% off electronics",
+                "type": "coupon",
+                "rarity": "common",
+                "category": "electronics",
+                "merchant_id": "merchant_1",
+                "retired": false,
+                "collected": true,
+                "first_obtained_at": "2024-01-01T12:00:00Z",
+                "times_obtained": 3
+            },
+            {
+                "template_id": "template_2",
+                "title": "Free headphones",
+                "type": "coupon",
+                "rarity": "legendary",
+                "category": "electronics",
+                "merchant_id": "merchant_1",
+                "retired": false,
+                "collected": false,
+                "first_obtained_at": null,
+                "times_obtained": 0
+            }
+        ],
+        "filters": query,
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn get_collection_summary() -> Json<Value> {
+    Json(json!({
+        "overall": {"collected": 1, "total": 2, "percent": 50.0},
+        "by_category": {"electronics": {"collected": 1, "total": 2, "percent": 50.0}},
+        "by_merchant": {"merchant_1": {"collected": 1, "total": 2, "percent": 50.0}},
+        "by_rarity": {
+            "common": {"collected": 1, "total": 1, "percent": 100.0},
+            "legendary": {"collected": 0, "total": 1, "percent": 0.0}
+        },
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn get_reward_set_progress() -> Json<Value> {
+    Json(json!({
+        "sets": [
+            {
+                "set_id": "set_1",
+                "name": "Electronics Saver Set",
+                "description": "Collect every electronics coupon",
+                "bonus_pack_type_id": "loot_2",
+                "bonus_pack_name": "Premium Pack",
+                "collected": 2,
+                "total": 3,
+                "missing_template_ids": ["template_3"],
+                "completed_at": null
+            }
+        ],
+        "service": "lootpacks-service"
+    }))
+}
+
+#[derive(Deserialize)]
+struct RewardSetRequest {
+    name: String,
+    bonus_pack_type_id: String,
+    template_ids: Vec<String>,
+}
+
+fn validate_reward_set(req: &RewardSetRequest) -> Option<(StatusCode, Json<Value>)> {
+    if req.name.trim().is_empty() || req.template_ids.len() < 2 {
+        return Some((StatusCode::BAD_REQUEST, Json(json!({
+            "error": "A set needs a name and at least 2 templates",
+            "service": "lootpacks-service"
+        }))));
+    }
+    None
+}
+
+async fn list_reward_sets() -> Json<Value> {
+    Json(json!({
+        "sets": [
+            {
+                "id": "set_1",
+                "name": "Electronics Saver Set",
+                "bonus_pack_type_id": "loot_2",
+                "is_active": true,
+                "template_ids": ["template_1", "template_2", "template_3"]
+            }
+        ],
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn create_reward_set(Json(req): Json<RewardSetRequest>) -> (StatusCode, Json<Value>) {
+    if let Some(error) = validate_reward_set(&req) {
+        return error;
+    }
+    (StatusCode::OK, Json(json!({
+        "id": "set_2",
+        "name": req.name,
+        "bonus_pack_type_id": req.bonus_pack_type_id,
+        "template_ids": req.template_ids,
+        "service": "lootpacks-service"
+    })))
+}
+
+async fn update_reward_set(Path(id): Path<String>, Json(req): Json<RewardSetRequest>) -> (StatusCode, Json<Value>) {
+    if let Some(error) = validate_reward_set(&req) {
+        return error;
+    }
+    (StatusCode::OK, Json(json!({
+        "id": id,
+        "name": req.name,
+        "bonus_pack_type_id": req.bonus_pack_type_id,
+        "template_ids": req.template_ids,
+        "updated": true,
+        "service": "lootpacks-service"
+    })))
+}
+
+fn request_timezone(headers: &HeaderMap) -> String {
+    headers
+        .get("x-timezone")
+        .and_then(|v| v.to_str().ok())
+        .map(|tz| tz.trim().to_string())
+        .filter(|tz| !tz.is_empty())
+        .unwrap_or_else(|| "Asia/Kolkata".to_string())
+}
+
+async fn get_login_calendar(headers: HeaderMap) -> Json<Value> {
+    Json(json!({
+        "month": "2024-01",
+        "timezone": request_timezone(&headers),
+        "today": 3,
+        "days": [
+            {"day": 1, "grant_type": "coins", "coins_amount": 10, "pack_type_id": null, "label": null, "status": "claimed"},
+            {"day": 2, "grant_type": "coins", "coins_amount": 15, "pack_type_id": null, "label": null, "status": "missed"},
+            {"day": 3, "grant_type": "coins", "coins_amount": 20, "pack_type_id": null, "label": null, "status": "available"},
+            {"day": 7, "grant_type": "pack", "coins_amount": null, "pack_type_id": "loot_2", "label": "Week one bonus", "status": "upcoming"}
+        ],
+        "claimed_count": 1,
+        "missed_count": 1,
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn claim_login_calendar(headers: HeaderMap) -> Json<Value> {
+    Json(json!({
+        "month": "2024-01",
+        "day": 3,
+        "timezone": request_timezone(&headers),
+        "grant_type": "coins",
+        "coins_granted": 20,
+        "new_balance": 520,
+        "user_pack_id": null,
+        "service": "lootpacks-service"
+    }))
+}
+
+fn valid_calendar_month(month: &str) -> bool {
+    let mut parts = month.splitn(2, '-');
+    let year = parts.next().and_then(|y| y.parse::<u32>().ok());
+    let month = parts.next().and_then(|m| m.parse::<u32>().ok());
+    matches!((year, month), (Some(_), Some(1..=12)))
+}
+
+async fn get_login_calendar_month(Path(month): Path<String>) -> (StatusCode, Json<Value>) {
+    if !valid_calendar_month(&month) {
+        return (StatusCode::BAD_REQUEST, Json(json!({
+            "error": format!("Invalid month '{}', expected YYYY-MM", month),
+            "service": "lootpacks-service"
+        })));
+    }
+    (StatusCode::OK, Json(json!({
+        "month": month,
+        "days": [
+            {"day": 1, "grant_type": "coins", "coins_amount": 10, "pack_type_id": null, "label": null},
+            {"day": 7, "grant_type": "pack", "coins_amount": null, "pack_type_id": "loot_2", "label": "Week one bonus"}
+        ],
+        "service": "lootpacks-service"
+    })))
+}
+
+async fn set_login_calendar_month(Path(month): Path<String>, Json(req): Json<Value>) -> (StatusCode, Json<Value>) {
+    if !valid_calendar_month(&month) {
+        return (StatusCode::BAD_REQUEST, Json(json!({
+            "error": format!("Invalid month '{}', expected YYYY-MM", month),
+            "service": "lootpacks-service"
+        })));
+    }
+    (StatusCode::OK, Json(json!({
+        "month": month,
+        "days": req.get("days").cloned().unwrap_or_else(|| json!([])),
+        "service": "lootpacks-service"
+    })))
+}
+
+fn sample_community_event(id: &str) -> Value {
+    json!({
+        "id": id,
+        "name": "Million Pack Week",
+        "description": "Open 1,000,000 packs together this week and everyone who joins in gets a Premium Pack",
+        "pack_type_id": null,
+        "goal": 1000000,
+        "progress": 642318,
+        "starts_at": "2024-01-01T00:00:00Z",
+        "ends_at": "2024-01-08T00:00:00Z",
+        "reward_pack_type_id": "loot_2",
+        "min_contribution": 1,
+        "status": "running",
+        "goal_reached_at": null,
+        "closed_at": null
+    })
+}
+
+fn with_participation(mut event: Value) -> Value {
+    event["percent"] = json!(64.2);
+    event["your_contribution"] = json!(12);
+    event["eligible"] = json!(true);
+    event["user_pack_id"] = Value::Null;
+    event
+}
+
+async fn list_community_events() -> Json<Value> {
+    Json(json!({
+        "events": [with_participation(sample_community_event("event_1"))],
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn get_community_event(Path(id): Path<String>) -> Json<Value> {
+    let mut event = with_participation(sample_community_event(&id));
+    event["service"] = json!("lootpacks-service");
+    Json(event)
+}
+
+async fn admin_list_community_events() -> Json<Value> {
+    Json(json!({
+        "events": [sample_community_event("event_1")],
+        "service": "lootpacks-service"
+    }))
+}
+
+#[derive(Deserialize)]
+struct CreateCommunityEventRequest {
+    name: String,
+    goal: i64,
+    starts_at: String,
+    ends_at: String,
+    reward_pack_type_id: String,
+}
+
+async fn create_community_event(Json(req): Json<CreateCommunityEventRequest>) -> (StatusCode, Json<Value>) {
+    if req.name.trim().is_empty() || req.goal <= 0 {
+        return (StatusCode::BAD_REQUEST, Json(json!({
+            "error": "An event needs a name and a positive goal",
+            "service": "lootpacks-service"
+        })));
+    }
+    let mut event = sample_community_event("event_2");
+    event["name"] = json!(req.name);
+    event["goal"] = json!(req.goal);
+    event["progress"] = json!(0);
+    event["starts_at"] = json!(req.starts_at);
+    event["ends_at"] = json!(req.ends_at);
+    event["reward_pack_type_id"] = json!(req.reward_pack_type_id);
+    event["service"] = json!("lootpacks-service");
+    (StatusCode::OK, Json(event))
+}
+
+fn sample_wheel(id: &str, name: &str) -> Value {
+    json!({
+        "id": id,
+        "name": name,
+        "cooldown_hours": 24,
+        "is_active": true,
+        "segments": [
+            {"position": 0, "label": "10 coins", "color": "#FFC107", "reward_template_id": "template_coins_10", "weight": 50, "chance": 0.5},
+            {"position": 1, "label": "50 coins", "color": "#FF9800", "reward_template_id": "template_coins_50", "weight": 30, "chance": 0.3},
+            {"position": 2, "label": "20% off", "color": "#4CAF50", "reward_template_id": "template_1", "weight": 15, "chance": 0.15},
+            {"position": 3, "label": "Jackpot", "color": "#9C27B0", "reward_template_id": "template_2", "weight": 5, "chance": 0.05}
+        ],
+        "next_spin_at": null
+    })
+}
+
+async fn list_wheels() -> Json<Value> {
+    Json(json!({
+        "wheels": [sample_wheel("wheel_1", "Daily Wheel")],
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn spin_wheel(Path(id): Path<String>) -> Json<Value> {
+    Json(json!({
+        "wheel_id": id,
+        "position": 1,
+        "reward": {"type": "points", "value": "+50", "rarity": "common", "title": "50 DealCoins"},
+        "coins_credited": 50,
+        "new_balance": 550,
+        "next_spin_at": "2024-01-02T12:00:00Z",
+        "completed_sets": [],
+        "service": "lootpacks-service"
+    }))
+}
+
+#[derive(Deserialize)]
+struct WheelSegmentRequest {
+    label: String,
+    reward_template_id: Option<String>,
+    coins: Option<i32>,
+    weight: i32,
+}
+
+#[derive(Deserialize)]
+struct WheelRequest {
+    name: String,
+    segments: Vec<WheelSegmentRequest>,
+}
+
+fn validate_wheel(req: &WheelRequest) -> Option<(StatusCode, Json<Value>)> {
+    let error = if req.name.trim().is_empty() {
+        Some("name is required".to_string())
+    } else if req.segments.len() < 2 || req.segments.len() > 16 {
+        Some("A wheel needs between 2 and 16 segments".to_string())
+    } else {
+        req.segments
+            .iter()
+            .find(|s| s.weight <= 0 || s.reward_template_id.is_some() == s.coins.is_some())
+            .map(|s| format!("Segment '{}' needs a positive weight and either a reward_template_id or coins", s.label))
+    };
+    error.map(|error| (StatusCode::BAD_REQUEST, Json(json!({"error": error, "service": "lootpacks-service"}))))
+}
+
+async fn create_wheel(Json(req): Json<WheelRequest>) -> (StatusCode, Json<Value>) {
+    if let Some(error) = validate_wheel(&req) {
+        return error;
+    }
+    let mut wheel = sample_wheel("wheel_2", &req.name);
+    wheel["service"] = json!("lootpacks-service");
+    (StatusCode::OK, Json(wheel))
+}
+
+async fn update_wheel(Path(id): Path<String>, Json(req): Json<WheelRequest>) -> (StatusCode, Json<Value>) {
+    if let Some(error) = validate_wheel(&req) {
+        return error;
+    }
+    let mut wheel = sample_wheel(&id, &req.name);
+    wheel["service"] = json!("lootpacks-service");
+    (StatusCode::OK, Json(wheel))
+}
+
+#[derive(Deserialize)]
+struct FriendPullsQuery {
+    friend_ids: Option<String>,
+    limit: Option<i64>,
+}
+
+async fn get_friend_pulls(Query(query): Query<FriendPullsQuery>) -> (StatusCode, Json<Value>) {
+    let friend_ids: Vec<&str> = query
+        .friend_ids
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .collect();
+    if friend_ids.len() > 500 {
+        return (StatusCode::BAD_REQUEST, Json(json!({"error": "At most 500 friend_ids are accepted", "service": "lootpacks-service"})));
+    }
+
+    let pulls: Vec<Value> = friend_ids
+        .iter()
+        .take(query.limit.unwrap_or(20).clamp(1, 100) as usize)
+        .map(|friend_id| json!({
+            "user_id": friend_id,
+            "template_id": "template_1",
+            "title": "Flat 50% off flights",
+            "rarity": "legendary",
+            "pack_type_id": "loot_1",
+            "pack_name": "Premium Pack",
+            "pulled_at": "2024-01-01T11:45:00Z"
+        }))
+        .collect();
+    (StatusCode::OK, Json(json!({"pulls": pulls, "service": "lootpacks-service"})))
+}
+
+async fn get_privacy_settings() -> Json<Value> {
+    Json(json!({
+        "share_pulls_with_friends": true,
+        "show_in_live_feed": true,
+        "updated_at": null,
+        "service": "lootpacks-service"
+    }))
+}
+
+#[derive(Deserialize)]
+struct UpdatePrivacyRequest {
+    share_pulls_with_friends: Option<bool>,
+    show_in_live_feed: Option<bool>,
+}
+
+async fn update_privacy_settings(Json(req): Json<UpdatePrivacyRequest>) -> Json<Value> {
+    Json(json!({
+        "share_pulls_with_friends": req.share_pulls_with_friends.unwrap_or(true),
+        "show_in_live_feed": req.show_in_live_feed.unwrap_or(true),
+        "updated_at": "2024-01-01T12:00:00Z",
+        "service": "lootpacks-service"
+    }))
+}
+
+fn sample_team(id: &str, name: &str) -> Value {
+    json!({
+        "id": id,
+        "name": name,
+        "owner_id": "user_123",
+        "meter_coins": 3400,
+        "goal_coins": 10000,
+        "fills": 2,
+        "members": [
+            {"user_id": "user_123", "contributed_coins": 12500, "joined_at": "2024-01-01T12:00:00Z"},
+            {"user_id": "user_456", "contributed_coins": 10900, "joined_at": "2024-01-01T12:30:00Z"}
+        ],
+        "created_at": "2024-01-01T12:00:00Z"
+    })
+}
+
+#[derive(Deserialize)]
+struct CreateTeamRequest {
+    name: String,
+}
+
+async fn create_team(Json(req): Json<CreateTeamRequest>) -> (StatusCode, Json<Value>) {
+    let name = req.name.trim();
+    if name.is_empty() || name.chars().count() > 32 {
+        return (StatusCode::BAD_REQUEST, Json(json!({"error": "Team names are 1 to 32 characters", "service": "lootpacks-service"})));
+    }
+    let mut team = sample_team("team_1", name);
+    team["meter_coins"] = json!(0);
+    team["fills"] = json!(0);
+    team["members"] = json!([{"user_id": "user_123", "contributed_coins": 0, "joined_at": "2024-01-01T12:00:00Z"}]);
+    team["service"] = json!("lootpacks-service");
+    (StatusCode::OK, Json(team))
+}
+
+async fn get_team(Path(id): Path<String>) -> Json<Value> {
+    let mut team = sample_team(&id, "Deal Hunters");
+    team["service"] = json!("lootpacks-service");
+    Json(team)
+}
+
+async fn join_team(Path(id): Path<String>) -> Json<Value> {
+    let mut team = sample_team(&id, "Deal Hunters");
+    team["service"] = json!("lootpacks-service");
+    Json(team)
+}
+
+async fn get_my_team() -> Json<Value> {
+    Json(json!({"team": sample_team("team_1", "Deal Hunters"), "service": "lootpacks-service"}))
+}
+
+async fn leave_team() -> Json<Value> {
+    Json(json!({"message": "Left team", "service": "lootpacks-service"}))
+}
+
+async fn get_team_settings() -> Json<Value> {
+    Json(json!({
+        "settings": {"goal_coins": 10000, "reward_pack_type_id": "loot_2", "max_members": 30},
+        "service": "lootpacks-service"
+    }))
+}
+
+#[derive(Deserialize, Serialize)]
+struct TeamSettingsRequest {
+    goal_coins: i64,
+    reward_pack_type_id: String,
+    max_members: i32,
+}
+
+async fn update_team_settings(Json(req): Json<TeamSettingsRequest>) -> (StatusCode, Json<Value>) {
+    let error = if req.goal_coins <= 0 {
+        Some("goal_coins must be positive")
+    } else if req.max_members < 2 {
+        Some("max_members must be at least 2")
+    } else {
+        None
+    };
+    if let Some(error) = error {
+        return (StatusCode::BAD_REQUEST, Json(json!({"error": error, "service": "lootpacks-service"})));
+    }
+    (StatusCode::OK, Json(json!({"settings": req, "service": "lootpacks-service"})))
+}
+
+fn sample_shared_puzzle(id: &str, pieces_collected: i32) -> Value {
+    json!({
+        "id": id,
+        "team_id": "team_1",
+        "pieces_required": 20,
+        "pieces_collected": pieces_collected,
+        "reward_pack_type_id": "loot_2",
+        "reward_packs": 3,
+        "claim_policy": "split",
+        "contributors": [
+            {"user_id": "user_123", "pieces": 8},
+            {"user_id": "user_456", "pieces": 5}
+        ],
+        "your_share": 0,
+        "claimed": false,
+        "started_at": "2024-01-01T12:00:00Z",
+        "completed_at": null
+    })
+}
+
+async fn list_team_puzzles() -> Json<Value> {
+    Json(json!({"puzzles": [sample_shared_puzzle("puzzle_1", 13)], "service": "lootpacks-service"}))
+}
+
+#[derive(Deserialize)]
+struct ContributePiecesRequest {
+    pieces: i32,
+}
+
+async fn contribute_puzzle_pieces(Json(req): Json<ContributePiecesRequest>) -> (StatusCode, Json<Value>) {
+    if req.pieces <= 0 {
+        return (StatusCode::BAD_REQUEST, Json(json!({"error": "pieces must be positive", "service": "lootpacks-service"})));
+    }
+    let pieces = req.pieces.min(7);
+    let mut puzzle = sample_shared_puzzle("puzzle_1", 13 + pieces);
+    puzzle["contributors"][0]["pieces"] = json!(8 + pieces);
+    (StatusCode::OK, Json(json!({
+        "pieces_contributed": pieces,
+        "pieces_remaining": 7 - pieces,
+        "completed": pieces == 7,
+        "puzzle": puzzle,
+        "service": "lootpacks-service"
+    })))
+}
+
+async fn claim_shared_puzzle(Path(id): Path<String>) -> Json<Value> {
+    Json(json!({
+        "puzzle_id": id,
+        "packs": 2,
+        "user_pack_ids": ["user_pack_1", "user_pack_2"],
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn get_shared_puzzle_settings() -> Json<Value> {
+    Json(json!({
+        "settings": {"pieces_required": 20, "reward_pack_type_id": "loot_2", "reward_packs": 3, "claim_policy": "split"},
+        "service": "lootpacks-service"
+    }))
+}
+
+#[derive(Deserialize, Serialize)]
+struct SharedPuzzleSettingsRequest {
+    pieces_required: i32,
+    reward_pack_type_id: String,
+    reward_packs: i32,
+    claim_policy: String,
+}
+
+async fn update_shared_puzzle_settings(Json(req): Json<SharedPuzzleSettingsRequest>) -> (StatusCode, Json<Value>) {
+    let error = if req.pieces_required <= 0 {
+        Some("pieces_required must be positive")
+    } else if req.reward_packs <= 0 {
+        Some("reward_packs must be positive")
+    } else if !["duplicate", "split"].contains(&req.claim_policy.as_str()) {
+        Some("claim_policy must be one of duplicate, split")
+    } else {
+        None
+    };
+    if let Some(error) = error {
+        return (StatusCode::BAD_REQUEST, Json(json!({"error": error, "service": "lootpacks-service"})));
+    }
+    (StatusCode::OK, Json(json!({"settings": req, "service": "lootpacks-service"})))
+}
+
+#[derive(Deserialize)]
+struct TransferCoinsRequest {
+    recipient_id: String,
+    amount: i32,
+    note: Option<String>,
+}
+
+async fn transfer_coins(Json(req): Json<TransferCoinsRequest>) -> (StatusCode, Json<Value>) {
+    let recipient_id = req.recipient_id.trim();
+    let error = if recipient_id.is_empty() || recipient_id == "user_123" {
+        Some("Choose someone else to send DealCoins to".to_string())
+    } else if req.amount <= 0 {
+        Some("amount must be positive".to_string())
+    } else if req.note.as_deref().is_some_and(|n| n.trim().chars().count() > 140) {
+        Some("Notes are at most 140 characters".to_string())
+    } else if req.amount > 1000 {
+        Some("You can send 1000 more DealCoins today (daily cap 1000)".to_string())
+    } else {
+        None
+    };
+    if let Some(error) = error {
+        return (StatusCode::BAD_REQUEST, Json(json!({"error": error, "service": "lootpacks-service"})));
+    }
+    (StatusCode::OK, Json(json!({
+        "transfer_id": "transfer_1",
+        "recipient_id": recipient_id,
+        "amount": req.amount,
+        "new_balance": 1250 - req.amount.min(1250),
+        "send_remaining_today": 1000 - req.amount,
+        "service": "lootpacks-service"
+    })))
+}
+
+async fn get_winback_settings() -> Json<Value> {
+    Json(json!({
+        "settings": {"enabled": false, "dormant_days": 30, "pack_type_id": null, "pack_expiry_days": 7, "min_days_between": 90},
+        "service": "lootpacks-service"
+    }))
+}
+
+#[derive(Deserialize, Serialize)]
+struct WinBackSettingsRequest {
+    enabled: bool,
+    dormant_days: i32,
+    pack_type_id: Option<String>,
+    pack_expiry_days: i32,
+    min_days_between: i32,
+}
+
+async fn update_winback_settings(Json(req): Json<WinBackSettingsRequest>) -> (StatusCode, Json<Value>) {
+    let error = if req.dormant_days <= 0 || req.pack_expiry_days <= 0 || req.min_days_between <= 0 {
+        Some("dormant_days, pack_expiry_days and min_days_between must be positive")
+    } else if req.enabled && req.pack_type_id.is_none() {
+        Some("Choose a pack before enabling win-back grants")
+    } else {
+        None
+    };
+    if let Some(error) = error {
+        return (StatusCode::BAD_REQUEST, Json(json!({"error": error, "service": "lootpacks-service"})));
+    }
+    (StatusCode::OK, Json(json!({"settings": req, "service": "lootpacks-service"})))
+}
+
+async fn run_deal_sync() -> Json<Value> {
+    Json(json!({
+        "run_id": "sync_1",
+        "deals_fetched": 0,
+        "templates_created": 0,
+        "templates_updated": 0,
+        "templates_disabled": 0,
+        "templates_enabled": 0,
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn list_deal_sync_runs() -> Json<Value> {
+    Json(json!({
+        "runs": [],
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn list_event_export_checkpoints() -> Json<Value> {
+    Json(json!({
+        "checkpoints": [
+            {"stream": "pack_opens", "exported_through": "2024-01-01T00:00:00Z"},
+            {"stream": "redemptions", "exported_through": "2024-01-01T00:00:00Z"},
+            {"stream": "reward_grants", "exported_through": "2024-01-01T00:00:00Z"}
+        ],
+        "service": "lootpacks-service"
+    }))
+}
+
+/// Reward codes under each encryption key, for following a backfill or key rotation
+async fn get_code_encryption_status() -> Json<Value> {
+    Json(json!({
+        "active_key_id": "k2",
+        "codes_by_key": {"k2": 18250, "k1": 1200, "plaintext": 0},
+        "pending_rewrite": 1200,
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn list_vip_plans() -> Json<Value> {
+    Json(json!({
+        "plans": [{"plan_id": "vip_monthly", "premium_pack_type_id": "loot_3", "packs_per_period": 3, "coin_stipend": 500, "is_active": true}],
+        "service": "lootpacks-service"
+    }))
+}
+
+#[derive(Deserialize, Serialize)]
+struct VipPlanRequest {
+    premium_pack_type_id: String,
+    packs_per_period: i32,
+    coin_stipend: i32,
+    is_active: bool,
+}
+
+async fn upsert_vip_plan(Path(plan_id): Path<String>, Json(req): Json<VipPlanRequest>) -> (StatusCode, Json<Value>) {
+    if req.packs_per_period < 0 || req.coin_stipend < 0 {
+        return (StatusCode::BAD_REQUEST, Json(json!({"error": "packs_per_period and coin_stipend can't be negative", "service": "lootpacks-service"})));
+    }
+    (StatusCode::OK, Json(json!({"plan_id": plan_id, "plan": req, "service": "lootpacks-service"})))
+}
+
+async fn get_streak_settings() -> Json<Value> {
+    Json(json!({"settings": {"streak_window_hours": 30}, "service": "lootpacks-service"}))
+}
+
+#[derive(Deserialize, Serialize)]
+struct StreakSettingsRequest {
+    streak_window_hours: i32,
+}
+
+async fn update_streak_settings(Json(req): Json<StreakSettingsRequest>) -> (StatusCode, Json<Value>) {
+    if !(24..=72).contains(&req.streak_window_hours) {
+        return (StatusCode::BAD_REQUEST, Json(json!({"error": "streak_window_hours must be between 24 and 72", "service": "lootpacks-service"})));
+    }
+    (StatusCode::OK, Json(json!({"settings": req, "service": "lootpacks-service"})))
+}
+
+async fn get_transfer_settings() -> Json<Value> {
+    Json(json!({
+        "settings": {
+            "enabled": true,
+            "daily_send_cap": 1000,
+            "daily_receive_cap": 2000,
+            "min_account_age_days": 7,
+            "min_level": 3
+        },
+        "service": "lootpacks-service"
+    }))
+}
+
+#[derive(Deserialize, Serialize)]
+struct TransferSettingsRequest {
+    enabled: bool,
+    daily_send_cap: i32,
+    daily_receive_cap: i32,
+    min_account_age_days: i32,
+    min_level: i32,
+}
+
+async fn update_transfer_settings(Json(req): Json<TransferSettingsRequest>) -> (StatusCode, Json<Value>) {
+    let error = if req.daily_send_cap <= 0 || req.daily_receive_cap <= 0 {
+        Some("Daily caps must be positive")
+    } else if req.min_account_age_days < 0 {
+        Some("min_account_age_days can't be negative")
+    } else if req.min_level < 1 {
+        Some("min_level must be at least 1")
+    } else {
+        None
+    };
+    if let Some(error) = error {
+        return (StatusCode::BAD_REQUEST, Json(json!({"error": error, "service": "lootpacks-service"})));
+    }
+    (StatusCode::OK, Json(json!({"settings": req, "service": "lootpacks-service"})))
+}
+
+async fn list_transfer_flags() -> Json<Value> {
+    Json(json!({
+        "flags": [{
+            "id": "flag_1",
+            "transfer_id": "transfer_1",
+            "sender_id": "user_123",
+            "recipient_id": "user_456",
+            "amount": 900,
+            "reason": "drains_balance",
+            "details": {"balance_before": 950},
+            "created_at": "2024-01-01T12:00:00Z"
+        }],
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn review_transfer_flag(Path(id): Path<String>) -> Json<Value> {
+    Json(json!({"message": format!("Flag {} reviewed", id), "service": "lootpacks-service"}))
+}
+
+fn sample_live_feed_entry(id: i64) -> Value {
+    json!({
+        "id": id,
+        "template_id": "template_1",
+        "title": "Flat 50% off flights",
+        "rarity": "legendary",
+        "pack_name": "Premium Pack",
+        "pulled_at": "2024-01-01T11:45:00Z"
+    })
+}
+
+#[derive(Deserialize)]
+struct LiveFeedQuery {
+    after_id: Option<i64>,
+    limit: Option<i64>,
+}
+
+async fn get_live_feed(Query(query): Query<LiveFeedQuery>) -> Json<Value> {
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+    let entries: Vec<Value> = match query.after_id {
+        Some(after_id) => (after_id + 1..=after_id + limit.min(2)).map(sample_live_feed_entry).collect(),
+        None => (1..=limit.min(2)).rev().map(sample_live_feed_entry).collect(),
+    };
+    Json(json!({"entries": entries, "service": "lootpacks-service"}))
+}
+
+async fn stream_live_feed() -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let ticks = IntervalStream::new(tokio::time::interval(std::time::Duration::from_secs(10)));
+    let mut next_id = 0;
+    let entries = ticks.map(move |_| {
+        next_id += 1;
+        Ok(Event::default().event("pull").data(sample_live_feed_entry(next_id).to_string()))
+    });
+    Sse::new(entries).keep_alive(KeepAlive::default())
+}
+
+#[derive(Deserialize)]
+struct BulkRedeemRequest {
+    reward_ids: Vec<String>,
+}
+
+async fn redeem_rewards_bulk(Json(req): Json<BulkRedeemRequest>) -> Json<Value> {
+    let results: Vec<Value> = req.reward_ids.iter()
+        .map(|id| json!({"reward_id": id, "redeemed": true, "error": null}))
+        .collect();
+    Json(json!({
+        "applied": true,
+        "redeemed_count": results.len(),
+        "results": results,
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn claim_cashback(Path(id): Path<String>) -> Json<Value> {
+    Json(json!({
+        "reward_id": id,
+        "payout_status": "submitted",
+        "payout_amount": "50.00",
+        "payout_reference": "payout_123",
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn redeem_gift_card(Path(id): Path<String>) -> Json<Value> {
+    Json(json!({
+        "reward_id": id,
+        "fulfillment_status": "pending",
+        "attempts": 0,
+        "next_attempt_at": null,
+        "error": null,
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn get_gift_card_status(Path(id): Path<String>) -> Json<Value> {
+    Json(json!({
+        "reward_id": id,
+        "fulfillment_status": "submitted",
+        "attempts": 1,
+        "next_attempt_at": null,
+        "error": null,
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn retry_gift_card(Path(id): Path<String>) -> Json<Value> {
+    Json(json!({"message": "Gift card order requeued", "reward_id": id, "service": "lootpacks-service"}))
+}
+
+#[derive(Deserialize)]
+struct RerollRequest {
+    token: String,
+}
+
+async fn reroll_reward(Path(id): Path<String>, Json(req): Json<RerollRequest>) -> (StatusCode, Json<Value>) {
+    if req.token.is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(json!({
+            "error": "Reroll token is invalid, used or expired",
+            "service": "lootpacks-service"
+        })));
+    }
+    (StatusCode::OK, Json(json!({
+        "reward": {"id": id, "type": "coupon", "value": "SAVE30", "rarity": "rare"},
+        "new_balance": 450,
+        "completed_sets": [],
+        "service": "lootpacks-service"
+    })))
+}
+
+async fn trade_in_reward(Path(id): Path<String>) -> Json<Value> {
+    Json(json!({
+        "reward_id": id,
+        "coins_credited": 15,
+        "new_balance": 515,
+        "trade_ins_remaining_today": 9,
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn list_trade_in_rates() -> Json<Value> {
+    Json(json!({
+        "rates": [
+            {"rarity": "common", "coins": 5},
+            {"rarity": "rare", "coins": 15},
+            {"rarity": "epic", "coins": 40},
+            {"rarity": "legendary", "coins": 100}
+        ],
+        "service": "lootpacks-service"
+    }))
+}
+
+#[derive(Deserialize)]
+struct UpdateTradeInRateRequest {
+    coins: i32,
+}
+
+async fn update_trade_in_rate(
+    Path(rarity): Path<String>,
+    Json(req): Json<UpdateTradeInRateRequest>,
+) -> (StatusCode, Json<Value>) {
+    if req.coins < 0 {
+        return (StatusCode::BAD_REQUEST, Json(json!({"error": "coins can't be negative", "service": "lootpacks-service"})));
+    }
+    (StatusCode::OK, Json(json!({"rarity": rarity, "coins": req.coins, "service": "lootpacks-service"})))
+}
+
+async fn share_reward(Path(id): Path<String>) -> Json<Value> {
+    Json(json!({"reward_id": id, "token": "share_demo_token", "expires_in_days": 7, "service": "lootpacks-service"}))
+}
+
+async fn revoke_reward_share(Path(id): Path<String>) -> Json<Value> {
+    Json(json!({"message": "Share links revoked", "reward_id": id, "service": "lootpacks-service"}))
+}
+
+#[derive(Deserialize)]
+struct ProposeTradeRequest {
+    recipient_id: String,
+    offered_reward_ids: Vec<String>,
+    requested_reward_ids: Vec<String>,
+}
+
+async fn list_trades() -> Json<Value> {
+    Json(json!({"trades": [], "service": "lootpacks-service"}))
+}
+
+async fn propose_trade(Json(req): Json<ProposeTradeRequest>) -> (StatusCode, Json<Value>) {
+    if req.offered_reward_ids.is_empty() || req.requested_reward_ids.is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(json!({
+            "error": "Each side of a trade needs at least one reward",
+            "service": "lootpacks-service"
+        })));
+    }
+    (StatusCode::OK, Json(json!({
+        "id": "trade_1",
+        "recipient_id": req.recipient_id,
+        "status": "offered",
+        "offered_reward_ids": req.offered_reward_ids,
+        "requested_reward_ids": req.requested_reward_ids,
+        "service": "lootpacks-service"
+    })))
+}
+
+async fn get_trade(Path(id): Path<String>) -> Json<Value> {
+    Json(json!({"id": id, "status": "offered", "service": "lootpacks-service"}))
+}
+
+async fn accept_trade(Path(id): Path<String>) -> Json<Value> {
+    Json(json!({"id": id, "status": "swapped", "service": "lootpacks-service"}))
+}
+
+async fn cancel_trade(Path(id): Path<String>) -> Json<Value> {
+    Json(json!({"id": id, "status": "cancelled", "cancel_reason": "withdrawn", "service": "lootpacks-service"}))
+}
+
+async fn get_shared_reward(Path(_token): Path<String>) -> Json<Value> {
+    Json(json!({
+        "reward": {"type": "coupon", "title": "20% off electronics", "value": "20%", "rarity": "epic"},
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn list_discounts(Path(id): Path<String>) -> Json<Value> {
+    Json(json!({
+        "pack_type_id": id,
+        "discounts": [{"id": "discount_1", "percent_off": 20, "label": "Weekend sale"}],
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn create_discount(Path(id): Path<String>, Json(discount): Json<Value>) -> Json<Value> {
+    Json(json!({"message": "Discount scheduled", "pack_type_id": id, "discount": discount, "service": "lootpacks-service"}))
+}
+
+async fn delete_discount(Path(id): Path<String>) -> Json<Value> {
+    Json(json!({"message": "Discount cancelled", "id": id, "service": "lootpacks-service"}))
+}
+
+async fn list_translations(Path(id): Path<String>) -> Json<Value> {
+    Json(json!({
+        "id": id,
+        "translations": [{"locale": "hi", "text": "दैनिक पैक", "description": null}],
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn upsert_translation(Path((id, locale)): Path<(String, String)>, Json(req): Json<Value>) -> Json<Value> {
+    Json(json!({
+        "id": id,
+        "locale": locale.to_lowercase(),
+        "text": req.get("text"),
+        "description": req.get("description"),
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn delete_translation(Path((id, locale)): Path<(String, String)>) -> Json<Value> {
+    Json(json!({"message": "Translation deleted", "id": id, "locale": locale, "service": "lootpacks-service"}))
+}
+
+async fn list_flags() -> Json<Value> {
+    Json(json!({
+        "flags": [
+            {"key": "trading", "enabled": false, "rollout_percentage": 0, "allowlist": []},
+            {"key": "crafting", "enabled": false, "rollout_percentage": 0, "allowlist": []},
+            {"key": "bulk_opening", "enabled": false, "rollout_percentage": 0, "allowlist": []}
+        ],
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn upsert_flag(Path(key): Path<String>, Json(flag): Json<Value>) -> Json<Value> {
+    Json(json!({"message": "Feature flag saved", "key": key, "flag": flag, "service": "lootpacks-service"}))
+}
+
+async fn delete_flag(Path(key): Path<String>) -> Json<Value> {
+    Json(json!({"message": "Feature flag deleted", "key": key, "service": "lootpacks-service"}))
+}
+
+#[derive(Deserialize)]
+struct CouponValidationRequest {
+    code: String,
+    reserve_seconds: Option<i64>,
+}
+
+async fn validate_coupon(Json(req): Json<CouponValidationRequest>) -> Json<Value> {
+    Json(json!({
+        "valid": true,
+        "code": req.code,
+        "type": "coupon",
+        "value": "20%",
+        "rarity": "common",
+        "reserved": req.reserve_seconds.unwrap_or(0) > 0,
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn reserve_reward(Path(id): Path<String>) -> Json<Value> {
+    Json(json!({
+        "reward_id": id,
+        "reservation_id": "reservation_123",
+        "hold_seconds": 600,
+        "service": "lootpacks-service"
+    }))
+}
+
+async fn release_reward(Path(id): Path<String>) -> Json<Value> {
+    Json(json!({"message": "Reservation released", "reward_id": id, "service": "lootpacks-service"}))
+}
+
+async fn confirm_reward(Path(id): Path<String>) -> Json<Value> {
+    Json(json!({"message": "Reward redeemed", "reward_id": id, "service": "lootpacks-service"}))
+}
+
+async fn payout_webhook(Json(_webhook): Json<Value>) -> Json<Value> {
+    Json(json!({"message": "Payout status recorded", "service": "lootpacks-service"}))
+}
+
+async fn gift_card_webhook(Json(_webhook): Json<Value>) -> Json<Value> {
+    Json(json!({"message": "Fulfillment status recorded", "service": "lootpacks-service"}))
+}
+
+async fn subscription_webhook(Json(_subscription): Json<Value>) -> Json<Value> {
+    Json(json!({"message": "Subscription recorded", "service": "lootpacks-service"}))
+}
+
+#[derive(Deserialize)]
+struct ChargebackEvent {
+    dispute_id: String,
+    user_id: String,
+    coins: i32,
+    status: String,
+}
+
+async fn chargeback_webhook(Json(event): Json<ChargebackEvent>) -> (StatusCode, Json<Value>) {
+    if event.coins <= 0 {
+        return (StatusCode::BAD_REQUEST, Json(json!({"error": "coins must be positive", "service": "lootpacks-service"})));
+    }
+    let (status, balance) = match event.status.as_str() {
+        "opened" => ("open", 1250 - event.coins),
+        "won" => ("won", 1250),
+        "lost" => ("lost", 1250 - event.coins),
+        _ => {
+            return (StatusCode::BAD_REQUEST, Json(json!({"error": "status must be opened, won or lost", "service": "lootpacks-service"})));
+        }
+    };
+    (StatusCode::OK, Json(json!({
+        "dispute_id": event.dispute_id,
+        "user_id": event.user_id,
+        "status": status,
+        "clawed_back_coins": event.coins,
+        "balance": balance,
+        "service": "lootpacks-service"
+    })))
+}
+
+async fn list_open_chargebacks() -> Json<Value> {
+    Json(json!({
+        "chargebacks": [
+            {"dispute_id": "dp_1", "payment_id": "pay_1", "user_id": "user_123", "coins": 1500, "clawed_back_coins": 1500, "opened_at": "2024-01-01T12:00:00Z"}
+        ],
+        "service": "lootpacks-service"
+    }))
+}
+
+#[derive(Deserialize)]
+struct PurchaseCompletedEvent {
+    event_id: String,
+    user_id: String,
+    reward_id: String,
+    order_id: String,
+}
+
+async fn purchase_completed(Json(event): Json<PurchaseCompletedEvent>) -> (StatusCode, Json<Value>) {
+    if event.user_id.is_empty() || event.order_id.is_empty() || event.reward_id.is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(json!({"error": "user_id, reward_id and order_id are required", "service": "lootpacks-service"})));
+    }
+    (StatusCode::OK, Json(json!({
+        "event_id": event.event_id,
+        "duplicate": false,
+        "savings": {"amount": "150.00", "currency": "INR"},
+        "xp_awarded": 20,
+        "level": 5,
+        "leveled_up": false,
+        "service": "lootpacks-service"
+    })))
+}
+
+#[derive(Deserialize)]
+struct RefundPackRequest {
+    #[serde(default)]
+    reason: String,
+}
+
+async fn refund_pack_open(
+    Extension(principal): Extension<Principal>,
+    Path(id): Path<String>,
+    Json(req): Json<RefundPackRequest>,
+) -> (StatusCode, Json<Value>) {
+    if req.reason.trim().is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(json!({"error": "A refund reason is required", "service": "lootpacks-service"})));
+    }
+    (StatusCode::OK, Json(json!({
+        "pack_history_id": id,
+        "user_id": "user_123",
+        "refunded_coins": 199,
+        "new_balance": 1449,
+        "voided_reward_ids": ["reward_1", "reward_2"],
+        "flagged_reward_ids": ["reward_3"],
+        "refunded_by": principal.name,
+        "service": "lootpacks-service"
+    })))
+}
+
+async fn support_lookup_user(Path(user_id): Path<String>) -> Json<Value> {
+    Json(json!({
+        "user_id": user_id,
+        "stats": {"deal_coins": 1250, "level": 5, "member_status": "Gold", "total_packs_opened": 47, "total_savings_inr": "2340.50", "created_at": "2023-11-02T09:15:00Z"},
+        "recent_opens": [
+            {"pack_history_id": "history_1", "pack_name": "Daily Deal Pack", "rewards_count": 2, "total_value_inr": "160.00", "opened_at": "2024-01-01T12:00:00Z"}
+        ],
+        "rewards": [
+            {"id": "reward_1", "title": "20% off Electronics", "type": "coupon", "rarity": "rare", "status": "available", "code_hint": "…X7K2", "fulfillment_status": null, "payout_status": null, "expires_at": "2024-01-08T12:00:00Z", "created_at": "2024-01-01T12:00:00Z"},
+            {"id": "reward_2", "title": "₹50 Cashback", "type": "cashback", "rarity": "common", "status": "used", "code_hint": null, "fulfillment_status": null, "payout_status": "paid", "expires_at": null, "created_at": "2023-12-28T08:00:00Z"}
+        ],
+        "ad_gate": {
+            "daily_pack_unlocked": false,
+            "completions": [{"placement": "daily_pack_ad", "completed_at": "2024-01-01T11:55:00Z"}]
+        },
+        "streak": {
+            "daily_streak": 3,
+            "timezone": "Asia/Kolkata",
+            "next_claim_at": "2024-01-01T18:30:00Z",
+            "streak_until": "2024-01-03T00:30:00Z",
+            "events": [
+                {"kind": "daily_claim", "at": "2024-01-01T12:00:00Z", "details": {"pack_history_id": "history_1"}},
+                {"kind": "streak_repaired", "at": "2023-12-30T10:00:00Z", "details": {"lost_streak": 6, "price_coins": 110}},
+                {"kind": "streak_broken", "at": "2023-12-29T18:30:00Z", "details": {"lost_streak": 6, "repair_deadline": "2023-12-31T18:30:00Z"}}
+            ]
+        },
+        "service": "lootpacks-service"
+    }))
+}
+
+#[derive(Deserialize)]
+struct SupportActionRequest {
+    #[serde(default)]
+    reason: String,
+}
+
+/// Support actions must say why; see `support::require_reason`
+const MIN_SUPPORT_REASON_CHARS: usize = 10;
+
+fn missing_support_reason(req: &SupportActionRequest) -> Option<(StatusCode, Json<Value>)> {
+    (req.reason.trim().chars().count() < MIN_SUPPORT_REASON_CHARS).then(|| {
+        (StatusCode::BAD_REQUEST, Json(json!({
+            "error": format!("A reason of at least {} characters is required", MIN_SUPPORT_REASON_CHARS),
+            "service": "lootpacks-service"
+        })))
+    })
+}
+
+async fn support_resend_code(
+    Extension(principal): Extension<Principal>,
+    Path((user_id, reward_id)): Path<(String, String)>,
+    Json(req): Json<SupportActionRequest>,
+) -> (StatusCode, Json<Value>) {
+    if let Some(rejection) = missing_support_reason(&req) {
+        return rejection;
+    }
+    (StatusCode::OK, Json(json!({
+        "message": format!("Code for reward {} resent to {}", reward_id, user_id),
+        "actor": principal.name,
+        "service": "lootpacks-service"
+    })))
+}
+
+#[derive(Deserialize)]
+struct SupportGrantRequest {
+    pack_type_id: String,
+    #[serde(default)]
+    reason: String,
+}
+
+async fn support_grant_pack(
+    Extension(principal): Extension<Principal>,
+    Path(user_id): Path<String>,
+    Json(req): Json<SupportGrantRequest>,
+) -> (StatusCode, Json<Value>) {
+    if let Some(rejection) = missing_support_reason(&SupportActionRequest { reason: req.reason }) {
+        return rejection;
+    }
+    (StatusCode::CREATED, Json(json!({
+        "user_id": user_id,
+        "user_pack_id": "user_pack_4",
+        "pack_type_id": req.pack_type_id,
+        "pack_name": "Daily Pack",
+        "actor": principal.name,
+        "service": "lootpacks-service"
+    })))
+}
+
+async fn support_extend_expiry(
+    Extension(principal): Extension<Principal>,
+    Path((_user_id, reward_id)): Path<(String, String)>,
+    Json(req): Json<SupportActionRequest>,
+) -> (StatusCode, Json<Value>) {
+    if let Some(rejection) = missing_support_reason(&req) {
+        return rejection;
+    }
+    (StatusCode::OK, Json(json!({
+        "reward_id": reward_id,
+        "previous_expires_at": "2024-01-08T12:00:00Z",
+        "expires_at": "2024-01-15T12:00:00Z",
+        "actor": principal.name,
+        "service": "lootpacks-service"
+    })))
+}
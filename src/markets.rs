@@ -0,0 +1,263 @@
+use crate::error::{AppError, Result};
+use crate::audit;
+use crate::lootpacks::LootpackService;
+use crate::pack_admin;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{FromRow, PgExecutor, PgPool};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Used when the markets table has no default, e.g. before migrations finish
+const FALLBACK_MARKET: &str = "IN";
+
+/// The market a request is served in. Packs and templates restricted to other markets are hidden.
+#[derive(Debug, Clone)]
+pub struct Market {
+    code: String,
+}
+
+impl Market {
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+}
+
+/// Resolve the caller's market. The market claim from the verified auth token wins over
+/// the `X-Market` header; unknown or inactive markets fall back to the default market.
+pub async fn resolve<'e, E: PgExecutor<'e>>(
+    executor: E,
+    token_market: Option<&str>,
+    header_market: Option<&str>,
+) -> Result<Market> {
+    let candidates: Vec<String> = [token_market, header_market]
+        .into_iter()
+        .flatten()
+        .map(|code| code.trim().to_uppercase())
+        .filter(|code| !code.is_empty())
+        .collect();
+
+    let code = sqlx::query_scalar!(
+        r#"
+        SELECT code FROM markets
+        WHERE is_active AND (code = ANY($1) OR is_default)
+        ORDER BY array_position($1, code) NULLS LAST
+        LIMIT 1
+        "#,
+        &candidates
+    )
+    .fetch_optional(executor)
+    .await?
+    .unwrap_or_else(|| FALLBACK_MARKET.to_string());
+
+    Ok(Market { code })
+}
+
+#[derive(Debug, FromRow, Serialize)]
+pub struct MarketConfig {
+    pub code: String,
+    pub name: String,
+    pub default_currency: String,
+    pub is_active: bool,
+    pub is_default: bool,
+    /// Local hour (0-23) daily packs reset at
+    pub daily_reset_hour: i16,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpsertMarketRequest {
+    pub name: String,
+    pub default_currency: String,
+    pub is_active: Option<bool>,
+    pub is_default: Option<bool>,
+    pub daily_reset_hour: Option<i16>,
+}
+
+/// `markets: null` offers the pack or template everywhere
+#[derive(Debug, Deserialize)]
+pub struct SetMarketsRequest {
+    pub markets: Option<Vec<String>>,
+}
+
+/// Per-market admin configuration
+pub struct MarketService {
+    db: PgPool,
+    lootpacks: Arc<LootpackService>,
+}
+
+impl MarketService {
+    pub fn new(db: PgPool, lootpacks: Arc<LootpackService>) -> Self {
+        Self { db, lootpacks }
+    }
+
+    pub async fn list_markets(&self) -> Result<Vec<MarketConfig>> {
+        let markets = sqlx::query_as!(
+            MarketConfig,
+            "SELECT code, name, default_currency, is_active, is_default, daily_reset_hour, updated_at FROM markets ORDER BY code"
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(markets)
+    }
+
+    pub async fn upsert_market(&self, actor: &str, code: &str, req: UpsertMarketRequest) -> Result<MarketConfig> {
+        let code = code.to_uppercase();
+        if code.len() != 2 || !code.chars().all(|c| c.is_ascii_uppercase()) {
+            return Err(AppError::BadRequest(format!("Invalid market '{}'", code)));
+        }
+        let default_currency = req.default_currency.to_uppercase();
+        let is_default = req.is_default.unwrap_or(false);
+        if is_default && req.is_active == Some(false) {
+            return Err(AppError::BadRequest("The default market must be active".to_string()));
+        }
+        if req.daily_reset_hour.is_some_and(|hour| !(0..=23).contains(&hour)) {
+            return Err(AppError::BadRequest("daily_reset_hour must be between 0 and 23".to_string()));
+        }
+
+        let mut tx = self.db.begin().await?;
+        if is_default {
+            sqlx::query!("UPDATE markets SET is_default = false, updated_at = NOW() WHERE is_default AND code <> $1", code)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        let market = sqlx::query_as!(
+            MarketConfig,
+            r#"
+            INSERT INTO markets (code, name, default_currency, is_active, is_default, daily_reset_hour)
+            VALUES ($1, $2, $3, COALESCE($4, true), $5, COALESCE($6, 0))
+            ON CONFLICT (code) DO UPDATE SET
+                name = EXCLUDED.name,
+                default_currency = EXCLUDED.default_currency,
+                is_active = COALESCE($4, markets.is_active),
+                is_default = markets.is_default OR EXCLUDED.is_default,
+                daily_reset_hour = COALESCE($6, markets.daily_reset_hour),
+                updated_at = NOW()
+            RETURNING code, name, default_currency, is_active, is_default, daily_reset_hour, updated_at
+            "#,
+            code,
+            req.name,
+            default_currency,
+            req.is_active,
+            is_default,
+            req.daily_reset_hour
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(db) if db.is_foreign_key_violation() => {
+                AppError::BadRequest(format!("Unsupported currency '{}'", default_currency))
+            }
+            other => other.into(),
+        })?;
+
+        if market.is_default && !market.is_active {
+            return Err(AppError::BadRequest("The default market must be active".to_string()));
+        }
+
+        audit::record(&mut *tx, actor, "market.updated", Some(&market.code), json!({
+            "name": market.name,
+            "default_currency": market.default_currency,
+            "is_active": market.is_active,
+            "is_default": market.is_default,
+            "daily_reset_hour": market.daily_reset_hour,
+        }))
+        .await?;
+        tx.commit().await?;
+        Ok(market)
+    }
+
+    /// Restrict a pack to markets; recorded as a new pack version so it can be rolled back
+    pub async fn set_pack_markets(&self, actor: &str, pack_type_id: Uuid, req: SetMarketsRequest) -> Result<()> {
+        let markets = self.validate_markets(req.markets).await?;
+
+        let mut tx = self.db.begin().await?;
+        pack_admin::lock_pack(&mut tx, pack_type_id, actor).await?;
+        pack_admin::ensure_baseline(&mut tx, pack_type_id).await?;
+
+        sqlx::query!(
+            "UPDATE pack_types SET markets = $2, updated_at = NOW() WHERE id = $1",
+            pack_type_id,
+            markets.as_deref()
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let version = pack_admin::snapshot(&mut tx, pack_type_id, actor, Some("Markets changed")).await?;
+        audit::record(
+            &mut *tx,
+            actor,
+            "pack.markets_updated",
+            Some(&pack_type_id.to_string()),
+            json!({ "markets": markets, "version": version.version }),
+        )
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn set_template_markets(&self, actor: &str, template_id: Uuid, req: SetMarketsRequest) -> Result<()> {
+        let markets = self.validate_markets(req.markets).await?;
+
+        let mut tx = self.db.begin().await?;
+        sqlx::query!("SELECT set_config('app.actor', $1, true)", actor)
+            .execute(&mut *tx)
+            .await?;
+
+        let updated = sqlx::query!(
+            "UPDATE reward_templates SET markets = $2 WHERE id = $1",
+            template_id,
+            markets.as_deref()
+        )
+        .execute(&mut *tx)
+        .await?;
+        if updated.rows_affected() == 0 {
+            return Err(AppError::NotFound("Reward template not found".to_string()));
+        }
+
+        audit::record(
+            &mut *tx,
+            actor,
+            "reward_template.markets_updated",
+            Some(&template_id.to_string()),
+            json!({ "markets": markets }),
+        )
+        .await?;
+
+        tx.commit().await?;
+        // Pools are filtered per open, but cached template versions changed
+        self.lootpacks.clear_caches().await;
+        Ok(())
+    }
+
+    /// Normalise codes and reject unknown markets; an empty list means no restriction
+    async fn validate_markets(&self, markets: Option<Vec<String>>) -> Result<Option<Vec<String>>> {
+        let Some(markets) = markets else {
+            return Ok(None);
+        };
+        let mut markets: Vec<String> = markets.iter().map(|m| m.trim().to_uppercase()).collect();
+        markets.sort();
+        markets.dedup();
+        if markets.is_empty() {
+            return Ok(None);
+        }
+
+        let known = sqlx::query_scalar!("SELECT code FROM markets WHERE code = ANY($1)", &markets)
+            .fetch_all(&self.db)
+            .await?;
+        let unknown: Vec<&str> = markets
+            .iter()
+            .filter(|m| !known.contains(m))
+            .map(String::as_str)
+            .collect();
+        if !unknown.is_empty() {
+            return Err(AppError::BadRequest(format!("Unknown markets: {}", unknown.join(", "))));
+        }
+
+        Ok(Some(markets))
+    }
+}
@@ -0,0 +1,198 @@
+use crate::error::{AppError, Result};
+use crate::lootpacks::SampleReward;
+use crate::{audit, first_purchase};
+use crate::wallet::{self, CoinWallet};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use serde_json::json;
+use sqlx::{PgConnection, PgPool};
+use std::sync::Arc;
+use tracing::{error, info};
+use uuid::Uuid;
+
+/// Hidden options dealt for each mystery open
+pub const MYSTERY_OPTIONS: usize = 3;
+
+/// How long the user has to pick before the open is refunded
+const CHOICE_TTL_MINUTES: i64 = 10;
+const SWEEP_INTERVAL_SECONDS: u64 = 60;
+
+/// Handed out with a mystery open in place of rewards
+#[derive(Debug, Serialize)]
+pub struct MysteryOffer {
+    pub choice_id: Uuid,
+    pub option_count: usize,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// A pending choice the user has just made
+pub struct HeldChoice {
+    pub pack_history_id: Uuid,
+    pub pack_type_id: Uuid,
+    pub pack_name: String,
+    pub options: Vec<SampleReward>,
+}
+
+/// Store the dealt options until the user picks one. Runs in the open's transaction.
+pub async fn hold(
+    conn: &mut PgConnection,
+    user_id: &str,
+    pack_type_id: Uuid,
+    pack_history_id: Uuid,
+    options: &[SampleReward],
+) -> Result<MysteryOffer> {
+    let encoded = serde_json::to_value(options)
+        .map_err(|e| AppError::InternalError(format!("Failed to encode mystery options: {}", e)))?;
+    let expires_at = Utc::now() + Duration::minutes(CHOICE_TTL_MINUTES);
+
+    let choice_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO mystery_choices (user_id, pack_type_id, pack_history_id, options, expires_at)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id
+        "#,
+        user_id,
+        pack_type_id,
+        pack_history_id,
+        encoded,
+        expires_at
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(MysteryOffer { choice_id, option_count: options.len(), expires_at })
+}
+
+/// Record what the open cost so an expired choice can be refunded
+pub async fn set_price(conn: &mut PgConnection, choice_id: Uuid, coins_paid: i32) -> Result<()> {
+    sqlx::query!("UPDATE mystery_choices SET coins_paid = $2 WHERE id = $1", choice_id, coins_paid)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// Claim the user's single pick. The status flip is one conditional update, so a second
+/// pick (or a pick racing the expiry sweep) finds nothing left to claim.
+pub async fn take(conn: &mut PgConnection, user_id: &str, choice_id: Uuid, index: usize) -> Result<HeldChoice> {
+    let index = i32::try_from(index)
+        .ok()
+        .filter(|index| (*index as usize) < MYSTERY_OPTIONS)
+        .ok_or_else(|| AppError::BadRequest(format!("Pick an option between 0 and {}", MYSTERY_OPTIONS - 1)))?;
+    let taken = sqlx::query!(
+        r#"
+        UPDATE mystery_choices mc
+        SET status = 'chosen', chosen_index = $3, resolved_at = NOW()
+        FROM pack_types pt
+        WHERE mc.id = $1 AND mc.user_id = $2 AND mc.status = 'pending' AND mc.expires_at > NOW()
+          AND $3 < jsonb_array_length(mc.options) AND pt.id = mc.pack_type_id
+        RETURNING mc.pack_history_id, mc.pack_type_id, pt.name AS pack_name, mc.options
+        "#,
+        choice_id,
+        user_id,
+        index
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let Some(taken) = taken else {
+        let status = sqlx::query_scalar!(
+            r#"
+            SELECT CASE WHEN status = 'pending' AND expires_at <= NOW() THEN 'expired' ELSE status END AS "status!"
+            FROM mystery_choices WHERE id = $1 AND user_id = $2
+            "#,
+            choice_id,
+            user_id
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Mystery box not found".to_string()))?;
+
+        return Err(AppError::BadRequest(match status.as_str() {
+            "chosen" => "A reward has already been picked from this mystery box".to_string(),
+            "expired" => "This mystery box has expired and was refunded".to_string(),
+            _ => format!("Pick an option between 0 and {}", MYSTERY_OPTIONS - 1),
+        }));
+    };
+
+    let options: Vec<SampleReward> = serde_json::from_value(taken.options)
+        .map_err(|e| AppError::InternalError(format!("Stored mystery options are invalid: {}", e)))?;
+    Ok(HeldChoice {
+        pack_history_id: taken.pack_history_id,
+        pack_type_id: taken.pack_type_id,
+        pack_name: taken.pack_name,
+        options,
+    })
+}
+
+pub async fn record_reward(conn: &mut PgConnection, choice_id: Uuid, reward_id: Uuid) -> Result<()> {
+    sqlx::query!("UPDATE mystery_choices SET reward_id = $2 WHERE id = $1", choice_id, reward_id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+pub struct MysteryBoxService {
+    db: PgPool,
+    wallet: Arc<dyn CoinWallet>,
+}
+
+impl MysteryBoxService {
+    pub fn new(db: PgPool, wallet: Arc<dyn CoinWallet>) -> Self {
+        Self { db, wallet }
+    }
+
+    /// Refund choices that ran out of time without a pick
+    pub async fn expire_due(&self) -> Result<usize> {
+        let mut tx = self.db.begin().await?;
+        let expired = sqlx::query!(
+            r#"
+            UPDATE mystery_choices SET status = 'expired', resolved_at = NOW()
+            WHERE id IN (
+                SELECT id FROM mystery_choices
+                WHERE status = 'pending' AND expires_at <= NOW()
+                ORDER BY expires_at
+                LIMIT 100
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, user_id, pack_history_id, coins_paid
+            "#
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let mut wallet_entries = Vec::new();
+        for choice in &expired {
+            let reference = choice.id.to_string();
+            if choice.coins_paid > 0 {
+                wallet_entries.push(
+                    self.wallet.apply(&mut tx, &choice.user_id, choice.coins_paid, "mystery_refund", Some(&reference)).await?,
+                );
+                first_purchase::revoke_bonus(&mut tx, choice.pack_history_id).await?;
+            }
+            audit::record(&mut *tx, "system", "mystery_box.expired", Some(&reference), json!({
+                "user_id": choice.user_id,
+                "refunded": choice.coins_paid,
+            }))
+            .await?;
+        }
+
+        wallet::commit_with_wallet(self.wallet.as_ref(), tx, &wallet_entries).await?;
+        if !expired.is_empty() {
+            info!("Refunded {} expired mystery boxes", expired.len());
+        }
+        Ok(expired.len())
+    }
+
+    /// Run `expire_due` every minute
+    pub fn spawn_sweeper(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(SWEEP_INTERVAL_SECONDS));
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.expire_due().await {
+                    error!("Mystery box sweep failed: {:?}", e);
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,447 @@
+use crate::code_crypto::CodeCipher;
+use crate::error::{AppError, Result};
+use crate::happy_hours;
+use crate::http_client::HttpServiceClient;
+use crate::privacy;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{FromRow, PgExecutor, PgPool};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// How long a worker holds the events it is delivering before another may retry them
+const DELIVERY_LEASE_SECONDS: f64 = 300.0;
+
+/// Events the lootpacks service notifies users about
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum NotificationEvent {
+    RewardExpiring { reward_id: Uuid, title: String, expires_at: DateTime<Utc> },
+    DailyPackReady { available_since: DateTime<Utc> },
+    StreakAtRisk { streak: i32, breaks_at: DateTime<Utc> },
+    GiftCardDelivered { reward_id: Uuid, title: String, value: String },
+    /// A wishlisted template joined the pool of an active pack
+    WishlistInPack { template_id: Uuid, title: String, pack_type_id: Uuid, pack_name: String },
+    /// A happy hour boosting a wishlisted template's rarity started in a pack that can drop it
+    WishlistBoosted { template_id: Uuid, title: String, pack_type_id: Uuid, pack_name: String, ends_at: DateTime<Utc> },
+    /// A lapsed user was granted a pack to come back for
+    WelcomeBack { user_pack_id: Uuid, pack_type_id: Uuid, pack_name: String, expires_at: DateTime<Utc> },
+    /// Support sent a reward's code again, e.g. after the user lost the original message.
+    /// `code` is queued encrypted, as stored, and decrypted only for delivery.
+    RewardCodeResent { reward_id: Uuid, title: String, code: String, expires_at: Option<DateTime<Utc>> },
+}
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct NotificationPreferences {
+    pub push_enabled: bool,
+    pub email_enabled: bool,
+    pub reward_expiring: bool,
+    pub daily_pack_ready: bool,
+    pub streak_at_risk: bool,
+    pub daily_digest: bool,
+    pub wishlist_available: bool,
+}
+
+impl Default for NotificationPreferences {
+    fn default() -> Self {
+        Self {
+            push_enabled: true,
+            email_enabled: true,
+            reward_expiring: true,
+            daily_pack_ready: true,
+            streak_at_risk: true,
+            daily_digest: false,
+            wishlist_available: true,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateNotificationPreferencesRequest {
+    pub push_enabled: Option<bool>,
+    pub email_enabled: Option<bool>,
+    pub reward_expiring: Option<bool>,
+    pub daily_pack_ready: Option<bool>,
+    pub streak_at_risk: Option<bool>,
+    pub daily_digest: Option<bool>,
+    pub wishlist_available: Option<bool>,
+}
+
+#[derive(Serialize)]
+struct SendNotificationsRequest<'a> {
+    user_id: &'a str,
+    channels: Vec<&'static str>,
+    digest: bool,
+    events: Vec<Value>,
+    idempotency_key: String,
+}
+
+/// Thin client for the platform notification service
+pub struct NotificationClient {
+    http: HttpServiceClient,
+}
+
+impl NotificationClient {
+    pub fn new(base_url: String, timeout: std::time::Duration) -> Result<Self> {
+        Ok(Self { http: HttpServiceClient::new("notifications", base_url, timeout)? })
+    }
+
+    async fn send(&self, request: &SendNotificationsRequest<'_>) -> Result<()> {
+        let response = self.http.send(self.http.post("/notifications").json(request)).await?;
+
+        if !response.status().is_success() {
+            return Err(AppError::InternalError(format!(
+                "Notification service returned {}", response.status()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Queue an event for the next dispatch run. Event keys make this idempotent.
+pub async fn queue<'e, E: PgExecutor<'e>>(
+    executor: E,
+    user_id: &str,
+    event_key: &str,
+    event: &NotificationEvent,
+) -> Result<()> {
+    let payload = serde_json::to_value(event)
+        .map_err(|e| AppError::InternalError(format!("Failed to encode notification: {}", e)))?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO notification_events (user_id, event_key, payload)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (user_id, event_key) DO NOTHING
+        "#,
+        user_id,
+        event_key,
+        payload
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+pub struct NotificationService {
+    db: PgPool,
+    client: NotificationClient,
+    codes: Arc<CodeCipher>,
+}
+
+impl NotificationService {
+    pub fn new(db: PgPool, client: NotificationClient, codes: Arc<CodeCipher>) -> Self {
+        Self { db, client, codes }
+    }
+
+    /// Get the user's notification preferences, falling back to defaults
+    pub async fn get_preferences(&self, user_id: &str) -> Result<NotificationPreferences> {
+        let preferences = sqlx::query_as!(
+            NotificationPreferences,
+            r#"
+            SELECT push_enabled, email_enabled, reward_expiring, daily_pack_ready,
+                   streak_at_risk, daily_digest, wishlist_available
+            FROM notification_preferences
+            WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .fetch_optional(&self.db)
+        .await?;
+
+        Ok(preferences.unwrap_or_default())
+    }
+
+    /// Update any subset of the user's notification preferences
+    pub async fn update_preferences(
+        &self,
+        user_id: &str,
+        req: UpdateNotificationPreferencesRequest,
+    ) -> Result<NotificationPreferences> {
+        let current = self.get_preferences(user_id).await?;
+
+        let preferences = sqlx::query_as!(
+            NotificationPreferences,
+            r#"
+            INSERT INTO notification_preferences
+                (user_id, push_enabled, email_enabled, reward_expiring, daily_pack_ready,
+                 streak_at_risk, daily_digest, wishlist_available)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (user_id) DO UPDATE SET
+                push_enabled = EXCLUDED.push_enabled,
+                email_enabled = EXCLUDED.email_enabled,
+                reward_expiring = EXCLUDED.reward_expiring,
+                daily_pack_ready = EXCLUDED.daily_pack_ready,
+                streak_at_risk = EXCLUDED.streak_at_risk,
+                daily_digest = EXCLUDED.daily_digest,
+                wishlist_available = EXCLUDED.wishlist_available,
+                updated_at = NOW()
+            RETURNING push_enabled, email_enabled, reward_expiring, daily_pack_ready,
+                      streak_at_risk, daily_digest, wishlist_available
+            "#,
+            user_id,
+            req.push_enabled.unwrap_or(current.push_enabled),
+            req.email_enabled.unwrap_or(current.email_enabled),
+            req.reward_expiring.unwrap_or(current.reward_expiring),
+            req.daily_pack_ready.unwrap_or(current.daily_pack_ready),
+            req.streak_at_risk.unwrap_or(current.streak_at_risk),
+            req.daily_digest.unwrap_or(current.daily_digest),
+            req.wishlist_available.unwrap_or(current.wishlist_available)
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(preferences)
+    }
+
+    /// Find new events and deliver everything that is due. Meant to run every few
+    /// minutes from a background task; safe to run concurrently or repeatedly.
+    pub async fn dispatch_due(&self) -> Result<usize> {
+        self.collect_events().await?;
+        self.deliver_pending().await
+    }
+
+    /// Queue events for every user whose preferences allow them. Event keys make
+    /// re-scans idempotent.
+    async fn collect_events(&self) -> Result<()> {
+        let now = Utc::now();
+
+        let expiring = sqlx::query!(
+            r#"
+            SELECT ur.id, ur.user_id, ur.title, ur.expires_at AS "expires_at!"
+            FROM user_rewards ur
+            LEFT JOIN notification_preferences np ON np.user_id = ur.user_id
+            WHERE COALESCE(ur.is_used, false) = false
+              AND ur.expires_at > NOW() AND ur.expires_at <= NOW() + INTERVAL '24 hours'
+              AND COALESCE(np.reward_expiring, true)
+            "#
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        for reward in expiring {
+            let event = NotificationEvent::RewardExpiring {
+                reward_id: reward.id,
+                title: reward.title,
+                expires_at: reward.expires_at,
+            };
+            self.queue_event(&reward.user_id, &format!("reward_expiring:{}", reward.id), &event).await?;
+        }
+
+        // Streaks break once the window after the last claim closes; remind in the final 8 hours.
+        // Users on vacation aren't nudged to claim.
+        let claims = sqlx::query!(
+            r#"
+            SELECT uls.user_id, uls.last_daily_claim AS "last_daily_claim!", uls.daily_streak,
+                   udc.next_claim_at AS "next_claim_at!", udc.streak_until AS "streak_until!",
+                   COALESCE(np.daily_pack_ready, true) AS "daily_pack_ready!",
+                   COALESCE(np.streak_at_risk, true) AS "streak_at_risk!"
+            FROM user_lootpack_stats uls
+            JOIN user_daily_clock udc ON udc.user_id = uls.user_id
+            LEFT JOIN notification_preferences np ON np.user_id = uls.user_id
+            WHERE udc.next_claim_at <= NOW() AND udc.streak_until > NOW()
+              AND uls.last_daily_claim IS NOT NULL
+              AND NOT EXISTS (
+                  SELECT 1 FROM streak_vacations sv
+                  WHERE sv.user_id = uls.user_id AND sv.starts_at <= NOW() AND sv.ends_at > NOW()
+              )
+            "#
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        for claim in claims {
+            let claim_key = claim.last_daily_claim.timestamp();
+            if claim.daily_pack_ready {
+                let event = NotificationEvent::DailyPackReady {
+                    available_since: claim.next_claim_at,
+                };
+                self.queue_event(&claim.user_id, &format!("daily_pack_ready:{}", claim_key), &event).await?;
+            }
+
+            let breaks_at = claim.streak_until;
+            let streak = claim.daily_streak.unwrap_or(0);
+            if claim.streak_at_risk && streak >= 2 && breaks_at - now <= Duration::hours(8) {
+                let event = NotificationEvent::StreakAtRisk { streak, breaks_at };
+                self.queue_event(&claim.user_id, &format!("streak_at_risk:{}", claim_key), &event).await?;
+            }
+        }
+
+        // Happy hours are recomputed on every run; keying on the window end sends one
+        // event per user, template and window however often the scan runs
+        for window in happy_hours::active_happy_hours(&self.db, None).await? {
+            if window.multiplier <= 1.0 {
+                continue;
+            }
+            let wished = sqlx::query!(
+                r#"
+                SELECT wi.user_id, rt.id AS template_id, rt.title, pt.name AS pack_name
+                FROM wishlist_items wi
+                JOIN reward_templates rt ON rt.id = wi.template_id
+                JOIN pack_reward_mappings prm ON prm.reward_template_id = rt.id AND prm.pack_type_id = $1
+                JOIN pack_types pt ON pt.id = prm.pack_type_id
+                LEFT JOIN notification_preferences np ON np.user_id = wi.user_id
+                WHERE rt.rarity = $2 AND pt.is_active AND COALESCE(np.wishlist_available, true)
+                "#,
+                window.pack_type_id,
+                window.rarity
+            )
+            .fetch_all(&self.db)
+            .await?;
+
+            for wish in wished {
+                let event = NotificationEvent::WishlistBoosted {
+                    template_id: wish.template_id,
+                    title: wish.title,
+                    pack_type_id: window.pack_type_id,
+                    pack_name: wish.pack_name,
+                    ends_at: window.ends_at,
+                };
+                let key = format!(
+                    "wishlist_boosted:{}:{}:{}",
+                    wish.template_id, window.pack_type_id, window.ends_at.timestamp()
+                );
+                self.queue_event(&wish.user_id, &key, &event).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn queue_event(&self, user_id: &str, event_key: &str, event: &NotificationEvent) -> Result<()> {
+        queue(&self.db, user_id, event_key, event).await
+    }
+
+    /// Send unsent events. Digest users get at most one batch per 24 hours. Events are
+    /// leased before sending, so concurrent workers skip them. A batch is fixed once it
+    /// is first sent: a failed batch is retried with the same events and key, and events
+    /// queued meanwhile wait for the next run.
+    async fn deliver_pending(&self) -> Result<usize> {
+        let pending = sqlx::query!(
+            r#"
+            UPDATE notification_events ne
+            SET delivery_claimed_until = NOW() + make_interval(secs => $1)
+            FROM (
+                SELECT ne.id,
+                       COALESCE(np.push_enabled, true) AS push_enabled,
+                       COALESCE(np.email_enabled, true) AS email_enabled,
+                       COALESCE(np.daily_digest, false) AS daily_digest
+                FROM notification_events ne
+                LEFT JOIN notification_preferences np ON np.user_id = ne.user_id
+                WHERE ne.sent_at IS NULL
+                  AND (ne.delivery_claimed_until IS NULL OR ne.delivery_claimed_until <= NOW())
+                  AND (COALESCE(np.daily_digest, false) = false OR NOT EXISTS (
+                      SELECT 1 FROM notification_events sent
+                      WHERE sent.user_id = ne.user_id AND sent.sent_at > NOW() - INTERVAL '24 hours'
+                  ))
+                FOR UPDATE OF ne SKIP LOCKED
+            ) due
+            WHERE ne.id = due.id
+            RETURNING ne.id, ne.user_id, ne.payload, ne.created_at, ne.delivery_batch_id,
+                      due.push_enabled AS "push_enabled!", due.email_enabled AS "email_enabled!",
+                      due.daily_digest AS "daily_digest!"
+            "#,
+            DELIVERY_LEASE_SECONDS
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        let mut by_user: HashMap<String, Vec<_>> = HashMap::new();
+        for row in pending {
+            by_user.entry(row.user_id.clone()).or_default().push(row);
+        }
+
+        let mut delivered = 0;
+        for (user_id, mut rows) in by_user {
+            rows.sort_by_key(|r| r.created_at);
+
+            // Finish a batch that was already attempted before starting a new one
+            let batch_id = match rows.iter().find_map(|r| r.delivery_batch_id) {
+                Some(batch_id) => {
+                    let (batch, waiting): (Vec<_>, Vec<_>) =
+                        rows.into_iter().partition(|r| r.delivery_batch_id == Some(batch_id));
+                    let waiting: Vec<Uuid> = waiting.iter().map(|r| r.id).collect();
+                    sqlx::query!(
+                        "UPDATE notification_events SET delivery_claimed_until = NULL WHERE id = ANY($1)",
+                        &waiting
+                    )
+                    .execute(&self.db)
+                    .await?;
+                    rows = batch;
+                    batch_id
+                }
+                None => Uuid::new_v4(),
+            };
+            let first = &rows[0];
+            let event_ids: Vec<Uuid> = rows.iter().map(|r| r.id).collect();
+
+            let mut channels = Vec::new();
+            if first.push_enabled {
+                channels.push("push");
+            }
+            if first.email_enabled {
+                channels.push("email");
+            }
+
+            // Users with every channel off still have their events marked as handled
+            if !channels.is_empty() {
+                let events = match rows.iter().map(|r| self.reveal_code(r.payload.clone())).collect::<Result<Vec<_>>>() {
+                    Ok(events) => events,
+                    Err(e) => {
+                        warn!("Failed to decrypt codes in notifications for {}: {:?}", privacy::pseudonym(&user_id), e);
+                        continue;
+                    }
+                };
+
+                // Fix the batch's contents before the first send, so a retry replays exactly these events
+                sqlx::query!(
+                    "UPDATE notification_events SET delivery_batch_id = $1 WHERE id = ANY($2)",
+                    batch_id,
+                    &event_ids
+                )
+                .execute(&self.db)
+                .await?;
+
+                let request = SendNotificationsRequest {
+                    user_id: &user_id,
+                    channels,
+                    digest: first.daily_digest,
+                    events,
+                    idempotency_key: format!("lootpacks-{}", batch_id),
+                };
+
+                if let Err(e) = self.client.send(&request).await {
+                    warn!("Failed to send {} notifications to {}: {:?}", event_ids.len(), privacy::pseudonym(&user_id), e);
+                    continue;
+                }
+                delivered += event_ids.len();
+            }
+
+            sqlx::query!(
+                "UPDATE notification_events SET sent_at = NOW(), delivery_claimed_until = NULL WHERE id = ANY($1)",
+                &event_ids
+            )
+            .execute(&self.db)
+            .await?;
+        }
+
+        if delivered > 0 {
+            info!("Delivered {} lootpack notifications", delivered);
+        }
+        Ok(delivered)
+    }
+
+    /// Decrypt the reward code an event carries, if any
+    fn reveal_code(&self, mut payload: Value) -> Result<Value> {
+        if let Some(code) = payload.get_mut("code") {
+            if let Some(stored) = code.as_str() {
+                *code = Value::String(self.codes.reveal(stored)?);
+            }
+        }
+        Ok(payload)
+    }
+}
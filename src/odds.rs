@@ -0,0 +1,119 @@
+use crate::models::lootpacks::RewardPool;
+use crate::error::Result;
+use async_trait::async_trait;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Guardrail: no adjustment may move a weight by more than this factor either way
+pub const MAX_ODDS_DEVIATION: f64 = 2.0;
+
+/// The multiplier actually applied for a configured one, within the deviation guardrail.
+/// A multiplier that isn't a positive number is ignored.
+pub fn clamp_multiplier(multiplier: f64) -> f64 {
+    if !multiplier.is_finite() || multiplier <= 0.0 {
+        return 1.0;
+    }
+    multiplier.clamp(1.0 / MAX_ODDS_DEVIATION, MAX_ODDS_DEVIATION)
+}
+
+/// Per-rarity weight multipliers for one user and pack
+#[derive(Debug, Clone, Default)]
+pub struct OddsAdjustment {
+    pub rarity_multipliers: HashMap<String, f64>,
+}
+
+impl OddsAdjustment {
+    pub fn is_neutral(&self) -> bool {
+        self.rarity_multipliers.values().all(|m| (*m - 1.0).abs() < f64::EPSILON)
+    }
+
+    /// Reweight a pool, clamping every multiplier to the deviation guardrail
+    pub fn apply(&self, pool: RewardPool) -> RewardPool {
+        if self.is_neutral() {
+            return pool;
+        }
+
+        RewardPool::from_weights(pool.rewards.into_iter().map(|w| {
+            let multiplier = clamp_multiplier(self.rarity_multipliers.get(&w.template.rarity).copied().unwrap_or(1.0));
+            // A template weighted out of the pool stays out; any other keeps a chance
+            let weight = if w.weight > 0 { ((w.weight as f64) * multiplier).round().max(1.0) as i32 } else { w.weight };
+            (w.template, weight)
+        }))
+    }
+}
+
+/// Hold every weight of `adjusted` within `MAX_ODDS_DEVIATION` of its weight in `base`.
+/// Per-user adjustments compound, so the guardrail applies to their combined effect.
+/// Weights an adjustment took to zero stay at zero.
+pub fn within_guardrail(base: &RewardPool, adjusted: RewardPool) -> RewardPool {
+    let base_weights: HashMap<Uuid, i32> = base.rewards.iter().map(|w| (w.template.id, w.weight)).collect();
+    RewardPool::from_weights(adjusted.rewards.into_iter().map(|w| {
+        let weight = match base_weights.get(&w.template.id) {
+            // A non-positive base weight would invert the clamp bounds; nothing to deal there
+            Some(&base_weight) if base_weight <= 0 => 0,
+            Some(&base_weight) if w.weight > 0 => {
+                let base_weight = base_weight as f64;
+                (w.weight as f64)
+                    .clamp(base_weight / MAX_ODDS_DEVIATION, base_weight * MAX_ODDS_DEVIATION)
+                    .round()
+                    .max(1.0) as i32
+            }
+            _ => w.weight,
+        };
+        (w.template, weight)
+    }))
+}
+
+/// Source of drop-weight adjustments, e.g. from the data science team's segment models
+#[async_trait]
+pub trait OddsProvider: Send + Sync {
+    async fn adjustment(&self, user_id: &str, pack_type_id: Uuid) -> Result<OddsAdjustment>;
+}
+
+/// Default provider: configured weights are used as-is
+pub struct StaticOddsProvider;
+
+#[async_trait]
+impl OddsProvider for StaticOddsProvider {
+    async fn adjustment(&self, _user_id: &str, _pack_type_id: Uuid) -> Result<OddsAdjustment> {
+        Ok(OddsAdjustment::default())
+    }
+}
+
+/// Reads per-segment rarity multipliers from `segment_odds_multipliers`.
+/// When a user is in several segments the multipliers compound.
+pub struct SegmentOddsProvider {
+    db: PgPool,
+}
+
+impl SegmentOddsProvider {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl OddsProvider for SegmentOddsProvider {
+    async fn adjustment(&self, user_id: &str, pack_type_id: Uuid) -> Result<OddsAdjustment> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT som.rarity, som.multiplier
+            FROM user_segments us
+            JOIN segment_odds_multipliers som ON som.segment = us.segment
+            WHERE us.user_id = $1 AND (som.pack_type_id IS NULL OR som.pack_type_id = $2)
+            "#,
+            user_id,
+            pack_type_id
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        let mut rarity_multipliers: HashMap<String, f64> = HashMap::new();
+        for row in rows {
+            *rarity_multipliers.entry(row.rarity).or_insert(1.0) *= row.multiplier;
+        }
+
+        Ok(OddsAdjustment { rarity_multipliers })
+    }
+}
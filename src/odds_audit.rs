@@ -0,0 +1,299 @@
+use crate::models::lootpacks::PackType;
+use crate::error::Result;
+use crate::audit;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use serde_json::json;
+use sqlx::{FromRow, PgPool};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// Rewards dealt per pack needed before a window is tested; smaller samples are too noisy
+const MIN_SAMPLES: i64 = 500;
+
+/// One-sided z-score for the alert threshold (p ~ 0.001)
+const ALERT_Z: f64 = 3.09;
+
+/// Templates reported in each result
+const TOP_DEVIATIONS: usize = 5;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TemplateDeviation {
+    pub template_id: Uuid,
+    pub observed: i64,
+    pub expected: f64,
+}
+
+#[derive(Debug, FromRow, Serialize)]
+pub struct OddsAuditResult {
+    pub id: Uuid,
+    pub pack_type_id: Uuid,
+    pub window_start: DateTime<Utc>,
+    pub window_end: DateTime<Utc>,
+    pub opens: i64,
+    pub samples: i64,
+    pub chi_squared: f64,
+    pub degrees_of_freedom: i32,
+    pub critical_value: f64,
+    pub drifted: bool,
+    pub top_deviations: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Periodic chi-squared test of dealt rewards against the odds they were drawn from.
+///
+/// Each random open stores the weights it drew from, after segment odds, personalization
+/// and happy hours, so the test compares drops with the adjusted odds rather than the
+/// configured ones. What it catches is selection bugs and bad seeds. Opens without stored
+/// odds (chosen rewards, mystery picks, and opens from before odds were stored) aren't tested.
+pub struct OddsAuditJob {
+    db: PgPool,
+    window: Duration,
+}
+
+impl OddsAuditJob {
+    pub fn new(db: PgPool, window: Duration) -> Self {
+        Self { db, window }
+    }
+
+    /// Audit every pack once per window
+    pub fn spawn(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let period = self.window.to_std().unwrap_or(std::time::Duration::from_secs(24 * 60 * 60));
+            let mut interval = tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.run().await {
+                    error!("Odds audit failed: {:?}", e);
+                }
+            }
+        })
+    }
+
+    /// Audit every active pack over the trailing window. Returns the results recorded.
+    pub async fn run(&self) -> Result<Vec<OddsAuditResult>> {
+        let window_end = Utc::now();
+        let window_start = window_end - self.window;
+
+        let packs = sqlx::query_as!(
+            PackType,
+            r#"
+            SELECT id, name, type, description, icon, color_gradient,
+                   price_coins, cooldown_hours, min_rewards, max_rewards,
+                   possible_reward_types, is_active, created_at, updated_at
+            FROM pack_types
+            WHERE is_active = true
+            "#
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        let mut results = Vec::new();
+        for pack in packs {
+            if let Some(result) = self.audit_pack(&pack, window_start, window_end).await? {
+                results.push(result);
+            }
+        }
+
+        info!("Odds audit checked {} packs, {} drifted",
+              results.len(), results.iter().filter(|r| r.drifted).count());
+        Ok(results)
+    }
+
+    async fn audit_pack(
+        &self,
+        pack: &PackType,
+        window_start: DateTime<Utc>,
+        window_end: DateTime<Utc>,
+    ) -> Result<Option<OddsAuditResult>> {
+        // Only opens that recorded their odds are tested
+        let opens = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!" FROM user_pack_history
+            WHERE pack_type_id = $1 AND opened_at >= $2 AND opened_at < $3 AND draw_odds IS NOT NULL
+            "#,
+            pack.id,
+            window_start,
+            window_end
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        let observed: HashMap<Uuid, i64> = sqlx::query!(
+            r#"
+            SELECT ur.template_id AS "template_id!", COUNT(*) AS "count!"
+            FROM user_rewards ur
+            JOIN user_pack_history uph ON uph.id = ur.pack_history_id
+            WHERE uph.pack_type_id = $1 AND uph.opened_at >= $2 AND uph.opened_at < $3
+              AND uph.draw_odds IS NOT NULL AND ur.template_id IS NOT NULL
+            GROUP BY ur.template_id
+            "#,
+            pack.id,
+            window_start,
+            window_end
+        )
+        .fetch_all(&self.db)
+        .await?
+        .into_iter()
+        .map(|r| (r.template_id, r.count))
+        .collect();
+
+        let samples: i64 = observed.values().sum();
+        if samples < MIN_SAMPLES {
+            return Ok(None);
+        }
+
+        // Expected drops summed over opens, each under the odds it drew from: one slot
+        // uniformly from the guarantee candidates when there are any, the rest by weight
+        let expected: HashMap<Uuid, f64> = sqlx::query!(
+            r#"
+            WITH opens AS (
+                SELECT uph.draw_odds,
+                       jsonb_array_length(uph.draw_odds->'guaranteed') AS candidates,
+                       GREATEST(COUNT(ur.id) - CASE WHEN jsonb_array_length(uph.draw_odds->'guaranteed') > 0 THEN 1 ELSE 0 END, 0)
+                           AS weighted_slots,
+                       (SELECT SUM(w.value::float8) FROM jsonb_each_text(uph.draw_odds->'weights') w) AS total_weight
+                FROM user_pack_history uph
+                JOIN user_rewards ur ON ur.pack_history_id = uph.id AND ur.template_id IS NOT NULL
+                WHERE uph.pack_type_id = $1 AND uph.opened_at >= $2 AND uph.opened_at < $3
+                  AND uph.draw_odds IS NOT NULL
+                GROUP BY uph.id
+            )
+            SELECT shares.template_id AS "template_id!", SUM(shares.expected) AS "expected!"
+            FROM (
+                SELECT w.key::uuid AS template_id, o.weighted_slots * w.value::float8 / o.total_weight AS expected
+                FROM opens o
+                CROSS JOIN LATERAL jsonb_each_text(o.draw_odds->'weights') w
+                WHERE o.total_weight > 0
+                UNION ALL
+                SELECT g.value::uuid, 1.0::float8 / o.candidates
+                FROM opens o
+                CROSS JOIN LATERAL jsonb_array_elements_text(o.draw_odds->'guaranteed') g
+                WHERE o.candidates > 0
+            ) shares
+            GROUP BY shares.template_id
+            "#,
+            pack.id,
+            window_start,
+            window_end
+        )
+        .fetch_all(&self.db)
+        .await?
+        .into_iter()
+        .map(|r| (r.template_id, r.expected))
+        .collect();
+
+        if expected.len() < 2 {
+            return Ok(None);
+        }
+
+        let mut deviations: Vec<TemplateDeviation> = expected
+            .iter()
+            .map(|(template_id, expected)| TemplateDeviation {
+                template_id: *template_id,
+                observed: observed.get(template_id).copied().unwrap_or(0),
+                expected: *expected,
+            })
+            .collect();
+
+        let chi_squared: f64 = deviations
+            .iter()
+            .filter(|d| d.expected > 0.0)
+            .map(|d| (d.observed as f64 - d.expected).powi(2) / d.expected)
+            .sum();
+        // Drops from templates that weren't in the odds they were drawn from are drift by definition
+        let unexpected: i64 = observed
+            .iter()
+            .filter(|(id, _)| !expected.contains_key(*id))
+            .map(|(_, count)| *count)
+            .sum();
+
+        let degrees_of_freedom = deviations.len() as i32 - 1;
+        let critical_value = chi_squared_critical(degrees_of_freedom as f64, ALERT_Z);
+        let drifted = chi_squared > critical_value || unexpected > 0;
+
+        deviations.sort_by(|a, b| {
+            let gap = |d: &TemplateDeviation| (d.observed as f64 - d.expected).abs();
+            gap(b).partial_cmp(&gap(a)).unwrap_or(std::cmp::Ordering::Equal)
+        });
+        deviations.truncate(TOP_DEVIATIONS);
+        let top_deviations = serde_json::to_value(&deviations).unwrap_or_default();
+
+        let mut tx = self.db.begin().await?;
+
+        let result = sqlx::query_as!(
+            OddsAuditResult,
+            r#"
+            INSERT INTO odds_audit_results
+                (pack_type_id, window_start, window_end, opens, samples, chi_squared,
+                 degrees_of_freedom, critical_value, drifted, top_deviations)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            RETURNING id, pack_type_id, window_start, window_end, opens, samples, chi_squared,
+                      degrees_of_freedom, critical_value, drifted, top_deviations, created_at
+            "#,
+            pack.id,
+            window_start,
+            window_end,
+            opens,
+            samples,
+            chi_squared,
+            degrees_of_freedom,
+            critical_value,
+            drifted,
+            top_deviations
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if drifted {
+            warn!("Odds drift detected for pack {}: chi2={:.1} > {:.1} (df={}), {} unexpected drops",
+                  pack.name, chi_squared, critical_value, degrees_of_freedom, unexpected);
+            audit::record(
+                &mut *tx,
+                "system",
+                "odds.drift_detected",
+                Some(&pack.id.to_string()),
+                json!({
+                    "audit_result_id": result.id,
+                    "chi_squared": chi_squared,
+                    "critical_value": critical_value,
+                    "unexpected_drops": unexpected,
+                }),
+            )
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(Some(result))
+    }
+}
+
+/// Latest audit results, newest first, for the admin dashboard
+pub async fn recent_results(db: &PgPool, pack_type_id: Option<Uuid>, limit: i64) -> Result<Vec<OddsAuditResult>> {
+    let results = sqlx::query_as!(
+        OddsAuditResult,
+        r#"
+        SELECT id, pack_type_id, window_start, window_end, opens, samples, chi_squared,
+               degrees_of_freedom, critical_value, drifted, top_deviations, created_at
+        FROM odds_audit_results
+        WHERE ($1::uuid IS NULL OR pack_type_id = $1)
+        ORDER BY created_at DESC
+        LIMIT $2
+        "#,
+        pack_type_id,
+        limit.clamp(1, 200)
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(results)
+}
+
+/// Wilson-Hilferty approximation of the chi-squared quantile at a one-sided z-score
+fn chi_squared_critical(degrees_of_freedom: f64, z: f64) -> f64 {
+    let k = degrees_of_freedom.max(1.0);
+    let term = 2.0 / (9.0 * k);
+    k * (1.0 - term + z * term.sqrt()).powi(3)
+}
@@ -0,0 +1,328 @@
+use crate::error::{AppError, Result};
+use crate::audit;
+use crate::lootpacks::{LootpackService, CACHE_FLUSH_CHANNEL};
+use crate::reveal;
+use crate::wishlist;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::{FromRow, PgConnection, PgPool};
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MappingConfig {
+    pub reward_template_id: Uuid,
+    pub weight: i32,
+}
+
+/// Partial update of a pack's configuration; omitted fields are left unchanged
+#[derive(Debug, Deserialize)]
+pub struct UpdatePackRequest {
+    pub price_coins: Option<i32>,
+    pub cooldown_hours: Option<i32>,
+    pub min_rewards: Option<i32>,
+    pub max_rewards: Option<i32>,
+    pub is_active: Option<bool>,
+    /// dealt, best_last or ascending
+    pub reveal_order: Option<String>,
+    /// Replaces the pack's reward mappings
+    pub mappings: Option<Vec<MappingConfig>>,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RollbackRequest {
+    pub version: i32,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, FromRow, Serialize)]
+pub struct PackVersion {
+    pub version: i32,
+    pub snapshot: Value,
+    pub created_by: String,
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Admin pack management. Every change stores a versioned snapshot of the pack's
+/// full configuration so any earlier version can be restored in one step.
+pub struct PackAdminService {
+    db: PgPool,
+    lootpacks: Arc<LootpackService>,
+}
+
+impl PackAdminService {
+    pub fn new(db: PgPool, lootpacks: Arc<LootpackService>) -> Self {
+        Self { db, lootpacks }
+    }
+
+    pub async fn list_versions(&self, pack_type_id: Uuid) -> Result<Vec<PackVersion>> {
+        let versions = sqlx::query_as!(
+            PackVersion,
+            r#"
+            SELECT version, snapshot, created_by, note, created_at
+            FROM pack_type_versions
+            WHERE pack_type_id = $1
+            ORDER BY version DESC
+            "#,
+            pack_type_id
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(versions)
+    }
+
+    /// Apply a configuration change and snapshot the result
+    pub async fn update_pack(&self, actor: &str, pack_type_id: Uuid, req: UpdatePackRequest) -> Result<PackVersion> {
+        let mut tx = self.db.begin().await?;
+        let version = self.update_pack_in(&mut tx, actor, pack_type_id, req).await?;
+        tx.commit().await?;
+        self.lootpacks.invalidate_pack_cache(pack_type_id).await;
+        Ok(version)
+    }
+
+    /// `update_pack` inside the caller's transaction, for changes that must land together
+    /// with the caller's own writes. The caller drops the pack's cache once it commits.
+    pub(crate) async fn update_pack_in(
+        &self,
+        conn: &mut PgConnection,
+        actor: &str,
+        pack_type_id: Uuid,
+        req: UpdatePackRequest,
+    ) -> Result<PackVersion> {
+        if let Some(order) = &req.reveal_order {
+            reveal::validate_order(order)?;
+        }
+
+        lock_pack(&mut *conn, pack_type_id, actor).await?;
+        // Keep a baseline so the very first change can be rolled back
+        ensure_baseline(&mut *conn, pack_type_id).await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE pack_types SET
+                price_coins = COALESCE($2, price_coins),
+                cooldown_hours = COALESCE($3, cooldown_hours),
+                min_rewards = COALESCE($4, min_rewards),
+                max_rewards = COALESCE($5, max_rewards),
+                is_active = COALESCE($6, is_active),
+                reveal_order = COALESCE($7, reveal_order),
+                updated_at = NOW()
+            WHERE id = $1
+            "#,
+            pack_type_id,
+            req.price_coins,
+            req.cooldown_hours,
+            req.min_rewards,
+            req.max_rewards,
+            req.is_active,
+            req.reveal_order
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        if let Some(mappings) = &req.mappings {
+            if mappings.iter().any(|m| m.weight <= 0) {
+                return Err(AppError::BadRequest("Mapping weights must be positive".to_string()));
+            }
+            let mappings = serde_json::to_value(mappings)
+                .map_err(|e| AppError::InternalError(format!("Failed to encode mappings: {}", e)))?;
+            replace_mappings(&mut *conn, pack_type_id, &mappings).await?;
+        }
+
+        let version = snapshot(&mut *conn, pack_type_id, actor, req.note.as_deref()).await?;
+        audit::record(
+            &mut *conn,
+            actor,
+            "pack.updated",
+            Some(&pack_type_id.to_string()),
+            json!({ "version": version.version }),
+        )
+        .await?;
+        notify_cache_flush(&mut *conn).await?;
+
+        Ok(version)
+    }
+
+    /// Drop the pack's cached pool after a change committed through `update_pack_in`
+    pub(crate) async fn invalidate_pack_cache(&self, pack_type_id: Uuid) {
+        self.lootpacks.invalidate_pack_cache(pack_type_id).await;
+    }
+
+    /// Restore a previous version's pack settings and reward mappings atomically.
+    /// The restore itself is recorded as a new version.
+    pub async fn rollback(&self, actor: &str, pack_type_id: Uuid, req: RollbackRequest) -> Result<PackVersion> {
+        let mut tx = self.db.begin().await?;
+        lock_pack(&mut tx, pack_type_id, actor).await?;
+
+        let target = sqlx::query_scalar!(
+            "SELECT snapshot FROM pack_type_versions WHERE pack_type_id = $1 AND version = $2",
+            pack_type_id,
+            req.version
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Pack version not found".to_string()))?;
+
+        sqlx::query!(
+            r#"
+            UPDATE pack_types pt SET
+                name = r.name, description = r.description, icon = r.icon,
+                color_gradient = r.color_gradient, price_coins = r.price_coins,
+                cooldown_hours = r.cooldown_hours, min_rewards = r.min_rewards,
+                max_rewards = r.max_rewards, possible_reward_types = r.possible_reward_types,
+                is_active = r.is_active, updated_at = NOW(),
+                -- Snapshots older than icon uploads / markets / reveal orders keep the current values
+                icon_asset_id = CASE WHEN $2 ? 'icon_asset_id' THEN r.icon_asset_id ELSE pt.icon_asset_id END,
+                markets = CASE WHEN $2 ? 'markets' THEN r.markets ELSE pt.markets END,
+                reveal_order = CASE WHEN $2 ? 'reveal_order' THEN r.reveal_order ELSE pt.reveal_order END
+            FROM jsonb_populate_record(NULL::pack_types, $2) r
+            WHERE pt.id = $1
+            "#,
+            pack_type_id,
+            target["pack"]
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        replace_mappings(&mut tx, pack_type_id, &target["mappings"]).await?;
+
+        let note = req.note.unwrap_or_else(|| format!("Rollback to version {}", req.version));
+        let version = snapshot(&mut tx, pack_type_id, actor, Some(&note)).await?;
+        audit::record(
+            &mut *tx,
+            actor,
+            "pack.rolled_back",
+            Some(&pack_type_id.to_string()),
+            json!({ "restored_version": req.version, "new_version": version.version }),
+        )
+        .await?;
+        notify_cache_flush(&mut tx).await?;
+
+        tx.commit().await?;
+        self.lootpacks.invalidate_pack_cache(pack_type_id).await;
+        Ok(version)
+    }
+}
+
+/// Have every other instance drop its cached pools once the change commits; this
+/// instance drops its own right after the commit
+async fn notify_cache_flush(conn: &mut PgConnection) -> Result<()> {
+    sqlx::query!("SELECT pg_notify($1, '')", CACHE_FLUSH_CHANNEL)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// Lock the pack row so concurrent edits get sequential versions, and attribute
+/// trigger-recorded mapping history to the admin
+pub(crate) async fn lock_pack(conn: &mut PgConnection, pack_type_id: Uuid, actor: &str) -> Result<()> {
+    sqlx::query_scalar!("SELECT id FROM pack_types WHERE id = $1 FOR UPDATE", pack_type_id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Pack type not found".to_string()))?;
+
+    sqlx::query!("SELECT set_config('app.actor', $1, true)", actor)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+pub(crate) async fn ensure_baseline(conn: &mut PgConnection, pack_type_id: Uuid) -> Result<()> {
+    let exists = sqlx::query_scalar!(
+        r#"SELECT EXISTS (SELECT 1 FROM pack_type_versions WHERE pack_type_id = $1) AS "exists!""#,
+        pack_type_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    if !exists {
+        snapshot(conn, pack_type_id, "system", Some("Baseline")).await?;
+    }
+    Ok(())
+}
+
+/// Make the pack's mappings exactly `mappings` (a JSON array of MappingConfig).
+/// Users who wishlisted a template that joins the pool are notified.
+pub(crate) async fn replace_mappings(conn: &mut PgConnection, pack_type_id: Uuid, mappings: &Value) -> Result<()> {
+    let added = sqlx::query_scalar!(
+        r#"
+        SELECT m.reward_template_id AS "reward_template_id!"
+        FROM jsonb_to_recordset($2) AS m(reward_template_id uuid, weight int)
+        WHERE COALESCE(m.weight, 1) > 0
+          AND NOT EXISTS (
+              SELECT 1 FROM pack_reward_mappings prm
+              WHERE prm.pack_type_id = $1 AND prm.reward_template_id = m.reward_template_id AND COALESCE(prm.weight, 1) > 0
+          )
+        "#,
+        pack_type_id,
+        mappings
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        DELETE FROM pack_reward_mappings
+        WHERE pack_type_id = $1
+          AND reward_template_id NOT IN (
+              SELECT m.reward_template_id FROM jsonb_to_recordset($2) AS m(reward_template_id uuid, weight int)
+          )
+        "#,
+        pack_type_id,
+        mappings
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO pack_reward_mappings (pack_type_id, reward_template_id, weight)
+        SELECT $1, m.reward_template_id, m.weight
+        FROM jsonb_to_recordset($2) AS m(reward_template_id uuid, weight int)
+        ON CONFLICT (pack_type_id, reward_template_id) DO UPDATE SET weight = EXCLUDED.weight
+        "#,
+        pack_type_id,
+        mappings
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    wishlist::notify_pool_additions(conn, pack_type_id, &added).await?;
+    Ok(())
+}
+
+/// Store the pack's current configuration as the next version
+pub(crate) async fn snapshot(conn: &mut PgConnection, pack_type_id: Uuid, actor: &str, note: Option<&str>) -> Result<PackVersion> {
+    let version = sqlx::query_as!(
+        PackVersion,
+        r#"
+        INSERT INTO pack_type_versions (pack_type_id, version, snapshot, created_by, note)
+        SELECT pt.id,
+               COALESCE((SELECT MAX(version) FROM pack_type_versions WHERE pack_type_id = pt.id), 0) + 1,
+               jsonb_build_object(
+                   'pack', to_jsonb(pt) - 'created_at' - 'updated_at',
+                   'mappings', COALESCE((
+                       SELECT jsonb_agg(jsonb_build_object('reward_template_id', prm.reward_template_id,
+                                                           'weight', prm.weight)
+                                        ORDER BY prm.reward_template_id)
+                       FROM pack_reward_mappings prm WHERE prm.pack_type_id = pt.id
+                   ), '[]'::jsonb)
+               ),
+               $2, $3
+        FROM pack_types pt
+        WHERE pt.id = $1
+        RETURNING version, snapshot, created_by, note, created_at
+        "#,
+        pack_type_id,
+        actor,
+        note
+    )
+    .fetch_one(conn)
+    .await?;
+
+    Ok(version)
+}
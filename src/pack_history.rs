@@ -0,0 +1,146 @@
+use crate::models::lootpacks::UserReward;
+use crate::code_crypto::CodeCipher;
+use crate::error::{AppError, Result};
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+const DEFAULT_PAGE_SIZE: i64 = 20;
+const MAX_PAGE_SIZE: i64 = 100;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct PackHistoryQuery {
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+    pub pack_type_id: Option<Uuid>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PackHistoryEntry {
+    pub id: Uuid,
+    pub pack_type_id: Uuid,
+    pub pack_name: String,
+    pub opened_at: DateTime<Utc>,
+    pub rewards_count: i32,
+    pub rewards: Vec<UserReward>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PackHistoryResponse {
+    pub entries: Vec<PackHistoryEntry>,
+    pub next_cursor: Option<String>,
+}
+
+pub struct PackHistoryService {
+    db: PgPool,
+    codes: Arc<CodeCipher>,
+}
+
+impl PackHistoryService {
+    pub fn new(db: PgPool, codes: Arc<CodeCipher>) -> Self {
+        Self { db, codes }
+    }
+
+    /// Get a page of the user's pack openings, newest first, with the rewards from each
+    pub async fn get_pack_history(&self, user_id: &str, query: &PackHistoryQuery) -> Result<PackHistoryResponse> {
+        let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+        let (cursor_at, cursor_id) = match query.cursor.as_deref() {
+            Some(cursor) => {
+                let (at, id) = decode_cursor(cursor)?;
+                (Some(at), Some(id))
+            }
+            None => (None, None),
+        };
+
+        // Fetch one extra row to know whether another page exists
+        let mut rows = sqlx::query!(
+            r#"
+            SELECT uph.id, uph.pack_type_id, pt.name AS pack_name,
+                   uph.opened_at AS "opened_at!", uph.rewards_count
+            FROM user_pack_history uph
+            JOIN pack_types pt ON pt.id = uph.pack_type_id
+            WHERE uph.user_id = $1
+              AND ($2::uuid IS NULL OR uph.pack_type_id = $2)
+              AND ($3::timestamptz IS NULL OR uph.opened_at >= $3)
+              AND ($4::timestamptz IS NULL OR uph.opened_at < $4)
+              AND ($5::timestamptz IS NULL OR (uph.opened_at, uph.id) < ($5, $6::uuid))
+            ORDER BY uph.opened_at DESC, uph.id DESC
+            LIMIT $7
+            "#,
+            user_id,
+            query.pack_type_id,
+            query.from,
+            query.to,
+            cursor_at,
+            cursor_id,
+            limit + 1
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        let has_more = rows.len() as i64 > limit;
+        rows.truncate(limit as usize);
+
+        let history_ids: Vec<Uuid> = rows.iter().map(|r| r.id).collect();
+        let mut rewards = sqlx::query_as!(
+            UserReward,
+            r#"
+            SELECT id, user_id, pack_history_id, template_id, type, title, value,
+                   description, code, rarity, source, expires_at, is_used, used_at, created_at
+            FROM user_rewards
+            WHERE pack_history_id = ANY($1)
+            ORDER BY created_at
+            "#,
+            &history_ids
+        )
+        .fetch_all(&self.db)
+        .await?;
+        self.codes.reveal_rewards(&mut rewards)?;
+
+        let mut rewards_by_history: HashMap<Uuid, Vec<UserReward>> = HashMap::new();
+        for reward in rewards {
+            if let Some(history_id) = reward.pack_history_id {
+                rewards_by_history.entry(history_id).or_default().push(reward);
+            }
+        }
+
+        let next_cursor = if has_more {
+            rows.last().map(|r| encode_cursor(r.opened_at, r.id))
+        } else {
+            None
+        };
+
+        let entries = rows
+            .into_iter()
+            .map(|r| PackHistoryEntry {
+                id: r.id,
+                pack_type_id: r.pack_type_id,
+                pack_name: r.pack_name,
+                opened_at: r.opened_at,
+                rewards_count: r.rewards_count,
+                rewards: rewards_by_history.remove(&r.id).unwrap_or_default(),
+            })
+            .collect();
+
+        Ok(PackHistoryResponse { entries, next_cursor })
+    }
+}
+
+/// Cursors are `<opened_at micros>_<history id>` so ties on timestamp stay stable
+fn encode_cursor(opened_at: DateTime<Utc>, id: Uuid) -> String {
+    format!("{}_{}", opened_at.timestamp_micros(), id)
+}
+
+fn decode_cursor(cursor: &str) -> Result<(DateTime<Utc>, Uuid)> {
+    let invalid = || AppError::BadRequest("Invalid cursor".to_string());
+    let (micros, id) = cursor.split_once('_').ok_or_else(invalid)?;
+    let micros: i64 = micros.parse().map_err(|_| invalid())?;
+    let opened_at = Utc.timestamp_micros(micros).single().ok_or_else(invalid)?;
+    let id = Uuid::parse_str(id).map_err(|_| invalid())?;
+    Ok((opened_at, id))
+}
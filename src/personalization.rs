@@ -0,0 +1,100 @@
+use crate::error::{AppError, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+#[derive(Debug, Deserialize)]
+pub struct CategoryScore {
+    pub category: String,
+    pub score: f64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdatePersonalizationRequest {
+    pub opt_out: Option<bool>,
+    /// Replaces the user's category affinities, e.g. from the profile service sync
+    pub categories: Option<Vec<CategoryScore>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PersonalizationSettings {
+    pub opt_out: bool,
+    pub top_categories: Vec<String>,
+}
+
+pub struct PersonalizationService {
+    db: PgPool,
+}
+
+impl PersonalizationService {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    /// Get the user's opt-out flag and strongest categories
+    pub async fn get_settings(&self, user_id: &str) -> Result<PersonalizationSettings> {
+        let opt_out = sqlx::query_scalar!(
+            "SELECT personalization_opt_out FROM user_lootpack_stats WHERE user_id = $1",
+            user_id
+        )
+        .fetch_optional(&self.db)
+        .await?
+        .unwrap_or(false);
+
+        let top_categories = sqlx::query_scalar!(
+            r#"
+            SELECT category FROM user_category_preferences
+            WHERE user_id = $1 AND score > 0
+            ORDER BY score DESC
+            LIMIT 3
+            "#,
+            user_id
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(PersonalizationSettings { opt_out, top_categories })
+    }
+
+    /// Update opt-out and/or replace category affinities
+    pub async fn update_settings(&self, user_id: &str, req: UpdatePersonalizationRequest) -> Result<PersonalizationSettings> {
+        let mut tx = self.db.begin().await?;
+
+        if let Some(opt_out) = req.opt_out {
+            let updated = sqlx::query!(
+                "UPDATE user_lootpack_stats SET personalization_opt_out = $2, updated_at = NOW() WHERE user_id = $1",
+                user_id,
+                opt_out
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            if updated.rows_affected() == 0 {
+                return Err(AppError::NotFound("User stats not found".to_string()));
+            }
+        }
+
+        if let Some(categories) = req.categories {
+            sqlx::query!("DELETE FROM user_category_preferences WHERE user_id = $1", user_id)
+                .execute(&mut *tx)
+                .await?;
+
+            for entry in categories {
+                sqlx::query!(
+                    r#"
+                    INSERT INTO user_category_preferences (user_id, category, score)
+                    VALUES ($1, $2, $3)
+                    ON CONFLICT (user_id, category) DO UPDATE SET score = EXCLUDED.score, updated_at = NOW()
+                    "#,
+                    user_id,
+                    entry.category,
+                    entry.score
+                )
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+
+        tx.commit().await?;
+        self.get_settings(user_id).await
+    }
+}
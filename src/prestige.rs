@@ -0,0 +1,177 @@
+use crate::audit;
+use crate::error::{AppError, Result};
+use crate::inbox::{self, NewMessage};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::json;
+use sqlx::{PgConnection, PgPool};
+
+/// Levels stop here; progress past it waits for a prestige
+pub const MAX_LEVEL: i32 = 50;
+
+/// Extra DealCoins on coin rewards per prestige, in percent, and the cap
+const COIN_BONUS_PCT_PER_PRESTIGE: i32 = 5;
+const MAX_COIN_BONUS_PCT: i32 = 25;
+
+/// Member status by prestige count; later prestiges stay at the last one
+const MEMBER_STATUSES: [&str; 5] = ["Bronze", "Silver", "Gold", "Platinum", "Diamond"];
+
+pub fn member_status(prestige: i32) -> &'static str {
+    MEMBER_STATUSES[(prestige.max(0) as usize).min(MEMBER_STATUSES.len() - 1)]
+}
+
+pub fn coin_bonus_pct(prestige: i32) -> i32 {
+    (prestige * COIN_BONUS_PCT_PER_PRESTIGE).clamp(0, MAX_COIN_BONUS_PCT)
+}
+
+/// Bonus DealCoins on `coins` credited by an open
+pub fn coin_bonus(prestige: i32, coins: i32) -> i32 {
+    coins.max(0) * coin_bonus_pct(prestige) / 100
+}
+
+#[derive(Debug, Serialize)]
+pub struct PrestigeView {
+    pub prestige: i32,
+    /// Permanent profile badge, e.g. "prestige_2"
+    pub badge: Option<String>,
+    /// Cosmetic frame around the user's pack art
+    pub pack_frame: Option<String>,
+    pub coin_bonus_pct: i32,
+    pub can_prestige: bool,
+    pub max_level: i32,
+    pub last_prestiged_at: Option<DateTime<Utc>>,
+}
+
+impl PrestigeView {
+    fn new(prestige: i32, level: i32, last_prestiged_at: Option<DateTime<Utc>>) -> Self {
+        let earned = prestige > 0;
+        PrestigeView {
+            prestige,
+            badge: earned.then(|| format!("prestige_{}", prestige)),
+            pack_frame: earned.then(|| format!("frame_{}", member_status(prestige).to_lowercase())),
+            coin_bonus_pct: coin_bonus_pct(prestige),
+            can_prestige: level >= MAX_LEVEL,
+            max_level: MAX_LEVEL,
+            last_prestiged_at,
+        }
+    }
+}
+
+/// The user's prestige count; 0 for users who never prestiged
+pub async fn count(conn: &mut PgConnection, user_id: &str) -> Result<i32> {
+    let prestige = sqlx::query_scalar!("SELECT prestige FROM user_prestige WHERE user_id = $1", user_id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .unwrap_or(0);
+
+    Ok(prestige)
+}
+
+pub async fn view(conn: &mut PgConnection, user_id: &str, level: i32) -> Result<PrestigeView> {
+    let row = sqlx::query!(
+        "SELECT prestige, last_prestiged_at FROM user_prestige WHERE user_id = $1",
+        user_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(match row {
+        Some(row) => PrestigeView::new(row.prestige, level, row.last_prestiged_at),
+        None => PrestigeView::new(0, level, None),
+    })
+}
+
+pub struct PrestigeService {
+    db: PgPool,
+}
+
+impl PrestigeService {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    pub async fn get(&self, user_id: &str) -> Result<PrestigeView> {
+        let mut conn = self.db.acquire().await?;
+        let level = sqlx::query_scalar!("SELECT level FROM user_lootpack_stats WHERE user_id = $1", user_id)
+            .fetch_optional(&mut *conn)
+            .await?
+            .flatten()
+            .unwrap_or(1);
+        view(&mut conn, user_id, level).await
+    }
+
+    /// Reset a max-level user to level 1 and raise their prestige. Coins, packs and
+    /// rewards are kept.
+    pub async fn prestige(&self, user_id: &str) -> Result<PrestigeView> {
+        let mut tx = self.db.begin().await?;
+        // Same lock as opens, so no level up lands mid-reset
+        let stats = sqlx::query!(
+            "SELECT level, total_packs_opened FROM user_lootpack_stats WHERE user_id = $1 FOR UPDATE",
+            user_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::BadRequest(format!("Reach level {} to prestige", MAX_LEVEL)))?;
+        if stats.level.unwrap_or(1) < MAX_LEVEL {
+            return Err(AppError::BadRequest(format!("Reach level {} to prestige", MAX_LEVEL)));
+        }
+
+        let prestige = sqlx::query_scalar!(
+            r#"
+            INSERT INTO user_prestige (user_id, prestige, last_prestiged_at)
+            VALUES ($1, 1, NOW())
+            ON CONFLICT (user_id) DO UPDATE SET
+                prestige = user_prestige.prestige + 1, last_prestiged_at = NOW()
+            RETURNING prestige
+            "#,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            "INSERT INTO prestige_events (user_id, prestige, packs_opened) VALUES ($1, $2, $3)",
+            user_id,
+            prestige,
+            stats.total_packs_opened.unwrap_or(0)
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let status = member_status(prestige);
+        sqlx::query!(
+            r#"
+            UPDATE user_lootpack_stats
+            SET level = 1, level_progress = 0, member_status = $2, updated_at = NOW()
+            WHERE user_id = $1
+            "#,
+            user_id,
+            status
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        inbox::send(&mut tx, user_id, NewMessage {
+            kind: "milestone",
+            title: &format!("Prestige {} reached!", prestige),
+            body: &format!(
+                "You're now a {} member and earn {}% more DealCoins from every pack.",
+                status,
+                coin_bonus_pct(prestige)
+            ),
+            claim_pack_type_id: None,
+            expires_at: None,
+        })
+        .await?;
+
+        audit::record(&mut *tx, user_id, "prestige.reached", None, json!({
+            "prestige": prestige,
+            "member_status": status,
+        }))
+        .await?;
+
+        let view = view(&mut tx, user_id, 1).await?;
+        tx.commit().await?;
+        Ok(view)
+    }
+}
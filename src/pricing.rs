@@ -0,0 +1,161 @@
+use crate::error::{AppError, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgExecutor, PgPool};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, FromRow, Deserialize, Serialize)]
+pub struct PackDiscount {
+    pub id: Uuid,
+    pub pack_type_id: Uuid,
+    pub percent_off: i32,
+    pub label: Option<String>,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateDiscountRequest {
+    pub percent_off: i32,
+    pub label: Option<String>,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+}
+
+/// Price shown to and charged from the user right now
+#[derive(Debug, Clone, Serialize)]
+pub struct PackPricing {
+    pub original_price: Option<i32>,
+    pub price: Option<i32>,
+    pub discount_percent: Option<i32>,
+    pub discount_label: Option<String>,
+    pub discount_ends_at: Option<DateTime<Utc>>,
+}
+
+impl PackPricing {
+    pub fn compute(price_coins: Option<i32>, discount: Option<&PackDiscount>) -> Self {
+        match (price_coins, discount) {
+            (Some(price), Some(discount)) => Self {
+                original_price: Some(price),
+                // Round the discount down so we never charge more than advertised
+                price: Some(price - price * discount.percent_off / 100),
+                discount_percent: Some(discount.percent_off),
+                discount_label: discount.label.clone(),
+                discount_ends_at: Some(discount.ends_at),
+            },
+            _ => Self {
+                original_price: price_coins,
+                price: price_coins,
+                discount_percent: None,
+                discount_label: None,
+                discount_ends_at: None,
+            },
+        }
+    }
+}
+
+/// Best discount currently running for each pack (largest percent wins)
+pub async fn active_discounts<'e, E: PgExecutor<'e>>(executor: E) -> Result<Vec<PackDiscount>> {
+    let discounts = sqlx::query_as!(
+        PackDiscount,
+        r#"
+        SELECT DISTINCT ON (pack_type_id)
+               id, pack_type_id, percent_off, label, starts_at, ends_at
+        FROM pack_discounts
+        WHERE starts_at <= NOW() AND ends_at > NOW()
+        ORDER BY pack_type_id, percent_off DESC
+        "#
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(discounts)
+}
+
+/// Best discount currently running for one pack
+pub async fn active_discount<'e, E: PgExecutor<'e>>(executor: E, pack_type_id: Uuid) -> Result<Option<PackDiscount>> {
+    let discount = sqlx::query_as!(
+        PackDiscount,
+        r#"
+        SELECT id, pack_type_id, percent_off, label, starts_at, ends_at
+        FROM pack_discounts
+        WHERE pack_type_id = $1 AND starts_at <= NOW() AND ends_at > NOW()
+        ORDER BY percent_off DESC
+        LIMIT 1
+        "#,
+        pack_type_id
+    )
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(discount)
+}
+
+/// Admin management of scheduled discounts
+pub struct PricingService {
+    db: PgPool,
+}
+
+impl PricingService {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    /// List current and upcoming discounts for a pack
+    pub async fn list_discounts(&self, pack_type_id: Uuid) -> Result<Vec<PackDiscount>> {
+        let discounts = sqlx::query_as!(
+            PackDiscount,
+            r#"
+            SELECT id, pack_type_id, percent_off, label, starts_at, ends_at
+            FROM pack_discounts
+            WHERE pack_type_id = $1 AND ends_at > NOW()
+            ORDER BY starts_at
+            "#,
+            pack_type_id
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(discounts)
+    }
+
+    /// Schedule a discount window
+    pub async fn create_discount(&self, pack_type_id: Uuid, req: CreateDiscountRequest) -> Result<PackDiscount> {
+        if !(1..=100).contains(&req.percent_off) {
+            return Err(AppError::BadRequest("percent_off must be between 1 and 100".to_string()));
+        }
+        if req.ends_at <= req.starts_at || req.ends_at <= Utc::now() {
+            return Err(AppError::BadRequest("Discount window must end after it starts and in the future".to_string()));
+        }
+
+        let discount = sqlx::query_as!(
+            PackDiscount,
+            r#"
+            INSERT INTO pack_discounts (pack_type_id, percent_off, label, starts_at, ends_at)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id, pack_type_id, percent_off, label, starts_at, ends_at
+            "#,
+            pack_type_id,
+            req.percent_off,
+            req.label,
+            req.starts_at,
+            req.ends_at
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(discount)
+    }
+
+    /// Cancel a discount
+    pub async fn delete_discount(&self, discount_id: Uuid) -> Result<()> {
+        let result = sqlx::query!("DELETE FROM pack_discounts WHERE id = $1", discount_id)
+            .execute(&self.db)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound("Discount not found".to_string()));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+use crate::audit;
+use crate::error::{AppError, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::Sha256;
+use sqlx::PgPool;
+use std::fmt;
+use std::sync::OnceLock;
+use tracing::warn;
+
+/// Hex characters kept from the HMAC; 128 bits is plenty to keep users apart
+const PSEUDONYM_HEX_CHARS: usize = 32;
+
+/// Key for `pseudonym`, shared by every replica so the same user maps to the same
+/// pseudonym in logs, error reports and exports from any of them
+static PSEUDONYM_KEY: OnceLock<Vec<u8>> = OnceLock::new();
+
+/// Load `USER_PSEUDONYM_KEY` (base64). Call once at startup, before anything logs a user.
+/// Without it pseudonyms are unkeyed hashes, which anyone holding a user id can recompute.
+pub fn init_pseudonyms_from_env() -> Result<()> {
+    let key = match std::env::var("USER_PSEUDONYM_KEY") {
+        Ok(key) => BASE64
+            .decode(key.trim())
+            .map_err(|e| AppError::InternalError(format!("Invalid USER_PSEUDONYM_KEY: {}", e)))?,
+        Err(_) => {
+            warn!("USER_PSEUDONYM_KEY is unset; user pseudonyms are unkeyed");
+            Vec::new()
+        }
+    };
+    PSEUDONYM_KEY
+        .set(key)
+        .map_err(|_| AppError::InternalError("User pseudonym key was already set".to_string()))
+}
+
+/// A user id as it may appear outside the database: in logs, error reports and exported
+/// events. Formats as a stable keyed hash, so a user's activity can still be followed
+/// without naming them. Raw ids stay in the database.
+#[derive(Clone, Copy)]
+pub struct Pseudonym<'a>(&'a str);
+
+pub fn pseudonym(user_id: &str) -> Pseudonym<'_> {
+    Pseudonym(user_id)
+}
+
+impl fmt::Display for Pseudonym<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let key = PSEUDONYM_KEY.get().map(Vec::as_slice).unwrap_or_default();
+        let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+        mac.update(self.0.as_bytes());
+        let digest = hex::encode(mac.finalize().into_bytes());
+        write!(f, "u_{}", &digest[..PSEUDONYM_HEX_CHARS])
+    }
+}
+
+impl fmt::Debug for Pseudonym<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PrivacySettings {
+    /// Show the user's epic and legendary pulls in their friends' feeds
+    pub share_pulls_with_friends: bool,
+    /// Include the user's pulls, anonymized, in the public live feed
+    pub show_in_live_feed: bool,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Partial update; omitted settings are left unchanged
+#[derive(Debug, Deserialize)]
+pub struct UpdatePrivacyRequest {
+    pub share_pulls_with_friends: Option<bool>,
+    pub show_in_live_feed: Option<bool>,
+}
+
+pub struct PrivacyService {
+    db: PgPool,
+}
+
+impl PrivacyService {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    pub async fn get_settings(&self, user_id: &str) -> Result<PrivacySettings> {
+        let settings = sqlx::query_as!(
+            PrivacySettings,
+            r#"
+            SELECT share_pulls_with_friends, show_in_live_feed, updated_at AS "updated_at?"
+            FROM user_privacy_settings
+            WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .fetch_optional(&self.db)
+        .await?;
+
+        Ok(settings.unwrap_or(PrivacySettings {
+            share_pulls_with_friends: true,
+            show_in_live_feed: true,
+            updated_at: None,
+        }))
+    }
+
+    pub async fn update_settings(&self, user_id: &str, req: UpdatePrivacyRequest) -> Result<PrivacySettings> {
+        let mut tx = self.db.begin().await?;
+        let settings = sqlx::query_as!(
+            PrivacySettings,
+            r#"
+            INSERT INTO user_privacy_settings (user_id, share_pulls_with_friends, show_in_live_feed)
+            VALUES ($1, COALESCE($2, true), COALESCE($3, true))
+            ON CONFLICT (user_id) DO UPDATE SET
+                share_pulls_with_friends = COALESCE($2, user_privacy_settings.share_pulls_with_friends),
+                show_in_live_feed = COALESCE($3, user_privacy_settings.show_in_live_feed),
+                updated_at = NOW()
+            RETURNING share_pulls_with_friends, show_in_live_feed, updated_at AS "updated_at?"
+            "#,
+            user_id,
+            req.share_pulls_with_friends,
+            req.show_in_live_feed
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        audit::record(&mut *tx, user_id, "privacy.updated", None, json!({
+            "share_pulls_with_friends": settings.share_pulls_with_friends,
+            "show_in_live_feed": settings.show_in_live_feed,
+        }))
+        .await?;
+
+        tx.commit().await?;
+        Ok(settings)
+    }
+}
@@ -0,0 +1,137 @@
+use crate::error::{AppError, Result};
+use crate::inbox::{self, NewMessage};
+use crate::{audit, user_packs};
+use crate::wallet::{self, CoinWallet};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+pub struct RedeemPromoRequest {
+    pub code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RedeemPromoResponse {
+    pub code: String,
+    pub grant_type: String,
+    pub coins_granted: Option<i32>,
+    pub new_balance: Option<i32>,
+    pub user_pack_id: Option<Uuid>,
+}
+
+pub struct PromoService {
+    db: PgPool,
+    wallet: Arc<dyn CoinWallet>,
+}
+
+impl PromoService {
+    pub fn new(db: PgPool, wallet: Arc<dyn CoinWallet>) -> Self {
+        Self { db, wallet }
+    }
+
+    /// Redeem a marketing promo code for coins or a free pack
+    pub async fn redeem(&self, user_id: &str, req: RedeemPromoRequest) -> Result<RedeemPromoResponse> {
+        let code = req.code.trim().to_uppercase();
+        let mut tx = self.db.begin().await?;
+
+        // Row lock serializes redemptions of the same code so usage limits hold
+        let promo = sqlx::query!(
+            r#"
+            SELECT code, grant_type, coins_amount, pack_type_id, max_uses, uses,
+                   per_user_limit, expires_at, is_active
+            FROM promo_codes
+            WHERE code = $1
+            FOR UPDATE
+            "#,
+            code
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .filter(|p| p.is_active)
+        .ok_or_else(|| AppError::NotFound("Promo code not found".to_string()))?;
+
+        if promo.expires_at.map(|exp| exp <= chrono::Utc::now()).unwrap_or(false) {
+            return Err(AppError::BadRequest("Promo code has expired".to_string()));
+        }
+        if promo.max_uses.map(|max| promo.uses >= max).unwrap_or(false) {
+            return Err(AppError::BadRequest("Promo code has been fully redeemed".to_string()));
+        }
+
+        let user_uses = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM promo_redemptions WHERE code = $1 AND user_id = $2"#,
+            code,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if user_uses >= promo.per_user_limit as i64 {
+            return Err(AppError::BadRequest("You have already redeemed this promo code".to_string()));
+        }
+
+        let redemption_id = sqlx::query_scalar!(
+            "INSERT INTO promo_redemptions (code, user_id) VALUES ($1, $2) RETURNING id",
+            code,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query!("UPDATE promo_codes SET uses = uses + 1 WHERE code = $1", code)
+            .execute(&mut *tx)
+            .await?;
+
+        let reference = redemption_id.to_string();
+        let mut response = RedeemPromoResponse {
+            code: code.clone(),
+            grant_type: promo.grant_type.clone(),
+            coins_granted: None,
+            new_balance: None,
+            user_pack_id: None,
+        };
+
+        let mut wallet_entries = Vec::new();
+        match (promo.grant_type.as_str(), promo.coins_amount, promo.pack_type_id) {
+            ("coins", Some(amount), _) => {
+                let entry = self.wallet.apply(&mut tx, user_id, amount, "promo_code", Some(&reference)).await?;
+                response.coins_granted = Some(amount);
+                response.new_balance = Some(entry.balance);
+                wallet_entries.push(entry);
+            }
+            ("pack", _, Some(pack_type_id)) => {
+                let pack_id = user_packs::grant(&mut tx, user_id, pack_type_id, "promo_code", None).await?;
+                inbox::send(&mut tx, user_id, NewMessage {
+                    kind: "grant",
+                    title: "You've got a free pack!",
+                    body: &format!("Promo code {} added a pack to your collection. Open it any time.", code),
+                    claim_pack_type_id: None,
+                    expires_at: None,
+                }).await?;
+                response.user_pack_id = Some(pack_id);
+            }
+            _ => {
+                return Err(AppError::InternalError(format!("Promo code {} is misconfigured", code)));
+            }
+        }
+
+        audit::record(
+            &mut *tx,
+            user_id,
+            "promo.redeemed",
+            Some(&code),
+            json!({
+                "redemption_id": redemption_id,
+                "grant_type": promo.grant_type,
+                "coins": response.coins_granted,
+                "user_pack_id": response.user_pack_id,
+            }),
+        )
+        .await?;
+
+        wallet::commit_with_wallet(self.wallet.as_ref(), tx, &wallet_entries).await?;
+        Ok(response)
+    }
+}
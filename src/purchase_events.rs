@@ -0,0 +1,209 @@
+use crate::currency::{self, Money};
+use crate::error::{AppError, Result};
+use crate::inbox::{self, NewMessage};
+use crate::lootpacks::LEVEL_UP_BONUS;
+use crate::prestige;
+use crate::wallet::{self, CoinWallet};
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Level progress for completing a purchase with a lootpack coupon; a level is 100 progress
+const XP_PER_PURCHASE: i32 = 20;
+
+/// A purchase-completed event published by checkout
+#[derive(Debug, Deserialize)]
+pub struct PurchaseCompleted {
+    pub event_id: String,
+    pub user_id: String,
+    /// The lootpack reward applied at checkout
+    pub reward_id: Uuid,
+    pub order_id: String,
+    /// Discount actually applied to the order; falls back to the reward's face value
+    pub savings_amount: Option<BigDecimal>,
+    pub currency: Option<String>,
+    pub completed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PurchaseEventResult {
+    pub event_id: String,
+    /// The event was already processed; nothing changed
+    pub duplicate: bool,
+    pub savings: Option<Money>,
+    pub xp_awarded: i32,
+    pub level: Option<i32>,
+    pub leveled_up: bool,
+}
+
+pub struct PurchaseEventService {
+    db: PgPool,
+    wallet: Arc<dyn CoinWallet>,
+}
+
+impl PurchaseEventService {
+    pub fn new(db: PgPool, wallet: Arc<dyn CoinWallet>) -> Self {
+        Self { db, wallet }
+    }
+
+    /// Credit savings and XP for a completed purchase. Idempotent on `event_id`, and a
+    /// coupon's savings are only ever counted once even if its reservation was confirmed too.
+    pub async fn handle(&self, event: PurchaseCompleted) -> Result<PurchaseEventResult> {
+        let mut tx = self.db.begin().await?;
+
+        let inserted = sqlx::query!(
+            r#"
+            INSERT INTO purchase_events (event_id, user_id, reward_id, order_id, completed_at)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (event_id) DO NOTHING
+            "#,
+            event.event_id,
+            event.user_id,
+            event.reward_id,
+            event.order_id,
+            event.completed_at
+        )
+        .execute(&mut *tx)
+        .await;
+        match inserted {
+            Ok(result) if result.rows_affected() == 0 => {
+                return Ok(PurchaseEventResult {
+                    event_id: event.event_id,
+                    duplicate: true,
+                    savings: None,
+                    xp_awarded: 0,
+                    level: None,
+                    leveled_up: false,
+                });
+            }
+            Ok(_) => {}
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+                return Err(AppError::BadRequest("A purchase was already recorded for this reward".to_string()));
+            }
+            Err(e) => return Err(e.into()),
+        }
+
+        // Refunded, traded-in and escrowed rewards, and ones that expired before the purchase,
+        // can't be used and earn nothing
+        let reward = sqlx::query!(
+            r#"
+            UPDATE user_rewards
+            SET is_used = true, used_at = COALESCE(used_at, $3), reservation_id = NULL, reserved_until = NULL
+            WHERE id = $1 AND user_id = $2
+              AND voided_at IS NULL AND traded_in_at IS NULL AND escrow_trade_id IS NULL
+              AND (expires_at IS NULL OR expires_at > $3)
+            RETURNING value_amount, value_currency, savings_recorded_at
+            "#,
+            event.reward_id,
+            event.user_id,
+            event.completed_at
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(reward) = reward else {
+            let exists = sqlx::query_scalar!(
+                r#"SELECT EXISTS (SELECT 1 FROM user_rewards WHERE id = $1 AND user_id = $2) AS "exists!""#,
+                event.reward_id,
+                event.user_id
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+            return Err(if exists {
+                AppError::BadRequest("Reward was refunded, traded or expired and can't be used".to_string())
+            } else {
+                AppError::NotFound("Reward not found".to_string())
+            });
+        };
+
+        let savings = match (event.savings_amount, reward.value_amount) {
+            _ if reward.savings_recorded_at.is_some() => None,
+            (Some(amount), _) | (None, Some(amount)) if amount > BigDecimal::zero() => Some(Money {
+                amount,
+                currency: event
+                    .currency
+                    .or(reward.value_currency)
+                    .unwrap_or_else(|| currency::BASE_CURRENCY.to_string()),
+            }),
+            _ => None,
+        };
+        if let Some(saved) = &savings {
+            currency::record_savings(&mut tx, &event.user_id, saved).await?;
+            sqlx::query!("UPDATE user_rewards SET savings_recorded_at = NOW() WHERE id = $1", event.reward_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        let level = sqlx::query!(
+            "SELECT level, level_progress FROM user_lootpack_stats WHERE user_id = $1 FOR UPDATE",
+            event.user_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let (mut xp_awarded, mut new_level, mut leveled_up) = (0, None, false);
+        let mut wallet_entries = Vec::new();
+        if let Some(row) = level {
+            let mut current_level = row.level.unwrap_or(1);
+            let mut current_progress = row.level_progress.unwrap_or(0);
+            // At the cap progress waits for a prestige, same as pack openings
+            if current_level < prestige::MAX_LEVEL {
+                xp_awarded = XP_PER_PURCHASE;
+                current_progress += XP_PER_PURCHASE;
+                if current_progress >= 100 {
+                    current_level += 1;
+                    current_progress = 0;
+                    leveled_up = true;
+                }
+            }
+
+            sqlx::query!(
+                "UPDATE user_lootpack_stats SET level = $2, level_progress = $3, updated_at = NOW() WHERE user_id = $1",
+                event.user_id,
+                current_level,
+                current_progress
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            if leveled_up {
+                wallet_entries.push(
+                    self.wallet.apply(&mut tx, &event.user_id, LEVEL_UP_BONUS, "level_up_bonus", Some(&event.event_id)).await?,
+                );
+                inbox::send(&mut tx, &event.user_id, NewMessage {
+                    kind: "milestone",
+                    title: &format!("Level {} reached!", current_level),
+                    body: &format!("You earned {} bonus DealCoins for levelling up.", LEVEL_UP_BONUS),
+                    claim_pack_type_id: None,
+                    expires_at: None,
+                }).await?;
+            }
+            new_level = Some(current_level);
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE purchase_events SET savings_amount = $2, savings_currency = $3, xp_awarded = $4
+            WHERE event_id = $1
+            "#,
+            event.event_id,
+            savings.as_ref().map(|s| s.amount.clone()),
+            savings.as_ref().map(|s| s.currency.clone()),
+            xp_awarded
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        wallet::commit_with_wallet(self.wallet.as_ref(), tx, &wallet_entries).await?;
+        Ok(PurchaseEventResult {
+            event_id: event.event_id,
+            duplicate: false,
+            savings,
+            xp_awarded,
+            level: new_level,
+            leveled_up,
+        })
+    }
+}
@@ -0,0 +1,134 @@
+use crate::error::{AppError, Result};
+use crate::live_feed;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+/// Rarities worth showing off in the feeds
+pub const FEED_RARITIES: [&str; 2] = ["epic", "legendary"];
+
+/// Pulls kept per user; older ones are pruned as new ones arrive
+const PULLS_PER_USER: i64 = 50;
+const MAX_FRIEND_IDS: usize = 500;
+const DEFAULT_FEED_LIMIT: i64 = 20;
+const MAX_FEED_LIMIT: i64 = 100;
+
+/// Record the feed-worthy rewards among `reward_ids`, just inserted for `user_id`, and
+/// publish them to the live feed. Rewards already recorded are replaced, so a reroll
+/// into or out of a feed rarity updates the friends feed too.
+pub async fn record(conn: &mut PgConnection, user_id: &str, reward_ids: &[Uuid]) -> Result<()> {
+    if reward_ids.is_empty() {
+        return Ok(());
+    }
+
+    sqlx::query!("DELETE FROM recent_pulls WHERE reward_id = ANY($1)", reward_ids)
+        .execute(&mut *conn)
+        .await?;
+
+    let inserted = sqlx::query!(
+        r#"
+        INSERT INTO recent_pulls (reward_id, user_id, template_id, pack_type_id, title, rarity)
+        SELECT ur.id, ur.user_id, ur.template_id, uph.pack_type_id, ur.title, ur.rarity
+        FROM user_rewards ur
+        LEFT JOIN user_pack_history uph ON uph.id = ur.pack_history_id
+        WHERE ur.id = ANY($2) AND ur.user_id = $1 AND ur.template_id IS NOT NULL AND ur.rarity = ANY($3)
+        "#,
+        user_id,
+        reward_ids,
+        &FEED_RARITIES[..]
+    )
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+
+    if inserted > 0 {
+        sqlx::query!(
+            r#"
+            DELETE FROM recent_pulls
+            WHERE user_id = $1 AND reward_id NOT IN (
+                SELECT reward_id FROM recent_pulls WHERE user_id = $1
+                ORDER BY pulled_at DESC
+                LIMIT $2
+            )
+            "#,
+            user_id,
+            PULLS_PER_USER
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        live_feed::publish(&mut *conn, reward_ids).await?;
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct FriendPullsQuery {
+    /// Comma-separated user ids from the platform friend graph
+    pub friend_ids: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FriendPull {
+    pub user_id: String,
+    pub template_id: Uuid,
+    pub title: String,
+    pub rarity: String,
+    pub pack_type_id: Option<Uuid>,
+    pub pack_name: Option<String>,
+    pub pulled_at: DateTime<Utc>,
+}
+
+pub struct RecentPullService {
+    db: PgPool,
+}
+
+impl RecentPullService {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    /// Latest epic and legendary pulls of the given friends, leaving out anyone who
+    /// opted out of sharing. The caller is never included in their own feed.
+    pub async fn friend_pulls(&self, user_id: &str, query: &FriendPullsQuery) -> Result<Vec<FriendPull>> {
+        let friend_ids: Vec<String> = query
+            .friend_ids
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|id| !id.is_empty() && *id != user_id)
+            .map(String::from)
+            .collect();
+        if friend_ids.len() > MAX_FRIEND_IDS {
+            return Err(AppError::BadRequest(format!("At most {} friend_ids are accepted", MAX_FRIEND_IDS)));
+        }
+        if friend_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let limit = query.limit.unwrap_or(DEFAULT_FEED_LIMIT).clamp(1, MAX_FEED_LIMIT);
+
+        let pulls = sqlx::query_as!(
+            FriendPull,
+            r#"
+            SELECT rp.user_id, rp.template_id, rp.title, rp.rarity, rp.pack_type_id,
+                   pt.name AS "pack_name?", rp.pulled_at
+            FROM recent_pulls rp
+            LEFT JOIN pack_types pt ON pt.id = rp.pack_type_id
+            LEFT JOIN user_privacy_settings ups ON ups.user_id = rp.user_id
+            WHERE rp.user_id = ANY($1) AND COALESCE(ups.share_pulls_with_friends, true)
+            ORDER BY rp.pulled_at DESC
+            LIMIT $2
+            "#,
+            &friend_ids,
+            limit
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(pulls)
+    }
+}
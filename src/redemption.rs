@@ -0,0 +1,235 @@
+use crate::error::{AppError, Result};
+use crate::currency::{self, Money};
+use crate::reward_types;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+const MAX_BULK_REDEEM: usize = 50;
+const DEFAULT_HOLD_SECONDS: i64 = 600;
+const MAX_HOLD_SECONDS: i64 = 1800;
+
+#[derive(Debug, Deserialize)]
+pub struct BulkRedeemRequest {
+    pub reward_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RedeemItemResult {
+    pub reward_id: Uuid,
+    pub redeemed: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BulkRedeemResponse {
+    pub applied: bool,
+    pub redeemed_count: usize,
+    pub results: Vec<RedeemItemResult>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReserveRewardRequest {
+    pub hold_seconds: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReservationRequest {
+    pub reservation_id: Uuid,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReservationResponse {
+    pub reward_id: Uuid,
+    pub reservation_id: Uuid,
+    pub reserved_until: DateTime<Utc>,
+}
+
+pub struct RedemptionService {
+    db: PgPool,
+}
+
+impl RedemptionService {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    /// Redeem several bulk-redeemable (point) rewards at once. Either every reward is redeemed or none is;
+    /// the per-item results explain which ones blocked the batch.
+    pub async fn redeem_bulk(&self, user_id: &str, req: BulkRedeemRequest) -> Result<BulkRedeemResponse> {
+        let mut reward_ids = req.reward_ids;
+        reward_ids.sort();
+        reward_ids.dedup();
+
+        if reward_ids.is_empty() || reward_ids.len() > MAX_BULK_REDEEM {
+            return Err(AppError::BadRequest(format!(
+                "reward_ids must contain between 1 and {} rewards", MAX_BULK_REDEEM
+            )));
+        }
+
+        let mut tx = self.db.begin().await?;
+
+        // Lock in id order so concurrent batches can't deadlock
+        let rows = sqlx::query!(
+            r#"
+            SELECT id, user_id, type, is_used, expires_at, reserved_until, escrow_trade_id
+            FROM user_rewards
+            WHERE id = ANY($1)
+            ORDER BY id
+            FOR UPDATE
+            "#,
+            &reward_ids
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let rows: HashMap<Uuid, _> = rows.into_iter().map(|r| (r.id, r)).collect();
+        let now = Utc::now();
+
+        let results: Vec<RedeemItemResult> = reward_ids
+            .iter()
+            .map(|id| {
+                let error = match rows.get(id) {
+                    None => Some("not_found"),
+                    Some(r) if r.user_id != user_id => Some("not_found"),
+                    Some(r) if !reward_types::spec(&r.r#type).map(|t| t.bulk_redeemable).unwrap_or(false) => {
+                        Some("not_bulk_redeemable")
+                    }
+                    Some(r) if r.is_used.unwrap_or(false) => Some("already_used"),
+                    Some(r) if r.expires_at.map(|exp| exp <= now).unwrap_or(false) => Some("expired"),
+                    Some(r) if r.reserved_until.map(|until| until > now).unwrap_or(false) => Some("reserved"),
+                    Some(r) if r.escrow_trade_id.is_some() => Some("in_trade"),
+                    Some(_) => None,
+                };
+                RedeemItemResult {
+                    reward_id: *id,
+                    redeemed: error.is_none(),
+                    error: error.map(str::to_string),
+                }
+            })
+            .collect();
+
+        if results.iter().any(|r| !r.redeemed) {
+            tx.rollback().await?;
+            return Ok(BulkRedeemResponse {
+                applied: false,
+                redeemed_count: 0,
+                results: results
+                    .into_iter()
+                    .map(|r| RedeemItemResult { redeemed: false, ..r })
+                    .collect(),
+            });
+        }
+
+        sqlx::query!(
+            "UPDATE user_rewards SET is_used = true, used_at = NOW() WHERE id = ANY($1)",
+            &reward_ids
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(BulkRedeemResponse {
+            applied: true,
+            redeemed_count: results.len(),
+            results,
+        })
+    }
+
+    /// Place a time-boxed hold on a reward for an in-flight checkout
+    pub async fn reserve_reward(&self, reward_id: Uuid, req: ReserveRewardRequest) -> Result<ReservationResponse> {
+        let hold_seconds = req.hold_seconds.unwrap_or(DEFAULT_HOLD_SECONDS);
+        if !(1..=MAX_HOLD_SECONDS).contains(&hold_seconds) {
+            return Err(AppError::BadRequest(format!(
+                "hold_seconds must be between 1 and {}", MAX_HOLD_SECONDS
+            )));
+        }
+
+        let reservation_id = Uuid::new_v4();
+        let reserved_until = Utc::now() + Duration::seconds(hold_seconds);
+
+        // Conditional update so two checkouts can't both win the hold
+        let reserved = sqlx::query!(
+            r#"
+            UPDATE user_rewards
+            SET reservation_id = $2, reserved_until = $3
+            WHERE id = $1
+              AND COALESCE(is_used, false) = false
+              AND (expires_at IS NULL OR expires_at > NOW())
+              AND (reserved_until IS NULL OR reserved_until <= NOW())
+              AND escrow_trade_id IS NULL
+            RETURNING id
+            "#,
+            reward_id,
+            reservation_id,
+            reserved_until
+        )
+        .fetch_optional(&self.db)
+        .await?;
+
+        if reserved.is_none() {
+            return Err(AppError::BadRequest(
+                "Reward is used, expired, already reserved or held in a trade".to_string()
+            ));
+        }
+
+        Ok(ReservationResponse {
+            reward_id,
+            reservation_id,
+            reserved_until,
+        })
+    }
+
+    /// Release a hold without redeeming, e.g. when checkout is abandoned
+    pub async fn release_reservation(&self, reward_id: Uuid, req: ReservationRequest) -> Result<()> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE user_rewards SET reservation_id = NULL, reserved_until = NULL
+            WHERE id = $1 AND reservation_id = $2
+            "#,
+            reward_id,
+            req.reservation_id
+        )
+        .execute(&self.db)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound("Reservation not found".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Confirm a hold once the order completes, redeeming the reward and adding its
+    /// value to the user's savings
+    pub async fn confirm_reservation(&self, reward_id: Uuid, req: ReservationRequest) -> Result<()> {
+        let mut tx = self.db.begin().await?;
+
+        let confirmed = sqlx::query!(
+            r#"
+            UPDATE user_rewards
+            SET is_used = true, used_at = NOW(), reservation_id = NULL, reserved_until = NULL,
+                savings_recorded_at = COALESCE(savings_recorded_at, NOW())
+            WHERE id = $1 AND reservation_id = $2 AND reserved_until > NOW()
+              AND COALESCE(is_used, false) = false
+            RETURNING user_id, value_amount, value_currency
+            "#,
+            reward_id,
+            req.reservation_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::BadRequest(
+            "Reservation not found or already expired".to_string()
+        ))?;
+
+        if let (Some(amount), Some(currency)) = (confirmed.value_amount, confirmed.value_currency) {
+            currency::record_savings(&mut tx, &confirmed.user_id, &Money { amount, currency }).await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+}
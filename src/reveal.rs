@@ -0,0 +1,92 @@
+use crate::error::{AppError, Result};
+use crate::models::lootpacks::GeneratedReward;
+use serde::Serialize;
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+/// Accepted values of `pack_types.reveal_order`
+pub const REVEAL_ORDERS: [&str; 3] = ["dealt", "best_last", "ascending"];
+
+/// One reward's place in the reveal sequence
+#[derive(Debug, Serialize)]
+pub struct RevealStep {
+    /// Index into the open's `rewards`
+    pub reward_index: usize,
+    pub animation_tier: &'static str,
+}
+
+/// How the client should play back an open, so every client sequences it the same way
+#[derive(Debug, Serialize)]
+pub struct RevealPlan {
+    pub order: String,
+    /// Rewards in the order to reveal them
+    pub steps: Vec<RevealStep>,
+}
+
+pub fn validate_order(order: &str) -> Result<()> {
+    if !REVEAL_ORDERS.contains(&order) {
+        return Err(AppError::BadRequest(format!(
+            "reveal_order must be one of {}", REVEAL_ORDERS.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+/// The pack's configured reveal order
+pub async fn pack_order(conn: &mut PgConnection, pack_type_id: Uuid) -> Result<String> {
+    let order = sqlx::query_scalar!("SELECT reveal_order FROM pack_types WHERE id = $1", pack_type_id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Pack type not found".to_string()))?;
+    Ok(order)
+}
+
+pub(crate) fn rarity_rank(rarity: &str) -> u8 {
+    match rarity {
+        "legendary" => 4,
+        "epic" => 3,
+        "rare" => 2,
+        _ => 1,
+    }
+}
+
+fn animation_tier(rarity: &str) -> &'static str {
+    match rarity_rank(rarity) {
+        4 => "cinematic",
+        3 => "burst",
+        2 => "glow",
+        _ => "standard",
+    }
+}
+
+/// Sequence `rewards` for the reveal screen. Unknown orders fall back to `dealt`.
+pub fn plan(rewards: &[GeneratedReward], order: &str) -> RevealPlan {
+    let mut indices: Vec<usize> = (0..rewards.len()).collect();
+    match order {
+        "ascending" => indices.sort_by_key(|&i| rarity_rank(&rewards[i].rarity)),
+        "best_last" => {
+            // Ties go to the earliest draw so the rest keep their relative order
+            let best = indices
+                .iter()
+                .copied()
+                .rev()
+                .max_by_key(|&i| rarity_rank(&rewards[i].rarity));
+            if let Some(best) = best {
+                indices.retain(|&i| i != best);
+                indices.push(best);
+            }
+        }
+        _ => {}
+    }
+
+    RevealPlan {
+        order: order.to_string(),
+        steps: indices
+            .into_iter()
+            .map(|reward_index| RevealStep {
+                reward_index,
+                animation_tier: animation_tier(&rewards[reward_index].rarity),
+            })
+            .collect(),
+    }
+}
@@ -0,0 +1,308 @@
+use crate::error::{AppError, Result};
+use crate::inbox::{self, NewMessage};
+use crate::{audit, user_packs};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+const MAX_SET_SIZE: usize = 50;
+
+#[derive(Debug, Serialize)]
+pub struct RewardSet {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub bonus_pack_type_id: Uuid,
+    pub is_active: bool,
+    pub template_ids: Vec<Uuid>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RewardSetRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub bonus_pack_type_id: Uuid,
+    pub template_ids: Vec<Uuid>,
+    #[serde(default = "default_active")]
+    pub is_active: bool,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+#[derive(Debug, Serialize)]
+pub struct SetProgress {
+    pub set_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub bonus_pack_type_id: Uuid,
+    pub bonus_pack_name: String,
+    pub collected: i64,
+    pub total: i64,
+    pub missing_template_ids: Vec<Uuid>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+/// A set finished by the templates just collected, and the bonus pack it granted
+#[derive(Debug, Clone, Serialize)]
+pub struct CompletedSet {
+    pub set_id: Uuid,
+    pub name: String,
+    pub bonus_user_pack_id: Uuid,
+}
+
+/// Grant the bonus for every active set that `template_ids` just completed. Call after
+/// the templates are in `user_collection`, in the same transaction; the caller must hold
+/// the user's stats row lock so two opens can't each see the other's templates missing.
+pub async fn award_completed(
+    conn: &mut PgConnection,
+    user_id: &str,
+    template_ids: &[Uuid],
+) -> Result<Vec<CompletedSet>> {
+    if template_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let finished = sqlx::query!(
+        r#"
+        SELECT rs.id, rs.name, rs.bonus_pack_type_id
+        FROM reward_sets rs
+        WHERE rs.is_active
+          AND EXISTS (
+              SELECT 1 FROM reward_set_templates rst
+              WHERE rst.set_id = rs.id AND rst.template_id = ANY($2)
+          )
+          AND NOT EXISTS (
+              SELECT 1 FROM reward_set_completions c WHERE c.set_id = rs.id AND c.user_id = $1
+          )
+          AND NOT EXISTS (
+              SELECT 1 FROM reward_set_templates rst
+              LEFT JOIN user_collection uc ON uc.template_id = rst.template_id AND uc.user_id = $1
+              WHERE rst.set_id = rs.id AND uc.template_id IS NULL
+          )
+        ORDER BY rs.created_at
+        "#,
+        user_id,
+        template_ids
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut completed = Vec::new();
+    for set in finished {
+        let claimed = sqlx::query!(
+            r#"
+            INSERT INTO reward_set_completions (set_id, user_id) VALUES ($1, $2)
+            ON CONFLICT (set_id, user_id) DO NOTHING
+            "#,
+            set.id,
+            user_id
+        )
+        .execute(&mut *conn)
+        .await?
+        .rows_affected();
+        if claimed == 0 {
+            continue;
+        }
+
+        let user_pack_id = user_packs::grant(&mut *conn, user_id, set.bonus_pack_type_id, "set_bonus", None).await?;
+        sqlx::query!(
+            "UPDATE reward_set_completions SET bonus_user_pack_id = $3 WHERE set_id = $1 AND user_id = $2",
+            set.id,
+            user_id,
+            user_pack_id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        inbox::send(&mut *conn, user_id, NewMessage {
+            kind: "milestone",
+            title: &format!("{} complete!", set.name),
+            body: "You collected every reward in the set. A bonus pack is waiting in your packs.",
+            claim_pack_type_id: None,
+            expires_at: None,
+        })
+        .await?;
+
+        audit::record(&mut *conn, user_id, "reward_set.completed", Some(&set.id.to_string()), json!({
+            "bonus_pack_type_id": set.bonus_pack_type_id,
+            "user_pack_id": user_pack_id,
+        }))
+        .await?;
+
+        completed.push(CompletedSet { set_id: set.id, name: set.name, bonus_user_pack_id: user_pack_id });
+    }
+
+    Ok(completed)
+}
+
+pub struct RewardSetService {
+    db: PgPool,
+}
+
+impl RewardSetService {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    pub async fn list_sets(&self) -> Result<Vec<RewardSet>> {
+        let sets = sqlx::query_as!(
+            RewardSet,
+            r#"
+            SELECT rs.id, rs.name, rs.description, rs.bonus_pack_type_id, rs.is_active, rs.updated_at,
+                   COALESCE(ARRAY_AGG(rst.template_id) FILTER (WHERE rst.template_id IS NOT NULL), '{}') AS "template_ids!"
+            FROM reward_sets rs
+            LEFT JOIN reward_set_templates rst ON rst.set_id = rs.id
+            GROUP BY rs.id
+            ORDER BY rs.created_at
+            "#
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(sets)
+    }
+
+    pub async fn create_set(&self, actor: &str, req: RewardSetRequest) -> Result<Uuid> {
+        Self::validate(&req)?;
+
+        let mut tx = self.db.begin().await?;
+        let set_id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO reward_sets (name, description, bonus_pack_type_id, is_active, created_by)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id
+            "#,
+            req.name.trim(),
+            req.description,
+            req.bonus_pack_type_id,
+            req.is_active,
+            actor
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        Self::replace_templates(&mut tx, set_id, &req.template_ids).await?;
+        audit::record(&mut *tx, actor, "reward_set.created", Some(&set_id.to_string()), json!({
+            "name": req.name,
+            "template_ids": req.template_ids,
+            "bonus_pack_type_id": req.bonus_pack_type_id,
+        }))
+        .await?;
+
+        tx.commit().await?;
+        Ok(set_id)
+    }
+
+    /// Replace a set's definition. Users who already completed it keep their bonus; users
+    /// who now hold every template get it on their next open of one of them.
+    pub async fn update_set(&self, actor: &str, set_id: Uuid, req: RewardSetRequest) -> Result<()> {
+        Self::validate(&req)?;
+
+        let mut tx = self.db.begin().await?;
+        let updated = sqlx::query!(
+            r#"
+            UPDATE reward_sets
+            SET name = $2, description = $3, bonus_pack_type_id = $4, is_active = $5, updated_at = NOW()
+            WHERE id = $1
+            "#,
+            set_id,
+            req.name.trim(),
+            req.description,
+            req.bonus_pack_type_id,
+            req.is_active
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if updated == 0 {
+            return Err(AppError::NotFound("Reward set not found".to_string()));
+        }
+
+        Self::replace_templates(&mut tx, set_id, &req.template_ids).await?;
+        audit::record(&mut *tx, actor, "reward_set.updated", Some(&set_id.to_string()), json!({
+            "name": req.name,
+            "template_ids": req.template_ids,
+            "bonus_pack_type_id": req.bonus_pack_type_id,
+            "is_active": req.is_active,
+        }))
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Progress on every active set, plus inactive ones the user already completed
+    pub async fn get_progress(&self, user_id: &str) -> Result<Vec<SetProgress>> {
+        let progress = sqlx::query_as!(
+            SetProgress,
+            r#"
+            SELECT rs.id AS set_id, rs.name, rs.description, rs.bonus_pack_type_id,
+                   pt.name AS bonus_pack_name,
+                   COUNT(uc.template_id) AS "collected!",
+                   COUNT(*) AS "total!",
+                   COALESCE(ARRAY_AGG(rst.template_id) FILTER (WHERE uc.template_id IS NULL), '{}') AS "missing_template_ids!",
+                   c.completed_at AS "completed_at?"
+            FROM reward_sets rs
+            JOIN pack_types pt ON pt.id = rs.bonus_pack_type_id
+            JOIN reward_set_templates rst ON rst.set_id = rs.id
+            LEFT JOIN user_collection uc ON uc.template_id = rst.template_id AND uc.user_id = $1
+            LEFT JOIN reward_set_completions c ON c.set_id = rs.id AND c.user_id = $1
+            WHERE rs.is_active OR c.completed_at IS NOT NULL
+            GROUP BY rs.id, pt.name, c.completed_at
+            ORDER BY c.completed_at IS NOT NULL, rs.created_at
+            "#,
+            user_id
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(progress)
+    }
+
+    fn validate(req: &RewardSetRequest) -> Result<()> {
+        if req.name.trim().is_empty() {
+            return Err(AppError::BadRequest("name is required".to_string()));
+        }
+        if req.template_ids.len() < 2 || req.template_ids.len() > MAX_SET_SIZE {
+            return Err(AppError::BadRequest(format!(
+                "A set needs between 2 and {} templates", MAX_SET_SIZE
+            )));
+        }
+        Ok(())
+    }
+
+    async fn replace_templates(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        set_id: Uuid,
+        template_ids: &[Uuid],
+    ) -> Result<()> {
+        sqlx::query!("DELETE FROM reward_set_templates WHERE set_id = $1", set_id)
+            .execute(&mut **tx)
+            .await?;
+
+        let inserted = sqlx::query!(
+            r#"
+            INSERT INTO reward_set_templates (set_id, template_id)
+            SELECT $1, rt.id FROM reward_templates rt WHERE rt.id = ANY($2)
+            "#,
+            set_id,
+            template_ids
+        )
+        .execute(&mut **tx)
+        .await?
+        .rows_affected();
+
+        let mut unique = template_ids.to_vec();
+        unique.sort();
+        unique.dedup();
+        if inserted as usize != unique.len() {
+            return Err(AppError::BadRequest("template_ids contains unknown templates".to_string()));
+        }
+        Ok(())
+    }
+}
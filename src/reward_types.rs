@@ -0,0 +1,201 @@
+use crate::error::{AppError, Result};
+use serde_json::Value;
+
+/// Behaviour of a reward type. Everything type-specific in generation, inventory
+/// and redemption is looked up here instead of matching on type strings.
+/// Per-type data (SKU, booking link, ...) lives in the template's `metadata`.
+pub struct RewardTypeSpec {
+    pub key: &'static str,
+    /// Needs a redeemable code (generated locally or claimed from the provider)
+    pub has_code: bool,
+    /// Gets an expiry date; coin rewards never expire
+    pub expires: bool,
+    /// Value is credited to DealCoins when the pack is opened
+    pub credits_coins: bool,
+    /// Can be redeemed through the bulk redemption endpoint
+    pub bulk_redeemable: bool,
+    /// Can change hands through a trade
+    pub tradable: bool,
+    /// Metadata keys a template of this type must define
+    pub required_metadata: &'static [&'static str],
+    pub instructions: fn(code: Option<&str>) -> Vec<String>,
+}
+
+pub const REWARD_TYPES: &[RewardTypeSpec] = &[
+    RewardTypeSpec {
+        key: "coupon",
+        has_code: true,
+        expires: true,
+        credits_coins: false,
+        bulk_redeemable: false,
+        tradable: true,
+        required_metadata: &[],
+        instructions: coupon_instructions,
+    },
+    RewardTypeSpec {
+        key: "voucher",
+        has_code: true,
+        expires: true,
+        credits_coins: false,
+        bulk_redeemable: false,
+        tradable: true,
+        required_metadata: &[],
+        instructions: voucher_instructions,
+    },
+    RewardTypeSpec {
+        key: "points",
+        has_code: false,
+        expires: false,
+        credits_coins: true,
+        bulk_redeemable: true,
+        tradable: false,
+        required_metadata: &[],
+        instructions: points_instructions,
+    },
+    RewardTypeSpec {
+        key: "cashback",
+        has_code: false,
+        expires: true,
+        credits_coins: false,
+        bulk_redeemable: false,
+        tradable: false,
+        required_metadata: &[],
+        instructions: cashback_instructions,
+    },
+    RewardTypeSpec {
+        key: "free_shipping",
+        has_code: true,
+        expires: true,
+        credits_coins: false,
+        bulk_redeemable: false,
+        tradable: true,
+        required_metadata: &["merchant"],
+        instructions: free_shipping_instructions,
+    },
+    RewardTypeSpec {
+        key: "product_sample",
+        has_code: false,
+        expires: true,
+        credits_coins: false,
+        bulk_redeemable: false,
+        tradable: true,
+        required_metadata: &["sku", "fulfillment_partner"],
+        instructions: product_sample_instructions,
+    },
+    RewardTypeSpec {
+        key: "experience",
+        has_code: true,
+        expires: true,
+        credits_coins: false,
+        bulk_redeemable: false,
+        tradable: true,
+        required_metadata: &["partner", "booking_url"],
+        instructions: experience_instructions,
+    },
+    RewardTypeSpec {
+        key: "gift_card",
+        has_code: false,
+        expires: true,
+        credits_coins: false,
+        bulk_redeemable: false,
+        tradable: false,
+        required_metadata: &["provider"],
+        instructions: gift_card_instructions,
+    },
+];
+
+/// Look up a registered reward type
+pub fn spec(reward_type: &str) -> Option<&'static RewardTypeSpec> {
+    REWARD_TYPES.iter().find(|spec| spec.key == reward_type)
+}
+
+/// Check that a template's type is registered and its metadata has the required keys
+pub fn validate_template(reward_type: &str, metadata: Option<&Value>) -> Result<()> {
+    let spec = spec(reward_type)
+        .ok_or_else(|| AppError::BadRequest(format!("Unknown reward type '{}'", reward_type)))?;
+
+    let missing: Vec<&str> = spec
+        .required_metadata
+        .iter()
+        .copied()
+        .filter(|key| metadata.and_then(|m| m.get(*key)).map(|v| v.is_null()).unwrap_or(true))
+        .collect();
+
+    if !missing.is_empty() {
+        return Err(AppError::BadRequest(format!(
+            "Reward type '{}' requires metadata: {}", reward_type, missing.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+/// Redemption steps for the reward detail screen
+pub fn redemption_instructions(reward_type: &str, code: Option<&str>) -> Vec<String> {
+    match spec(reward_type) {
+        Some(spec) => (spec.instructions)(code),
+        None => vec!["Follow the instructions in the reward description".to_string()],
+    }
+}
+
+fn coupon_instructions(code: Option<&str>) -> Vec<String> {
+    vec![
+        "Add eligible items to your cart on the partner store".to_string(),
+        format!("Enter code {} at checkout", code.unwrap_or("shown above")),
+        "The discount is applied before payment".to_string(),
+    ]
+}
+
+fn voucher_instructions(code: Option<&str>) -> Vec<String> {
+    vec![
+        format!("Open the merchant app and redeem voucher {}", code.unwrap_or("shown above")),
+        "Vouchers can be used once and are not exchangeable for cash".to_string(),
+    ]
+}
+
+fn points_instructions(_code: Option<&str>) -> Vec<String> {
+    vec![
+        "DealCoins were credited to your balance automatically".to_string(),
+        "Spend them on premium packs".to_string(),
+    ]
+}
+
+fn cashback_instructions(_code: Option<&str>) -> Vec<String> {
+    vec![
+        "Tap Claim to send the cashback to your DealMate wallet".to_string(),
+        "Payouts usually settle within 24 hours".to_string(),
+    ]
+}
+
+fn free_shipping_instructions(code: Option<&str>) -> Vec<String> {
+    vec![
+        format!("Apply code {} on the merchant's checkout page", code.unwrap_or("shown above")),
+        "Shipping fees are waived on your next order".to_string(),
+    ]
+}
+
+fn product_sample_instructions(_code: Option<&str>) -> Vec<String> {
+    vec![
+        "Confirm your delivery address to request the sample".to_string(),
+        "Samples ship within 7 working days while stocks last".to_string(),
+    ]
+}
+
+fn experience_instructions(code: Option<&str>) -> Vec<String> {
+    vec![
+        "Book a slot using the partner link on this reward".to_string(),
+        format!("Quote booking code {} when you arrive", code.unwrap_or("shown above")),
+    ]
+}
+
+fn gift_card_instructions(code: Option<&str>) -> Vec<String> {
+    match code {
+        Some(code) => vec![
+            format!("Add gift card {} to your account on the partner store", code),
+            "The balance can be spent on any eligible order".to_string(),
+        ],
+        None => vec![
+            "Tap Redeem to request your gift card".to_string(),
+            "We'll notify you as soon as the card is delivered".to_string(),
+        ],
+    }
+}
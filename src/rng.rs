@@ -0,0 +1,49 @@
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::sync::Mutex;
+
+/// Source of randomness for reward generation.
+///
+/// Production uses `ThreadRngProvider`; tests inject `SeededRngProvider` so
+/// weighted selection and rarity guarantees are reproducible.
+pub trait RngProvider: Send + Sync {
+    /// Uniform integer in `low..=high`
+    fn gen_range(&self, low: i32, high: i32) -> i32;
+
+    /// Uniform index in `0..len`
+    fn gen_index(&self, len: usize) -> usize;
+}
+
+pub struct ThreadRngProvider;
+
+impl RngProvider for ThreadRngProvider {
+    fn gen_range(&self, low: i32, high: i32) -> i32 {
+        rand::thread_rng().gen_range(low..=high)
+    }
+
+    fn gen_index(&self, len: usize) -> usize {
+        rand::thread_rng().gen_range(0..len)
+    }
+}
+
+pub struct SeededRngProvider {
+    rng: Mutex<ChaCha8Rng>,
+}
+
+impl SeededRngProvider {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: Mutex::new(ChaCha8Rng::seed_from_u64(seed)),
+        }
+    }
+}
+
+impl RngProvider for SeededRngProvider {
+    fn gen_range(&self, low: i32, high: i32) -> i32 {
+        self.rng.lock().unwrap().gen_range(low..=high)
+    }
+
+    fn gen_index(&self, len: usize) -> usize {
+        self.rng.lock().unwrap().gen_range(0..len)
+    }
+}
@@ -0,0 +1,254 @@
+use crate::error::{AppError, Result};
+use crate::pack_admin::{MappingConfig, PackAdminService, UpdatePackRequest};
+use chrono::{DateTime, Utc};
+use cron::Schedule;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool};
+use std::str::FromStr;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+const ROTATION_ACTOR: &str = "system:rotation";
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RotationPool {
+    pub label: String,
+    pub mappings: Vec<MappingConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateRotationRequest {
+    /// Cron expression with seconds, evaluated in UTC, e.g. `0 0 0 * * Mon` for every Monday
+    pub schedule: String,
+    pub pools: Vec<RotationPool>,
+}
+
+/// The pool a rotating pack is currently dealing from, for the pack list badge
+#[derive(Debug, Clone, Serialize)]
+pub struct RotationInfo {
+    pub label: String,
+    pub position: i32,
+    pub pool_count: i64,
+    pub next_change_at: DateTime<Utc>,
+}
+
+fn parse_schedule(schedule: &str) -> Result<Schedule> {
+    Schedule::from_str(schedule)
+        .map_err(|e| AppError::BadRequest(format!("Invalid cron expression '{}': {}", schedule, e)))
+}
+
+fn next_after(schedule: &Schedule, after: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    schedule
+        .after(&after)
+        .next()
+        .ok_or_else(|| AppError::BadRequest("Cron expression never fires again".to_string()))
+}
+
+/// Current rotation state for every active rotating pack
+pub async fn current_rotations(db: &PgPool) -> Result<Vec<(Uuid, RotationInfo)>> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT pr.pack_type_id, pr.current_position, pr.next_change_at, pool.label,
+               (SELECT COUNT(*) FROM pack_rotation_pools p WHERE p.rotation_id = pr.id) AS "pool_count!"
+        FROM pack_rotations pr
+        JOIN pack_rotation_pools pool ON pool.rotation_id = pr.id AND pool.position = pr.current_position
+        WHERE pr.is_active
+        "#
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| (r.pack_type_id, RotationInfo {
+            label: r.label,
+            position: r.current_position,
+            pool_count: r.pool_count,
+            next_change_at: r.next_change_at,
+        }))
+        .collect())
+}
+
+/// Admin setup of rotations and the worker that applies them
+pub struct RotationService {
+    db: PgPool,
+    pack_admin: Arc<PackAdminService>,
+}
+
+impl RotationService {
+    pub fn new(db: PgPool, pack_admin: Arc<PackAdminService>) -> Self {
+        Self { db, pack_admin }
+    }
+
+    /// Create or replace a pack's rotation. The first pool is applied immediately, in the
+    /// same transaction, so the stored position always matches the pack's mappings.
+    pub async fn set_rotation(&self, actor: &str, pack_type_id: Uuid, req: CreateRotationRequest) -> Result<RotationInfo> {
+        let schedule = parse_schedule(&req.schedule)?;
+        if req.pools.len() < 2 {
+            return Err(AppError::BadRequest("A rotation needs at least two pools".to_string()));
+        }
+        if req.pools.iter().any(|p| p.mappings.is_empty()) {
+            return Err(AppError::BadRequest("Every pool needs at least one reward".to_string()));
+        }
+        let next_change_at = next_after(&schedule, Utc::now())?;
+
+        let mut tx = self.db.begin().await?;
+
+        sqlx::query!("DELETE FROM pack_rotations WHERE pack_type_id = $1", pack_type_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let rotation_id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO pack_rotations (pack_type_id, schedule, current_since, next_change_at, created_by)
+            VALUES ($1, $2, NOW(), $3, $4)
+            RETURNING id
+            "#,
+            pack_type_id,
+            req.schedule,
+            next_change_at,
+            actor
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        for (position, pool) in req.pools.iter().enumerate() {
+            let mappings = serde_json::to_value(&pool.mappings)
+                .map_err(|e| AppError::InternalError(format!("Failed to encode pool: {}", e)))?;
+            sqlx::query!(
+                "INSERT INTO pack_rotation_pools (rotation_id, position, label, mappings) VALUES ($1, $2, $3, $4)",
+                rotation_id,
+                position as i32,
+                pool.label,
+                mappings
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let pool_count = req.pools.len() as i64;
+        let first = req.pools.into_iter().next().expect("validated above");
+        self.apply_pool(&mut tx, pack_type_id, &first.label, first.mappings).await?;
+
+        tx.commit().await?;
+        self.pack_admin.invalidate_pack_cache(pack_type_id).await;
+
+        Ok(RotationInfo {
+            label: first.label,
+            position: 0,
+            pool_count,
+            next_change_at,
+        })
+    }
+
+    pub async fn remove_rotation(&self, pack_type_id: Uuid) -> Result<()> {
+        let result = sqlx::query!("DELETE FROM pack_rotations WHERE pack_type_id = $1", pack_type_id)
+            .execute(&self.db)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound("Rotation not found".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Check for due rotations every minute
+    pub fn spawn(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(60));
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.apply_due().await {
+                    error!("Pack rotation failed: {:?}", e);
+                }
+            }
+        })
+    }
+
+    /// Advance every rotation whose change time has passed. Returns how many packs rotated.
+    /// A rotation that fails is logged and retried on the next run; the rest still rotate.
+    pub async fn apply_due(&self) -> Result<usize> {
+        let due = sqlx::query!(
+            r#"
+            SELECT id, pack_type_id, schedule, current_position
+            FROM pack_rotations
+            WHERE is_active AND next_change_at <= NOW()
+            "#
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        let mut rotated = 0;
+        for rotation in due {
+            match self.advance(rotation.id, rotation.pack_type_id, &rotation.schedule, rotation.current_position).await {
+                Ok(true) => rotated += 1,
+                Ok(false) => {}
+                Err(e) => warn!("Rotating pack {} failed: {:?}", rotation.pack_type_id, e),
+            }
+        }
+
+        Ok(rotated)
+    }
+
+    /// Move one rotation to its next pool. The position and the pack's mappings change in
+    /// one transaction, so a failed swap leaves the rotation due rather than skipped.
+    async fn advance(&self, rotation_id: Uuid, pack_type_id: Uuid, schedule: &str, current_position: i32) -> Result<bool> {
+        let schedule = parse_schedule(schedule)?;
+        let mut tx = self.db.begin().await?;
+
+        // Claim the rotation first so concurrent workers don't both advance it
+        let claimed = sqlx::query!(
+            r#"
+            UPDATE pack_rotations
+            SET current_position = (current_position + 1)
+                    % (SELECT COUNT(*) FROM pack_rotation_pools WHERE rotation_id = $1)::int,
+                current_since = NOW(),
+                next_change_at = $3
+            WHERE id = $1 AND current_position = $2 AND next_change_at <= NOW()
+            RETURNING current_position
+            "#,
+            rotation_id,
+            current_position,
+            next_after(&schedule, Utc::now())?
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(claimed) = claimed else { return Ok(false) };
+
+        let pool = sqlx::query!(
+            "SELECT label, mappings FROM pack_rotation_pools WHERE rotation_id = $1 AND position = $2",
+            rotation_id,
+            claimed.current_position
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let mappings: Vec<MappingConfig> = serde_json::from_value(pool.mappings)
+            .map_err(|e| AppError::InternalError(format!("Invalid rotation pool: {}", e)))?;
+        self.apply_pool(&mut tx, pack_type_id, &pool.label, mappings).await?;
+
+        tx.commit().await?;
+        self.pack_admin.invalidate_pack_cache(pack_type_id).await;
+        info!("Rotated pack {} to pool '{}'", pack_type_id, pool.label);
+        Ok(true)
+    }
+
+    /// Swap the pack's mappings through pack admin so the change is versioned
+    async fn apply_pool(&self, conn: &mut PgConnection, pack_type_id: Uuid, label: &str, mappings: Vec<MappingConfig>) -> Result<()> {
+        self.pack_admin
+            .update_pack_in(conn, ROTATION_ACTOR, pack_type_id, UpdatePackRequest {
+                price_coins: None,
+                cooldown_hours: None,
+                min_rewards: None,
+                max_rewards: None,
+                is_active: None,
+                reveal_order: None,
+                mappings: Some(mappings),
+                note: Some(format!("Rotation: {}", label)),
+            })
+            .await?;
+        Ok(())
+    }
+}
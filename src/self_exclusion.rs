@@ -0,0 +1,120 @@
+use crate::error::{AppError, Result};
+use crate::audit;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{PgExecutor, PgPool};
+
+/// Allowed lengths in days for each kind of break
+const COOL_OFF_DAYS: std::ops::RangeInclusive<i64> = 1..=42;
+const SELF_EXCLUSION_DAYS: std::ops::RangeInclusive<i64> = 180..=1825;
+
+#[derive(Debug, Serialize)]
+pub struct ActiveExclusion {
+    pub kind: String,
+    pub ends_at: DateTime<Utc>,
+}
+
+/// The user's running break, if any. With overlapping rows the latest end wins.
+pub async fn active<'e, E: PgExecutor<'e>>(executor: E, user_id: &str) -> Result<Option<ActiveExclusion>> {
+    let exclusion = sqlx::query_as!(
+        ActiveExclusion,
+        r#"
+        SELECT kind, ends_at FROM user_self_exclusions
+        WHERE user_id = $1 AND starts_at <= NOW() AND ends_at > NOW()
+        ORDER BY ends_at DESC
+        LIMIT 1
+        "#,
+        user_id
+    )
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(exclusion)
+}
+
+/// Reject paid purchases while the user is on a break. Every paid flow calls this.
+pub async fn enforce<'e, E: PgExecutor<'e>>(executor: E, user_id: &str) -> Result<()> {
+    if let Some(exclusion) = active(executor, user_id).await? {
+        return Err(AppError::BadRequest(format!(
+            "Pack purchases are paused on your account until {}", exclusion.ends_at.to_rfc3339()
+        )));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StartExclusionRequest {
+    /// cool_off (1-42 days) or self_exclusion (180-1825 days)
+    pub kind: String,
+    pub duration_days: i64,
+}
+
+pub struct SelfExclusionService {
+    db: PgPool,
+}
+
+impl SelfExclusionService {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    pub async fn get(&self, user_id: &str) -> Result<Option<ActiveExclusion>> {
+        active(&self.db, user_id).await
+    }
+
+    /// Start a break, or extend the running one. A break can never end earlier than
+    /// one already in force; the table's trigger also blocks edits and deletes.
+    pub async fn start(&self, user_id: &str, req: StartExclusionRequest) -> Result<ActiveExclusion> {
+        let allowed = match req.kind.as_str() {
+            "cool_off" => COOL_OFF_DAYS,
+            "self_exclusion" => SELF_EXCLUSION_DAYS,
+            other => return Err(AppError::BadRequest(format!("Unknown exclusion kind '{}'", other))),
+        };
+        if !allowed.contains(&req.duration_days) {
+            return Err(AppError::BadRequest(format!(
+                "A {} lasts between {} and {} days", req.kind, allowed.start(), allowed.end()
+            )));
+        }
+        let ends_at = Utc::now() + Duration::days(req.duration_days);
+
+        let mut tx = self.db.begin().await?;
+        // Serialize with concurrent requests for the same user
+        sqlx::query!("SELECT pg_advisory_xact_lock(hashtext($1))", format!("self_exclusion:{}", user_id))
+            .execute(&mut *tx)
+            .await?;
+
+        if let Some(current) = active(&mut *tx, user_id).await? {
+            if current.ends_at >= ends_at {
+                return Err(AppError::BadRequest(format!(
+                    "Your current break already runs until {}; it can only be extended",
+                    current.ends_at.to_rfc3339()
+                )));
+            }
+        }
+
+        let exclusion = sqlx::query_as!(
+            ActiveExclusion,
+            r#"
+            INSERT INTO user_self_exclusions (user_id, kind, ends_at)
+            VALUES ($1, $2, $3)
+            RETURNING kind, ends_at
+            "#,
+            user_id,
+            req.kind,
+            ends_at
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        audit::record(&mut *tx, user_id, "self_exclusion.started", Some(user_id), json!({
+            "kind": exclusion.kind,
+            "duration_days": req.duration_days,
+            "ends_at": exclusion.ends_at,
+        }))
+        .await?;
+
+        tx.commit().await?;
+        Ok(exclusion)
+    }
+}
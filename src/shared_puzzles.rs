@@ -0,0 +1,409 @@
+use crate::error::{AppError, Result};
+use crate::{audit, user_packs};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+pub const CLAIM_POLICIES: [&str; 2] = ["duplicate", "split"];
+
+const PUZZLES_LISTED: i64 = 10;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SharedPuzzleSettings {
+    pub pieces_required: i32,
+    pub reward_pack_type_id: Uuid,
+    pub reward_packs: i32,
+    pub claim_policy: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Contributor {
+    pub user_id: String,
+    pub pieces: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SharedPuzzleView {
+    pub id: Uuid,
+    pub team_id: Uuid,
+    pub pieces_required: i32,
+    pub pieces_collected: i32,
+    pub reward_pack_type_id: Uuid,
+    pub reward_packs: i32,
+    pub claim_policy: String,
+    pub contributors: Vec<Contributor>,
+    /// Packs the viewer gets on claiming; zero until the puzzle is complete
+    pub your_share: i64,
+    pub claimed: bool,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ContributeRequest {
+    pub pieces: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ContributeResponse {
+    /// Pieces actually taken; a contribution is capped at what the puzzle still needs
+    pub pieces_contributed: i32,
+    pub pieces_remaining: i32,
+    pub completed: bool,
+    pub puzzle: SharedPuzzleView,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ClaimResponse {
+    pub puzzle_id: Uuid,
+    pub packs: i64,
+    pub user_pack_ids: Vec<Uuid>,
+}
+
+/// Packs each contributor gets under `policy`. `split` shares the reward by pieces using
+/// largest remainders, earlier contributors first among equals, so the shares always add
+/// up to `reward_packs`. `contributors` must be in first-contribution order.
+pub fn shares(policy: &str, reward_packs: i64, contributors: &[Contributor]) -> Vec<i64> {
+    if policy != "split" {
+        return vec![reward_packs; contributors.len()];
+    }
+    let total: i64 = contributors.iter().map(|c| c.pieces).sum();
+    if total == 0 {
+        return vec![0; contributors.len()];
+    }
+
+    let mut shares: Vec<i64> = contributors.iter().map(|c| reward_packs * c.pieces / total).collect();
+    let mut by_remainder: Vec<usize> = (0..contributors.len()).collect();
+    // Stable sort keeps first-contribution order among equal remainders
+    by_remainder.sort_by_key(|&i| std::cmp::Reverse(reward_packs * contributors[i].pieces % total));
+    let leftover = reward_packs - shares.iter().sum::<i64>();
+    for &i in by_remainder.iter().take(leftover as usize) {
+        shares[i] += 1;
+    }
+    shares
+}
+
+/// Team members pool their puzzle pieces into one puzzle per team. Contributions lock the
+/// puzzle row, so exactly one of them completes it and later pieces start the next puzzle.
+pub struct SharedPuzzleService {
+    db: PgPool,
+}
+
+impl SharedPuzzleService {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    /// The team's puzzle in progress and its most recent completed ones
+    pub async fn team_puzzles(&self, user_id: &str) -> Result<Vec<SharedPuzzleView>> {
+        let mut conn = self.db.acquire().await?;
+        let team_id = Self::team_of(&mut conn, user_id).await?;
+        let ids = sqlx::query_scalar!(
+            r#"
+            SELECT id FROM shared_puzzles
+            WHERE team_id = $1
+            ORDER BY completed_at IS NULL DESC, started_at DESC
+            LIMIT $2
+            "#,
+            team_id,
+            PUZZLES_LISTED
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let mut puzzles = Vec::with_capacity(ids.len());
+        for id in ids {
+            puzzles.push(Self::load_puzzle(&mut conn, id, user_id).await?);
+        }
+        Ok(puzzles)
+    }
+
+    /// Move pieces from the user's own puzzle into the team's puzzle in progress,
+    /// starting one if there is none
+    pub async fn contribute(&self, user_id: &str, req: ContributeRequest) -> Result<ContributeResponse> {
+        if req.pieces <= 0 {
+            return Err(AppError::BadRequest("pieces must be positive".to_string()));
+        }
+
+        let mut tx = self.db.begin().await?;
+        let team_id = Self::team_of(&mut tx, user_id).await?;
+        let settings = sqlx::query!(
+            "SELECT pieces_required, reward_pack_type_id, reward_packs, claim_policy FROM shared_puzzle_settings"
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("Shared puzzles aren't available yet".to_string()))?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO shared_puzzles (team_id, pieces_required, reward_pack_type_id, reward_packs, claim_policy)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (team_id) WHERE completed_at IS NULL DO NOTHING
+            "#,
+            team_id,
+            settings.pieces_required,
+            settings.reward_pack_type_id,
+            settings.reward_packs,
+            settings.claim_policy
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        // Serializes contributions, so only one of them can see the puzzle reach its total.
+        // A contribution that waited on the one completing it finds no open puzzle.
+        let puzzle = sqlx::query!(
+            r#"
+            SELECT id, pieces_required, pieces_collected FROM shared_puzzles
+            WHERE team_id = $1 AND completed_at IS NULL
+            FOR UPDATE
+            "#,
+            team_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::BadRequest(
+            "Your team's puzzle was just completed; contribute again to start the next one".to_string()
+        ))?;
+
+        let pieces = req.pieces.min(puzzle.pieces_required - puzzle.pieces_collected);
+        let taken = sqlx::query!(
+            r#"
+            UPDATE user_lootpack_stats SET puzzle_pieces = puzzle_pieces - $2, updated_at = NOW()
+            WHERE user_id = $1 AND puzzle_pieces >= $2
+            "#,
+            user_id,
+            pieces
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if taken == 0 {
+            return Err(AppError::BadRequest("You don't have that many puzzle pieces".to_string()));
+        }
+
+        sqlx::query!(
+            "INSERT INTO shared_puzzle_contributions (puzzle_id, user_id, pieces) VALUES ($1, $2, $3)",
+            puzzle.id,
+            user_id,
+            pieces
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let updated = sqlx::query!(
+            r#"
+            UPDATE shared_puzzles SET
+                pieces_collected = pieces_collected + $2,
+                completed_at = CASE WHEN pieces_collected + $2 >= pieces_required THEN NOW() END
+            WHERE id = $1
+            RETURNING pieces_required, pieces_collected, completed_at
+            "#,
+            puzzle.id,
+            pieces
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        let completed = updated.completed_at.is_some();
+
+        audit::record(&mut *tx, user_id, "shared_puzzle.contributed", Some(&puzzle.id.to_string()), json!({
+            "team_id": team_id,
+            "pieces": pieces,
+            "completed": completed,
+        }))
+        .await?;
+
+        let view = Self::load_puzzle(&mut tx, puzzle.id, user_id).await?;
+        tx.commit().await?;
+        Ok(ContributeResponse {
+            pieces_contributed: pieces,
+            pieces_remaining: updated.pieces_required - updated.pieces_collected,
+            completed,
+            puzzle: view,
+        })
+    }
+
+    /// Claim the user's share of a completed puzzle. Anyone who contributed can claim,
+    /// including members who have since left the team.
+    pub async fn claim(&self, user_id: &str, puzzle_id: Uuid) -> Result<ClaimResponse> {
+        let mut tx = self.db.begin().await?;
+        let view = Self::load_puzzle(&mut tx, puzzle_id, user_id).await?;
+        if view.completed_at.is_none() {
+            return Err(AppError::BadRequest("This puzzle isn't complete yet".to_string()));
+        }
+        if !view.contributors.iter().any(|c| c.user_id == user_id) {
+            return Err(AppError::BadRequest("Only contributors can claim this puzzle".to_string()));
+        }
+        if view.your_share == 0 {
+            return Err(AppError::BadRequest("Your share of this puzzle's reward is zero packs".to_string()));
+        }
+
+        let claimed = sqlx::query!(
+            r#"
+            INSERT INTO shared_puzzle_claims (puzzle_id, user_id, packs)
+            VALUES ($1, $2, $3)
+            ON CONFLICT DO NOTHING
+            "#,
+            puzzle_id,
+            user_id,
+            view.your_share as i32
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if claimed == 0 {
+            return Err(AppError::BadRequest("You already claimed this puzzle".to_string()));
+        }
+
+        let mut user_pack_ids = Vec::with_capacity(view.your_share as usize);
+        for _ in 0..view.your_share {
+            user_pack_ids
+                .push(user_packs::grant(&mut tx, user_id, view.reward_pack_type_id, "shared_puzzle", None).await?);
+        }
+        sqlx::query!(
+            r#"
+            UPDATE user_lootpack_stats SET puzzle_packs_claimed = puzzle_packs_claimed + $2, updated_at = NOW()
+            WHERE user_id = $1
+            "#,
+            user_id,
+            view.your_share as i32
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        audit::record(&mut *tx, user_id, "shared_puzzle.claimed", Some(&puzzle_id.to_string()), json!({
+            "claim_policy": view.claim_policy,
+            "packs": view.your_share,
+        }))
+        .await?;
+
+        tx.commit().await?;
+        Ok(ClaimResponse { puzzle_id, packs: view.your_share, user_pack_ids })
+    }
+
+    pub async fn get_settings(&self) -> Result<Option<SharedPuzzleSettings>> {
+        let settings = sqlx::query_as!(
+            SharedPuzzleSettings,
+            "SELECT pieces_required, reward_pack_type_id, reward_packs, claim_policy FROM shared_puzzle_settings"
+        )
+        .fetch_optional(&self.db)
+        .await?;
+
+        Ok(settings)
+    }
+
+    /// Changes apply to puzzles started from now on
+    pub async fn set_settings(&self, actor: &str, settings: SharedPuzzleSettings) -> Result<SharedPuzzleSettings> {
+        if settings.pieces_required <= 0 {
+            return Err(AppError::BadRequest("pieces_required must be positive".to_string()));
+        }
+        if settings.reward_packs <= 0 {
+            return Err(AppError::BadRequest("reward_packs must be positive".to_string()));
+        }
+        if !CLAIM_POLICIES.contains(&settings.claim_policy.as_str()) {
+            return Err(AppError::BadRequest(format!("claim_policy must be one of {}", CLAIM_POLICIES.join(", "))));
+        }
+
+        let mut tx = self.db.begin().await?;
+        let known = sqlx::query_scalar!("SELECT id FROM pack_types WHERE id = $1", settings.reward_pack_type_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        if known.is_none() {
+            return Err(AppError::BadRequest(format!("Unknown pack type {}", settings.reward_pack_type_id)));
+        }
+
+        sqlx::query!(
+            r#"
+            INSERT INTO shared_puzzle_settings (id, pieces_required, reward_pack_type_id, reward_packs, claim_policy, updated_by)
+            VALUES (true, $1, $2, $3, $4, $5)
+            ON CONFLICT (id) DO UPDATE SET
+                pieces_required = EXCLUDED.pieces_required, reward_pack_type_id = EXCLUDED.reward_pack_type_id,
+                reward_packs = EXCLUDED.reward_packs, claim_policy = EXCLUDED.claim_policy,
+                updated_by = EXCLUDED.updated_by, updated_at = NOW()
+            "#,
+            settings.pieces_required,
+            settings.reward_pack_type_id,
+            settings.reward_packs,
+            settings.claim_policy,
+            actor
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        audit::record(&mut *tx, actor, "shared_puzzle.settings_updated", None, json!({
+            "pieces_required": settings.pieces_required,
+            "reward_pack_type_id": settings.reward_pack_type_id,
+            "reward_packs": settings.reward_packs,
+            "claim_policy": settings.claim_policy,
+        }))
+        .await?;
+
+        tx.commit().await?;
+        Ok(settings)
+    }
+
+    async fn team_of(conn: &mut PgConnection, user_id: &str) -> Result<Uuid> {
+        sqlx::query_scalar!("SELECT team_id FROM team_members WHERE user_id = $1", user_id)
+            .fetch_optional(&mut *conn)
+            .await?
+            .ok_or_else(|| AppError::BadRequest("You aren't in a team".to_string()))
+    }
+
+    async fn load_puzzle(conn: &mut PgConnection, puzzle_id: Uuid, user_id: &str) -> Result<SharedPuzzleView> {
+        let puzzle = sqlx::query!(
+            r#"
+            SELECT id, team_id, pieces_required, pieces_collected, reward_pack_type_id, reward_packs,
+                   claim_policy, started_at, completed_at,
+                   EXISTS (SELECT 1 FROM shared_puzzle_claims c WHERE c.puzzle_id = p.id AND c.user_id = $2) AS "claimed!"
+            FROM shared_puzzles p
+            WHERE id = $1
+            "#,
+            puzzle_id,
+            user_id
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Puzzle not found".to_string()))?;
+
+        let contributors = sqlx::query_as!(
+            Contributor,
+            r#"
+            SELECT user_id, SUM(pieces) AS "pieces!"
+            FROM shared_puzzle_contributions
+            WHERE puzzle_id = $1
+            GROUP BY user_id
+            ORDER BY MIN(contributed_at)
+            "#,
+            puzzle_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let your_share = if puzzle.completed_at.is_some() {
+            let shares = shares(&puzzle.claim_policy, puzzle.reward_packs as i64, &contributors);
+            contributors
+                .iter()
+                .position(|c| c.user_id == user_id)
+                .map(|i| shares[i])
+                .unwrap_or(0)
+        } else {
+            0
+        };
+
+        Ok(SharedPuzzleView {
+            id: puzzle.id,
+            team_id: puzzle.team_id,
+            pieces_required: puzzle.pieces_required,
+            pieces_collected: puzzle.pieces_collected,
+            reward_pack_type_id: puzzle.reward_pack_type_id,
+            reward_packs: puzzle.reward_packs,
+            claim_policy: puzzle.claim_policy,
+            contributors,
+            your_share,
+            claimed: puzzle.claimed,
+            started_at: puzzle.started_at,
+            completed_at: puzzle.completed_at,
+        })
+    }
+}
@@ -0,0 +1,142 @@
+use crate::error::{AppError, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const SHARE_TTL_DAYS: i64 = 7;
+
+#[derive(Debug, Serialize)]
+pub struct ShareLinkResponse {
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Public view of a shared reward. Never includes the redeemable code.
+#[derive(Debug, Serialize)]
+pub struct SharedRewardView {
+    pub r#type: String,
+    pub title: String,
+    pub value: String,
+    pub description: Option<String>,
+    pub rarity: String,
+    pub source: Option<String>,
+    pub pulled_at: Option<DateTime<Utc>>,
+}
+
+pub struct ShareService {
+    db: PgPool,
+    signing_key: Vec<u8>,
+}
+
+impl ShareService {
+    pub fn new(db: PgPool, signing_key: Vec<u8>) -> Self {
+        Self { db, signing_key }
+    }
+
+    /// Create a public share link for a reward the user owns
+    pub async fn create_share(&self, user_id: &str, reward_id: Uuid) -> Result<ShareLinkResponse> {
+        let owned = sqlx::query!(
+            "SELECT id FROM user_rewards WHERE id = $1 AND user_id = $2",
+            reward_id,
+            user_id
+        )
+        .fetch_optional(&self.db)
+        .await?;
+
+        if owned.is_none() {
+            return Err(AppError::NotFound("Reward not found".to_string()));
+        }
+
+        let expires_at = Utc::now() + Duration::days(SHARE_TTL_DAYS);
+        let share = sqlx::query!(
+            r#"
+            INSERT INTO reward_shares (reward_id, user_id, expires_at)
+            VALUES ($1, $2, $3)
+            RETURNING id
+            "#,
+            reward_id,
+            user_id,
+            expires_at
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(ShareLinkResponse {
+            token: self.sign(share.id),
+            expires_at,
+        })
+    }
+
+    /// Revoke every active share link for a reward
+    pub async fn revoke_shares(&self, user_id: &str, reward_id: Uuid) -> Result<u64> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE reward_shares SET revoked_at = NOW()
+            WHERE reward_id = $1 AND user_id = $2 AND revoked_at IS NULL
+            "#,
+            reward_id,
+            user_id
+        )
+        .execute(&self.db)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Resolve a public token to a sanitized reward view
+    pub async fn get_shared_reward(&self, token: &str) -> Result<SharedRewardView> {
+        let share_id = self.verify(token)
+            .ok_or_else(|| AppError::NotFound("Share link not found".to_string()))?;
+
+        let row = sqlx::query!(
+            r#"
+            SELECT ur.type, ur.title, ur.value, ur.description, ur.rarity, ur.source, ur.created_at
+            FROM reward_shares rs
+            JOIN user_rewards ur ON ur.id = rs.reward_id
+            WHERE rs.id = $1 AND rs.revoked_at IS NULL AND rs.expires_at > NOW()
+            "#,
+            share_id
+        )
+        .fetch_optional(&self.db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Share link not found".to_string()))?;
+
+        Ok(SharedRewardView {
+            r#type: row.r#type,
+            title: row.title,
+            value: row.value,
+            description: row.description,
+            rarity: row.rarity,
+            source: row.source,
+            pulled_at: row.created_at,
+        })
+    }
+
+    /// Tokens are `<share id>.<hmac>` so forged ids are rejected without a DB hit
+    fn sign(&self, share_id: Uuid) -> String {
+        let mut mac = HmacSha256::new_from_slice(&self.signing_key).expect("HMAC accepts any key length");
+        mac.update(share_id.as_bytes());
+        format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(share_id.as_bytes()),
+            URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes())
+        )
+    }
+
+    fn verify(&self, token: &str) -> Option<Uuid> {
+        let (id_part, sig_part) = token.split_once('.')?;
+        let share_id = Uuid::from_slice(&URL_SAFE_NO_PAD.decode(id_part).ok()?).ok()?;
+        let signature = URL_SAFE_NO_PAD.decode(sig_part).ok()?;
+
+        let mut mac = HmacSha256::new_from_slice(&self.signing_key).ok()?;
+        mac.update(share_id.as_bytes());
+        mac.verify_slice(&signature).ok()?;
+        Some(share_id)
+    }
+}
@@ -0,0 +1,110 @@
+use crate::error::{AppError, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+/// Pack pages are hot; stats are reused per pack for this long
+const CACHE_TTL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TopReward {
+    pub template_id: Uuid,
+    pub title: String,
+    pub rarity: String,
+    pub times_pulled: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SocialProof {
+    pub pack_type_id: Uuid,
+    /// Opens since midnight UTC
+    pub opened_today: i64,
+    pub last_legendary_at: Option<DateTime<Utc>>,
+    pub minutes_since_last_legendary: Option<i64>,
+    /// Rarest reward pulled from the pack today, most pulled first among equals
+    pub top_reward_today: Option<TopReward>,
+    pub computed_at: DateTime<Utc>,
+}
+
+/// "Opened X times today"-style stats for pack pages, read from the daily rollups and
+/// recent pulls the open path already maintains
+pub struct SocialProofService {
+    db: PgPool,
+    cache: RwLock<HashMap<Uuid, (Instant, SocialProof)>>,
+}
+
+impl SocialProofService {
+    pub fn new(db: PgPool) -> Self {
+        Self { db, cache: RwLock::new(HashMap::new()) }
+    }
+
+    pub async fn get(&self, pack_type_id: Uuid) -> Result<SocialProof> {
+        let cached = self.cache.read().await
+            .get(&pack_type_id)
+            .filter(|(computed, _)| computed.elapsed() < CACHE_TTL)
+            .map(|(_, proof)| proof.clone());
+        let mut proof = match cached {
+            Some(proof) => proof,
+            None => {
+                let proof = self.compute(pack_type_id).await?;
+                self.cache.write().await.insert(pack_type_id, (Instant::now(), proof.clone()));
+                proof
+            }
+        };
+
+        // Keep the relative time current even when the rest comes from the cache
+        proof.minutes_since_last_legendary = proof
+            .last_legendary_at
+            .map(|at| Utc::now().signed_duration_since(at).num_minutes().max(0));
+        Ok(proof)
+    }
+
+    async fn compute(&self, pack_type_id: Uuid) -> Result<SocialProof> {
+        let today = Utc::now().date_naive();
+        let stats = sqlx::query!(
+            r#"
+            SELECT COALESCE(dps.opens, 0) AS "opened_today!",
+                   (SELECT MAX(rp.pulled_at) FROM recent_pulls rp
+                    WHERE rp.pack_type_id = pt.id AND rp.rarity = 'legendary') AS last_legendary_at
+            FROM pack_types pt
+            LEFT JOIN daily_pack_stats_live dps ON dps.pack_type_id = pt.id AND dps.day = $2
+            WHERE pt.id = $1 AND pt.is_active = true
+            "#,
+            pack_type_id,
+            today
+        )
+        .fetch_optional(&self.db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Pack type not found".to_string()))?;
+
+        let top_reward_today = sqlx::query_as!(
+            TopReward,
+            r#"
+            SELECT rt.id AS template_id, rt.title, rt.rarity, dts.dealt AS "times_pulled!"
+            FROM daily_template_stats_live dts
+            JOIN reward_templates rt ON rt.id = dts.template_id
+            WHERE dts.day = $2 AND dts.pack_type_id = $1
+            ORDER BY CASE rt.rarity WHEN 'legendary' THEN 4 WHEN 'epic' THEN 3 WHEN 'rare' THEN 2 ELSE 1 END DESC,
+                     dts.dealt DESC
+            LIMIT 1
+            "#,
+            pack_type_id,
+            today
+        )
+        .fetch_optional(&self.db)
+        .await?;
+
+        Ok(SocialProof {
+            pack_type_id,
+            opened_today: stats.opened_today,
+            last_legendary_at: stats.last_legendary_at,
+            minutes_since_last_legendary: None,
+            top_reward_today,
+            computed_at: Utc::now(),
+        })
+    }
+}
@@ -0,0 +1,298 @@
+use crate::error::{AppError, Result};
+use crate::audit;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{PgConnection, PgPool};
+
+/// Rolling windows limits apply over, in hours
+const PERIODS: [(&str, i32); 2] = [("daily", 24), ("weekly", 24 * 7)];
+
+/// Cooling-off delay before a raised or removed personal limit applies
+const RAISE_DELAY_HOURS: i64 = 72;
+
+/// The stricter of two limits, `None` being unlimited
+fn tighter(a: Option<i32>, b: Option<i32>) -> Option<i32> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// `new` allows more than `current`
+fn is_raise(new: Option<i32>, current: Option<i32>) -> bool {
+    match (new, current) {
+        (None, Some(_)) => true,
+        (Some(new), Some(current)) => new > current,
+        _ => false,
+    }
+}
+
+fn window_hours(period: &str) -> Result<i32> {
+    PERIODS
+        .iter()
+        .find(|(name, _)| *name == period)
+        .map(|(_, hours)| *hours)
+        .ok_or_else(|| AppError::BadRequest(format!("Unknown period '{}'; use daily or weekly", period)))
+}
+
+#[derive(Debug, Serialize)]
+pub struct PendingLimits {
+    pub coin_limit: Option<i32>,
+    pub open_limit: Option<i32>,
+    pub effective_at: DateTime<Utc>,
+}
+
+/// A period's limits and what's left of them. Limits cover pack purchases and reroll
+/// fees; free, daily and granted packs don't count.
+#[derive(Debug, Serialize)]
+pub struct PeriodAllowance {
+    pub period: String,
+    pub window_hours: i32,
+    /// Effective limits: the stricter of the user's own limit and the default
+    pub coin_limit: Option<i32>,
+    pub open_limit: Option<i32>,
+    /// The user's own limits, if set
+    pub personal_coin_limit: Option<i32>,
+    pub personal_open_limit: Option<i32>,
+    pub coins_spent: i64,
+    pub opens: i64,
+    pub coins_remaining: Option<i64>,
+    pub opens_remaining: Option<i64>,
+    pub pending: Option<PendingLimits>,
+}
+
+/// Apply queued raises whose cooling-off period is over
+async fn apply_due_raises(conn: &mut PgConnection, user_id: &str) -> Result<()> {
+    sqlx::query!(
+        r#"
+        UPDATE user_spending_limits
+        SET coin_limit = pending_coin_limit, open_limit = pending_open_limit,
+            pending_coin_limit = NULL, pending_open_limit = NULL, pending_effective_at = NULL,
+            updated_at = NOW()
+        WHERE user_id = $1 AND pending_effective_at <= NOW()
+        "#,
+        user_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Limits and usage for every period
+pub async fn allowances(conn: &mut PgConnection, user_id: &str) -> Result<Vec<PeriodAllowance>> {
+    apply_due_raises(conn, user_id).await?;
+
+    let rows = sqlx::query!(
+        r#"
+        SELECT d.period, d.coin_limit AS default_coin_limit, d.open_limit AS default_open_limit,
+               u.coin_limit AS "coin_limit?", u.open_limit AS "open_limit?",
+               u.pending_coin_limit AS "pending_coin_limit?", u.pending_open_limit AS "pending_open_limit?",
+               u.pending_effective_at AS "pending_effective_at?"
+        FROM spending_limit_defaults d
+        LEFT JOIN user_spending_limits u ON u.period = d.period AND u.user_id = $1
+        ORDER BY d.period
+        "#,
+        user_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut allowances = Vec::with_capacity(rows.len());
+    for row in rows {
+        let window_hours = window_hours(&row.period)?;
+        let usage = sqlx::query!(
+            r#"
+            SELECT COALESCE(-SUM(delta), 0)::BIGINT AS "coins_spent!",
+                   COUNT(*) FILTER (WHERE reason = 'pack_purchase') AS "opens!"
+            FROM coin_ledger
+            WHERE user_id = $1 AND reason IN ('pack_purchase', 'reward_reroll')
+              AND created_at > NOW() - make_interval(hours => $2)
+            "#,
+            user_id,
+            window_hours
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        let coin_limit = tighter(row.coin_limit, row.default_coin_limit);
+        let open_limit = tighter(row.open_limit, row.default_open_limit);
+        allowances.push(PeriodAllowance {
+            period: row.period,
+            window_hours,
+            coin_limit,
+            open_limit,
+            personal_coin_limit: row.coin_limit,
+            personal_open_limit: row.open_limit,
+            coins_spent: usage.coins_spent,
+            opens: usage.opens,
+            coins_remaining: coin_limit.map(|l| (l as i64 - usage.coins_spent).max(0)),
+            opens_remaining: open_limit.map(|l| (l as i64 - usage.opens).max(0)),
+            pending: row.pending_effective_at.map(|effective_at| PendingLimits {
+                coin_limit: row.pending_coin_limit,
+                open_limit: row.pending_open_limit,
+                effective_at,
+            }),
+        });
+    }
+
+    Ok(allowances)
+}
+
+/// Reject a purchase that would go over any limit. Runs in the opening transaction
+/// after the user's stats row is locked, so concurrent opens can't both squeeze in.
+pub async fn enforce(conn: &mut PgConnection, user_id: &str, pack_cost: i32) -> Result<()> {
+    for allowance in allowances(conn, user_id).await? {
+        if allowance.opens_remaining == Some(0) {
+            return Err(AppError::BadRequest(format!(
+                "You've reached your {} limit of {} pack opens",
+                allowance.period,
+                allowance.open_limit.unwrap_or_default()
+            )));
+        }
+        if let Some(remaining) = allowance.coins_remaining {
+            if (pack_cost as i64) > remaining {
+                return Err(AppError::BadRequest(format!(
+                    "This pack costs {} DealCoins but you have {} left of your {} limit of {}",
+                    pack_cost,
+                    remaining,
+                    allowance.period,
+                    allowance.coin_limit.unwrap_or_default()
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The user's new personal limits for a period; `null` removes a personal limit
+#[derive(Debug, Deserialize)]
+pub struct UpdateLimitsRequest {
+    pub period: String,
+    pub coin_limit: Option<i32>,
+    pub open_limit: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateDefaultLimitsRequest {
+    pub coin_limit: Option<i32>,
+    pub open_limit: Option<i32>,
+}
+
+pub struct SpendingLimitService {
+    db: PgPool,
+}
+
+impl SpendingLimitService {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    pub async fn get_limits(&self, user_id: &str) -> Result<Vec<PeriodAllowance>> {
+        let mut conn = self.db.acquire().await?;
+        allowances(&mut conn, user_id).await
+    }
+
+    /// Lower limits apply immediately. Raising or removing a limit is queued for
+    /// `RAISE_DELAY_HOURS`; until then the stricter value stays in force. A new
+    /// request replaces any queued raise.
+    pub async fn update_limits(&self, user_id: &str, req: UpdateLimitsRequest) -> Result<Vec<PeriodAllowance>> {
+        window_hours(&req.period)?;
+        if req.coin_limit.map(|l| l <= 0).unwrap_or(false) || req.open_limit.map(|l| l <= 0).unwrap_or(false) {
+            return Err(AppError::BadRequest("Limits must be positive".to_string()));
+        }
+
+        let mut tx = self.db.begin().await?;
+        apply_due_raises(&mut tx, user_id).await?;
+
+        let current = sqlx::query!(
+            "SELECT coin_limit, open_limit FROM user_spending_limits WHERE user_id = $1 AND period = $2 FOR UPDATE",
+            user_id,
+            req.period
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        let (current_coins, current_opens) = current.map(|c| (c.coin_limit, c.open_limit)).unwrap_or((None, None));
+
+        let raising = is_raise(req.coin_limit, current_coins) || is_raise(req.open_limit, current_opens);
+        let (coin_limit, open_limit) = if raising {
+            (tighter(current_coins, req.coin_limit), tighter(current_opens, req.open_limit))
+        } else {
+            (req.coin_limit, req.open_limit)
+        };
+        let pending_effective_at = raising.then(|| Utc::now() + Duration::hours(RAISE_DELAY_HOURS));
+
+        sqlx::query!(
+            r#"
+            INSERT INTO user_spending_limits
+                (user_id, period, coin_limit, open_limit, pending_coin_limit, pending_open_limit, pending_effective_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (user_id, period) DO UPDATE SET
+                coin_limit = EXCLUDED.coin_limit,
+                open_limit = EXCLUDED.open_limit,
+                pending_coin_limit = EXCLUDED.pending_coin_limit,
+                pending_open_limit = EXCLUDED.pending_open_limit,
+                pending_effective_at = EXCLUDED.pending_effective_at,
+                updated_at = NOW()
+            "#,
+            user_id,
+            req.period,
+            coin_limit,
+            open_limit,
+            if raising { req.coin_limit } else { None },
+            if raising { req.open_limit } else { None },
+            pending_effective_at
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        audit::record(
+            &mut *tx,
+            user_id,
+            if raising { "limits.raise_requested" } else { "limits.lowered" },
+            Some(user_id),
+            json!({
+                "period": req.period,
+                "coin_limit": req.coin_limit,
+                "open_limit": req.open_limit,
+                "effective_at": pending_effective_at,
+            }),
+        )
+        .await?;
+
+        let allowances = allowances(&mut tx, user_id).await?;
+        tx.commit().await?;
+        Ok(allowances)
+    }
+
+    pub async fn set_default(&self, actor: &str, period: &str, req: UpdateDefaultLimitsRequest) -> Result<()> {
+        window_hours(period)?;
+        if req.coin_limit.map(|l| l <= 0).unwrap_or(false) || req.open_limit.map(|l| l <= 0).unwrap_or(false) {
+            return Err(AppError::BadRequest("Limits must be positive".to_string()));
+        }
+
+        let mut tx = self.db.begin().await?;
+        sqlx::query!(
+            r#"
+            UPDATE spending_limit_defaults
+            SET coin_limit = $2, open_limit = $3, updated_by = $4, updated_at = NOW()
+            WHERE period = $1
+            "#,
+            period,
+            req.coin_limit,
+            req.open_limit,
+            actor
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        audit::record(&mut *tx, actor, "limits.default_updated", Some(period), json!({
+            "coin_limit": req.coin_limit,
+            "open_limit": req.open_limit,
+        }))
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,190 @@
+use crate::error::{AppError, Result};
+use crate::daily_reset::{self, DailyClock};
+use crate::{audit, self_exclusion};
+use crate::wallet::{self, CoinWallet};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use serde_json::json;
+use sqlx::{PgConnection, PgPool};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// How long after lapsing a streak can still be repaired
+const REPAIR_WINDOW_HOURS: i64 = 48;
+/// Repair price: a base plus a per-day amount for the lost streak, capped
+const REPAIR_BASE_COINS: i32 = 50;
+const REPAIR_COINS_PER_DAY: i32 = 10;
+const REPAIR_MAX_COINS: i32 = 500;
+
+pub fn repair_price(lost_streak: i32) -> i32 {
+    (REPAIR_BASE_COINS + REPAIR_COINS_PER_DAY * lost_streak).min(REPAIR_MAX_COINS)
+}
+
+/// When a streak of `streak` days lapsed, if it has by `now`.
+/// Single-day streaks have nothing worth repairing.
+pub fn lapsed_at(clock: &DailyClock, streak: i32, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let breaks_at = clock.streak_until?;
+    (streak >= 2 && breaks_at <= now).then_some(breaks_at)
+}
+
+/// Remember a lapsed streak so it can be repaired. Idempotent per break.
+pub async fn record_break(conn: &mut PgConnection, user_id: &str, lost_streak: i32, broken_at: DateTime<Utc>) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO streak_breaks (user_id, lost_streak, broken_at, repair_deadline, price_coins)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (user_id, broken_at) DO NOTHING
+        "#,
+        user_id,
+        lost_streak,
+        broken_at,
+        broken_at + Duration::hours(REPAIR_WINDOW_HOURS),
+        repair_price(lost_streak)
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+pub struct RepairOffer {
+    pub break_id: Uuid,
+    pub lost_streak: i32,
+    pub price_coins: i32,
+    pub broken_at: DateTime<Utc>,
+    pub repair_deadline: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RepairResponse {
+    pub daily_streak: i32,
+    pub price_paid: i32,
+    pub new_balance: i32,
+}
+
+pub struct StreakRepairService {
+    db: PgPool,
+    wallet: Arc<dyn CoinWallet>,
+}
+
+impl StreakRepairService {
+    pub fn new(db: PgPool, wallet: Arc<dyn CoinWallet>) -> Self {
+        Self { db, wallet }
+    }
+
+    /// The repair on offer, if the user's latest break can still be repaired
+    pub async fn offer(&self, user_id: &str) -> Result<Option<RepairOffer>> {
+        let mut tx = self.db.begin().await?;
+        Self::detect(&mut tx, user_id).await?;
+        let offer = Self::open_offer(&mut tx, user_id).await?;
+        tx.commit().await?;
+        Ok(offer)
+    }
+
+    /// Buy back the latest lapsed streak. Claims made since the break add on top of it,
+    /// and a user who hasn't claimed since can claim right away without losing it again.
+    pub async fn repair(&self, user_id: &str) -> Result<RepairResponse> {
+        let mut tx = self.db.begin().await?;
+        if self_exclusion::active(&mut *tx, user_id).await?.is_some() {
+            return Err(AppError::BadRequest("Streak repairs are paused on your account during a break".to_string()));
+        }
+
+        // Same lock as daily claims, so a claim can't slip in between
+        let stats = sqlx::query!(
+            r#"
+            SELECT daily_streak, last_daily_claim FROM user_lootpack_stats
+            WHERE user_id = $1
+            FOR UPDATE
+            "#,
+            user_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("There's no streak to repair".to_string()))?;
+
+        Self::detect(&mut tx, user_id).await?;
+        let offer = Self::open_offer(&mut tx, user_id)
+            .await?
+            .ok_or_else(|| AppError::BadRequest("There's no broken streak you can repair".to_string()))?;
+
+        let repaired = sqlx::query!(
+            "UPDATE streak_breaks SET repaired_at = NOW() WHERE id = $1 AND repaired_at IS NULL",
+            offer.break_id
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if repaired == 0 {
+            return Err(AppError::BadRequest("This streak was already repaired".to_string()));
+        }
+
+        let entry = self
+            .wallet
+            .apply(&mut tx, user_id, -offer.price_coins, "streak_repair", Some(&offer.break_id.to_string()))
+            .await?;
+        let new_balance = entry.balance;
+
+        let claimed_since = stats.last_daily_claim.is_some_and(|last| last > offer.broken_at);
+        let daily_streak = if claimed_since {
+            offer.lost_streak + stats.daily_streak.unwrap_or(1)
+        } else {
+            // Nothing claimed since; the daily pack opens now and the next claim continues
+            daily_reset::reopen_streak(&mut tx, user_id).await?;
+            offer.lost_streak
+        };
+        sqlx::query!(
+            "UPDATE user_lootpack_stats SET daily_streak = $2, updated_at = NOW() WHERE user_id = $1",
+            user_id,
+            daily_streak
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        audit::record(&mut *tx, user_id, "streak.repaired", Some(&offer.break_id.to_string()), json!({
+            "lost_streak": offer.lost_streak,
+            "daily_streak": daily_streak,
+            "price_coins": offer.price_coins,
+        }))
+        .await?;
+
+        wallet::commit_with_wallet(self.wallet.as_ref(), tx, &[entry]).await?;
+        Ok(RepairResponse { daily_streak, price_paid: offer.price_coins, new_balance })
+    }
+
+    /// Record a break the user hasn't come back to claim after yet
+    async fn detect(conn: &mut PgConnection, user_id: &str) -> Result<()> {
+        let streak = sqlx::query_scalar!("SELECT daily_streak FROM user_lootpack_stats WHERE user_id = $1", user_id)
+            .fetch_optional(&mut *conn)
+            .await?
+            .flatten()
+            .unwrap_or(1);
+
+        let clock = daily_reset::load(conn, user_id).await?;
+        if let Some(broken_at) = lapsed_at(&clock, streak, Utc::now()) {
+            record_break(conn, user_id, streak, broken_at).await?;
+        }
+        Ok(())
+    }
+
+    /// Only the latest break counts; a newer one supersedes any older offer
+    async fn open_offer(conn: &mut PgConnection, user_id: &str) -> Result<Option<RepairOffer>> {
+        let offer = sqlx::query_as!(
+            RepairOffer,
+            r#"
+            SELECT id AS break_id, lost_streak, price_coins, broken_at, repair_deadline
+            FROM (
+                SELECT * FROM streak_breaks WHERE user_id = $1
+                ORDER BY broken_at DESC
+                LIMIT 1
+            ) latest
+            WHERE repaired_at IS NULL AND repair_deadline > NOW()
+            "#,
+            user_id
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(offer)
+    }
+}
@@ -0,0 +1,453 @@
+use crate::audit;
+use crate::code_crypto::CodeCipher;
+use crate::error::{AppError, Result};
+use crate::lootpacks::{DAILY_PACK_AD_PLACEMENT, DAILY_PACK_AD_WINDOW_HOURS};
+use crate::inbox::{self, NewMessage};
+use crate::notifications::{self, NotificationEvent};
+use crate::user_packs;
+use chrono::{DateTime, Duration, Utc};
+use bigdecimal::BigDecimal;
+use serde::Serialize;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// How much of each history the user lookup returns
+const RECENT_OPENS_LISTED: i64 = 20;
+const REWARDS_LISTED: i64 = 50;
+const AD_COMPLETIONS_LISTED: i64 = 20;
+const STREAK_TIMELINE_DAYS: i64 = 60;
+
+/// Support actions need a reason someone reviewing the audit log can act on
+const MIN_REASON_CHARS: usize = 10;
+/// How far one extension pushes a reward's expiry; each reward can be extended once
+const EXPIRY_EXTENSION_DAYS: i64 = 7;
+/// Code resends per reward per day, so a compromised support account can't spam a user
+const MAX_CODE_RESENDS_PER_DAY: i64 = 3;
+/// Compensation packs support may grant one user per day
+const MAX_GRANTS_PER_USER_PER_DAY: i64 = 3;
+/// Trailing characters of a code support may see
+const CODE_HINT_CHARS: usize = 4;
+
+#[derive(Debug, Serialize)]
+pub struct SupportUserView {
+    pub user_id: String,
+    pub stats: SupportStats,
+    pub recent_opens: Vec<SupportPackOpen>,
+    pub rewards: Vec<SupportReward>,
+    pub ad_gate: AdGateHistory,
+    pub streak: StreakTimeline,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SupportStats {
+    pub deal_coins: Option<i32>,
+    pub level: Option<i32>,
+    pub member_status: Option<String>,
+    pub total_packs_opened: Option<i32>,
+    pub total_savings_inr: Option<BigDecimal>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SupportPackOpen {
+    pub pack_history_id: Uuid,
+    pub pack_name: String,
+    pub rewards_count: i32,
+    pub total_value_inr: Option<BigDecimal>,
+    pub opened_at: Option<DateTime<Utc>>,
+}
+
+/// A reward as support sees it: its effective status, and only the tail of its code
+#[derive(Debug, Serialize)]
+pub struct SupportReward {
+    pub id: Uuid,
+    pub title: String,
+    pub r#type: String,
+    pub rarity: String,
+    pub status: String,
+    pub code_hint: Option<String>,
+    pub fulfillment_status: Option<String>,
+    pub payout_status: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AdGateHistory {
+    /// Whether a completed ad currently unlocks the daily pack
+    pub daily_pack_unlocked: bool,
+    pub completions: Vec<AdCompletion>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AdCompletion {
+    pub placement: String,
+    pub completed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StreakTimeline {
+    pub daily_streak: Option<i32>,
+    pub timezone: Option<String>,
+    pub next_claim_at: Option<DateTime<Utc>>,
+    pub streak_until: Option<DateTime<Utc>>,
+    /// Newest first: daily claims, breaks, repairs and vacations
+    pub events: Vec<StreakEvent>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StreakEvent {
+    pub kind: String,
+    pub at: DateTime<Utc>,
+    pub details: Value,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GrantedPack {
+    pub user_pack_id: Uuid,
+    pub pack_type_id: Uuid,
+    pub pack_name: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExtendExpiryResponse {
+    pub reward_id: Uuid,
+    pub previous_expires_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Read-mostly tooling for support agents. Lookups are audited like the actions, since
+/// they expose a user's history to staff.
+pub struct SupportService {
+    db: PgPool,
+    codes: Arc<CodeCipher>,
+}
+
+impl SupportService {
+    pub fn new(db: PgPool, codes: Arc<CodeCipher>) -> Self {
+        Self { db, codes }
+    }
+
+    /// Everything support usually asks a user about, in one response
+    pub async fn lookup_user(&self, actor: &str, user_id: &str) -> Result<SupportUserView> {
+        // Unlike the user-facing stats, a lookup never provisions an account
+        let stats = sqlx::query_as!(
+            SupportStats,
+            r#"
+            SELECT deal_coins, level, member_status, total_packs_opened, total_savings_inr, created_at
+            FROM user_lootpack_stats
+            WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .fetch_optional(&self.db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+        let recent_opens = sqlx::query_as!(
+            SupportPackOpen,
+            r#"
+            SELECT ph.id AS pack_history_id, pt.name AS pack_name, ph.rewards_count,
+                   ph.total_value_inr, ph.opened_at
+            FROM user_pack_history ph
+            JOIN pack_types pt ON pt.id = ph.pack_type_id
+            WHERE ph.user_id = $1
+            ORDER BY ph.opened_at DESC
+            LIMIT $2
+            "#,
+            user_id,
+            RECENT_OPENS_LISTED
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        // Codes are stored encrypted, so the hint is cut from the decrypted code below
+        let mut rewards = sqlx::query_as!(
+            SupportReward,
+            r#"
+            SELECT id, title, type, rarity,
+                   CASE
+                       WHEN voided_at IS NOT NULL THEN 'voided'
+                       WHEN traded_in_at IS NOT NULL THEN 'traded_in'
+                       WHEN COALESCE(is_used, false) THEN 'used'
+                       WHEN escrow_trade_id IS NOT NULL THEN 'in_trade'
+                       WHEN reserved_until > NOW() THEN 'reserved'
+                       WHEN expires_at <= NOW() THEN 'expired'
+                       ELSE 'available'
+                   END AS "status!",
+                   code AS code_hint,
+                   fulfillment_status, payout_status, expires_at, created_at
+            FROM user_rewards
+            WHERE user_id = $1
+            ORDER BY created_at DESC
+            LIMIT $2
+            "#,
+            user_id,
+            REWARDS_LISTED
+        )
+        .fetch_all(&self.db)
+        .await?;
+        for reward in &mut rewards {
+            reward.code_hint = self.codes.reveal_opt(reward.code_hint.take())?.map(|code| code_hint(&code));
+        }
+
+        // The ads service owns this table; see `ads::TableAdVerifier`
+        let completions = sqlx::query_as!(
+            AdCompletion,
+            r#"
+            SELECT ad_placement AS placement, completed_at AS "completed_at!"
+            FROM user_ad_interactions
+            WHERE user_id = $1 AND is_completed = true AND completed_at IS NOT NULL
+            ORDER BY completed_at DESC
+            LIMIT $2
+            "#,
+            user_id,
+            AD_COMPLETIONS_LISTED
+        )
+        .fetch_all(&self.db)
+        .await?;
+        let unlock_after = Utc::now() - Duration::hours(DAILY_PACK_AD_WINDOW_HOURS);
+        let daily_pack_unlocked = completions
+            .iter()
+            .any(|c| c.placement == DAILY_PACK_AD_PLACEMENT && c.completed_at > unlock_after);
+
+        let streak = self.streak_timeline(user_id).await?;
+
+        audit::record(&self.db, actor, "support.user_viewed", Some(user_id), json!({})).await?;
+
+        Ok(SupportUserView {
+            user_id: user_id.to_string(),
+            stats,
+            recent_opens,
+            rewards,
+            ad_gate: AdGateHistory { daily_pack_unlocked, completions },
+            streak,
+        })
+    }
+
+    async fn streak_timeline(&self, user_id: &str) -> Result<StreakTimeline> {
+        let current = sqlx::query!(
+            r#"
+            SELECT uls.daily_streak, udc.timezone AS "timezone?", udc.next_claim_at, udc.streak_until
+            FROM user_lootpack_stats uls
+            LEFT JOIN user_daily_clock udc ON udc.user_id = uls.user_id
+            WHERE uls.user_id = $1
+            "#,
+            user_id
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        // Daily packs are the free packs opened straight from a claim, not from inventory
+        let events = sqlx::query_as!(
+            StreakEvent,
+            r#"
+            SELECT kind AS "kind!", at AS "at!", details AS "details!" FROM (
+                SELECT 'daily_claim' AS kind, ph.opened_at AS at, jsonb_build_object('pack_history_id', ph.id) AS details
+                FROM user_pack_history ph
+                JOIN pack_types pt ON pt.id = ph.pack_type_id AND pt.type = 'free'
+                WHERE ph.user_id = $1
+                  AND NOT EXISTS (SELECT 1 FROM user_packs up WHERE up.pack_history_id = ph.id)
+                UNION ALL
+                SELECT 'streak_broken', broken_at,
+                       jsonb_build_object('lost_streak', lost_streak, 'repair_deadline', repair_deadline)
+                FROM streak_breaks WHERE user_id = $1
+                UNION ALL
+                SELECT 'streak_repaired', repaired_at, jsonb_build_object('lost_streak', lost_streak, 'price_coins', price_coins)
+                FROM streak_breaks WHERE user_id = $1 AND repaired_at IS NOT NULL
+                UNION ALL
+                SELECT 'vacation_started', starts_at, jsonb_build_object('ends_at', ends_at)
+                FROM streak_vacations WHERE user_id = $1
+                UNION ALL
+                SELECT 'vacation_ended', COALESCE(ended_early_at, ends_at),
+                       jsonb_build_object('ended_early', ended_early_at IS NOT NULL)
+                FROM streak_vacations WHERE user_id = $1 AND COALESCE(ended_early_at, ends_at) <= NOW()
+            ) timeline
+            WHERE at > NOW() - make_interval(days => $2)
+            ORDER BY at DESC
+            "#,
+            user_id,
+            STREAK_TIMELINE_DAYS as i32
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(StreakTimeline {
+            daily_streak: current.daily_streak,
+            timezone: current.timezone,
+            next_claim_at: current.next_claim_at,
+            streak_until: current.streak_until,
+            events,
+        })
+    }
+
+    /// Send an unused reward's code to the user again through the notification service
+    pub async fn resend_code(&self, actor: &str, user_id: &str, reward_id: Uuid, reason: &str) -> Result<()> {
+        let reason = require_reason(reason)?;
+        let mut tx = self.db.begin().await?;
+
+        let reward = sqlx::query!(
+            r#"
+            SELECT title, code, expires_at FROM user_rewards
+            WHERE id = $1 AND user_id = $2
+              AND COALESCE(is_used, false) = false AND traded_in_at IS NULL
+              AND (expires_at IS NULL OR expires_at > NOW())
+            FOR UPDATE
+            "#,
+            reward_id,
+            user_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("No unused, unexpired reward with this id".to_string()))?;
+        // Queued as stored; the notification service decrypts it only when delivering
+        let code = reward.code.ok_or_else(|| AppError::BadRequest("Reward has no code".to_string()))?;
+
+        let resent_today = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!" FROM audit_log
+            WHERE action = 'support.reward_code_resent' AND subject = $1
+              AND created_at > NOW() - INTERVAL '1 day'
+            "#,
+            reward_id.to_string()
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if resent_today >= MAX_CODE_RESENDS_PER_DAY {
+            return Err(AppError::BadRequest("Code was already resent too often today".to_string()));
+        }
+
+        let event = NotificationEvent::RewardCodeResent {
+            reward_id,
+            title: reward.title,
+            code,
+            expires_at: reward.expires_at,
+        };
+        let event_key = format!("reward_code_resent:{}:{}", reward_id, Utc::now().timestamp_millis());
+        notifications::queue(&mut *tx, user_id, &event_key, &event).await?;
+
+        audit::record(&mut *tx, actor, "support.reward_code_resent", Some(&reward_id.to_string()), json!({
+            "user_id": user_id,
+            "reason": reason,
+        }))
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Push an unused reward's expiry back by a week, from now if it already lapsed
+    pub async fn extend_expiry(&self, actor: &str, user_id: &str, reward_id: Uuid, reason: &str) -> Result<ExtendExpiryResponse> {
+        let reason = require_reason(reason)?;
+        let mut tx = self.db.begin().await?;
+
+        let previous_expires_at = sqlx::query_scalar!(
+            r#"
+            SELECT expires_at AS "expires_at!" FROM user_rewards
+            WHERE id = $1 AND user_id = $2
+              AND COALESCE(is_used, false) = false AND traded_in_at IS NULL AND expires_at IS NOT NULL
+            FOR UPDATE
+            "#,
+            reward_id,
+            user_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("No unused, expiring reward with this id".to_string()))?;
+
+        let already_extended = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM audit_log WHERE action = 'support.reward_expiry_extended' AND subject = $1
+            ) AS "extended!"
+            "#,
+            reward_id.to_string()
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if already_extended {
+            return Err(AppError::BadRequest("Reward expiry was already extended once".to_string()));
+        }
+
+        let expires_at = previous_expires_at.max(Utc::now()) + Duration::days(EXPIRY_EXTENSION_DAYS);
+        sqlx::query!("UPDATE user_rewards SET expires_at = $2 WHERE id = $1", reward_id, expires_at)
+            .execute(&mut *tx)
+            .await?;
+
+        audit::record(&mut *tx, actor, "support.reward_expiry_extended", Some(&reward_id.to_string()), json!({
+            "user_id": user_id,
+            "reason": reason,
+            "previous_expires_at": previous_expires_at,
+            "expires_at": expires_at,
+        }))
+        .await?;
+        tx.commit().await?;
+
+        Ok(ExtendExpiryResponse { reward_id, previous_expires_at, expires_at })
+    }
+
+    /// Put a compensation pack in the user's unopened packs and tell them in their inbox.
+    /// Compensation packs don't count toward the monthly free-claim quota.
+    pub async fn grant_pack(&self, actor: &str, user_id: &str, pack_type_id: Uuid, reason: &str) -> Result<GrantedPack> {
+        let reason = require_reason(reason)?;
+        let mut tx = self.db.begin().await?;
+
+        let pack_name = sqlx::query_scalar!(
+            "SELECT name FROM pack_types WHERE id = $1 AND is_active = true",
+            pack_type_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Pack type not found".to_string()))?;
+
+        let granted_today = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!" FROM audit_log
+            WHERE action = 'support.pack_granted' AND subject = $1
+              AND created_at > NOW() - INTERVAL '1 day'
+            "#,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if granted_today >= MAX_GRANTS_PER_USER_PER_DAY {
+            return Err(AppError::BadRequest("This user was already granted too many packs today".to_string()));
+        }
+
+        let user_pack_id = user_packs::grant(&mut tx, user_id, pack_type_id, "compensation", None).await?;
+        inbox::send(&mut tx, user_id, NewMessage {
+            kind: "grant",
+            title: &format!("You received a {}", pack_name),
+            body: "It's waiting in your unopened packs.",
+            claim_pack_type_id: None,
+            expires_at: None,
+        })
+        .await?;
+
+        audit::record(&mut *tx, actor, "support.pack_granted", Some(user_id), json!({
+            "user_pack_id": user_pack_id,
+            "pack_type_id": pack_type_id,
+            "reason": reason,
+        }))
+        .await?;
+        tx.commit().await?;
+
+        Ok(GrantedPack { user_pack_id, pack_type_id, pack_name })
+    }
+}
+
+fn code_hint(code: &str) -> String {
+    let skip = code.chars().count().saturating_sub(CODE_HINT_CHARS);
+    format!("…{}", code.chars().skip(skip).collect::<String>())
+}
+
+fn require_reason(reason: &str) -> Result<&str> {
+    let reason = reason.trim();
+    if reason.chars().count() < MIN_REASON_CHARS {
+        return Err(AppError::BadRequest(format!(
+            "A reason of at least {} characters is required", MIN_REASON_CHARS
+        )));
+    }
+    Ok(reason)
+}
@@ -0,0 +1,439 @@
+use crate::error::{AppError, Result};
+use crate::inbox::{self, NewMessage};
+use crate::{audit, user_packs};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{PgConnection, PgPool};
+use std::sync::Arc;
+use tracing::{error, info};
+use uuid::Uuid;
+
+const WORKER_INTERVAL_SECONDS: u64 = 60;
+const DISTRIBUTION_BATCH_SIZE: i64 = 500;
+const MAX_NAME_LENGTH: usize = 32;
+
+/// Add a coin spend to the spender's team meter. Runs in the open's transaction; every fill
+/// it completes snapshots the current members, who the worker then grants the team pack to.
+pub async fn record_spend(conn: &mut PgConnection, user_id: &str, coins: i32) -> Result<()> {
+    if coins <= 0 {
+        return Ok(());
+    }
+    let Some(settings) = sqlx::query!("SELECT goal_coins, reward_pack_type_id FROM team_settings")
+        .fetch_optional(&mut *conn)
+        .await?
+    else {
+        return Ok(());
+    };
+
+    let team_id = sqlx::query_scalar!(
+        r#"
+        UPDATE team_members SET contributed_coins = contributed_coins + $2
+        WHERE user_id = $1
+        RETURNING team_id
+        "#,
+        user_id,
+        coins as i64
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+    let Some(team_id) = team_id else {
+        return Ok(());
+    };
+
+    // The team row lock orders concurrent spends, so each fill is numbered once
+    let team = sqlx::query!(
+        "UPDATE teams SET meter_coins = meter_coins + $2 WHERE id = $1 RETURNING meter_coins, fills",
+        team_id,
+        coins as i64
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let completed = team.meter_coins / settings.goal_coins;
+    if completed == 0 {
+        return Ok(());
+    }
+
+    sqlx::query!(
+        "UPDATE teams SET meter_coins = meter_coins - $2, fills = fills + $3 WHERE id = $1",
+        team_id,
+        completed * settings.goal_coins,
+        completed as i32
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    for fill_number in team.fills + 1..=team.fills + completed as i32 {
+        sqlx::query!(
+            "INSERT INTO team_fills (team_id, fill_number, reward_pack_type_id) VALUES ($1, $2, $3)",
+            team_id,
+            fill_number,
+            settings.reward_pack_type_id
+        )
+        .execute(&mut *conn)
+        .await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO team_fill_rewards (team_id, fill_number, user_id)
+            SELECT team_id, $2, user_id FROM team_members WHERE team_id = $1
+            "#,
+            team_id,
+            fill_number
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TeamSettings {
+    pub goal_coins: i64,
+    pub reward_pack_type_id: Uuid,
+    pub max_members: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TeamMember {
+    pub user_id: String,
+    pub contributed_coins: i64,
+    pub joined_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TeamView {
+    pub id: Uuid,
+    pub name: String,
+    pub owner_id: String,
+    pub meter_coins: i64,
+    /// Coins per fill; `None` while team packs aren't configured
+    pub goal_coins: Option<i64>,
+    pub fills: i32,
+    pub members: Vec<TeamMember>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateTeamRequest {
+    pub name: String,
+}
+
+pub struct TeamService {
+    db: PgPool,
+}
+
+impl TeamService {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    pub async fn create_team(&self, user_id: &str, req: CreateTeamRequest) -> Result<TeamView> {
+        let name = req.name.trim();
+        if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+            return Err(AppError::BadRequest(format!("Team names are 1 to {} characters", MAX_NAME_LENGTH)));
+        }
+
+        let mut tx = self.db.begin().await?;
+        let team_id = sqlx::query_scalar!(
+            "INSERT INTO teams (name, owner_id) VALUES ($1, $2) ON CONFLICT DO NOTHING RETURNING id",
+            name,
+            user_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("That team name is taken".to_string()))?;
+
+        Self::add_member(&mut tx, team_id, user_id).await?;
+        audit::record(&mut *tx, user_id, "team.created", Some(&team_id.to_string()), json!({ "name": name }))
+            .await?;
+
+        let team = Self::load_team(&mut tx, team_id).await?;
+        tx.commit().await?;
+        Ok(team)
+    }
+
+    pub async fn get_team(&self, team_id: Uuid) -> Result<TeamView> {
+        let mut conn = self.db.acquire().await?;
+        Self::load_team(&mut conn, team_id).await
+    }
+
+    pub async fn my_team(&self, user_id: &str) -> Result<Option<TeamView>> {
+        let mut conn = self.db.acquire().await?;
+        let team_id = sqlx::query_scalar!("SELECT team_id FROM team_members WHERE user_id = $1", user_id)
+            .fetch_optional(&mut *conn)
+            .await?;
+        match team_id {
+            Some(team_id) => Ok(Some(Self::load_team(&mut conn, team_id).await?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn join_team(&self, user_id: &str, team_id: Uuid) -> Result<TeamView> {
+        let mut tx = self.db.begin().await?;
+        // Lock the team so concurrent joins can't overshoot the member cap
+        sqlx::query_scalar!("SELECT id FROM teams WHERE id = $1 FOR UPDATE", team_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Team not found".to_string()))?;
+
+        let max_members = sqlx::query_scalar!("SELECT max_members FROM team_settings")
+            .fetch_optional(&mut *tx)
+            .await?;
+        if let Some(max_members) = max_members {
+            let members = sqlx::query_scalar!(
+                r#"SELECT COUNT(*) AS "count!" FROM team_members WHERE team_id = $1"#,
+                team_id
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+            if members >= max_members as i64 {
+                return Err(AppError::BadRequest("This team is full".to_string()));
+            }
+        }
+
+        Self::add_member(&mut tx, team_id, user_id).await?;
+        audit::record(&mut *tx, user_id, "team.joined", Some(&team_id.to_string()), json!({})).await?;
+
+        let team = Self::load_team(&mut tx, team_id).await?;
+        tx.commit().await?;
+        Ok(team)
+    }
+
+    /// Leave the user's team. Fills already reached are still granted; ownership passes to
+    /// the longest-standing member.
+    pub async fn leave_team(&self, user_id: &str) -> Result<()> {
+        let mut tx = self.db.begin().await?;
+        // Member row before team row, the same order spends take them in
+        let team_id = sqlx::query_scalar!("SELECT team_id FROM team_members WHERE user_id = $1 FOR UPDATE", user_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| AppError::BadRequest("You aren't in a team".to_string()))?;
+
+        // Same lock as spends, so a fill never snapshots a half-departed member
+        sqlx::query!("SELECT id FROM teams WHERE id = $1 FOR UPDATE", team_id)
+            .fetch_one(&mut *tx)
+            .await?;
+        sqlx::query!("DELETE FROM team_members WHERE user_id = $1", user_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!(
+            r#"
+            UPDATE teams SET owner_id = COALESCE(
+                (SELECT user_id FROM team_members WHERE team_id = $1 ORDER BY joined_at LIMIT 1),
+                owner_id
+            )
+            WHERE id = $1 AND owner_id = $2
+            "#,
+            team_id,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        audit::record(&mut *tx, user_id, "team.left", Some(&team_id.to_string()), json!({})).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn get_settings(&self) -> Result<Option<TeamSettings>> {
+        let settings = sqlx::query_as!(
+            TeamSettings,
+            "SELECT goal_coins, reward_pack_type_id, max_members FROM team_settings"
+        )
+        .fetch_optional(&self.db)
+        .await?;
+
+        Ok(settings)
+    }
+
+    /// Changes apply to fills from now on; fills already reached keep their pack
+    pub async fn set_settings(&self, actor: &str, settings: TeamSettings) -> Result<TeamSettings> {
+        if settings.goal_coins <= 0 {
+            return Err(AppError::BadRequest("goal_coins must be positive".to_string()));
+        }
+        if settings.max_members < 2 {
+            return Err(AppError::BadRequest("max_members must be at least 2".to_string()));
+        }
+
+        let mut tx = self.db.begin().await?;
+        let known = sqlx::query_scalar!("SELECT id FROM pack_types WHERE id = $1", settings.reward_pack_type_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        if known.is_none() {
+            return Err(AppError::BadRequest(format!("Unknown pack type {}", settings.reward_pack_type_id)));
+        }
+
+        sqlx::query!(
+            r#"
+            INSERT INTO team_settings (id, goal_coins, reward_pack_type_id, max_members, updated_by)
+            VALUES (true, $1, $2, $3, $4)
+            ON CONFLICT (id) DO UPDATE SET
+                goal_coins = EXCLUDED.goal_coins, reward_pack_type_id = EXCLUDED.reward_pack_type_id,
+                max_members = EXCLUDED.max_members, updated_by = EXCLUDED.updated_by, updated_at = NOW()
+            "#,
+            settings.goal_coins,
+            settings.reward_pack_type_id,
+            settings.max_members,
+            actor
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        audit::record(&mut *tx, actor, "team.settings_updated", None, json!({
+            "goal_coins": settings.goal_coins,
+            "reward_pack_type_id": settings.reward_pack_type_id,
+            "max_members": settings.max_members,
+        }))
+        .await?;
+
+        tx.commit().await?;
+        Ok(settings)
+    }
+
+    /// Grant team packs for reached fills until none are left
+    pub async fn run_due(&self) -> Result<()> {
+        let mut granted = 0;
+        loop {
+            let batch = self.distribute_batch().await?;
+            if batch == 0 {
+                break;
+            }
+            granted += batch;
+        }
+        if granted > 0 {
+            info!("Granted {} team packs", granted);
+        }
+        Ok(())
+    }
+
+    /// One transaction's worth of grants. Reward rows are locked and skipped when taken, so
+    /// replicas running the worker side by side never grant the same row twice.
+    async fn distribute_batch(&self) -> Result<usize> {
+        let mut tx = self.db.begin().await?;
+        let batch = sqlx::query!(
+            r#"
+            SELECT r.team_id, r.fill_number, r.user_id, f.reward_pack_type_id, t.name AS team_name
+            FROM team_fill_rewards r
+            JOIN team_fills f ON f.team_id = r.team_id AND f.fill_number = r.fill_number
+            JOIN teams t ON t.id = r.team_id
+            WHERE r.rewarded_at IS NULL
+            ORDER BY f.filled_at
+            LIMIT $1
+            FOR UPDATE OF r SKIP LOCKED
+            "#,
+            DISTRIBUTION_BATCH_SIZE
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        for reward in &batch {
+            let user_pack_id =
+                user_packs::grant(&mut tx, &reward.user_id, reward.reward_pack_type_id, "team_fill", None).await?;
+            sqlx::query!(
+                r#"
+                UPDATE team_fill_rewards SET user_pack_id = $4, rewarded_at = NOW()
+                WHERE team_id = $1 AND fill_number = $2 AND user_id = $3
+                "#,
+                reward.team_id,
+                reward.fill_number,
+                reward.user_id,
+                user_pack_id
+            )
+            .execute(&mut *tx)
+            .await?;
+            inbox::send(&mut tx, &reward.user_id, NewMessage {
+                kind: "grant",
+                title: &format!("{} filled the team meter!", reward.team_name),
+                body: "Your team's spending filled the meter. Your team pack is waiting in your packs.",
+                claim_pack_type_id: None,
+                expires_at: None,
+            })
+            .await?;
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE team_fills f SET distributed_at = NOW()
+            WHERE f.distributed_at IS NULL
+              AND NOT EXISTS (
+                  SELECT 1 FROM team_fill_rewards r
+                  WHERE r.team_id = f.team_id AND r.fill_number = f.fill_number AND r.rewarded_at IS NULL
+              )
+            "#
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(batch.len())
+    }
+
+    /// Run `run_due` every minute
+    pub fn spawn_worker(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(WORKER_INTERVAL_SECONDS));
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.run_due().await {
+                    error!("Team pack distribution failed: {:?}", e);
+                }
+            }
+        })
+    }
+
+    async fn add_member(conn: &mut PgConnection, team_id: Uuid, user_id: &str) -> Result<()> {
+        let joined = sqlx::query!(
+            "INSERT INTO team_members (user_id, team_id) VALUES ($1, $2) ON CONFLICT (user_id) DO NOTHING",
+            user_id,
+            team_id
+        )
+        .execute(&mut *conn)
+        .await?
+        .rows_affected();
+        if joined == 0 {
+            return Err(AppError::BadRequest("You're already in a team; leave it first".to_string()));
+        }
+        Ok(())
+    }
+
+    async fn load_team(conn: &mut PgConnection, team_id: Uuid) -> Result<TeamView> {
+        let team = sqlx::query!(
+            r#"
+            SELECT t.id, t.name, t.owner_id, t.meter_coins, t.fills, t.created_at,
+                   (SELECT goal_coins FROM team_settings) AS goal_coins
+            FROM teams t
+            WHERE t.id = $1
+            "#,
+            team_id
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Team not found".to_string()))?;
+
+        let members = sqlx::query_as!(
+            TeamMember,
+            r#"
+            SELECT user_id, contributed_coins, joined_at
+            FROM team_members
+            WHERE team_id = $1
+            ORDER BY contributed_coins DESC, joined_at
+            "#,
+            team_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(TeamView {
+            id: team.id,
+            name: team.name,
+            owner_id: team.owner_id,
+            meter_coins: team.meter_coins,
+            goal_coins: team.goal_coins,
+            fills: team.fills,
+            members,
+            created_at: team.created_at,
+        })
+    }
+}
@@ -0,0 +1,121 @@
+use crate::error::{AppError, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+#[derive(Debug, FromRow, Serialize)]
+pub struct TemplateVersion {
+    pub version: i32,
+    pub snapshot: Value,
+    pub changed_by: Option<String>,
+    pub changed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, FromRow, Serialize)]
+pub struct MappingChange {
+    pub reward_template_id: Uuid,
+    pub weight: Option<i32>,
+    pub version: i32,
+    pub operation: String,
+    pub changed_by: Option<String>,
+    pub changed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MappingHistoryQuery {
+    /// Reconstruct the pack's weights as they were at this instant
+    pub as_of: Option<DateTime<Utc>>,
+}
+
+/// Admin view of how templates and pack weights changed over time.
+/// History rows are written by triggers (see migration 0020).
+pub struct TemplateHistoryService {
+    db: PgPool,
+}
+
+impl TemplateHistoryService {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    /// Every version of a template, newest first
+    pub async fn template_history(&self, template_id: Uuid) -> Result<Vec<TemplateVersion>> {
+        let versions = sqlx::query_as!(
+            TemplateVersion,
+            r#"
+            SELECT version, snapshot, changed_by, changed_at
+            FROM reward_template_versions
+            WHERE template_id = $1
+            ORDER BY version DESC
+            "#,
+            template_id
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        if versions.is_empty() {
+            return Err(AppError::NotFound("Reward template not found".to_string()));
+        }
+        Ok(versions)
+    }
+
+    /// A single template version, e.g. the one stamped on a past reward
+    pub async fn template_version(&self, template_id: Uuid, version: i32) -> Result<TemplateVersion> {
+        sqlx::query_as!(
+            TemplateVersion,
+            r#"
+            SELECT version, snapshot, changed_by, changed_at
+            FROM reward_template_versions
+            WHERE template_id = $1 AND version = $2
+            "#,
+            template_id,
+            version
+        )
+        .fetch_optional(&self.db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Template version not found".to_string()))
+    }
+
+    /// Weight changes for a pack, newest first. With `as_of`, returns only the
+    /// mappings that were live at that time, each at its latest version then.
+    pub async fn mapping_history(&self, pack_type_id: Uuid, query: &MappingHistoryQuery) -> Result<Vec<MappingChange>> {
+        let changes = match query.as_of {
+            None => sqlx::query_as!(
+                MappingChange,
+                r#"
+                SELECT reward_template_id, weight, version, operation, changed_by, changed_at
+                FROM pack_reward_mapping_history
+                WHERE pack_type_id = $1
+                ORDER BY changed_at DESC
+                LIMIT 500
+                "#,
+                pack_type_id
+            )
+            .fetch_all(&self.db)
+            .await?,
+            Some(as_of) => sqlx::query_as!(
+                MappingChange,
+                r#"
+                SELECT reward_template_id, weight, version, operation, changed_by, changed_at
+                FROM (
+                    SELECT DISTINCT ON (reward_template_id)
+                           reward_template_id, weight, version, operation, changed_by, changed_at
+                    FROM pack_reward_mapping_history
+                    WHERE pack_type_id = $1 AND changed_at <= $2
+                    ORDER BY reward_template_id, changed_at DESC
+                ) latest
+                WHERE operation <> 'delete'
+                ORDER BY weight DESC NULLS LAST
+                "#,
+                pack_type_id,
+                as_of
+            )
+            .fetch_all(&self.db)
+            .await?,
+        };
+
+        Ok(changes)
+    }
+}
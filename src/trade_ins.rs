@@ -0,0 +1,179 @@
+use crate::error::{AppError, Result};
+use crate::{audit, reward_types};
+use crate::wallet::{self, CoinWallet};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{FromRow, PgPool};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Caps over a rolling 24 hours so trade-ins can't become a coin faucet
+const DAILY_TRADE_IN_LIMIT: i64 = 10;
+const DAILY_TRADE_IN_COIN_CAP: i64 = 300;
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct TradeInRate {
+    pub rarity: String,
+    pub coins: i32,
+    pub updated_by: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateTradeInRateRequest {
+    pub coins: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TradeInResponse {
+    pub reward_id: Uuid,
+    pub coins_credited: i32,
+    pub new_balance: i32,
+    pub trade_ins_remaining_today: i64,
+}
+
+pub struct TradeInService {
+    db: PgPool,
+    wallet: Arc<dyn CoinWallet>,
+}
+
+impl TradeInService {
+    pub fn new(db: PgPool, wallet: Arc<dyn CoinWallet>) -> Self {
+        Self { db, wallet }
+    }
+
+    pub async fn list_rates(&self) -> Result<Vec<TradeInRate>> {
+        let rates = sqlx::query_as!(
+            TradeInRate,
+            "SELECT rarity, coins, updated_by, updated_at FROM trade_in_rates ORDER BY coins"
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(rates)
+    }
+
+    pub async fn set_rate(&self, actor: &str, rarity: &str, req: UpdateTradeInRateRequest) -> Result<TradeInRate> {
+        if req.coins < 0 {
+            return Err(AppError::BadRequest("coins can't be negative".to_string()));
+        }
+
+        let mut tx = self.db.begin().await?;
+        let rate = sqlx::query_as!(
+            TradeInRate,
+            r#"
+            INSERT INTO trade_in_rates (rarity, coins, updated_by)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (rarity) DO UPDATE SET
+                coins = EXCLUDED.coins, updated_by = EXCLUDED.updated_by, updated_at = NOW()
+            RETURNING rarity, coins, updated_by, updated_at
+            "#,
+            rarity,
+            req.coins,
+            actor
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        audit::record(&mut *tx, actor, "trade_in.rate_updated", Some(rarity), json!({ "coins": req.coins })).await?;
+        tx.commit().await?;
+        Ok(rate)
+    }
+
+    /// Consume an unused reward and credit DealCoins for its rarity
+    pub async fn trade_in(&self, user_id: &str, reward_id: Uuid) -> Result<TradeInResponse> {
+        let mut tx = self.db.begin().await?;
+
+        // Lock the user's stats row first so concurrent trade-ins can't both fit under the cap
+        sqlx::query!("SELECT user_id FROM user_lootpack_stats WHERE user_id = $1 FOR UPDATE", user_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Reward not found".to_string()))?;
+
+        let reward = sqlx::query!(
+            r#"
+            SELECT type, rarity, is_used, expires_at, reserved_until, escrow_trade_id,
+                   payout_status, fulfillment_status
+            FROM user_rewards
+            WHERE id = $1 AND user_id = $2
+            FOR UPDATE
+            "#,
+            reward_id,
+            user_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Reward not found".to_string()))?;
+
+        let now = Utc::now();
+        if reward_types::spec(&reward.r#type).map(|t| t.credits_coins).unwrap_or(false) {
+            return Err(AppError::BadRequest("Coin rewards were already credited and can't be traded in".to_string()));
+        }
+        if reward.is_used.unwrap_or(false) || reward.payout_status.is_some() || reward.fulfillment_status.is_some() {
+            return Err(AppError::BadRequest("Reward has already been used".to_string()));
+        }
+        if reward.expires_at.map(|exp| exp <= now).unwrap_or(false) {
+            return Err(AppError::BadRequest("Reward has expired".to_string()));
+        }
+        if reward.reserved_until.map(|until| until > now).unwrap_or(false) || reward.escrow_trade_id.is_some() {
+            return Err(AppError::BadRequest("Reward is on hold and can't be traded in right now".to_string()));
+        }
+
+        let coins = sqlx::query_scalar!("SELECT coins FROM trade_in_rates WHERE rarity = $1", reward.rarity)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| AppError::BadRequest(format!("{} rewards can't be traded in", reward.rarity)))?;
+
+        let usage = sqlx::query!(
+            r#"
+            SELECT COUNT(*) AS "count!", COALESCE(SUM(delta), 0)::BIGINT AS "coins!"
+            FROM coin_ledger
+            WHERE user_id = $1 AND reason = 'trade_in' AND created_at > NOW() - INTERVAL '24 hours'
+            "#,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if usage.count >= DAILY_TRADE_IN_LIMIT {
+            return Err(AppError::BadRequest(format!(
+                "You can trade in up to {} rewards a day", DAILY_TRADE_IN_LIMIT
+            )));
+        }
+        if usage.coins + coins as i64 > DAILY_TRADE_IN_COIN_CAP {
+            return Err(AppError::BadRequest(format!(
+                "Trade-ins are capped at {} DealCoins a day; {} left today",
+                DAILY_TRADE_IN_COIN_CAP,
+                (DAILY_TRADE_IN_COIN_CAP - usage.coins).max(0)
+            )));
+        }
+
+        sqlx::query!(
+            "UPDATE user_rewards SET is_used = true, used_at = NOW(), traded_in_at = NOW() WHERE id = $1",
+            reward_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let reference = reward_id.to_string();
+        let entry = self.wallet.apply(&mut tx, user_id, coins, "trade_in", Some(&reference)).await?;
+        let new_balance = entry.balance;
+
+        audit::record(&mut *tx, user_id, "reward.traded_in", Some(&reference), json!({
+            "type": reward.r#type,
+            "rarity": reward.rarity,
+            "coins": coins,
+        }))
+        .await?;
+
+        wallet::commit_with_wallet(self.wallet.as_ref(), tx, &[entry]).await?;
+
+        Ok(TradeInResponse {
+            reward_id,
+            coins_credited: coins,
+            new_balance,
+            trade_ins_remaining_today: DAILY_TRADE_IN_LIMIT - usage.count - 1,
+        })
+    }
+}
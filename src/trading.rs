@@ -0,0 +1,464 @@
+use crate::error::{AppError, Result};
+use crate::audit;
+use crate::feature_flags::FeatureFlagService;
+use crate::reward_types;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{PgConnection, PgPool};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+const TRADING_FLAG: &str = "trading";
+const MAX_ITEMS_PER_SIDE: usize = 10;
+const OFFER_TTL_HOURS: i64 = 48;
+
+/// A locked trade normally settles right after it's accepted; one still locked after
+/// this long (e.g. the process died in between) is settled or cancelled by the sweeper
+const LOCK_TIMEOUT_SECONDS: i64 = 60;
+const SWEEP_INTERVAL_SECONDS: u64 = 60;
+
+#[derive(Debug, Serialize)]
+pub struct Trade {
+    pub id: Uuid,
+    pub proposer_id: String,
+    pub recipient_id: String,
+    /// offered, locked, swapped or cancelled
+    pub status: String,
+    pub offered_reward_ids: Vec<Uuid>,
+    pub requested_reward_ids: Vec<Uuid>,
+    pub expires_at: DateTime<Utc>,
+    pub locked_at: Option<DateTime<Utc>>,
+    pub swapped_at: Option<DateTime<Utc>>,
+    pub cancelled_at: Option<DateTime<Utc>>,
+    pub cancel_reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProposeTradeRequest {
+    pub recipient_id: String,
+    /// The proposer's rewards
+    pub offered_reward_ids: Vec<Uuid>,
+    /// The recipient's rewards
+    pub requested_reward_ids: Vec<Uuid>,
+}
+
+async fn load_trade(conn: &mut PgConnection, trade_id: Uuid) -> Result<Trade> {
+    let row = sqlx::query!(
+        r#"
+        SELECT id, proposer_id, recipient_id, status, expires_at, locked_at, swapped_at,
+               cancelled_at, cancel_reason, created_at
+        FROM trades WHERE id = $1
+        "#,
+        trade_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Trade not found".to_string()))?;
+
+    let items = sqlx::query!(
+        "SELECT reward_id, from_user_id FROM trade_items WHERE trade_id = $1 ORDER BY reward_id",
+        trade_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let (offered, requested): (Vec<_>, Vec<_>) = items.into_iter().partition(|i| i.from_user_id == row.proposer_id);
+    Ok(Trade {
+        id: row.id,
+        proposer_id: row.proposer_id,
+        recipient_id: row.recipient_id,
+        status: row.status,
+        offered_reward_ids: offered.into_iter().map(|i| i.reward_id).collect(),
+        requested_reward_ids: requested.into_iter().map(|i| i.reward_id).collect(),
+        expires_at: row.expires_at,
+        locked_at: row.locked_at,
+        swapped_at: row.swapped_at,
+        cancelled_at: row.cancelled_at,
+        cancel_reason: row.cancel_reason,
+        created_at: row.created_at,
+    })
+}
+
+/// Lock every reward in the trade, in id order so overlapping trades can't deadlock, and
+/// check each is still owned by its side and free to trade. Rewards already held by
+/// `escrow_trade_id` count as free; any other escrow means another trade has them.
+async fn lock_items(
+    conn: &mut PgConnection,
+    items: &HashMap<Uuid, String>,
+    escrow_trade_id: Option<Uuid>,
+) -> Result<()> {
+    let mut reward_ids: Vec<Uuid> = items.keys().copied().collect();
+    reward_ids.sort();
+
+    let rows = sqlx::query!(
+        r#"
+        SELECT id, user_id, type, is_used, expires_at, reserved_until, escrow_trade_id
+        FROM user_rewards
+        WHERE id = ANY($1)
+        ORDER BY id
+        FOR UPDATE
+        "#,
+        &reward_ids
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let now = Utc::now();
+    for reward_id in &reward_ids {
+        let problem = match rows.iter().find(|r| r.id == *reward_id) {
+            None => Some("not found"),
+            Some(r) if Some(&r.user_id) != items.get(reward_id) => Some("no longer owned by the trader"),
+            Some(r) if !reward_types::spec(&r.r#type).map(|t| t.tradable).unwrap_or(false) => Some("not tradable"),
+            Some(r) if r.is_used.unwrap_or(false) => Some("already used"),
+            Some(r) if r.expires_at.map(|exp| exp <= now).unwrap_or(false) => Some("expired"),
+            Some(r) if r.reserved_until.map(|until| until > now).unwrap_or(false) => Some("reserved for a checkout"),
+            Some(r) if r.escrow_trade_id.is_some() && r.escrow_trade_id != escrow_trade_id => Some("held in another trade"),
+            Some(_) => None,
+        };
+        if let Some(problem) = problem {
+            return Err(AppError::BadRequest(format!("Reward {} is {}", reward_id, problem)));
+        }
+    }
+    Ok(())
+}
+
+/// Reward id -> owning user for every item in a trade
+async fn trade_items(conn: &mut PgConnection, trade_id: Uuid) -> Result<HashMap<Uuid, String>> {
+    let items = sqlx::query!("SELECT reward_id, from_user_id FROM trade_items WHERE trade_id = $1", trade_id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+    Ok(items.into_iter().map(|i| (i.reward_id, i.from_user_id)).collect())
+}
+
+/// Release a locked trade's escrow and close it
+async fn cancel_locked(conn: &mut PgConnection, trade_id: Uuid, reason: &str) -> Result<()> {
+    sqlx::query!("UPDATE user_rewards SET escrow_trade_id = NULL WHERE escrow_trade_id = $1", trade_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query!(
+        r#"
+        UPDATE trades SET status = 'cancelled', cancelled_at = NOW(), cancel_reason = $2, updated_at = NOW()
+        WHERE id = $1
+        "#,
+        trade_id,
+        reason
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    audit::record(&mut *conn, "system", "trade.cancelled", Some(&trade_id.to_string()), json!({ "reason": reason })).await?;
+    Ok(())
+}
+
+/// Reward trades between users. A trade is `offered` by the proposer, `locked` when the
+/// recipient accepts (both sides' rewards go into escrow), then `swapped` in a single
+/// transaction holding row locks on every reward, so an exchange never half-completes.
+/// Open offers can be withdrawn or declined; stale ones are cancelled by the sweeper.
+pub struct TradingService {
+    db: PgPool,
+    flags: Arc<FeatureFlagService>,
+}
+
+impl TradingService {
+    pub fn new(db: PgPool, flags: Arc<FeatureFlagService>) -> Self {
+        Self { db, flags }
+    }
+
+    pub async fn list_trades(&self, user_id: &str) -> Result<Vec<Trade>> {
+        let ids = sqlx::query_scalar!(
+            r#"
+            SELECT id FROM trades
+            WHERE proposer_id = $1 OR recipient_id = $1
+            ORDER BY created_at DESC
+            LIMIT 100
+            "#,
+            user_id
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        let mut conn = self.db.acquire().await?;
+        let mut trades = Vec::with_capacity(ids.len());
+        for id in ids {
+            trades.push(load_trade(&mut conn, id).await?);
+        }
+        Ok(trades)
+    }
+
+    pub async fn get_trade(&self, user_id: &str, trade_id: Uuid) -> Result<Trade> {
+        let mut conn = self.db.acquire().await?;
+        let trade = load_trade(&mut conn, trade_id).await?;
+        if trade.proposer_id != user_id && trade.recipient_id != user_id {
+            return Err(AppError::NotFound("Trade not found".to_string()));
+        }
+        Ok(trade)
+    }
+
+    /// Offer some of the proposer's rewards for some of the recipient's
+    pub async fn propose(&self, user_id: &str, req: ProposeTradeRequest) -> Result<Trade> {
+        self.flags.require_enabled(user_id, TRADING_FLAG).await?;
+
+        if req.recipient_id == user_id {
+            return Err(AppError::BadRequest("You can't trade with yourself".to_string()));
+        }
+        let mut items = HashMap::new();
+        for (side, owner) in [(&req.offered_reward_ids, user_id), (&req.requested_reward_ids, req.recipient_id.as_str())] {
+            if side.is_empty() || side.len() > MAX_ITEMS_PER_SIDE {
+                return Err(AppError::BadRequest(format!(
+                    "Each side of a trade needs between 1 and {} rewards", MAX_ITEMS_PER_SIDE
+                )));
+            }
+            for reward_id in side {
+                if items.insert(*reward_id, owner.to_string()).is_some() {
+                    return Err(AppError::BadRequest(format!("Reward {} is listed twice", reward_id)));
+                }
+            }
+        }
+
+        let mut tx = self.db.begin().await?;
+        lock_items(&mut tx, &items, None).await?;
+
+        let trade_id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO trades (proposer_id, recipient_id, expires_at)
+            VALUES ($1, $2, $3)
+            RETURNING id
+            "#,
+            user_id,
+            req.recipient_id,
+            Utc::now() + Duration::hours(OFFER_TTL_HOURS)
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let (reward_ids, owners): (Vec<Uuid>, Vec<String>) = items.into_iter().unzip();
+        sqlx::query!(
+            r#"
+            INSERT INTO trade_items (trade_id, reward_id, from_user_id)
+            SELECT $1, reward_id, from_user_id FROM UNNEST($2::uuid[], $3::text[]) AS i(reward_id, from_user_id)
+            "#,
+            trade_id,
+            &reward_ids,
+            &owners
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        audit::record(&mut *tx, user_id, "trade.proposed", Some(&trade_id.to_string()), json!({
+            "recipient_id": req.recipient_id,
+            "offered_reward_ids": req.offered_reward_ids,
+            "requested_reward_ids": req.requested_reward_ids,
+        }))
+        .await?;
+
+        let trade = load_trade(&mut tx, trade_id).await?;
+        tx.commit().await?;
+        Ok(trade)
+    }
+
+    /// Accept an offer: put both sides in escrow, then swap them
+    pub async fn accept(&self, user_id: &str, trade_id: Uuid) -> Result<Trade> {
+        self.flags.require_enabled(user_id, TRADING_FLAG).await?;
+
+        let mut tx = self.db.begin().await?;
+        let trade = sqlx::query!(
+            "SELECT status, recipient_id, expires_at FROM trades WHERE id = $1 FOR UPDATE",
+            trade_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .filter(|t| t.recipient_id == user_id)
+        .ok_or_else(|| AppError::NotFound("Trade not found".to_string()))?;
+
+        if trade.status != "offered" {
+            return Err(AppError::BadRequest(format!("Trade is already {}", trade.status)));
+        }
+        if trade.expires_at <= Utc::now() {
+            return Err(AppError::BadRequest("This offer has expired".to_string()));
+        }
+
+        let items = trade_items(&mut tx, trade_id).await?;
+        lock_items(&mut tx, &items, None).await?;
+
+        let reward_ids: Vec<Uuid> = items.keys().copied().collect();
+        sqlx::query!(
+            "UPDATE user_rewards SET escrow_trade_id = $1 WHERE id = ANY($2)",
+            trade_id,
+            &reward_ids
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "UPDATE trades SET status = 'locked', locked_at = NOW(), updated_at = NOW() WHERE id = $1",
+            trade_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        audit::record(&mut *tx, user_id, "trade.locked", Some(&trade_id.to_string()), json!({
+            "reward_ids": reward_ids,
+        }))
+        .await?;
+        tx.commit().await?;
+
+        self.settle(trade_id).await?;
+        self.get_trade(user_id, trade_id).await
+    }
+
+    /// Swap a locked trade's rewards. Safe to call repeatedly; a trade that can no longer
+    /// settle (e.g. a reward expired while locked) is cancelled and its escrow released.
+    pub async fn settle(&self, trade_id: Uuid) -> Result<()> {
+        let mut tx = self.db.begin().await?;
+        let trade = sqlx::query!(
+            "SELECT proposer_id, recipient_id FROM trades WHERE id = $1 AND status = 'locked' FOR UPDATE",
+            trade_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(trade) = trade else {
+            return Ok(());
+        };
+
+        let items = trade_items(&mut tx, trade_id).await?;
+        if let Err(e) = lock_items(&mut tx, &items, Some(trade_id)).await {
+            warn!("Cancelling trade {} that can no longer settle: {:?}", trade_id, e);
+            cancel_locked(&mut tx, trade_id, "settlement_failed").await?;
+            tx.commit().await?;
+            return Err(e);
+        }
+
+        let swapped = sqlx::query!(
+            r#"
+            UPDATE user_rewards
+            SET user_id = CASE WHEN user_id = $2 THEN $3 ELSE $2 END, escrow_trade_id = NULL
+            WHERE escrow_trade_id = $1
+            "#,
+            trade_id,
+            trade.proposer_id,
+            trade.recipient_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        if swapped.rows_affected() != items.len() as u64 {
+            // Every item was checked under lock above; a mismatch means escrow was tampered with
+            return Err(AppError::InternalError(format!(
+                "Trade {} escrow holds {} of {} rewards", trade_id, swapped.rows_affected(), items.len()
+            )));
+        }
+
+        // Share links belong to the previous owner
+        let reward_ids: Vec<Uuid> = items.keys().copied().collect();
+        sqlx::query!(
+            "UPDATE reward_shares SET revoked_at = NOW() WHERE reward_id = ANY($1) AND revoked_at IS NULL",
+            &reward_ids
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            "UPDATE trades SET status = 'swapped', swapped_at = NOW(), updated_at = NOW() WHERE id = $1",
+            trade_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        audit::record(&mut *tx, "system", "trade.swapped", Some(&trade_id.to_string()), json!({
+            "proposer_id": trade.proposer_id,
+            "recipient_id": trade.recipient_id,
+            "reward_ids": reward_ids,
+        }))
+        .await?;
+        tx.commit().await?;
+
+        info!("Trade {} swapped {} rewards", trade_id, items.len());
+        Ok(())
+    }
+
+    /// Withdraw (proposer) or decline (recipient) an open offer
+    pub async fn cancel(&self, user_id: &str, trade_id: Uuid) -> Result<Trade> {
+        let mut tx = self.db.begin().await?;
+        let trade = sqlx::query!(
+            "SELECT status, proposer_id, recipient_id FROM trades WHERE id = $1 FOR UPDATE",
+            trade_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .filter(|t| t.proposer_id == user_id || t.recipient_id == user_id)
+        .ok_or_else(|| AppError::NotFound("Trade not found".to_string()))?;
+
+        if trade.status != "offered" {
+            return Err(AppError::BadRequest(format!("Trade is already {}", trade.status)));
+        }
+        let reason = if trade.proposer_id == user_id { "withdrawn" } else { "declined" };
+
+        sqlx::query!(
+            r#"
+            UPDATE trades SET status = 'cancelled', cancelled_at = NOW(), cancel_reason = $2, updated_at = NOW()
+            WHERE id = $1
+            "#,
+            trade_id,
+            reason
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        audit::record(&mut *tx, user_id, "trade.cancelled", Some(&trade_id.to_string()), json!({ "reason": reason }))
+            .await?;
+
+        let trade = load_trade(&mut tx, trade_id).await?;
+        tx.commit().await?;
+        Ok(trade)
+    }
+
+    /// Cancel expired offers and resolve trades stuck in `locked`
+    pub async fn sweep(&self) -> Result<()> {
+        let expired = sqlx::query_scalar!(
+            r#"
+            UPDATE trades SET status = 'cancelled', cancelled_at = NOW(), cancel_reason = 'expired', updated_at = NOW()
+            WHERE status = 'offered' AND expires_at <= NOW()
+            RETURNING id
+            "#
+        )
+        .fetch_all(&self.db)
+        .await?;
+        for trade_id in &expired {
+            audit::record(&self.db, "system", "trade.cancelled", Some(&trade_id.to_string()), json!({ "reason": "expired" }))
+                .await?;
+        }
+
+        let stuck = sqlx::query_scalar!(
+            "SELECT id FROM trades WHERE status = 'locked' AND locked_at <= NOW() - make_interval(secs => $1)",
+            LOCK_TIMEOUT_SECONDS as f64
+        )
+        .fetch_all(&self.db)
+        .await?;
+        for trade_id in stuck {
+            if let Err(e) = self.settle(trade_id).await {
+                warn!("Stuck trade {} did not settle: {:?}", trade_id, e);
+            }
+        }
+
+        if !expired.is_empty() {
+            info!("Cancelled {} expired trade offers", expired.len());
+        }
+        Ok(())
+    }
+
+    /// Run `sweep` every minute
+    pub fn spawn_sweeper(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(SWEEP_INTERVAL_SECONDS));
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.sweep().await {
+                    error!("Trade sweep failed: {:?}", e);
+                }
+            }
+        })
+    }
+}
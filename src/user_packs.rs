@@ -0,0 +1,77 @@
+use crate::error::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{FromRow, PgConnection, PgPool};
+use uuid::Uuid;
+
+/// A pack the user owns but hasn't opened yet
+#[derive(Debug, FromRow, Serialize)]
+pub struct UserPack {
+    pub id: Uuid,
+    pub pack_type_id: Uuid,
+    pub pack_name: String,
+    pub source: String,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Put an unopened pack in the user's inventory. Runs in the caller's transaction.
+pub async fn grant(
+    conn: &mut PgConnection,
+    user_id: &str,
+    pack_type_id: Uuid,
+    source: &str,
+    expires_at: Option<DateTime<Utc>>,
+) -> Result<Uuid> {
+    let id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO user_packs (user_id, pack_type_id, source, expires_at)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id
+        "#,
+        user_id,
+        pack_type_id,
+        source,
+        expires_at
+    )
+    .fetch_one(conn)
+    .await?;
+
+    Ok(id)
+}
+
+/// List the user's unopened, unexpired packs
+pub async fn list_unopened(db: &PgPool, user_id: &str) -> Result<Vec<UserPack>> {
+    let packs = sqlx::query_as!(
+        UserPack,
+        r#"
+        SELECT up.id, up.pack_type_id, pt.name AS pack_name, up.source, up.expires_at, up.created_at
+        FROM user_packs up
+        JOIN pack_types pt ON pt.id = up.pack_type_id
+        WHERE up.user_id = $1 AND up.status = 'unopened'
+          AND (up.expires_at IS NULL OR up.expires_at > NOW())
+        ORDER BY up.expires_at ASC NULLS LAST, up.created_at
+        "#,
+        user_id
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(packs)
+}
+
+/// Number of unopened, unexpired packs the user holds
+pub async fn count_unopened(db: &PgPool, user_id: &str) -> Result<i64> {
+    let count = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) AS "count!" FROM user_packs
+        WHERE user_id = $1 AND status = 'unopened'
+          AND (expires_at IS NULL OR expires_at > NOW())
+        "#,
+        user_id
+    )
+    .fetch_one(db)
+    .await?;
+
+    Ok(count)
+}
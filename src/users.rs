@@ -0,0 +1,102 @@
+use crate::error::{AppError, Result};
+use crate::http_client::HttpServiceClient;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long a confirmed user is trusted before asking the user service again
+const KNOWN_USER_TTL: Duration = Duration::from_secs(600);
+/// Unknown ids are re-checked sooner so a user who just signed up isn't locked out
+const UNKNOWN_USER_TTL: Duration = Duration::from_secs(30);
+/// Answers cached at most; past this expired ones are pruned and, if still full, new ones
+/// go uncached
+const MAX_CACHED_USERS: usize = 100_000;
+const MAX_USER_ID_LEN: usize = 128;
+
+/// Account ids are `[A-Za-z0-9_-]`. Anything else can't name an account and must not be
+/// spliced into the lookup path, where `../health` or `x?y` would reach another endpoint.
+fn is_well_formed(user_id: &str) -> bool {
+    !user_id.is_empty()
+        && user_id.len() <= MAX_USER_ID_LEN
+        && user_id.bytes().all(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'-'))
+}
+
+fn cache_ttl(exists: bool) -> Duration {
+    if exists { KNOWN_USER_TTL } else { UNKNOWN_USER_TTL }
+}
+
+/// Decides whether a user id belongs to a real account before stats are provisioned for it
+#[async_trait]
+pub trait UserDirectory: Send + Sync {
+    async fn exists(&self, user_id: &str) -> Result<bool>;
+}
+
+/// Accepts every id; the default for deployments without a user service
+pub struct OpenUserDirectory;
+
+#[async_trait]
+impl UserDirectory for OpenUserDirectory {
+    async fn exists(&self, _user_id: &str) -> Result<bool> {
+        Ok(true)
+    }
+}
+
+/// Typed client for the user service's account lookup, caching answers per user
+pub struct UserServiceClient {
+    http: HttpServiceClient,
+    known: tokio::sync::RwLock<HashMap<String, (Instant, bool)>>, // Lookup result per user id
+}
+
+impl UserServiceClient {
+    pub fn new(base_url: String, timeout: Duration) -> Result<Self> {
+        Ok(Self {
+            http: HttpServiceClient::new("user service", base_url, timeout)?,
+            known: tokio::sync::RwLock::new(HashMap::new()),
+        })
+    }
+
+    /// `user_id` must be well formed; it goes into the path as it is
+    async fn lookup(&self, user_id: &str) -> Result<bool> {
+        let response = self.http.send(self.http.get(&format!("/users/{}", user_id))).await?;
+
+        match response.status() {
+            status if status.is_success() => Ok(true),
+            reqwest::StatusCode::NOT_FOUND => Ok(false),
+            status => Err(AppError::InternalError(format!("User service returned {} for user lookup", status))),
+        }
+    }
+}
+
+#[async_trait]
+impl UserDirectory for UserServiceClient {
+    async fn exists(&self, user_id: &str) -> Result<bool> {
+        if !is_well_formed(user_id) {
+            return Ok(false);
+        }
+        if let Some((fetched, exists)) = self.known.read().await.get(user_id) {
+            if fetched.elapsed() < cache_ttl(*exists) {
+                return Ok(*exists);
+            }
+        }
+
+        // Errors aren't cached or papered over: provisioning waits for a real answer
+        let exists = self.lookup(user_id).await?;
+        let mut known = self.known.write().await;
+        if known.len() >= MAX_CACHED_USERS {
+            known.retain(|_, (fetched, exists)| fetched.elapsed() < cache_ttl(*exists));
+        }
+        if known.len() < MAX_CACHED_USERS || known.contains_key(user_id) {
+            known.insert(user_id.to_string(), (Instant::now(), exists));
+        }
+        Ok(exists)
+    }
+}
+
+/// Validate against the user service when `USER_SERVICE_URL` is set, otherwise accept every id
+pub fn from_env(timeout: Duration) -> Result<Arc<dyn UserDirectory>> {
+    match std::env::var("USER_SERVICE_URL") {
+        Ok(base_url) => Ok(Arc::new(UserServiceClient::new(base_url, timeout)?)),
+        Err(_) => Ok(Arc::new(OpenUserDirectory)),
+    }
+}
@@ -0,0 +1,196 @@
+use crate::audit;
+use crate::error::{AppError, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+const MAX_VACATION_DAYS: i32 = 14;
+/// Vacations allowed in any rolling year
+const VACATIONS_PER_YEAR: i64 = 2;
+
+#[derive(Debug, Serialize)]
+pub struct Vacation {
+    pub id: Uuid,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VacationStatus {
+    pub active: Option<Vacation>,
+    /// Vacations still available in the current rolling year
+    pub remaining_vacations: i64,
+    /// When the oldest vacation counted against the allowance stops counting
+    pub allowance_resets_at: Option<DateTime<Utc>>,
+    pub max_days: i32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StartVacationRequest {
+    pub days: i32,
+}
+
+/// End a running vacation because the user claimed their daily pack. The claim sets
+/// a fresh streak deadline, so the deadline isn't adjusted here.
+pub async fn end_on_claim(conn: &mut PgConnection, user_id: &str, claimed_at: DateTime<Utc>) -> Result<()> {
+    sqlx::query!(
+        r#"
+        UPDATE streak_vacations SET ends_at = $2, ended_early_at = $2
+        WHERE user_id = $1 AND starts_at <= $2 AND ends_at > $2
+        "#,
+        user_id,
+        claimed_at
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+pub struct VacationService {
+    db: PgPool,
+}
+
+impl VacationService {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    pub async fn status(&self, user_id: &str) -> Result<VacationStatus> {
+        let mut conn = self.db.acquire().await?;
+        Self::load_status(&mut conn, user_id).await
+    }
+
+    /// Pause the streak for `days` days starting now. Missed days in between don't break
+    /// it, but only claims count towards it.
+    pub async fn start(&self, user_id: &str, req: StartVacationRequest) -> Result<VacationStatus> {
+        if !(1..=MAX_VACATION_DAYS).contains(&req.days) {
+            return Err(AppError::BadRequest(format!("Vacations last 1 to {} days", MAX_VACATION_DAYS)));
+        }
+
+        let mut tx = self.db.begin().await?;
+        // Same lock as daily claims, so a claim can't race the deadline change
+        sqlx::query!("SELECT user_id FROM user_lootpack_stats WHERE user_id = $1 FOR UPDATE", user_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| AppError::BadRequest("There's no streak to pause".to_string()))?;
+
+        let status = Self::load_status(&mut tx, user_id).await?;
+        if status.active.is_some() {
+            return Err(AppError::BadRequest("You're already on vacation".to_string()));
+        }
+        if status.remaining_vacations <= 0 {
+            return Err(AppError::BadRequest(format!(
+                "You've used your {} vacations for this year",
+                VACATIONS_PER_YEAR
+            )));
+        }
+
+        let now = Utc::now();
+        let ends_at = now + Duration::days(req.days as i64);
+        let extended = sqlx::query!(
+            "UPDATE user_daily_clock SET streak_until = streak_until + ($3 - $2) WHERE user_id = $1 AND streak_until > $2",
+            user_id,
+            now,
+            ends_at
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if extended == 0 {
+            return Err(AppError::BadRequest("There's no running streak to pause".to_string()));
+        }
+
+        let vacation_id = sqlx::query_scalar!(
+            "INSERT INTO streak_vacations (user_id, starts_at, ends_at) VALUES ($1, $2, $3) RETURNING id",
+            user_id,
+            now,
+            ends_at
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        audit::record(&mut *tx, user_id, "streak.vacation_started", Some(&vacation_id.to_string()), json!({
+            "days": req.days,
+            "ends_at": ends_at,
+        }))
+        .await?;
+
+        let status = Self::load_status(&mut tx, user_id).await?;
+        tx.commit().await?;
+        Ok(status)
+    }
+
+    /// Come back early. The streak deadline loses the unused part of the vacation; the
+    /// vacation still counts against the allowance.
+    pub async fn end(&self, user_id: &str) -> Result<VacationStatus> {
+        let mut tx = self.db.begin().await?;
+        sqlx::query!("SELECT user_id FROM user_lootpack_stats WHERE user_id = $1 FOR UPDATE", user_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        let now = Utc::now();
+        let vacation = Self::load_status(&mut tx, user_id)
+            .await?
+            .active
+            .ok_or_else(|| AppError::BadRequest("You're not on vacation".to_string()))?;
+
+        sqlx::query!(
+            "UPDATE streak_vacations SET ends_at = $2, ended_early_at = $2 WHERE id = $1",
+            vacation.id,
+            now
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "UPDATE user_daily_clock SET streak_until = streak_until - ($3 - $2) WHERE user_id = $1",
+            user_id,
+            now,
+            vacation.ends_at
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        audit::record(&mut *tx, user_id, "streak.vacation_ended", Some(&vacation.id.to_string()), json!({
+            "unused_hours": (vacation.ends_at - now).num_hours(),
+        }))
+        .await?;
+
+        let status = Self::load_status(&mut tx, user_id).await?;
+        tx.commit().await?;
+        Ok(status)
+    }
+
+    async fn load_status(conn: &mut PgConnection, user_id: &str) -> Result<VacationStatus> {
+        let active = sqlx::query_as!(
+            Vacation,
+            r#"
+            SELECT id, starts_at, ends_at FROM streak_vacations
+            WHERE user_id = $1 AND starts_at <= NOW() AND ends_at > NOW()
+            "#,
+            user_id
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        let recent = sqlx::query!(
+            r#"
+            SELECT COUNT(*) AS "used!", MIN(starts_at) + INTERVAL '1 year' AS resets_at
+            FROM streak_vacations
+            WHERE user_id = $1 AND starts_at > NOW() - INTERVAL '1 year'
+            "#,
+            user_id
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(VacationStatus {
+            active,
+            remaining_vacations: (VACATIONS_PER_YEAR - recent.used).max(0),
+            allowance_resets_at: recent.resets_at,
+            max_days: MAX_VACATION_DAYS,
+        })
+    }
+}
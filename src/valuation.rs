@@ -0,0 +1,73 @@
+use crate::currency::{self, Money, BASE_CURRENCY};
+use crate::error::Result;
+use crate::reward_types;
+use bigdecimal::{BigDecimal, RoundingMode, Zero};
+use sqlx::PgConnection;
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// Rupee value of one DealCoin, used to value points rewards
+const INR_PER_DEAL_COIN: &str = "0.10";
+
+/// Currency symbols reward values are written with, e.g. "₹150" or "$5"
+const CURRENCY_SYMBOLS: &[(&str, &str)] = &[("₹", "INR"), ("Rs", "INR"), ("$", "USD"), ("€", "EUR"), ("£", "GBP")];
+
+/// Estimated value of a reward from its type and display value, for templates without an
+/// explicit `value_amount`. Only for display and stats: it is stored apart from
+/// `value_amount` and never pays out. `None` when the reward has no meaningful cash value
+/// (free shipping, samples, experiences), is a percentage off an unknown basket, or the
+/// value can't be read. Gift cards are never estimated, since they are ordered for
+/// exactly their value.
+pub fn estimate(reward_type: &str, value: &str) -> Option<Money> {
+    let spec = reward_types::spec(reward_type)?;
+    let value = value.trim();
+
+    let amount = if spec.credits_coins {
+        let coins = BigDecimal::from(value.trim_start_matches('+').parse::<i64>().ok()?);
+        Money {
+            amount: coins * BigDecimal::from_str(INR_PER_DEAL_COIN).ok()?,
+            currency: BASE_CURRENCY.to_string(),
+        }
+    } else {
+        match spec.key {
+            "coupon" | "voucher" | "cashback" if !value.contains('%') => face_value(value)?,
+            _ => return None,
+        }
+    };
+
+    let amount = Money { amount: amount.amount.with_scale_round(2, RoundingMode::HalfEven), ..amount };
+    (amount.amount > BigDecimal::zero()).then_some(amount)
+}
+
+/// Read an amount like "₹150", "Rs.99" or "₹1,000 on ₹5,000+"; bare numbers are rupees
+fn face_value(value: &str) -> Option<Money> {
+    let (currency, rest) = CURRENCY_SYMBOLS
+        .iter()
+        .find_map(|(symbol, currency)| value.strip_prefix(symbol).map(|rest| (*currency, rest)))
+        .unwrap_or((BASE_CURRENCY, value));
+
+    Some(Money { amount: currency::parse_amount(rest)?, currency: currency.to_string() })
+}
+
+/// Set a pack opening's `total_value_inr` from the rewards granted for it, converted at
+/// today's rates, falling back to the estimate for rewards without a stated value.
+/// Rewards in a currency without a rate are left out.
+pub async fn record_pack_value(conn: &mut PgConnection, pack_history_id: Uuid) -> Result<()> {
+    sqlx::query!(
+        r#"
+        UPDATE user_pack_history SET total_value_inr = (
+            SELECT COALESCE(SUM(ROUND(COALESCE(ur.value_amount, ur.value_estimate_amount) / cr.per_inr, 2)), 0)
+            FROM user_rewards ur
+            JOIN currency_rates cr
+              ON cr.currency = CASE WHEN ur.value_amount IS NOT NULL THEN ur.value_currency ELSE ur.value_estimate_currency END
+            WHERE ur.pack_history_id = $1 AND COALESCE(ur.value_amount, ur.value_estimate_amount) IS NOT NULL
+        )
+        WHERE id = $1
+        "#,
+        pack_history_id
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
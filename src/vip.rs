@@ -0,0 +1,331 @@
+use crate::billing::{BillingClient, Subscription, VIP_PRODUCT};
+use crate::error::{AppError, Result};
+use crate::inbox::{self, NewMessage};
+use crate::{audit, privacy, user_packs};
+use crate::wallet::{self, CoinWallet};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{PgConnection, PgPool};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+const GRANT_BATCH_SIZE: i64 = 100;
+const WORKER_INTERVAL_SECONDS: u64 = 300;
+/// How long a subscriber whose grant couldn't go through waits before the next attempt
+const GRANT_RETRY_SECONDS: i64 = 3600;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VipPlan {
+    pub plan_id: String,
+    pub premium_pack_type_id: Uuid,
+    pub packs_per_period: i32,
+    pub coin_stipend: i32,
+    pub is_active: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VipStatus {
+    pub plan_id: String,
+    pub status: String,
+    pub current_period_end: DateTime<Utc>,
+    pub packs_per_period: i32,
+    pub coin_stipend: i32,
+    /// When this period's packs and coins were granted; `None` until the worker runs
+    pub granted_at: Option<DateTime<Utc>>,
+}
+
+/// The stored VIP status for stats; no call to billing
+pub async fn status(conn: &mut PgConnection, user_id: &str) -> Result<Option<VipStatus>> {
+    let status = sqlx::query_as!(
+        VipStatus,
+        r#"
+        SELECT vs.plan_id, vs.status, vs.current_period_end, vp.packs_per_period, vp.coin_stipend,
+               vg.granted_at AS "granted_at?"
+        FROM vip_subscriptions vs
+        JOIN vip_plans vp ON vp.plan_id = vs.plan_id
+        LEFT JOIN vip_grants vg ON vg.user_id = vs.user_id AND vg.period_start = vs.current_period_start
+        WHERE vs.user_id = $1
+        "#,
+        user_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(status)
+}
+
+/// VIP entitlements: packs and a coin stipend once per billing period
+pub struct VipService {
+    db: PgPool,
+    billing: Arc<BillingClient>,
+    wallet: Arc<dyn CoinWallet>,
+}
+
+impl VipService {
+    pub fn new(db: PgPool, billing: Arc<BillingClient>, wallet: Arc<dyn CoinWallet>) -> Self {
+        Self { db, billing, wallet }
+    }
+
+    /// Check the user's subscription with billing and grant the period if it's due.
+    /// Falls back to the stored status when billing can't be reached.
+    pub async fn get_status(&self, user_id: &str) -> Result<Option<VipStatus>> {
+        match self.billing.subscription(user_id, VIP_PRODUCT).await {
+            Ok(subscription) => {
+                self.store(user_id, subscription.as_ref()).await?;
+                if let Some(subscription) = subscription.filter(|s| s.is_entitled(Utc::now())) {
+                    self.grant_period(&subscription).await?;
+                }
+            }
+            Err(e) => warn!("VIP check for {} fell back to stored status: {:?}", privacy::pseudonym(user_id), e),
+        }
+
+        let mut conn = self.db.acquire().await?;
+        status(&mut conn, user_id).await
+    }
+
+    pub async fn list_plans(&self) -> Result<Vec<VipPlan>> {
+        let plans = sqlx::query_as!(
+            VipPlan,
+            "SELECT plan_id, premium_pack_type_id, packs_per_period, coin_stipend, is_active FROM vip_plans ORDER BY plan_id"
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(plans)
+    }
+
+    /// Configure what a billing plan grants each period. Periods already granted keep
+    /// what they got.
+    pub async fn upsert_plan(&self, actor: &str, plan: VipPlan) -> Result<VipPlan> {
+        if plan.packs_per_period < 0 || plan.coin_stipend < 0 {
+            return Err(AppError::BadRequest("packs_per_period and coin_stipend can't be negative".to_string()));
+        }
+
+        let mut tx = self.db.begin().await?;
+        let premium = sqlx::query_scalar!(
+            "SELECT type = 'premium' AS \"premium!\" FROM pack_types WHERE id = $1",
+            plan.premium_pack_type_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("Pack type not found".to_string()))?;
+        if !premium {
+            return Err(AppError::BadRequest("VIP plans grant premium packs".to_string()));
+        }
+
+        sqlx::query!(
+            r#"
+            INSERT INTO vip_plans (plan_id, premium_pack_type_id, packs_per_period, coin_stipend, is_active)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (plan_id) DO UPDATE SET
+                premium_pack_type_id = EXCLUDED.premium_pack_type_id, packs_per_period = EXCLUDED.packs_per_period,
+                coin_stipend = EXCLUDED.coin_stipend, is_active = EXCLUDED.is_active, updated_at = NOW()
+            "#,
+            plan.plan_id,
+            plan.premium_pack_type_id,
+            plan.packs_per_period,
+            plan.coin_stipend,
+            plan.is_active
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        audit::record(&mut *tx, actor, "vip.plan_updated", Some(&plan.plan_id), json!({
+            "premium_pack_type_id": plan.premium_pack_type_id,
+            "packs_per_period": plan.packs_per_period,
+            "coin_stipend": plan.coin_stipend,
+            "is_active": plan.is_active,
+        }))
+        .await?;
+
+        tx.commit().await?;
+        Ok(plan)
+    }
+
+    /// Record a subscription change pushed by billing. Idempotent; stale updates for
+    /// an older period are ignored.
+    pub async fn handle_webhook(&self, subscription: Subscription) -> Result<()> {
+        self.billing.invalidate(&subscription.user_id).await;
+        self.store(&subscription.user_id, Some(&subscription)).await
+    }
+
+    async fn store(&self, user_id: &str, subscription: Option<&Subscription>) -> Result<()> {
+        match subscription {
+            Some(s) => {
+                sqlx::query!(
+                    r#"
+                    INSERT INTO vip_subscriptions (user_id, plan_id, status, current_period_start, current_period_end)
+                    VALUES ($1, $2, $3, $4, $5)
+                    ON CONFLICT (user_id) DO UPDATE SET
+                        plan_id = EXCLUDED.plan_id, status = EXCLUDED.status,
+                        current_period_start = EXCLUDED.current_period_start,
+                        current_period_end = EXCLUDED.current_period_end, checked_at = NOW(),
+                        grant_attempted_at = CASE
+                            WHEN EXCLUDED.current_period_start > vip_subscriptions.current_period_start THEN NULL
+                            ELSE vip_subscriptions.grant_attempted_at
+                        END
+                    WHERE vip_subscriptions.current_period_start <= EXCLUDED.current_period_start
+                    "#,
+                    user_id,
+                    s.plan_id,
+                    s.status,
+                    s.current_period_start,
+                    s.current_period_end
+                )
+                .execute(&self.db)
+                .await?;
+            }
+            None => {
+                sqlx::query!("DELETE FROM vip_subscriptions WHERE user_id = $1", user_id)
+                    .execute(&self.db)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Grant the packs and stipend for the subscription's current period, once. The grant
+    /// row is claimed first in the same transaction, so retries and concurrent workers
+    /// can't grant a period twice.
+    async fn grant_period(&self, subscription: &Subscription) -> Result<bool> {
+        let mut tx = self.db.begin().await?;
+        let plan = sqlx::query!(
+            r#"
+            SELECT premium_pack_type_id, packs_per_period, coin_stipend
+            FROM vip_plans
+            WHERE plan_id = $1 AND is_active
+            "#,
+            subscription.plan_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(plan) = plan else {
+            warn!("No active VIP plan '{}' for user {}", subscription.plan_id, privacy::pseudonym(&subscription.user_id));
+            return Ok(false);
+        };
+
+        let claimed = sqlx::query!(
+            r#"
+            INSERT INTO vip_grants (user_id, period_start, plan_id, packs_granted, coins_granted)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (user_id, period_start) DO NOTHING
+            "#,
+            subscription.user_id,
+            subscription.current_period_start,
+            subscription.plan_id,
+            plan.packs_per_period,
+            plan.coin_stipend
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if claimed == 0 {
+            return Ok(false);
+        }
+
+        let user_id = subscription.user_id.as_str();
+        let reference = format!("{}:{}", subscription.plan_id, subscription.current_period_start.timestamp());
+        for _ in 0..plan.packs_per_period {
+            user_packs::grant(&mut tx, user_id, plan.premium_pack_type_id, "vip", Some(subscription.current_period_end))
+                .await?;
+        }
+        let mut wallet_entries = Vec::new();
+        if plan.coin_stipend > 0 {
+            wallet_entries.push(self.wallet.apply(&mut tx, user_id, plan.coin_stipend, "vip_stipend", Some(&reference)).await?);
+        }
+
+        inbox::send(&mut tx, user_id, NewMessage {
+            kind: "grant",
+            title: "Your VIP rewards are here",
+            body: &format!(
+                "{} premium packs and {} DealCoins have been added to your account.",
+                plan.packs_per_period, plan.coin_stipend
+            ),
+            claim_pack_type_id: None,
+            expires_at: None,
+        })
+        .await?;
+
+        audit::record(&mut *tx, "system", "vip.period_granted", Some(user_id), json!({
+            "plan_id": subscription.plan_id,
+            "period_start": subscription.current_period_start,
+            "packs": plan.packs_per_period,
+            "coins": plan.coin_stipend,
+        }))
+        .await?;
+
+        wallet::commit_with_wallet(self.wallet.as_ref(), tx, &wallet_entries).await?;
+        Ok(true)
+    }
+
+    /// Grant every stored subscriber whose current period hasn't been granted yet.
+    /// Each is re-checked with billing first, so cancellations billing didn't push
+    /// still stop the grant. Every attempt is stamped when claimed, so subscribers whose
+    /// plan is missing or whose billing check fails wait their turn behind the rest.
+    pub async fn process_due(&self) -> Result<usize> {
+        let due = sqlx::query_scalar!(
+            r#"
+            UPDATE vip_subscriptions SET grant_attempted_at = NOW()
+            WHERE user_id IN (
+                SELECT vs.user_id FROM vip_subscriptions vs
+                WHERE vs.status = 'active' AND vs.current_period_start <= NOW() AND vs.current_period_end > NOW()
+                  AND (vs.grant_attempted_at IS NULL OR vs.grant_attempted_at <= NOW() - make_interval(secs => $2))
+                  AND NOT EXISTS (
+                      SELECT 1 FROM vip_grants vg
+                      WHERE vg.user_id = vs.user_id AND vg.period_start = vs.current_period_start
+                  )
+                ORDER BY vs.grant_attempted_at NULLS FIRST, vs.current_period_start
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING user_id
+            "#,
+            GRANT_BATCH_SIZE,
+            GRANT_RETRY_SECONDS as f64
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        let mut granted = 0;
+        for user_id in due {
+            let subscription = match self.billing.subscription(&user_id, VIP_PRODUCT).await {
+                Ok(subscription) => subscription,
+                Err(e) => {
+                    warn!("VIP entitlement check for {} failed: {:?}", privacy::pseudonym(&user_id), e);
+                    continue;
+                }
+            };
+            if let Err(e) = self.store(&user_id, subscription.as_ref()).await {
+                warn!("Storing VIP status for {} failed: {:?}", privacy::pseudonym(&user_id), e);
+                continue;
+            }
+            if let Some(subscription) = subscription.filter(|s| s.is_entitled(Utc::now())) {
+                match self.grant_period(&subscription).await {
+                    Ok(true) => granted += 1,
+                    Ok(false) => {}
+                    Err(e) => warn!("VIP grant for {} failed: {:?}", privacy::pseudonym(&user_id), e),
+                }
+            }
+        }
+
+        if granted > 0 {
+            info!("Granted VIP rewards to {} subscribers", granted);
+        }
+        Ok(granted)
+    }
+
+    pub fn spawn_worker(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(WORKER_INTERVAL_SECONDS));
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.process_due().await {
+                    error!("VIP grant run failed: {:?}", e);
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,322 @@
+use crate::error::{AppError, Result};
+use crate::http_client::HttpServiceClient;
+use crate::ledger;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// Holds older than this that are still uncaptured are captured by the reconciler. The
+/// wallet service releases holds nobody captures, which backs up a lost void.
+const RECONCILE_AFTER_SECONDS: i64 = 60;
+const RECONCILE_INTERVAL_SECONDS: u64 = 60;
+const RECONCILE_BATCH: i64 = 200;
+
+/// A coin movement made through a wallet, settled once the caller's transaction finishes
+#[derive(Debug, Clone)]
+pub struct WalletEntry {
+    /// The remote hold to capture or void; local movements need no settling
+    pub hold_id: Option<String>,
+    pub balance: i32,
+}
+
+/// Where DealCoins balances live. Movements are made inside the caller's transaction and
+/// settled after it: `confirm` once it commits, `compensate` if it doesn't.
+#[async_trait]
+pub trait CoinWallet: Send + Sync {
+    /// Move `delta` coins and mirror the movement in `coin_ledger`. Returns the entry to
+    /// settle; debits beyond the balance fail with "Insufficient DealCoins".
+    async fn apply(
+        &self,
+        conn: &mut PgConnection,
+        user_id: &str,
+        delta: i32,
+        reason: &str,
+        reference_id: Option<&str>,
+    ) -> Result<WalletEntry>;
+
+    /// Debit `amount` coins even past zero, for coins the user was never entitled to (see
+    /// `ledger::claw_back`). Settled like `apply`.
+    async fn claw_back(
+        &self,
+        conn: &mut PgConnection,
+        user_id: &str,
+        amount: i32,
+        reason: &str,
+        reference_id: Option<&str>,
+    ) -> Result<WalletEntry>;
+
+    /// The transaction holding these entries committed
+    async fn confirm(&self, entries: &[WalletEntry]);
+
+    /// The transaction holding these entries rolled back
+    async fn compensate(&self, entries: &[WalletEntry]);
+}
+
+/// Commit a transaction that moved coins through `wallet`, then settle its entries:
+/// capture on commit, void if the commit failed
+pub async fn commit_with_wallet(
+    wallet: &dyn CoinWallet,
+    tx: sqlx::Transaction<'_, sqlx::Postgres>,
+    entries: &[WalletEntry],
+) -> Result<()> {
+    if let Err(e) = tx.commit().await {
+        wallet.compensate(entries).await;
+        return Err(e.into());
+    }
+    wallet.confirm(entries).await;
+    Ok(())
+}
+
+/// Balances in `user_lootpack_stats.deal_coins`, moved through the local ledger. The
+/// transaction is the only phase, so settling is a no-op.
+pub struct LocalWallet;
+
+#[async_trait]
+impl CoinWallet for LocalWallet {
+    async fn apply(
+        &self,
+        conn: &mut PgConnection,
+        user_id: &str,
+        delta: i32,
+        reason: &str,
+        reference_id: Option<&str>,
+    ) -> Result<WalletEntry> {
+        let balance = ledger::apply(conn, user_id, delta, reason, reference_id).await?;
+        Ok(WalletEntry { hold_id: None, balance })
+    }
+
+    async fn claw_back(
+        &self,
+        conn: &mut PgConnection,
+        user_id: &str,
+        amount: i32,
+        reason: &str,
+        reference_id: Option<&str>,
+    ) -> Result<WalletEntry> {
+        let balance = ledger::claw_back(conn, user_id, amount, reason, reference_id).await?;
+        Ok(WalletEntry { hold_id: None, balance })
+    }
+
+    async fn confirm(&self, _entries: &[WalletEntry]) {}
+
+    async fn compensate(&self, _entries: &[WalletEntry]) {}
+}
+
+#[derive(Serialize)]
+struct HoldRequest<'a> {
+    /// Makes a retried hold a no-op on the wallet side
+    idempotency_key: Uuid,
+    user_id: &'a str,
+    amount: i32,
+    reason: &'a str,
+    reference_id: Option<&'a str>,
+    /// Let a debit take the balance below zero; clawbacks only
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    allow_negative: bool,
+}
+
+#[derive(Deserialize)]
+struct HoldResponse {
+    hold_id: String,
+    /// Balance with the hold applied
+    balance: i32,
+}
+
+/// Balances held by the central wallet service, in two phases: every movement is placed as
+/// a hold while the caller's transaction is open, then captured after it commits or voided
+/// if it doesn't. `deal_coins` is kept as a mirror of the wallet balance for reads.
+pub struct RemoteWallet {
+    db: PgPool,
+    http: HttpServiceClient,
+}
+
+impl RemoteWallet {
+    pub fn new(db: PgPool, base_url: String, timeout: Duration) -> Result<Self> {
+        Ok(Self { db, http: HttpServiceClient::new("wallet", base_url, timeout)? })
+    }
+
+    /// Place a hold for `delta` and record it in `wallet_operations` inside the caller's
+    /// transaction
+    async fn hold(
+        &self,
+        conn: &mut PgConnection,
+        user_id: &str,
+        delta: i32,
+        reason: &str,
+        reference_id: Option<&str>,
+        allow_negative: bool,
+    ) -> Result<WalletEntry> {
+        let operation_id = Uuid::new_v4();
+        let request = self.http.post("/holds").json(&HoldRequest {
+            idempotency_key: operation_id,
+            user_id,
+            amount: delta,
+            reason,
+            reference_id,
+            allow_negative,
+        });
+        let response = self.http.send(request).await?;
+
+        if response.status() == reqwest::StatusCode::PAYMENT_REQUIRED {
+            return Err(AppError::BadRequest("Insufficient DealCoins".to_string()));
+        }
+        if !response.status().is_success() {
+            return Err(AppError::InternalError(format!("Wallet returned {} for hold", response.status())));
+        }
+        let hold: HoldResponse = response
+            .json()
+            .await
+            .map_err(|e| AppError::InternalError(format!("Invalid wallet response: {}", e)))?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO wallet_operations (id, hold_id, user_id, delta, reason, reference_id)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            operation_id,
+            hold.hold_id,
+            user_id,
+            delta,
+            reason,
+            reference_id
+        )
+        .execute(&mut *conn)
+        .await?;
+        sqlx::query!(
+            "UPDATE user_lootpack_stats SET deal_coins = $2, updated_at = NOW() WHERE user_id = $1",
+            user_id,
+            hold.balance
+        )
+        .execute(&mut *conn)
+        .await?;
+        ledger::record(conn, user_id, delta, reason, reference_id, hold.balance).await?;
+
+        Ok(WalletEntry { hold_id: Some(hold.hold_id), balance: hold.balance })
+    }
+
+    async fn settle(&self, hold_id: &str, action: &str) -> Result<()> {
+        // Captures and voids are idempotent on the wallet side
+        let response = self.http.send(self.http.post(&format!("/holds/{}/{}", hold_id, action))).await?;
+
+        if !response.status().is_success() {
+            return Err(AppError::InternalError(format!(
+                "Wallet returned {} for {} of hold {}", response.status(), action, hold_id
+            )));
+        }
+        Ok(())
+    }
+
+    async fn capture(&self, hold_id: &str) -> Result<()> {
+        self.settle(hold_id, "capture").await?;
+        sqlx::query!(
+            "UPDATE wallet_operations SET status = 'captured', captured_at = NOW() WHERE hold_id = $1",
+            hold_id
+        )
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Capture holds whose transaction committed but whose capture never landed
+    pub async fn reconcile(&self) -> Result<usize> {
+        let held = sqlx::query_scalar!(
+            r#"
+            SELECT hold_id FROM wallet_operations
+            WHERE status = 'held' AND created_at < NOW() - make_interval(secs => $1)
+            ORDER BY created_at
+            LIMIT $2
+            "#,
+            RECONCILE_AFTER_SECONDS as f64,
+            RECONCILE_BATCH
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        let mut captured = 0;
+        for hold_id in held {
+            match self.capture(&hold_id).await {
+                Ok(()) => captured += 1,
+                Err(e) => warn!("Wallet hold {} still uncaptured: {:?}", hold_id, e),
+            }
+        }
+        Ok(captured)
+    }
+
+    pub fn spawn_reconciler(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(RECONCILE_INTERVAL_SECONDS));
+            loop {
+                interval.tick().await;
+                match self.reconcile().await {
+                    Ok(0) => {}
+                    Ok(captured) => info!("Captured {} outstanding wallet holds", captured),
+                    Err(e) => error!("Wallet reconciliation failed: {:?}", e),
+                }
+            }
+        })
+    }
+}
+
+#[async_trait]
+impl CoinWallet for RemoteWallet {
+    async fn apply(
+        &self,
+        conn: &mut PgConnection,
+        user_id: &str,
+        delta: i32,
+        reason: &str,
+        reference_id: Option<&str>,
+    ) -> Result<WalletEntry> {
+        self.hold(conn, user_id, delta, reason, reference_id, false).await
+    }
+
+    async fn claw_back(
+        &self,
+        conn: &mut PgConnection,
+        user_id: &str,
+        amount: i32,
+        reason: &str,
+        reference_id: Option<&str>,
+    ) -> Result<WalletEntry> {
+        self.hold(conn, user_id, -amount, reason, reference_id, true).await
+    }
+
+    async fn confirm(&self, entries: &[WalletEntry]) {
+        for hold_id in entries.iter().filter_map(|e| e.hold_id.as_deref()) {
+            // The reconciler retries anything left held
+            if let Err(e) = self.capture(hold_id).await {
+                warn!("Failed to capture wallet hold {}: {:?}", hold_id, e);
+            }
+        }
+    }
+
+    async fn compensate(&self, entries: &[WalletEntry]) {
+        for hold_id in entries.iter().filter_map(|e| e.hold_id.as_deref()) {
+            // An unvoided hold is released by the wallet once it goes uncaptured
+            if let Err(e) = self.settle(hold_id, "void").await {
+                warn!("Failed to void wallet hold {}: {:?}", hold_id, e);
+            }
+        }
+    }
+}
+
+/// Pick the wallet from configuration. `COIN_WALLET` is `local` (the default) or `remote`,
+/// which needs `WALLET_SERVICE_URL` and starts the hold reconciler.
+pub fn from_env(db: PgPool, timeout: Duration) -> Result<Arc<dyn CoinWallet>> {
+    match std::env::var("COIN_WALLET").unwrap_or_else(|_| "local".to_string()).as_str() {
+        "local" => Ok(Arc::new(LocalWallet)),
+        "remote" => {
+            let base_url = std::env::var("WALLET_SERVICE_URL")
+                .map_err(|_| AppError::InternalError("COIN_WALLET=remote needs WALLET_SERVICE_URL".to_string()))?;
+            let wallet = Arc::new(RemoteWallet::new(db, base_url, timeout)?);
+            wallet.clone().spawn_reconciler();
+            Ok(wallet)
+        }
+        other => Err(AppError::InternalError(format!("Unknown COIN_WALLET '{}'", other))),
+    }
+}
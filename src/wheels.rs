@@ -0,0 +1,294 @@
+use crate::error::{AppError, Result};
+use crate::{audit, cooldowns};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{FromRow, PgConnection, PgPool};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+const MIN_SEGMENTS: usize = 2;
+const MAX_SEGMENTS: usize = 16;
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct WheelSegment {
+    pub position: i32,
+    pub label: String,
+    pub color: Option<String>,
+    pub reward_template_id: Uuid,
+    pub weight: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SegmentView {
+    #[serde(flatten)]
+    pub segment: WheelSegment,
+    /// Share of the wheel's total weight, for odds disclosure
+    pub chance: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WheelView {
+    pub id: Uuid,
+    pub name: String,
+    pub cooldown_hours: i32,
+    pub is_active: bool,
+    pub segments: Vec<SegmentView>,
+    /// When the user can spin next, if the wheel is on cooldown for them
+    pub next_spin_at: Option<DateTime<Utc>>,
+}
+
+/// A segment deals either an existing template or a fixed number of DealCoins
+#[derive(Debug, Deserialize)]
+pub struct SegmentRequest {
+    pub label: String,
+    pub color: Option<String>,
+    pub reward_template_id: Option<Uuid>,
+    pub coins: Option<i32>,
+    pub weight: i32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WheelRequest {
+    pub name: String,
+    pub cooldown_hours: Option<i32>,
+    pub is_active: Option<bool>,
+    /// Segments in the order they appear on the wheel
+    pub segments: Vec<SegmentRequest>,
+}
+
+/// Segments of a wheel in wheel order
+pub async fn load_segments(conn: &mut PgConnection, wheel_id: Uuid) -> Result<Vec<WheelSegment>> {
+    let segments = sqlx::query_as!(
+        WheelSegment,
+        r#"
+        SELECT position, label, color, reward_template_id, weight
+        FROM wheel_segments
+        WHERE wheel_id = $1
+        ORDER BY position
+        "#,
+        wheel_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(segments)
+}
+
+fn with_chances(segments: Vec<WheelSegment>) -> Vec<SegmentView> {
+    let total: i64 = segments.iter().map(|s| s.weight as i64).sum();
+    segments
+        .into_iter()
+        .map(|segment| SegmentView {
+            chance: if total > 0 { segment.weight as f64 / total as f64 } else { 0.0 },
+            segment,
+        })
+        .collect()
+}
+
+pub struct WheelService {
+    db: PgPool,
+}
+
+impl WheelService {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    /// Active wheels with their odds and the user's next spin time
+    pub async fn list_wheels(&self, user_id: &str) -> Result<Vec<WheelView>> {
+        let mut conn = self.db.acquire().await?;
+        let wheels = sqlx::query!(
+            r#"
+            SELECT w.id, w.name, w.cooldown_hours, w.is_active, uwc.last_spun_at AS "last_spun_at?"
+            FROM wheels w
+            LEFT JOIN user_wheel_cooldowns uwc ON uwc.wheel_id = w.id AND uwc.user_id = $1
+            WHERE w.is_active
+            ORDER BY w.created_at
+            "#,
+            user_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let mut views = Vec::with_capacity(wheels.len());
+        for wheel in wheels {
+            views.push(WheelView {
+                segments: with_chances(load_segments(&mut conn, wheel.id).await?),
+                next_spin_at: wheel
+                    .last_spun_at
+                    .and_then(|at| cooldowns::next_available_at(at, Some(wheel.cooldown_hours))),
+                id: wheel.id,
+                name: wheel.name,
+                cooldown_hours: wheel.cooldown_hours,
+                is_active: wheel.is_active,
+            });
+        }
+        Ok(views)
+    }
+
+    /// Create a wheel, or replace one when `wheel_id` is given. Coin segments are backed by
+    /// a `points` template per amount so the draw and odds tooling treat them like any reward.
+    pub async fn save_wheel(&self, actor: &str, wheel_id: Option<Uuid>, req: WheelRequest) -> Result<WheelView> {
+        if req.name.trim().is_empty() {
+            return Err(AppError::BadRequest("name is required".to_string()));
+        }
+        if req.segments.len() < MIN_SEGMENTS || req.segments.len() > MAX_SEGMENTS {
+            return Err(AppError::BadRequest(format!(
+                "A wheel needs between {} and {} segments", MIN_SEGMENTS, MAX_SEGMENTS
+            )));
+        }
+        let cooldown_hours = req.cooldown_hours.unwrap_or(24);
+        if cooldown_hours <= 0 {
+            return Err(AppError::BadRequest("cooldown_hours must be positive".to_string()));
+        }
+
+        let mut tx = self.db.begin().await?;
+        let wheel_id = match wheel_id {
+            Some(id) => {
+                let updated = sqlx::query!(
+                    r#"
+                    UPDATE wheels
+                    SET name = $2, cooldown_hours = $3, is_active = COALESCE($4, is_active),
+                        updated_by = $5, updated_at = NOW()
+                    WHERE id = $1
+                    "#,
+                    id,
+                    req.name.trim(),
+                    cooldown_hours,
+                    req.is_active,
+                    actor
+                )
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+                if updated == 0 {
+                    return Err(AppError::NotFound("Wheel not found".to_string()));
+                }
+                id
+            }
+            None => {
+                sqlx::query_scalar!(
+                    r#"
+                    INSERT INTO wheels (name, cooldown_hours, is_active, updated_by)
+                    VALUES ($1, $2, COALESCE($3, true), $4)
+                    RETURNING id
+                    "#,
+                    req.name.trim(),
+                    cooldown_hours,
+                    req.is_active,
+                    actor
+                )
+                .fetch_one(&mut *tx)
+                .await?
+            }
+        };
+
+        sqlx::query!("DELETE FROM wheel_segments WHERE wheel_id = $1", wheel_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let mut templates = HashSet::new();
+        for (position, segment) in req.segments.iter().enumerate() {
+            if segment.weight <= 0 {
+                return Err(AppError::BadRequest("Segment weights must be positive".to_string()));
+            }
+            let template_id = match (segment.reward_template_id, segment.coins) {
+                (Some(template_id), None) => {
+                    let active = sqlx::query_scalar!(
+                        "SELECT COALESCE(is_active, true) FROM reward_templates WHERE id = $1",
+                        template_id
+                    )
+                    .fetch_optional(&mut *tx)
+                    .await?
+                    .flatten();
+                    if active != Some(true) {
+                        return Err(AppError::BadRequest(format!("Reward template {} is unknown or inactive", template_id)));
+                    }
+                    template_id
+                }
+                (None, Some(coins)) if coins > 0 => coin_template(&mut tx, coins).await?,
+                _ => {
+                    return Err(AppError::BadRequest(format!(
+                        "Segment '{}' needs either a reward_template_id or a positive coins amount", segment.label
+                    )));
+                }
+            };
+            if !templates.insert(template_id) {
+                return Err(AppError::BadRequest("Each segment must deal a different reward".to_string()));
+            }
+
+            sqlx::query!(
+                r#"
+                INSERT INTO wheel_segments (wheel_id, position, label, color, reward_template_id, weight)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                "#,
+                wheel_id,
+                position as i32,
+                segment.label,
+                segment.color,
+                template_id,
+                segment.weight
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let segments = load_segments(&mut tx, wheel_id).await?;
+        audit::record(&mut *tx, actor, "wheel.saved", Some(&wheel_id.to_string()), json!({
+            "name": req.name,
+            "cooldown_hours": cooldown_hours,
+            "segments": segments.iter().map(|s| json!({
+                "reward_template_id": s.reward_template_id,
+                "weight": s.weight,
+            })).collect::<Vec<_>>(),
+        }))
+        .await?;
+
+        let is_active = sqlx::query_scalar!("SELECT is_active FROM wheels WHERE id = $1", wheel_id)
+            .fetch_one(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(WheelView {
+            id: wheel_id,
+            name: req.name.trim().to_string(),
+            cooldown_hours,
+            is_active,
+            segments: with_chances(segments),
+            next_spin_at: None,
+        })
+    }
+}
+
+/// The wheel's `points` template for a coin amount, created on first use
+async fn coin_template(conn: &mut PgConnection, coins: i32) -> Result<Uuid> {
+    let value = format!("+{}", coins);
+    let existing = sqlx::query_scalar!(
+        r#"
+        SELECT id FROM reward_templates
+        WHERE type = 'points' AND value = $1 AND metadata->>'source' = 'wheel' AND COALESCE(is_active, true)
+        LIMIT 1
+        "#,
+        value
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+    if let Some(id) = existing {
+        return Ok(id);
+    }
+
+    let id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO reward_templates (type, title, value, description, rarity, metadata)
+        VALUES ('points', $1, $2, 'DealCoins from the wheel', 'common', '{"source": "wheel"}'::jsonb)
+        RETURNING id
+        "#,
+        format!("{} DealCoins", coins),
+        value
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(id)
+}
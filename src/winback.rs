@@ -0,0 +1,201 @@
+use crate::audit;
+use crate::error::{AppError, Result};
+use crate::notifications::{self, NotificationEvent};
+use crate::user_packs;
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{PgConnection, PgPool};
+use std::sync::Arc;
+use tracing::{error, info};
+use uuid::Uuid;
+
+const WINBACK_BATCH_SIZE: i64 = 200;
+const WORKER_INTERVAL_SECONDS: u64 = 3600;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WinBackSettings {
+    pub enabled: bool,
+    pub dormant_days: i32,
+    /// Pack granted to lapsed users; nothing is granted while unset
+    pub pack_type_id: Option<Uuid>,
+    pub pack_expiry_days: i32,
+    pub min_days_between: i32,
+}
+
+/// Finds users who stopped opening packs and grants them a time-limited welcome-back pack
+pub struct WinBackService {
+    db: PgPool,
+}
+
+impl WinBackService {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    pub async fn get_settings(&self) -> Result<WinBackSettings> {
+        let mut conn = self.db.acquire().await?;
+        Self::load_settings(&mut conn).await
+    }
+
+    pub async fn set_settings(&self, actor: &str, settings: WinBackSettings) -> Result<WinBackSettings> {
+        if settings.dormant_days <= 0 || settings.pack_expiry_days <= 0 || settings.min_days_between <= 0 {
+            return Err(AppError::BadRequest("dormant_days, pack_expiry_days and min_days_between must be positive".to_string()));
+        }
+        if settings.enabled && settings.pack_type_id.is_none() {
+            return Err(AppError::BadRequest("Choose a pack before enabling win-back grants".to_string()));
+        }
+
+        let mut tx = self.db.begin().await?;
+        if let Some(pack_type_id) = settings.pack_type_id {
+            let active = sqlx::query_scalar!("SELECT is_active FROM pack_types WHERE id = $1", pack_type_id)
+                .fetch_optional(&mut *tx)
+                .await?
+                .ok_or_else(|| AppError::BadRequest("Pack type not found".to_string()))?;
+            if !active.unwrap_or(false) {
+                return Err(AppError::BadRequest("The win-back pack must be active".to_string()));
+            }
+        }
+
+        sqlx::query!(
+            r#"
+            INSERT INTO winback_settings
+                (id, enabled, dormant_days, pack_type_id, pack_expiry_days, min_days_between, updated_by)
+            VALUES (true, $1, $2, $3, $4, $5, $6)
+            ON CONFLICT (id) DO UPDATE SET
+                enabled = EXCLUDED.enabled, dormant_days = EXCLUDED.dormant_days,
+                pack_type_id = EXCLUDED.pack_type_id, pack_expiry_days = EXCLUDED.pack_expiry_days,
+                min_days_between = EXCLUDED.min_days_between, updated_by = EXCLUDED.updated_by, updated_at = NOW()
+            "#,
+            settings.enabled,
+            settings.dormant_days,
+            settings.pack_type_id,
+            settings.pack_expiry_days,
+            settings.min_days_between,
+            actor
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        audit::record(&mut *tx, actor, "winback.settings_updated", None, json!({
+            "enabled": settings.enabled,
+            "dormant_days": settings.dormant_days,
+            "pack_type_id": settings.pack_type_id,
+            "pack_expiry_days": settings.pack_expiry_days,
+            "min_days_between": settings.min_days_between,
+        }))
+        .await?;
+
+        tx.commit().await?;
+        Ok(settings)
+    }
+
+    /// Grant a welcome-back pack to users whose last open is older than `dormant_days`.
+    /// A user is skipped if they were targeted within `min_days_between`, or haven't
+    /// opened anything since their last win-back grant, so a user who never comes back
+    /// isn't targeted again. Users on a self-exclusion break are left alone. Only one
+    /// replica runs at a time; the others skip the run.
+    pub async fn process_due(&self) -> Result<usize> {
+        let mut tx = self.db.begin().await?;
+        let locked = sqlx::query_scalar!(r#"SELECT pg_try_advisory_xact_lock(hashtext('winback')) AS "locked!""#)
+            .fetch_one(&mut *tx)
+            .await?;
+        if !locked {
+            return Ok(0);
+        }
+        let settings = Self::load_settings(&mut tx).await?;
+        let Some(pack_type_id) = settings.pack_type_id.filter(|_| settings.enabled) else {
+            return Ok(0);
+        };
+        let pack_name = sqlx::query_scalar!(
+            "SELECT name FROM pack_types WHERE id = $1 AND is_active = true",
+            pack_type_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(pack_name) = pack_name else {
+            return Ok(0);
+        };
+
+        let lapsed = sqlx::query!(
+            r#"
+            SELECT uls.user_id, COALESCE(MAX(h.opened_at), uls.created_at) AS "last_active_at!"
+            FROM user_lootpack_stats uls
+            LEFT JOIN user_pack_history h ON h.user_id = uls.user_id
+            WHERE NOT EXISTS (
+                SELECT 1 FROM user_self_exclusions se
+                WHERE se.user_id = uls.user_id AND se.starts_at <= NOW() AND se.ends_at > NOW()
+            )
+            GROUP BY uls.user_id, uls.created_at
+            HAVING COALESCE(MAX(h.opened_at), uls.created_at) < NOW() - make_interval(days => $1)
+               AND NOT EXISTS (
+                   SELECT 1 FROM winback_grants wg
+                   WHERE wg.user_id = uls.user_id
+                     AND (wg.granted_at > NOW() - make_interval(days => $2)
+                          OR wg.granted_at > COALESCE(MAX(h.opened_at), uls.created_at))
+               )
+            LIMIT $3
+            "#,
+            settings.dormant_days,
+            settings.min_days_between,
+            WINBACK_BATCH_SIZE
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let expires_at = Utc::now() + Duration::days(settings.pack_expiry_days as i64);
+        for user in &lapsed {
+            let user_pack_id =
+                user_packs::grant(&mut tx, &user.user_id, pack_type_id, "winback", Some(expires_at)).await?;
+            sqlx::query!(
+                "INSERT INTO winback_grants (user_id, user_pack_id, last_active_at) VALUES ($1, $2, $3)",
+                user.user_id,
+                user_pack_id,
+                user.last_active_at
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            let event = NotificationEvent::WelcomeBack {
+                user_pack_id,
+                pack_type_id,
+                pack_name: pack_name.clone(),
+                expires_at,
+            };
+            notifications::queue(&mut *tx, &user.user_id, &format!("welcome_back:{}", user_pack_id), &event).await?;
+        }
+
+        tx.commit().await?;
+        if !lapsed.is_empty() {
+            info!("Granted win-back packs to {} lapsed users", lapsed.len());
+        }
+        Ok(lapsed.len())
+    }
+
+    async fn load_settings(conn: &mut PgConnection) -> Result<WinBackSettings> {
+        let settings = sqlx::query_as!(
+            WinBackSettings,
+            r#"
+            SELECT enabled, dormant_days, pack_type_id, pack_expiry_days, min_days_between
+            FROM winback_settings
+            "#
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| AppError::InternalError("Win-back settings are missing".to_string()))?;
+
+        Ok(settings)
+    }
+
+    pub fn spawn_worker(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(WORKER_INTERVAL_SECONDS));
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.process_due().await {
+                    error!("Win-back run failed: {:?}", e);
+                }
+            }
+        })
+    }
+}
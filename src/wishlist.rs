@@ -0,0 +1,134 @@
+use crate::error::{AppError, Result};
+use crate::notifications::{self, NotificationEvent};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+const MAX_WISHLIST_ITEMS: i64 = 50;
+
+#[derive(Debug, Serialize)]
+pub struct WishlistItem {
+    pub template_id: Uuid,
+    pub title: String,
+    pub rarity: String,
+    /// Active packs that can drop the template right now
+    pub available_in: Vec<Uuid>,
+    pub added_at: DateTime<Utc>,
+}
+
+/// Queue a notification for everyone who wishlisted one of `template_ids`, just added to
+/// the pack's pool. Runs in the mapping change's transaction; nothing is sent for an
+/// inactive pack. Event keys allow one notice per template and pack per day.
+pub async fn notify_pool_additions(conn: &mut PgConnection, pack_type_id: Uuid, template_ids: &[Uuid]) -> Result<()> {
+    if template_ids.is_empty() {
+        return Ok(());
+    }
+
+    let wished = sqlx::query!(
+        r#"
+        SELECT wi.user_id, rt.id AS template_id, rt.title, pt.name AS pack_name
+        FROM wishlist_items wi
+        JOIN reward_templates rt ON rt.id = wi.template_id
+        JOIN pack_types pt ON pt.id = $1
+        LEFT JOIN notification_preferences np ON np.user_id = wi.user_id
+        WHERE wi.template_id = ANY($2) AND pt.is_active AND COALESCE(np.wishlist_available, true)
+        "#,
+        pack_type_id,
+        template_ids
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let today = Utc::now().date_naive();
+    for wish in wished {
+        let event = NotificationEvent::WishlistInPack {
+            template_id: wish.template_id,
+            title: wish.title,
+            pack_type_id,
+            pack_name: wish.pack_name,
+        };
+        let key = format!("wishlist_in_pack:{}:{}:{}", wish.template_id, pack_type_id, today);
+        notifications::queue(&mut *conn, &wish.user_id, &key, &event).await?;
+    }
+
+    Ok(())
+}
+
+pub struct WishlistService {
+    db: PgPool,
+}
+
+impl WishlistService {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    pub async fn list(&self, user_id: &str) -> Result<Vec<WishlistItem>> {
+        let items = sqlx::query_as!(
+            WishlistItem,
+            r#"
+            SELECT rt.id AS template_id, rt.title, rt.rarity,
+                   ARRAY(
+                       SELECT prm.pack_type_id FROM pack_reward_mappings prm
+                       JOIN pack_types pt ON pt.id = prm.pack_type_id
+                       WHERE prm.reward_template_id = rt.id AND pt.is_active
+                   ) AS "available_in!",
+                   wi.created_at AS added_at
+            FROM wishlist_items wi
+            JOIN reward_templates rt ON rt.id = wi.template_id
+            WHERE wi.user_id = $1
+            ORDER BY wi.created_at DESC
+            "#,
+            user_id
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(items)
+    }
+
+    pub async fn add(&self, user_id: &str, template_id: Uuid) -> Result<Vec<WishlistItem>> {
+        let mut tx = self.db.begin().await?;
+        let known = sqlx::query_scalar!("SELECT id FROM reward_templates WHERE id = $1", template_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        if known.is_none() {
+            return Err(AppError::NotFound("Reward template not found".to_string()));
+        }
+
+        let count = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM wishlist_items WHERE user_id = $1 AND template_id <> $2"#,
+            user_id,
+            template_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if count >= MAX_WISHLIST_ITEMS {
+            return Err(AppError::BadRequest(format!("Wishlists hold at most {} rewards", MAX_WISHLIST_ITEMS)));
+        }
+
+        sqlx::query!(
+            "INSERT INTO wishlist_items (user_id, template_id) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+            user_id,
+            template_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        self.list(user_id).await
+    }
+
+    pub async fn remove(&self, user_id: &str, template_id: Uuid) -> Result<Vec<WishlistItem>> {
+        sqlx::query!(
+            "DELETE FROM wishlist_items WHERE user_id = $1 AND template_id = $2",
+            user_id,
+            template_id
+        )
+        .execute(&self.db)
+        .await?;
+
+        self.list(user_id).await
+    }
+}